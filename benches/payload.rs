@@ -0,0 +1,94 @@
+//! Payload handling benchmarks.
+//!
+//! Measures the costs that dominate plan time for providers with large
+//! states: decoding request messages, parsing their JSON payloads and
+//! serializing the planned state.
+//!
+//! Run with `cargo bench --bench payload` (add `--features simd-json` to
+//! compare parsers). Pass a number of resources in the state to change its
+//! size (default: 2000, roughly 230 KB).
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use hemmer_provider_sdk::generated::PlanRequest;
+use hemmer_provider_sdk::payload::{decode_json, encode_json};
+use prost::bytes::Bytes;
+use prost::Message;
+use serde_json::{json, Value};
+
+fn large_state(entries: usize) -> Value {
+    let rules: Vec<Value> = (0..entries)
+        .map(|i| {
+            json!({
+                "id": format!("rule-{}", i),
+                "cidr": format!("10.{}.{}.0/24", i / 256 % 256, i % 256),
+                "ports": [22, 80, 443],
+                "description": "allow traffic from the internal network",
+            })
+        })
+        .collect();
+    json!({"id": "sg-1", "name": "internal", "rules": rules})
+}
+
+fn bench(name: &str, bytes: usize, mut f: impl FnMut()) {
+    // Warm up, then run for about a second
+    for _ in 0..3 {
+        f();
+    }
+    let start = Instant::now();
+    let mut iterations = 0u32;
+    while start.elapsed() < Duration::from_secs(1) {
+        f();
+        iterations += 1;
+    }
+    let per_iter = start.elapsed() / iterations;
+    let throughput = bytes as f64 / per_iter.as_secs_f64() / (1024.0 * 1024.0);
+    println!(
+        "{:<32} {:>10.1?}/iter {:>10.1} MiB/s",
+        name, per_iter, throughput
+    );
+}
+
+fn main() {
+    let entries = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(2000);
+    let state = large_state(entries);
+    let state_json = serde_json::to_vec(&state).unwrap();
+    let request = PlanRequest {
+        resource_type: "security_group".to_string(),
+        prior_state: state_json.clone().into(),
+        proposed_state: state_json.clone().into(),
+        config: state_json.clone().into(),
+        client_capabilities: None,
+    };
+    let wire = Bytes::from(request.encode_to_vec());
+    println!(
+        "state: {} resources, {} bytes; request: {} bytes",
+        entries,
+        state_json.len(),
+        wire.len()
+    );
+
+    bench("decode PlanRequest", wire.len(), || {
+        black_box(PlanRequest::decode(black_box(wire.clone())).unwrap());
+    });
+    bench("decode + parse PlanRequest", wire.len(), || {
+        let request = PlanRequest::decode(black_box(wire.clone())).unwrap();
+        for payload in [
+            &request.prior_state,
+            &request.proposed_state,
+            &request.config,
+        ] {
+            black_box(decode_json(payload).unwrap());
+        }
+    });
+    bench("serialize state (to_vec)", state_json.len(), || {
+        black_box(serde_json::to_vec(black_box(&state)).unwrap());
+    });
+    bench("serialize state (encode_json)", state_json.len(), || {
+        black_box(encode_json(black_box(&state)));
+    });
+}
@@ -15,6 +15,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let out_dir = std::path::PathBuf::from("src");
         tonic_prost_build::configure()
             .out_dir(&out_dir)
+            // Ordered maps keep GetSchema responses byte-for-byte stable
+            .btree_map(".")
+            // Payload fields borrow from the decode buffer instead of copying
+            .bytes(".")
             .compile_protos(&["proto/provider.proto"], &["proto"])?;
 
         // Rename the generated file
@@ -0,0 +1,257 @@
+//! Signed handshakes for provider binary attestation.
+//!
+//! In hardened environments core may want proof that the process it spawned is
+//! the provider binary it expects, not something that replaced it on disk. It
+//! asks for one by passing a random nonce in [`NONCE_ENV`]; a provider holding
+//! a signing key then signs the nonce, bound to the protocol version and
+//! address it is about to announce, and appends the signature to the
+//! handshake line:
+//!
+//! ```text
+//! HEMMER_PROVIDER|1|127.0.0.1:50051|ed25519:<public key hex>:<signature hex>
+//! ```
+//!
+//! The signed message is `HEMMER_PROVIDER|<protocol_version>|<address>|<nonce>`
+//! (see [`handshake_message`]), and core checks it against the public key it
+//! has on record with [`verify_handshake`]. Without a nonce, or without a key,
+//! the plain handshake is printed.
+//!
+//! The key is an Ed25519 seed (32 bytes, hex-encoded) read from
+//! [`SIGNING_KEY_ENV`], or from the file named by [`SIGNING_KEY_FILE_ENV`], or
+//! set explicitly with
+//! [`ServeOptions::with_handshake_signer`](crate::ServeOptions::with_handshake_signer).
+//!
+//! ```
+//! use hemmer_provider_sdk::attestation::{verify_handshake, HandshakeSigner};
+//!
+//! let signer = HandshakeSigner::from_hex(
+//!     "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+//! )
+//! .unwrap();
+//! let line = signer.sign_handshake(1, "127.0.0.1:50051", "n-42");
+//! assert!(verify_handshake(&line, "n-42", &signer.public_key()).is_ok());
+//! assert!(verify_handshake(&line, "n-43", &signer.public_key()).is_err());
+//! ```
+//!
+//! Enable it with the `attestation` cargo feature.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+
+use crate::error::ProviderError;
+use crate::proto_compat::{from_hex, to_hex};
+use crate::types::HANDSHAKE_PREFIX;
+
+/// Environment variable core uses to pass the handshake nonce.
+pub const NONCE_ENV: &str = "HEMMER_HANDSHAKE_NONCE";
+
+/// Environment variable holding the hex-encoded Ed25519 signing seed.
+pub const SIGNING_KEY_ENV: &str = "HEMMER_PROVIDER_SIGNING_KEY";
+
+/// Environment variable naming a file that holds the hex-encoded Ed25519
+/// signing seed.
+pub const SIGNING_KEY_FILE_ENV: &str = "HEMMER_PROVIDER_SIGNING_KEY_FILE";
+
+/// Signs handshakes with an Ed25519 key.
+///
+/// Cloning shares the same key.
+#[derive(Clone)]
+pub struct HandshakeSigner {
+    key: Arc<Ed25519KeyPair>,
+}
+
+impl HandshakeSigner {
+    /// Create a signer from a 32-byte Ed25519 seed.
+    pub fn from_seed(seed: &[u8]) -> Result<Self, ProviderError> {
+        let key = Ed25519KeyPair::from_seed_unchecked(seed).map_err(|_| {
+            ProviderError::Configuration(format!(
+                "Handshake signing key must be a 32-byte Ed25519 seed, got {} bytes",
+                seed.len()
+            ))
+        })?;
+        Ok(Self { key: Arc::new(key) })
+    }
+
+    /// Create a signer from a hex-encoded seed. Whitespace is ignored.
+    pub fn from_hex(hex: &str) -> Result<Self, ProviderError> {
+        let seed = from_hex(hex).map_err(|e| {
+            ProviderError::Configuration(format!("Invalid handshake signing key: {}", e))
+        })?;
+        Self::from_seed(&seed)
+    }
+
+    /// Read a hex-encoded seed from a file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ProviderError> {
+        let path = path.as_ref();
+        let hex = std::fs::read_to_string(path).map_err(|e| {
+            ProviderError::Configuration(format!(
+                "Failed to read handshake signing key {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::from_hex(&hex)
+    }
+
+    /// The signer configured by [`SIGNING_KEY_ENV`] or
+    /// [`SIGNING_KEY_FILE_ENV`], if either is set.
+    pub fn from_env() -> Result<Option<Self>, ProviderError> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// The signer configured by variables looked up by name.
+    pub(crate) fn from_lookup(
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Option<Self>, ProviderError> {
+        if let Some(hex) = lookup(SIGNING_KEY_ENV) {
+            return Self::from_hex(&hex).map(Some);
+        }
+        match lookup(SIGNING_KEY_FILE_ENV) {
+            Some(path) => Self::from_file(path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The 32-byte public key core verifies signatures with.
+    pub fn public_key(&self) -> Vec<u8> {
+        self.key.public_key().as_ref().to_vec()
+    }
+
+    /// Sign `message`.
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        self.key.sign(message).as_ref().to_vec()
+    }
+
+    /// The signed handshake line announcing `addr` in answer to `nonce`.
+    pub fn sign_handshake(&self, protocol_version: u32, addr: &str, nonce: &str) -> String {
+        let message = handshake_message(protocol_version, addr, nonce);
+        format!(
+            "{}|{}|{}|ed25519:{}:{}",
+            HANDSHAKE_PREFIX,
+            protocol_version,
+            addr,
+            to_hex(&self.public_key()),
+            to_hex(&self.sign(message.as_bytes()))
+        )
+    }
+}
+
+impl fmt::Debug for HandshakeSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HandshakeSigner")
+            .field("public_key", &to_hex(&self.public_key()))
+            .finish()
+    }
+}
+
+/// The message signed in a handshake.
+pub fn handshake_message(protocol_version: u32, addr: &str, nonce: &str) -> String {
+    format!(
+        "{}|{}|{}|{}",
+        HANDSHAKE_PREFIX, protocol_version, addr, nonce
+    )
+}
+
+/// Check that a signed handshake line answers `nonce` and was signed with
+/// `public_key`.
+pub fn verify_handshake(line: &str, nonce: &str, public_key: &[u8]) -> Result<(), ProviderError> {
+    let invalid =
+        |reason: &str| ProviderError::Validation(format!("Invalid handshake: {}", reason));
+    let parts: Vec<&str> = line.trim_end().split('|').collect();
+    let [prefix, version, addr, attestation] = parts[..] else {
+        return Err(invalid("not a signed handshake"));
+    };
+    if prefix != HANDSHAKE_PREFIX {
+        return Err(invalid("unexpected prefix"));
+    }
+    let version: u32 = version
+        .parse()
+        .map_err(|_| invalid("protocol version is not a number"))?;
+    let Some((key, signature)) = attestation
+        .strip_prefix("ed25519:")
+        .and_then(|rest| rest.split_once(':'))
+    else {
+        return Err(invalid("unsupported attestation"));
+    };
+    if from_hex(key).ok().as_deref() != Some(public_key) {
+        return Err(invalid("signed with an unexpected key"));
+    }
+    let signature = from_hex(signature).map_err(|_| invalid("signature is not hex"))?;
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(
+            handshake_message(version, addr, nonce).as_bytes(),
+            &signature,
+        )
+        .map_err(|_| invalid("signature does not match"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 8032, section 7.1, test 1
+    const SEED: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+    const PUBLIC_KEY: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = HandshakeSigner::from_hex(SEED).unwrap();
+        assert_eq!(to_hex(&signer.public_key()), PUBLIC_KEY);
+
+        let line = signer.sign_handshake(1, "127.0.0.1:50051", "nonce-1");
+        assert!(line.starts_with(&format!(
+            "HEMMER_PROVIDER|1|127.0.0.1:50051|ed25519:{}:",
+            PUBLIC_KEY
+        )));
+        verify_handshake(&line, "nonce-1", &signer.public_key()).unwrap();
+
+        // A replayed handshake or a different binary doesn't verify
+        let err = verify_handshake(&line, "nonce-2", &signer.public_key()).unwrap_err();
+        assert!(err.to_string().contains("signature does not match"));
+        let other = HandshakeSigner::from_seed(&[7; 32]).unwrap();
+        let err = verify_handshake(&line, "nonce-1", &other.public_key()).unwrap_err();
+        assert!(err.to_string().contains("unexpected key"));
+        let moved = line.replace("127.0.0.1:50051", "127.0.0.1:50052");
+        assert!(verify_handshake(&moved, "nonce-1", &signer.public_key()).is_err());
+        assert!(verify_handshake(
+            "HEMMER_PROVIDER|1|127.0.0.1:50051",
+            "nonce-1",
+            &signer.public_key()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        assert!(HandshakeSigner::from_hex("abcd").is_err());
+        assert!(HandshakeSigner::from_hex("not hex").is_err());
+        assert!(HandshakeSigner::from_file("/nonexistent/key").is_err());
+    }
+
+    #[test]
+    fn test_from_file_and_env() {
+        let path = std::env::temp_dir().join(format!("hemmer-signing-key-{}", std::process::id()));
+        std::fs::write(&path, format!("{}\n", SEED)).unwrap();
+        let signer = HandshakeSigner::from_file(&path).unwrap();
+        assert_eq!(to_hex(&signer.public_key()), PUBLIC_KEY);
+
+        let key_file = path.display().to_string();
+        let signer = HandshakeSigner::from_lookup(|name| {
+            (name == SIGNING_KEY_FILE_ENV).then(|| key_file.clone())
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(to_hex(&signer.public_key()), PUBLIC_KEY);
+        let signer = HandshakeSigner::from_lookup(|name| {
+            (name == SIGNING_KEY_ENV).then(|| SEED.to_string())
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(to_hex(&signer.public_key()), PUBLIC_KEY);
+        assert!(HandshakeSigner::from_lookup(|_| None).unwrap().is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,658 @@
+//! AWS Signature Version 4 request signing (feature: `aws-sig`).
+//!
+//! For providers that talk to AWS or AWS-compatible APIs (MinIO, Ceph,
+//! Cloudflare R2, ...) and only need to sign requests, without depending on
+//! the full AWS SDK.
+//!
+//! [`Credentials::resolve`] looks up credentials the way AWS tools do, with the
+//! provider configuration first:
+//!
+//! 1. `access_key`, `secret_key` and `token` in the provider config. Mark them
+//!    [`secret_ref`](crate::schema::Attribute::secret_ref) so users can write
+//!    `env:MY_KEY` or `vault:...`; the server resolves them before `configure`
+//! 2. `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`
+//! 3. The shared credentials file (`AWS_SHARED_CREDENTIALS_FILE` or
+//!    `~/.aws/credentials`), using the `profile` from the config, `AWS_PROFILE`
+//!    or `default`
+//!
+//! [`SigV4Signer`] computes the signing headers for a request. With the `http`
+//! feature, [`SigV4Signer::sign_request`] signs a [`reqwest::RequestBuilder`]
+//! directly.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! use hemmer_provider_sdk::aws::{Credentials, SigV4Signer};
+//!
+//! let credentials = Credentials::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY");
+//! let signer = SigV4Signer::new(credentials, "us-east-1", "service");
+//!
+//! let time = UNIX_EPOCH + Duration::from_secs(1_440_938_160); // 2015-08-30T12:36:00Z
+//! let headers = signer
+//!     .signing_headers_at("GET", "https://example.amazonaws.com/", &[], b"", time)
+//!     .unwrap();
+//! assert_eq!(headers[0], ("x-amz-date".to_string(), "20150830T123600Z".to_string()));
+//! assert!(headers.last().unwrap().1.ends_with(
+//!     "Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+//! ));
+//! ```
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ring::{digest, hmac};
+use serde_json::Value;
+
+use crate::error::ProviderError;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// AWS access credentials.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    /// The access key ID.
+    pub access_key_id: String,
+    /// The secret access key.
+    pub secret_access_key: String,
+    /// The session token, for temporary credentials.
+    pub session_token: Option<String>,
+}
+
+impl Credentials {
+    /// Create long-term credentials.
+    pub fn new(access_key_id: impl Into<String>, secret_access_key: impl Into<String>) -> Self {
+        Self {
+            access_key_id: access_key_id.into(),
+            secret_access_key: secret_access_key.into(),
+            session_token: None,
+        }
+    }
+
+    /// Set the session token of temporary credentials.
+    pub fn with_session_token(mut self, token: impl Into<String>) -> Self {
+        self.session_token = Some(token.into());
+        self
+    }
+
+    /// Resolve credentials from the provider config, the environment and the
+    /// shared credentials file, in that order (see the [module docs](self)).
+    ///
+    /// Fails with [`ProviderError::Configuration`] if no source has
+    /// credentials, or if the config sets only one of `access_key` and
+    /// `secret_key`.
+    pub fn resolve(config: &Value) -> Result<Self, ProviderError> {
+        if let Some(credentials) = Self::from_config(config)? {
+            return Ok(credentials);
+        }
+        if let Some(credentials) = Self::from_env() {
+            return Ok(credentials);
+        }
+        let profile = config["profile"].as_str();
+        if let Some(credentials) = Self::from_shared_file(profile)? {
+            return Ok(credentials);
+        }
+        Err(ProviderError::Configuration(
+            "No AWS credentials found: set access_key and secret_key, \
+             AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, or a profile in the shared credentials file"
+                .to_string(),
+        ))
+    }
+
+    /// Credentials from the `access_key`, `secret_key` and `token` config attributes.
+    pub fn from_config(config: &Value) -> Result<Option<Self>, ProviderError> {
+        let field = |name: &str| config[name].as_str().filter(|s| !s.is_empty());
+        match (field("access_key"), field("secret_key")) {
+            (Some(id), Some(secret)) => {
+                let mut credentials = Self::new(id, secret);
+                credentials.session_token = field("token").map(String::from);
+                Ok(Some(credentials))
+            },
+            (None, None) => Ok(None),
+            _ => Err(ProviderError::Configuration(
+                "access_key and secret_key must be set together".to_string(),
+            )),
+        }
+    }
+
+    /// Credentials from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+    /// `AWS_SESSION_TOKEN`.
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|s| !s.is_empty());
+        let mut credentials = Self::new(var("AWS_ACCESS_KEY_ID")?, var("AWS_SECRET_ACCESS_KEY")?);
+        credentials.session_token = var("AWS_SESSION_TOKEN");
+        Some(credentials)
+    }
+
+    /// Credentials for `profile` (default: `AWS_PROFILE`, then `default`) from
+    /// the shared credentials file.
+    ///
+    /// Returns `None` if the file or the profile doesn't exist.
+    pub fn from_shared_file(profile: Option<&str>) -> Result<Option<Self>, ProviderError> {
+        let Some(path) = shared_credentials_path() else {
+            return Ok(None);
+        };
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(ProviderError::Configuration(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            },
+        };
+        let profile = profile
+            .map(String::from)
+            .or_else(|| std::env::var("AWS_PROFILE").ok())
+            .unwrap_or_else(|| "default".to_string());
+        Ok(Self::parse_profile(&contents, &profile))
+    }
+
+    /// Credentials for `profile` in the contents of a shared credentials file.
+    fn parse_profile(contents: &str, profile: &str) -> Option<Self> {
+        let (mut id, mut secret, mut token) = (None, None, None);
+        let mut in_profile = false;
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                in_profile = section.trim() == profile;
+                continue;
+            }
+            if !in_profile {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let value = Some(value.trim().to_string());
+                match key.trim() {
+                    "aws_access_key_id" => id = value,
+                    "aws_secret_access_key" => secret = value,
+                    "aws_session_token" => token = value,
+                    _ => {},
+                }
+            }
+        }
+        let mut credentials = Self::new(id?, secret?);
+        credentials.session_token = token;
+        Some(credentials)
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+fn shared_credentials_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("AWS_SHARED_CREDENTIALS_FILE") {
+        return Some(PathBuf::from(path));
+    }
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".aws").join("credentials"))
+}
+
+/// Signs requests with AWS Signature Version 4.
+#[derive(Debug, Clone)]
+pub struct SigV4Signer {
+    credentials: Credentials,
+    region: String,
+    service: String,
+    s3: bool,
+}
+
+impl SigV4Signer {
+    /// Create a signer for `service` (e.g. `ec2`, `s3`) in `region`.
+    pub fn new(
+        credentials: Credentials,
+        region: impl Into<String>,
+        service: impl Into<String>,
+    ) -> Self {
+        Self {
+            credentials,
+            region: region.into(),
+            service: service.into(),
+            s3: false,
+        }
+    }
+
+    /// Sign the way S3 expects: the path is not encoded a second time and the
+    /// payload hash is sent in `x-amz-content-sha256`.
+    pub fn for_s3(mut self) -> Self {
+        self.s3 = true;
+        self
+    }
+
+    /// The credentials requests are signed with.
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    /// The headers to add to a request so it is signed as of now.
+    ///
+    /// `headers` are the headers the request will be sent with; all of them
+    /// are signed, so none may change afterwards. The `host` header is taken
+    /// from `url` unless given.
+    pub fn signing_headers(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<Vec<(String, String)>, ProviderError> {
+        self.signing_headers_at(method, url, headers, body, SystemTime::now())
+    }
+
+    /// Like [`signing_headers`](Self::signing_headers), as of `time`.
+    pub fn signing_headers_at(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+        time: SystemTime,
+    ) -> Result<Vec<(String, String)>, ProviderError> {
+        let (authority, path, query) = split_url(url)?;
+        let timestamp = amz_date(time);
+        let date = &timestamp[..8];
+        let payload_hash = hex(digest::digest(&digest::SHA256, body).as_ref());
+
+        let mut added = vec![("x-amz-date".to_string(), timestamp.clone())];
+        if self.s3 {
+            added.push(("x-amz-content-sha256".to_string(), payload_hash.clone()));
+        }
+        if let Some(token) = &self.credentials.session_token {
+            added.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+
+        // Canonical headers: lowercase names, sorted, values trimmed and with
+        // repeated values joined by commas
+        let mut canonical: Vec<(String, String)> = Vec::new();
+        let mut push = |name: &str, value: &str| {
+            let name = name.to_ascii_lowercase();
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            match canonical.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, existing)) => {
+                    existing.push(',');
+                    existing.push_str(&value);
+                },
+                None => canonical.push((name, value)),
+            }
+        };
+        if !headers.iter().any(|(n, _)| n.eq_ignore_ascii_case("host")) {
+            push("host", authority);
+        }
+        for (name, value) in headers {
+            push(name, value);
+        }
+        for (name, value) in &added {
+            push(name, value);
+        }
+        canonical.sort_by(|a, b| a.0.cmp(&b.0));
+        let signed_headers = canonical
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let header_lines: String = canonical
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method.to_ascii_uppercase(),
+            self.canonical_path(path),
+            canonical_query(query),
+            header_lines,
+            signed_headers,
+            payload_hash
+        );
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            ALGORITHM,
+            timestamp,
+            scope,
+            hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref())
+        );
+
+        let key = [date, &self.region, &self.service, "aws4_request"]
+            .iter()
+            .fold(
+                format!("AWS4{}", self.credentials.secret_access_key).into_bytes(),
+                |key, part| hmac_sha256(&key, part.as_bytes()),
+            );
+        let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        added.push((
+            "authorization".to_string(),
+            format!(
+                "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+                ALGORITHM, self.credentials.access_key_id, scope, signed_headers, signature
+            ),
+        ));
+        Ok(added)
+    }
+
+    /// Sign a request built with [`reqwest`], as of now.
+    ///
+    /// The body must be in memory (not a stream). Retries of the signed
+    /// request reuse the signature, which AWS accepts for 5 minutes.
+    #[cfg(feature = "http")]
+    pub fn sign_request(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::RequestBuilder, ProviderError> {
+        let (client, request) = request.build_split();
+        let mut request = request
+            .map_err(|e| ProviderError::InvalidRequest(format!("Invalid HTTP request: {}", e)))?;
+        let body = match request.body() {
+            None => &[][..],
+            Some(body) => body.as_bytes().ok_or_else(|| {
+                ProviderError::InvalidRequest(
+                    "Streaming request bodies can't be signed".to_string(),
+                )
+            })?,
+        };
+        let headers: Vec<(&str, &str)> = request
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)))
+            .collect();
+        let added = self.signing_headers(
+            request.method().as_str(),
+            request.url().as_str(),
+            &headers,
+            body,
+        )?;
+        for (name, value) in added {
+            let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .expect("signing header names are valid");
+            let value = reqwest::header::HeaderValue::from_str(&value).map_err(|e| {
+                ProviderError::Configuration(format!("Invalid AWS credentials: {}", e))
+            })?;
+            request.headers_mut().insert(name, value);
+        }
+        Ok(reqwest::RequestBuilder::from_parts(client, request))
+    }
+
+    fn canonical_path(&self, path: &str) -> String {
+        if path.is_empty() {
+            return "/".to_string();
+        }
+        // Paths in URLs are already encoded once; every service except S3
+        // expects them encoded again
+        uri_encode(path, false, self.s3)
+    }
+}
+
+/// Split `url` into its authority, path and query.
+fn split_url(url: &str) -> Result<(&str, &str, &str), ProviderError> {
+    let rest = url
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .ok_or_else(|| ProviderError::InvalidRequest(format!("Invalid URL '{}'", url)))?;
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (rest, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let (authority, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    let authority = authority.rsplit('@').next().unwrap_or_default();
+    if authority.is_empty() {
+        return Err(ProviderError::InvalidRequest(format!(
+            "URL '{}' has no host",
+            url
+        )));
+    }
+    Ok((authority, path, query))
+}
+
+/// The query string with each name and value encoded, sorted by name and value.
+fn canonical_query(query: &str) -> String {
+    let mut params: Vec<(String, String)> = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (name, value) = p.split_once('=').unwrap_or((p, ""));
+            (
+                uri_encode(&percent_decode(name), true, false),
+                uri_encode(&percent_decode(value), true, false),
+            )
+        })
+        .collect();
+    params.sort();
+    params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Percent-encode everything but unreserved characters, and `/` unless
+/// `encode_slash` is set. Existing escapes are kept if `keep_escapes` is set.
+fn uri_encode(s: &str, encode_slash: bool, keep_escapes: bool) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(b as char)
+            },
+            b'/' if !encode_slash => encoded.push('/'),
+            b'%' if keep_escapes => encoded.push('%'),
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escape {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            },
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data)
+        .as_ref()
+        .to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `time` as `YYYYMMDDTHHMMSSZ`.
+fn amz_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn example_time() -> SystemTime {
+        // 2015-08-30T12:36:00Z, used by the AWS examples
+        UNIX_EPOCH + Duration::from_secs(1_440_938_160)
+    }
+
+    fn example_signer(service: &str) -> SigV4Signer {
+        SigV4Signer::new(
+            Credentials::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY"),
+            "us-east-1",
+            service,
+        )
+    }
+
+    #[test]
+    fn test_signing_headers() {
+        // From the AWS documentation's IAM ListUsers example
+        let headers = example_signer("iam")
+            .signing_headers_at(
+                "GET",
+                "https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08",
+                &[(
+                    "Content-Type",
+                    "application/x-www-form-urlencoded; charset=utf-8",
+                )],
+                b"",
+                example_time(),
+            )
+            .unwrap();
+        assert_eq!(
+            headers,
+            vec![
+                ("x-amz-date".to_string(), "20150830T123600Z".to_string()),
+                (
+                    "authorization".to_string(),
+                    "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+                     SignedHeaders=content-type;host;x-amz-date, \
+                     Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+                        .to_string()
+                ),
+            ]
+        );
+
+        let mut signer = example_signer("s3").for_s3();
+        signer.credentials.session_token = Some("session".to_string());
+        let headers = signer
+            .signing_headers_at(
+                "PUT",
+                "https://bucket.s3.amazonaws.com/a%20b",
+                &[],
+                b"x",
+                example_time(),
+            )
+            .unwrap();
+        let names: Vec<_> = headers.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "x-amz-date",
+                "x-amz-content-sha256",
+                "x-amz-security-token",
+                "authorization"
+            ]
+        );
+        assert!(headers[3]
+            .1
+            .contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token,"));
+    }
+
+    #[test]
+    fn test_canonical_parts() {
+        assert_eq!(
+            canonical_query("b=2&a=x%20y&a=1&flag"),
+            "a=1&a=x%20y&b=2&flag="
+        );
+        assert_eq!(
+            example_signer("ec2").canonical_path("/a%20b/c"),
+            "/a%2520b/c"
+        );
+        assert_eq!(
+            example_signer("s3").for_s3().canonical_path("/a%20b/c"),
+            "/a%20b/c"
+        );
+        assert_eq!(
+            split_url("https://user@host:8443/p?q=1#frag").unwrap(),
+            ("host:8443", "/p", "q=1")
+        );
+        assert_eq!(amz_date(UNIX_EPOCH), "19700101T000000Z");
+        assert_eq!(
+            amz_date(UNIX_EPOCH + Duration::from_secs(951_827_696)),
+            "20000229T123456Z"
+        );
+    }
+
+    #[test]
+    fn test_credentials() {
+        let credentials = Credentials::from_config(&json!({
+            "access_key": "AKID",
+            "secret_key": "secret",
+            "token": "session",
+        }))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            credentials,
+            Credentials::new("AKID", "secret").with_session_token("session")
+        );
+        assert!(!format!("{:?}", credentials).contains("secret"));
+        assert!(Credentials::from_config(&json!({"access_key": "AKID"})).is_err());
+        assert_eq!(Credentials::from_config(&json!({})).unwrap(), None);
+
+        let file = "\
+[default]
+aws_access_key_id = DEFAULTID
+aws_secret_access_key = defaultsecret
+
+# comment
+[work]
+aws_access_key_id=WORKID
+aws_secret_access_key=worksecret
+aws_session_token=worktoken
+";
+        assert_eq!(
+            Credentials::parse_profile(file, "work"),
+            Some(Credentials::new("WORKID", "worksecret").with_session_token("worktoken"))
+        );
+        assert_eq!(
+            Credentials::parse_profile(file, "default"),
+            Some(Credentials::new("DEFAULTID", "defaultsecret"))
+        );
+        assert_eq!(Credentials::parse_profile(file, "missing"), None);
+    }
+}
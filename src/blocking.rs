@@ -0,0 +1,773 @@
+//! Synchronous providers wrapping blocking vendor SDKs.
+//!
+//! Calling a blocking client from an `async fn` stalls the runtime thread the
+//! server uses for every other request. Instead of hand-wiring a thread pool,
+//! implement [`BlockingProviderService`] and serve it through
+//! [`BlockingProvider`], which runs each method on tokio's blocking pool with
+//! `spawn_blocking` and fails reads that run longer than a timeout:
+//!
+//! ```ignore
+//! use hemmer_provider_sdk::blocking::{BlockingProvider, BlockingProviderService};
+//!
+//! impl BlockingProviderService for VendorProvider {
+//!     fn schema(&self) -> ProviderSchema { /* ... */ }
+//!
+//!     fn read(&self, resource_type: &str, state: Value) -> Result<Value, ProviderError> {
+//!         let client = Context::current().data::<vendor::Client>()?;
+//!         Ok(client.get_server(state["id"].as_str().unwrap_or_default())?.into())
+//!     }
+//!     // ...
+//! }
+//!
+//! serve(BlockingProvider::new(VendorProvider).with_timeout(Duration::from_secs(120))).await?;
+//! ```
+//!
+//! The operation [`Context`] is in scope inside the blocking methods, so
+//! [`Context::current`] works as it does in async providers. A blocking thread
+//! can't be interrupted, so long-running methods should check
+//! [`Context::is_cancelled`] where they can. A read, plan or other call
+//! without side effects that times out fails with
+//! [`ProviderError::DeadlineExceeded`] and finishes in the background. A
+//! create, update or delete that times out is logged and waited for instead,
+//! and its real result is reported: failing it early would tell core the
+//! change didn't happen, and release the resource's lock, while the change
+//! may still go through.
+//!
+//! # CPU-heavy work in async providers
+//!
+//! Async providers that do heavy CPU work during an operation (rendering
+//! templates, hashing, signing) should move it off the runtime with
+//! [`Context::run_blocking`]. The work runs on tokio's blocking pool within a
+//! [`BlockingBudget`], which bounds how many such tasks run at once so a burst
+//! of plans can't occupy every thread:
+//!
+//! ```ignore
+//! async fn plan(&self, resource_type: &str, prior: Option<Value>, proposed: Value, config: Value)
+//!     -> Result<PlanResult, ProviderError>
+//! {
+//!     let rendered = Context::current()
+//!         .run_blocking(move || templates::render(&config))
+//!         .await??;
+//!     // ...
+//! }
+//! ```
+//!
+//! The server's budget is set with
+//! [`ServeOptions::with_blocking_budget`](crate::ServeOptions::with_blocking_budget);
+//! outside of a server, a process-wide budget sized to the number of CPUs is used.
+
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use tokio::sync::Semaphore;
+use tokio::task::{JoinError, JoinHandle};
+use tracing::warn;
+
+use crate::context::Context;
+use crate::error::ProviderError;
+use crate::plan::plan_from_schema;
+use crate::schema::{Diagnostic, ProviderSchema};
+use crate::server::ProviderService;
+use crate::types::{
+    ConfigureResult, CreateRequest, DeleteRequest, ImportedResource, PlanResult,
+    ServerCapabilities, UpdateRequest,
+};
+
+/// How long a blocking call may run when no timeout is set.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The synchronous counterpart of [`ProviderService`].
+///
+/// Methods have the same meaning and defaults as their async versions; serve
+/// an implementation with [`BlockingProvider`].
+pub trait BlockingProviderService: Send + Sync + 'static {
+    /// Return the provider's schema including all resources and data sources.
+    fn schema(&self) -> ProviderSchema;
+
+    /// Optional protocol features this provider supports.
+    fn capabilities(&self) -> ServerCapabilities {
+        ServerCapabilities::default()
+    }
+
+    /// Validate the provider configuration before configuring.
+    fn validate_provider_config(
+        &self,
+        config: serde_json::Value,
+    ) -> Result<Vec<Diagnostic>, ProviderError> {
+        let _ = config;
+        Ok(vec![])
+    }
+
+    /// Configure the provider with credentials and settings.
+    fn configure(&self, config: serde_json::Value) -> Result<ConfigureResult, ProviderError>;
+
+    /// Stop the provider gracefully.
+    fn stop(&self) -> Result<(), ProviderError> {
+        Ok(())
+    }
+
+    /// Validate a resource's configuration before planning.
+    fn validate_resource_config(
+        &self,
+        resource_type: &str,
+        config: serde_json::Value,
+    ) -> Result<Vec<Diagnostic>, ProviderError> {
+        let _ = (resource_type, config);
+        Ok(vec![])
+    }
+
+    /// Upgrade resource state from an older schema version.
+    fn upgrade_resource_state(
+        &self,
+        resource_type: &str,
+        version: i64,
+        state: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError> {
+        let _ = (resource_type, version);
+        Ok(state)
+    }
+
+    /// Plan changes for a resource. The default plans from the resource's
+    /// schema with [`plan_from_schema`].
+    fn plan(
+        &self,
+        resource_type: &str,
+        prior_state: Option<serde_json::Value>,
+        proposed_state: serde_json::Value,
+        config: serde_json::Value,
+    ) -> Result<PlanResult, ProviderError> {
+        let schema = self.schema();
+        let resource = schema
+            .resources
+            .get(resource_type)
+            .ok_or_else(|| ProviderError::UnknownResource(resource_type.to_string()))?;
+        plan_from_schema(resource, prior_state.as_ref(), proposed_state, &config)
+    }
+
+    /// Create a new resource.
+    fn create(
+        &self,
+        resource_type: &str,
+        planned_state: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError>;
+
+    /// Create a new resource, given everything core sent for the create,
+    /// including write-only inputs in [`config`](CreateRequest::config). The
+    /// default calls [`create`](Self::create).
+    fn handle_create(&self, request: CreateRequest) -> Result<serde_json::Value, ProviderError> {
+        self.create(&request.resource_type, request.planned_state)
+    }
+
+    /// Read the current state of a resource.
+    fn read(
+        &self,
+        resource_type: &str,
+        current_state: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError>;
+
+    /// Update an existing resource.
+    fn update(
+        &self,
+        resource_type: &str,
+        prior_state: serde_json::Value,
+        planned_state: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError>;
+
+    /// Update an existing resource, given everything core sent for the
+    /// update. The default calls [`update`](Self::update).
+    fn handle_update(&self, request: UpdateRequest) -> Result<serde_json::Value, ProviderError> {
+        self.update(
+            &request.resource_type,
+            request.prior_state,
+            request.planned_state,
+        )
+    }
+
+    /// Delete a resource.
+    fn delete(
+        &self,
+        resource_type: &str,
+        current_state: serde_json::Value,
+    ) -> Result<(), ProviderError>;
+
+    /// Delete a resource, given everything core sent for the delete. The
+    /// default calls [`delete`](Self::delete).
+    fn handle_delete(&self, request: DeleteRequest) -> Result<(), ProviderError> {
+        self.delete(&request.resource_type, request.current_state)
+    }
+
+    /// Import existing infrastructure into management.
+    fn import_resource(
+        &self,
+        resource_type: &str,
+        id: &str,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        let _ = id;
+        Err(ProviderError::Sdk(format!(
+            "Import not supported for resource type: {}",
+            resource_type
+        )))
+    }
+
+    /// Import every existing resource matching a provider-defined query.
+    fn import_query(
+        &self,
+        resource_type: &str,
+        query: serde_json::Value,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        let _ = query;
+        Err(ProviderError::Sdk(format!(
+            "Import queries not supported for resource type: {}",
+            resource_type
+        )))
+    }
+
+    /// Validate a data source's configuration.
+    fn validate_data_source_config(
+        &self,
+        data_source_type: &str,
+        config: serde_json::Value,
+    ) -> Result<Vec<Diagnostic>, ProviderError> {
+        let _ = (data_source_type, config);
+        Ok(vec![])
+    }
+
+    /// Read data from an external source.
+    fn read_data_source(
+        &self,
+        data_source_type: &str,
+        config: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError> {
+        let _ = config;
+        Err(ProviderError::UnknownResource(format!(
+            "Unknown data source type: {}",
+            data_source_type
+        )))
+    }
+}
+
+/// Serves a [`BlockingProviderService`] as a [`ProviderService`], running
+/// each call on tokio's blocking thread pool.
+#[derive(Debug)]
+pub struct BlockingProvider<P> {
+    provider: Arc<P>,
+    timeout: Duration,
+}
+
+impl<P: BlockingProviderService> BlockingProvider<P> {
+    /// Wrap `provider`, with calls limited to [`DEFAULT_TIMEOUT`].
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Set how long a call may run before it fails with
+    /// [`ProviderError::DeadlineExceeded`]. Creates, updates and deletes are
+    /// waited for past it (see the [module docs](self)).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The wrapped provider.
+    pub fn provider(&self) -> &P {
+        &self.provider
+    }
+
+    /// Run `call` on the blocking pool with the current [`Context`] in scope.
+    async fn run<T, F>(&self, operation: &str, call: F) -> Result<T, ProviderError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&P) -> Result<T, ProviderError> + Send + 'static,
+    {
+        match tokio::time::timeout(self.timeout, self.spawn(call)).await {
+            Ok(result) => joined(operation, result),
+            Err(_) => Err(ProviderError::DeadlineExceeded(format!(
+                "Blocking {} call did not finish within {:?}",
+                operation, self.timeout
+            ))),
+        }
+    }
+
+    /// Run a `call` that changes infrastructure like [`run`](Self::run), but
+    /// wait for it past the timeout, so the caller keeps the resource's lock
+    /// until the change is done and reports what really happened.
+    async fn run_write<T, F>(&self, operation: &str, call: F) -> Result<T, ProviderError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&P) -> Result<T, ProviderError> + Send + 'static,
+    {
+        let mut task = self.spawn(call);
+        let result = match tokio::time::timeout(self.timeout, &mut task).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!(
+                    operation,
+                    timeout = ?self.timeout,
+                    "Blocking call is still running past its timeout; waiting for its result"
+                );
+                task.await
+            },
+        };
+        joined(operation, result)
+    }
+
+    /// Start `call` on the blocking pool with the current [`Context`] in scope.
+    fn spawn<T, F>(&self, call: F) -> JoinHandle<Result<T, ProviderError>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&P) -> Result<T, ProviderError> + Send + 'static,
+    {
+        let provider = Arc::clone(&self.provider);
+        let context = Context::current();
+        tokio::task::spawn_blocking(move || context.sync_scope(|| call(&provider)))
+    }
+}
+
+/// The result of a finished blocking call, or an error if it panicked.
+fn joined<T>(
+    operation: &str,
+    result: Result<Result<T, ProviderError>, JoinError>,
+) -> Result<T, ProviderError> {
+    result.unwrap_or_else(|e| {
+        Err(ProviderError::Sdk(format!(
+            "Blocking {} call failed: {}",
+            operation, e
+        )))
+    })
+}
+
+/// A bounded share of tokio's blocking pool for CPU-heavy work.
+///
+/// Cloning shares the same budget.
+#[derive(Debug, Clone)]
+pub struct BlockingBudget {
+    permits: Arc<Semaphore>,
+    max_tasks: usize,
+}
+
+impl BlockingBudget {
+    /// A budget running at most `max_tasks` tasks at once (at least one).
+    pub fn new(max_tasks: usize) -> Self {
+        let max_tasks = max_tasks.max(1);
+        Self {
+            permits: Arc::new(Semaphore::new(max_tasks)),
+            max_tasks,
+        }
+    }
+
+    /// The process-wide budget used when the context carries none.
+    pub(crate) fn shared() -> &'static BlockingBudget {
+        static SHARED: OnceLock<BlockingBudget> = OnceLock::new();
+        SHARED.get_or_init(BlockingBudget::default)
+    }
+
+    /// The most tasks that run at once.
+    pub fn max_tasks(&self) -> usize {
+        self.max_tasks
+    }
+
+    /// How many more tasks could start right now.
+    pub fn available(&self) -> usize {
+        self.permits.available_permits()
+    }
+
+    /// Run `f` on the blocking pool with the current [`Context`] in scope,
+    /// waiting for a free slot first.
+    pub async fn run<R, F>(&self, f: F) -> Result<R, ProviderError>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        self.run_in(Context::current(), f).await
+    }
+
+    /// Run `f` on the blocking pool with `context` in scope.
+    ///
+    /// Fails with [`ProviderError::Unavailable`] if the operation is cancelled
+    /// while waiting for a slot. Once started, `f` runs to completion and
+    /// keeps its slot until then, even if the caller stops waiting.
+    pub(crate) async fn run_in<R, F>(&self, context: Context, f: F) -> Result<R, ProviderError>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        let permit = tokio::select! {
+            permit = Arc::clone(&self.permits).acquire_owned() => {
+                permit.expect("blocking budget semaphore is never closed")
+            },
+            _ = context.cancelled() => {
+                return Err(ProviderError::Unavailable(
+                    "Operation cancelled while waiting to run blocking work".to_string(),
+                ));
+            },
+        };
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            context.sync_scope(f)
+        })
+        .await
+        .map_err(|e| ProviderError::Sdk(format!("Blocking task failed: {}", e)))
+    }
+}
+
+impl Default for BlockingBudget {
+    /// One task per available CPU.
+    fn default() -> Self {
+        Self::new(std::thread::available_parallelism().map_or(1, usize::from))
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: BlockingProviderService> ProviderService for BlockingProvider<P> {
+    fn schema(&self) -> ProviderSchema {
+        self.provider.schema()
+    }
+
+    fn capabilities(&self) -> ServerCapabilities {
+        self.provider.capabilities()
+    }
+
+    async fn validate_provider_config(
+        &self,
+        config: serde_json::Value,
+    ) -> Result<Vec<Diagnostic>, ProviderError> {
+        self.run("validate_provider_config", move |p| {
+            p.validate_provider_config(config)
+        })
+        .await
+    }
+
+    async fn configure(&self, config: serde_json::Value) -> Result<ConfigureResult, ProviderError> {
+        self.run("configure", move |p| p.configure(config)).await
+    }
+
+    async fn stop(&self) -> Result<(), ProviderError> {
+        self.run("stop", |p| p.stop()).await
+    }
+
+    async fn validate_resource_config(
+        &self,
+        resource_type: &str,
+        config: serde_json::Value,
+    ) -> Result<Vec<Diagnostic>, ProviderError> {
+        let resource_type = resource_type.to_string();
+        self.run("validate_resource_config", move |p| {
+            p.validate_resource_config(&resource_type, config)
+        })
+        .await
+    }
+
+    async fn upgrade_resource_state(
+        &self,
+        resource_type: &str,
+        version: i64,
+        state: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError> {
+        let resource_type = resource_type.to_string();
+        self.run("upgrade_resource_state", move |p| {
+            p.upgrade_resource_state(&resource_type, version, state)
+        })
+        .await
+    }
+
+    async fn plan(
+        &self,
+        resource_type: &str,
+        prior_state: Option<serde_json::Value>,
+        proposed_state: serde_json::Value,
+        config: serde_json::Value,
+    ) -> Result<PlanResult, ProviderError> {
+        let resource_type = resource_type.to_string();
+        self.run("plan", move |p| {
+            p.plan(&resource_type, prior_state, proposed_state, config)
+        })
+        .await
+    }
+
+    async fn create(
+        &self,
+        resource_type: &str,
+        planned_state: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError> {
+        let resource_type = resource_type.to_string();
+        self.run_write("create", move |p| p.create(&resource_type, planned_state))
+            .await
+    }
+
+    async fn handle_create(
+        &self,
+        request: CreateRequest,
+    ) -> Result<serde_json::Value, ProviderError> {
+        self.run_write("create", move |p| p.handle_create(request))
+            .await
+    }
+
+    async fn read(
+        &self,
+        resource_type: &str,
+        current_state: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError> {
+        let resource_type = resource_type.to_string();
+        self.run("read", move |p| p.read(&resource_type, current_state))
+            .await
+    }
+
+    async fn update(
+        &self,
+        resource_type: &str,
+        prior_state: serde_json::Value,
+        planned_state: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError> {
+        let resource_type = resource_type.to_string();
+        self.run_write("update", move |p| {
+            p.update(&resource_type, prior_state, planned_state)
+        })
+        .await
+    }
+
+    async fn handle_update(
+        &self,
+        request: UpdateRequest,
+    ) -> Result<serde_json::Value, ProviderError> {
+        self.run_write("update", move |p| p.handle_update(request))
+            .await
+    }
+
+    async fn delete(
+        &self,
+        resource_type: &str,
+        current_state: serde_json::Value,
+    ) -> Result<(), ProviderError> {
+        let resource_type = resource_type.to_string();
+        self.run_write("delete", move |p| p.delete(&resource_type, current_state))
+            .await
+    }
+
+    async fn handle_delete(&self, request: DeleteRequest) -> Result<(), ProviderError> {
+        self.run_write("delete", move |p| p.handle_delete(request))
+            .await
+    }
+
+    async fn import_resource(
+        &self,
+        resource_type: &str,
+        id: &str,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        let (resource_type, id) = (resource_type.to_string(), id.to_string());
+        self.run("import_resource", move |p| {
+            p.import_resource(&resource_type, &id)
+        })
+        .await
+    }
+
+    async fn import_query(
+        &self,
+        resource_type: &str,
+        query: serde_json::Value,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        let resource_type = resource_type.to_string();
+        self.run("import_query", move |p| {
+            p.import_query(&resource_type, query)
+        })
+        .await
+    }
+
+    async fn validate_data_source_config(
+        &self,
+        data_source_type: &str,
+        config: serde_json::Value,
+    ) -> Result<Vec<Diagnostic>, ProviderError> {
+        let data_source_type = data_source_type.to_string();
+        self.run("validate_data_source_config", move |p| {
+            p.validate_data_source_config(&data_source_type, config)
+        })
+        .await
+    }
+
+    async fn read_data_source(
+        &self,
+        data_source_type: &str,
+        config: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError> {
+        let data_source_type = data_source_type.to_string();
+        self.run("read_data_source", move |p| {
+            p.read_data_source(&data_source_type, config)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ProviderData;
+    use crate::schema::{Attribute, Schema};
+    use serde_json::json;
+
+    struct Vendor;
+
+    impl BlockingProviderService for Vendor {
+        fn schema(&self) -> ProviderSchema {
+            ProviderSchema::new().with_resource(
+                "server",
+                Schema::v0().with_attribute("name", Attribute::required_string()),
+            )
+        }
+
+        fn configure(&self, _: serde_json::Value) -> Result<ConfigureResult, ProviderError> {
+            Ok(ConfigureResult::new())
+        }
+
+        fn create(
+            &self,
+            _: &str,
+            mut state: serde_json::Value,
+        ) -> Result<serde_json::Value, ProviderError> {
+            // Blocking calls are fine here; the runtime keeps serving
+            std::thread::sleep(Duration::from_millis(10));
+            state["endpoint"] = json!(Context::current().data::<String>()?.as_str());
+            Ok(state)
+        }
+
+        fn read(
+            &self,
+            _: &str,
+            state: serde_json::Value,
+        ) -> Result<serde_json::Value, ProviderError> {
+            if state["name"] == "slow" {
+                std::thread::sleep(Duration::from_millis(200));
+            }
+            if state["name"] == "panic" {
+                panic!("vendor SDK panicked");
+            }
+            Ok(state)
+        }
+
+        fn update(
+            &self,
+            _: &str,
+            _: serde_json::Value,
+            state: serde_json::Value,
+        ) -> Result<serde_json::Value, ProviderError> {
+            if state["name"] == "slow" {
+                std::thread::sleep(Duration::from_millis(200));
+            }
+            Ok(state)
+        }
+
+        fn handle_delete(&self, request: DeleteRequest) -> Result<(), ProviderError> {
+            // The API needs the write-only confirmation from the config
+            match request.config["confirm"].as_str() {
+                Some("yes") => Ok(()),
+                _ => Err(ProviderError::Validation("confirm is required".to_string())),
+            }
+        }
+
+        fn delete(&self, _: &str, _: serde_json::Value) -> Result<(), ProviderError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocking_provider() {
+        let provider = BlockingProvider::new(Vendor);
+        assert!(provider.schema().resources.contains_key("server"));
+
+        // The operation context is in scope on the blocking thread
+        let context =
+            Context::new().with_provider_data(ProviderData::new("https://api".to_string()));
+        let state = context
+            .scope(provider.create("server", json!({"name": "web"})))
+            .await
+            .unwrap();
+        assert_eq!(state, json!({"name": "web", "endpoint": "https://api"}));
+
+        let planned = provider
+            .plan(
+                "server",
+                None,
+                json!({"name": "web"}),
+                json!({"name": "web"}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(planned.planned_state["name"], "web");
+
+        let err = BlockingProvider::new(Vendor)
+            .with_timeout(Duration::from_millis(50))
+            .read("server", json!({"name": "slow"}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::DeadlineExceeded(_)));
+        assert!(err.to_string().contains("Blocking read call"));
+
+        let err = provider
+            .read("server", json!({"name": "panic"}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::Sdk(_)));
+
+        let err = provider.import_resource("server", "web").await.unwrap_err();
+        assert!(err.to_string().contains("Import not supported"));
+
+        // Writes are waited for past the timeout and report their real result
+        let provider = BlockingProvider::new(Vendor).with_timeout(Duration::from_millis(50));
+        let request = UpdateRequest::new("server", json!({"name": "web"}), json!({"name": "slow"}));
+        let state = provider.handle_update(request).await.unwrap();
+        assert_eq!(state, json!({"name": "slow"}));
+
+        // Write-only config reaches the blocking provider
+        let request = DeleteRequest::new("server", json!({"name": "web"}));
+        assert!(provider.handle_delete(request.clone()).await.is_err());
+        provider
+            .handle_delete(request.with_config(json!({"confirm": "yes"})))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_blocking_budget() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let budget = BlockingBudget::new(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let context =
+            Context::new().with_provider_data(ProviderData::new("https://api".to_string()));
+
+        let tasks = (0..6).map(|_| {
+            let (running, max_running) = (Arc::clone(&running), Arc::clone(&max_running));
+            context.clone().scope(budget.run(move || {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(20));
+                running.fetch_sub(1, Ordering::SeqCst);
+                // The operation context is in scope on the blocking thread
+                Context::current().data::<String>().unwrap().len()
+            }))
+        });
+        let results = futures_util::future::join_all(tasks).await;
+        assert!(results.into_iter().all(|r| r.unwrap() == 11));
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        assert_eq!(budget.available(), 2);
+
+        // Waiting for a slot gives up when the operation is cancelled
+        let budget = BlockingBudget::new(1);
+        let busy = budget.run(|| std::thread::sleep(Duration::from_millis(100)));
+        let source = crate::context::CancellationSource::new();
+        let context = Context::new()
+            .with_cancellation(source.token())
+            .with_blocking_budget(budget.clone());
+        let waiting = context.run_blocking(|| ());
+        let cancel = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            source.cancel();
+        };
+        let (busy, waiting, ()) = tokio::join!(busy, waiting, cancel);
+        busy.unwrap();
+        assert!(matches!(waiting, Err(ProviderError::Unavailable(_))));
+    }
+}
@@ -0,0 +1,1034 @@
+//! Response caching for data sources and resource reads.
+//!
+//! Large configurations often read the same data source with the same arguments
+//! many times during a single plan. [`DataSourceCache`] memoizes
+//! `read_data_source` results for a TTL, keyed by the data source type and a
+//! normalized form of its configuration, and coalesces concurrent reads of the
+//! same key into a single upstream call.
+//!
+//! [`ReadCache`] does the same for resource `read`s of types that declare a
+//! [refresh interval](crate::schema::Schema::with_refresh_interval), so full
+//! refreshes of thousands of slow objects are throttled. The server builds one
+//! from [`ProviderMetadata::refresh_intervals`](crate::types::ProviderMetadata::refresh_intervals).
+//!
+//! [`ReadCoalescer`] only collapses concurrent identical reads of either kind,
+//! without keeping results afterwards. The server uses one when
+//! [`ServeOptions::with_read_coalescing`](crate::ServeOptions::with_read_coalescing)
+//! is enabled.
+//!
+//! [`SessionCache`] keeps both kinds of reads for the rest of a plan or apply
+//! run. Core names the run with a session id in the `hemmer-session-id` request
+//! metadata and ends it with the `EndSession` RPC; the server then drops that
+//! session's results. See
+//! [`ServeOptions::with_session_cache`](crate::ServeOptions::with_session_cache).
+//!
+//! # Example
+//!
+//! ```ignore
+//! use hemmer_provider_sdk::cache::DataSourceCache;
+//! use std::time::Duration;
+//!
+//! struct MyProvider {
+//!     cache: DataSourceCache,
+//! }
+//!
+//! #[async_trait]
+//! impl ProviderService for MyProvider {
+//!     async fn read_data_source(
+//!         &self,
+//!         data_source_type: &str,
+//!         config: serde_json::Value,
+//!     ) -> Result<serde_json::Value, ProviderError> {
+//!         self.cache
+//!             .get_or_read(data_source_type, &config, || self.fetch(data_source_type, &config))
+//!             .await
+//!     }
+//!
+//!     async fn create(&self, resource_type: &str, planned: serde_json::Value)
+//!         -> Result<serde_json::Value, ProviderError>
+//!     {
+//!         // Creating an image changes what the image lookup returns
+//!         self.cache.invalidate_type("mycloud_images");
+//!         // ...
+//!     }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+use crate::error::ProviderError;
+use crate::state::canonical_bytes;
+
+/// Counters describing cache effectiveness.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Reads served from the cache.
+    pub hits: u64,
+    /// Reads that called through to the data source.
+    pub misses: u64,
+    /// Entries dropped because they expired or were invalidated.
+    pub evictions: u64,
+    /// Entries currently cached.
+    pub entries: usize,
+}
+
+struct Slot<T = Value> {
+    value: OnceCell<(Instant, T)>,
+}
+
+impl<T> Default for Slot<T> {
+    fn default() -> Self {
+        Self {
+            value: OnceCell::new(),
+        }
+    }
+}
+
+impl<T> Slot<T> {
+    fn is_expired(&self, ttl: Duration) -> bool {
+        self.value
+            .get()
+            .is_some_and(|(stored_at, _)| stored_at.elapsed() >= ttl)
+    }
+}
+
+/// A TTL cache for `read_data_source` results.
+///
+/// Errors are never cached. Cloning a `DataSourceCache` shares the same entries.
+#[derive(Clone)]
+pub struct DataSourceCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<String, Arc<Slot>>>>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+    evictions: Arc<AtomicU64>,
+}
+
+impl DataSourceCache {
+    /// Create a cache whose entries live for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+            evictions: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// The cache key for a data source read.
+    ///
+    /// Object keys are sorted and `null` attributes dropped, so configs that
+    /// differ only in key order or unset optional attributes share an entry.
+    pub fn cache_key(data_source_type: &str, config: &Value) -> String {
+        format!("{}:{}", data_source_type, normalize(config))
+    }
+
+    /// Return the cached result for this read, or call `read` and cache it.
+    ///
+    /// Concurrent calls with the same key wait for a single `read`.
+    pub async fn get_or_read<F, Fut>(
+        &self,
+        data_source_type: &str,
+        config: &Value,
+        read: F,
+    ) -> Result<Value, ProviderError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Value, ProviderError>>,
+    {
+        let key = Self::cache_key(data_source_type, config);
+        let slot = live_slot(&self.entries, key, self.ttl, &self.evictions);
+        let (value, called) = fill(&slot, read).await?;
+
+        if called {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            debug!(data_source_type, "Data source cache miss");
+        } else {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            debug!(data_source_type, "Data source cache hit");
+        }
+        Ok(value)
+    }
+
+    /// Drop the entry for a single read.
+    pub fn invalidate(&self, data_source_type: &str, config: &Value) {
+        let key = Self::cache_key(data_source_type, config);
+        if self.entries.lock().unwrap().remove(&key).is_some() {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Drop every entry for a data source type.
+    pub fn invalidate_type(&self, data_source_type: &str) {
+        let prefix = format!("{}:", data_source_type);
+        self.invalidate_where(|key| key.starts_with(&prefix));
+    }
+
+    /// Drop every entry whose [cache key](Self::cache_key) matches `predicate`.
+    pub fn invalidate_where(&self, predicate: impl Fn(&str) -> bool) {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|key, _| !predicate(key));
+        let removed = (before - entries.len()) as u64;
+        self.evictions.fetch_add(removed, Ordering::Relaxed);
+    }
+
+    /// Drop all entries.
+    pub fn clear(&self) {
+        self.invalidate_where(|_| true);
+    }
+
+    /// Current hit/miss/eviction counters.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+}
+
+impl std::fmt::Debug for DataSourceCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DataSourceCache")
+            .field("ttl", &self.ttl)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// A cache for resource `read` results, honoring per-type refresh intervals.
+///
+/// Entries are keyed by the resource type and the [canonical](crate::state)
+/// state passed to `read`, so a refresh that sends back the state the last read
+/// returned is served from the cache until the type's interval has passed.
+/// Types without an interval always call through. Errors are never cached.
+/// Cloning a `ReadCache` shares the same entries.
+///
+/// The cached results are JSON states by default; the server caches
+/// [`ReadResult`](crate::ReadResult)s so a read's status survives the cache.
+pub struct ReadCache<T = Value> {
+    intervals: Arc<HashMap<String, Duration>>,
+    entries: Arc<Mutex<HashMap<String, Arc<Slot<T>>>>>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+    evictions: Arc<AtomicU64>,
+}
+
+impl<T> Clone for ReadCache<T> {
+    fn clone(&self) -> Self {
+        Self {
+            intervals: Arc::clone(&self.intervals),
+            entries: Arc::clone(&self.entries),
+            hits: Arc::clone(&self.hits),
+            misses: Arc::clone(&self.misses),
+            evictions: Arc::clone(&self.evictions),
+        }
+    }
+}
+
+impl<T> Default for ReadCache<T> {
+    fn default() -> Self {
+        Self {
+            intervals: Arc::default(),
+            entries: Arc::default(),
+            hits: Arc::default(),
+            misses: Arc::default(),
+            evictions: Arc::default(),
+        }
+    }
+}
+
+impl<T: Clone> ReadCache<T> {
+    /// Create a cache from `(resource type, refresh interval)` pairs. Zero
+    /// intervals are ignored.
+    pub fn new<K: Into<String>>(intervals: impl IntoIterator<Item = (K, Duration)>) -> Self {
+        Self {
+            intervals: Arc::new(
+                intervals
+                    .into_iter()
+                    .filter(|(_, interval)| !interval.is_zero())
+                    .map(|(resource_type, interval)| (resource_type.into(), interval))
+                    .collect(),
+            ),
+            ..Self::default()
+        }
+    }
+
+    /// The refresh interval for `resource_type`, if it has one.
+    pub fn interval(&self, resource_type: &str) -> Option<Duration> {
+        self.intervals.get(resource_type).copied()
+    }
+
+    /// Return the cached result of reading `state`, or call `read` and cache it.
+    ///
+    /// Concurrent calls with the same key wait for a single `read`.
+    pub async fn get_or_read<F, Fut>(
+        &self,
+        resource_type: &str,
+        state: &Value,
+        read: F,
+    ) -> Result<T, ProviderError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let Some(interval) = self.interval(resource_type) else {
+            return read().await;
+        };
+        let key = ReadCache::cache_key(resource_type, state);
+        let slot = live_slot(&self.entries, key, interval, &self.evictions);
+        let (value, called) = fill(&slot, read).await?;
+
+        if called {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            debug!(resource_type, "Served read from refresh cache");
+        }
+        Ok(value)
+    }
+
+    /// Drop the entry for `state`, e.g. after the resource was updated or
+    /// deleted.
+    pub fn invalidate(&self, resource_type: &str, state: &Value) {
+        if self.intervals.contains_key(resource_type) {
+            let key = ReadCache::cache_key(resource_type, state);
+            if self.entries.lock().unwrap().remove(&key).is_some() {
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Current hit/miss/eviction counters.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+}
+
+impl ReadCache {
+    fn cache_key(resource_type: &str, state: &Value) -> String {
+        format!(
+            "{}:{}",
+            resource_type,
+            String::from_utf8_lossy(&canonical_bytes(state))
+        )
+    }
+}
+
+impl<T: Clone> std::fmt::Debug for ReadCache<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadCache")
+            .field("intervals", &self.intervals)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// How long a session may go unused before its results are dropped, in case
+/// core never ends it.
+pub const DEFAULT_SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Caches resource and data source reads for the duration of a core session.
+///
+/// Within one plan or apply run, core may read the same data source or
+/// resource state many times. Results are kept per session id, keyed like
+/// [`ReadCache`] and [`DataSourceCache`], until the session ends, so later
+/// reads in the same run are served without an upstream call while other runs
+/// are unaffected. Concurrent identical reads share one call. Errors are never
+/// cached.
+///
+/// Writes should [`invalidate`](Self::invalidate) the prior state of the
+/// resource they change and [`invalidate_data_sources`](Self::invalidate_data_sources),
+/// whose results may depend on it. Sessions not used for the idle timeout are
+/// dropped. Cloning a `SessionCache` shares the same sessions.
+pub struct SessionCache<T = Value> {
+    idle_timeout: Duration,
+    sessions: Arc<Mutex<HashMap<String, Session<T>>>>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+    evictions: Arc<AtomicU64>,
+}
+
+struct Session<T> {
+    last_used: Instant,
+    entries: HashMap<String, Arc<Slot<T>>>,
+}
+
+impl<T> Clone for SessionCache<T> {
+    fn clone(&self) -> Self {
+        Self {
+            idle_timeout: self.idle_timeout,
+            sessions: Arc::clone(&self.sessions),
+            hits: Arc::clone(&self.hits),
+            misses: Arc::clone(&self.misses),
+            evictions: Arc::clone(&self.evictions),
+        }
+    }
+}
+
+impl<T> Default for SessionCache<T> {
+    fn default() -> Self {
+        Self::new(DEFAULT_SESSION_IDLE_TIMEOUT)
+    }
+}
+
+impl<T> SessionCache<T> {
+    /// Create a cache that drops sessions unused for `idle_timeout`.
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            idle_timeout,
+            sessions: Arc::default(),
+            hits: Arc::default(),
+            misses: Arc::default(),
+            evictions: Arc::default(),
+        }
+    }
+}
+
+impl<T: Clone> SessionCache<T> {
+    /// Return the result of reading `state` earlier in the session, or call
+    /// `read` and keep its result for the session.
+    pub async fn read<F, Fut>(
+        &self,
+        session_id: &str,
+        resource_type: &str,
+        state: &Value,
+        read: F,
+    ) -> Result<T, ProviderError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        self.get_or_read(session_id, resource_key(resource_type, state), read)
+            .await
+    }
+
+    /// Return the result of reading the data source earlier in the session,
+    /// or call `read` and keep its result for the session.
+    pub async fn read_data_source<F, Fut>(
+        &self,
+        session_id: &str,
+        data_source_type: &str,
+        config: &Value,
+        read: F,
+    ) -> Result<T, ProviderError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let key = data_source_key(data_source_type, config);
+        self.get_or_read(session_id, key, read).await
+    }
+
+    /// Drop the result of reading `state` from every session.
+    pub fn invalidate(&self, resource_type: &str, state: &Value) {
+        let key = resource_key(resource_type, state);
+        self.invalidate_where(|k| k == key);
+    }
+
+    /// Drop the result of reading the data source with `config` from every
+    /// session.
+    pub fn invalidate_data_source(&self, data_source_type: &str, config: &Value) {
+        let key = data_source_key(data_source_type, config);
+        self.invalidate_where(|k| k == key);
+    }
+
+    /// Drop every data source result from every session.
+    pub fn invalidate_data_sources(&self) {
+        self.invalidate_where(|k| k.starts_with("data_source:"));
+    }
+
+    /// Drop a session and its results. Returns whether it existed.
+    pub fn end_session(&self, session_id: &str) -> bool {
+        let session = self.sessions.lock().unwrap().remove(session_id);
+        match session {
+            Some(session) => {
+                self.evictions
+                    .fetch_add(session.entries.len() as u64, Ordering::Relaxed);
+                debug!(session_id, "Session ended, dropped cached reads");
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Drop every session.
+    pub fn clear(&self) {
+        let removed: usize = self
+            .sessions
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, session)| session.entries.len())
+            .sum();
+        self.evictions.fetch_add(removed as u64, Ordering::Relaxed);
+    }
+
+    /// The number of live sessions.
+    pub fn sessions(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    /// Current hit/miss/eviction counters, with `entries` summed over sessions.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: self
+                .sessions
+                .lock()
+                .unwrap()
+                .values()
+                .map(|session| session.entries.len())
+                .sum(),
+        }
+    }
+
+    async fn get_or_read<F, Fut>(
+        &self,
+        session_id: &str,
+        key: String,
+        read: F,
+    ) -> Result<T, ProviderError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let slot = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let now = Instant::now();
+            let before: usize = sessions.values().map(|s| s.entries.len()).sum();
+            sessions.retain(|_, session| now.duration_since(session.last_used) < self.idle_timeout);
+            let after: usize = sessions.values().map(|s| s.entries.len()).sum();
+            self.evictions
+                .fetch_add((before - after) as u64, Ordering::Relaxed);
+
+            let session = sessions
+                .entry(session_id.to_string())
+                .or_insert_with(|| Session {
+                    last_used: now,
+                    entries: HashMap::new(),
+                });
+            session.last_used = now;
+            Arc::clone(session.entries.entry(key).or_default())
+        };
+        let (value, called) = fill(&slot, read).await?;
+
+        if called {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            debug!(session_id, "Served read from session cache");
+        }
+        Ok(value)
+    }
+
+    fn invalidate_where(&self, predicate: impl Fn(&str) -> bool) {
+        let mut removed = 0;
+        for session in self.sessions.lock().unwrap().values_mut() {
+            let before = session.entries.len();
+            session.entries.retain(|key, _| !predicate(key));
+            removed += before - session.entries.len();
+        }
+        self.evictions.fetch_add(removed as u64, Ordering::Relaxed);
+    }
+}
+
+impl<T: Clone> std::fmt::Debug for SessionCache<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionCache")
+            .field("idle_timeout", &self.idle_timeout)
+            .field("sessions", &self.sessions())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// The key shared by resource reads in [`ReadCoalescer`] and [`SessionCache`].
+fn resource_key(resource_type: &str, state: &Value) -> String {
+    format!("resource:{}", ReadCache::cache_key(resource_type, state))
+}
+
+/// The key shared by data source reads in [`ReadCoalescer`] and [`SessionCache`].
+fn data_source_key(data_source_type: &str, config: &Value) -> String {
+    format!(
+        "data_source:{}",
+        DataSourceCache::cache_key(data_source_type, config)
+    )
+}
+
+/// Collapses concurrent identical reads into a single upstream call.
+///
+/// Refreshing a large configuration can send many `Read`s for the same
+/// resource state, or `ReadDataSource`s with the same configuration, at once.
+/// A coalescer lets the first of them call the provider and hands its result
+/// to the others that arrive while it is running. Unlike the caches, nothing is
+/// kept once the call completes, so a later read always calls through.
+///
+/// Reads are keyed like [`ReadCache`] (resource type and canonical state) and
+/// [`DataSourceCache`] (data source type and normalized configuration).
+/// Errors are not shared: a waiting read makes its own call instead. Cloning a
+/// `ReadCoalescer` shares the in-flight reads.
+pub struct ReadCoalescer<T = Value> {
+    in_flight: Arc<Mutex<HashMap<String, Arc<OnceCell<T>>>>>,
+    calls: Arc<AtomicU64>,
+    coalesced: Arc<AtomicU64>,
+}
+
+impl<T> Clone for ReadCoalescer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            in_flight: Arc::clone(&self.in_flight),
+            calls: Arc::clone(&self.calls),
+            coalesced: Arc::clone(&self.coalesced),
+        }
+    }
+}
+
+impl<T> Default for ReadCoalescer<T> {
+    fn default() -> Self {
+        Self {
+            in_flight: Arc::default(),
+            calls: Arc::default(),
+            coalesced: Arc::default(),
+        }
+    }
+}
+
+impl<T: Clone> ReadCoalescer<T> {
+    /// Create a coalescer with no reads in flight.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `read` for a resource, unless an identical read is in flight, in
+    /// which case wait for its result.
+    pub async fn read<F, Fut>(
+        &self,
+        resource_type: &str,
+        state: &Value,
+        read: F,
+    ) -> Result<T, ProviderError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        self.coalesce(resource_key(resource_type, state), read)
+            .await
+    }
+
+    /// Call `read` for a data source, unless an identical read is in flight,
+    /// in which case wait for its result.
+    pub async fn read_data_source<F, Fut>(
+        &self,
+        data_source_type: &str,
+        config: &Value,
+        read: F,
+    ) -> Result<T, ProviderError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        self.coalesce(data_source_key(data_source_type, config), read)
+            .await
+    }
+
+    /// Call counters: `misses` counts upstream calls, `hits` counts reads that
+    /// shared another's result, and `entries` the reads in flight.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.coalesced.load(Ordering::Relaxed),
+            misses: self.calls.load(Ordering::Relaxed),
+            evictions: 0,
+            entries: self.in_flight.lock().unwrap().len(),
+        }
+    }
+
+    async fn coalesce<F, Fut>(&self, key: String, read: F) -> Result<T, ProviderError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let cell = Arc::clone(
+            self.in_flight
+                .lock()
+                .unwrap()
+                .entry(key.clone())
+                .or_default(),
+        );
+        let mut called = false;
+        let result = cell
+            .get_or_try_init(|| {
+                called = true;
+                read()
+            })
+            .await
+            .cloned();
+
+        if called {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            // Later reads must call through, unless another read already
+            // replaced the entry
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if in_flight
+                .get(&key)
+                .is_some_and(|current| Arc::ptr_eq(current, &cell))
+            {
+                in_flight.remove(&key);
+            }
+        } else {
+            self.coalesced.fetch_add(1, Ordering::Relaxed);
+            debug!("Coalesced concurrent read");
+        }
+        result
+    }
+}
+
+impl<T: Clone> std::fmt::Debug for ReadCoalescer<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadCoalescer")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// The slot for `key`, replaced with an empty one if it has expired.
+fn live_slot<T>(
+    entries: &Mutex<HashMap<String, Arc<Slot<T>>>>,
+    key: String,
+    ttl: Duration,
+    evictions: &AtomicU64,
+) -> Arc<Slot<T>> {
+    let mut entries = entries.lock().unwrap();
+    let slot = entries.entry(key).or_default();
+    if slot.is_expired(ttl) {
+        *slot = Arc::default();
+        evictions.fetch_add(1, Ordering::Relaxed);
+    }
+    Arc::clone(slot)
+}
+
+/// The value in `slot`, calling `read` to fill it if it is empty. Returns
+/// whether `read` was called.
+async fn fill<T: Clone, F, Fut>(slot: &Slot<T>, read: F) -> Result<(T, bool), ProviderError>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, ProviderError>>,
+{
+    let mut called = false;
+    let (_, value) = slot
+        .value
+        .get_or_try_init(|| {
+            called = true;
+            async {
+                let value = read().await?;
+                Ok::<_, ProviderError>((Instant::now(), value))
+            }
+        })
+        .await?;
+    Ok((value.clone(), called))
+}
+
+/// Serialize a value as [canonical JSON](crate::state::canonical_bytes),
+/// without `null` attributes.
+fn normalize(value: &Value) -> String {
+    fn without_nulls(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .filter(|(_, v)| !v.is_null())
+                    .map(|(k, v)| (k.clone(), without_nulls(v)))
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.iter().map(without_nulls).collect()),
+            other => other.clone(),
+        }
+    }
+    String::from_utf8(canonical_bytes(&without_nulls(value))).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_cache_key_normalized() {
+        let a = json!({"name": "web", "region": "us-east-1", "filter": null, "limit": 10.0});
+        let b = json!({"region": "us-east-1", "name": "web", "limit": 10});
+        assert_eq!(
+            DataSourceCache::cache_key("images", &a),
+            DataSourceCache::cache_key("images", &b)
+        );
+        assert_ne!(
+            DataSourceCache::cache_key("images", &a),
+            DataSourceCache::cache_key("zones", &a)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_or_read_caches_and_invalidates() {
+        let cache = DataSourceCache::new(Duration::from_secs(60));
+        let calls = AtomicUsize::new(0);
+        let config = json!({"name": "web"});
+        let read = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({"id": "img-1"}))
+        };
+
+        for _ in 0..3 {
+            let value = cache.get_or_read("images", &config, read).await.unwrap();
+            assert_eq!(value["id"], "img-1");
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        cache.invalidate_type("images");
+        cache.get_or_read("images", &config, read).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (2, 2, 1));
+        assert_eq!(stats.entries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_errors_not_cached_and_ttl_expiry() {
+        let cache = DataSourceCache::new(Duration::ZERO);
+        let config = json!({});
+
+        let err = cache
+            .get_or_read("images", &config, || async {
+                Err(ProviderError::Unavailable("down".to_string()))
+            })
+            .await;
+        assert!(err.is_err());
+
+        let value = cache
+            .get_or_read("images", &config, || async { Ok(json!(1)) })
+            .await
+            .unwrap();
+        assert_eq!(value, json!(1));
+
+        // Zero TTL: the next read is a miss again
+        let value = cache
+            .get_or_read("images", &config, || async { Ok(json!(2)) })
+            .await
+            .unwrap();
+        assert_eq!(value, json!(2));
+        assert_eq!(cache.stats().hits, 0);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_reads_coalesce() {
+        let cache = DataSourceCache::new(Duration::from_secs(60));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..10)
+            .map(|_| {
+                let cache = cache.clone();
+                let calls = Arc::clone(&calls);
+                tokio::spawn(async move {
+                    cache
+                        .get_or_read("images", &json!({"name": "web"}), || async move {
+                            calls.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(10)).await;
+                            Ok(json!("ok"))
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.await.unwrap().unwrap(), "ok");
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_read_coalescer() {
+        let coalescer = ReadCoalescer::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..10)
+            .map(|i| {
+                let coalescer = coalescer.clone();
+                let calls = Arc::clone(&calls);
+                // Key order doesn't change the key
+                let state = if i % 2 == 0 {
+                    json!({"id": "b-1", "size": 10})
+                } else {
+                    json!({"size": 10, "id": "b-1"})
+                };
+                tokio::spawn(async move {
+                    coalescer
+                        .read("bucket", &state, || async move {
+                            calls.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            Ok(json!({"id": "b-1", "size": 11}))
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.await.unwrap().unwrap()["size"], 11);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let stats = coalescer.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (9, 1, 0));
+
+        // Nothing is kept once the read completes
+        let state = json!({"id": "b-1", "size": 10});
+        coalescer
+            .read("bucket", &state, || async { Ok(json!(null)) })
+            .await
+            .unwrap();
+        // A data source with the same name and arguments is a different read
+        coalescer
+            .read_data_source("bucket", &state, || async {
+                Err(ProviderError::ResourceExhausted("throttled".to_string()))
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(coalescer.stats().misses, 3);
+        assert_eq!(coalescer.stats().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_read_cache_honors_intervals() {
+        let cache = ReadCache::new([
+            ("bucket", Duration::from_secs(600)),
+            ("object", Duration::ZERO),
+        ]);
+        assert_eq!(cache.interval("bucket"), Some(Duration::from_secs(600)));
+        assert_eq!(cache.interval("object"), None);
+
+        let calls = AtomicUsize::new(0);
+        let read = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({"id": "b-1", "size": 10}))
+        };
+        let state = json!({"id": "b-1", "size": 10});
+        for _ in 0..3 {
+            cache.get_or_read("bucket", &state, read).await.unwrap();
+        }
+        // Key order and 10 vs 10.0 don't change the key
+        cache
+            .get_or_read("bucket", &json!({"size": 10.0, "id": "b-1"}), read)
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        cache.invalidate("bucket", &state);
+        cache.get_or_read("bucket", &state, read).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Types without an interval are never cached
+        for _ in 0..2 {
+            cache.get_or_read("object", &state, read).await.unwrap();
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (3, 2, 1));
+        assert_eq!(stats.entries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_session_cache() {
+        let cache: SessionCache = SessionCache::new(Duration::from_secs(60));
+        let calls = AtomicUsize::new(0);
+        let state = json!({"id": "i-1"});
+        let read = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({"id": "i-1", "size": "large"}))
+        };
+
+        // Reads within a session share one call; other sessions read again
+        for _ in 0..3 {
+            cache
+                .read("plan-1", "instance", &state, read)
+                .await
+                .unwrap();
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        cache
+            .read("plan-2", "instance", &state, read)
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let config = json!({"name": "web"});
+        for _ in 0..2 {
+            cache
+                .read_data_source("plan-1", "images", &config, read)
+                .await
+                .unwrap();
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(cache.stats().entries, 3);
+
+        // A write drops the written state and data sources from every session
+        cache.invalidate("instance", &state);
+        cache.invalidate_data_sources();
+        assert_eq!(cache.stats().entries, 0);
+
+        cache
+            .read("plan-1", "instance", &state, read)
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert!(cache.end_session("plan-1"));
+        assert!(!cache.end_session("plan-1"));
+        assert_eq!(cache.sessions(), 1);
+
+        cache
+            .read("plan-1", "instance", &state, read)
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+
+        let failing = || async { Err(ProviderError::Unavailable("down".to_string())) };
+        assert!(cache
+            .read("plan-1", "instance", &json!({"id": "i-2"}), failing)
+            .await
+            .is_err());
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (3, 5));
+    }
+
+    #[tokio::test]
+    async fn test_session_cache_idle_timeout() {
+        let cache: SessionCache = SessionCache::new(Duration::from_millis(20));
+        let read = || async { Ok(json!({})) };
+        cache
+            .read("plan-1", "instance", &json!({}), read)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        cache
+            .read("plan-2", "instance", &json!({}), read)
+            .await
+            .unwrap();
+        assert_eq!(cache.sessions(), 1);
+        assert_eq!(cache.stats().evictions, 1);
+    }
+}
@@ -0,0 +1,251 @@
+//! State transformation hooks.
+//!
+//! A [`StateCodec`] set with [`ServeOptions::with_state_codec`](crate::ServeOptions::with_state_codec)
+//! lets a provider keep plaintext secrets out of the state core stores. The
+//! server encodes every state it returns (planned, created, read, updated,
+//! upgraded and imported states) and decodes every state it receives (prior,
+//! planned and current states) before the provider sees it, so
+//! [`ProviderService`](crate::ProviderService) methods always work with
+//! plaintext values. Configuration and proposed state come from the user's
+//! configuration and are never transformed.
+//!
+//! Codecs run with the operation [`Context`](crate::context::Context) in scope,
+//! so a key stored by `configure` is available through
+//! [`Context::provider_data`](crate::context::Context::provider_data).
+//!
+//! [`SensitiveAttributeCodec`] applies a pair of functions to the `sensitive`
+//! attributes of each resource schema, which covers the common case of
+//! encrypting individual fields:
+//!
+//! ```
+//! use hemmer_provider_sdk::codec::{SensitiveAttributeCodec, StateCodec};
+//! use hemmer_provider_sdk::schema::{Attribute, ProviderSchema, Schema};
+//! use serde_json::{json, Value};
+//!
+//! # tokio_test::block_on(async {
+//! let schema = ProviderSchema::new().with_resource(
+//!     "db_user",
+//!     Schema::v0()
+//!         .with_attribute("name", Attribute::required_string())
+//!         .with_attribute("password", Attribute::required_string().sensitive()),
+//! );
+//!
+//! // A real codec would encrypt with a provider-held key
+//! let codec = SensitiveAttributeCodec::new(
+//!     schema,
+//!     |v: &Value| Ok(json!(format!("enc:{}", v.as_str().unwrap_or_default()))),
+//!     |v: &Value| Ok(json!(v.as_str().unwrap_or_default().trim_start_matches("enc:"))),
+//! );
+//!
+//! let state = json!({"name": "app", "password": "hunter2"});
+//! let stored = codec.encode("db_user", state.clone()).await.unwrap();
+//! assert_eq!(stored, json!({"name": "app", "password": "enc:hunter2"}));
+//! assert_eq!(codec.decode("db_user", stored).await.unwrap(), state);
+//! # });
+//! ```
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::error::ProviderError;
+use crate::schema::{Block, BlockNestingMode, ProviderSchema};
+
+/// Transforms resource state on its way to and from core.
+///
+/// `decode` must invert `encode`. Null states are never passed to a codec.
+#[async_trait::async_trait]
+pub trait StateCodec: Send + Sync + 'static {
+    /// Transform a state before it is returned to core.
+    async fn encode(&self, resource_type: &str, state: Value) -> Result<Value, ProviderError>;
+
+    /// Transform a state received from core before the provider sees it.
+    async fn decode(&self, resource_type: &str, state: Value) -> Result<Value, ProviderError>;
+}
+
+/// A [`StateCodec`] that transforms the `sensitive` attributes of each resource.
+///
+/// `encode` and `decode` are called for every non-null sensitive attribute
+/// value, including those within nested blocks and every attribute of a
+/// [sensitive block](crate::schema::NestedBlock::sensitive). Resource types
+/// missing from the schema pass through unchanged.
+pub struct SensitiveAttributeCodec<E, D> {
+    schema: ProviderSchema,
+    encode: E,
+    decode: D,
+}
+
+impl<E, D> SensitiveAttributeCodec<E, D>
+where
+    E: Fn(&Value) -> Result<Value, ProviderError> + Send + Sync + 'static,
+    D: Fn(&Value) -> Result<Value, ProviderError> + Send + Sync + 'static,
+{
+    /// Create a codec for the resources in `schema`.
+    pub fn new(schema: ProviderSchema, encode: E, decode: D) -> Self {
+        Self {
+            schema,
+            encode,
+            decode,
+        }
+    }
+
+    fn transform(
+        &self,
+        resource_type: &str,
+        mut state: Value,
+        f: &(dyn Fn(&Value) -> Result<Value, ProviderError> + Sync),
+    ) -> Result<Value, ProviderError> {
+        if let Some(schema) = self.schema.resources.get(resource_type) {
+            transform_sensitive(&schema.block, &mut state, false, f)?;
+        }
+        Ok(state)
+    }
+}
+
+#[async_trait::async_trait]
+impl<E, D> StateCodec for SensitiveAttributeCodec<E, D>
+where
+    E: Fn(&Value) -> Result<Value, ProviderError> + Send + Sync + 'static,
+    D: Fn(&Value) -> Result<Value, ProviderError> + Send + Sync + 'static,
+{
+    async fn encode(&self, resource_type: &str, state: Value) -> Result<Value, ProviderError> {
+        self.transform(resource_type, state, &self.encode)
+    }
+
+    async fn decode(&self, resource_type: &str, state: Value) -> Result<Value, ProviderError> {
+        self.transform(resource_type, state, &self.decode)
+    }
+}
+
+impl<E, D> fmt::Debug for SensitiveAttributeCodec<E, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SensitiveAttributeCodec")
+            .field(
+                "resources",
+                &self.schema.resources.keys().collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Apply `f` to every non-null sensitive attribute value in `value`, or to
+/// every attribute if the block is inside a sensitive block.
+pub(crate) fn transform_sensitive(
+    block: &Block,
+    value: &mut Value,
+    inherited: bool,
+    f: &(dyn Fn(&Value) -> Result<Value, ProviderError> + Sync),
+) -> Result<(), ProviderError> {
+    let Some(obj) = value.as_object_mut() else {
+        return Ok(());
+    };
+
+    for (name, attr) in &block.attributes {
+        if !attr.flags.sensitive && !inherited {
+            continue;
+        }
+        if let Some(slot) = obj.get_mut(name).filter(|v| !v.is_null()) {
+            *slot = f(slot)?;
+        }
+    }
+
+    for (name, nested) in &block.blocks {
+        let Some(child) = obj.get_mut(name) else {
+            continue;
+        };
+        let sensitive = inherited || nested.sensitive;
+        match nested.nesting_mode {
+            BlockNestingMode::Single => transform_sensitive(&nested.block, child, sensitive, f)?,
+            BlockNestingMode::List | BlockNestingMode::Set => {
+                for item in child.as_array_mut().into_iter().flatten() {
+                    transform_sensitive(&nested.block, item, sensitive, f)?;
+                }
+            },
+            BlockNestingMode::Map => {
+                for item in child
+                    .as_object_mut()
+                    .into_iter()
+                    .flat_map(|m| m.values_mut())
+                {
+                    transform_sensitive(&nested.block, item, sensitive, f)?;
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Attribute, NestedBlock, Schema};
+    use serde_json::json;
+
+    fn reverse(v: &Value) -> Result<Value, ProviderError> {
+        let s = v
+            .as_str()
+            .ok_or_else(|| ProviderError::Sdk("expected string".to_string()))?;
+        Ok(json!(s.chars().rev().collect::<String>()))
+    }
+
+    #[tokio::test]
+    async fn test_sensitive_attribute_codec_nested() {
+        let schema = ProviderSchema::new().with_resource(
+            "db",
+            Schema::v0()
+                .with_attribute("name", Attribute::required_string())
+                .with_attribute("password", Attribute::optional_string().sensitive())
+                .with_block(
+                    "user",
+                    NestedBlock::list(
+                        Block::new()
+                            .with_attribute("login", Attribute::required_string())
+                            .with_attribute("token", Attribute::required_string().sensitive()),
+                    ),
+                ),
+        );
+        let codec = SensitiveAttributeCodec::new(schema, reverse, reverse);
+
+        let state = json!({
+            "name": "main",
+            "password": null,
+            "user": [{"login": "alice", "token": "abc"}, {"login": "bob", "token": "xyz"}]
+        });
+        let encoded = codec.encode("db", state.clone()).await.unwrap();
+        assert_eq!(encoded["password"], Value::Null);
+        assert_eq!(encoded["user"][0]["token"], "cba");
+        assert_eq!(encoded["user"][1]["login"], "bob");
+        assert_eq!(codec.decode("db", encoded).await.unwrap(), state);
+
+        // Unknown resource types pass through
+        let other = json!({"password": "abc"});
+        assert_eq!(codec.encode("other", other.clone()).await.unwrap(), other);
+    }
+
+    #[tokio::test]
+    async fn test_sensitive_block_covers_all_attributes() {
+        let schema = ProviderSchema::new().with_resource(
+            "db",
+            Schema::v0()
+                .with_attribute("name", Attribute::required_string())
+                .with_block(
+                    "credentials",
+                    NestedBlock::single(
+                        Block::new()
+                            .with_attribute("user", Attribute::required_string())
+                            .with_attribute("key", Attribute::required_string()),
+                    )
+                    .sensitive(),
+                ),
+        );
+        let codec = SensitiveAttributeCodec::new(schema, reverse, reverse);
+
+        let state = json!({"name": "main", "credentials": {"user": "abc", "key": "xyz"}});
+        let encoded = codec.encode("db", state.clone()).await.unwrap();
+        assert_eq!(
+            encoded,
+            json!({"name": "main", "credentials": {"user": "cba", "key": "zyx"}})
+        );
+        assert_eq!(codec.decode("db", encoded).await.unwrap(), state);
+    }
+}
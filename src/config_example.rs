@@ -0,0 +1,246 @@
+//! Example configuration snippets.
+//!
+//! [`resource_example`] and [`data_source_example`] render a schema as a
+//! Hemmer configuration block for documentation and the devtool `example`
+//! command. Required attributes get a placeholder value for their type,
+//! optional attributes are commented out (showing their default, if any),
+//! and computed-only attributes are left out. Nested blocks are written out
+//! as many times as they are required, or once, commented out, if they are
+//! optional. Attribute descriptions become trailing comments.
+//!
+//! ```
+//! use hemmer_provider_sdk::config_example::resource_example;
+//! use hemmer_provider_sdk::schema::{Attribute, Block, NestedBlock, Schema};
+//! use serde_json::json;
+//!
+//! let schema = Schema::v0()
+//!     .with_attribute("name", Attribute::required_string().with_description("Bucket name"))
+//!     .with_attribute("versioning", Attribute::optional_bool().with_default(json!(false)))
+//!     .with_attribute("arn", Attribute::computed_string())
+//!     .with_block(
+//!         "lifecycle_rule",
+//!         NestedBlock::list(Block::new().with_attribute("days", Attribute::required_int64())),
+//!     );
+//!
+//! assert_eq!(
+//!     resource_example("bucket", &schema),
+//!     r#"resource "bucket" "example" {
+//!   name       = "example" # Bucket name
+//!   ## versioning = false
+//!
+//!   ## lifecycle_rule {
+//!   ##   days = 0
+//!   ## }
+//! }
+//! "#
+//! );
+//! ```
+
+use serde_json::Value;
+
+use crate::schema::{example_for_type, Attribute, Block, BlockNestingMode, Schema};
+
+/// An example configuration block for a resource.
+pub fn resource_example(resource_type: &str, schema: &Schema) -> String {
+    render("resource", resource_type, schema)
+}
+
+/// An example configuration block for a data source.
+pub fn data_source_example(data_source_type: &str, schema: &Schema) -> String {
+    render("data", data_source_type, schema)
+}
+
+fn render(kind: &str, type_name: &str, schema: &Schema) -> String {
+    let mut out = format!("{} {:?} \"example\" {{\n", kind, type_name);
+    for line in block_lines(&schema.block, 1, None) {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn is_configurable(attr: &Attribute) -> bool {
+    attr.flags.required || attr.flags.optional
+}
+
+/// Indentation for a line at `depth`, commented out from `comment_at` if set.
+fn indent(depth: usize, comment_at: Option<usize>) -> String {
+    match comment_at {
+        Some(at) => format!("{}# {}", "  ".repeat(at), "  ".repeat(depth - at)),
+        None => "  ".repeat(depth),
+    }
+}
+
+/// The lines of a block's body, indented by `depth` levels and commented out
+/// from the level `comment_at` if the block is.
+fn block_lines(block: &Block, depth: usize, comment_at: Option<usize>) -> Vec<String> {
+    let line = |commented: bool, text: &str| {
+        let comment_at = comment_at.or(commented.then_some(depth));
+        format!("{}{}", indent(depth, comment_at), text)
+    };
+
+    let mut attributes: Vec<(&String, &Attribute)> = block
+        .attributes
+        .iter()
+        .filter(|(_, attr)| is_configurable(attr))
+        .collect();
+    // Required attributes first, each group in schema order
+    attributes.sort_by_key(|(_, attr)| !attr.flags.required);
+    let width = attributes
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for (name, attr) in attributes {
+        let value = match &attr.default {
+            Some(default) if !attr.flags.required => default.clone(),
+            _ => example_for_type(&attr.attr_type),
+        };
+        let mut text = format!("{:width$} = {}", name, inline(&value), width = width);
+        if let Some(description) = &attr.description {
+            text = format!("{} # {}", text, description);
+        }
+        lines.push(line(!attr.flags.required, &text));
+    }
+
+    for (name, nested) in &block.blocks {
+        let optional = nested.min_items == 0;
+        let nested_comment_at = comment_at.or(optional.then_some(depth));
+        for i in 0..nested.min_items.max(1) {
+            lines.push(String::new());
+            let header = match nested.nesting_mode {
+                BlockNestingMode::Map => format!("{} \"example_{}\" {{", name, i),
+                _ => format!("{} {{", name),
+            };
+            lines.push(line(optional, &header));
+            lines.extend(block_lines(&nested.block, depth + 1, nested_comment_at));
+            lines.push(line(optional, "}"));
+            if nested.nesting_mode == BlockNestingMode::Single {
+                break;
+            }
+        }
+    }
+
+    // A body that starts with a block doesn't need the blank line
+    if lines.first().is_some_and(String::is_empty) {
+        lines.remove(0);
+    }
+    lines
+}
+
+/// A value in configuration syntax, on one line.
+fn inline(value: &Value) -> String {
+    match value {
+        Value::Array(items) => format!(
+            "[{}]",
+            items.iter().map(inline).collect::<Vec<_>>().join(", ")
+        ),
+        Value::Object(map) if map.is_empty() => "{}".to_string(),
+        Value::Object(map) => format!(
+            "{{ {} }}",
+            map.iter()
+                .map(|(key, value)| format!("{} = {}", key_name(key), inline(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        other => other.to_string(),
+    }
+}
+
+/// An object key, quoted unless it is an identifier.
+fn key_name(key: &str) -> String {
+    let is_identifier = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if is_identifier {
+        key.to_string()
+    } else {
+        format!("{:?}", key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{AttributeFlags, AttributeType, NestedBlock};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_nested_blocks_and_values() {
+        let schema = Schema::v0()
+            .with_attribute("zone", Attribute::required_string())
+            .with_attribute(
+                "ports",
+                Attribute::new(
+                    AttributeType::list(AttributeType::Int64),
+                    AttributeFlags::optional(),
+                )
+                .with_default(json!([80, 443])),
+            )
+            .with_attribute(
+                "endpoint",
+                Attribute::new(
+                    AttributeType::object(BTreeMap::from([
+                        ("host".to_string(), AttributeType::String),
+                        ("port".to_string(), AttributeType::Int64),
+                    ])),
+                    AttributeFlags::required(),
+                ),
+            )
+            .with_attribute("id", Attribute::computed_string())
+            .with_block(
+                "disk",
+                NestedBlock::list(Block::new().with_attribute("size", Attribute::required_int64()))
+                    .with_min_items(2),
+            )
+            .with_block(
+                "network",
+                NestedBlock::single(Block::new().with_block(
+                    "rule",
+                    NestedBlock::map(
+                        Block::new().with_attribute("cidr", Attribute::required_string()),
+                    ),
+                )),
+            );
+
+        assert_eq!(
+            data_source_example("instance", &schema),
+            r#"data "instance" "example" {
+  endpoint = { host = "example", port = 0 }
+  zone     = "example"
+  # ports    = [80, 443]
+
+  disk {
+    size = 0
+  }
+
+  disk {
+    size = 0
+  }
+
+  # network {
+  #   rule "example_0" {
+  #     cidr = "example"
+  #   }
+  # }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_key_names() {
+        assert_eq!(
+            inline(&json!({"Name": "web", "kubernetes.io/role": "node"})),
+            r#"{ Name = "web", "kubernetes.io/role" = "node" }"#
+        );
+    }
+}
@@ -0,0 +1,697 @@
+//! Per-operation context for provider implementations.
+//!
+//! The SDK runs every provider operation with a [`Context`] in scope, which
+//! providers can access with [`Context::current`] without changing the
+//! [`ProviderService`](crate::ProviderService) method signatures.
+//!
+//! The context carries the provider data returned from `configure` through
+//! [`ConfigureResult::with_provider_data`](crate::ConfigureResult::with_provider_data),
+//! replacing the usual `OnceCell<Option<Client>>` + "provider not configured"
+//! boilerplate:
+//!
+//! ```ignore
+//! async fn configure(&self, config: Value) -> Result<ConfigureResult, ProviderError> {
+//!     let client = ApiClient::new(config["endpoint"].as_str().unwrap_or_default())?;
+//!     Ok(ConfigureResult::new().with_provider_data(client))
+//! }
+//!
+//! async fn read(&self, resource_type: &str, state: Value) -> Result<Value, ProviderError> {
+//!     let client = Context::current().data::<ApiClient>()?;
+//!     client.get(&state["id"]).await
+//! }
+//! ```
+//!
+//! Data that needs asynchronous cleanup (closing a connection pool, revoking a
+//! session) can register a teardown hook with
+//! [`ConfigureResult::with_teardown`](crate::ConfigureResult::with_teardown).
+//! The server runs it after the provider's `stop()`; data replaced by a later
+//! `configure` is torn down once the last operation using it finishes.
+//!
+//! It also carries a cancellation signal, raised when the server shuts down
+//! (on `Stop` or an OS signal), so long-running operations can stop waiting and
+//! return instead of being abandoned:
+//!
+//! ```ignore
+//! tokio::select! {
+//!     result = client.wait_until_ready(&id) => result?,
+//!     _ = Context::current().cancelled() => {
+//!         return Err(ProviderError::Unavailable("Provider is shutting down".to_string()));
+//!     },
+//! }
+//! ```
+//!
+//! And it carries the [`ClientCapabilities`] sent by core with the request, so
+//! a provider can use optional behaviors only when core understands them:
+//!
+//! ```ignore
+//! if Context::current().client_capabilities().warnings_on_apply {
+//!     diagnostics.push(Diagnostic::warning("Instance type is deprecated"));
+//! }
+//! ```
+//!
+//! Core may call `configure` again on a long-running provider process, e.g.
+//! after credentials are rotated. The new provider data replaces the old once
+//! `configure` succeeds; operations already running keep the data they started
+//! with, and [`Context::configuration_generation`] tells them apart.
+//!
+//! CPU-heavy work (template rendering, crypto) can be moved off the runtime
+//! with [`Context::run_blocking`], which runs it on a bounded share of the
+//! blocking pool; see [`crate::blocking`].
+//!
+//! The context names the operation, its resource type and a request id, which
+//! [`provider_info!`](crate::provider_info) and the other provider logging
+//! macros add to every event; see [`crate::logging`].
+//!
+//! Providers raise advisory events outside of a request (quota warnings,
+//! deprecation notices) with the [`EventSender`] from [`Context::events`];
+//! see [`crate::events`].
+//!
+//! The context is task-local: work spawned with `tokio::spawn` does not inherit
+//! it, so capture what you need (or use [`Context::scope`]) before spawning.
+
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+
+use tokio::sync::watch;
+use tracing::{debug, warn};
+
+use crate::blocking::BlockingBudget;
+use crate::error::ProviderError;
+use crate::events::EventSender;
+use crate::schema::ProviderSchema;
+use crate::store::LocalStore;
+
+tokio::task_local! {
+    static CURRENT: Context;
+}
+
+type TeardownFn = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+/// A teardown hook, run at most once. If [`ProviderData::teardown`] was never
+/// called, it is spawned when the last clone of the data is dropped.
+struct Teardown(Mutex<Option<TeardownFn>>);
+
+impl Drop for Teardown {
+    fn drop(&mut self) {
+        if let Some(teardown) = self.0.get_mut().unwrap().take() {
+            match tokio::runtime::Handle::try_current() {
+                Ok(runtime) => {
+                    runtime.spawn(teardown());
+                },
+                Err(_) => warn!("Provider data dropped outside a runtime, skipping teardown"),
+            }
+        }
+    }
+}
+
+/// Type-erased data produced by `configure` and shared with later operations.
+#[derive(Clone)]
+pub struct ProviderData {
+    data: Arc<dyn Any + Send + Sync>,
+    teardown: Option<Arc<Teardown>>,
+}
+
+impl ProviderData {
+    /// Wrap a value as provider data.
+    pub fn new<T: Send + Sync + 'static>(data: T) -> Self {
+        Self {
+            data: Arc::new(data),
+            teardown: None,
+        }
+    }
+
+    /// Get the data as `T`, if it has that type.
+    pub fn downcast<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        Arc::clone(&self.data).downcast::<T>().ok()
+    }
+
+    /// Register a hook that cleans up the data, replacing any earlier one.
+    ///
+    /// The hook is ignored, with a warning, if the data is not a `T`.
+    pub fn with_teardown<T, F, Fut>(mut self, teardown: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(Arc<T>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let Some(data) = self.downcast::<T>() else {
+            warn!(
+                "Provider data is not a {}, ignoring its teardown hook",
+                std::any::type_name::<T>()
+            );
+            return self;
+        };
+        let teardown: TeardownFn = Box::new(move || Box::pin(teardown(data)));
+        self.teardown = Some(Arc::new(Teardown(Mutex::new(Some(teardown)))));
+        self
+    }
+
+    /// Run the teardown hook, if it hasn't run yet.
+    pub async fn teardown(&self) {
+        let teardown = self
+            .teardown
+            .as_ref()
+            .and_then(|teardown| teardown.0.lock().unwrap().take());
+        if let Some(teardown) = teardown {
+            teardown().await;
+        }
+    }
+}
+
+impl fmt::Debug for ProviderData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProviderData(..)")
+    }
+}
+
+/// Raises a cancellation signal observed through [`Cancellation`] handles.
+#[derive(Debug, Clone)]
+pub struct CancellationSource {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl CancellationSource {
+    /// Create a source that has not been cancelled.
+    pub fn new() -> Self {
+        Self {
+            sender: Arc::new(watch::Sender::new(false)),
+        }
+    }
+
+    /// Raise the signal. Returns `false` if it was already raised.
+    pub fn cancel(&self) -> bool {
+        self.sender
+            .send_if_modified(|cancelled| !std::mem::replace(cancelled, true))
+    }
+
+    /// Whether the signal has been raised.
+    pub fn is_cancelled(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// A handle that observes this source.
+    pub fn token(&self) -> Cancellation {
+        Cancellation {
+            receiver: self.sender.subscribe(),
+        }
+    }
+}
+
+impl Default for CancellationSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Observes a [`CancellationSource`].
+#[derive(Debug, Clone)]
+pub struct Cancellation {
+    receiver: watch::Receiver<bool>,
+}
+
+impl Cancellation {
+    /// Whether cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    /// Wait until cancellation is requested.
+    pub async fn cancelled(&self) {
+        let mut receiver = self.receiver.clone();
+        // An error means the source is gone and can no longer cancel
+        if receiver.wait_for(|cancelled| *cancelled).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Optional behaviors supported by the core that sent the current request.
+///
+/// Everything is unsupported by default, which is also what older cores that
+/// don't send capabilities get.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClientCapabilities {
+    /// Core accepts deferred actions.
+    pub deferral_allowed: bool,
+    /// Core shows warning diagnostics returned from create, update and delete.
+    pub warnings_on_apply: bool,
+    /// Core displays streamed progress updates.
+    pub streamed_progress: bool,
+}
+
+impl From<crate::generated::ClientCapabilities> for ClientCapabilities {
+    fn from(capabilities: crate::generated::ClientCapabilities) -> Self {
+        Self {
+            deferral_allowed: capabilities.deferral_allowed,
+            warnings_on_apply: capabilities.warnings_on_apply,
+            streamed_progress: capabilities.streamed_progress,
+        }
+    }
+}
+
+/// The context of the provider operation currently being executed.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    provider_data: Option<ProviderData>,
+    configuration_generation: u64,
+    idempotency_token: Option<String>,
+    session_id: Option<String>,
+    operation: Option<String>,
+    resource_type: Option<String>,
+    request_id: Option<String>,
+    schema: Option<Arc<ProviderSchema>>,
+    cancellation: Option<Cancellation>,
+    client_capabilities: ClientCapabilities,
+    events: Option<EventSender>,
+    blocking_budget: Option<BlockingBudget>,
+    local_store: Option<LocalStore>,
+}
+
+impl Context {
+    /// Create an empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The context of the current operation.
+    ///
+    /// Outside of an SDK-driven operation this returns an empty context.
+    pub fn current() -> Self {
+        CURRENT.try_with(Clone::clone).unwrap_or_default()
+    }
+
+    /// Set the provider data.
+    pub fn with_provider_data(mut self, data: ProviderData) -> Self {
+        self.provider_data = Some(data);
+        self
+    }
+
+    /// Set the idempotency token.
+    pub fn with_idempotency_token(mut self, token: impl Into<String>) -> Self {
+        self.idempotency_token = Some(token.into());
+        self
+    }
+
+    /// The idempotency token of the current create or update operation.
+    ///
+    /// Retries of the same request carry the same token; see [`crate::idempotency`].
+    pub fn idempotency_token(&self) -> Option<&str> {
+        self.idempotency_token.as_deref()
+    }
+
+    /// Set the session id.
+    pub fn with_session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    /// The id of the plan or apply run the current operation belongs to.
+    ///
+    /// Core sends it in the `hemmer-session-id` request metadata; it is `None`
+    /// when core doesn't. See [`crate::cache::SessionCache`].
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Set the name of the operation (e.g. `read`).
+    pub fn with_operation(mut self, operation: impl Into<String>) -> Self {
+        self.operation = Some(operation.into());
+        self
+    }
+
+    /// The name of the current operation, as in its `grpc.<operation>` span.
+    pub fn operation(&self) -> Option<&str> {
+        self.operation.as_deref()
+    }
+
+    /// Set the resource or data source type the operation is for.
+    pub fn with_resource_type(mut self, resource_type: impl Into<String>) -> Self {
+        self.resource_type = Some(resource_type.into());
+        self
+    }
+
+    /// The resource or data source type of the current operation; `None` for
+    /// provider-level operations like `configure`.
+    pub fn resource_type(&self) -> Option<&str> {
+        self.resource_type.as_deref()
+    }
+
+    /// Set the request id.
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    /// The id the SDK assigned to the current operation, also recorded as the
+    /// `request_id` field of its span. Logged by [`provider_info!`](crate::provider_info)
+    /// and the other provider logging macros.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Set the provider schema.
+    pub fn with_schema(mut self, schema: Arc<ProviderSchema>) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// The provider schema the server has loaded, so operations can use it
+    /// without building it again; `None` before it is first loaded.
+    pub fn schema(&self) -> Option<Arc<ProviderSchema>> {
+        self.schema.clone()
+    }
+
+    /// Set the cancellation signal.
+    pub fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Whether the operation has been asked to stop (e.g. the server is shutting down).
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(Cancellation::is_cancelled)
+    }
+
+    /// Wait until the operation is asked to stop.
+    ///
+    /// Never completes if the context has no cancellation signal.
+    pub async fn cancelled(&self) {
+        match &self.cancellation {
+            Some(cancellation) => cancellation.cancelled().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Set the capabilities of the calling core.
+    pub fn with_client_capabilities(mut self, capabilities: ClientCapabilities) -> Self {
+        self.client_capabilities = capabilities;
+        self
+    }
+
+    /// The capabilities of the core that sent the current request.
+    pub fn client_capabilities(&self) -> ClientCapabilities {
+        self.client_capabilities
+    }
+
+    /// Set the sender for provider-initiated events.
+    pub fn with_events(mut self, events: EventSender) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// The sender for advisory events streamed to core (see [`crate::events`]).
+    ///
+    /// Clone it into background tasks to raise events outside of a request.
+    /// Without a server, events go to a sender nobody reads.
+    pub fn events(&self) -> EventSender {
+        self.events.clone().unwrap_or_default()
+    }
+
+    /// Set the budget for [`run_blocking`](Self::run_blocking).
+    pub fn with_blocking_budget(mut self, budget: BlockingBudget) -> Self {
+        self.blocking_budget = Some(budget);
+        self
+    }
+
+    /// Set the provider-local store.
+    pub fn with_local_store(mut self, store: LocalStore) -> Self {
+        self.local_store = Some(store);
+        self
+    }
+
+    /// The provider-process key/value store (see [`crate::store`]), cleared
+    /// when the provider stops.
+    ///
+    /// Outside of a server, a process-wide in-memory store is used.
+    pub fn local_store(&self) -> LocalStore {
+        self.local_store
+            .clone()
+            .unwrap_or_else(|| LocalStore::shared().clone())
+    }
+
+    /// Run the synchronous, CPU-heavy `f` on the blocking pool with this
+    /// context in scope, so it doesn't stall the runtime serving other
+    /// requests.
+    ///
+    /// Tasks wait for a slot in the server's [`BlockingBudget`] (or a
+    /// process-wide one outside of a server). Fails with
+    /// [`ProviderError::Unavailable`] if the operation is cancelled while
+    /// waiting, or [`ProviderError::Sdk`] if `f` panics.
+    pub async fn run_blocking<R, F>(&self, f: F) -> Result<R, ProviderError>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        let budget = self
+            .blocking_budget
+            .as_ref()
+            .unwrap_or_else(|| BlockingBudget::shared());
+        budget.run_in(self.clone(), f).await
+    }
+
+    /// Whether `configure` has stored provider data.
+    pub fn is_configured(&self) -> bool {
+        self.provider_data.is_some()
+    }
+
+    /// How many times `configure` had stored provider data when this operation
+    /// started: 0 before the first configure, then 1, 2, ... on each reconfigure.
+    ///
+    /// Providers caching state derived from the provider data (e.g. a signed
+    /// token) can key it by generation to notice that it is stale.
+    pub fn configuration_generation(&self) -> u64 {
+        self.configuration_generation
+    }
+
+    /// The provider data stored by `configure`, as `T`.
+    ///
+    /// Returns [`ProviderError::FailedPrecondition`] if the provider has not been
+    /// configured, or [`ProviderError::Sdk`] if the stored data is not a `T`.
+    pub fn provider_data<T: Send + Sync + 'static>(&self) -> Result<Arc<T>, ProviderError> {
+        let data = self.provider_data.as_ref().ok_or_else(|| {
+            ProviderError::FailedPrecondition("Provider not configured".to_string())
+        })?;
+        data.downcast::<T>().ok_or_else(|| {
+            ProviderError::Sdk(format!(
+                "Provider data is not a {}",
+                std::any::type_name::<T>()
+            ))
+        })
+    }
+
+    /// The provider data stored by `configure`, as `T`.
+    ///
+    /// Shorthand for [`provider_data`](Self::provider_data).
+    pub fn data<T: Send + Sync + 'static>(&self) -> Result<Arc<T>, ProviderError> {
+        self.provider_data()
+    }
+
+    /// Run `f` with this context as the current context.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        CURRENT.scope(self, f).await
+    }
+
+    /// Run the synchronous `f` with this context as the current context.
+    ///
+    /// Use this to carry the context onto a thread that isn't running the
+    /// operation's task, e.g. in `spawn_blocking` (see [`crate::blocking`]).
+    pub fn sync_scope<R>(self, f: impl FnOnce() -> R) -> R {
+        CURRENT.sync_scope(self, f)
+    }
+}
+
+/// Holds the provider data from the last successful `configure`.
+///
+/// Cloning shares the same store, so one store can back several front ends
+/// (e.g. the gRPC server and the HTTP gateway).
+#[derive(Debug, Clone, Default)]
+pub struct ProviderDataStore {
+    data: Arc<RwLock<Configured>>,
+}
+
+#[derive(Debug, Default)]
+struct Configured {
+    data: Option<ProviderData>,
+    generation: u64,
+}
+
+impl ProviderDataStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the stored provider data, returning its new generation.
+    pub fn set(&self, data: ProviderData) -> u64 {
+        let mut configured = self.data.write().unwrap();
+        configured.data = Some(data);
+        configured.generation += 1;
+        configured.generation
+    }
+
+    /// The stored provider data, if any.
+    pub fn get(&self) -> Option<ProviderData> {
+        self.data.read().unwrap().data.clone()
+    }
+
+    /// Remove the stored provider data and run its teardown hook.
+    ///
+    /// Operations still holding the data keep it; later operations see an
+    /// unconfigured provider.
+    pub async fn teardown(&self) {
+        let data = self.data.write().unwrap().data.take();
+        if let Some(data) = data {
+            debug!("Tearing down provider data");
+            data.teardown().await;
+        }
+    }
+
+    /// How many times provider data has been stored.
+    pub fn generation(&self) -> u64 {
+        self.data.read().unwrap().generation
+    }
+
+    /// A context carrying the stored provider data.
+    pub fn context(&self) -> Context {
+        let configured = self.data.read().unwrap();
+        Context {
+            provider_data: configured.data.clone(),
+            configuration_generation: configured.generation,
+            ..Context::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Client {
+        endpoint: String,
+    }
+
+    #[tokio::test]
+    async fn test_context_provider_data() {
+        assert!(!Context::current().is_configured());
+
+        let store = ProviderDataStore::new();
+        let err = store
+            .context()
+            .scope(async { Context::current().provider_data::<Client>().map(|_| ()) })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::FailedPrecondition(_)));
+
+        store.set(ProviderData::new(Client {
+            endpoint: "https://api.example.com".to_string(),
+        }));
+        let endpoint = store
+            .context()
+            .scope(async {
+                let client = Context::current().provider_data::<Client>().unwrap();
+                client.endpoint.clone()
+            })
+            .await;
+        assert_eq!(endpoint, "https://api.example.com");
+
+        let err = store
+            .context()
+            .scope(async { Context::current().provider_data::<String>().map(|_| ()) })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::Sdk(_)));
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_swaps_provider_data() {
+        let store = ProviderDataStore::new();
+        assert_eq!(store.context().configuration_generation(), 0);
+        assert_eq!(
+            store.set(ProviderData::new(Client {
+                endpoint: "https://old.example.com".to_string(),
+            })),
+            1
+        );
+
+        // An operation in flight keeps the data it started with
+        let in_flight = store.context();
+        assert_eq!(
+            store.set(ProviderData::new(Client {
+                endpoint: "https://new.example.com".to_string(),
+            })),
+            2
+        );
+        let old = in_flight.provider_data::<Client>().unwrap();
+        assert_eq!(old.endpoint, "https://old.example.com");
+        assert_eq!(in_flight.configuration_generation(), 1);
+
+        let current = store.context();
+        assert_eq!(
+            current.provider_data::<Client>().unwrap().endpoint,
+            "https://new.example.com"
+        );
+        assert_eq!(current.configuration_generation(), 2);
+        assert_eq!(store.generation(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_provider_data_teardown() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let closed = Arc::new(AtomicUsize::new(0));
+        let data = |endpoint: &str| {
+            let closed = Arc::clone(&closed);
+            ProviderData::new(Client {
+                endpoint: endpoint.to_string(),
+            })
+            .with_teardown(move |client: Arc<Client>| async move {
+                assert!(!client.endpoint.is_empty());
+                closed.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+
+        let store = ProviderDataStore::new();
+        store.set(data("https://old.example.com"));
+        let in_flight = store.context();
+        store.set(data("https://new.example.com"));
+
+        // The replaced data is torn down once the last operation using it is done
+        assert_eq!(
+            in_flight.data::<Client>().unwrap().endpoint,
+            "https://old.example.com"
+        );
+        drop(in_flight);
+        tokio::task::yield_now().await;
+        assert_eq!(closed.load(Ordering::SeqCst), 1);
+
+        store.teardown().await;
+        assert_eq!(closed.load(Ordering::SeqCst), 2);
+        assert!(!store.context().is_configured());
+
+        // A hook for the wrong type is ignored
+        let data = ProviderData::new(1u32).with_teardown(|_: Arc<String>| async {});
+        assert!(data.teardown.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_context_cancellation() {
+        assert!(!Context::current().is_cancelled());
+
+        let source = CancellationSource::new();
+        let context = Context::new().with_cancellation(source.token());
+        let waiter = tokio::spawn(context.clone().scope(async {
+            Context::current().cancelled().await;
+            Context::current().is_cancelled()
+        }));
+
+        assert!(source.cancel());
+        assert!(!source.cancel());
+        assert!(waiter.await.unwrap());
+        assert!(context.is_cancelled());
+    }
+}
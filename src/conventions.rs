@@ -0,0 +1,343 @@
+//! Well-known attributes shared across providers.
+//!
+//! Most resources have some of the same attributes: a computed `id`, a
+//! user-chosen `name` (or a `name_prefix` to generate one from), and `tags`
+//! merged with provider-level `default_tags`. The fragments here define them
+//! once, with the helpers that reconcile them, so every provider built on the
+//! SDK behaves the same way:
+//!
+//! - [`id_fragment`]: a computed `id`, kept across updates; read it with
+//!   [`state_id`]
+//! - [`name_fragment`]: `name` and `name_prefix`, which conflict
+//!   ([`validate_name`]); a resource created without a `name` gets one
+//!   generated from the prefix ([`resolve_name`])
+//! - [`tags_fragment`]: `tags`, and the computed `tags_all` holding `tags`
+//!   merged over the provider's [`DefaultTags`] (from
+//!   [`default_tags_fragment`] in the provider config)
+//!
+//! # Example
+//!
+//! ```
+//! use hemmer_provider_sdk::conventions::{
+//!     id_fragment, name_fragment, resolve_name, tags_fragment, DefaultTags,
+//! };
+//! use hemmer_provider_sdk::plan::plan_from_schema;
+//! use hemmer_provider_sdk::schema::Schema;
+//! use serde_json::json;
+//!
+//! let schema = Schema::v0()
+//!     .with_fragment(&id_fragment())
+//!     .with_fragment(&name_fragment())
+//!     .with_fragment(&tags_fragment());
+//! let default_tags = DefaultTags::from_config(&json!({"default_tags": {"team": "infra"}}));
+//!
+//! let config = json!({"name_prefix": "web-", "tags": {"env": "prod"}});
+//! let plan = default_tags.plan(plan_from_schema(&schema, None, config.clone(), &config).unwrap(), None);
+//! assert_eq!(plan.planned_state["tags_all"], json!({"env": "prod", "team": "infra"}));
+//!
+//! // In create
+//! let mut state = plan.planned_state;
+//! assert!(resolve_name(&mut state).starts_with("web-"));
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{Map, Value};
+
+use crate::error::ProviderError;
+use crate::schema::{
+    Attribute, AttributeFlags, AttributeType, Diagnostic, PlanModifier, SchemaFragment,
+};
+use crate::types::{AttributeChange, PlanResult};
+
+/// The prefix of generated names when neither `name` nor `name_prefix` is set.
+pub const DEFAULT_NAME_PREFIX: &str = "hemmer-";
+
+/// A computed `id` attribute, kept across updates.
+pub fn id_fragment() -> SchemaFragment {
+    SchemaFragment::new("id").with_attribute(
+        "id",
+        Attribute::computed_string().with_description("The provider-assigned identifier."),
+    )
+}
+
+/// Read the `id` of a state.
+///
+/// Fails with [`ProviderError::Validation`] if the state has no string `id`.
+pub fn state_id(state: &Value) -> Result<&str, ProviderError> {
+    state["id"]
+        .as_str()
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| ProviderError::Validation("State has no 'id'".to_string()))
+}
+
+/// `name` and `name_prefix` attributes. Both force replacement; `name` is
+/// computed when only `name_prefix` (or neither) is set.
+pub fn name_fragment() -> SchemaFragment {
+    SchemaFragment::new("name")
+        .with_attribute(
+            "name",
+            Attribute::optional_computed_string()
+                .with_force_new()
+                .with_description("The name. Generated from `name_prefix` if not set."),
+        )
+        .with_attribute(
+            "name_prefix",
+            Attribute::optional_string()
+                .with_force_new()
+                .with_description("Generate a unique name starting with this prefix."),
+        )
+}
+
+/// Check that `name` and `name_prefix` are not both set.
+pub fn validate_name(config: &Value) -> Vec<Diagnostic> {
+    let is_set = |attribute: &str| !config[attribute].is_null();
+    if is_set("name") && is_set("name_prefix") {
+        vec![Diagnostic::conflicting_attributes("name", "name_prefix")]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Make sure a state being created has a `name`, generating a
+/// [`unique_name`] from `name_prefix` (or [`DEFAULT_NAME_PREFIX`]) if it is
+/// unset. Returns the name.
+pub fn resolve_name(state: &mut Value) -> String {
+    if let Some(name) = state["name"].as_str() {
+        return name.to_string();
+    }
+    let prefix = state["name_prefix"]
+        .as_str()
+        .unwrap_or(DEFAULT_NAME_PREFIX)
+        .to_string();
+    let name = unique_name(&prefix);
+    if let Some(obj) = state.as_object_mut() {
+        obj.insert("name".to_string(), Value::String(name.clone()));
+    }
+    name
+}
+
+/// A name starting with `prefix` that is unique to this call: the current
+/// time in nanoseconds and a per-process counter, in hex.
+pub fn unique_name(prefix: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!(
+        "{}{:x}{:04x}",
+        prefix,
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed) & 0xffff
+    )
+}
+
+fn string_map(flags: AttributeFlags) -> Attribute {
+    Attribute::new(AttributeType::map(AttributeType::String), flags)
+}
+
+/// A `tags` map, and the computed `tags_all`: `tags` merged over the
+/// provider's [`DefaultTags`].
+pub fn tags_fragment() -> SchemaFragment {
+    SchemaFragment::new("tags")
+        .with_attribute(
+            "tags",
+            string_map(AttributeFlags::optional()).with_description("Tags for the resource."),
+        )
+        .with_attribute(
+            "tags_all",
+            string_map(AttributeFlags::computed())
+                .with_plan_modifier(PlanModifier::UnknownOnUpdate)
+                .with_description("`tags` merged over the provider's `default_tags`."),
+        )
+}
+
+/// A `default_tags` map for the provider config, applied to every resource
+/// with [`tags_fragment`].
+pub fn default_tags_fragment() -> SchemaFragment {
+    SchemaFragment::new("default_tags").with_attribute(
+        "default_tags",
+        string_map(AttributeFlags::optional())
+            .with_description("Tags applied to every resource that supports tags."),
+    )
+}
+
+/// Provider-level tags merged into each resource's `tags_all`.
+///
+/// Read it from the provider config in `configure` and keep it in the
+/// provider data. Resource `tags` win over default tags with the same key.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DefaultTags {
+    tags: Map<String, Value>,
+}
+
+impl DefaultTags {
+    /// No default tags.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the `default_tags` attribute of a provider config.
+    pub fn from_config(config: &Value) -> Self {
+        Self {
+            tags: config["default_tags"]
+                .as_object()
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Add a default tag.
+    pub fn with_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.insert(key.into(), Value::String(value.into()));
+        self
+    }
+
+    /// `tags` (an object, or null) merged over the default tags.
+    pub fn merge(&self, tags: &Value) -> Value {
+        let mut merged = self.tags.clone();
+        if let Some(tags) = tags.as_object() {
+            merged.extend(tags.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        Value::Object(merged)
+    }
+
+    /// Set `tags_all` in a state from its `tags`.
+    pub fn apply(&self, state: &mut Value) {
+        let merged = self.merge(&state["tags"]);
+        if let Some(obj) = state.as_object_mut() {
+            obj.insert("tags_all".to_string(), merged);
+        }
+    }
+
+    /// Plan `tags_all`: set it in the planned state and replace any planned
+    /// change to it with the change from the prior `tags_all`.
+    pub fn plan(&self, mut result: PlanResult, prior_state: Option<&Value>) -> PlanResult {
+        if result.planned_state.is_null() {
+            return result;
+        }
+        self.apply(&mut result.planned_state);
+        result
+            .changes
+            .retain(|c| c.path != "tags_all" && !c.path.starts_with("tags_all."));
+        let before = prior_state
+            .map(|prior| &prior["tags_all"])
+            .filter(|v| !v.is_null());
+        let after = &result.planned_state["tags_all"];
+        if before != Some(after) {
+            result.changes.push(AttributeChange::new(
+                "tags_all",
+                before.cloned(),
+                Some(after.clone()),
+            ));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::plan_from_schema;
+    use crate::schema::Schema;
+    use serde_json::json;
+
+    fn schema() -> Schema {
+        Schema::v0()
+            .with_fragment(&id_fragment())
+            .with_fragment(&name_fragment())
+            .with_fragment(&tags_fragment())
+    }
+
+    #[test]
+    fn test_name_conventions() {
+        assert!(validate_name(&json!({"name": "web"})).is_empty());
+        assert!(validate_name(&json!({"name": null, "name_prefix": "web-"})).is_empty());
+        let diagnostics = validate_name(&json!({"name": "web", "name_prefix": "web-"}));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].attribute.as_deref(), Some("name"));
+
+        let mut named = json!({"name": "web", "name_prefix": null});
+        assert_eq!(resolve_name(&mut named), "web");
+
+        let mut prefixed = json!({"name": null, "name_prefix": "web-"});
+        let name = resolve_name(&mut prefixed);
+        assert!(name.starts_with("web-"));
+        assert_eq!(prefixed["name"], json!(name));
+
+        let mut unnamed = json!({"name": null});
+        assert!(resolve_name(&mut unnamed).starts_with(DEFAULT_NAME_PREFIX));
+        assert_ne!(unique_name("a-"), unique_name("a-"));
+
+        // A generated name is kept on update, and a new prefix replaces
+        let prior = json!({"id": "i-1", "name": name, "name_prefix": "web-", "tags_all": {}});
+        let config = json!({"name_prefix": "web-"});
+        let plan = plan_from_schema(&schema(), Some(&prior), config.clone(), &config).unwrap();
+        assert!(plan.changes.is_empty(), "{:?}", plan.changes);
+        let config = json!({"name_prefix": "api-"});
+        let plan = plan_from_schema(&schema(), Some(&prior), config.clone(), &config).unwrap();
+        assert!(plan.requires_replace);
+    }
+
+    #[test]
+    fn test_default_tags() {
+        let defaults =
+            DefaultTags::from_config(&json!({"default_tags": {"team": "infra", "env": "dev"}}));
+        assert_eq!(
+            defaults,
+            DefaultTags::new()
+                .with_tag("team", "infra")
+                .with_tag("env", "dev")
+        );
+        assert_eq!(
+            defaults.merge(&json!({"env": "prod"})),
+            json!({"team": "infra", "env": "prod"})
+        );
+        assert_eq!(
+            defaults.merge(&Value::Null),
+            json!({"team": "infra", "env": "dev"})
+        );
+
+        let config = json!({"tags": {"env": "prod"}});
+        let plan = defaults.plan(
+            plan_from_schema(&schema(), None, config.clone(), &config).unwrap(),
+            None,
+        );
+        assert_eq!(
+            plan.planned_state["tags_all"],
+            json!({"team": "infra", "env": "prod"})
+        );
+        assert_eq!(
+            plan.changes
+                .iter()
+                .filter(|c| c.path.starts_with("tags_all"))
+                .count(),
+            1
+        );
+
+        let prior = json!({"id": "i-1", "name": "web", "tags": {"env": "prod"}, "tags_all": {"team": "infra", "env": "prod"}});
+        let unchanged = defaults.plan(
+            plan_from_schema(&schema(), Some(&prior), config.clone(), &config).unwrap(),
+            Some(&prior),
+        );
+        assert!(unchanged.changes.is_empty(), "{:?}", unchanged.changes);
+
+        // A new default tag changes tags_all, though the resource's tags did not change
+        let plan = defaults.clone().with_tag("owner", "ops").plan(
+            plan_from_schema(&schema(), Some(&prior), config.clone(), &config).unwrap(),
+            Some(&prior),
+        );
+        assert_eq!(plan.changes.len(), 1);
+        assert_eq!(plan.changes[0].path, "tags_all");
+        assert_eq!(plan.planned_state["id"], json!("i-1"));
+    }
+
+    #[test]
+    fn test_state_id() {
+        assert_eq!(state_id(&json!({"id": "i-1"})).unwrap(), "i-1");
+        assert!(state_id(&json!({"id": null})).is_err());
+        assert!(state_id(&json!({"id": ""})).is_err());
+    }
+}
@@ -0,0 +1,724 @@
+//! Development CLI for providers.
+//!
+//! This module gives every provider binary a small built-in command line
+//! interface for debugging without spinning up Hemmer core. Commands call the
+//! [`ProviderService`] trait methods directly and pretty-print the results.
+//!
+//! Enable it with the `devtool` cargo feature.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use hemmer_provider_sdk::devtool;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     // With no arguments, this serves the provider as usual.
+//!     devtool::run(MyProvider::new()).await
+//! }
+//! ```
+//!
+//! # Commands
+//!
+//! ```text
+//! my-provider                                   # serve the provider (handshake + gRPC)
+//! my-provider schema                            # print the full provider schema
+//! my-provider metadata                          # print resource and data source names
+//! my-provider manifest --name my --version 1.0  # print the provider manifest
+//! my-provider validate config.json              # validate provider configuration
+//! my-provider validate --resource bucket c.json # validate a resource configuration
+//! my-provider plan --resource bucket --config c.json [--prior state.json]
+//! my-provider example bucket                    # print an example resource configuration
+//! my-provider doctor                            # lint the schema and run self-checks
+//! my-provider names                             # list every name as JSON, for editors
+//! my-provider names resources                   # list resource types, one per line
+//! my-provider names --resource bucket           # list a resource's attribute paths
+//! ```
+//!
+//! `names` is meant for shell completion scripts and editor plugins. As JSON,
+//! it lists the resource and data source types with every attribute path
+//! (nested block attributes as `block.attribute`), its type, whether it is
+//! required or computed and its description, plus the function names.
+//!
+//! When `plan` is run without `--config`, a minimal configuration generated by
+//! [`example_value`] is used.
+//!
+//! Every command that takes a JSON document accepts either a file path or an
+//! inline JSON object (e.g. `--config '{"name": "test"}'`). Commands that talk
+//! to the provider accept `--provider-config <json>` to call `configure` first.
+
+use std::io::Write;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::config_example::{data_source_example, resource_example};
+use crate::context::ProviderDataStore;
+use crate::schema::{example_value, Block, Diagnostic, DiagnosticSeverity, ProviderSchema};
+use crate::server::{serve, ProviderService};
+
+/// Run the development CLI using the process arguments.
+///
+/// If no subcommand is given (or the subcommand is `serve`), the provider is
+/// served over gRPC exactly like [`serve`].
+pub async fn run<P: ProviderService>(provider: P) -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args[0] == "serve" {
+        return serve(provider).await;
+    }
+
+    let mut stdout = std::io::stdout();
+    let success = run_with_args(&provider, &args, &mut stdout).await?;
+    if !success {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Run a single devtool command against a provider, writing output to `out`.
+///
+/// `args` excludes the program name. Returns `Ok(false)` when the command ran
+/// but reported error diagnostics, so callers can set a non-zero exit status.
+pub async fn run_with_args<P: ProviderService, W: Write>(
+    provider: &P,
+    args: &[String],
+    out: &mut W,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let parsed = ParsedArgs::parse(args)?;
+
+    match parsed.command.as_str() {
+        "schema" => match provider.schema_async().await {
+            Ok(schema) => {
+                writeln!(out, "{}", serde_json::to_string_pretty(&schema)?)?;
+                Ok(true)
+            },
+            Err(diagnostics) => {
+                write_diagnostics(out, &diagnostics)?;
+                Ok(false)
+            },
+        },
+        "metadata" => match provider
+            .schema_async()
+            .await
+            .and_then(|_| provider.try_metadata())
+        {
+            Ok(metadata) => {
+                writeln!(out, "{}", serde_json::to_string_pretty(&metadata)?)?;
+                Ok(true)
+            },
+            Err(diagnostics) => {
+                write_diagnostics(out, &diagnostics)?;
+                Ok(false)
+            },
+        },
+        "manifest" => match provider.schema_async().await.and_then(|schema| {
+            let metadata = provider.try_metadata()?;
+            Ok(schema
+                .to_manifest()
+                .with_capabilities(metadata.capabilities))
+        }) {
+            Ok(manifest) => {
+                let name = match parsed.flag("name") {
+                    Some(name) => name.to_string(),
+                    None => executable_name(),
+                };
+                let mut manifest =
+                    manifest.with_provider(name, parsed.flag("version").unwrap_or(""));
+                if let Some(distribution) = provider.distribution() {
+                    let diagnostics = distribution.validate();
+                    if has_errors(&diagnostics) {
+                        write_diagnostics(out, &diagnostics)?;
+                        return Ok(false);
+                    }
+                    manifest = manifest.with_distribution(distribution);
+                }
+                writeln!(out, "{}", serde_json::to_string_pretty(&manifest)?)?;
+                Ok(true)
+            },
+            Err(diagnostics) => {
+                write_diagnostics(out, &diagnostics)?;
+                Ok(false)
+            },
+        },
+        "validate" => {
+            let config = match parsed.positional.first() {
+                Some(arg) => load_json(arg)?,
+                None => parsed.required_json("config")?,
+            };
+            let diagnostics = match parsed.flag("resource") {
+                Some(resource_type) => {
+                    crate::server::validate_resource_config_with_schema(
+                        provider,
+                        provider.schema_async().await.map(Arc::new),
+                        resource_type,
+                        config,
+                    )
+                    .await?
+                },
+                None => match parsed.flag("data-source") {
+                    Some(data_source_type) => {
+                        provider
+                            .validate_data_source_config(data_source_type, config)
+                            .await?
+                    },
+                    None => {
+                        crate::server::validate_provider_config_with_schema(
+                            provider,
+                            provider.schema_async().await.map(Arc::new),
+                            config,
+                        )
+                        .await?
+                    },
+                },
+            };
+            write_diagnostics(out, &diagnostics)?;
+            if diagnostics.is_empty() {
+                writeln!(out, "Configuration is valid")?;
+            }
+            Ok(!has_errors(&diagnostics))
+        },
+        "plan" => {
+            let provider_data = ProviderDataStore::new();
+            if !configure_if_requested(provider, &parsed, &provider_data, out).await? {
+                return Ok(false);
+            }
+            let resource_type = parsed.required_flag("resource")?;
+            let config = match parsed.optional_json("config")? {
+                Some(config) => config,
+                None => {
+                    let schema = provider
+                        .schema_async()
+                        .await
+                        .map_err(crate::server::schema_unavailable)?;
+                    let resource_schema = schema
+                        .resources
+                        .get(resource_type)
+                        .ok_or_else(|| format!("Unknown resource type '{}'", resource_type))?;
+                    example_value(resource_schema)
+                },
+            };
+            let prior_state = parsed.optional_json("prior")?;
+            let result = provider_data
+                .context()
+                .scope(provider.plan(resource_type, prior_state, config.clone(), config))
+                .await?;
+            writeln!(out, "{}", serde_json::to_string_pretty(&result)?)?;
+            Ok(true)
+        },
+        "example" => {
+            let schema = provider
+                .schema_async()
+                .await
+                .map_err(crate::server::schema_unavailable)?;
+            let example = match parsed.flag("data-source") {
+                Some(data_source_type) => {
+                    let data_source_schema =
+                        schema.data_sources.get(data_source_type).ok_or_else(|| {
+                            format!("Unknown data source type '{}'", data_source_type)
+                        })?;
+                    data_source_example(data_source_type, data_source_schema)
+                },
+                None => {
+                    let resource_type = match parsed.positional.first() {
+                        Some(resource_type) => resource_type.as_str(),
+                        None => parsed.required_flag("resource")?,
+                    };
+                    let resource_schema = schema
+                        .resources
+                        .get(resource_type)
+                        .ok_or_else(|| format!("Unknown resource type '{}'", resource_type))?;
+                    resource_example(resource_type, resource_schema)
+                },
+            };
+            write!(out, "{}", example)?;
+            Ok(true)
+        },
+        "doctor" => {
+            let provider_data = ProviderDataStore::new();
+            if !configure_if_requested(provider, &parsed, &provider_data, out).await? {
+                return Ok(false);
+            }
+            let diagnostics = provider_data
+                .context()
+                .scope(crate::lint::self_check(provider))
+                .await;
+            write_diagnostics(out, &diagnostics)?;
+            if diagnostics.is_empty() {
+                writeln!(out, "No problems found")?;
+            }
+            Ok(!has_errors(&diagnostics))
+        },
+        "names" => {
+            let schema = provider
+                .schema_async()
+                .await
+                .map_err(crate::server::schema_unavailable)?;
+            let attributes = |schemas: &std::collections::BTreeMap<_, crate::schema::Schema>,
+                              kind: &str,
+                              name: &str| {
+                schemas
+                    .get(name)
+                    .map(|schema| attribute_names(&schema.block))
+                    .ok_or_else(|| format!("Unknown {} '{}'", kind, name))
+            };
+            let lines: Vec<String> = if let Some(resource_type) = parsed.flag("resource") {
+                attributes(&schema.resources, "resource type", resource_type)?
+                    .into_iter()
+                    .map(|(path, _)| path)
+                    .collect()
+            } else if let Some(data_source_type) = parsed.flag("data-source") {
+                attributes(&schema.data_sources, "data source type", data_source_type)?
+                    .into_iter()
+                    .map(|(path, _)| path)
+                    .collect()
+            } else {
+                match parsed.positional.first().map(String::as_str) {
+                    Some("resources") => schema.resources.keys().cloned().collect(),
+                    Some("data-sources") => schema.data_sources.keys().cloned().collect(),
+                    Some("functions") => schema.functions.keys().cloned().collect(),
+                    Some(other) => return Err(format!("Unknown name kind '{}'", other).into()),
+                    None => {
+                        writeln!(
+                            out,
+                            "{}",
+                            serde_json::to_string_pretty(&schema_names(&schema))?
+                        )?;
+                        return Ok(true);
+                    },
+                }
+            };
+            for line in lines {
+                writeln!(out, "{}", line)?;
+            }
+            Ok(true)
+        },
+        "help" | "--help" | "-h" => {
+            write!(out, "{}", USAGE)?;
+            Ok(true)
+        },
+        other => Err(format!("Unknown command '{}'\n\n{}", other, USAGE).into()),
+    }
+}
+
+const USAGE: &str = "\
+Usage: <provider> [COMMAND] [OPTIONS]
+
+Commands:
+  serve                         Serve the provider over gRPC (default)
+  schema                        Print the provider schema as JSON
+  metadata                      Print provider metadata as JSON
+  manifest                      Print the provider manifest as JSON
+      --name <name>             Provider name (default: the executable name)
+      --version <version>       Provider version
+  validate <config>             Validate provider configuration
+      --resource <type>         Validate a resource configuration instead
+      --data-source <type>      Validate a data source configuration instead
+  plan                          Plan a resource change
+      --resource <type>         Resource type to plan
+      --config <json>           Proposed configuration (default: schema example)
+      --prior <json>            Prior state (omit to plan a create)
+  example <type>                Print an example resource configuration
+      --data-source <type>      Print a data source example instead
+  doctor                        Lint the schema and run the provider's self-checks
+  names [KIND]                  List names for completion: all as JSON, or one
+                                KIND (resources, data-sources, functions) per line
+      --resource <type>         List a resource's attribute paths instead
+      --data-source <type>      List a data source's attribute paths instead
+  help                          Print this message
+
+Options:
+  --provider-config <json>      Configure the provider before running the command
+
+JSON arguments accept either a file path or an inline JSON document.
+";
+
+/// Every resource, data source and function name in `schema`, with the
+/// attributes of each resource and data source, for the `names` command.
+fn schema_names(schema: &ProviderSchema) -> Value {
+    let types = |schemas: &std::collections::BTreeMap<String, crate::schema::Schema>| {
+        schemas
+            .iter()
+            .map(|(name, schema)| {
+                let attributes = attribute_names(&schema.block)
+                    .into_iter()
+                    .map(|(_, attribute)| attribute)
+                    .collect();
+                (name.clone(), Value::Array(attributes))
+            })
+            .collect::<serde_json::Map<_, _>>()
+    };
+    serde_json::json!({
+        "resources": types(&schema.resources),
+        "data_sources": types(&schema.data_sources),
+        "functions": schema.functions.keys().collect::<Vec<_>>(),
+    })
+}
+
+/// The attribute paths of `block`, nested blocks and their attributes
+/// included, each with a JSON description of the attribute or block.
+fn attribute_names(block: &Block) -> Vec<(String, Value)> {
+    fn collect(block: &Block, prefix: &str, out: &mut Vec<(String, Value)>) {
+        for (name, attribute) in &block.attributes {
+            let path = format!("{}{}", prefix, name);
+            let entry = serde_json::json!({
+                "path": path,
+                "kind": "attribute",
+                "type": attribute.attr_type,
+                "required": attribute.flags.required,
+                "computed": attribute.flags.computed,
+                "description": attribute.description,
+            });
+            out.push((path, entry));
+        }
+        for (name, nested) in &block.blocks {
+            let path = format!("{}{}", prefix, name);
+            let entry = serde_json::json!({
+                "path": path,
+                "kind": "block",
+                "nesting": nested.nesting_mode,
+                "required": nested.min_items > 0,
+                "description": nested.block.description,
+            });
+            out.push((path.clone(), entry));
+            collect(&nested.block, &format!("{}.", path), out);
+        }
+    }
+
+    let mut out = Vec::new();
+    collect(block, "", &mut out);
+    out
+}
+
+/// The file name of the running executable, without extension.
+fn executable_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| path.file_stem().map(|s| s.to_string_lossy().into_owned()))
+        .unwrap_or_default()
+}
+
+/// Call `configure` when `--provider-config` was given.
+///
+/// Returns `Ok(false)` if configuration produced error diagnostics.
+async fn configure_if_requested<P: ProviderService, W: Write>(
+    provider: &P,
+    parsed: &ParsedArgs,
+    provider_data: &ProviderDataStore,
+    out: &mut W,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let Some(config) = parsed.optional_json("provider-config")? else {
+        return Ok(true);
+    };
+    let result = provider.configure(config).await?;
+    write_diagnostics(out, &result.diagnostics)?;
+    if result.has_errors() {
+        return Ok(false);
+    }
+    if let Some(data) = result.provider_data {
+        provider_data.set(data);
+    }
+    Ok(true)
+}
+
+fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|d| matches!(d.severity, DiagnosticSeverity::Error))
+}
+
+fn write_diagnostics<W: Write>(out: &mut W, diagnostics: &[Diagnostic]) -> std::io::Result<()> {
+    for diag in diagnostics {
+        writeln!(out, "{}", diag)?;
+    }
+    Ok(())
+}
+
+/// Load a JSON document from a file path or an inline JSON string.
+fn load_json(arg: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    let trimmed = arg.trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        return Ok(serde_json::from_str(arg)?);
+    }
+    let contents = std::fs::read_to_string(arg)
+        .map_err(|e| format!("Failed to read JSON file '{}': {}", arg, e))?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Minimal argument parser: a command, `--flag value` pairs, and positionals.
+struct ParsedArgs {
+    command: String,
+    flags: Vec<(String, String)>,
+    positional: Vec<String>,
+}
+
+impl ParsedArgs {
+    fn parse(args: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut iter = args.iter();
+        let command = iter.next().cloned().unwrap_or_else(|| "help".to_string());
+
+        let mut flags = Vec::new();
+        let mut positional = Vec::new();
+        while let Some(arg) = iter.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                if let Some((name, value)) = name.split_once('=') {
+                    flags.push((name.to_string(), value.to_string()));
+                } else {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| format!("Missing value for --{}", name))?;
+                    flags.push((name.to_string(), value.clone()));
+                }
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+
+        Ok(Self {
+            command,
+            flags,
+            positional,
+        })
+    }
+
+    fn flag(&self, name: &str) -> Option<&str> {
+        self.flags
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn required_flag(&self, name: &str) -> Result<&str, Box<dyn std::error::Error>> {
+        self.flag(name)
+            .ok_or_else(|| format!("Missing required option --{}", name).into())
+    }
+
+    fn optional_json(&self, name: &str) -> Result<Option<Value>, Box<dyn std::error::Error>> {
+        self.flag(name).map(load_json).transpose()
+    }
+
+    fn required_json(&self, name: &str) -> Result<Value, Box<dyn std::error::Error>> {
+        load_json(self.required_flag(name)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ProviderError;
+    use crate::manifest::ProviderManifest;
+    use crate::schema::{Attribute, ProviderSchema, Schema};
+    use crate::types::{ConfigureResult, PlanResult};
+
+    struct TestProvider;
+
+    #[async_trait::async_trait]
+    impl ProviderService for TestProvider {
+        fn schema(&self) -> ProviderSchema {
+            ProviderSchema::new().with_resource(
+                "test_resource",
+                Schema::v0().with_attribute("name", Attribute::required_string()),
+            )
+        }
+
+        async fn configure(&self, _config: Value) -> Result<ConfigureResult, ProviderError> {
+            Ok(ConfigureResult::new())
+        }
+
+        async fn validate_resource_config(
+            &self,
+            _resource_type: &str,
+            config: Value,
+        ) -> Result<Vec<Diagnostic>, ProviderError> {
+            Ok(crate::validation::validate(
+                &self.schema().resources["test_resource"],
+                &config,
+            ))
+        }
+
+        async fn plan(
+            &self,
+            _resource_type: &str,
+            prior_state: Option<Value>,
+            proposed_state: Value,
+            _config: Value,
+        ) -> Result<PlanResult, ProviderError> {
+            Ok(PlanResult::from_diff(prior_state.as_ref(), &proposed_state))
+        }
+
+        async fn create(
+            &self,
+            _resource_type: &str,
+            planned: Value,
+        ) -> Result<Value, ProviderError> {
+            Ok(planned)
+        }
+
+        async fn read(&self, _resource_type: &str, current: Value) -> Result<Value, ProviderError> {
+            Ok(current)
+        }
+
+        async fn update(
+            &self,
+            _resource_type: &str,
+            _prior_state: Value,
+            planned: Value,
+        ) -> Result<Value, ProviderError> {
+            Ok(planned)
+        }
+
+        async fn delete(&self, _resource_type: &str, _current: Value) -> Result<(), ProviderError> {
+            Ok(())
+        }
+    }
+
+    async fn run_cmd(args: &[&str]) -> (bool, String) {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        let mut out = Vec::new();
+        let ok = run_with_args(&TestProvider, &args, &mut out).await.unwrap();
+        (ok, String::from_utf8(out).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_devtool_schema() {
+        let (ok, output) = run_cmd(&["schema"]).await;
+        assert!(ok);
+        let schema: ProviderSchema = serde_json::from_str(&output).unwrap();
+        assert!(schema.resources.contains_key("test_resource"));
+    }
+
+    #[tokio::test]
+    async fn test_devtool_manifest() {
+        let (ok, output) = run_cmd(&["manifest", "--name", "test", "--version", "0.3.1"]).await;
+        assert!(ok);
+        let manifest: ProviderManifest = serde_json::from_str(&output).unwrap();
+        assert_eq!(manifest.name, "test");
+        assert_eq!(manifest.version, "0.3.1");
+        assert_eq!(manifest.resources, vec!["test_resource"]);
+        assert_eq!(
+            manifest.schema_hash,
+            crate::manifest::schema_hash(&TestProvider.schema())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_devtool_validate_resource() {
+        let (ok, output) = run_cmd(&[
+            "validate",
+            "--resource",
+            "test_resource",
+            r#"{"name": "test"}"#,
+        ])
+        .await;
+        assert!(ok);
+        assert!(output.contains("Configuration is valid"));
+
+        let (ok, output) = run_cmd(&["validate", "--resource", "test_resource", "{}"]).await;
+        assert!(!ok);
+        assert!(output.contains("Missing required attribute 'name'"));
+    }
+
+    #[tokio::test]
+    async fn test_devtool_plan() {
+        let (ok, output) = run_cmd(&[
+            "plan",
+            "--resource",
+            "test_resource",
+            "--config",
+            r#"{"name": "test"}"#,
+        ])
+        .await;
+        assert!(ok);
+        let result: PlanResult = serde_json::from_str(&output).unwrap();
+        assert_eq!(result.changes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_devtool_plan_uses_example_config() {
+        let (ok, output) = run_cmd(&["plan", "--resource", "test_resource"]).await;
+        assert!(ok);
+        let result: PlanResult = serde_json::from_str(&output).unwrap();
+        assert_eq!(result.planned_state["name"], "example");
+    }
+
+    #[tokio::test]
+    async fn test_devtool_example() {
+        let (ok, output) = run_cmd(&["example", "test_resource"]).await;
+        assert!(ok);
+        assert_eq!(
+            output,
+            "resource \"test_resource\" \"example\" {\n  name = \"example\"\n}\n"
+        );
+
+        let args = vec!["example".to_string(), "bogus".to_string()];
+        let mut out = Vec::new();
+        let err = run_with_args(&TestProvider, &args, &mut out)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown resource type 'bogus'"));
+    }
+
+    #[tokio::test]
+    async fn test_devtool_doctor() {
+        let (ok, output) = run_cmd(&["doctor"]).await;
+        assert!(ok);
+        assert!(output.contains("No problems found"));
+    }
+
+    #[tokio::test]
+    async fn test_devtool_plan_requires_resource() {
+        let args = vec!["plan".to_string()];
+        let mut out = Vec::new();
+        let err = run_with_args(&TestProvider, &args, &mut out)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("--resource"));
+    }
+
+    #[tokio::test]
+    async fn test_devtool_names() {
+        let (ok, output) = run_cmd(&["names", "resources"]).await;
+        assert!(ok);
+        assert_eq!(output, "test_resource\n");
+        let (_, output) = run_cmd(&["names", "--resource", "test_resource"]).await;
+        assert_eq!(output, "name\n");
+        let (_, output) = run_cmd(&["names", "data-sources"]).await;
+        assert!(output.is_empty());
+
+        let (_, output) = run_cmd(&["names"]).await;
+        let names: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(names["resources"]["test_resource"][0]["path"], "name");
+        assert_eq!(names["resources"]["test_resource"][0]["required"], true);
+        assert_eq!(names["functions"], serde_json::json!([]));
+
+        let args = vec![
+            "names".to_string(),
+            "--resource".to_string(),
+            "x".to_string(),
+        ];
+        let mut out = Vec::new();
+        assert!(run_with_args(&TestProvider, &args, &mut out).await.is_err());
+
+        // Nested block attributes are listed under the block
+        let block = Block::new()
+            .with_attribute("id", Attribute::computed_string())
+            .with_block(
+                "disk",
+                crate::schema::NestedBlock::list(
+                    Block::new().with_attribute("size", Attribute::required_int64()),
+                ),
+            );
+        let paths: Vec<_> = attribute_names(&block)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(paths, ["id", "disk", "disk.size"]);
+    }
+
+    #[tokio::test]
+    async fn test_devtool_unknown_command() {
+        let args = vec!["bogus".to_string()];
+        let mut out = Vec::new();
+        assert!(run_with_args(&TestProvider, &args, &mut out).await.is_err());
+    }
+}
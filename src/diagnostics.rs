@@ -0,0 +1,275 @@
+//! Collections of diagnostics.
+//!
+//! A configuration with a list of 5,000 bad items produces 5,000 diagnostics,
+//! often the same one reported again by both the SDK's schema validation and
+//! the provider's own. [`Diagnostics`] collects them, drops the repeats and
+//! caps the list, replacing whatever is cut with a single summary:
+//!
+//! ```
+//! use hemmer_provider_sdk::diagnostics::Diagnostics;
+//! use hemmer_provider_sdk::schema::Diagnostic;
+//!
+//! let mut diagnostics: Diagnostics = (0..5000)
+//!     .map(|i| Diagnostic::error("Invalid port").with_attribute(format!("ports.{}", i)))
+//!     .collect();
+//! diagnostics.append(vec![Diagnostic::error("Invalid port").with_attribute("ports.0")]);
+//! diagnostics.dedupe();
+//! assert_eq!(diagnostics.len(), 5000);
+//!
+//! diagnostics.truncate(100);
+//! assert_eq!(diagnostics.len(), 101);
+//! assert_eq!(
+//!     diagnostics.iter().last().unwrap().summary,
+//!     "4900 more diagnostics not shown"
+//! );
+//! assert!(diagnostics.has_errors());
+//! ```
+//!
+//! The server dedupes the diagnostics it merges from the schema and the
+//! provider, and truncates every diagnostic list it returns to
+//! [`DEFAULT_DIAGNOSTIC_LIMIT`].
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::schema::{Diagnostic, DiagnosticSeverity};
+
+/// The number of diagnostics the server returns in one response before
+/// summarizing the rest.
+pub const DEFAULT_DIAGNOSTIC_LIMIT: usize = 100;
+
+/// The number of distinct summaries listed in the detail of a truncation
+/// summary.
+const SUMMARY_EXAMPLES: usize = 5;
+
+/// An ordered collection of [`Diagnostic`]s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    /// Create an empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a diagnostic.
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.0.push(diagnostic);
+    }
+
+    /// Add every diagnostic in `diagnostics`, after those already collected.
+    pub fn append(&mut self, diagnostics: impl IntoIterator<Item = Diagnostic>) {
+        self.0.extend(diagnostics);
+    }
+
+    /// Remove diagnostics identical to an earlier one, keeping the order of
+    /// the rest.
+    pub fn dedupe(&mut self) {
+        let mut seen = HashSet::new();
+        self.0.retain(|d| seen.insert(d.clone()));
+    }
+
+    /// Order the diagnostics by attribute path, those without a path first.
+    ///
+    /// List indices compare as numbers, so `ports.2` comes before `ports.10`.
+    /// The sort is stable: diagnostics on the same path keep their order.
+    pub fn sort_by_path(&mut self) {
+        self.0.sort_by(|a, b| match (a.path(), b.path()) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(a), Some(b)) => compare_segments(a.segments(), b.segments()),
+        });
+    }
+
+    /// Whether any diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        self.0
+            .iter()
+            .any(|d| d.severity == DiagnosticSeverity::Error)
+    }
+
+    /// Keep the first `limit` diagnostics, replacing the rest with one
+    /// diagnostic counting them.
+    ///
+    /// The summary is an error if any removed diagnostic was, so
+    /// [`has_errors`](Self::has_errors) is unchanged; its detail lists the
+    /// most common of the removed summaries.
+    pub fn truncate(&mut self, limit: usize) {
+        if self.0.len() <= limit {
+            return;
+        }
+        let removed = self.0.split_off(limit);
+        let errors = removed
+            .iter()
+            .filter(|d| d.severity == DiagnosticSeverity::Error)
+            .count();
+
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for d in &removed {
+            *counts.entry(d.summary.as_str()).or_default() += 1;
+        }
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        let mut examples: Vec<_> = counts
+            .iter()
+            .take(SUMMARY_EXAMPLES)
+            .map(|(summary, count)| format!("{} ({})", summary, count))
+            .collect();
+        if counts.len() > SUMMARY_EXAMPLES {
+            examples.push(format!("{} others", counts.len() - SUMMARY_EXAMPLES));
+        }
+
+        let summary = format!("{} more diagnostics not shown", removed.len());
+        let summary = if errors > 0 {
+            Diagnostic::error(summary)
+        } else {
+            Diagnostic::warning(summary)
+        };
+        self.0.push(summary.with_detail(format!(
+            "{} errors and {} warnings: {}",
+            errors,
+            removed.len() - errors,
+            examples.join(", ")
+        )));
+    }
+
+    /// The number of diagnostics.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether there are no diagnostics.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the diagnostics.
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.0.iter()
+    }
+
+    /// The diagnostics as a `Vec`.
+    pub fn into_vec(self) -> Vec<Diagnostic> {
+        self.0
+    }
+}
+
+/// Compare path segments, numerically where both are list indices.
+fn compare_segments(a: &[String], b: &[String]) -> Ordering {
+    for (a, b) in a.iter().zip(b) {
+        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+impl From<Vec<Diagnostic>> for Diagnostics {
+    fn from(diagnostics: Vec<Diagnostic>) -> Self {
+        Self(diagnostics)
+    }
+}
+
+impl From<Diagnostics> for Vec<Diagnostic> {
+    fn from(diagnostics: Diagnostics) -> Self {
+        diagnostics.0
+    }
+}
+
+impl FromIterator<Diagnostic> for Diagnostics {
+    fn from_iter<I: IntoIterator<Item = Diagnostic>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Diagnostic> for Diagnostics {
+    fn extend<I: IntoIterator<Item = Diagnostic>>(&mut self, iter: I) {
+        self.append(iter);
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedupe_and_sort() {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.push(Diagnostic::error("Bad size").with_attribute("disk.10.size"));
+        diagnostics.push(Diagnostic::warning("Deprecated").with_attribute("disk.2.type"));
+        diagnostics.append(vec![
+            Diagnostic::error("Bad size").with_attribute("disk.10.size"),
+            Diagnostic::error("Bad config"),
+            Diagnostic::error("Bad size").with_attribute("disk.2"),
+        ]);
+        assert!(diagnostics.has_errors());
+
+        diagnostics.dedupe();
+        assert_eq!(diagnostics.len(), 4);
+
+        diagnostics.sort_by_path();
+        let paths: Vec<_> = diagnostics.iter().map(|d| d.attribute.as_deref()).collect();
+        assert_eq!(
+            paths,
+            [
+                None,
+                Some("disk.2"),
+                Some("disk.2.type"),
+                Some("disk.10.size")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut diagnostics: Diagnostics = (0..3)
+            .map(|i| Diagnostic::warning(format!("Warning {}", i)))
+            .collect();
+        diagnostics.truncate(3);
+        assert_eq!(diagnostics.len(), 3);
+        diagnostics.truncate(1);
+        assert_eq!(diagnostics.len(), 2);
+        let summary = diagnostics.iter().last().unwrap();
+        assert_eq!(summary.severity, DiagnosticSeverity::Warning);
+        assert_eq!(summary.summary, "2 more diagnostics not shown");
+        assert!(!diagnostics.has_errors());
+
+        let mut diagnostics: Diagnostics = (0..10)
+            .map(|i| Diagnostic::warning(format!("Warning {}", i)))
+            .chain((0..20).map(|_| Diagnostic::error("Invalid port")))
+            .collect();
+        diagnostics.truncate(0);
+        let summary = diagnostics.into_vec().pop().unwrap();
+        assert_eq!(summary.severity, DiagnosticSeverity::Error);
+        assert_eq!(
+            summary.detail.as_deref(),
+            Some(
+                "20 errors and 10 warnings: Invalid port (20), Warning 0 (1), Warning 1 (1), \
+                 Warning 2 (1), Warning 3 (1), 6 others"
+            )
+        );
+    }
+}
@@ -0,0 +1,554 @@
+//! Runtime resource registration.
+//!
+//! [`DynamicProvider`] implements [`ProviderService`] by dispatching to
+//! resources and data sources registered in a [`ResourceRegistry`] at runtime,
+//! rather than a fixed `match resource_type`. The schema returned from GetSchema
+//! always reflects the current registry, so resources can be generated from
+//! external descriptors (a CRD list, an API catalog) fetched during `configure`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use hemmer_provider_sdk::dynamic::{DynamicProvider, DynamicResource};
+//!
+//! let provider = DynamicProvider::new()
+//!     .with_provider_config(Schema::v0().with_attribute("endpoint", Attribute::required_string()))
+//!     .with_configure(|config, registry| async move {
+//!         let client = ApiClient::new(config["endpoint"].as_str().unwrap_or_default());
+//!         for kind in client.list_kinds().await? {
+//!             registry.register(KindResource::new(client.clone(), kind));
+//!         }
+//!         Ok(ConfigureResult::new().with_provider_data(client))
+//!     });
+//!
+//! serve(provider).await
+//! ```
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+
+use serde_json::Value;
+
+use crate::error::ProviderError;
+use crate::schema::{unknown_type, Diagnostic, ProviderSchema, Schema};
+use crate::server::ProviderService;
+use crate::types::{ConfigureResult, ImportedResource, PlanResult};
+
+/// A resource type that can be registered at runtime.
+///
+/// Validation and planning default to schema validation and
+/// [`PlanResult::from_diff`].
+#[async_trait::async_trait]
+pub trait DynamicResource: Send + Sync + 'static {
+    /// The resource type name (e.g. `"k8s_deployment"`).
+    fn type_name(&self) -> &str;
+
+    /// The resource schema.
+    fn schema(&self) -> Schema;
+
+    /// Validate the resource configuration.
+    async fn validate(&self, config: Value) -> Result<Vec<Diagnostic>, ProviderError> {
+        Ok(crate::validation::validate(&self.schema(), &config))
+    }
+
+    /// Upgrade state from an older schema version.
+    async fn upgrade_state(&self, version: i64, state: Value) -> Result<Value, ProviderError> {
+        let _ = version;
+        Ok(state)
+    }
+
+    /// Plan changes for the resource.
+    async fn plan(
+        &self,
+        prior_state: Option<Value>,
+        proposed_state: Value,
+        config: Value,
+    ) -> Result<PlanResult, ProviderError> {
+        let _ = config;
+        Ok(PlanResult::from_diff(prior_state.as_ref(), &proposed_state))
+    }
+
+    /// Create the resource.
+    async fn create(&self, planned_state: Value) -> Result<Value, ProviderError>;
+
+    /// Read the resource.
+    async fn read(&self, current_state: Value) -> Result<Value, ProviderError>;
+
+    /// Update the resource.
+    async fn update(
+        &self,
+        prior_state: Value,
+        planned_state: Value,
+    ) -> Result<Value, ProviderError>;
+
+    /// Delete the resource.
+    async fn delete(&self, current_state: Value) -> Result<(), ProviderError>;
+
+    /// Import existing infrastructure by ID.
+    async fn import(&self, id: &str) -> Result<Vec<ImportedResource>, ProviderError> {
+        let _ = id;
+        Err(ProviderError::Sdk(format!(
+            "Import not supported for resource type: {}",
+            self.type_name()
+        )))
+    }
+
+    /// Import every existing resource matching a provider-defined query.
+    async fn import_query(&self, query: Value) -> Result<Vec<ImportedResource>, ProviderError> {
+        let _ = query;
+        Err(ProviderError::Sdk(format!(
+            "Import queries not supported for resource type: {}",
+            self.type_name()
+        )))
+    }
+}
+
+/// A data source type that can be registered at runtime.
+#[async_trait::async_trait]
+pub trait DynamicDataSource: Send + Sync + 'static {
+    /// The data source type name.
+    fn type_name(&self) -> &str;
+
+    /// The data source schema.
+    fn schema(&self) -> Schema;
+
+    /// Validate the data source configuration.
+    async fn validate(&self, config: Value) -> Result<Vec<Diagnostic>, ProviderError> {
+        Ok(crate::validation::validate(&self.schema(), &config))
+    }
+
+    /// Read the data source.
+    async fn read(&self, config: Value) -> Result<Value, ProviderError>;
+}
+
+/// The set of resources and data sources served by a [`DynamicProvider`].
+///
+/// Cloning shares the same registry.
+#[derive(Clone, Default)]
+pub struct ResourceRegistry {
+    resources: Arc<RwLock<HashMap<String, Arc<dyn DynamicResource>>>>,
+    data_sources: Arc<RwLock<HashMap<String, Arc<dyn DynamicDataSource>>>>,
+}
+
+impl ResourceRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a resource, replacing any existing resource with the same type name.
+    pub fn register(&self, resource: impl DynamicResource) {
+        self.resources
+            .write()
+            .unwrap()
+            .insert(resource.type_name().to_string(), Arc::new(resource));
+    }
+
+    /// Register a data source, replacing any existing data source with the same type name.
+    pub fn register_data_source(&self, data_source: impl DynamicDataSource) {
+        self.data_sources
+            .write()
+            .unwrap()
+            .insert(data_source.type_name().to_string(), Arc::new(data_source));
+    }
+
+    /// Remove a resource. Returns whether it was registered.
+    pub fn unregister(&self, type_name: &str) -> bool {
+        self.resources.write().unwrap().remove(type_name).is_some()
+    }
+
+    /// Remove a data source. Returns whether it was registered.
+    pub fn unregister_data_source(&self, type_name: &str) -> bool {
+        self.data_sources
+            .write()
+            .unwrap()
+            .remove(type_name)
+            .is_some()
+    }
+
+    /// Remove all resources and data sources.
+    pub fn clear(&self) {
+        self.resources.write().unwrap().clear();
+        self.data_sources.write().unwrap().clear();
+    }
+
+    /// Look up a registered resource.
+    pub fn resource(&self, type_name: &str) -> Result<Arc<dyn DynamicResource>, ProviderError> {
+        let resources = self.resources.read().unwrap();
+        resources
+            .get(type_name)
+            .cloned()
+            .ok_or_else(|| unknown_type(type_name, resources.keys()))
+    }
+
+    /// Look up a registered data source.
+    pub fn data_source(
+        &self,
+        type_name: &str,
+    ) -> Result<Arc<dyn DynamicDataSource>, ProviderError> {
+        let data_sources = self.data_sources.read().unwrap();
+        data_sources
+            .get(type_name)
+            .cloned()
+            .ok_or_else(|| unknown_type(type_name, data_sources.keys()))
+    }
+
+    /// Build a provider schema from the registered types.
+    pub fn schema(&self, provider_config: Schema) -> ProviderSchema {
+        let mut schema = ProviderSchema::new().with_provider_config(provider_config);
+        for (name, resource) in self.resources.read().unwrap().iter() {
+            schema = schema.with_resource(name.clone(), resource.schema());
+        }
+        for (name, data_source) in self.data_sources.read().unwrap().iter() {
+            schema = schema.with_data_source(name.clone(), data_source.schema());
+        }
+        schema
+    }
+}
+
+impl std::fmt::Debug for ResourceRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut resources: Vec<_> = self.resources.read().unwrap().keys().cloned().collect();
+        let mut data_sources: Vec<_> = self.data_sources.read().unwrap().keys().cloned().collect();
+        resources.sort();
+        data_sources.sort();
+        f.debug_struct("ResourceRegistry")
+            .field("resources", &resources)
+            .field("data_sources", &data_sources)
+            .finish()
+    }
+}
+
+type ConfigureFuture = Pin<Box<dyn Future<Output = Result<ConfigureResult, ProviderError>> + Send>>;
+type ConfigureHook = Arc<dyn Fn(Value, ResourceRegistry) -> ConfigureFuture + Send + Sync>;
+
+/// A [`ProviderService`] backed by a runtime [`ResourceRegistry`].
+#[derive(Clone, Default)]
+pub struct DynamicProvider {
+    registry: ResourceRegistry,
+    provider_config: Schema,
+    configure: Option<ConfigureHook>,
+}
+
+impl DynamicProvider {
+    /// Create a provider with an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a provider serving an existing registry.
+    pub fn with_registry(registry: ResourceRegistry) -> Self {
+        Self {
+            registry,
+            ..Self::default()
+        }
+    }
+
+    /// Set the provider configuration schema.
+    pub fn with_provider_config(mut self, schema: Schema) -> Self {
+        self.provider_config = schema;
+        self
+    }
+
+    /// Register a resource.
+    pub fn with_resource(self, resource: impl DynamicResource) -> Self {
+        self.registry.register(resource);
+        self
+    }
+
+    /// Register a data source.
+    pub fn with_data_source(self, data_source: impl DynamicDataSource) -> Self {
+        self.registry.register_data_source(data_source);
+        self
+    }
+
+    /// Set the `configure` hook, which may register further resources.
+    pub fn with_configure<F, Fut>(mut self, configure: F) -> Self
+    where
+        F: Fn(Value, ResourceRegistry) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ConfigureResult, ProviderError>> + Send + 'static,
+    {
+        self.configure = Some(Arc::new(move |config, registry| {
+            Box::pin(configure(config, registry))
+        }));
+        self
+    }
+
+    /// The registry this provider serves.
+    pub fn registry(&self) -> &ResourceRegistry {
+        &self.registry
+    }
+}
+
+impl std::fmt::Debug for DynamicProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynamicProvider")
+            .field("registry", &self.registry)
+            .field("configure", &self.configure.is_some())
+            .finish()
+    }
+}
+
+#[async_trait::async_trait]
+impl ProviderService for DynamicProvider {
+    fn schema(&self) -> ProviderSchema {
+        self.registry.schema(self.provider_config.clone())
+    }
+
+    async fn validate_provider_config(
+        &self,
+        config: Value,
+    ) -> Result<Vec<Diagnostic>, ProviderError> {
+        Ok(crate::validation::validate(&self.provider_config, &config))
+    }
+
+    async fn configure(&self, config: Value) -> Result<ConfigureResult, ProviderError> {
+        match &self.configure {
+            Some(configure) => configure(config, self.registry.clone()).await,
+            None => Ok(ConfigureResult::new()),
+        }
+    }
+
+    async fn validate_resource_config(
+        &self,
+        resource_type: &str,
+        config: Value,
+    ) -> Result<Vec<Diagnostic>, ProviderError> {
+        self.registry
+            .resource(resource_type)?
+            .validate(config)
+            .await
+    }
+
+    async fn upgrade_resource_state(
+        &self,
+        resource_type: &str,
+        version: i64,
+        state: Value,
+    ) -> Result<Value, ProviderError> {
+        self.registry
+            .resource(resource_type)?
+            .upgrade_state(version, state)
+            .await
+    }
+
+    async fn plan(
+        &self,
+        resource_type: &str,
+        prior_state: Option<Value>,
+        proposed_state: Value,
+        config: Value,
+    ) -> Result<PlanResult, ProviderError> {
+        self.registry
+            .resource(resource_type)?
+            .plan(prior_state, proposed_state, config)
+            .await
+    }
+
+    async fn create(
+        &self,
+        resource_type: &str,
+        planned_state: Value,
+    ) -> Result<Value, ProviderError> {
+        self.registry
+            .resource(resource_type)?
+            .create(planned_state)
+            .await
+    }
+
+    async fn read(
+        &self,
+        resource_type: &str,
+        current_state: Value,
+    ) -> Result<Value, ProviderError> {
+        self.registry
+            .resource(resource_type)?
+            .read(current_state)
+            .await
+    }
+
+    async fn update(
+        &self,
+        resource_type: &str,
+        prior_state: Value,
+        planned_state: Value,
+    ) -> Result<Value, ProviderError> {
+        self.registry
+            .resource(resource_type)?
+            .update(prior_state, planned_state)
+            .await
+    }
+
+    async fn delete(&self, resource_type: &str, current_state: Value) -> Result<(), ProviderError> {
+        self.registry
+            .resource(resource_type)?
+            .delete(current_state)
+            .await
+    }
+
+    async fn import_resource(
+        &self,
+        resource_type: &str,
+        id: &str,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        self.registry.resource(resource_type)?.import(id).await
+    }
+
+    async fn import_query(
+        &self,
+        resource_type: &str,
+        query: Value,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        self.registry
+            .resource(resource_type)?
+            .import_query(query)
+            .await
+    }
+
+    async fn validate_data_source_config(
+        &self,
+        data_source_type: &str,
+        config: Value,
+    ) -> Result<Vec<Diagnostic>, ProviderError> {
+        self.registry
+            .data_source(data_source_type)?
+            .validate(config)
+            .await
+    }
+
+    async fn read_data_source(
+        &self,
+        data_source_type: &str,
+        config: Value,
+    ) -> Result<Value, ProviderError> {
+        self.registry
+            .data_source(data_source_type)?
+            .read(config)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Attribute;
+    use crate::testing::ProviderTester;
+    use serde_json::json;
+
+    /// A resource generated from a descriptor, echoing its planned state.
+    struct KindResource {
+        kind: String,
+    }
+
+    #[async_trait::async_trait]
+    impl DynamicResource for KindResource {
+        fn type_name(&self) -> &str {
+            &self.kind
+        }
+
+        fn schema(&self) -> Schema {
+            Schema::v0()
+                .with_attribute("name", Attribute::required_string())
+                .with_attribute("id", Attribute::computed_string())
+        }
+
+        async fn create(&self, mut planned_state: Value) -> Result<Value, ProviderError> {
+            planned_state["id"] = json!(format!("{}-1", self.kind));
+            Ok(planned_state)
+        }
+
+        async fn read(&self, current_state: Value) -> Result<Value, ProviderError> {
+            Ok(current_state)
+        }
+
+        async fn update(
+            &self,
+            _prior: Value,
+            planned_state: Value,
+        ) -> Result<Value, ProviderError> {
+            Ok(planned_state)
+        }
+
+        async fn delete(&self, _current_state: Value) -> Result<(), ProviderError> {
+            Ok(())
+        }
+    }
+
+    struct Regions;
+
+    #[async_trait::async_trait]
+    impl DynamicDataSource for Regions {
+        fn type_name(&self) -> &str {
+            "regions"
+        }
+
+        fn schema(&self) -> Schema {
+            Schema::v0().with_attribute("names", Attribute::computed_string())
+        }
+
+        async fn read(&self, _config: Value) -> Result<Value, ProviderError> {
+            Ok(json!({"names": ["us", "eu"]}))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_configure_registers_from_descriptors() {
+        let provider = DynamicProvider::new()
+            .with_data_source(Regions)
+            .with_configure(|config, registry| async move {
+                for kind in config["kinds"].as_array().into_iter().flatten() {
+                    registry.register(KindResource {
+                        kind: kind.as_str().unwrap_or_default().to_string(),
+                    });
+                }
+                Ok(ConfigureResult::new())
+            });
+        let tester = ProviderTester::new(provider);
+
+        assert!(tester.resource_types().is_empty());
+        assert_eq!(tester.data_source_types(), vec!["regions".to_string()]);
+
+        tester
+            .configure(json!({"kinds": ["widget", "gadget"]}))
+            .await
+            .unwrap();
+
+        let mut types = tester.resource_types();
+        types.sort();
+        assert_eq!(types, vec!["gadget", "widget"]);
+        assert!(tester.schema().resources.contains_key("widget"));
+
+        let state = tester
+            .lifecycle_create("widget", json!({"name": "w"}))
+            .await
+            .unwrap();
+        assert_eq!(state["id"], "widget-1");
+
+        let data = tester.read_data_source("regions", json!({})).await.unwrap();
+        assert_eq!(data["names"][1], "eu");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_and_unregistered_types() {
+        let provider = DynamicProvider::new().with_resource(KindResource {
+            kind: "widget".to_string(),
+        });
+        let registry = provider.registry().clone();
+        let tester = ProviderTester::new(provider);
+
+        tester
+            .validate_resource_config("widget", json!({"name": "w"}))
+            .await
+            .unwrap();
+        assert!(tester
+            .validate_resource_config("widget", json!({}))
+            .await
+            .is_err());
+
+        assert!(registry.unregister("widget"));
+        let err = tester.read("widget", json!({})).await.unwrap_err();
+        assert!(matches!(err, ProviderError::UnknownResource(_)));
+    }
+}
@@ -92,6 +92,52 @@ impl ProviderError {
         }
     }
 
+    /// The gRPC status code for this error.
+    ///
+    /// Used by `From<ProviderError> for tonic::Status`, and reported as the
+    /// `code` of the error diagnostic the server returns, so core can tell a
+    /// missing resource from a throttled or unauthorized request.
+    pub fn code(&self) -> tonic::Code {
+        match self {
+            Self::NotFound(_) | Self::UnknownResource(_) => tonic::Code::NotFound,
+            Self::Validation(_) | Self::Serialization(_) | Self::InvalidRequest(_) => {
+                tonic::Code::InvalidArgument
+            },
+            Self::Configuration(_) | Self::FailedPrecondition(_) => tonic::Code::FailedPrecondition,
+            Self::Sdk(_) => tonic::Code::Internal,
+            Self::Transport(_) | Self::Unavailable(_) => tonic::Code::Unavailable,
+            Self::AlreadyExists(_) => tonic::Code::AlreadyExists,
+            Self::PermissionDenied(_) => tonic::Code::PermissionDenied,
+            Self::ResourceExhausted(_) => tonic::Code::ResourceExhausted,
+            Self::DeadlineExceeded(_) => tonic::Code::DeadlineExceeded,
+            Self::Unimplemented(_) => tonic::Code::Unimplemented,
+        }
+    }
+
+    /// A stable name for the variant, e.g. `"not_found"`.
+    ///
+    /// Reported as the `error_kind` of the server's error diagnostics, since
+    /// several variants share a gRPC code.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "not_found",
+            Self::Validation(_) => "validation",
+            Self::Sdk(_) => "sdk",
+            Self::Configuration(_) => "configuration",
+            Self::UnknownResource(_) => "unknown_resource",
+            Self::Serialization(_) => "serialization",
+            Self::Transport(_) => "transport",
+            Self::AlreadyExists(_) => "already_exists",
+            Self::PermissionDenied(_) => "permission_denied",
+            Self::ResourceExhausted(_) => "resource_exhausted",
+            Self::Unavailable(_) => "unavailable",
+            Self::DeadlineExceeded(_) => "deadline_exceeded",
+            Self::FailedPrecondition(_) => "failed_precondition",
+            Self::Unimplemented(_) => "unimplemented",
+            Self::InvalidRequest(_) => "invalid_request",
+        }
+    }
+
     // Compatibility aliases for generator v0.3.5
 
     /// Alias for [`ProviderError::Configuration`] for generator compatibility.
@@ -127,27 +173,13 @@ impl ProviderError {
 
 impl From<ProviderError> for tonic::Status {
     fn from(err: ProviderError) -> Self {
-        match err {
-            ProviderError::NotFound(msg) => tonic::Status::not_found(msg),
-            ProviderError::Validation(msg) => tonic::Status::invalid_argument(msg),
-            ProviderError::Configuration(msg) => tonic::Status::failed_precondition(msg),
-            ProviderError::UnknownResource(msg) => tonic::Status::not_found(msg),
-            ProviderError::Sdk(msg) => tonic::Status::internal(msg),
-            ProviderError::Serialization(err) => {
-                tonic::Status::invalid_argument(format!("Serialization error: {}", err))
-            },
-            ProviderError::Transport(err) => {
-                tonic::Status::unavailable(format!("Transport error: {}", err))
-            },
-            ProviderError::AlreadyExists(msg) => tonic::Status::already_exists(msg),
-            ProviderError::PermissionDenied(msg) => tonic::Status::permission_denied(msg),
-            ProviderError::ResourceExhausted(msg) => tonic::Status::resource_exhausted(msg),
-            ProviderError::Unavailable(msg) => tonic::Status::unavailable(msg),
-            ProviderError::DeadlineExceeded(msg) => tonic::Status::deadline_exceeded(msg),
-            ProviderError::FailedPrecondition(msg) => tonic::Status::failed_precondition(msg),
-            ProviderError::Unimplemented(msg) => tonic::Status::unimplemented(msg),
-            ProviderError::InvalidRequest(msg) => tonic::Status::invalid_argument(msg),
-        }
+        let code = err.code();
+        let message = match err {
+            ProviderError::Serialization(err) => format!("Serialization error: {}", err),
+            ProviderError::Transport(err) => format!("Transport error: {}", err),
+            other => other.message().to_string(),
+        };
+        tonic::Status::new(code, message)
     }
 }
 
@@ -186,6 +218,22 @@ mod tests {
         assert_eq!(status.code(), tonic::Code::Internal);
     }
 
+    #[test]
+    fn test_error_code_and_kind() {
+        let err = ProviderError::ResourceExhausted("quota".to_string());
+        assert_eq!(err.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(err.kind(), "resource_exhausted");
+
+        let err = ProviderError::UnknownResource("widget".to_string());
+        assert_eq!(err.code(), tonic::Code::NotFound);
+        assert_eq!(err.kind(), "unknown_resource");
+
+        let err = ProviderError::Serialization(serde_json::from_str::<u8>("x").unwrap_err());
+        let status: tonic::Status = err.into();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(status.message().starts_with("Serialization error: "));
+    }
+
     #[test]
     fn test_new_error_variants_display() {
         let err = ProviderError::AlreadyExists("bucket-123".to_string());
@@ -0,0 +1,250 @@
+//! Provider-initiated events.
+//!
+//! Some things a provider learns are worth telling the user about even though
+//! no request is asking: an API quota is nearly exhausted, an endpoint is
+//! deprecated, credentials expire soon. [`EventSender::send`] queues an
+//! advisory [`Event`], which the server streams to core over the `Events` RPC.
+//!
+//! Every operation's [`Context`](crate::Context) carries the server's sender,
+//! and it can be cloned into background tasks started from `configure`:
+//!
+//! ```ignore
+//! async fn configure(&self, config: Value) -> Result<ConfigureResult, ProviderError> {
+//!     let client = ApiClient::new(&config)?;
+//!     let events = Context::current().events();
+//!     let quota = client.clone();
+//!     tokio::spawn(async move {
+//!         loop {
+//!             if quota.remaining().await < 100 {
+//!                 events.send(Event::new(
+//!                     "quota",
+//!                     Diagnostic::warning("API quota nearly exhausted"),
+//!                 ));
+//!             }
+//!             tokio::time::sleep(Duration::from_secs(60)).await;
+//!         }
+//!     });
+//!     Ok(ConfigureResult::new().with_provider_data(client))
+//! }
+//! ```
+//!
+//! Events are numbered from 1 and kept in a bounded backlog until core
+//! acknowledges them, so events raised before core subscribes, or while it is
+//! reconnecting, are not lost. When the backlog is full the oldest event is
+//! dropped.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::watch;
+use tracing::debug;
+
+use crate::schema::Diagnostic;
+
+/// Default number of unacknowledged events kept by an [`EventSender`].
+pub const DEFAULT_EVENT_BACKLOG: usize = 256;
+
+/// An advisory event raised by the provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// Assigned by [`EventSender::send`], increasing from 1.
+    pub sequence: u64,
+    /// Event category, e.g. `"quota"` or `"deprecation"`.
+    pub kind: String,
+    /// Severity, summary and detail shown to the user.
+    pub diagnostic: Diagnostic,
+    /// The resource type the event concerns, if any.
+    pub resource_type: Option<String>,
+}
+
+impl Event {
+    /// Create an event of the given kind.
+    pub fn new(kind: impl Into<String>, diagnostic: Diagnostic) -> Self {
+        Self {
+            sequence: 0,
+            kind: kind.into(),
+            diagnostic,
+            resource_type: None,
+        }
+    }
+
+    /// Set the resource type the event concerns.
+    pub fn with_resource_type(mut self, resource_type: impl Into<String>) -> Self {
+        self.resource_type = Some(resource_type.into());
+        self
+    }
+}
+
+struct Backlog {
+    events: VecDeque<Event>,
+    next_sequence: u64,
+}
+
+/// Queues [`Event`]s for core.
+///
+/// Cloning an `EventSender` shares the same backlog.
+#[derive(Clone)]
+pub struct EventSender {
+    backlog: Arc<Mutex<Backlog>>,
+    capacity: usize,
+    latest: Arc<watch::Sender<u64>>,
+}
+
+impl EventSender {
+    /// Create a sender keeping up to `capacity` unacknowledged events.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            backlog: Arc::new(Mutex::new(Backlog {
+                events: VecDeque::new(),
+                next_sequence: 1,
+            })),
+            capacity: capacity.max(1),
+            latest: Arc::new(watch::Sender::new(0)),
+        }
+    }
+
+    /// Queue an event, returning its sequence number.
+    pub fn send(&self, mut event: Event) -> u64 {
+        let sequence = {
+            let mut backlog = self.backlog.lock().unwrap();
+            event.sequence = backlog.next_sequence;
+            backlog.next_sequence += 1;
+            if backlog.events.len() == self.capacity {
+                if let Some(dropped) = backlog.events.pop_front() {
+                    debug!(
+                        sequence = dropped.sequence,
+                        "Event backlog full, dropping oldest event"
+                    );
+                }
+            }
+            backlog.events.push_back(event);
+            backlog.next_sequence - 1
+        };
+        self.latest.send_replace(sequence);
+        sequence
+    }
+
+    /// Drop the events up to and including `sequence`, which core has processed.
+    pub fn ack(&self, sequence: u64) {
+        let mut backlog = self.backlog.lock().unwrap();
+        while backlog
+            .events
+            .front()
+            .is_some_and(|event| event.sequence <= sequence)
+        {
+            backlog.events.pop_front();
+        }
+    }
+
+    /// The queued events with a sequence greater than `sequence`.
+    pub fn events_after(&self, sequence: u64) -> Vec<Event> {
+        let backlog = self.backlog.lock().unwrap();
+        backlog
+            .events
+            .iter()
+            .filter(|event| event.sequence > sequence)
+            .cloned()
+            .collect()
+    }
+
+    /// Follow the queued events, starting with those still in the backlog.
+    pub fn subscribe(&self) -> EventSubscription {
+        EventSubscription {
+            sender: self.clone(),
+            latest: self.latest.subscribe(),
+            last: 0,
+        }
+    }
+}
+
+impl Default for EventSender {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_BACKLOG)
+    }
+}
+
+impl std::fmt::Debug for EventSender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let backlog = self.backlog.lock().unwrap();
+        f.debug_struct("EventSender")
+            .field("queued", &backlog.events.len())
+            .field("next_sequence", &backlog.next_sequence)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+/// A reader of the events queued on an [`EventSender`].
+#[derive(Debug)]
+pub struct EventSubscription {
+    sender: EventSender,
+    latest: watch::Receiver<u64>,
+    last: u64,
+}
+
+impl EventSubscription {
+    /// Wait for events not yet returned by this subscription.
+    pub async fn next(&mut self) -> Vec<Event> {
+        loop {
+            let events = self.sender.events_after(self.last);
+            if let Some(event) = events.last() {
+                self.last = event.sequence;
+                return events;
+            }
+            // The sender lives as long as this subscription, so this can't fail
+            let _ = self.latest.changed().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn event(summary: &str) -> Event {
+        Event::new("quota", Diagnostic::warning(summary))
+    }
+
+    #[test]
+    fn test_send_and_ack() {
+        let sender = EventSender::new(2);
+        assert_eq!(sender.send(event("a")), 1);
+        assert_eq!(sender.send(event("b")), 2);
+        // Full: the oldest event is dropped
+        assert_eq!(sender.send(event("c").with_resource_type("bucket")), 3);
+        let queued = sender.events_after(0);
+        assert_eq!(
+            queued.iter().map(|e| e.sequence).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        assert_eq!(queued[1].resource_type.as_deref(), Some("bucket"));
+
+        sender.ack(2);
+        assert_eq!(sender.events_after(0).len(), 1);
+        sender.ack(3);
+        assert!(sender.events_after(0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscription_replays_then_follows() {
+        let sender = EventSender::default();
+        sender.send(event("before subscribing"));
+
+        let mut subscription = sender.subscribe();
+        let events = subscription.next().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].diagnostic.summary, "before subscribing");
+
+        let background = sender.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            background.send(event("later"));
+        });
+        let events = tokio::time::timeout(Duration::from_secs(5), subscription.next())
+            .await
+            .unwrap();
+        assert_eq!(events[0].sequence, 2);
+        assert_eq!(events[0].diagnostic.summary, "later");
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+
+use crate::context::Context;
+use crate::error::ProviderError;
+use crate::schema::{
+    Attribute, AttributeFlags, AttributeType, PlanModifier, ProviderSchema, Schema,
+};
+use crate::server::ProviderService;
+use crate::types::{ConfigureResult, ImportedResource};
+use crate::upgrade;
+
+/// Settings stored by `configure` and read back through [`Context::provider_data`].
+struct Settings {
+    max_entries: Option<usize>,
+}
+
+/// An in-memory key/value store provider.
+///
+/// - Provider config: optional `max_entries`; creates beyond it fail with
+///   [`ProviderError::ResourceExhausted`]
+/// - `kv_entry` resource (schema version 1): `key` (forces replacement),
+///   `value`, optional `tags`, and computed `id` and `version`, which counts
+///   updates. Importable by key, or in bulk by a `{"prefix": ...}` query.
+///   Version 0 states stored the value as `data`
+/// - `kv_entries` data source: the values of all entries whose key starts
+///   with `prefix`
+///
+/// Cloning shares the same entries, so a test can keep a clone to inspect or
+/// seed the store while the original is being served.
+#[derive(Debug, Clone, Default)]
+pub struct KvProvider {
+    entries: Arc<Mutex<BTreeMap<String, Value>>>,
+}
+
+impl KvProvider {
+    /// Create a provider with an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry, as if it had been created outside of core (e.g. to test import).
+    pub fn with_entry(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into();
+        let state = json!({
+            "id": key,
+            "key": key,
+            "value": value.into(),
+            "tags": null,
+            "version": 1,
+        });
+        self.entries.lock().unwrap().insert(key, state);
+        self
+    }
+
+    /// The stored state of an entry.
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    /// The number of stored entries.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn entry_schema() -> Schema {
+        Schema::new(1)
+            .with_attribute(
+                "key",
+                Attribute::required_string()
+                    .with_force_new()
+                    .with_description("Key of the entry"),
+            )
+            .with_attribute(
+                "value",
+                Attribute::required_string().with_description("Value of the entry"),
+            )
+            .with_attribute(
+                "tags",
+                Attribute::new(
+                    AttributeType::map(AttributeType::String),
+                    AttributeFlags::optional(),
+                )
+                .with_description("Free-form labels"),
+            )
+            .with_attribute(
+                "id",
+                Attribute::computed_string().with_description("Same as the key"),
+            )
+            .with_attribute(
+                "version",
+                Attribute::computed_int64()
+                    .with_plan_modifier(PlanModifier::UnknownOnUpdate)
+                    .with_description("Incremented on every update"),
+            )
+            .with_import_format("{key}")
+    }
+
+    fn check_type(resource_type: &str) -> Result<(), ProviderError> {
+        match resource_type {
+            "kv_entry" => Ok(()),
+            _ => Err(ProviderError::UnknownResource(resource_type.to_string())),
+        }
+    }
+
+    fn key(state: &Value) -> Result<String, ProviderError> {
+        state["key"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| ProviderError::InvalidRequest("State has no key".to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl ProviderService for KvProvider {
+    fn schema(&self) -> ProviderSchema {
+        ProviderSchema::new()
+            .with_provider_config(Schema::v0().with_attribute(
+                "max_entries",
+                Attribute::optional_int64().with_description("Maximum number of entries"),
+            ))
+            .with_resource("kv_entry", Self::entry_schema())
+            .with_data_source(
+                "kv_entries",
+                Schema::v0()
+                    .with_attribute(
+                        "prefix",
+                        Attribute::optional_string()
+                            .with_description("Only include keys with this prefix"),
+                    )
+                    .with_attribute(
+                        "values",
+                        Attribute::new(
+                            AttributeType::map(AttributeType::String),
+                            AttributeFlags::computed(),
+                        )
+                        .with_description("Values by key"),
+                    ),
+            )
+    }
+
+    async fn configure(&self, config: Value) -> Result<ConfigureResult, ProviderError> {
+        let max_entries = match config["max_entries"].as_i64() {
+            Some(n) if n < 0 => {
+                return Err(ProviderError::Configuration(
+                    "max_entries must not be negative".to_string(),
+                ))
+            },
+            n => n.map(|n| n as usize),
+        };
+        Ok(ConfigureResult::new().with_provider_data(Settings { max_entries }))
+    }
+
+    async fn upgrade_resource_state(
+        &self,
+        resource_type: &str,
+        version: i64,
+        mut state: Value,
+    ) -> Result<Value, ProviderError> {
+        Self::check_type(resource_type)?;
+        match version {
+            0 => {
+                upgrade::rename_attribute(&mut state, "data", "value")?;
+                Ok(state)
+            },
+            1 => Ok(state),
+            _ => Err(ProviderError::FailedPrecondition(format!(
+                "State version {} is newer than this provider",
+                version
+            ))),
+        }
+    }
+
+    async fn create(
+        &self,
+        resource_type: &str,
+        mut planned: Value,
+    ) -> Result<Value, ProviderError> {
+        Self::check_type(resource_type)?;
+        let key = Self::key(&planned)?;
+        let settings = Context::current().provider_data::<Settings>().ok();
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.contains_key(&key) {
+            return Err(ProviderError::AlreadyExists(key));
+        }
+        if let Some(max) = settings.and_then(|s| s.max_entries) {
+            if entries.len() >= max {
+                return Err(ProviderError::ResourceExhausted(format!(
+                    "Store is limited to {} entries",
+                    max
+                )));
+            }
+        }
+        planned["id"] = json!(key);
+        planned["version"] = json!(1);
+        entries.insert(key, planned.clone());
+        Ok(planned)
+    }
+
+    async fn read(&self, resource_type: &str, current: Value) -> Result<Value, ProviderError> {
+        Self::check_type(resource_type)?;
+        let key = Self::key(&current)?;
+        self.get(&key).ok_or(ProviderError::NotFound(key))
+    }
+
+    async fn update(
+        &self,
+        resource_type: &str,
+        prior: Value,
+        mut planned: Value,
+    ) -> Result<Value, ProviderError> {
+        Self::check_type(resource_type)?;
+        let key = Self::key(&prior)?;
+        let mut entries = self.entries.lock().unwrap();
+        let stored = entries
+            .get_mut(&key)
+            .ok_or_else(|| ProviderError::NotFound(key.clone()))?;
+        planned["id"] = json!(key);
+        planned["version"] = json!(stored["version"].as_i64().unwrap_or_default() + 1);
+        *stored = planned.clone();
+        Ok(planned)
+    }
+
+    async fn delete(&self, resource_type: &str, current: Value) -> Result<(), ProviderError> {
+        Self::check_type(resource_type)?;
+        let key = Self::key(&current)?;
+        // Deleting an entry that is already gone succeeds
+        self.entries.lock().unwrap().remove(&key);
+        Ok(())
+    }
+
+    async fn import_resource(
+        &self,
+        resource_type: &str,
+        id: &str,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        Self::check_type(resource_type)?;
+        let id = Self::entry_schema().parse_import_id(id)?;
+        let key = id.get("key").unwrap_or_default();
+        let state = self
+            .get(key)
+            .ok_or_else(|| ProviderError::NotFound(key.to_string()))?;
+        Ok(vec![ImportedResource::new(resource_type, state)])
+    }
+
+    async fn import_query(
+        &self,
+        resource_type: &str,
+        query: Value,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        Self::check_type(resource_type)?;
+        let prefix = query["prefix"].as_str().unwrap_or_default();
+        Ok(self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(_, state)| ImportedResource::new(resource_type, state.clone()))
+            .collect())
+    }
+
+    async fn read_data_source(
+        &self,
+        data_source_type: &str,
+        mut config: Value,
+    ) -> Result<Value, ProviderError> {
+        if data_source_type != "kv_entries" {
+            return Err(ProviderError::UnknownResource(data_source_type.to_string()));
+        }
+        let prefix = config["prefix"].as_str().unwrap_or_default();
+        let values: serde_json::Map<_, _> = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, state)| (key.clone(), state["value"].clone()))
+            .collect();
+        config["values"] = Value::Object(values);
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::self_check;
+    use crate::testing::{assert_plan_replaces, ProviderTester};
+
+    #[tokio::test]
+    async fn test_kv_provider() {
+        assert!(self_check(&KvProvider::new()).await.is_empty());
+
+        let tester = ProviderTester::new(KvProvider::new().with_entry("existing", "x"));
+        tester.configure(json!({"max_entries": 2})).await.unwrap();
+
+        let state = tester
+            .lifecycle_create("kv_entry", json!({"key": "a", "value": "1"}))
+            .await
+            .unwrap();
+        assert_eq!(state["version"], 1);
+        let err = tester
+            .create("kv_entry", json!({"key": "b", "value": "2"}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::ResourceExhausted(_)));
+
+        let updated = tester
+            .lifecycle_update("kv_entry", state.clone(), json!({"key": "a", "value": "2"}))
+            .await
+            .unwrap();
+        assert_eq!(updated["version"], 2);
+        let plan = tester
+            .plan_update(
+                "kv_entry",
+                updated.clone(),
+                json!({"key": "z", "value": "2"}),
+            )
+            .await
+            .unwrap();
+        assert_plan_replaces(&plan);
+
+        let data = tester
+            .read_data_source("kv_entries", json!({"prefix": "a"}))
+            .await
+            .unwrap();
+        assert_eq!(data["values"], json!({"a": "2"}));
+
+        let imported = tester
+            .import_resource("kv_entry", "existing")
+            .await
+            .unwrap();
+        assert_eq!(imported[0].state["value"], "x");
+        assert!(tester.import_resource("kv_entry", "missing").await.is_err());
+        let imported = tester
+            .import_query("kv_entry", json!({"prefix": "ex"}))
+            .await
+            .unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].state["key"], "existing");
+
+        tester.lifecycle_delete("kv_entry", updated).await.unwrap();
+        assert_eq!(tester.provider().len(), 1);
+
+        let upgraded = tester
+            .upgrade_resource_state("kv_entry", 0, json!({"key": "a", "data": "old"}))
+            .await
+            .unwrap();
+        assert_eq!(upgraded, json!({"key": "a", "value": "old"}));
+    }
+}
@@ -0,0 +1,43 @@
+//! Complete example providers (feature: `examples`).
+//!
+//! These providers keep their state in memory, so they need no credentials or
+//! network access. They serve as living documentation of the SDK and as
+//! realistic fixtures for core's integration tests and for exercising
+//! [`testing`](crate::testing) helpers:
+//!
+//! - [`KvProvider`]: a key/value store with a resource, a data source, import
+//!   and a state upgrade
+//! - [`NoopProvider`]: a resource that does nothing, for testing core's plan
+//!   and apply flow without side effects
+//!
+//! # Example
+//!
+//! ```
+//! use hemmer_provider_sdk::examples::KvProvider;
+//! use hemmer_provider_sdk::testing::ProviderTester;
+//! use serde_json::json;
+//!
+//! # tokio_test::block_on(async {
+//! let tester = ProviderTester::new(KvProvider::new());
+//! tester.configure(json!({})).await.unwrap();
+//!
+//! let state = tester
+//!     .lifecycle_create("kv_entry", json!({"key": "greeting", "value": "hello"}))
+//!     .await
+//!     .unwrap();
+//! assert_eq!(state["id"], "greeting");
+//! assert_eq!(tester.provider().get("greeting").unwrap()["value"], "hello");
+//! # });
+//! ```
+//!
+//! Either provider can be served like any other:
+//!
+//! ```ignore
+//! hemmer_provider_sdk::serve(KvProvider::new()).await?;
+//! ```
+
+mod kv;
+mod noop;
+
+pub use kv::KvProvider;
+pub use noop::NoopProvider;
@@ -0,0 +1,115 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+
+use crate::error::ProviderError;
+use crate::schema::{Attribute, AttributeFlags, AttributeType, ProviderSchema, Schema};
+use crate::server::ProviderService;
+use crate::types::ConfigureResult;
+
+/// A provider whose only resource does nothing.
+///
+/// The `noop` resource has optional `triggers` (a map of strings; changing it
+/// forces replacement) and a computed `id` that is new for every create, so
+/// core's plan, replace and apply flow can be tested without side effects.
+/// Cloning shares the ID counter.
+#[derive(Debug, Clone, Default)]
+pub struct NoopProvider {
+    created: Arc<AtomicU64>,
+}
+
+impl NoopProvider {
+    /// Create a provider.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of resources created so far.
+    pub fn created(&self) -> u64 {
+        self.created.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait::async_trait]
+impl ProviderService for NoopProvider {
+    fn schema(&self) -> ProviderSchema {
+        ProviderSchema::new().with_resource(
+            "noop",
+            Schema::v0()
+                .with_attribute(
+                    "triggers",
+                    Attribute::new(
+                        AttributeType::map(AttributeType::String),
+                        AttributeFlags::optional(),
+                    )
+                    .with_force_new()
+                    .with_description("Arbitrary values that force replacement when changed"),
+                )
+                .with_attribute(
+                    "id",
+                    Attribute::computed_string().with_description("Unique per create"),
+                ),
+        )
+    }
+
+    async fn configure(&self, _config: Value) -> Result<ConfigureResult, ProviderError> {
+        Ok(ConfigureResult::new())
+    }
+
+    async fn create(
+        &self,
+        _resource_type: &str,
+        mut planned: Value,
+    ) -> Result<Value, ProviderError> {
+        let n = self.created.fetch_add(1, Ordering::SeqCst) + 1;
+        planned["id"] = json!(format!("noop-{}", n));
+        Ok(planned)
+    }
+
+    async fn read(&self, _resource_type: &str, current: Value) -> Result<Value, ProviderError> {
+        Ok(current)
+    }
+
+    async fn update(
+        &self,
+        _resource_type: &str,
+        _prior: Value,
+        planned: Value,
+    ) -> Result<Value, ProviderError> {
+        Ok(planned)
+    }
+
+    async fn delete(&self, _resource_type: &str, _current: Value) -> Result<(), ProviderError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_plan_no_changes, assert_plan_replaces, ProviderTester};
+
+    #[tokio::test]
+    async fn test_noop_provider() {
+        let tester = ProviderTester::new(NoopProvider::new());
+        let config = json!({"triggers": {"version": "1"}});
+        let state = tester
+            .lifecycle_create("noop", config.clone())
+            .await
+            .unwrap();
+        assert_eq!(state["id"], "noop-1");
+
+        let plan = tester
+            .plan_update("noop", state.clone(), config)
+            .await
+            .unwrap();
+        assert_plan_no_changes(&plan);
+        let plan = tester
+            .plan_update("noop", state, json!({"triggers": {"version": "2"}}))
+            .await
+            .unwrap();
+        assert_plan_replaces(&plan);
+        assert_eq!(tester.provider().created(), 1);
+    }
+}
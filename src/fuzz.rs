@@ -0,0 +1,478 @@
+//! Schema-aware mutation helpers for fuzzing provider handlers.
+//!
+//! Handlers like `upgrade_resource_state` and `read` receive states written by
+//! older provider versions, edited by hand, or truncated on disk, so they should
+//! return errors rather than panic on anything core hands them. Random bytes
+//! rarely get past JSON parsing, so [`mutate_state`] starts from a plausible
+//! state and corrupts it the way real states go wrong: attributes of the wrong
+//! type, required attributes missing, unexpected extra fields, nulls, and
+//! pathologically deep nesting.
+//!
+//! Mutations draw from a [`FuzzRng`]: [`ByteRng`] turns fuzzer input into
+//! choices, so coverage-guided fuzzers can steer them, and [`SeedRng`] gives
+//! reproducible mutations for ordinary tests.
+//!
+//! # Example
+//!
+//! ```
+//! use hemmer_provider_sdk::fuzz::{mutate_state, SeedRng};
+//! use hemmer_provider_sdk::schema::{Attribute, Schema};
+//! use serde_json::json;
+//!
+//! let schema = Schema::v0()
+//!     .with_attribute("name", Attribute::required_string())
+//!     .with_attribute("size", Attribute::optional_int64());
+//! let state = json!({"name": "disk", "size": 10});
+//!
+//! let mut rng = SeedRng::new(7);
+//! for _ in 0..100 {
+//!     let corrupted = mutate_state(&schema, &state, &mut rng);
+//!     assert_ne!(corrupted, state);
+//! }
+//! ```
+//!
+//! # cargo-fuzz
+//!
+//! [`cargo_fuzz_target`] generates a `fuzz_targets/*.rs` harness that feeds
+//! mutated states to a handler, for a fuzz crate depending on the provider,
+//! this SDK, `tokio` and `libfuzzer-sys`:
+//!
+//! ```no_run
+//! use hemmer_provider_sdk::fuzz::{cargo_fuzz_target, FuzzTarget};
+//!
+//! let source = cargo_fuzz_target(
+//!     "my_provider::MyProvider::new()",
+//!     "bucket",
+//!     FuzzTarget::UpgradeResourceState { version: 0 },
+//! );
+//! std::fs::write("fuzz/fuzz_targets/upgrade_bucket.rs", source).unwrap();
+//! ```
+
+use serde_json::{Map, Number, Value};
+
+use crate::schema::{example_value, AttributeType, Block, BlockNestingMode, Schema};
+
+/// Nesting depth of the values produced by deep nesting mutations.
+const DEEP_NESTING_DEPTH: usize = 256;
+
+/// A source of random choices for mutations.
+pub trait FuzzRng {
+    /// The next random number.
+    fn next_u64(&mut self) -> u64;
+
+    /// A random number below `n`, which must not be zero.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// True with probability `1 / n`.
+    fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+}
+
+/// Draws choices from fuzzer input, returning zeros once it is used up.
+#[derive(Debug, Clone)]
+pub struct ByteRng<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteRng<'a> {
+    /// Draw from `data`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl FuzzRng for ByteRng<'_> {
+    fn next_u64(&mut self) -> u64 {
+        let (head, rest) = self.data.split_at(self.data.len().min(8));
+        self.data = rest;
+        head.iter().fold(0, |n, byte| (n << 8) | u64::from(*byte))
+    }
+}
+
+/// A seeded pseudo-random generator (SplitMix64), for reproducible mutations.
+#[derive(Debug, Clone)]
+pub struct SeedRng {
+    state: u64,
+}
+
+impl SeedRng {
+    /// Create a generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+impl FuzzRng for SeedRng {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// Produce a corrupted copy of `value` that still resembles a state of `schema`.
+///
+/// Applies one to three mutations at random places in the block tree: an
+/// attribute set to a value of another type, a required attribute or nested
+/// block removed, an unknown field added, an attribute or block set to null,
+/// or a value replaced with deeply nested arrays and objects. A `value` that
+/// is not an object is replaced with the schema's [`example_value`] first.
+pub fn mutate_state(schema: &Schema, value: &Value, rng: &mut impl FuzzRng) -> Value {
+    let mut state = match value {
+        Value::Object(_) => value.clone(),
+        _ => example_value(schema),
+    };
+    for _ in 0..=rng.below(3) {
+        if let Value::Object(object) = &mut state {
+            mutate_block(&schema.block, object, rng);
+        }
+    }
+    state
+}
+
+/// Mutate `object`, or one of the nested block instances in it.
+fn mutate_block(block: &Block, object: &mut Map<String, Value>, rng: &mut impl FuzzRng) {
+    // Descend into a nested block instance half of the time
+    let present: Vec<_> = block
+        .blocks
+        .iter()
+        .filter(|(name, _)| object.get(*name).is_some_and(|v| !v.is_null()))
+        .collect();
+    if !present.is_empty() && rng.one_in(2) {
+        let (name, nested) = present[rng.below(present.len())];
+        let instance = match (&nested.nesting_mode, object.get_mut(name)) {
+            (BlockNestingMode::Single, Some(Value::Object(instance))) => Some(instance),
+            (BlockNestingMode::List | BlockNestingMode::Set, Some(Value::Array(items)))
+                if !items.is_empty() =>
+            {
+                let index = rng.below(items.len());
+                items[index].as_object_mut()
+            },
+            (BlockNestingMode::Map, Some(Value::Object(entries))) if !entries.is_empty() => {
+                let index = rng.below(entries.len());
+                entries
+                    .values_mut()
+                    .nth(index)
+                    .and_then(Value::as_object_mut)
+            },
+            _ => None,
+        };
+        if let Some(instance) = instance {
+            return mutate_block(&nested.block, instance, rng);
+        }
+    }
+
+    let names: Vec<&String> = block.attributes.keys().chain(block.blocks.keys()).collect();
+    match rng.below(5) {
+        // Type flip
+        0 => {
+            if let Some(name) = pick(&names, rng) {
+                let replacement = match block.attributes.get(name) {
+                    Some(attr) => wrong_type(&attr.attr_type, rng),
+                    // A block of the wrong shape, e.g. an object for a list
+                    None => match object.get(name) {
+                        Some(Value::Array(_)) => Value::Object(Map::new()),
+                        _ => Value::Array(vec![Value::Bool(true)]),
+                    },
+                };
+                object.insert(name.clone(), replacement);
+            }
+        },
+        // Missing required attribute (or any attribute, if none are required)
+        1 => {
+            let required: Vec<_> = block
+                .attributes
+                .iter()
+                .filter(|(_, attr)| attr.flags.required)
+                .map(|(name, _)| name)
+                .chain(
+                    block
+                        .blocks
+                        .iter()
+                        .filter(|(_, nested)| nested.min_items > 0)
+                        .map(|(name, _)| name),
+                )
+                .collect();
+            match pick(&required, rng).or_else(|| pick(&names, rng)) {
+                Some(name) => {
+                    object.remove(name);
+                },
+                None => object.clear(),
+            }
+        },
+        // Extra field
+        2 => {
+            let value = random_scalar(rng);
+            object.insert(format!("fuzz_extra_{}", rng.below(1000)), value);
+        },
+        // Null
+        3 => {
+            if let Some(name) = pick(&names, rng) {
+                object.insert(name.clone(), Value::Null);
+            }
+        },
+        // Deep nesting
+        _ => {
+            let name = match pick(&names, rng) {
+                Some(name) => name.clone(),
+                None => "fuzz_deep".to_string(),
+            };
+            object.insert(name, deeply_nested(rng));
+        },
+    }
+}
+
+/// A random element of `names`, if there are any.
+fn pick<'a>(names: &[&'a String], rng: &mut impl FuzzRng) -> Option<&'a String> {
+    (!names.is_empty()).then(|| names[rng.below(names.len())])
+}
+
+/// A value that does not match `attr_type`.
+fn wrong_type(attr_type: &AttributeType, rng: &mut impl FuzzRng) -> Value {
+    let candidates = [
+        Value::String("fuzz".to_string()),
+        Value::Number(Number::from(-1)),
+        serde_json::json!(1.5),
+        Value::Bool(true),
+        Value::Array(vec![Value::Null]),
+        Value::Object(Map::from_iter([("fuzz".to_string(), Value::Null)])),
+    ];
+    let mismatched: Vec<_> = candidates
+        .into_iter()
+        .filter(|candidate| !matches_type(attr_type, candidate))
+        .collect();
+    match mismatched.len() {
+        // Dynamic accepts anything, so corrupt it with nesting instead
+        0 => deeply_nested(rng),
+        len => mismatched[rng.below(len)].clone(),
+    }
+}
+
+/// Whether `value` has the JSON shape of `attr_type`.
+fn matches_type(attr_type: &AttributeType, value: &Value) -> bool {
+    match attr_type {
+        AttributeType::String => value.is_string(),
+        AttributeType::Int64 => value.is_i64() || value.is_u64(),
+        AttributeType::Float64 => value.is_number(),
+        AttributeType::Bool => value.is_boolean(),
+        AttributeType::List(_) | AttributeType::Set(_) => value.is_array(),
+        AttributeType::Map(_) | AttributeType::Object(_) => value.is_object(),
+        AttributeType::Dynamic => true,
+    }
+}
+
+/// A random string, number or boolean, including awkward edge cases.
+fn random_scalar(rng: &mut impl FuzzRng) -> Value {
+    match rng.below(6) {
+        0 => Value::String(String::new()),
+        1 => Value::String("\u{0}\u{FFFD}🦀\n".repeat(1 + rng.below(4))),
+        2 => Value::Number(Number::from(i64::MIN)),
+        3 => Value::Number(Number::from(u64::MAX)),
+        4 => serde_json::json!(f64::MAX),
+        _ => Value::Bool(rng.one_in(2)),
+    }
+}
+
+/// Arrays and objects nested [`DEEP_NESTING_DEPTH`] levels deep.
+fn deeply_nested(rng: &mut impl FuzzRng) -> Value {
+    let mut value = Value::Null;
+    for _ in 0..DEEP_NESTING_DEPTH {
+        value = if rng.one_in(2) {
+            Value::Array(vec![value])
+        } else {
+            Value::Object(Map::from_iter([("fuzz".to_string(), value)]))
+        };
+    }
+    value
+}
+
+/// The handler a generated fuzz target exercises.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FuzzTarget {
+    /// `upgrade_resource_state`, with states claiming to be from `version`.
+    UpgradeResourceState {
+        /// The schema version passed with the state.
+        version: i64,
+    },
+    /// `read`.
+    Read,
+}
+
+/// Generate the source of a cargo-fuzz target that feeds mutated states of
+/// `resource_type` to a handler of the provider built by the expression
+/// `provider` (e.g. `"my_provider::MyProvider::new()"`).
+///
+/// Errors returned by the handler are expected; the fuzzer looks for panics,
+/// hangs and excessive memory use.
+pub fn cargo_fuzz_target(provider: &str, resource_type: &str, target: FuzzTarget) -> String {
+    let call = match target {
+        FuzzTarget::UpgradeResourceState { version } => format!(
+            "provider.upgrade_resource_state({:?}, {}, state)",
+            resource_type, version
+        ),
+        FuzzTarget::Read => format!("provider.read({:?}, state)", resource_type),
+    };
+    format!(
+        r#"#![no_main]
+
+use hemmer_provider_sdk::fuzz::{{mutate_state, ByteRng}};
+use hemmer_provider_sdk::schema::example_value;
+use hemmer_provider_sdk::ProviderService;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {{
+    let provider = {provider};
+    let schema = provider
+        .schema()
+        .resources
+        .remove({resource_type:?})
+        .expect("unknown resource type");
+    let state = mutate_state(&schema, &example_value(&schema), &mut ByteRng::new(data));
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    // Errors are fine; panics are what the fuzzer is looking for
+    let _ = runtime.block_on({call});
+}});
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Attribute, AttributeFlags, NestedBlock};
+    use crate::validation::validate_state;
+    use serde_json::json;
+
+    fn schema() -> Schema {
+        Schema::v0()
+            .with_attribute("name", Attribute::required_string())
+            .with_attribute("size", Attribute::optional_int64())
+            .with_attribute(
+                "tags",
+                Attribute::new(
+                    AttributeType::map(AttributeType::String),
+                    AttributeFlags::optional(),
+                ),
+            )
+            .with_block(
+                "rule",
+                NestedBlock::list(
+                    Block::new()
+                        .with_attribute("port", Attribute::required_int64())
+                        .with_attribute("cidr", Attribute::optional_string()),
+                )
+                .with_min_items(1),
+            )
+    }
+
+    fn state() -> Value {
+        json!({
+            "name": "web",
+            "size": 2,
+            "tags": {"env": "prod"},
+            "rule": [{"port": 80, "cidr": "0.0.0.0/0"}, {"port": 443}],
+        })
+    }
+
+    #[test]
+    fn test_mutate_state_corrupts_states() {
+        let schema = schema();
+        assert!(validate_state(&schema, &state()).is_empty());
+
+        let mut rng = SeedRng::new(1);
+        let mut invalid = 0;
+        let mut nested = 0;
+        for _ in 0..500 {
+            let mutated = mutate_state(&schema, &state(), &mut rng);
+            assert_ne!(mutated, state());
+            if !validate_state(&schema, &mutated).is_empty() {
+                invalid += 1;
+            }
+            if mutated["rule"] != state()["rule"] {
+                nested += 1;
+            }
+        }
+        // Extra fields and nulls of optional attributes are still valid
+        assert!(invalid > 250, "only {} invalid states", invalid);
+        assert!(nested > 50, "only {} nested mutations", nested);
+    }
+
+    #[test]
+    fn test_mutate_state_is_reproducible() {
+        let schema = schema();
+        let first: Vec<_> = {
+            let mut rng = SeedRng::new(42);
+            (0..20)
+                .map(|_| mutate_state(&schema, &state(), &mut rng))
+                .collect()
+        };
+        let mut rng = SeedRng::new(42);
+        for expected in first {
+            assert_eq!(mutate_state(&schema, &state(), &mut rng), expected);
+        }
+
+        // Fuzzer input drives the choices; running out of it is fine
+        let data = [3, 1, 4, 1, 5, 9, 2, 6];
+        assert_eq!(
+            mutate_state(&schema, &state(), &mut ByteRng::new(&data)),
+            mutate_state(&schema, &state(), &mut ByteRng::new(&data))
+        );
+        mutate_state(&schema, &state(), &mut ByteRng::new(&[]));
+    }
+
+    #[test]
+    fn test_mutate_state_non_object() {
+        let schema = schema();
+        let mutated = mutate_state(&schema, &json!("garbage"), &mut SeedRng::new(3));
+        assert!(mutated.is_object());
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let mut rng = SeedRng::new(5);
+        for attr_type in [
+            AttributeType::String,
+            AttributeType::Int64,
+            AttributeType::Float64,
+            AttributeType::Bool,
+            AttributeType::list(AttributeType::String),
+            AttributeType::map(AttributeType::String),
+            AttributeType::Dynamic,
+        ] {
+            for _ in 0..10 {
+                let value = wrong_type(&attr_type, &mut rng);
+                assert!(
+                    attr_type == AttributeType::Dynamic || !matches_type(&attr_type, &value),
+                    "{:?} accepted {}",
+                    attr_type,
+                    value
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cargo_fuzz_target() {
+        let source = cargo_fuzz_target(
+            "my_provider::MyProvider::new()",
+            "bucket",
+            FuzzTarget::UpgradeResourceState { version: 1 },
+        );
+        assert!(source.starts_with("#![no_main]"));
+        assert!(source.contains("let provider = my_provider::MyProvider::new();"));
+        assert!(source.contains(r#".remove("bucket")"#));
+        assert!(source.contains(r#"provider.upgrade_resource_state("bucket", 1, state)"#));
+
+        let source = cargo_fuzz_target("P", "bucket", FuzzTarget::Read);
+        assert!(source.contains(r#"runtime.block_on(provider.read("bucket", state))"#));
+    }
+}
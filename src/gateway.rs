@@ -0,0 +1,478 @@
+//! gRPC-Web and JSON/HTTP gateway for debugging running providers.
+//!
+//! When enabled via [`ServeOptions::with_gateway`](crate::ServeOptions::with_gateway),
+//! the provider server additionally accepts HTTP/1.1, translates gRPC-Web requests
+//! for browser-based tooling, and exposes a small read-only JSON API:
+//!
+//! | Method | Path | Operation |
+//! |--------|------|-----------|
+//! | `GET`  | `/v1/metadata` | `metadata` |
+//! | `GET`  | `/v1/schema` | `schema` |
+//! | `POST` | `/v1/provider/validate` | `validate_provider_config` |
+//! | `POST` | `/v1/resources/{type}/validate` | `validate_resource_config` |
+//! | `POST` | `/v1/resources/{type}/read` | `read` |
+//! | `POST` | `/v1/data-sources/{type}/validate` | `validate_data_source_config` |
+//! | `POST` | `/v1/data-sources/{type}/read` | `read_data_source` |
+//!
+//! POST bodies are the JSON config or state passed to the operation. Operations
+//! that change infrastructure (`create`, `update`, `delete`) are never exposed.
+//!
+//! Requests run through the same RPC handlers as core's, so states are decoded
+//! and encoded with the [`StateCodec`](crate::codec::StateCodec), normalized and
+//! read under the resource locks, and resolved secrets are redacted from
+//! diagnostics. Sensitive attribute values in returned states are additionally
+//! replaced with `[REDACTED]`.
+//!
+//! ```bash
+//! curl http://127.0.0.1:50051/v1/schema
+//! curl -X POST -d '{"id": "abc"}' http://127.0.0.1:50051/v1/resources/example_thing/read
+//! ```
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use prost::bytes::Bytes;
+use tonic::service::Routes;
+
+use crate::codec::transform_sensitive;
+use crate::context::ProviderDataStore;
+use crate::error::ProviderError;
+use crate::generated::provider_server::Provider;
+use crate::schema::{Block, Diagnostic, DiagnosticSeverity};
+use crate::secrets::REDACTED;
+use crate::server::{ProviderGrpcService, ProviderService};
+
+/// Build the JSON gateway router for a provider.
+///
+/// Operations see the provider data in `provider_data`, so pass the store shared
+/// with whatever front end handles `configure`.
+pub fn router<P: ProviderService>(provider: Arc<P>, provider_data: ProviderDataStore) -> Router {
+    service_router(Arc::new(
+        ProviderGrpcService::new(provider).with_provider_data(provider_data),
+    ))
+}
+
+/// Build the JSON gateway router on the gRPC service.
+fn service_router<P: ProviderService>(service: Arc<ProviderGrpcService<P>>) -> Router {
+    Router::new()
+        .route("/v1/metadata", get(metadata::<P>))
+        .route("/v1/schema", get(schema::<P>))
+        .route("/v1/provider/validate", post(validate_provider::<P>))
+        .route(
+            "/v1/resources/{resource_type}/validate",
+            post(validate_resource::<P>),
+        )
+        .route(
+            "/v1/resources/{resource_type}/read",
+            post(read_resource::<P>),
+        )
+        .route(
+            "/v1/data-sources/{data_source_type}/validate",
+            post(validate_data_source::<P>),
+        )
+        .route(
+            "/v1/data-sources/{data_source_type}/read",
+            post(read_data_source::<P>),
+        )
+        .with_state(GatewayState { service })
+}
+
+/// Combine the gRPC service with the JSON gateway, with gRPC-Web translation.
+pub(crate) fn routes<P: ProviderService>(service: Arc<ProviderGrpcService<P>>) -> Routes {
+    let grpc = crate::generated::provider_server::ProviderServer::from_arc(Arc::clone(&service));
+    let router = service_router(service)
+        .merge(Routes::new(grpc).into_axum_router())
+        .layer(tonic_web::GrpcWebLayer::new());
+    Routes::from(router)
+}
+
+struct GatewayState<P: ProviderService> {
+    service: Arc<ProviderGrpcService<P>>,
+}
+
+impl<P: ProviderService> GatewayState<P> {
+    /// Replace the sensitive attribute values in a state with [`REDACTED`].
+    async fn redact(
+        &self,
+        block: impl FnOnce(&crate::ProviderSchema) -> Option<&Block>,
+        state: &mut serde_json::Value,
+    ) {
+        let schema = self.service.current_schema().await.unwrap_or_default();
+        if let Some(block) = block(&schema) {
+            let redact =
+                |_: &serde_json::Value| Ok(serde_json::Value::String(REDACTED.to_string()));
+            let _ = transform_sensitive(block, state, false, &redact);
+        }
+    }
+}
+
+impl<P: ProviderService> Clone for GatewayState<P> {
+    fn clone(&self) -> Self {
+        Self {
+            service: Arc::clone(&self.service),
+        }
+    }
+}
+
+/// Error response returned by the gateway.
+struct GatewayError(tonic::Status);
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        let status = self.0;
+        let http_status = match status.code() {
+            tonic::Code::NotFound => StatusCode::NOT_FOUND,
+            tonic::Code::InvalidArgument => StatusCode::BAD_REQUEST,
+            tonic::Code::FailedPrecondition => StatusCode::PRECONDITION_FAILED,
+            tonic::Code::AlreadyExists => StatusCode::CONFLICT,
+            tonic::Code::PermissionDenied => StatusCode::FORBIDDEN,
+            tonic::Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+            tonic::Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            tonic::Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+            tonic::Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let body = serde_json::json!({
+            "code": format!("{:?}", status.code()),
+            "message": status.message(),
+        });
+        (http_status, Json(body)).into_response()
+    }
+}
+
+impl From<ProviderError> for GatewayError {
+    fn from(err: ProviderError) -> Self {
+        Self(tonic::Status::from(err))
+    }
+}
+
+impl From<tonic::Status> for GatewayError {
+    fn from(status: tonic::Status) -> Self {
+        Self(status)
+    }
+}
+
+type GatewayResult = Result<Json<serde_json::Value>, GatewayError>;
+
+fn to_json<T: serde::Serialize>(value: &T) -> GatewayResult {
+    Ok(Json(
+        serde_json::to_value(value).map_err(ProviderError::from)?,
+    ))
+}
+
+fn to_payload(value: &serde_json::Value) -> Result<Bytes, GatewayError> {
+    Ok(serde_json::to_vec(value)
+        .map_err(ProviderError::from)?
+        .into())
+}
+
+fn from_payload(bytes: &[u8]) -> Result<serde_json::Value, GatewayError> {
+    if bytes.is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+    Ok(serde_json::from_slice(bytes).map_err(ProviderError::from)?)
+}
+
+/// Fail with the first diagnostic reporting a provider error, if any.
+///
+/// Diagnostics without a code are findings of the operation, not failures.
+fn check(diagnostics: &[crate::generated::Diagnostic]) -> Result<(), GatewayError> {
+    match diagnostics.iter().find(|d| d.code != 0) {
+        Some(d) => Err(tonic::Status::new(tonic::Code::from(d.code), d.summary.clone()).into()),
+        None => Ok(()),
+    }
+}
+
+/// The diagnostics of a validation response, as returned by the provider.
+fn diagnostics_json(diagnostics: Vec<crate::generated::Diagnostic>) -> GatewayResult {
+    check(&diagnostics)?;
+    let non_empty = |s: String| (!s.is_empty()).then_some(s);
+    let diagnostics: Vec<Diagnostic> = diagnostics
+        .into_iter()
+        .map(|d| Diagnostic {
+            severity: match d.severity() {
+                crate::generated::diagnostic::Severity::Warning => DiagnosticSeverity::Warning,
+                _ => DiagnosticSeverity::Error,
+            },
+            summary: d.summary,
+            detail: non_empty(d.detail),
+            attribute: non_empty(d.attribute),
+            suggestion: non_empty(d.suggestion),
+            message: None,
+        })
+        .collect();
+    to_json(&diagnostics)
+}
+
+async fn metadata<P: ProviderService>(State(state): State<GatewayState<P>>) -> GatewayResult {
+    let metadata = state
+        .service
+        .current_metadata()
+        .await
+        .map_err(crate::server::schema_unavailable)?;
+    to_json(&metadata)
+}
+
+async fn schema<P: ProviderService>(State(state): State<GatewayState<P>>) -> GatewayResult {
+    let schema = state
+        .service
+        .current_schema()
+        .await
+        .map_err(crate::server::schema_unavailable)?;
+    to_json(&*schema)
+}
+
+async fn validate_provider<P: ProviderService>(
+    State(state): State<GatewayState<P>>,
+    Json(config): Json<serde_json::Value>,
+) -> GatewayResult {
+    let response = state
+        .service
+        .validate_provider_config(tonic::Request::new(
+            crate::generated::ValidateProviderConfigRequest {
+                config: to_payload(&config)?,
+            },
+        ))
+        .await?;
+    diagnostics_json(response.into_inner().diagnostics)
+}
+
+async fn validate_resource<P: ProviderService>(
+    State(state): State<GatewayState<P>>,
+    Path(resource_type): Path<String>,
+    Json(config): Json<serde_json::Value>,
+) -> GatewayResult {
+    let response = state
+        .service
+        .validate_resource_config(tonic::Request::new(
+            crate::generated::ValidateResourceConfigRequest {
+                resource_type,
+                config: to_payload(&config)?,
+            },
+        ))
+        .await?;
+    diagnostics_json(response.into_inner().diagnostics)
+}
+
+async fn read_resource<P: ProviderService>(
+    State(state): State<GatewayState<P>>,
+    Path(resource_type): Path<String>,
+    Json(current_state): Json<serde_json::Value>,
+) -> GatewayResult {
+    let response = state
+        .service
+        .read(tonic::Request::new(crate::generated::ReadRequest {
+            resource_type: resource_type.clone(),
+            current_state: to_payload(&current_state)?,
+            client_capabilities: None,
+        }))
+        .await?
+        .into_inner();
+    check(&response.diagnostics)?;
+    let mut result = from_payload(&response.state)?;
+    state
+        .redact(
+            |schema| schema.resources.get(&resource_type).map(|s| &s.block),
+            &mut result,
+        )
+        .await;
+    Ok(Json(result))
+}
+
+async fn validate_data_source<P: ProviderService>(
+    State(state): State<GatewayState<P>>,
+    Path(data_source_type): Path<String>,
+    Json(config): Json<serde_json::Value>,
+) -> GatewayResult {
+    let response = state
+        .service
+        .validate_data_source_config(tonic::Request::new(
+            crate::generated::ValidateDataSourceConfigRequest {
+                data_source_type,
+                config: to_payload(&config)?,
+            },
+        ))
+        .await?;
+    diagnostics_json(response.into_inner().diagnostics)
+}
+
+async fn read_data_source<P: ProviderService>(
+    State(state): State<GatewayState<P>>,
+    Path(data_source_type): Path<String>,
+    Json(config): Json<serde_json::Value>,
+) -> GatewayResult {
+    let response = state
+        .service
+        .read_data_source(tonic::Request::new(
+            crate::generated::ReadDataSourceRequest {
+                data_source_type: data_source_type.clone(),
+                config: to_payload(&config)?,
+                client_capabilities: None,
+            },
+        ))
+        .await?
+        .into_inner();
+    check(&response.diagnostics)?;
+    let mut result = from_payload(&response.state)?;
+    state
+        .redact(
+            |schema| schema.data_sources.get(&data_source_type).map(|s| &s.block),
+            &mut result,
+        )
+        .await;
+    Ok(Json(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Attribute, ProviderSchema, Schema};
+    use crate::types::{ConfigureResult, PlanResult};
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    struct TestProvider;
+
+    #[async_trait::async_trait]
+    impl ProviderService for TestProvider {
+        fn schema(&self) -> ProviderSchema {
+            ProviderSchema::new().with_resource(
+                "test_thing",
+                Schema::v0()
+                    .with_attribute("id", Attribute::required_string())
+                    .with_attribute("password", Attribute::optional_string().sensitive()),
+            )
+        }
+
+        async fn configure(
+            &self,
+            _config: serde_json::Value,
+        ) -> Result<ConfigureResult, ProviderError> {
+            Ok(ConfigureResult::new())
+        }
+
+        async fn plan(
+            &self,
+            _resource_type: &str,
+            _prior_state: Option<serde_json::Value>,
+            proposed_state: serde_json::Value,
+            _config: serde_json::Value,
+        ) -> Result<PlanResult, ProviderError> {
+            Ok(PlanResult::no_change(proposed_state))
+        }
+
+        async fn create(
+            &self,
+            _resource_type: &str,
+            planned_state: serde_json::Value,
+        ) -> Result<serde_json::Value, ProviderError> {
+            Ok(planned_state)
+        }
+
+        async fn read(
+            &self,
+            resource_type: &str,
+            current_state: serde_json::Value,
+        ) -> Result<serde_json::Value, ProviderError> {
+            if current_state["id"] == "missing" {
+                return Err(ProviderError::NotFound(resource_type.to_string()));
+            }
+            Ok(current_state)
+        }
+
+        async fn update(
+            &self,
+            _resource_type: &str,
+            _prior_state: serde_json::Value,
+            planned_state: serde_json::Value,
+        ) -> Result<serde_json::Value, ProviderError> {
+            Ok(planned_state)
+        }
+
+        async fn delete(
+            &self,
+            _resource_type: &str,
+            _current_state: serde_json::Value,
+        ) -> Result<(), ProviderError> {
+            Ok(())
+        }
+    }
+
+    async fn send(request: Request<Body>) -> (StatusCode, serde_json::Value) {
+        let response = router(Arc::new(TestProvider), ProviderDataStore::new())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn post_json(uri: &str, body: serde_json::Value) -> Request<Body> {
+        Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_gateway_schema() {
+        let (status, body) = send(Request::get("/v1/schema").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["resources"]["test_thing"].is_object());
+
+        let (status, body) = send(Request::get("/v1/metadata").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.is_object());
+    }
+
+    #[tokio::test]
+    async fn test_gateway_read_resource() {
+        let (status, body) = send(post_json(
+            "/v1/resources/test_thing/read",
+            serde_json::json!({"id": "abc"}),
+        ))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["id"], "abc");
+
+        let (status, body) = send(post_json(
+            "/v1/resources/test_thing/read",
+            serde_json::json!({"id": "missing"}),
+        ))
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "NotFound");
+    }
+
+    #[tokio::test]
+    async fn test_gateway_redacts_sensitive_state() {
+        let (status, body) = send(post_json(
+            "/v1/resources/test_thing/read",
+            serde_json::json!({"id": "abc", "password": "hunter2"}),
+        ))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["id"], "abc");
+        assert_eq!(body["password"], REDACTED);
+    }
+
+    #[tokio::test]
+    async fn test_gateway_does_not_expose_mutations() {
+        let response = router(Arc::new(TestProvider), ProviderDataStore::new())
+            .oneshot(post_json(
+                "/v1/resources/test_thing/create",
+                serde_json::json!({"id": "abc"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -11,6 +11,16 @@ pub struct Diagnostic {
     /// Attribute path where the issue occurred
     #[prost(string, tag = "4")]
     pub attribute: ::prost::alloc::string::String,
+    /// Suggested fix that core can offer to the user
+    #[prost(string, tag = "5")]
+    pub suggestion: ::prost::alloc::string::String,
+    /// For diagnostics reporting a provider error: the gRPC status code the error
+    /// maps to (e.g. 5 NOT_FOUND, 8 RESOURCE_EXHAUSTED) and its kind (e.g.
+    /// "not_found", "unknown_resource"). 0 and empty otherwise.
+    #[prost(int32, tag = "6")]
+    pub code: i32,
+    #[prost(string, tag = "7")]
+    pub error_kind: ::prost::alloc::string::String,
 }
 /// Nested message and enum types in `Diagnostic`.
 pub mod diagnostic {
@@ -51,6 +61,14 @@ pub struct Schema {
     pub version: i64,
     #[prost(message, optional, tag = "2")]
     pub block: ::core::option::Option<Block>,
+    /// JSON-encoded array of example configurations
+    #[prost(bytes = "bytes", tag = "3")]
+    pub examples: ::prost::bytes::Bytes,
+    #[prost(string, tag = "4")]
+    pub category: ::prost::alloc::string::String,
+    /// JSON-encoded object for documentation tooling
+    #[prost(bytes = "bytes", tag = "5")]
+    pub metadata: ::prost::bytes::Bytes,
 }
 /// Block represents a group of attributes and nested blocks.
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -68,8 +86,8 @@ pub struct Attribute {
     #[prost(string, tag = "1")]
     pub name: ::prost::alloc::string::String,
     /// JSON-encoded AttributeType
-    #[prost(bytes = "vec", tag = "2")]
-    pub r#type: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "2")]
+    pub r#type: ::prost::bytes::Bytes,
     #[prost(bool, tag = "3")]
     pub required: bool,
     #[prost(bool, tag = "4")]
@@ -83,8 +101,16 @@ pub struct Attribute {
     #[prost(bool, tag = "8")]
     pub force_new: bool,
     /// JSON-encoded default value
-    #[prost(bytes = "vec", tag = "9")]
-    pub default_value: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "9")]
+    pub default_value: ::prost::bytes::Bytes,
+    /// JSON-encoded array of example values
+    #[prost(bytes = "bytes", tag = "10")]
+    pub examples: ::prost::bytes::Bytes,
+    #[prost(string, tag = "11")]
+    pub category: ::prost::alloc::string::String,
+    /// JSON-encoded object for documentation tooling
+    #[prost(bytes = "bytes", tag = "12")]
+    pub metadata: ::prost::bytes::Bytes,
 }
 /// NestedBlock describes a nested block type.
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -99,6 +125,13 @@ pub struct NestedBlock {
     pub min_items: i32,
     #[prost(int32, tag = "5")]
     pub max_items: i32,
+    /// Any change inside the block forces replacement. Attributes inside the
+    /// block also carry force_new, so older clients see the same behavior.
+    #[prost(bool, tag = "6")]
+    pub force_new: bool,
+    /// Every attribute inside the block is sensitive (and marked as such).
+    #[prost(bool, tag = "7")]
+    pub sensitive: bool,
 }
 /// Nested message and enum types in `NestedBlock`.
 pub mod nested_block {
@@ -138,6 +171,20 @@ pub mod nested_block {
         }
     }
 }
+/// ClientCapabilities lists optional behaviors supported by the calling core,
+/// so providers can use them only when they are understood.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ClientCapabilities {
+    /// Core accepts deferred actions
+    #[prost(bool, tag = "1")]
+    pub deferral_allowed: bool,
+    /// Core shows warning diagnostics from apply operations
+    #[prost(bool, tag = "2")]
+    pub warnings_on_apply: bool,
+    /// Core displays streamed progress updates
+    #[prost(bool, tag = "3")]
+    pub streamed_progress: bool,
+}
 #[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct GetMetadataRequest {}
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -152,12 +199,51 @@ pub struct GetMetadataResponse {
     pub data_sources: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
     #[prost(message, repeated, tag = "4")]
     pub diagnostics: ::prost::alloc::vec::Vec<Diagnostic>,
+    /// Declared import ID formats by resource type
+    #[prost(btree_map = "string, message", tag = "5")]
+    pub import_formats:
+        ::prost::alloc::collections::BTreeMap<::prost::alloc::string::String, ImportFormats>,
+    /// Maximum concurrent operations by resource type
+    #[prost(btree_map = "string, uint32", tag = "6")]
+    pub max_parallelism: ::prost::alloc::collections::BTreeMap<::prost::alloc::string::String, u32>,
+    /// Suggested minimum seconds between refreshes by resource type
+    #[prost(btree_map = "string, uint64", tag = "7")]
+    pub refresh_intervals:
+        ::prost::alloc::collections::BTreeMap<::prost::alloc::string::String, u64>,
+}
+/// ImportFormats lists the ID patterns a resource can be imported by, such as
+/// "{zone_id}/{record_id}".
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ImportFormats {
+    #[prost(string, repeated, tag = "1")]
+    pub formats: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct ServerCapabilities {
     /// Provider supports planning destroy operations
     #[prost(bool, tag = "1")]
     pub plan_destroy: bool,
+    /// Provider supports moving state between resource types
+    #[prost(bool, tag = "2")]
+    pub move_state: bool,
+    /// Provider supports importing existing infrastructure
+    #[prost(bool, tag = "3")]
+    pub import: bool,
+    /// Provider supports provider functions
+    #[prost(bool, tag = "4")]
+    pub functions: bool,
+    /// Provider supports ephemeral resources
+    #[prost(bool, tag = "5")]
+    pub ephemeral_resources: bool,
+    /// Provider streams progress events during apply
+    #[prost(bool, tag = "6")]
+    pub streaming_progress: bool,
+    /// Provider supports applying several changes in one call
+    #[prost(bool, tag = "7")]
+    pub batch_apply: bool,
+    /// Provider returns resource identities alongside state
+    #[prost(bool, tag = "8")]
+    pub identity: bool,
 }
 #[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct GetSchemaRequest {
@@ -172,18 +258,75 @@ pub struct GetSchemaResponse {
     pub server_protocol_version: u32,
     #[prost(message, optional, tag = "2")]
     pub provider: ::core::option::Option<Schema>,
-    #[prost(map = "string, message", tag = "3")]
-    pub resources: ::std::collections::HashMap<::prost::alloc::string::String, Schema>,
-    #[prost(map = "string, message", tag = "4")]
-    pub data_sources: ::std::collections::HashMap<::prost::alloc::string::String, Schema>,
+    #[prost(btree_map = "string, message", tag = "3")]
+    pub resources: ::prost::alloc::collections::BTreeMap<::prost::alloc::string::String, Schema>,
+    #[prost(btree_map = "string, message", tag = "4")]
+    pub data_sources: ::prost::alloc::collections::BTreeMap<::prost::alloc::string::String, Schema>,
     #[prost(message, repeated, tag = "5")]
     pub diagnostics: ::prost::alloc::vec::Vec<Diagnostic>,
+    #[prost(btree_map = "string, message", tag = "6")]
+    pub functions: ::prost::alloc::collections::BTreeMap<::prost::alloc::string::String, Function>,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct StreamSchemaRequest {
+    /// Protocol version of the calling client
+    #[prost(uint32, tag = "1")]
+    pub client_protocol_version: u32,
+    /// Target encoded size of each chunk (0 = provider default)
+    #[prost(uint32, tag = "2")]
+    pub max_chunk_bytes: u32,
+    /// Core can decompress zstd-compressed chunks
+    #[prost(bool, tag = "3")]
+    pub accept_zstd: bool,
+}
+/// SchemaChunk carries part of a GetSchemaResponse. The first chunk holds the
+/// protocol version, provider schema, functions and diagnostics; the others
+/// hold resource and data source schemas. Core merges the maps of every chunk.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SchemaChunk {
+    /// Set unless the chunk is compressed
+    #[prost(message, optional, tag = "1")]
+    pub schema: ::core::option::Option<GetSchemaResponse>,
+    /// zstd-compressed encoding of the GetSchemaResponse part
+    #[prost(bytes = "bytes", tag = "2")]
+    pub zstd_schema: ::prost::bytes::Bytes,
+}
+/// Function describes the signature of a provider function, so core can
+/// type-check calls at plan time.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Function {
+    #[prost(message, repeated, tag = "1")]
+    pub parameters: ::prost::alloc::vec::Vec<FunctionParameter>,
+    /// Accepts any number of trailing arguments of this type.
+    #[prost(message, optional, tag = "2")]
+    pub variadic_parameter: ::core::option::Option<FunctionParameter>,
+    /// JSON-encoded AttributeType
+    #[prost(bytes = "bytes", tag = "3")]
+    pub return_type: ::prost::bytes::Bytes,
+    #[prost(string, tag = "4")]
+    pub description: ::prost::alloc::string::String,
+    /// The same arguments always produce the same result, so core may evaluate
+    /// calls during planning.
+    #[prost(bool, tag = "5")]
+    pub deterministic: bool,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct FunctionParameter {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// JSON-encoded AttributeType
+    #[prost(bytes = "bytes", tag = "2")]
+    pub r#type: ::prost::bytes::Bytes,
+    #[prost(string, tag = "3")]
+    pub description: ::prost::alloc::string::String,
+    #[prost(bool, tag = "4")]
+    pub allow_null: bool,
 }
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct ValidateProviderConfigRequest {
     /// JSON-encoded provider configuration
-    #[prost(bytes = "vec", tag = "1")]
-    pub config: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "1")]
+    pub config: ::prost::bytes::Bytes,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ValidateProviderConfigResponse {
@@ -193,8 +336,10 @@ pub struct ValidateProviderConfigResponse {
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct ConfigureRequest {
     /// JSON-encoded provider configuration
-    #[prost(bytes = "vec", tag = "1")]
-    pub config: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "1")]
+    pub config: ::prost::bytes::Bytes,
+    #[prost(message, optional, tag = "2")]
+    pub client_capabilities: ::core::option::Option<ClientCapabilities>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ConfigureResponse {
@@ -209,12 +354,19 @@ pub struct StopResponse {
     pub error: ::prost::alloc::string::String,
 }
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct EndSessionRequest {
+    #[prost(string, tag = "1")]
+    pub session_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct EndSessionResponse {}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct ValidateResourceConfigRequest {
     #[prost(string, tag = "1")]
     pub resource_type: ::prost::alloc::string::String,
     /// JSON-encoded resource configuration
-    #[prost(bytes = "vec", tag = "2")]
-    pub config: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "2")]
+    pub config: ::prost::bytes::Bytes,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ValidateResourceConfigResponse {
@@ -229,14 +381,14 @@ pub struct UpgradeResourceStateRequest {
     #[prost(int64, tag = "2")]
     pub version: i64,
     /// JSON-encoded state from older version
-    #[prost(bytes = "vec", tag = "3")]
-    pub raw_state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "3")]
+    pub raw_state: ::prost::bytes::Bytes,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct UpgradeResourceStateResponse {
     /// JSON-encoded state in current schema
-    #[prost(bytes = "vec", tag = "1")]
-    pub upgraded_state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "1")]
+    pub upgraded_state: ::prost::bytes::Bytes,
     #[prost(message, repeated, tag = "2")]
     pub diagnostics: ::prost::alloc::vec::Vec<Diagnostic>,
 }
@@ -245,51 +397,152 @@ pub struct PlanRequest {
     #[prost(string, tag = "1")]
     pub resource_type: ::prost::alloc::string::String,
     /// JSON-encoded current state (empty if creating)
-    #[prost(bytes = "vec", tag = "2")]
-    pub prior_state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "2")]
+    pub prior_state: ::prost::bytes::Bytes,
     /// JSON-encoded desired state
-    #[prost(bytes = "vec", tag = "3")]
-    pub proposed_state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "3")]
+    pub proposed_state: ::prost::bytes::Bytes,
     /// JSON-encoded raw configuration
-    #[prost(bytes = "vec", tag = "4")]
-    pub config: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "4")]
+    pub config: ::prost::bytes::Bytes,
+    #[prost(message, optional, tag = "5")]
+    pub client_capabilities: ::core::option::Option<ClientCapabilities>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct PlanResponse {
     /// JSON-encoded planned state after apply
-    #[prost(bytes = "vec", tag = "1")]
-    pub planned_state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "1")]
+    pub planned_state: ::prost::bytes::Bytes,
     #[prost(message, repeated, tag = "2")]
     pub changes: ::prost::alloc::vec::Vec<AttributeChange>,
     #[prost(bool, tag = "3")]
     pub requires_replace: bool,
     #[prost(message, repeated, tag = "4")]
     pub diagnostics: ::prost::alloc::vec::Vec<Diagnostic>,
+    /// Why the resource must be replaced
+    #[prost(message, repeated, tag = "5")]
+    pub replace_reasons: ::prost::alloc::vec::Vec<ReplaceReason>,
+    /// Unset if the provider can't estimate
+    #[prost(message, optional, tag = "6")]
+    pub impact: ::core::option::Option<ImpactEstimate>,
 }
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct AttributeChange {
     #[prost(string, tag = "1")]
     pub path: ::prost::alloc::string::String,
     /// JSON-encoded value before
-    #[prost(bytes = "vec", tag = "2")]
-    pub before: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "2")]
+    pub before: ::prost::bytes::Bytes,
     /// JSON-encoded value after
-    #[prost(bytes = "vec", tag = "3")]
-    pub after: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "3")]
+    pub after: ::prost::bytes::Bytes,
+    #[prost(enumeration = "attribute_change::Action", tag = "4")]
+    pub action: i32,
+    /// Redact before and after when rendering the plan
+    #[prost(bool, tag = "5")]
+    pub sensitive: bool,
+    /// Free-form explanation shown alongside the change
+    #[prost(string, tag = "6")]
+    pub note: ::prost::alloc::string::String,
+}
+/// Nested message and enum types in `AttributeChange`.
+pub mod attribute_change {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Action {
+        /// Infer from before/after
+        Unspecified = 0,
+        Create = 1,
+        Update = 2,
+        Delete = 3,
+        /// The change forces replacement of the resource
+        Replace = 4,
+    }
+    impl Action {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Self::Unspecified => "ACTION_UNSPECIFIED",
+                Self::Create => "CREATE",
+                Self::Update => "UPDATE",
+                Self::Delete => "DELETE",
+                Self::Replace => "REPLACE",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "ACTION_UNSPECIFIED" => Some(Self::Unspecified),
+                "CREATE" => Some(Self::Create),
+                "UPDATE" => Some(Self::Update),
+                "DELETE" => Some(Self::Delete),
+                "REPLACE" => Some(Self::Replace),
+                _ => None,
+            }
+        }
+    }
+}
+/// ReplaceReason explains why a change cannot be applied in place.
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ReplaceReason {
+    /// Attribute or block whose change forces replacement
+    #[prost(string, tag = "1")]
+    pub path: ::prost::alloc::string::String,
+    /// e.g. "'engine_version' cannot be changed in place"
+    #[prost(string, tag = "2")]
+    pub reason: ::prost::alloc::string::String,
+}
+/// ImpactEstimate is the provider's estimate of a change's impact, shown per
+/// resource in plan output. Unset fields are unknown.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImpactEstimate {
+    #[prost(double, optional, tag = "1")]
+    pub monthly_cost: ::core::option::Option<f64>,
+    /// ISO 4217 code of monthly_cost
+    #[prost(string, tag = "2")]
+    pub currency: ::prost::alloc::string::String,
+    /// 0.0 (none) to 1.0 (irreversible data loss)
+    #[prost(double, optional, tag = "3")]
+    pub destructiveness: ::core::option::Option<f64>,
+    #[prost(uint64, optional, tag = "4")]
+    pub propagation_delay_secs: ::core::option::Option<u64>,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct PreApplyCheckRequest {
+    #[prost(string, tag = "1")]
+    pub resource_type: ::prost::alloc::string::String,
+    /// JSON-encoded planned state from Plan (null for a delete)
+    #[prost(bytes = "bytes", tag = "2")]
+    pub planned_state: ::prost::bytes::Bytes,
+    #[prost(message, optional, tag = "3")]
+    pub client_capabilities: ::core::option::Option<ClientCapabilities>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PreApplyCheckResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub diagnostics: ::prost::alloc::vec::Vec<Diagnostic>,
 }
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct CreateRequest {
     #[prost(string, tag = "1")]
     pub resource_type: ::prost::alloc::string::String,
     /// JSON-encoded planned state from Plan
-    #[prost(bytes = "vec", tag = "2")]
-    pub planned_state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "2")]
+    pub planned_state: ::prost::bytes::Bytes,
+    #[prost(message, optional, tag = "3")]
+    pub client_capabilities: ::core::option::Option<ClientCapabilities>,
+    /// JSON-encoded raw configuration, including write-only values
+    #[prost(bytes = "bytes", tag = "4")]
+    pub config: ::prost::bytes::Bytes,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CreateResponse {
     /// JSON-encoded new state
-    #[prost(bytes = "vec", tag = "1")]
-    pub state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "1")]
+    pub state: ::prost::bytes::Bytes,
     #[prost(message, repeated, tag = "2")]
     pub diagnostics: ::prost::alloc::vec::Vec<Diagnostic>,
 }
@@ -298,33 +551,43 @@ pub struct ReadRequest {
     #[prost(string, tag = "1")]
     pub resource_type: ::prost::alloc::string::String,
     /// JSON-encoded current state
-    #[prost(bytes = "vec", tag = "2")]
-    pub current_state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "2")]
+    pub current_state: ::prost::bytes::Bytes,
+    #[prost(message, optional, tag = "3")]
+    pub client_capabilities: ::core::option::Option<ClientCapabilities>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ReadResponse {
     /// JSON-encoded refreshed state
-    #[prost(bytes = "vec", tag = "1")]
-    pub state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "1")]
+    pub state: ::prost::bytes::Bytes,
     #[prost(message, repeated, tag = "2")]
     pub diagnostics: ::prost::alloc::vec::Vec<Diagnostic>,
+    /// How complete the state is
+    #[prost(enumeration = "ReadStatus", tag = "3")]
+    pub status: i32,
 }
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct UpdateRequest {
     #[prost(string, tag = "1")]
     pub resource_type: ::prost::alloc::string::String,
     /// JSON-encoded state before update
-    #[prost(bytes = "vec", tag = "2")]
-    pub prior_state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "2")]
+    pub prior_state: ::prost::bytes::Bytes,
     /// JSON-encoded planned state from Plan
-    #[prost(bytes = "vec", tag = "3")]
-    pub planned_state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "3")]
+    pub planned_state: ::prost::bytes::Bytes,
+    #[prost(message, optional, tag = "4")]
+    pub client_capabilities: ::core::option::Option<ClientCapabilities>,
+    /// JSON-encoded raw configuration, including write-only values
+    #[prost(bytes = "bytes", tag = "5")]
+    pub config: ::prost::bytes::Bytes,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct UpdateResponse {
     /// JSON-encoded new state
-    #[prost(bytes = "vec", tag = "1")]
-    pub state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "1")]
+    pub state: ::prost::bytes::Bytes,
     #[prost(message, repeated, tag = "2")]
     pub diagnostics: ::prost::alloc::vec::Vec<Diagnostic>,
 }
@@ -333,8 +596,13 @@ pub struct DeleteRequest {
     #[prost(string, tag = "1")]
     pub resource_type: ::prost::alloc::string::String,
     /// JSON-encoded current state
-    #[prost(bytes = "vec", tag = "2")]
-    pub current_state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "2")]
+    pub current_state: ::prost::bytes::Bytes,
+    #[prost(message, optional, tag = "3")]
+    pub client_capabilities: ::core::option::Option<ClientCapabilities>,
+    /// JSON-encoded raw configuration (empty if removed from config)
+    #[prost(bytes = "bytes", tag = "4")]
+    pub config: ::prost::bytes::Bytes,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DeleteResponse {
@@ -348,6 +616,8 @@ pub struct ImportResourceStateRequest {
     /// External ID to import
     #[prost(string, tag = "2")]
     pub id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "3")]
+    pub client_capabilities: ::core::option::Option<ClientCapabilities>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ImportResourceStateResponse {
@@ -361,16 +631,40 @@ pub struct ImportedResource {
     #[prost(string, tag = "1")]
     pub resource_type: ::prost::alloc::string::String,
     /// JSON-encoded imported state
-    #[prost(bytes = "vec", tag = "2")]
-    pub state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "2")]
+    pub state: ::prost::bytes::Bytes,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct BulkImportResourceStateRequest {
+    #[prost(string, tag = "1")]
+    pub resource_type: ::prost::alloc::string::String,
+    /// External IDs to import
+    #[prost(string, repeated, tag = "2")]
+    pub ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// JSON-encoded provider-defined query, instead of ids
+    #[prost(bytes = "bytes", tag = "3")]
+    pub query: ::prost::bytes::Bytes,
+    #[prost(message, optional, tag = "4")]
+    pub client_capabilities: ::core::option::Option<ClientCapabilities>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BulkImportResult {
+    /// The requested ID (empty for query results)
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub imported: ::prost::alloc::vec::Vec<ImportedResource>,
+    /// Why this ID failed; other IDs are unaffected
+    #[prost(message, repeated, tag = "3")]
+    pub diagnostics: ::prost::alloc::vec::Vec<Diagnostic>,
 }
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct ValidateDataSourceConfigRequest {
     #[prost(string, tag = "1")]
     pub data_source_type: ::prost::alloc::string::String,
     /// JSON-encoded data source configuration
-    #[prost(bytes = "vec", tag = "2")]
-    pub config: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "2")]
+    pub config: ::prost::bytes::Bytes,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ValidateDataSourceConfigResponse {
@@ -382,16 +676,81 @@ pub struct ReadDataSourceRequest {
     #[prost(string, tag = "1")]
     pub data_source_type: ::prost::alloc::string::String,
     /// JSON-encoded data source configuration
-    #[prost(bytes = "vec", tag = "2")]
-    pub config: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "2")]
+    pub config: ::prost::bytes::Bytes,
+    #[prost(message, optional, tag = "3")]
+    pub client_capabilities: ::core::option::Option<ClientCapabilities>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ReadDataSourceResponse {
     /// JSON-encoded data source state
-    #[prost(bytes = "vec", tag = "1")]
-    pub state: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "bytes", tag = "1")]
+    pub state: ::prost::bytes::Bytes,
     #[prost(message, repeated, tag = "2")]
     pub diagnostics: ::prost::alloc::vec::Vec<Diagnostic>,
+    /// How complete the state is
+    #[prost(enumeration = "ReadStatus", tag = "3")]
+    pub status: i32,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct EventsRequest {
+    /// Highest event sequence core has processed
+    #[prost(uint64, tag = "1")]
+    pub ack_sequence: u64,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ProviderEvent {
+    /// Increasing from 1 for the life of the provider process
+    #[prost(uint64, tag = "1")]
+    pub sequence: u64,
+    /// Event category, e.g. "quota" or "deprecation"
+    #[prost(string, tag = "2")]
+    pub kind: ::prost::alloc::string::String,
+    /// Severity, summary and detail shown to the user
+    #[prost(message, optional, tag = "3")]
+    pub diagnostic: ::core::option::Option<Diagnostic>,
+    /// Resource type the event concerns (empty if none)
+    #[prost(string, tag = "4")]
+    pub resource_type: ::prost::alloc::string::String,
+}
+/// ReadStatus marks best-effort read results. A stale or degraded read comes
+/// with a warning diagnostic explaining what couldn't be read. A tainted read
+/// asks core to plan a replacement of the resource instead of an update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ReadStatus {
+    /// The state reflects the remote object
+    Current = 0,
+    /// The state is from an earlier read
+    Stale = 1,
+    /// Parts of the state couldn't be read
+    Degraded = 2,
+    /// The resource must be replaced
+    Tainted = 3,
+}
+impl ReadStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Current => "READ_STATUS_CURRENT",
+            Self::Stale => "READ_STATUS_STALE",
+            Self::Degraded => "READ_STATUS_DEGRADED",
+            Self::Tainted => "READ_STATUS_TAINTED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "READ_STATUS_CURRENT" => Some(Self::Current),
+            "READ_STATUS_STALE" => Some(Self::Stale),
+            "READ_STATUS_DEGRADED" => Some(Self::Degraded),
+            "READ_STATUS_TAINTED" => Some(Self::Tainted),
+            _ => None,
+        }
+    }
 }
 /// Generated client implementations.
 pub mod provider_client {
@@ -520,6 +879,28 @@ pub mod provider_client {
                 .insert(GrpcMethod::new("hemmer.provider.v1.Provider", "GetSchema"));
             self.inner.unary(req, path, codec).await
         }
+        /// StreamSchema returns the same schema as GetSchema split across several
+        /// messages, for providers whose schema exceeds the gRPC message size limit.
+        pub async fn stream_schema(
+            &mut self,
+            request: impl tonic::IntoRequest<super::StreamSchemaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::SchemaChunk>>,
+            tonic::Status,
+        > {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/hemmer.provider.v1.Provider/StreamSchema");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "hemmer.provider.v1.Provider",
+                "StreamSchema",
+            ));
+            self.inner.server_streaming(req, path, codec).await
+        }
         /// ValidateProviderConfig validates the provider configuration.
         pub async fn validate_provider_config(
             &mut self,
@@ -573,6 +954,26 @@ pub mod provider_client {
                 .insert(GrpcMethod::new("hemmer.provider.v1.Provider", "Stop"));
             self.inner.unary(req, path, codec).await
         }
+        /// EndSession tells the provider that the plan or apply run named by a
+        /// session id is over. Core sends the id of the run in the
+        /// "hemmer-session-id" metadata of each request; reads cached for the
+        /// session are dropped when it ends.
+        pub async fn end_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::EndSessionRequest>,
+        ) -> std::result::Result<tonic::Response<super::EndSessionResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/hemmer.provider.v1.Provider/EndSession");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("hemmer.provider.v1.Provider", "EndSession"));
+            self.inner.unary(req, path, codec).await
+        }
         /// ValidateResourceConfig validates a resource's configuration.
         pub async fn validate_resource_config(
             &mut self,
@@ -630,6 +1031,26 @@ pub mod provider_client {
                 .insert(GrpcMethod::new("hemmer.provider.v1.Provider", "Plan"));
             self.inner.unary(req, path, codec).await
         }
+        /// PreApplyCheck lets the provider veto an approved plan just before apply
+        /// (e.g. refusing a public bucket ACL). Error diagnostics block the apply.
+        pub async fn pre_apply_check(
+            &mut self,
+            request: impl tonic::IntoRequest<super::PreApplyCheckRequest>,
+        ) -> std::result::Result<tonic::Response<super::PreApplyCheckResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/hemmer.provider.v1.Provider/PreApplyCheck");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "hemmer.provider.v1.Provider",
+                "PreApplyCheck",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
         /// Create creates a new resource.
         pub async fn create(
             &mut self,
@@ -710,6 +1131,30 @@ pub mod provider_client {
             ));
             self.inner.unary(req, path, codec).await
         }
+        /// BulkImportResourceState imports many existing resources in one call, by a
+        /// list of IDs or a provider-defined query (e.g. every record in a DNS zone),
+        /// streaming a result per ID or per resource found as each completes.
+        pub async fn bulk_import_resource_state(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BulkImportResourceStateRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::BulkImportResult>>,
+            tonic::Status,
+        > {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/hemmer.provider.v1.Provider/BulkImportResourceState",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "hemmer.provider.v1.Provider",
+                "BulkImportResourceState",
+            ));
+            self.inner.server_streaming(req, path, codec).await
+        }
         /// ValidateDataSourceConfig validates a data source's configuration.
         pub async fn validate_data_source_config(
             &mut self,
@@ -751,6 +1196,26 @@ pub mod provider_client {
             ));
             self.inner.unary(req, path, codec).await
         }
+        /// Events streams advisory events (quota warnings, deprecation notices) that
+        /// the provider raises outside of any request. Core acknowledges the events
+        /// it has processed; unacknowledged events are sent again on reconnect.
+        pub async fn events(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::EventsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::ProviderEvent>>,
+            tonic::Status,
+        > {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/hemmer.provider.v1.Provider/Events");
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("hemmer.provider.v1.Provider", "Events"));
+            self.inner.streaming(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -777,6 +1242,17 @@ pub mod provider_server {
             &self,
             request: tonic::Request<super::GetSchemaRequest>,
         ) -> std::result::Result<tonic::Response<super::GetSchemaResponse>, tonic::Status>;
+        /// Server streaming response type for the StreamSchema method.
+        type StreamSchemaStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::SchemaChunk, tonic::Status>,
+            > + std::marker::Send
+            + 'static;
+        /// StreamSchema returns the same schema as GetSchema split across several
+        /// messages, for providers whose schema exceeds the gRPC message size limit.
+        async fn stream_schema(
+            &self,
+            request: tonic::Request<super::StreamSchemaRequest>,
+        ) -> std::result::Result<tonic::Response<Self::StreamSchemaStream>, tonic::Status>;
         /// ValidateProviderConfig validates the provider configuration.
         async fn validate_provider_config(
             &self,
@@ -795,6 +1271,14 @@ pub mod provider_server {
             &self,
             request: tonic::Request<super::StopRequest>,
         ) -> std::result::Result<tonic::Response<super::StopResponse>, tonic::Status>;
+        /// EndSession tells the provider that the plan or apply run named by a
+        /// session id is over. Core sends the id of the run in the
+        /// "hemmer-session-id" metadata of each request; reads cached for the
+        /// session are dropped when it ends.
+        async fn end_session(
+            &self,
+            request: tonic::Request<super::EndSessionRequest>,
+        ) -> std::result::Result<tonic::Response<super::EndSessionResponse>, tonic::Status>;
         /// ValidateResourceConfig validates a resource's configuration.
         async fn validate_resource_config(
             &self,
@@ -813,6 +1297,12 @@ pub mod provider_server {
             &self,
             request: tonic::Request<super::PlanRequest>,
         ) -> std::result::Result<tonic::Response<super::PlanResponse>, tonic::Status>;
+        /// PreApplyCheck lets the provider veto an approved plan just before apply
+        /// (e.g. refusing a public bucket ACL). Error diagnostics block the apply.
+        async fn pre_apply_check(
+            &self,
+            request: tonic::Request<super::PreApplyCheckRequest>,
+        ) -> std::result::Result<tonic::Response<super::PreApplyCheckResponse>, tonic::Status>;
         /// Create creates a new resource.
         async fn create(
             &self,
@@ -838,6 +1328,18 @@ pub mod provider_server {
             &self,
             request: tonic::Request<super::ImportResourceStateRequest>,
         ) -> std::result::Result<tonic::Response<super::ImportResourceStateResponse>, tonic::Status>;
+        /// Server streaming response type for the BulkImportResourceState method.
+        type BulkImportResourceStateStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::BulkImportResult, tonic::Status>,
+            > + std::marker::Send
+            + 'static;
+        /// BulkImportResourceState imports many existing resources in one call, by a
+        /// list of IDs or a provider-defined query (e.g. every record in a DNS zone),
+        /// streaming a result per ID or per resource found as each completes.
+        async fn bulk_import_resource_state(
+            &self,
+            request: tonic::Request<super::BulkImportResourceStateRequest>,
+        ) -> std::result::Result<tonic::Response<Self::BulkImportResourceStateStream>, tonic::Status>;
         /// ValidateDataSourceConfig validates a data source's configuration.
         async fn validate_data_source_config(
             &self,
@@ -851,6 +1353,18 @@ pub mod provider_server {
             &self,
             request: tonic::Request<super::ReadDataSourceRequest>,
         ) -> std::result::Result<tonic::Response<super::ReadDataSourceResponse>, tonic::Status>;
+        /// Server streaming response type for the Events method.
+        type EventsStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::ProviderEvent, tonic::Status>,
+            > + std::marker::Send
+            + 'static;
+        /// Events streams advisory events (quota warnings, deprecation notices) that
+        /// the provider raises outside of any request. Core acknowledges the events
+        /// it has processed; unacknowledged events are sent again on reconnect.
+        async fn events(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::EventsRequest>>,
+        ) -> std::result::Result<tonic::Response<Self::EventsStream>, tonic::Status>;
     }
     /// The Provider service defines all RPCs for a Hemmer provider.
     #[derive(Debug)]
@@ -1002,6 +1516,50 @@ pub mod provider_server {
                     };
                     Box::pin(fut)
                 },
+                "/hemmer.provider.v1.Provider/StreamSchema" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamSchemaSvc<T: Provider>(pub Arc<T>);
+                    impl<T: Provider>
+                        tonic::server::ServerStreamingService<super::StreamSchemaRequest>
+                        for StreamSchemaSvc<T>
+                    {
+                        type Response = super::SchemaChunk;
+                        type ResponseStream = T::StreamSchemaStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::StreamSchemaRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Provider>::stream_schema(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = StreamSchemaSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                },
                 "/hemmer.provider.v1.Provider/ValidateProviderConfig" => {
                     #[allow(non_camel_case_types)]
                     struct ValidateProviderConfigSvc<T: Provider>(pub Arc<T>);
@@ -1119,6 +1677,44 @@ pub mod provider_server {
                     };
                     Box::pin(fut)
                 },
+                "/hemmer.provider.v1.Provider/EndSession" => {
+                    #[allow(non_camel_case_types)]
+                    struct EndSessionSvc<T: Provider>(pub Arc<T>);
+                    impl<T: Provider> tonic::server::UnaryService<super::EndSessionRequest> for EndSessionSvc<T> {
+                        type Response = super::EndSessionResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::EndSessionRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut =
+                                async move { <T as Provider>::end_session(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = EndSessionSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                },
                 "/hemmer.provider.v1.Provider/ValidateResourceConfig" => {
                     #[allow(non_camel_case_types)]
                     struct ValidateResourceConfigSvc<T: Provider>(pub Arc<T>);
@@ -1240,6 +1836,45 @@ pub mod provider_server {
                     };
                     Box::pin(fut)
                 },
+                "/hemmer.provider.v1.Provider/PreApplyCheck" => {
+                    #[allow(non_camel_case_types)]
+                    struct PreApplyCheckSvc<T: Provider>(pub Arc<T>);
+                    impl<T: Provider> tonic::server::UnaryService<super::PreApplyCheckRequest> for PreApplyCheckSvc<T> {
+                        type Response = super::PreApplyCheckResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PreApplyCheckRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Provider>::pre_apply_check(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = PreApplyCheckSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                },
                 "/hemmer.provider.v1.Provider/Create" => {
                     #[allow(non_camel_case_types)]
                     struct CreateSvc<T: Provider>(pub Arc<T>);
@@ -1429,6 +2064,50 @@ pub mod provider_server {
                     };
                     Box::pin(fut)
                 },
+                "/hemmer.provider.v1.Provider/BulkImportResourceState" => {
+                    #[allow(non_camel_case_types)]
+                    struct BulkImportResourceStateSvc<T: Provider>(pub Arc<T>);
+                    impl<T: Provider>
+                        tonic::server::ServerStreamingService<super::BulkImportResourceStateRequest>
+                        for BulkImportResourceStateSvc<T>
+                    {
+                        type Response = super::BulkImportResult;
+                        type ResponseStream = T::BulkImportResourceStateStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BulkImportResourceStateRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Provider>::bulk_import_resource_state(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = BulkImportResourceStateSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                },
                 "/hemmer.provider.v1.Provider/ValidateDataSourceConfig" => {
                     #[allow(non_camel_case_types)]
                     struct ValidateDataSourceConfigSvc<T: Provider>(pub Arc<T>);
@@ -1512,6 +2191,45 @@ pub mod provider_server {
                     };
                     Box::pin(fut)
                 },
+                "/hemmer.provider.v1.Provider/Events" => {
+                    #[allow(non_camel_case_types)]
+                    struct EventsSvc<T: Provider>(pub Arc<T>);
+                    impl<T: Provider> tonic::server::StreamingService<super::EventsRequest> for EventsSvc<T> {
+                        type Response = super::ProviderEvent;
+                        type ResponseStream = T::EventsStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::EventsRequest>>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move { <T as Provider>::events(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = EventsSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                },
                 _ => Box::pin(async move {
                     let mut response = http::Response::new(tonic::body::Body::default());
                     let headers = response.headers_mut();
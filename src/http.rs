@@ -0,0 +1,521 @@
+//! Shared HTTP client for REST providers (feature: `http`).
+//!
+//! [`HttpClient`] wraps a [`reqwest::Client`] set up the way every REST
+//! provider needs it:
+//!
+//! - A standard `hemmer-provider/<name> <version>` user agent ([`user_agent`])
+//! - Retries with the SDK [`RetryPolicy`] for connection failures, timeouts and
+//!   429, 502, 503 and 504 responses, honoring `Retry-After`
+//! - Proxies from `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`, or set explicitly
+//! - Extra root certificates and client certificates for private endpoints
+//!
+//! [`error_for_status`] turns error responses into the matching
+//! [`ProviderError`], so `404` becomes [`ProviderError::NotFound`] and so on.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use hemmer_provider_sdk::http::{error_for_status, HttpClient};
+//! use hemmer_provider_sdk::retry::RetryPolicy;
+//!
+//! # async fn example() -> Result<(), hemmer_provider_sdk::ProviderError> {
+//! let client = HttpClient::builder("example", env!("CARGO_PKG_VERSION"))
+//!     .with_timeout(Duration::from_secs(30))
+//!     .with_retry_policy(RetryPolicy::new().with_max_attempts(5))
+//!     .build()?;
+//!
+//! let response = client
+//!     .send(client.get("https://api.example.com/v1/servers/s-1"))
+//!     .await?;
+//! let server: serde_json::Value = error_for_status(response)
+//!     .await?
+//!     .json()
+//!     .await
+//!     .map_err(|e| hemmer_provider_sdk::ProviderError::Sdk(e.to_string()))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requests with methods that are not idempotent (`POST`, `PATCH`) are only
+//! retried when the server cannot have acted on them: connection failures,
+//! `429` and `503`. Requests with streaming bodies can't be replayed and are
+//! sent once.
+
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use reqwest::{Certificate, Identity, Method, Proxy, RequestBuilder, Response, StatusCode};
+use tracing::debug;
+
+use crate::error::ProviderError;
+use crate::retry::{sleep_unless_cancelled, RetryPolicy};
+
+/// Longest response body included in an error message, in bytes.
+const MAX_ERROR_BODY: usize = 512;
+
+/// The standard user agent for a provider: `hemmer-provider/<name> <version>`.
+pub fn user_agent(name: &str, version: &str) -> String {
+    format!("hemmer-provider/{} {}", name, version)
+}
+
+/// Builds an [`HttpClient`].
+pub struct HttpClientBuilder {
+    user_agent: String,
+    retry_policy: RetryPolicy,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<String>,
+    no_proxy: bool,
+    root_certificates: Vec<Vec<u8>>,
+    identity: Option<Vec<u8>>,
+    accept_invalid_certs: bool,
+    default_headers: Vec<(String, String)>,
+    resolve: Vec<(String, SocketAddr)>,
+}
+
+impl HttpClientBuilder {
+    /// Start a client for the provider `name` at `version`.
+    pub fn new(name: &str, version: &str) -> Self {
+        Self {
+            user_agent: user_agent(name, version),
+            retry_policy: RetryPolicy::new(),
+            timeout: None,
+            connect_timeout: Some(Duration::from_secs(10)),
+            proxy: None,
+            no_proxy: false,
+            root_certificates: Vec::new(),
+            identity: None,
+            accept_invalid_certs: false,
+            default_headers: Vec::new(),
+            resolve: Vec::new(),
+        }
+    }
+
+    /// Set the retry policy. Default: [`RetryPolicy::new`].
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Set the timeout for each attempt, from connecting to reading the body.
+    /// Default: none.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the timeout for connecting. Default: 10 seconds.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Send all requests through this proxy URL instead of the one from the
+    /// environment.
+    pub fn with_proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Ignore proxy environment variables and connect directly.
+    pub fn without_proxy(mut self) -> Self {
+        self.no_proxy = true;
+        self
+    }
+
+    /// Trust the PEM-encoded root certificates in `pem` (e.g. a private CA
+    /// bundle) in addition to the built-in ones.
+    pub fn with_root_certificate_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.root_certificates.push(pem.into());
+        self
+    }
+
+    /// Authenticate with a client certificate: a PEM bundle holding the
+    /// certificate chain and the private key.
+    pub fn with_client_identity_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.identity = Some(pem.into());
+        self
+    }
+
+    /// Accept invalid TLS certificates. Only for testing against local
+    /// endpoints with self-signed certificates.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Send a header with every request.
+    pub fn with_default_header(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.default_headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Connect to `addr` for requests to `domain`, e.g. to reach an API
+    /// through an SSH tunnel. TLS and the `Host` header still use `domain`.
+    /// A port in the URL takes precedence over the port of `addr`.
+    pub fn with_resolve(mut self, domain: impl Into<String>, addr: SocketAddr) -> Self {
+        self.resolve.push((domain.into(), addr));
+        self
+    }
+
+    /// Build the client.
+    ///
+    /// Fails with [`ProviderError::Configuration`] if a certificate, proxy URL
+    /// or header is invalid.
+    pub fn build(self) -> Result<HttpClient, ProviderError> {
+        let invalid = |what: &str, e: &dyn fmt::Display| {
+            ProviderError::Configuration(format!("Invalid {}: {}", what, e))
+        };
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.default_headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| invalid(&format!("header name '{}'", name), &e))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| invalid(&format!("value for header '{}'", name), &e))?;
+            headers.insert(name, value);
+        }
+
+        let mut builder = reqwest::Client::builder()
+            .user_agent(&self.user_agent)
+            .default_headers(headers)
+            .danger_accept_invalid_certs(self.accept_invalid_certs);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        for (domain, addr) in &self.resolve {
+            builder = builder.resolve(domain, *addr);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if self.no_proxy {
+            builder = builder.no_proxy();
+        }
+        if let Some(url) = &self.proxy {
+            let proxy = Proxy::all(url).map_err(|e| invalid("proxy URL", &e))?;
+            builder = builder.proxy(proxy);
+        }
+        for pem in &self.root_certificates {
+            let certificates =
+                Certificate::from_pem_bundle(pem).map_err(|e| invalid("root certificate", &e))?;
+            if certificates.is_empty() {
+                return Err(invalid("root certificate", &"no certificate in PEM"));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        if let Some(pem) = &self.identity {
+            let identity = Identity::from_pem(pem).map_err(|e| invalid("client identity", &e))?;
+            builder = builder.identity(identity);
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| invalid("HTTP client configuration", &e))?;
+        Ok(HttpClient {
+            client,
+            retry_policy: self.retry_policy,
+            user_agent: self.user_agent,
+        })
+    }
+}
+
+impl fmt::Debug for HttpClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpClientBuilder")
+            .field("user_agent", &self.user_agent)
+            .field("retry_policy", &self.retry_policy)
+            .field("timeout", &self.timeout)
+            .field("proxy", &self.proxy)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A [`reqwest::Client`] with the SDK's retry policy and user agent.
+///
+/// Cloning is cheap and shares the connection pool, so build one client in
+/// `configure` and store it as provider data.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    retry_policy: RetryPolicy,
+    user_agent: String,
+}
+
+impl HttpClient {
+    /// Start building a client for the provider `name` at `version`.
+    pub fn builder(name: &str, version: &str) -> HttpClientBuilder {
+        HttpClientBuilder::new(name, version)
+    }
+
+    /// The underlying client, for requests that should not be retried.
+    pub fn inner(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// The retry policy used by [`send`](Self::send).
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// The user agent sent with every request.
+    pub fn user_agent(&self) -> &str {
+        &self.user_agent
+    }
+
+    /// Start a request.
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    /// Start a `GET` request.
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Start a `POST` request.
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Start a `PUT` request.
+    pub fn put(&self, url: &str) -> RequestBuilder {
+        self.client.put(url)
+    }
+
+    /// Start a `PATCH` request.
+    pub fn patch(&self, url: &str) -> RequestBuilder {
+        self.client.patch(url)
+    }
+
+    /// Start a `DELETE` request.
+    pub fn delete(&self, url: &str) -> RequestBuilder {
+        self.client.delete(url)
+    }
+
+    /// Send a request, retrying it according to the [`RetryPolicy`].
+    ///
+    /// Returns the last response, whatever its status; pass it to
+    /// [`error_for_status`] to turn error statuses into [`ProviderError`]s.
+    /// Fails if the request can't be sent at all.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, ProviderError> {
+        let request = request
+            .build()
+            .map_err(|e| ProviderError::InvalidRequest(format!("Invalid HTTP request: {}", e)))?;
+        let idempotent = is_idempotent(request.method());
+
+        let mut attempt = 1;
+        let mut next = Some(request);
+        loop {
+            let request = next.take().expect("request is set for every attempt");
+            next = request.try_clone();
+            let (method, url) = (request.method().clone(), request.url().clone());
+            let result = self.client.execute(request).await;
+
+            let retry = match &result {
+                Ok(response) => retry_status(response.status(), idempotent)
+                    .then(|| retry_after(response))
+                    .map(|after| after.unwrap_or_else(|| self.retry_policy.backoff(attempt))),
+                Err(e) if e.is_connect() || (idempotent && e.is_timeout()) => {
+                    Some(self.retry_policy.backoff(attempt))
+                },
+                Err(_) => None,
+            };
+            match retry {
+                Some(wait) if next.is_some() && self.retry_policy.should_retry(attempt) => {
+                    let wait = wait.min(self.retry_policy.max_backoff);
+                    debug!(%method, %url, attempt, ?wait, "Retrying HTTP request");
+                    if !sleep_unless_cancelled(wait).await {
+                        return result.map_err(request_error);
+                    }
+                    attempt += 1;
+                },
+                _ => return result.map_err(request_error),
+            }
+        }
+    }
+}
+
+/// Turn an error response into the matching [`ProviderError`], with the
+/// response body in the message. Other responses are returned unchanged.
+///
+/// | Status | Error |
+/// |--------|-------|
+/// | 400, 422 | [`Validation`](ProviderError::Validation) |
+/// | 401, 403 | [`PermissionDenied`](ProviderError::PermissionDenied) |
+/// | 404, 410 | [`NotFound`](ProviderError::NotFound) |
+/// | 409 | [`AlreadyExists`](ProviderError::AlreadyExists) |
+/// | 412 | [`FailedPrecondition`](ProviderError::FailedPrecondition) |
+/// | 429 | [`ResourceExhausted`](ProviderError::ResourceExhausted) |
+/// | 408, 504 | [`DeadlineExceeded`](ProviderError::DeadlineExceeded) |
+/// | 501 | [`Unimplemented`](ProviderError::Unimplemented) |
+/// | other 5xx | [`Unavailable`](ProviderError::Unavailable) |
+/// | other 4xx | [`InvalidRequest`](ProviderError::InvalidRequest) |
+pub async fn error_for_status(response: Response) -> Result<Response, ProviderError> {
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return Ok(response);
+    }
+    let url = response.url().to_string();
+    let body = response.text().await.unwrap_or_default();
+    let body = body.trim();
+    let message = if body.is_empty() {
+        format!("{} returned {}", url, status)
+    } else {
+        let mut end = body.len().min(MAX_ERROR_BODY);
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{} returned {}: {}", url, status, &body[..end])
+    };
+    Err(match status.as_u16() {
+        400 | 422 => ProviderError::Validation(message),
+        401 | 403 => ProviderError::PermissionDenied(message),
+        404 | 410 => ProviderError::NotFound(message),
+        409 => ProviderError::AlreadyExists(message),
+        412 => ProviderError::FailedPrecondition(message),
+        429 => ProviderError::ResourceExhausted(message),
+        408 | 504 => ProviderError::DeadlineExceeded(message),
+        501 => ProviderError::Unimplemented(message),
+        500..=599 => ProviderError::Unavailable(message),
+        _ => ProviderError::InvalidRequest(message),
+    })
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    !matches!(*method, Method::POST | Method::PATCH)
+}
+
+/// Whether a response with `status` is worth retrying.
+fn retry_status(status: StatusCode, idempotent: bool) -> bool {
+    match status {
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => true,
+        StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => idempotent,
+        _ => false,
+    }
+}
+
+/// The wait requested by a `Retry-After: <seconds>` header.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    seconds.trim().parse().ok().map(Duration::from_secs)
+}
+
+fn request_error(err: reqwest::Error) -> ProviderError {
+    if err.is_timeout() {
+        ProviderError::DeadlineExceeded(format!("HTTP request timed out: {}", err))
+    } else {
+        ProviderError::Unavailable(format!("HTTP request failed: {}", err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve one canned response per connection, recording each request head.
+    async fn serve(responses: Vec<&'static str>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0; 1024];
+                while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    head.extend_from_slice(&buf[..n]);
+                }
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&head).to_lowercase());
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn client() -> HttpClient {
+        HttpClient::builder("test", "1.2.3")
+            .with_retry_policy(RetryPolicy::new().with_initial_backoff(Duration::from_millis(1)))
+            .without_proxy()
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_send_retries_and_sets_user_agent() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nretry-after: 0\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok",
+        ])
+        .await;
+
+        let client = client();
+        let response = client.send(client.get(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "ok");
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].contains("user-agent: hemmer-provider/test 1.2.3\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_error_for_status() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 502 Bad Gateway\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            "HTTP/1.1 404 Not Found\r\ncontent-length: 14\r\nconnection: close\r\n\r\nno such server",
+        ])
+        .await;
+
+        // POST is not retried on 502
+        let client = client();
+        let response = client.send(client.post(&url).body("{}")).await.unwrap();
+        let err = error_for_status(response).await.unwrap_err();
+        assert!(matches!(err, ProviderError::Unavailable(_)));
+
+        let response = client.send(client.get(&url)).await.unwrap();
+        let err = error_for_status(response).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Resource not found: {}/ returned 404 Not Found: no such server",
+                url
+            )
+        );
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_build_rejects_invalid_settings() {
+        let err = HttpClient::builder("test", "1.0.0")
+            .with_root_certificate_pem("not a certificate")
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::Configuration(_)));
+
+        let err = HttpClient::builder("test", "1.0.0")
+            .with_default_header("bad header", "x")
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::Configuration(_)));
+    }
+}
@@ -0,0 +1,367 @@
+//! Localized diagnostics.
+//!
+//! Diagnostics are written in English. To show them to operators in another
+//! language, a diagnostic carries a [`Message`]: a key naming the message and
+//! the values substituted into it. A [`Catalog`] maps keys to templates for one
+//! locale, and [`ServeOptions::with_catalog`](crate::ServeOptions::with_catalog)
+//! makes the server render every diagnostic it returns with it. Diagnostics
+//! without a message, or whose key the catalog lacks, stay in English.
+//!
+//! The SDK's own diagnostics (schema validation, state validation and
+//! provider errors) already carry messages; [`SDK_MESSAGES`] lists their keys
+//! and English templates. Providers add keys for their own messages:
+//!
+//! ```
+//! use hemmer_provider_sdk::i18n::{Catalog, Message};
+//! use hemmer_provider_sdk::schema::Diagnostic;
+//!
+//! let diagnostic = Diagnostic::error("Bucket name 'logs' is taken")
+//!     .with_message(Message::new("bucket.name_taken").with_arg("name", "logs"));
+//!
+//! let catalog = Catalog::new("de")
+//!     .with_template("bucket.name_taken", "Der Bucket-Name '{name}' ist vergeben")
+//!     .with_template("missing_attribute", "Pflichtattribut '{path}' fehlt");
+//!
+//! assert_eq!(
+//!     catalog.localize(diagnostic).summary,
+//!     "Der Bucket-Name 'logs' ist vergeben"
+//! );
+//! assert_eq!(
+//!     catalog.localize(Diagnostic::missing_attribute("region")).summary,
+//!     "Pflichtattribut 'region' fehlt"
+//! );
+//! ```
+//!
+//! A message's summary template is found under its key, and its detail and
+//! suggestion templates under `<key>.detail` and `<key>.suggestion`.
+//! Templates name arguments in braces; unknown names are left as written.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ProviderError;
+use crate::schema::{Diagnostic, DiagnosticSeverity};
+
+/// Keys and English templates of the diagnostics emitted by the SDK.
+pub const SDK_MESSAGES: &[(&str, &str)] = &[
+    ("invalid_value", "Invalid value for '{path}'"),
+    ("invalid_value.detail", "Expected {expected}, got {got}"),
+    ("invalid_type", "Invalid type for attribute '{path}'"),
+    ("invalid_type.detail", "Expected {expected}, got {got}"),
+    (
+        "precision_loss",
+        "Value of attribute '{path}' may have lost precision",
+    ),
+    (
+        "precision_loss.detail",
+        "{value} arrived as a float beyond 2^53, where integers are rounded",
+    ),
+    (
+        "precision_loss.suggestion",
+        "Send large integers such as numeric IDs as JSON integers or strings",
+    ),
+    ("missing_attribute", "Missing required attribute '{path}'"),
+    (
+        "missing_attribute.detail",
+        "This attribute is required and must be provided",
+    ),
+    ("unsupported_attribute", "Unsupported attribute '{path}'"),
+    (
+        "unsupported_attribute.detail",
+        "This attribute is not defined in the schema",
+    ),
+    (
+        "conflicting_attributes",
+        "Conflicting attributes '{path}' and '{other}'",
+    ),
+    (
+        "conflicting_attributes.detail",
+        "Only one of these attributes may be set",
+    ),
+    (
+        "conflicting_attributes.suggestion",
+        "Remove '{path}' or '{other}'",
+    ),
+    ("expected_object", "Expected object"),
+    ("expected_object.detail", "Got {got}"),
+    ("missing_block", "Missing required block '{path}'"),
+    ("missing_block.detail", "At least one block is required"),
+    (
+        "block_required_items",
+        "Block '{path}' requires at least {min} item(s)",
+    ),
+    (
+        "block_min_items",
+        "Block '{path}' requires at least {min} item(s), got {got}",
+    ),
+    (
+        "block_max_items",
+        "Block '{path}' allows at most {max} item(s), got {got}",
+    ),
+    ("block_expected_list", "Expected list for block '{path}'"),
+    ("block_expected_list.detail", "Got {got}"),
+    ("block_expected_map", "Expected map for block '{path}'"),
+    ("block_expected_map.detail", "Got {got}"),
+    (
+        "state.configured_value_changed",
+        "Provider changed the configured value of '{path}'",
+    ),
+    (
+        "state.configured_value_changed.detail",
+        "Keep configured values as written (normalize when comparing instead), or every plan will show a diff",
+    ),
+    (
+        "state.value_not_chosen",
+        "Provider did not choose a value for '{path}'",
+    ),
+    (
+        "state.value_not_chosen.detail",
+        "Optional+computed attributes left unset in configuration must be set by the provider",
+    ),
+    (
+        "state.computed_not_set",
+        "Provider did not set computed attribute '{path}'",
+    ),
+    (
+        "state.computed_not_set.detail",
+        "Computed attributes that cannot be configured must be set in the returned state",
+    ),
+    ("error.not_found", "Resource not found: {message}"),
+    ("error.validation", "Validation error: {message}"),
+    ("error.sdk", "SDK error: {message}"),
+    ("error.configuration", "Configuration error: {message}"),
+    ("error.unknown_resource", "Unknown resource type: {message}"),
+    ("error.serialization", "Serialization error: {message}"),
+    ("error.transport", "Transport error: {message}"),
+    ("error.already_exists", "Resource already exists: {message}"),
+    ("error.permission_denied", "Permission denied: {message}"),
+    ("error.resource_exhausted", "Resource exhausted: {message}"),
+    ("error.unavailable", "Service unavailable: {message}"),
+    ("error.deadline_exceeded", "Deadline exceeded: {message}"),
+    ("error.failed_precondition", "Failed precondition: {message}"),
+    ("error.unimplemented", "Unimplemented: {message}"),
+    ("error.invalid_request", "Invalid request: {message}"),
+];
+
+/// Identifies a diagnostic's message for translation, with the values
+/// substituted into its templates.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Message {
+    /// The message key, e.g. `"missing_attribute"`.
+    pub key: String,
+    /// Values substituted for `{name}` in the templates.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub args: BTreeMap<String, String>,
+}
+
+impl Message {
+    /// Create a message with no arguments.
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            args: BTreeMap::new(),
+        }
+    }
+
+    /// Add an argument.
+    pub fn with_arg(mut self, name: impl Into<String>, value: impl fmt::Display) -> Self {
+        self.args.insert(name.into(), value.to_string());
+        self
+    }
+}
+
+/// Message templates for one locale.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Catalog {
+    locale: String,
+    templates: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// Create an empty catalog for a locale, e.g. `"de"` or `"ja-JP"`.
+    pub fn new(locale: impl Into<String>) -> Self {
+        Self {
+            locale: locale.into(),
+            templates: HashMap::new(),
+        }
+    }
+
+    /// Load templates from a JSON object of keys to templates, such as a
+    /// translation file shipped with the provider.
+    pub fn from_json(locale: impl Into<String>, json: &str) -> Result<Self, ProviderError> {
+        let templates: HashMap<String, String> = serde_json::from_str(json)?;
+        Ok(Self {
+            locale: locale.into(),
+            templates,
+        })
+    }
+
+    /// Add or replace a template.
+    pub fn with_template(mut self, key: impl Into<String>, template: impl Into<String>) -> Self {
+        self.templates.insert(key.into(), template.into());
+        self
+    }
+
+    /// Add the templates of another catalog, e.g. the provider's own messages
+    /// to a translation of [`SDK_MESSAGES`]. Its templates win.
+    pub fn merge(mut self, other: Catalog) -> Self {
+        self.templates.extend(other.templates);
+        self
+    }
+
+    /// The catalog's locale.
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Render the template for `key`, if the catalog has one.
+    pub fn format(&self, key: &str, args: &BTreeMap<String, String>) -> Option<String> {
+        self.templates.get(key).map(|t| render(t, args))
+    }
+
+    /// Translate a diagnostic's summary, detail and suggestion. Parts the
+    /// catalog has no template for are left unchanged.
+    pub fn localize(&self, mut diagnostic: Diagnostic) -> Diagnostic {
+        let Some(message) = &diagnostic.message else {
+            return diagnostic;
+        };
+        if let Some(summary) = self.format(&message.key, &message.args) {
+            diagnostic.summary = summary;
+        }
+        if let Some(detail) = self.format(&format!("{}.detail", message.key), &message.args) {
+            diagnostic.detail = Some(detail);
+        }
+        if let Some(suggestion) = self.format(&format!("{}.suggestion", message.key), &message.args)
+        {
+            diagnostic.suggestion = Some(suggestion);
+        }
+        diagnostic
+    }
+}
+
+fn render(template: &str, args: &BTreeMap<String, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after
+            .find('}')
+            .and_then(|end| Some((end, args.get(&after[..end])?)))
+        {
+            Some((end, value)) => {
+                out.push_str(value);
+                rest = &after[end + 1..];
+            },
+            None => {
+                out.push('{');
+                rest = after;
+            },
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn sdk_template(key: &str) -> Option<&'static str> {
+    SDK_MESSAGES
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, template)| *template)
+}
+
+/// A diagnostic for one of the SDK's own messages, rendered in English.
+pub(crate) fn sdk_diagnostic(severity: DiagnosticSeverity, message: Message) -> Diagnostic {
+    let part = |suffix: &str| sdk_template(&format!("{}{}", message.key, suffix));
+    let summary = part("")
+        .map(|t| render(t, &message.args))
+        .unwrap_or_default();
+    let mut diagnostic = match severity {
+        DiagnosticSeverity::Error => Diagnostic::error(summary),
+        DiagnosticSeverity::Warning => Diagnostic::warning(summary),
+    };
+    diagnostic.detail = part(".detail").map(|t| render(t, &message.args));
+    diagnostic.suggestion = part(".suggestion").map(|t| render(t, &message.args));
+    diagnostic.with_message(message)
+}
+
+/// The message of a provider error, keyed by its variant.
+pub(crate) fn error_message(err: &ProviderError) -> Message {
+    let message = match err {
+        ProviderError::Serialization(e) => e.to_string(),
+        ProviderError::Transport(e) => e.to_string(),
+        other => other.message().to_string(),
+    };
+    Message::new(format!("error.{}", err.kind())).with_arg("message", message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let args = Message::new("k")
+            .with_arg("path", "disk.0")
+            .with_arg("min", 2)
+            .args;
+        assert_eq!(
+            render("Block '{path}' needs {min} {unknown} {", &args),
+            "Block 'disk.0' needs 2 {unknown} {"
+        );
+    }
+
+    #[test]
+    fn test_sdk_messages_match_english_diagnostics() {
+        let d = Diagnostic::conflicting_attributes("password", "password_file");
+        assert_eq!(
+            d.summary,
+            "Conflicting attributes 'password' and 'password_file'"
+        );
+        assert_eq!(
+            d.suggestion.as_deref(),
+            Some("Remove 'password' or 'password_file'")
+        );
+        assert_eq!(d.message.as_ref().unwrap().key, "conflicting_attributes");
+
+        for err in [
+            ProviderError::NotFound("bucket".to_string()),
+            ProviderError::Serialization(serde_json::from_str::<u8>("x").unwrap_err()),
+            ProviderError::InvalidRequest("bad".to_string()),
+        ] {
+            let d = sdk_diagnostic(DiagnosticSeverity::Error, error_message(&err));
+            assert_eq!(d.summary, err.to_string());
+        }
+    }
+
+    #[test]
+    fn test_localize() {
+        let catalog = Catalog::from_json(
+            "fr",
+            r#"{"invalid_value": "Valeur invalide pour '{path}'", "invalid_value.detail": "{expected} attendu, {got} reçu"}"#,
+        )
+        .unwrap()
+        .merge(Catalog::new("fr").with_template("error.not_found", "Ressource introuvable : {message}"));
+        assert_eq!(catalog.locale(), "fr");
+
+        let d = catalog.localize(Diagnostic::invalid_value("size", 3, "a multiple of 8"));
+        assert_eq!(d.summary, "Valeur invalide pour 'size'");
+        assert_eq!(d.detail.as_deref(), Some("a multiple of 8 attendu, 3 reçu"));
+        assert_eq!(d.attribute.as_deref(), Some("size"));
+
+        let err = ProviderError::NotFound("b-1".to_string());
+        let d = catalog.localize(sdk_diagnostic(
+            DiagnosticSeverity::Error,
+            error_message(&err),
+        ));
+        assert_eq!(d.summary, "Ressource introuvable : b-1");
+
+        // No template: left in English
+        let d = catalog.localize(Diagnostic::missing_attribute("name"));
+        assert_eq!(d.summary, "Missing required attribute 'name'");
+        let plain = Diagnostic::warning("Deprecated");
+        assert_eq!(catalog.localize(plain.clone()), plain);
+    }
+}
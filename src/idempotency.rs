@@ -0,0 +1,270 @@
+//! Idempotency tokens for create and update operations.
+//!
+//! Core sends a unique key with every create and update in the
+//! [`IDEMPOTENCY_KEY_METADATA`](crate::IDEMPOTENCY_KEY_METADATA) request
+//! metadata, and the same key when it retries the RPC. The server derives a
+//! token from that key, so a retry carries the same token as the original,
+//! while two requests that only have the same payload (two instances with the
+//! same planned state) get different ones. Without a key, every call gets a
+//! token of its own. Providers
+//! read it from [`Context::idempotency_token`](crate::context::Context::idempotency_token)
+//! and pass it to upstream APIs that support idempotent requests (an
+//! [`IDEMPOTENCY_HEADER`], an AWS `ClientToken`, ...):
+//!
+//! ```ignore
+//! async fn create(&self, resource_type: &str, planned: Value) -> Result<Value, ProviderError> {
+//!     let mut request = self.client.post("/servers").json(&planned);
+//!     if let Some(token) = Context::current().idempotency_token() {
+//!         request = request.header(IDEMPOTENCY_HEADER, token);
+//!     }
+//!     // ...
+//! }
+//! ```
+//!
+//! The server also remembers successful results of keyed calls for a while
+//! (see [`ServeOptions::with_idempotency_ttl`](crate::ServeOptions::with_idempotency_ttl)):
+//! a retry that arrives while the original call is running waits for it, and a
+//! retry that arrives afterwards gets the same result without calling the
+//! provider again. A result is kept until its TTL expires, or until a delete
+//! removes the resource it describes. Failed calls are not remembered, so
+//! retrying them runs the operation again, and calls without a key are never
+//! deduplicated.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+use crate::error::ProviderError;
+
+/// Conventional HTTP header for idempotency keys.
+pub const IDEMPOTENCY_HEADER: &str = "Idempotency-Key";
+
+/// Derive a deterministic token from an operation and the parts identifying
+/// the request.
+///
+/// The token is a 32-character hex string (128-bit FNV-1a), stable across
+/// processes and SDK versions.
+pub fn derive_token(operation: &str, resource_type: &str, payloads: &[&[u8]]) -> String {
+    const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    let mut hash = OFFSET;
+    let parts = [operation.as_bytes(), resource_type.as_bytes()]
+        .into_iter()
+        .chain(payloads.iter().copied());
+    for part in parts {
+        // Length-prefix each part so ("ab", "c") and ("a", "bc") differ
+        for byte in (part.len() as u64).to_le_bytes().iter().chain(part) {
+            hash ^= *byte as u128;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    format!("{:032x}", hash)
+}
+
+type Slot = Arc<OnceCell<(Instant, Value)>>;
+
+fn in_flight(slot: &Slot) -> bool {
+    slot.get().is_none() && Arc::strong_count(slot) > 1
+}
+
+/// Remembers successful results by idempotency token for a TTL.
+///
+/// Cloning shares the same entries.
+#[derive(Clone)]
+pub struct IdempotencyStore {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<(String, String), Slot>>>,
+}
+
+impl IdempotencyStore {
+    /// Create a store that remembers results for `ttl`. A zero TTL disables it.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Run `operation` unless a call with the same token already succeeded
+    /// within the TTL; concurrent calls with the same token run it once.
+    pub async fn run<F>(
+        &self,
+        resource_type: &str,
+        token: &str,
+        operation: F,
+    ) -> Result<Value, ProviderError>
+    where
+        F: Future<Output = Result<Value, ProviderError>>,
+    {
+        if self.ttl.is_zero() {
+            return operation.await;
+        }
+
+        let slot = {
+            let mut entries = self.entries.lock().unwrap();
+            let ttl = self.ttl;
+            // Drop expired results and failed calls nobody is retrying
+            entries.retain(|_, slot| match slot.get() {
+                Some((stored_at, _)) => stored_at.elapsed() < ttl,
+                None => in_flight(slot),
+            });
+            let key = (resource_type.to_string(), token.to_string());
+            Arc::clone(entries.entry(key).or_default())
+        };
+
+        let mut called = false;
+        let (_, value) = slot
+            .get_or_try_init(|| {
+                called = true;
+                async { Ok::<_, ProviderError>((Instant::now(), operation.await?)) }
+            })
+            .await?;
+        if !called {
+            debug!(
+                token,
+                "Returning result of earlier request with the same idempotency token"
+            );
+        }
+        Ok(value.clone())
+    }
+
+    /// Forget the completed results for a resource type.
+    pub fn invalidate_type(&self, resource_type: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|(entry_type, _), slot| entry_type != resource_type || in_flight(slot));
+    }
+
+    /// Forget the completed results for a resource type that `is_resource`
+    /// matches, such as those describing a resource that was just deleted.
+    pub fn invalidate_resource(&self, resource_type: &str, is_resource: impl Fn(&Value) -> bool) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|(entry_type, _), slot| {
+                entry_type != resource_type
+                    || match slot.get() {
+                        Some((_, value)) => !is_resource(value),
+                        None => in_flight(slot),
+                    }
+            });
+    }
+
+    /// Number of remembered or in-flight tokens.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether no tokens are remembered or in flight.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Debug for IdempotencyStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdempotencyStore")
+            .field("ttl", &self.ttl)
+            .field("entries", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_derive_token() {
+        let a = derive_token("create", "server", &[br#"{"name":"a"}"#]);
+        assert_eq!(a.len(), 32);
+        assert_eq!(a, derive_token("create", "server", &[br#"{"name":"a"}"#]));
+        assert_ne!(a, derive_token("update", "server", &[br#"{"name":"a"}"#]));
+        assert_ne!(
+            derive_token("create", "server", &[b"ab", b"c"]),
+            derive_token("create", "server", &[b"a", b"bc"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retries_deduplicated() {
+        let store = IdempotencyStore::new(Duration::from_secs(60));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let create = || {
+            let calls = Arc::clone(&calls);
+            async move {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok(json!({ "id": n }))
+            }
+        };
+
+        let (a, b) = tokio::join!(
+            store.run("server", "t1", create()),
+            store.run("server", "t1", create())
+        );
+        assert_eq!(a.unwrap(), json!({"id": 0}));
+        assert_eq!(b.unwrap(), json!({"id": 0}));
+        assert_eq!(
+            store.run("server", "t1", create()).await.unwrap(),
+            json!({"id": 0})
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        assert_eq!(
+            store.run("server", "t2", create()).await.unwrap(),
+            json!({"id": 1})
+        );
+
+        // Parallel creates of the same type keep each other's results
+        assert_eq!(
+            store.run("server", "t1", create()).await.unwrap(),
+            json!({"id": 0})
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Deleting the resource t1 created forgets only its result
+        store.invalidate_resource("server", |state| state["id"] == 0);
+        assert_eq!(store.len(), 1);
+        assert_eq!(
+            store.run("server", "t1", create()).await.unwrap(),
+            json!({"id": 2})
+        );
+        store.invalidate_type("server");
+        assert!(store.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failures_not_remembered() {
+        let store = IdempotencyStore::new(Duration::from_secs(60));
+        let err = store
+            .run("server", "t1", async {
+                Err(ProviderError::Unavailable("try again".to_string()))
+            })
+            .await;
+        assert!(err.is_err());
+
+        let value = store
+            .run("server", "t1", async { Ok(json!("ok")) })
+            .await
+            .unwrap();
+        assert_eq!(value, "ok");
+
+        let disabled = IdempotencyStore::new(Duration::ZERO);
+        disabled
+            .run("server", "t1", async { Ok(json!(1)) })
+            .await
+            .unwrap();
+        assert!(disabled.is_empty());
+    }
+}
@@ -0,0 +1,240 @@
+//! Import ID formats.
+//!
+//! A resource declares the IDs it can be imported by with
+//! [`Schema::with_import_format`](crate::schema::Schema::with_import_format).
+//! A format is a pattern of literal text and `{name}` placeholders, such as
+//! `{zone_id}/{record_id}`. Formats are listed in
+//! [`ProviderMetadata::import_formats`](crate::ProviderMetadata::import_formats)
+//! so core can show them to users, and the server rejects IDs matching none of
+//! them before calling
+//! [`ProviderService::import_resource`](crate::ProviderService::import_resource),
+//! with an error naming the expected formats.
+//!
+//! In `import_resource`, [`Schema::parse_import_id`](crate::schema::Schema::parse_import_id)
+//! splits an ID into its named parts:
+//!
+//! ```
+//! use hemmer_provider_sdk::schema::{Attribute, Schema};
+//!
+//! let schema = Schema::v0()
+//!     .with_attribute("zone_id", Attribute::required_string())
+//!     .with_attribute("record_id", Attribute::computed_string())
+//!     .with_import_format("{zone_id}/{record_id}");
+//!
+//! let id = schema.parse_import_id("Z123/www").unwrap();
+//! assert_eq!(id.get("zone_id"), Some("Z123"));
+//! assert_eq!(id.get("record_id"), Some("www"));
+//!
+//! let err = schema.parse_import_id("www").unwrap_err();
+//! assert_eq!(
+//!     err.to_string(),
+//!     "Validation error: Invalid import ID 'www': expected '{zone_id}/{record_id}'"
+//! );
+//! ```
+//!
+//! Each placeholder matches a non-empty part up to the first occurrence of the
+//! literal text that follows it, and the last placeholder takes the rest of the
+//! ID, so `{zone_id}/{record_id}` splits `a/b/c` into `a` and `b/c`.
+
+use std::collections::BTreeMap;
+
+use crate::error::ProviderError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Field(String),
+}
+
+/// A parsed import ID pattern such as `{zone_id}/{record_id}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportFormat {
+    pattern: String,
+    parts: Vec<Part>,
+}
+
+impl ImportFormat {
+    /// Parse a pattern.
+    ///
+    /// Fails if a brace is unbalanced, a placeholder is empty or repeated, two
+    /// placeholders are adjacent (so the ID can't be split between them), or
+    /// there are no placeholders at all.
+    pub fn parse(pattern: &str) -> Result<Self, ProviderError> {
+        let invalid = |reason: &str| {
+            ProviderError::Sdk(format!("Invalid import format '{}': {}", pattern, reason))
+        };
+
+        let mut parts = Vec::new();
+        let mut rest = pattern;
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('{') {
+                let end = after.find('}').ok_or_else(|| invalid("unclosed '{'"))?;
+                let name = &after[..end];
+                if name.is_empty() || name.contains('{') {
+                    return Err(invalid("placeholders must be named, like {id}"));
+                }
+                if matches!(parts.last(), Some(Part::Field(_))) {
+                    return Err(invalid("placeholders must be separated by literal text"));
+                }
+                if parts.contains(&Part::Field(name.to_string())) {
+                    return Err(invalid(&format!("placeholder {{{}}} is repeated", name)));
+                }
+                parts.push(Part::Field(name.to_string()));
+                rest = &after[end + 1..];
+            } else {
+                let end = rest.find('{').unwrap_or(rest.len());
+                let literal = &rest[..end];
+                if literal.contains('}') {
+                    return Err(invalid("unmatched '}'"));
+                }
+                parts.push(Part::Literal(literal.to_string()));
+                rest = &rest[end..];
+            }
+        }
+
+        if !parts.iter().any(|p| matches!(p, Part::Field(_))) {
+            return Err(invalid("no placeholders"));
+        }
+        Ok(Self {
+            pattern: pattern.to_string(),
+            parts,
+        })
+    }
+
+    /// The pattern this format was parsed from.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// The placeholder names, in order.
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|p| match p {
+            Part::Field(name) => Some(name.as_str()),
+            Part::Literal(_) => None,
+        })
+    }
+
+    /// Split `id` into its named parts, or `None` if it doesn't match.
+    pub fn match_id(&self, id: &str) -> Option<ImportId> {
+        let mut parts = BTreeMap::new();
+        let mut rest = id;
+        for (i, part) in self.parts.iter().enumerate() {
+            match part {
+                Part::Literal(literal) => rest = rest.strip_prefix(literal.as_str())?,
+                Part::Field(name) => {
+                    let end = match self.parts.get(i + 1) {
+                        Some(Part::Literal(next)) => rest.find(next.as_str())?,
+                        _ => rest.len(),
+                    };
+                    if end == 0 {
+                        return None;
+                    }
+                    parts.insert(name.clone(), rest[..end].to_string());
+                    rest = &rest[end..];
+                },
+            }
+        }
+        rest.is_empty().then(|| ImportId {
+            id: id.to_string(),
+            parts,
+        })
+    }
+}
+
+/// An import ID split into the named parts of its format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportId {
+    id: String,
+    parts: BTreeMap<String, String>,
+}
+
+impl ImportId {
+    /// The ID as given by the user.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The value of a named part.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.parts.get(name).map(String::as_str)
+    }
+
+    /// All named parts.
+    pub fn parts(&self) -> &BTreeMap<String, String> {
+        &self.parts
+    }
+}
+
+/// Split `id` using the first of `formats` it matches.
+///
+/// With no formats, the whole ID is returned as the `id` part. An ID matching
+/// none of the formats is a [`ProviderError::Validation`] listing them.
+pub fn parse_import_id(formats: &[String], id: &str) -> Result<ImportId, ProviderError> {
+    if formats.is_empty() {
+        return Ok(ImportId {
+            id: id.to_string(),
+            parts: BTreeMap::from([("id".to_string(), id.to_string())]),
+        });
+    }
+    for pattern in formats {
+        if let Some(parsed) = ImportFormat::parse(pattern)?.match_id(id) {
+            return Ok(parsed);
+        }
+    }
+    let expected: Vec<_> = formats.iter().map(|f| format!("'{}'", f)).collect();
+    Err(ProviderError::Validation(format!(
+        "Invalid import ID '{}': expected {}",
+        id,
+        expected.join(" or ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_format() {
+        let format = ImportFormat::parse("{project}/zones/{zone}/{name}").unwrap();
+        assert_eq!(
+            format.fields().collect::<Vec<_>>(),
+            vec!["project", "zone", "name"]
+        );
+
+        let id = format.match_id("p1/zones/us-a/web/1").unwrap();
+        assert_eq!(id.get("project"), Some("p1"));
+        assert_eq!(id.get("zone"), Some("us-a"));
+        assert_eq!(id.get("name"), Some("web/1"));
+        assert_eq!(id.id(), "p1/zones/us-a/web/1");
+
+        assert!(format.match_id("p1/regions/us/web").is_none());
+        assert!(format.match_id("p1/zones//web").is_none());
+        assert!(format.match_id("p1/zones/us-a").is_none());
+
+        let suffixed = ImportFormat::parse("arn:{id}:end").unwrap();
+        assert_eq!(suffixed.match_id("arn:x:end").unwrap().get("id"), Some("x"));
+        assert!(suffixed.match_id("arn:x:end2").is_none());
+
+        for bad in ["{a", "a}", "{}", "{a}{b}", "{a}/{a}", "static"] {
+            assert!(ImportFormat::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_import_id() {
+        let formats = vec!["{zone}/{name}".to_string(), "{name}".to_string()];
+        assert_eq!(
+            parse_import_id(&formats, "a/b").unwrap().get("zone"),
+            Some("a")
+        );
+        assert_eq!(parse_import_id(&formats, "b").unwrap().get("zone"), None);
+
+        let err = parse_import_id(&formats[..1], "b").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Validation error: Invalid import ID 'b': expected '{zone}/{name}'"
+        );
+
+        assert_eq!(parse_import_id(&[], "i-1").unwrap().get("id"), Some("i-1"));
+    }
+}
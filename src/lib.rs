@@ -18,8 +18,8 @@
 //!
 //! ```ignore
 //! use hemmer_provider_sdk::{
-//!     serve, ProviderService, ProviderError, PlanResult,
-//!     schema::{ProviderSchema, Schema, Attribute, Diagnostic},
+//!     serve, ConfigureResult, ProviderService, ProviderError, PlanResult,
+//!     schema::{ProviderSchema, Schema, Attribute},
 //! };
 //!
 //! struct MyProvider;
@@ -36,8 +36,8 @@
 //!     async fn configure(
 //!         &self,
 //!         config: serde_json::Value,
-//!     ) -> Result<Vec<Diagnostic>, ProviderError> {
-//!         Ok(vec![])
+//!     ) -> Result<ConfigureResult, ProviderError> {
+//!         Ok(ConfigureResult::new())
 //!     }
 //!
 //!     async fn plan(
@@ -109,44 +109,108 @@
 //!
 //! - **GetMetadata**: Returns provider capabilities and resource/data source names
 //! - **GetSchema**: Returns full schema for provider config, resources, and data sources
+//! - **StreamSchema**: Returns the same schema split into chunks, for very large providers
 //! - **ValidateProviderConfig**: Validates provider configuration
 //! - **Configure**: Configures the provider with credentials
 //! - **Stop**: Gracefully shuts down the provider
+//! - **EndSession**: Drops reads cached for a finished plan or apply run
 //! - **ValidateResourceConfig**: Validates resource configuration
 //! - **UpgradeResourceState**: Migrates state from older schema versions
 //! - **Plan**: Calculates required changes
+//! - **PreApplyCheck**: Vetoes an approved plan just before apply
 //! - **Create/Read/Update/Delete**: CRUD operations for resources
 //! - **ImportResourceState**: Imports existing infrastructure
+//! - **BulkImportResourceState**: Imports many resources by IDs or a query, streaming results
 //! - **ValidateDataSourceConfig**: Validates data source configuration
 //! - **ReadDataSource**: Reads data from external sources
+//! - **Events**: Streams provider-initiated advisory events
 
 #![warn(missing_docs)]
 #![warn(clippy::all)]
 
+#[cfg(feature = "attestation")]
+pub mod attestation;
+#[cfg(feature = "aws-sig")]
+pub mod aws;
+pub mod blocking;
+pub mod cache;
+pub mod codec;
+pub mod config_example;
+pub mod context;
+pub mod conventions;
+#[cfg(feature = "devtool")]
+pub mod devtool;
+pub mod diagnostics;
+pub mod dynamic;
 pub mod error;
+pub mod events;
+#[cfg(feature = "examples")]
+pub mod examples;
+pub mod fuzz;
+#[cfg(feature = "gateway")]
+pub mod gateway;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod i18n;
+pub mod idempotency;
+pub mod import;
+pub mod lint;
+pub mod lock;
 pub mod logging;
+pub mod manifest;
+pub mod metrics;
+pub mod normalize;
+pub mod number;
+#[cfg(feature = "http")]
+pub mod oauth;
+pub mod pagination;
+pub mod patch;
+pub mod payload;
+pub mod plan;
+pub mod proto_compat;
+pub mod replay;
+pub mod retry;
 pub mod schema;
+pub mod schema_export;
+pub mod schema_stream;
+pub mod secrets;
 pub mod server;
+pub mod state;
+pub mod store;
 pub mod testing;
+#[cfg(feature = "ssh-tunnel")]
+pub mod tunnel;
+pub mod typed;
 pub mod types;
+pub mod upgrade;
 pub mod validation;
+pub mod value;
 
 #[allow(missing_docs)]
 #[allow(clippy::all)]
 pub mod generated;
 
 // Re-export main types at crate root
+pub use context::Context;
 pub use error::ProviderError;
-pub use logging::{init_logging, init_logging_with_default, try_init_logging};
+pub use logging::{
+    init_logging, init_logging_with_default, try_init_logging, try_init_logging_with_format,
+    LogFormat,
+};
 pub use schema::ProviderSchema;
 pub use server::{
     serve, serve_on, serve_on_with_options, serve_with_options, ProviderService, ServeOptions,
+    IDEMPOTENCY_KEY_METADATA, SESSION_ID_METADATA,
 };
 pub use types::{
-    check_protocol_version, AttributeChange, ImportedResource, PlanResult, ProviderMetadata,
-    ServerCapabilities, HANDSHAKE_PREFIX, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+    check_protocol_version, AttributeChange, ChangeAction, ConfigureResult, CreateRequest,
+    DeleteRequest, ImpactEstimate, ImportedResource, PlanResult, ProviderMetadata,
+    ProviderMetadataBuilder, ReadDataSourceRequest, ReadRequest, ReadResult, ReadStatus,
+    ReplaceReason, ServerCapabilities, UpdateRequest, HANDSHAKE_PREFIX, MIN_PROTOCOL_VERSION,
+    PROTOCOL_VERSION,
 };
 pub use validation::{is_valid, validate, validate_result};
+pub use value::ValueExt;
 
 /// A convenience type alias for Results with [`ProviderError`].
 ///
@@ -0,0 +1,677 @@
+//! Schema linter and provider self-checks.
+//!
+//! [`lint_schema`] finds mistakes in a [`ProviderSchema`] that would otherwise
+//! only surface when core (or a user) hits them: attributes that are neither
+//! required, optional nor computed, defaults that don't match their type,
+//! nested block limits that can never be satisfied, and so on.
+//!
+//! [`self_check`] runs the linter, checks that every resource and data source
+//! listed in [`ProviderService::metadata`] has a schema, and then runs the
+//! provider's own [`ProviderService::self_check`] hook. [`serve`](crate::serve)
+//! runs the static checks before emitting the handshake (see
+//! [`ServeOptions::with_self_check`](crate::ServeOptions::with_self_check)),
+//! and the `doctor` command of the [`devtool`](crate::devtool) runs all of them.
+//!
+//! # Example
+//!
+//! ```
+//! use hemmer_provider_sdk::lint::lint_schema;
+//! use hemmer_provider_sdk::schema::{Attribute, ProviderSchema, Schema};
+//! use serde_json::json;
+//!
+//! let schema = ProviderSchema::new().with_resource(
+//!     "example_server",
+//!     Schema::v0().with_attribute("size", Attribute::optional_int64().with_default(json!("big"))),
+//! );
+//!
+//! let diagnostics = lint_schema(&schema);
+//! assert_eq!(diagnostics.len(), 1);
+//! assert_eq!(diagnostics[0].attribute.as_deref(), Some("resource.example_server.size"));
+//! ```
+
+use crate::import::ImportFormat;
+use std::collections::BTreeMap;
+
+use crate::schema::{
+    Attribute, Block, Diagnostic, NestedBlock, PlanModifier, ProviderSchema, ResourceTypeName,
+};
+use crate::server::ProviderService;
+use crate::types::ProviderMetadata;
+use crate::validation::validate_attribute_type;
+
+/// Check a provider schema for definition mistakes.
+///
+/// Diagnostic attribute paths are prefixed with `provider`, `resource.<type>`,
+/// `data_source.<type>` or `function.<name>`.
+pub fn lint_schema(schema: &ProviderSchema) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    lint_block(&schema.provider.block, "provider", &mut diagnostics);
+
+    for (kind, schemas) in [
+        ("resource", &schema.resources),
+        ("data_source", &schema.data_sources),
+    ] {
+        for (name, schema) in schemas {
+            let path = format!("{}.{}", kind, name);
+            if name.is_empty() {
+                diagnostics.push(
+                    Diagnostic::error(format!("Empty {} type name", kind)).with_attribute(&path),
+                );
+            } else if let Err(e) = ResourceTypeName::parse(name) {
+                diagnostics.push(
+                    Diagnostic::warning(format!(
+                        "{} type '{}' is not named '<provider>_<name>'",
+                        capitalize(&kind.replace('_', " ")),
+                        name
+                    ))
+                    .with_detail(format!(
+                        "Core routes requests to a provider by the prefix of the type name. {}",
+                        e
+                    ))
+                    .with_attribute(&path),
+                );
+            }
+            let block = &schema.block;
+            if block.attributes.is_empty() && block.blocks.is_empty() {
+                diagnostics.push(
+                    Diagnostic::warning(format!("Schema for {} '{}' is empty", kind, name))
+                        .with_attribute(&path),
+                );
+            }
+            lint_block(block, &path, &mut diagnostics);
+            for pattern in &schema.import_formats {
+                if let Err(e) = ImportFormat::parse(pattern) {
+                    diagnostics.push(
+                        Diagnostic::error(format!("Invalid import format '{}'", pattern))
+                            .with_detail(e.to_string())
+                            .with_attribute(&path),
+                    );
+                }
+            }
+        }
+    }
+
+    lint_type_prefixes(schema, &mut diagnostics);
+
+    for (name, function) in &schema.functions {
+        let path = format!("function.{}", name);
+        if name.is_empty() {
+            diagnostics.push(Diagnostic::error("Empty function name").with_attribute(&path));
+        }
+        let mut seen = std::collections::BTreeSet::new();
+        for parameter in function
+            .parameters
+            .iter()
+            .chain(&function.variadic_parameter)
+        {
+            if parameter.name.is_empty() {
+                diagnostics.push(Diagnostic::error("Empty parameter name").with_attribute(&path));
+            } else if !seen.insert(parameter.name.as_str()) {
+                diagnostics.push(
+                    Diagnostic::error(format!("Parameter '{}' is repeated", parameter.name))
+                        .with_attribute(&path),
+                );
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Check that every type listed in `metadata` has a schema, and vice versa.
+pub fn lint_metadata(schema: &ProviderSchema, metadata: &ProviderMetadata) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (kind, listed, schemas) in [
+        ("resource", &metadata.resources, &schema.resources),
+        ("data source", &metadata.data_sources, &schema.data_sources),
+    ] {
+        for name in listed {
+            if !schemas.contains_key(name) {
+                diagnostics.push(
+                    Diagnostic::error(format!(
+                        "Metadata lists {} '{}' without a schema",
+                        kind, name
+                    ))
+                    .with_detail(format!(
+                        "Add a schema for '{}' or remove it from metadata()",
+                        name
+                    )),
+                );
+            }
+        }
+        for name in schemas.keys().filter(|n| !listed.contains(n)) {
+            diagnostics.push(
+                Diagnostic::warning(format!(
+                    "{} '{}' is missing from metadata",
+                    capitalize(kind),
+                    name
+                ))
+                .with_detail("Core uses metadata to discover types, so it will not be usable"),
+            );
+        }
+    }
+    diagnostics
+}
+
+/// Run the schema linter, the metadata check and [`ProviderService::self_check`].
+///
+/// Errors returned by the hook are reported as error diagnostics.
+pub async fn self_check<P: ProviderService + ?Sized>(provider: &P) -> Vec<Diagnostic> {
+    let mut diagnostics = static_checks(provider);
+    match provider.self_check().await {
+        Ok(found) => diagnostics.extend(found),
+        Err(e) => diagnostics
+            .push(Diagnostic::error("Provider self-check failed").with_detail(e.to_string())),
+    }
+    diagnostics
+}
+
+/// The checks that need no I/O: the schema linter and the metadata check.
+pub(crate) fn static_checks<P: ProviderService + ?Sized>(provider: &P) -> Vec<Diagnostic> {
+    let schema = match provider.try_schema() {
+        Ok(schema) => schema,
+        Err(diagnostics) => return diagnostics,
+    };
+    let mut diagnostics = lint_schema(&schema);
+    match provider.try_metadata() {
+        Ok(metadata) => diagnostics.extend(lint_metadata(&schema, &metadata)),
+        Err(found) => diagnostics.extend(found),
+    }
+    if let Some(distribution) = provider.distribution() {
+        diagnostics.extend(distribution.validate());
+    }
+    diagnostics
+}
+
+/// Names core's configuration parser reserves, which can't be used for
+/// attributes or blocks: keywords, literals and resource meta-arguments.
+pub const RESERVED_NAMES: &[&str] = &[
+    "depends_on",
+    "else",
+    "false",
+    "for",
+    "for_each",
+    "if",
+    "in",
+    "lifecycle",
+    "null",
+    "provider",
+    "self",
+    "true",
+];
+
+/// Why `name` can't be used for an attribute or block, if it can't.
+///
+/// Names must be lowercase snake_case (`[a-z_][a-z0-9_]*`) and not one of the
+/// [`RESERVED_NAMES`], or configurations can't refer to them.
+pub fn name_error(name: &str) -> Option<String> {
+    if name.is_empty() {
+        Some("Empty attribute or block name".to_string())
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        Some(format!("'{}' starts with a digit", name))
+    } else if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        Some(format!("'{}' is not lowercase snake_case", name))
+    } else if RESERVED_NAMES.contains(&name) {
+        Some(format!("'{}' is a reserved word", name))
+    } else {
+        None
+    }
+}
+
+/// Report type names whose provider prefix differs from the one most types use.
+fn lint_type_prefixes(schema: &ProviderSchema, diagnostics: &mut Vec<Diagnostic>) {
+    let types: Vec<(&str, &str, &str)> = [
+        ("resource", &schema.resources),
+        ("data_source", &schema.data_sources),
+    ]
+    .into_iter()
+    .flat_map(|(kind, schemas)| {
+        schemas.keys().filter_map(move |name| {
+            ResourceTypeName::split(name).map(|(provider, _)| (kind, name.as_str(), provider))
+        })
+    })
+    .collect();
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for (_, _, provider) in &types {
+        *counts.entry(provider).or_default() += 1;
+    }
+    // The most used prefix, the first alphabetically on a tie
+    let Some(common) = counts
+        .iter()
+        .max_by_key(|(prefix, count)| (**count, std::cmp::Reverse(**prefix)))
+        .map(|(prefix, _)| *prefix)
+    else {
+        return;
+    };
+    for (kind, name, provider) in types {
+        if provider != common {
+            diagnostics.push(
+                Diagnostic::warning(format!(
+                    "{} type '{}' has the provider prefix '{}_', other types use '{}_'",
+                    capitalize(&kind.replace('_', " ")),
+                    name,
+                    provider,
+                    common
+                ))
+                .with_attribute(format!("{}.{}", kind, name)),
+            );
+        }
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+pub(crate) fn lint_block(block: &Block, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let names: Vec<&String> = block.attributes.keys().chain(block.blocks.keys()).collect();
+    for (i, name) in names.iter().enumerate() {
+        if let Some(error) = name_error(name) {
+            diagnostics.push(
+                Diagnostic::error(error)
+                    .with_detail("Attribute and block names must be lowercase snake_case and not a reserved word, or configurations can't refer to them")
+                    .with_attribute(format!("{}.{}", path, name)),
+            );
+        }
+        if let Some(other) = names[..i]
+            .iter()
+            .find(|other| *other != name && other.eq_ignore_ascii_case(name))
+        {
+            diagnostics.push(
+                Diagnostic::error(format!("'{}' differs from '{}' only in case", name, other))
+                    .with_detail("Names that differ only in case are ambiguous in configuration languages and file systems")
+                    .with_attribute(format!("{}.{}", path, name)),
+            );
+        }
+    }
+
+    for (name, attr) in &block.attributes {
+        let attr_path = format!("{}.{}", path, name);
+        if block.blocks.contains_key(name) {
+            diagnostics.push(
+                Diagnostic::error(format!("'{}' is both an attribute and a block", name))
+                    .with_attribute(&attr_path),
+            );
+        }
+        lint_attribute(attr, &attr_path, diagnostics);
+    }
+
+    for (name, nested) in &block.blocks {
+        let block_path = format!("{}.{}", path, name);
+        lint_nested_block(nested, &block_path, diagnostics);
+    }
+}
+
+pub(crate) fn lint_attribute(attr: &Attribute, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let flags = &attr.flags;
+    let computed_only = flags.computed && !flags.required && !flags.optional;
+
+    if !flags.required && !flags.optional && !flags.computed {
+        diagnostics.push(
+            Diagnostic::error("Attribute is not required, optional or computed")
+                .with_detail("Set exactly one of required, optional or computed (optional may be combined with computed)")
+                .with_attribute(path),
+        );
+    }
+    if flags.required && (flags.optional || flags.computed) {
+        diagnostics.push(
+            Diagnostic::error("Required attribute is also optional or computed")
+                .with_detail("Required attributes must be set in configuration; use optional + computed for provider defaults")
+                .with_attribute(path),
+        );
+    }
+
+    if let Some(default) = &attr.default {
+        if flags.required || computed_only {
+            diagnostics.push(
+                Diagnostic::error("Default is set on a required or computed-only attribute")
+                    .with_detail("Defaults only apply to optional attributes")
+                    .with_attribute(path),
+            );
+        }
+        let mut type_errors = Vec::new();
+        validate_attribute_type(&attr.attr_type, default, path, &mut type_errors);
+        diagnostics.extend(type_errors.into_iter().map(|d| {
+            Diagnostic::error("Default does not match the attribute type")
+                .with_detail(d.summary)
+                .with_attribute(path)
+        }));
+    }
+
+    if attr.force_new && computed_only {
+        diagnostics.push(
+            Diagnostic::warning("force_new has no effect on a computed attribute")
+                .with_detail("Computed attributes are never changed by configuration")
+                .with_attribute(path),
+        );
+    }
+    if attr.secret_ref && computed_only {
+        diagnostics.push(
+            Diagnostic::error("secret_ref is set on a computed attribute")
+                .with_detail("Secret references are resolved from configuration")
+                .with_attribute(path),
+        );
+    }
+    if attr.exec_credential && computed_only {
+        diagnostics.push(
+            Diagnostic::error("exec_credential is set on a computed attribute")
+                .with_detail("Credential commands are read from configuration")
+                .with_attribute(path),
+        );
+    }
+    if attr.has_plan_modifier(PlanModifier::UnknownOnUpdate) && !flags.computed {
+        diagnostics.push(
+            Diagnostic::warning("UnknownOnUpdate has no effect on a non-computed attribute")
+                .with_attribute(path),
+        );
+    }
+}
+
+pub(crate) fn lint_nested_block(
+    nested: &NestedBlock,
+    path: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if nested.max_items > 0 && nested.min_items > nested.max_items {
+        diagnostics.push(
+            Diagnostic::error(format!(
+                "min_items ({}) is greater than max_items ({})",
+                nested.min_items, nested.max_items
+            ))
+            .with_attribute(path),
+        );
+    }
+    if nested.block.attributes.is_empty() && nested.block.blocks.is_empty() {
+        diagnostics.push(Diagnostic::warning("Nested block is empty").with_attribute(path));
+    } else if nested.force_new && !has_configurable_attribute(&nested.block) {
+        diagnostics.push(
+            Diagnostic::warning("force_new has no effect on a block of computed attributes")
+                .with_detail("Computed attributes are never changed by configuration")
+                .with_attribute(path),
+        );
+    }
+    lint_block(&nested.block, path, diagnostics);
+}
+
+fn has_configurable_attribute(block: &Block) -> bool {
+    block
+        .attributes
+        .values()
+        .any(|attr| attr.flags.required || attr.flags.optional)
+        || block
+            .blocks
+            .values()
+            .any(|nested| has_configurable_attribute(&nested.block))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ProviderError;
+    use crate::schema::{AttributeFlags, AttributeType, FunctionParameter, FunctionSchema, Schema};
+    use crate::types::ConfigureResult;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_lint_schema() {
+        let schema = ProviderSchema::new()
+            .with_resource(
+                "example_server",
+                Schema::v0()
+                    .with_attribute("name", Attribute::required_string())
+                    .with_attribute(
+                        "zone",
+                        Attribute::new(AttributeType::String, AttributeFlags::default()),
+                    )
+                    .with_attribute("id", Attribute::computed_string().with_force_new())
+                    .with_attribute("disk", Attribute::optional_string())
+                    .with_block(
+                        "disk",
+                        NestedBlock::list(
+                            Block::new().with_attribute("size", Attribute::required_int64()),
+                        )
+                        .with_min_items(3)
+                        .with_max_items(2),
+                    ),
+            )
+            .with_data_source("example_image", Schema::v0())
+            .with_function(
+                "join",
+                FunctionSchema::new(AttributeType::String)
+                    .with_parameter(FunctionParameter::new("sep", AttributeType::String))
+                    .with_variadic_parameter(FunctionParameter::new("sep", AttributeType::String)),
+            );
+
+        let summaries: Vec<_> = lint_schema(&schema)
+            .into_iter()
+            .map(|d| (d.severity, d.attribute.unwrap_or_default()))
+            .collect();
+        use crate::schema::DiagnosticSeverity::{Error, Warning};
+        assert_eq!(
+            summaries,
+            vec![
+                (Error, "resource.example_server.disk".to_string()),
+                (Warning, "resource.example_server.id".to_string()),
+                (Error, "resource.example_server.zone".to_string()),
+                (Error, "resource.example_server.disk".to_string()),
+                (Warning, "data_source.example_image".to_string()),
+                (Error, "function.join".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_type_names() {
+        let schema = ProviderSchema::new()
+            .with_resource("example_server", Schema::v0())
+            .with_resource("example_volume", Schema::v0())
+            .with_resource("server", Schema::v0())
+            .with_data_source("other_image", Schema::v0());
+        let summaries: Vec<_> = lint_schema(&schema)
+            .into_iter()
+            .filter(|d| d.summary.contains("type"))
+            .map(|d| d.summary)
+            .collect();
+        assert_eq!(
+            summaries,
+            vec![
+                "Resource type 'server' is not named '<provider>_<name>'",
+                "Data source type 'other_image' has the provider prefix 'other_', other types use 'example_'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_case_duplicates() {
+        let schema = ProviderSchema::new().with_resource(
+            "example_server",
+            Schema::v0()
+                .with_attribute("Name", Attribute::optional_string())
+                .with_attribute("name", Attribute::optional_string())
+                .with_block(
+                    "NAME",
+                    NestedBlock::single(
+                        Block::new().with_attribute("size", Attribute::optional_int64()),
+                    ),
+                ),
+        );
+        let paths: Vec<_> = lint_schema(&schema)
+            .into_iter()
+            .filter(|d| d.summary.ends_with("only in case"))
+            .map(|d| d.attribute.unwrap_or_default())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "resource.example_server.name",
+                "resource.example_server.NAME"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_names() {
+        assert_eq!(name_error("subnet_id"), None);
+        assert_eq!(name_error("_internal2"), None);
+        assert_eq!(
+            name_error("2fa").as_deref(),
+            Some("'2fa' starts with a digit")
+        );
+        assert_eq!(
+            name_error("subnet-id").as_deref(),
+            Some("'subnet-id' is not lowercase snake_case")
+        );
+        assert_eq!(
+            name_error("for_each").as_deref(),
+            Some("'for_each' is a reserved word")
+        );
+
+        let schema = ProviderSchema::new().with_resource(
+            "example_server",
+            Schema::v0()
+                .with_attribute("subnetId", Attribute::optional_string())
+                .with_block(
+                    "lifecycle",
+                    NestedBlock::single(
+                        Block::new().with_attribute("9lives", Attribute::optional_int64()),
+                    ),
+                ),
+        );
+        let paths: Vec<_> = lint_schema(&schema)
+            .into_iter()
+            .map(|d| d.attribute.unwrap_or_default())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "resource.example_server.subnetId",
+                "resource.example_server.lifecycle",
+                "resource.example_server.lifecycle.9lives",
+            ]
+        );
+    }
+
+    struct CheckedProvider;
+
+    #[async_trait::async_trait]
+    impl ProviderService for CheckedProvider {
+        fn schema(&self) -> ProviderSchema {
+            ProviderSchema::new().with_resource(
+                "example_server",
+                Schema::v0().with_attribute("name", Attribute::required_string()),
+            )
+        }
+
+        fn metadata(&self) -> ProviderMetadata {
+            ProviderMetadata {
+                resources: vec!["example_server".to_string(), "example_volume".to_string()],
+                ..Default::default()
+            }
+        }
+
+        fn distribution(&self) -> Option<crate::manifest::Distribution> {
+            Some(
+                crate::manifest::Distribution::new()
+                    .with_platform(crate::manifest::Platform::new("linux", "x86_64")),
+            )
+        }
+
+        async fn self_check(&self) -> Result<Vec<Diagnostic>, ProviderError> {
+            Err(ProviderError::Unavailable(
+                "api.example.com unreachable".to_string(),
+            ))
+        }
+
+        async fn configure(&self, _config: Value) -> Result<ConfigureResult, ProviderError> {
+            Ok(ConfigureResult::new())
+        }
+
+        async fn create(
+            &self,
+            _resource_type: &str,
+            planned: Value,
+        ) -> Result<Value, ProviderError> {
+            Ok(planned)
+        }
+
+        async fn read(&self, _resource_type: &str, state: Value) -> Result<Value, ProviderError> {
+            Ok(state)
+        }
+
+        async fn update(
+            &self,
+            _resource_type: &str,
+            _prior: Value,
+            planned: Value,
+        ) -> Result<Value, ProviderError> {
+            Ok(planned)
+        }
+
+        async fn delete(&self, _resource_type: &str, _state: Value) -> Result<(), ProviderError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_self_check() {
+        let diagnostics = self_check(&CheckedProvider).await;
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics[0]
+            .summary
+            .contains("'example_volume' without a schema"));
+        assert_eq!(
+            diagnostics[1].summary,
+            "Distribution has no signing key fingerprint"
+        );
+        assert_eq!(
+            diagnostics[2].detail.as_deref(),
+            Some("Service unavailable: api.example.com unreachable")
+        );
+
+        let default_value = json!("big");
+        let schema = ProviderSchema::new().with_resource(
+            "example_server",
+            Schema::v0().with_attribute(
+                "size",
+                Attribute::optional_int64().with_default(default_value),
+            ),
+        );
+        assert_eq!(
+            lint_schema(&schema)[0].summary,
+            "Default does not match the attribute type"
+        );
+
+        let schema = ProviderSchema::new().with_resource(
+            "example_server",
+            Schema::v0().with_block(
+                "status",
+                NestedBlock::single(
+                    Block::new().with_attribute("state", Attribute::computed_string()),
+                )
+                .with_force_new(),
+            ),
+        );
+        assert_eq!(
+            lint_schema(&schema)[0].summary,
+            "force_new has no effect on a block of computed attributes"
+        );
+
+        let schema = ProviderSchema::new().with_resource(
+            "example_server",
+            Schema::v0()
+                .with_attribute("name", Attribute::required_string())
+                .with_import_format("{zone}{name}"),
+        );
+        assert_eq!(
+            lint_schema(&schema)[0].summary,
+            "Invalid import format '{zone}{name}'"
+        );
+    }
+}
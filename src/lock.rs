@@ -0,0 +1,309 @@
+//! Per-resource concurrency guard.
+//!
+//! Core may run operations in parallel, and two of them can target the same
+//! remote object (e.g. a read racing an update). [`ResourceLocks`] serializes
+//! operations on the same resource inside the provider process, keyed by the
+//! resource type and an identity taken from the state (its `id` attribute by
+//! default).
+//!
+//! The server locks reads, updates, deletes and imports with the locks from
+//! [`ServeOptions::with_resource_locks`](crate::ServeOptions::with_resource_locks).
+//! Creates are not locked because the resource has no identity yet.
+//!
+//! [`ParallelismLimits`] caps how many operations on a resource type run at
+//! once, for APIs that throttle per resource family rather than globally. The
+//! server builds them from [`ProviderMetadata::max_parallelism`](crate::ProviderMetadata::max_parallelism),
+//! usually declared with [`Schema::with_max_parallelism`](crate::schema::Schema::with_max_parallelism).
+//!
+//! # Example
+//!
+//! ```
+//! use hemmer_provider_sdk::lock::ResourceLocks;
+//! use serde_json::json;
+//!
+//! # tokio_test::block_on(async {
+//! // Identify buckets by name instead of id
+//! let locks = ResourceLocks::new().with_identity(|resource_type, state| match resource_type {
+//!     "bucket" => state.get("name")?.as_str().map(String::from),
+//!     _ => ResourceLocks::default_identity(state),
+//! });
+//!
+//! let guard = locks.lock_state("bucket", &json!({"name": "logs"})).await;
+//! assert!(guard.is_some());
+//! # });
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard, OwnedSemaphorePermit, Semaphore};
+
+type IdentityFn = dyn Fn(&str, &Value) -> Option<String> + Send + Sync;
+type LockMap = Mutex<HashMap<(String, String), Arc<AsyncMutex<()>>>>;
+
+/// Locks keyed by resource type and identity.
+///
+/// Cloning shares the same locks.
+#[derive(Clone)]
+pub struct ResourceLocks {
+    locks: Arc<LockMap>,
+    identity: Arc<IdentityFn>,
+}
+
+impl ResourceLocks {
+    /// Create locks that identify resources by their `id` attribute.
+    pub fn new() -> Self {
+        Self {
+            locks: Arc::default(),
+            identity: Arc::new(|_, state| Self::default_identity(state)),
+        }
+    }
+
+    /// Set how a resource's identity is derived from its state.
+    ///
+    /// Returning `None` leaves the operation unlocked.
+    pub fn with_identity(
+        mut self,
+        identity: impl Fn(&str, &Value) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.identity = Arc::new(identity);
+        self
+    }
+
+    /// The default identity: the state's `id` attribute, if it is a string or number.
+    pub fn default_identity(state: &Value) -> Option<String> {
+        match state.get("id")? {
+            Value::String(id) if !id.is_empty() => Some(id.clone()),
+            Value::Number(id) => Some(id.to_string()),
+            _ => None,
+        }
+    }
+
+    /// The identity of a resource, if it has one.
+    pub fn identity(&self, resource_type: &str, state: &Value) -> Option<String> {
+        (self.identity)(resource_type, state)
+    }
+
+    /// Wait for exclusive access to a resource.
+    pub async fn lock(&self, resource_type: &str, identity: &str) -> ResourceGuard {
+        let key = (resource_type.to_string(), identity.to_string());
+        let mutex = {
+            let mut locks = self.locks.lock().unwrap();
+            Arc::clone(locks.entry(key.clone()).or_default())
+        };
+        let guard = mutex.lock_owned().await;
+        ResourceGuard {
+            locks: Arc::clone(&self.locks),
+            key,
+            guard: Some(guard),
+        }
+    }
+
+    /// Lock the resource identified by `state`, or return `None` if it has no identity.
+    pub async fn lock_state(&self, resource_type: &str, state: &Value) -> Option<ResourceGuard> {
+        let identity = self.identity(resource_type, state)?;
+        Some(self.lock(resource_type, &identity).await)
+    }
+
+    /// Number of resources that are locked or being waited on.
+    pub fn active(&self) -> usize {
+        self.locks.lock().unwrap().len()
+    }
+}
+
+impl Default for ResourceLocks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ResourceLocks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResourceLocks")
+            .field("active", &self.active())
+            .finish()
+    }
+}
+
+/// Exclusive access to a resource; released on drop.
+pub struct ResourceGuard {
+    locks: Arc<LockMap>,
+    key: (String, String),
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl ResourceGuard {
+    /// The resource type and identity this guard holds.
+    pub fn key(&self) -> (&str, &str) {
+        (&self.key.0, &self.key.1)
+    }
+}
+
+impl Drop for ResourceGuard {
+    fn drop(&mut self) {
+        let mut locks = self.locks.lock().unwrap();
+        // Release before checking so waiters see the lock as free
+        drop(self.guard.take());
+        // Remove the entry once nobody else holds or waits for it
+        if let Some(mutex) = locks.get(&self.key) {
+            if Arc::strong_count(mutex) == 1 {
+                locks.remove(&self.key);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ResourceGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResourceGuard")
+            .field("resource_type", &self.key.0)
+            .field("identity", &self.key.1)
+            .finish()
+    }
+}
+
+/// Per-resource-type caps on concurrent operations.
+///
+/// Cloning shares the same limits.
+#[derive(Clone, Default)]
+pub struct ParallelismLimits {
+    semaphores: Arc<HashMap<String, (u32, Arc<Semaphore>)>>,
+}
+
+impl ParallelismLimits {
+    /// Create limits from maximum parallelism by resource type. Zero means
+    /// unlimited.
+    pub fn new<K: Into<String>>(limits: impl IntoIterator<Item = (K, u32)>) -> Self {
+        let semaphores = limits
+            .into_iter()
+            .filter(|(_, max)| *max > 0)
+            .map(|(resource_type, max)| {
+                (
+                    resource_type.into(),
+                    (max, Arc::new(Semaphore::new(max as usize))),
+                )
+            })
+            .collect();
+        Self {
+            semaphores: Arc::new(semaphores),
+        }
+    }
+
+    /// The limit for a resource type, if it has one.
+    pub fn limit(&self, resource_type: &str) -> Option<u32> {
+        self.semaphores.get(resource_type).map(|(max, _)| *max)
+    }
+
+    /// Wait for a slot to run an operation on `resource_type`, or return
+    /// `None` at once if it is unlimited. The slot is freed on drop.
+    pub async fn acquire(&self, resource_type: &str) -> Option<OwnedSemaphorePermit> {
+        let (_, semaphore) = self.semaphores.get(resource_type)?;
+        // The semaphore is never closed
+        Arc::clone(semaphore).acquire_owned().await.ok()
+    }
+}
+
+impl fmt::Debug for ParallelismLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.semaphores.iter().map(|(k, (max, _))| (k, max)))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_same_resource_serialized() {
+        let locks = ResourceLocks::new();
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..5)
+            .map(|_| {
+                let locks = locks.clone();
+                let running = Arc::clone(&running);
+                let max_running = Arc::clone(&max_running);
+                tokio::spawn(async move {
+                    let _guard = locks.lock("server", "i-1").await;
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(max_running.load(Ordering::SeqCst), 1);
+        assert_eq!(locks.active(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_different_resources_independent() {
+        let locks = ResourceLocks::new();
+        let _a = locks.lock("server", "i-1").await;
+        let _b = locks.lock("server", "i-2").await;
+        let _c = locks.lock("disk", "i-1").await;
+        assert_eq!(locks.active(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_parallelism_limits() {
+        let limits = ParallelismLimits::new([("record", 2), ("zone", 0)]);
+        assert_eq!(limits.limit("record"), Some(2));
+        assert_eq!(limits.limit("zone"), None);
+        assert!(limits.acquire("zone").await.is_none());
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..6)
+            .map(|_| {
+                let limits = limits.clone();
+                let running = Arc::clone(&running);
+                let max_running = Arc::clone(&max_running);
+                tokio::spawn(async move {
+                    let _permit = limits.acquire("record").await;
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_identity_from_state() {
+        let locks = ResourceLocks::new();
+        assert_eq!(
+            locks.identity("server", &json!({"id": 42})),
+            Some("42".to_string())
+        );
+        assert!(locks
+            .lock_state("server", &json!({"id": null}))
+            .await
+            .is_none());
+
+        let guard = locks
+            .lock_state("server", &json!({"id": "i-1"}))
+            .await
+            .unwrap();
+        assert_eq!(guard.key(), ("server", "i-1"));
+        drop(guard);
+        assert_eq!(locks.active(), 0);
+    }
+}
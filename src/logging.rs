@@ -19,9 +19,30 @@
 //! }
 //! ```
 //!
+//! # Provider Logging
+//!
+//! Events logged with [`provider_info!`](crate::provider_info) (and
+//! `provider_debug!`, `provider_warn!`, `provider_error!`) carry the
+//! `operation`, `resource_type` and `request_id` of the current operation
+//! from the [`Context`](crate::Context), so they can be matched with the
+//! SDK's `grpc.*` span for the same request, which records the same
+//! `request_id`. They take the same arguments as `tracing::info!` and friends:
+//!
+//! ```ignore
+//! use hemmer_provider_sdk::provider_info;
+//!
+//! provider_info!(bucket = %name, "Created bucket");
+//! // INFO my_provider: Created bucket operation="create" resource_type="example_bucket" request_id="4242-17" bucket=logs
+//! ```
+//!
+//! Outside of an operation the context fields are left out.
+//!
 //! # Environment Variables
 //!
 //! - `RUST_LOG`: Controls log levels (e.g., `info`, `debug`, `hemmer_provider_sdk=debug`)
+//! - `HEMMER_PROVIDER_LOG_FORMAT`: `text`, `compact` or `json`; when set,
+//!   [`serve`](crate::serve) initializes logging in that format (see
+//!   [`ServeOptions::from_env`](crate::ServeOptions::from_env))
 //!
 //! # Examples
 //!
@@ -36,8 +57,44 @@
 //! RUST_LOG=debug ./my-provider
 //! ```
 
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
+/// How log lines are formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human-readable lines, as written by [`init_logging`].
+    #[default]
+    Text,
+    /// Shorter human-readable lines.
+    Compact,
+    /// One JSON object per line, for log aggregation.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "compact" => Ok(Self::Compact),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "Unknown log format '{}', expected 'text', 'compact' or 'json'",
+                other
+            )),
+        }
+    }
+}
+
 /// Initialize the default logging subscriber.
 ///
 /// This sets up a `tracing` subscriber that:
@@ -151,6 +208,148 @@ pub fn try_init_logging() -> bool {
         .is_ok()
 }
 
+/// Try to initialize logging in `format`, returning false if already
+/// initialized.
+///
+/// Like [`try_init_logging`], this respects `RUST_LOG` and defaults to `info`.
+pub fn try_init_logging_with_format(format: LogFormat) -> bool {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let layer = fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_target(true)
+        .with_thread_ids(false)
+        .with_file(false)
+        .with_line_number(false);
+
+    let registry = tracing_subscriber::registry().with(filter);
+    match format {
+        LogFormat::Text => registry.with(layer).try_init(),
+        LogFormat::Compact => registry.with(layer.compact()).try_init(),
+        LogFormat::Json => registry.with(layer.event_format(JsonFormat)).try_init(),
+    }
+    .is_ok()
+}
+
+/// Log an event at `level` with the `operation`, `resource_type` and
+/// `request_id` of the current [`Context`](crate::Context).
+///
+/// The level is a [`tracing::Level`]; the rest are the fields and message of
+/// a `tracing::event!`. See also [`provider_info!`](crate::provider_info).
+#[macro_export]
+macro_rules! provider_event {
+    ($level:expr, $($arg:tt)+) => {{
+        let context = $crate::Context::current();
+        $crate::tracing::event!(
+            $level,
+            operation = context.operation(),
+            resource_type = context.resource_type(),
+            request_id = context.request_id(),
+            $($arg)+
+        )
+    }};
+}
+
+/// Log a debug event with the current operation's context fields; see
+/// [`provider_event!`](crate::provider_event).
+#[macro_export]
+macro_rules! provider_debug {
+    ($($arg:tt)+) => {
+        $crate::provider_event!($crate::tracing::Level::DEBUG, $($arg)+)
+    };
+}
+
+/// Log an info event with the current operation's context fields; see
+/// [`provider_event!`](crate::provider_event).
+#[macro_export]
+macro_rules! provider_info {
+    ($($arg:tt)+) => {
+        $crate::provider_event!($crate::tracing::Level::INFO, $($arg)+)
+    };
+}
+
+/// Log a warning with the current operation's context fields; see
+/// [`provider_event!`](crate::provider_event).
+#[macro_export]
+macro_rules! provider_warn {
+    ($($arg:tt)+) => {
+        $crate::provider_event!($crate::tracing::Level::WARN, $($arg)+)
+    };
+}
+
+/// Log an error with the current operation's context fields; see
+/// [`provider_event!`](crate::provider_event).
+#[macro_export]
+macro_rules! provider_error {
+    ($($arg:tt)+) => {
+        $crate::provider_event!($crate::tracing::Level::ERROR, $($arg)+)
+    };
+}
+
+/// Formats events as JSON objects with `timestamp` (Unix seconds), `level`,
+/// `target`, `spans` (names, outermost first) and the event's fields.
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let metadata = event.metadata();
+        let mut line = Map::new();
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+        line.insert("timestamp".to_string(), timestamp.into());
+        line.insert("level".to_string(), metadata.level().as_str().into());
+        line.insert("target".to_string(), metadata.target().into());
+        if let Some(scope) = ctx.event_scope() {
+            let spans: Vec<Value> = scope.from_root().map(|span| span.name().into()).collect();
+            line.insert("spans".to_string(), spans.into());
+        }
+        let mut fields = JsonFields(Map::new());
+        event.record(&mut fields);
+        line.insert("fields".to_string(), fields.0.into());
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+/// Collects event fields into a JSON object.
+struct JsonFields(Map<String, Value>);
+
+impl Visit for JsonFields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     // Note: We can't easily test logging initialization in unit tests
@@ -167,4 +366,99 @@ mod tests {
         assert!(EnvFilter::try_new("hemmer_provider_sdk=debug").is_ok());
         assert!(EnvFilter::try_new("warn,hemmer_provider_sdk=debug").is_ok());
     }
+
+    #[test]
+    fn test_log_format_parsing() {
+        assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!("Compact".parse::<LogFormat>(), Ok(LogFormat::Compact));
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn test_json_format() {
+        use std::sync::{Arc, Mutex};
+
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let writer = Arc::clone(&buffer);
+        let subscriber = tracing_subscriber::fmt()
+            .event_format(JsonFormat)
+            .with_writer(move || WriteTo(Arc::clone(&writer)))
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("plan");
+            let _entered = span.enter();
+            tracing::warn!(resource_type = "bucket", attempts = 3, "Retrying");
+        });
+
+        let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["spans"], serde_json::json!(["plan"]));
+        assert_eq!(line["fields"]["message"], "Retrying");
+        assert_eq!(line["fields"]["resource_type"], "bucket");
+        assert_eq!(line["fields"]["attempts"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_provider_macros() {
+        use std::sync::{Arc, Mutex};
+
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let writer = Arc::clone(&buffer);
+        let subscriber = tracing_subscriber::fmt()
+            .event_format(JsonFormat)
+            .with_writer(move || WriteTo(Arc::clone(&writer)))
+            .finish();
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        let context = crate::Context::new()
+            .with_operation("create")
+            .with_resource_type("example_bucket")
+            .with_request_id("7-1");
+        context
+            .scope(async {
+                let name = "logs";
+                crate::provider_info!(bucket = %name, "Created {}", "bucket");
+                crate::provider_warn!("Quota nearly used");
+            })
+            .await;
+        crate::provider_error!(attempts = 3, "Outside");
+
+        let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(
+            lines[0]["fields"],
+            serde_json::json!({
+                "message": "Created bucket",
+                "operation": "create",
+                "resource_type": "example_bucket",
+                "request_id": "7-1",
+                "bucket": "logs",
+            })
+        );
+        assert_eq!(lines[1]["level"], "WARN");
+        assert_eq!(lines[1]["fields"]["request_id"], "7-1");
+        assert_eq!(
+            lines[2]["fields"],
+            serde_json::json!({"message": "Outside", "attempts": 3})
+        );
+    }
+
+    struct WriteTo(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for WriteTo {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
 }
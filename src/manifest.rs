@@ -0,0 +1,449 @@
+//! Machine-readable provider manifests.
+//!
+//! A manifest summarizes what a provider offers (its resource, data source
+//! and function names, server capabilities, protocol version and a hash of
+//! its full schema) as a small JSON document. Registries can index providers
+//! and clients can check capabilities without starting the provider and
+//! opening a gRPC session.
+//!
+//! ```
+//! use hemmer_provider_sdk::schema::{ProviderSchema, Schema};
+//!
+//! let manifest = ProviderSchema::new()
+//!     .with_resource("bucket", Schema::v0())
+//!     .with_data_source("region", Schema::v0())
+//!     .to_manifest()
+//!     .with_provider("example", "1.2.0");
+//! assert_eq!(manifest.resources, vec!["bucket"]);
+//!
+//! let json = manifest.to_json();
+//! assert_eq!(json["name"], "example");
+//! assert_eq!(json["schema_hash"].as_str().unwrap().len(), 16);
+//! ```
+//!
+//! The devtool prints one with `my-provider manifest --name example --version 1.2.0`.
+//!
+//! # Distribution metadata
+//!
+//! The registry also needs to know how a provider is distributed: which
+//! platforms it is built for, which protocol versions it speaks, the
+//! fingerprint of the key its releases are signed with and where its changelog
+//! lives. Providers embed a [`Distribution`] in their binary by implementing
+//! [`ProviderService::distribution`](crate::ProviderService::distribution);
+//! the manifest then includes it, and [`Distribution::validate`] (run by the
+//! devtool's `manifest` and `doctor` commands) checks it is complete.
+//!
+//! ```
+//! use hemmer_provider_sdk::manifest::{Distribution, Platform};
+//!
+//! let distribution = Distribution::new()
+//!     .with_platform(Platform::new("linux", "x86_64"))
+//!     .with_platform(Platform::new("darwin", "aarch64"))
+//!     .with_signing_key_fingerprint("3AA5 C34D 1E7F 2B9C 8D41  6E0F A2B3 C4D5 E6F7 0819")
+//!     .with_changelog_url("https://github.com/example/hemmer-provider-example/releases");
+//! assert!(distribution.validate().is_empty());
+//! assert_eq!(distribution.platforms[0].to_string(), "linux_x86_64");
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ProviderError;
+use crate::schema::{Diagnostic, ProviderSchema};
+use crate::state::canonical_bytes;
+use crate::types::{ServerCapabilities, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+
+/// A summary of a provider's schema and capabilities.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderManifest {
+    /// Provider name, e.g. `"aws"`. Empty unless set with
+    /// [`with_provider`](Self::with_provider).
+    pub name: String,
+    /// Provider version, e.g. `"1.2.0"`. Empty unless set with
+    /// [`with_provider`](Self::with_provider).
+    pub version: String,
+    /// The protocol version the provider speaks.
+    pub protocol_version: u32,
+    /// Resource type names, sorted.
+    pub resources: Vec<String>,
+    /// Data source type names, sorted.
+    pub data_sources: Vec<String>,
+    /// Function names, sorted.
+    pub functions: Vec<String>,
+    /// Server capabilities.
+    pub capabilities: ServerCapabilities,
+    /// [`schema_hash`] of the full schema.
+    pub schema_hash: String,
+    /// How the provider is distributed, if it declares it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distribution: Option<Distribution>,
+}
+
+impl ProviderManifest {
+    /// Build the manifest of a schema. Prefer
+    /// [`ProviderSchema::to_manifest`].
+    pub fn new(schema: &ProviderSchema) -> Self {
+        Self {
+            name: String::new(),
+            version: String::new(),
+            protocol_version: PROTOCOL_VERSION,
+            resources: schema.resources.keys().cloned().collect(),
+            data_sources: schema.data_sources.keys().cloned().collect(),
+            functions: schema.functions.keys().cloned().collect(),
+            capabilities: ServerCapabilities::default(),
+            schema_hash: schema_hash(schema),
+            distribution: None,
+        }
+    }
+
+    /// Set the provider name and version.
+    pub fn with_provider(mut self, name: impl Into<String>, version: impl Into<String>) -> Self {
+        self.name = name.into();
+        self.version = version.into();
+        self
+    }
+
+    /// Set the server capabilities, e.g. from
+    /// [`ProviderMetadata::capabilities`](crate::ProviderMetadata::capabilities).
+    pub fn with_capabilities(mut self, capabilities: ServerCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Set the distribution metadata.
+    pub fn with_distribution(mut self, distribution: Distribution) -> Self {
+        self.distribution = Some(distribution);
+        self
+    }
+
+    /// The manifest as JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+/// A stable hash of a provider schema: 16 hex digits of the 64-bit FNV-1a hash
+/// of its [canonical JSON](crate::state::canonical_bytes).
+///
+/// Equal schemas always hash the same, regardless of the order attributes
+/// were declared in, so a changed hash means a changed schema.
+pub fn schema_hash(schema: &ProviderSchema) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let value = serde_json::to_value(schema).unwrap_or_default();
+    let hash = canonical_bytes(&value)
+        .iter()
+        .fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+        });
+    format!("{:016x}", hash)
+}
+
+/// A target platform, e.g. `linux_x86_64`.
+///
+/// `os` and `arch` use Rust's names ([`std::env::consts::OS`] and
+/// [`std::env::consts::ARCH`]); the string form joins them with `_`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Platform {
+    /// Operating system, e.g. `"linux"`, `"macos"` or `"windows"`.
+    pub os: String,
+    /// CPU architecture, e.g. `"x86_64"` or `"aarch64"`.
+    pub arch: String,
+}
+
+impl Platform {
+    /// Create a platform.
+    pub fn new(os: impl Into<String>, arch: impl Into<String>) -> Self {
+        Self {
+            os: os.into(),
+            arch: arch.into(),
+        }
+    }
+
+    /// The platform this binary was built for.
+    pub fn current() -> Self {
+        Self::new(std::env::consts::OS, std::env::consts::ARCH)
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.os, self.arch)
+    }
+}
+
+impl FromStr for Platform {
+    type Err = ProviderError;
+
+    /// Parse `<os>_<arch>`. The architecture may itself contain `_`
+    /// (`x86_64`), so the string is split at the first `_`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('_') {
+            Some((os, arch)) if !os.is_empty() && !arch.is_empty() => Ok(Self::new(os, arch)),
+            _ => Err(ProviderError::Sdk(format!(
+                "Invalid platform '{}', expected <os>_<arch>",
+                s
+            ))),
+        }
+    }
+}
+
+impl Serialize for Platform {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Platform {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Distribution metadata for the provider registry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Distribution {
+    /// Platforms release binaries are built for.
+    pub platforms: Vec<Platform>,
+    /// Protocol versions the provider speaks. Defaults to every version this
+    /// SDK supports.
+    pub protocol_versions: Vec<u32>,
+    /// Fingerprint of the OpenPGP key release artifacts are signed with, as
+    /// hex digits (spaces and colons are allowed between them).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_key_fingerprint: Option<String>,
+    /// Where users find release notes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog_url: Option<String>,
+}
+
+impl Distribution {
+    /// Create distribution metadata with no platforms, speaking the protocol
+    /// versions this SDK supports.
+    pub fn new() -> Self {
+        Self {
+            platforms: Vec::new(),
+            protocol_versions: (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).collect(),
+            signing_key_fingerprint: None,
+            changelog_url: None,
+        }
+    }
+
+    /// Add a target platform.
+    pub fn with_platform(mut self, platform: Platform) -> Self {
+        if !self.platforms.contains(&platform) {
+            self.platforms.push(platform);
+        }
+        self
+    }
+
+    /// Add several target platforms, e.g. parsed from `linux_x86_64` strings.
+    pub fn with_platforms(mut self, platforms: impl IntoIterator<Item = Platform>) -> Self {
+        for platform in platforms {
+            self = self.with_platform(platform);
+        }
+        self
+    }
+
+    /// Set the protocol versions the provider speaks.
+    pub fn with_protocol_versions(mut self, versions: impl IntoIterator<Item = u32>) -> Self {
+        self.protocol_versions = versions.into_iter().collect();
+        self
+    }
+
+    /// Set the fingerprint of the release signing key.
+    pub fn with_signing_key_fingerprint(mut self, fingerprint: impl Into<String>) -> Self {
+        self.signing_key_fingerprint = Some(fingerprint.into());
+        self
+    }
+
+    /// Set the changelog URL.
+    pub fn with_changelog_url(mut self, url: impl Into<String>) -> Self {
+        self.changelog_url = Some(url.into());
+        self
+    }
+
+    /// Check the metadata is complete enough to publish: at least one
+    /// platform, a supported protocol version, a well-formed signing key
+    /// fingerprint and an https changelog URL.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        if self.platforms.is_empty() {
+            diagnostics.push(Diagnostic::error("Distribution lists no platforms"));
+        }
+        if !self
+            .protocol_versions
+            .iter()
+            .any(|v| (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(v))
+        {
+            diagnostics.push(
+                Diagnostic::error("Distribution lists no supported protocol version").with_detail(
+                    format!(
+                        "This SDK supports protocol versions {} to {}",
+                        MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+                    ),
+                ),
+            );
+        }
+        match &self.signing_key_fingerprint {
+            None => diagnostics.push(Diagnostic::warning(
+                "Distribution has no signing key fingerprint",
+            )),
+            Some(fingerprint) => {
+                let digits: String = fingerprint
+                    .chars()
+                    .filter(|c| !matches!(c, ' ' | ':'))
+                    .collect();
+                if !matches!(digits.len(), 40 | 64)
+                    || !digits.chars().all(|c| c.is_ascii_hexdigit())
+                {
+                    diagnostics.push(
+                        Diagnostic::error("Invalid signing key fingerprint").with_detail(
+                            "Expected 40 (v4) or 64 (v5) hex digits, optionally grouped with spaces or colons",
+                        ),
+                    );
+                }
+            },
+        }
+        if let Some(url) = &self.changelog_url {
+            if !url.starts_with("https://") {
+                diagnostics.push(
+                    Diagnostic::error("Changelog URL must use https")
+                        .with_detail(format!("Got '{}'", url)),
+                );
+            }
+        }
+        diagnostics
+    }
+}
+
+impl Default for Distribution {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Attribute, AttributeType, FunctionSchema, Schema};
+
+    fn schema() -> ProviderSchema {
+        ProviderSchema::new()
+            .with_resource(
+                "bucket",
+                Schema::v0()
+                    .with_attribute("name", Attribute::required_string())
+                    .with_attribute("region", Attribute::optional_string()),
+            )
+            .with_resource("object", Schema::v0())
+            .with_data_source("region", Schema::v0())
+            .with_function("upper", FunctionSchema::new(AttributeType::String))
+    }
+
+    #[test]
+    fn test_manifest() {
+        let manifest = schema()
+            .to_manifest()
+            .with_provider("example", "1.2.0")
+            .with_capabilities(ServerCapabilities::new().with_plan_destroy());
+        assert_eq!(manifest.resources, vec!["bucket", "object"]);
+        assert_eq!(manifest.data_sources, vec!["region"]);
+        assert_eq!(manifest.functions, vec!["upper"]);
+        assert_eq!(manifest.protocol_version, PROTOCOL_VERSION);
+
+        let json = manifest.to_json();
+        assert_eq!(json["version"], "1.2.0");
+        assert_eq!(json["capabilities"]["plan_destroy"], true);
+        let parsed: ProviderManifest = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, manifest);
+    }
+
+    #[test]
+    fn test_schema_hash() {
+        let hash = schema_hash(&schema());
+        assert_eq!(hash.len(), 16);
+
+        // Declaration order doesn't matter
+        let reordered = ProviderSchema::new()
+            .with_function("upper", FunctionSchema::new(AttributeType::String))
+            .with_data_source("region", Schema::v0())
+            .with_resource("object", Schema::v0())
+            .with_resource(
+                "bucket",
+                Schema::v0()
+                    .with_attribute("region", Attribute::optional_string())
+                    .with_attribute("name", Attribute::required_string()),
+            );
+        assert_eq!(schema_hash(&reordered), hash);
+
+        // Any schema change does
+        let changed = schema().with_resource("object", Schema::new(1));
+        assert_ne!(schema_hash(&changed), hash);
+    }
+
+    #[test]
+    fn test_platform() {
+        let platform: Platform = "linux_x86_64".parse().unwrap();
+        assert_eq!(platform, Platform::new("linux", "x86_64"));
+        assert_eq!(platform.to_string(), "linux_x86_64");
+        assert!("linux".parse::<Platform>().is_err());
+        assert_eq!(
+            Platform::current().to_string(),
+            format!("{}_{}", std::env::consts::OS, std::env::consts::ARCH)
+        );
+    }
+
+    #[test]
+    fn test_distribution() {
+        let distribution = Distribution::new()
+            .with_platforms([Platform::current(), Platform::current()])
+            .with_signing_key_fingerprint("3aa5c34d1e7f2b9c8d416e0fa2b3c4d5e6f70819")
+            .with_changelog_url("https://example.com/CHANGELOG.md");
+        assert_eq!(distribution.platforms.len(), 1);
+        assert_eq!(distribution.protocol_versions, vec![PROTOCOL_VERSION]);
+        assert!(distribution.validate().is_empty());
+
+        let json = schema()
+            .to_manifest()
+            .with_distribution(distribution.clone())
+            .to_json();
+        assert_eq!(
+            json["distribution"]["platforms"][0],
+            Platform::current().to_string()
+        );
+        let parsed: ProviderManifest = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.distribution, Some(distribution));
+
+        let summaries = |d: Distribution| {
+            d.validate()
+                .into_iter()
+                .map(|d| d.summary)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            summaries(Distribution::new().with_protocol_versions([99])),
+            vec![
+                "Distribution lists no platforms",
+                "Distribution lists no supported protocol version",
+                "Distribution has no signing key fingerprint",
+            ]
+        );
+        assert_eq!(
+            summaries(
+                Distribution::new()
+                    .with_platform(Platform::current())
+                    .with_signing_key_fingerprint("ABCD")
+                    .with_changelog_url("http://example.com")
+            ),
+            vec![
+                "Invalid signing key fingerprint",
+                "Changelog URL must use https"
+            ]
+        );
+    }
+}
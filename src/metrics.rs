@@ -0,0 +1,98 @@
+//! Operation metrics hooks.
+//!
+//! A [`MetricsSink`] set with [`ServeOptions::with_metrics_sink`](crate::ServeOptions::with_metrics_sink)
+//! is told when the server starts and finishes handling each provider
+//! operation, with the operation name (the RPC, e.g. `create` or
+//! `read_data_source`), the resource or data source type, how long it took
+//! and whether it succeeded. That is enough to feed statsd, CloudWatch or
+//! Prometheus counters and timers without an OpenTelemetry pipeline.
+//!
+//! The duration covers everything the server does on the provider's behalf:
+//! state decoding and encoding, waiting for resource locks and the provider
+//! call itself. `GetSchema`, `GetMetadata` and `Stop` are not reported.
+//!
+//! Both hooks run on the request path, so they should only record or enqueue
+//! the measurement, not block on I/O.
+//!
+//! ```
+//! use std::sync::atomic::{AtomicU64, Ordering};
+//! use std::time::Duration;
+//!
+//! use hemmer_provider_sdk::metrics::{MetricsSink, Outcome};
+//! use hemmer_provider_sdk::ServeOptions;
+//!
+//! #[derive(Default)]
+//! struct FailureCounter(AtomicU64);
+//!
+//! impl MetricsSink for FailureCounter {
+//!     fn on_operation_finish(
+//!         &self,
+//!         operation: &str,
+//!         resource_type: Option<&str>,
+//!         duration: Duration,
+//!         outcome: Outcome<'_>,
+//!     ) {
+//!         if !outcome.is_success() {
+//!             self.0.fetch_add(1, Ordering::Relaxed);
+//!             eprintln!(
+//!                 "{} {} failed after {:?}",
+//!                 operation,
+//!                 resource_type.unwrap_or("-"),
+//!                 duration
+//!             );
+//!         }
+//!     }
+//! }
+//!
+//! let options = ServeOptions::new().with_metrics_sink(FailureCounter::default());
+//! ```
+
+use std::time::Duration;
+
+use crate::error::ProviderError;
+
+/// Receives a measurement for every provider operation the server handles.
+pub trait MetricsSink: Send + Sync + 'static {
+    /// Called before the server starts handling an operation.
+    ///
+    /// `resource_type` is the resource or data source type, or `None` for
+    /// provider-level operations such as `configure`.
+    fn on_operation_start(&self, operation: &str, resource_type: Option<&str>) {
+        let _ = (operation, resource_type);
+    }
+
+    /// Called when an operation has finished, successfully or not.
+    fn on_operation_finish(
+        &self,
+        operation: &str,
+        resource_type: Option<&str>,
+        duration: Duration,
+        outcome: Outcome<'_>,
+    );
+}
+
+/// How an operation ended.
+#[derive(Debug, Clone, Copy)]
+pub enum Outcome<'a> {
+    /// The operation succeeded. Validation RPCs succeed even when they report
+    /// error diagnostics.
+    Success,
+    /// The operation failed with this error.
+    Error(&'a ProviderError),
+}
+
+impl Outcome<'_> {
+    /// Whether the operation succeeded.
+    pub fn is_success(&self) -> bool {
+        matches!(self, Outcome::Success)
+    }
+}
+
+impl<'a, T> From<&'a Result<T, ProviderError>> for Outcome<'a> {
+    fn from(result: &'a Result<T, ProviderError>) -> Self {
+        match result {
+            Ok(_) => Outcome::Success,
+            Err(e) => Outcome::Error(e),
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Attribute value normalizers.
+//!
+//! APIs often treat values as equal that differ in case or whitespace
+//! (`"Us-East-1"` vs `"us-east-1"`), or return JSON documents reformatted.
+//! Without help, each of those shows up as a diff on every plan. A
+//! [`Normalizer`] attached to an attribute with
+//! [`Attribute::with_normalizer`](crate::schema::Attribute::with_normalizer)
+//! rewrites the value to one canonical spelling, and the SDK applies it with
+//! [`normalize`]:
+//!
+//! - to the configuration before validation and planning
+//!   ([`plan_from_schema`](crate::plan::plan_from_schema) and the server's
+//!   `ValidateResourceConfig` and `Plan`)
+//! - to prior states before diffing, and to states returned by create, read,
+//!   update and import, so stored states are normalized too
+//!
+//! Normalizers may run more than once on the same value, so they must be
+//! idempotent.
+//!
+//! # Example
+//!
+//! ```
+//! use hemmer_provider_sdk::normalize::{normalize, Normalizer};
+//! use hemmer_provider_sdk::schema::{Attribute, Schema};
+//! use serde_json::json;
+//!
+//! let schema = Schema::v0()
+//!     .with_attribute(
+//!         "region",
+//!         Attribute::required_string()
+//!             .with_normalizer(Normalizer::trim())
+//!             .with_normalizer(Normalizer::lowercase()),
+//!     )
+//!     .with_attribute(
+//!         "policy",
+//!         Attribute::optional_string().with_normalizer(Normalizer::json_normalize()),
+//!     );
+//!
+//! let mut config = json!({"region": " US-East-1 ", "policy": "{ \"b\": 1, \"a\": 2 }"});
+//! normalize(&schema, &mut config);
+//! assert_eq!(config, json!({"region": "us-east-1", "policy": r#"{"a":2,"b":1}"#}));
+//! ```
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::schema::{Block, BlockNestingMode, Schema};
+use crate::state::canonical_bytes;
+
+type NormalizeFn = Arc<dyn Fn(&Value) -> Value + Send + Sync>;
+
+#[derive(Clone)]
+enum Kind {
+    Lowercase,
+    Trim,
+    Json,
+    Custom(NormalizeFn),
+}
+
+/// Rewrites an attribute value to a canonical spelling.
+///
+/// The built-in normalizers apply to strings, and to each string in a list,
+/// set or map. Normalizers are serialized by name; a custom normalizer
+/// deserializes as one that leaves values unchanged.
+#[derive(Clone)]
+pub struct Normalizer {
+    name: String,
+    kind: Kind,
+}
+
+impl Normalizer {
+    /// Lowercase strings.
+    pub fn lowercase() -> Self {
+        Self::builtin("lowercase", Kind::Lowercase)
+    }
+
+    /// Trim leading and trailing whitespace from strings.
+    pub fn trim() -> Self {
+        Self::builtin("trim", Kind::Trim)
+    }
+
+    /// Rewrite strings holding JSON documents (e.g. policies) as
+    /// [canonical JSON](crate::state::canonical_bytes). Strings that aren't
+    /// valid JSON are left for validation to report.
+    pub fn json_normalize() -> Self {
+        Self::builtin("json_normalize", Kind::Json)
+    }
+
+    /// A custom normalizer, called with each non-null value of the attribute.
+    ///
+    /// `name` identifies it in serialized schemas. `f` must be idempotent.
+    pub fn custom(
+        name: impl Into<String>,
+        f: impl Fn(&Value) -> Value + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            kind: Kind::Custom(Arc::new(f)),
+        }
+    }
+
+    fn builtin(name: &str, kind: Kind) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+        }
+    }
+
+    /// The normalizer's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Normalize a single value.
+    pub fn apply(&self, value: &Value) -> Value {
+        match &self.kind {
+            Kind::Custom(f) => f(value),
+            Kind::Lowercase => map_strings(value, &|s| s.to_lowercase()),
+            Kind::Trim => map_strings(value, &|s| s.trim().to_string()),
+            Kind::Json => map_strings(value, &|s| match serde_json::from_str::<Value>(s) {
+                Ok(doc) => String::from_utf8(canonical_bytes(&doc)).unwrap_or_else(|_| s.into()),
+                Err(_) => s.to_string(),
+            }),
+        }
+    }
+}
+
+fn map_strings(value: &Value, f: &dyn Fn(&str) -> String) -> Value {
+    match value {
+        Value::String(s) => Value::String(f(s)),
+        Value::Array(items) => Value::Array(items.iter().map(|v| map_strings(v, f)).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), map_strings(v, f)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+impl PartialEq for Normalizer {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl std::fmt::Debug for Normalizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Normalizer").field(&self.name).finish()
+    }
+}
+
+impl Serialize for Normalizer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name)
+    }
+}
+
+impl<'de> Deserialize<'de> for Normalizer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(match name.as_str() {
+            "lowercase" => Self::lowercase(),
+            "trim" => Self::trim(),
+            "json_normalize" => Self::json_normalize(),
+            _ => Self::custom(name, Value::clone),
+        })
+    }
+}
+
+/// Apply the schema's attribute normalizers to a configuration or state, in
+/// place. Null values are left alone.
+pub fn normalize(schema: &Schema, value: &mut Value) {
+    normalize_block(&schema.block, value);
+}
+
+/// Whether any attribute in the schema has a normalizer.
+pub fn has_normalizers(schema: &Schema) -> bool {
+    fn block_has(block: &Block) -> bool {
+        block.attributes.values().any(|a| !a.normalizers.is_empty())
+            || block.blocks.values().any(|nested| block_has(&nested.block))
+    }
+    block_has(&schema.block)
+}
+
+fn normalize_block(block: &Block, value: &mut Value) {
+    let Value::Object(map) = value else {
+        return;
+    };
+    for (name, attr) in &block.attributes {
+        if attr.normalizers.is_empty() {
+            continue;
+        }
+        if let Some(value) = map.get_mut(name).filter(|v| !v.is_null()) {
+            for normalizer in &attr.normalizers {
+                *value = normalizer.apply(value);
+            }
+        }
+    }
+    for (name, nested) in &block.blocks {
+        match (nested.nesting_mode, map.get_mut(name)) {
+            (BlockNestingMode::Single, Some(item)) => normalize_block(&nested.block, item),
+            (BlockNestingMode::List | BlockNestingMode::Set, Some(Value::Array(items))) => items
+                .iter_mut()
+                .for_each(|item| normalize_block(&nested.block, item)),
+            (BlockNestingMode::Map, Some(Value::Object(items))) => items
+                .values_mut()
+                .for_each(|item| normalize_block(&nested.block, item)),
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Attribute, AttributeFlags, AttributeType, NestedBlock};
+    use serde_json::json;
+
+    #[test]
+    fn test_builtin_normalizers() {
+        assert_eq!(Normalizer::lowercase().apply(&json!("ABC")), json!("abc"));
+        assert_eq!(
+            Normalizer::trim().apply(&json!(["  a", "b  "])),
+            json!(["a", "b"])
+        );
+        assert_eq!(
+            Normalizer::json_normalize().apply(&json!("{ \"b\": [1.0], \"a\": null }")),
+            json!(r#"{"a":null,"b":[1]}"#)
+        );
+        assert_eq!(
+            Normalizer::json_normalize().apply(&json!("not json")),
+            json!("not json")
+        );
+        assert_eq!(Normalizer::lowercase().apply(&json!(42)), json!(42));
+    }
+
+    #[test]
+    fn test_normalize_nested_blocks() {
+        let strip_dot = Normalizer::custom("strip_trailing_dot", |v| match v.as_str() {
+            Some(s) => json!(s.trim_end_matches('.')),
+            None => v.clone(),
+        });
+        let record = Block::new().with_attribute(
+            "name",
+            Attribute::required_string()
+                .with_normalizer(Normalizer::lowercase())
+                .with_normalizer(strip_dot),
+        );
+        let schema = Schema::v0()
+            .with_attribute(
+                "tags",
+                Attribute::new(
+                    AttributeType::map(AttributeType::String),
+                    AttributeFlags::optional(),
+                )
+                .with_normalizer(Normalizer::trim()),
+            )
+            .with_attribute(
+                "zone",
+                Attribute::optional_string().with_normalizer(Normalizer::lowercase()),
+            )
+            .with_block("record", NestedBlock::list(record.clone()))
+            .with_block("primary", NestedBlock::single(record));
+        assert!(has_normalizers(&schema));
+        assert!(!has_normalizers(&Schema::v0()));
+
+        let mut config = json!({
+            "tags": {"env": " prod "},
+            "zone": null,
+            "record": [{"name": "WWW.Example.com."}, {"name": "api.example.com"}],
+            "primary": {"name": "Example.COM."},
+        });
+        normalize(&schema, &mut config);
+        assert_eq!(
+            config,
+            json!({
+                "tags": {"env": "prod"},
+                "zone": null,
+                "record": [{"name": "www.example.com"}, {"name": "api.example.com"}],
+                "primary": {"name": "example.com"},
+            })
+        );
+    }
+
+    #[test]
+    fn test_normalizer_serde() {
+        let attr = Attribute::required_string()
+            .with_normalizer(Normalizer::lowercase())
+            .with_normalizer(Normalizer::custom("upper", |v| v.clone()));
+        let json = serde_json::to_value(&attr).unwrap();
+        assert_eq!(json["normalizers"], json!(["lowercase", "upper"]));
+
+        let back: Attribute = serde_json::from_value(json).unwrap();
+        assert_eq!(back, attr);
+        // Custom normalizers can't be restored, so they leave values unchanged
+        assert_eq!(back.normalizers[1].apply(&json!("a")), json!("a"));
+    }
+}
@@ -0,0 +1,308 @@
+//! Precision-safe integer handling.
+//!
+//! JSON has one number type, and many clients parse it as a float64. An
+//! int64 beyond 2^53 that passes through one of them arrives rounded
+//! (`9007199254740993` becomes `9007199254740992.0`), which silently corrupts
+//! numeric IDs. The helpers here accept an integer only when it is exact:
+//!
+//! - [`exact_i64`] reads an int64 attribute, accepting integral floats only
+//!   up to [`MAX_SAFE_INTEGER`] and failing with
+//!   [`NumberError::PrecisionLoss`] beyond it
+//! - [`id_string`] reads an ID that may be a string or a number, keeping
+//!   integer digits as written
+//!
+//! Schema validation reports int64 attributes that lost precision with a
+//! diagnostic instead of accepting them, and the `int64` accessors of
+//! [`PathValue`](crate::value::PathValue) use [`exact_i64`].
+//!
+//! Integers beyond the int64 range (128-bit IDs, say) survive end to end
+//! only with the `arbitrary-precision` feature, which keeps numbers as
+//! written instead of converting them to `f64`; [`id_string`] then returns
+//! their digits unchanged.
+//!
+//! Decimals are still rounded to the nearest `f64` when the server parses a
+//! payload, unless it is served with
+//! [`ServeOptions::with_exact_numbers`](crate::ServeOptions::with_exact_numbers).
+//! Then prices, balances and metric values round-trip digit for digit, and
+//! [`canonical_eq`](crate::state::canonical_eq) (which plans use to detect
+//! changes) compares them exactly, so `0.1` and `0.10` are equal but
+//! `0.1000000000000000000001` is a change.
+//!
+//! ```
+//! use hemmer_provider_sdk::number::{exact_i64, id_string, NumberError};
+//! use serde_json::json;
+//!
+//! assert_eq!(exact_i64(&json!(9007199254740993_i64)), Ok(9007199254740993));
+//! assert_eq!(exact_i64(&json!(42.0)), Ok(42));
+//! assert!(matches!(
+//!     exact_i64(&json!(9007199254740992.0)),
+//!     Err(NumberError::PrecisionLoss(_))
+//! ));
+//! assert_eq!(id_string(&json!(9007199254740993_i64)).unwrap(), "9007199254740993");
+//! ```
+
+use std::fmt;
+
+use serde_json::{Number, Value};
+
+use crate::validation::value_type_name;
+
+/// The largest integer a float64 holds exactly, along with every integer
+/// below it (2^53 - 1).
+pub const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// Why a value is not an exact integer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NumberError {
+    /// The value is not a number (or, for IDs, not a string or number).
+    NotANumber(&'static str),
+    /// The number has a fractional part.
+    NotAnInteger(String),
+    /// The integer does not fit in an int64.
+    OutOfRange(String),
+    /// The integer arrived as a float beyond [`MAX_SAFE_INTEGER`], so it may
+    /// have been rounded.
+    PrecisionLoss(String),
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotANumber(got) => write!(f, "expected an integer, got {}", got),
+            Self::NotAnInteger(n) => write!(f, "{} is not an integer", n),
+            Self::OutOfRange(n) => write!(f, "{} is out of range for int64", n),
+            Self::PrecisionLoss(n) => write!(
+                f,
+                "{} arrived as a float beyond 2^53 and may have lost precision",
+                n
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NumberError {}
+
+/// Whether `n` was written as an integer (digits only), rather than as a
+/// float.
+pub(crate) fn is_integer_literal(n: &Number) -> bool {
+    if n.is_i64() || n.is_u64() {
+        return true;
+    }
+    let text = n.to_string();
+    let digits = text.strip_prefix('-').unwrap_or(&text);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Classify a float that should hold an integer.
+fn float_integer(n: &Number) -> Result<i64, NumberError> {
+    let f = n.as_f64().unwrap_or(f64::NAN);
+    if f.fract() != 0.0 || !f.is_finite() {
+        Err(NumberError::NotAnInteger(n.to_string()))
+    } else if f.abs() > MAX_SAFE_INTEGER as f64 {
+        Err(NumberError::PrecisionLoss(n.to_string()))
+    } else {
+        Ok(f as i64)
+    }
+}
+
+/// Read an int64 exactly.
+///
+/// Integers are accepted across the int64 range; floats only when they are
+/// integral and no larger in magnitude than [`MAX_SAFE_INTEGER`].
+pub fn exact_i64(value: &Value) -> Result<i64, NumberError> {
+    let Value::Number(n) = value else {
+        return Err(NumberError::NotANumber(value_type_name(value)));
+    };
+    if let Some(i) = n.as_i64() {
+        Ok(i)
+    } else if is_integer_literal(n) {
+        Err(NumberError::OutOfRange(n.to_string()))
+    } else {
+        float_integer(n)
+    }
+}
+
+/// Whether `value` is a float that stands for an integer it may not hold
+/// exactly.
+pub fn is_precision_lost(value: &Value) -> bool {
+    matches!(exact_i64(value), Err(NumberError::PrecisionLoss(_)))
+}
+
+/// Read an ID that may be a string or a number.
+///
+/// Strings are returned as they are and integers as their digits (of any
+/// size, with the `arbitrary-precision` feature). Floats are accepted only
+/// when they hold an integer exactly.
+pub fn id_string(value: &Value) -> Result<String, NumberError> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) if is_integer_literal(n) => Ok(n.to_string()),
+        Value::Number(n) => float_integer(n).map(|i| i.to_string()),
+        other => Err(NumberError::NotANumber(value_type_name(other))),
+    }
+}
+
+/// The longest [`plain_decimal`] written out; beyond it, exponents are kept.
+#[cfg(feature = "arbitrary-precision")]
+const MAX_PLAIN_DIGITS: i64 = 1024;
+
+/// Round every decimal in `value` to the nearest `f64`, as serde_json
+/// without arbitrary precision parses it. Integers keep all their digits.
+#[cfg(feature = "arbitrary-precision")]
+pub(crate) fn round_decimals(value: &mut Value) {
+    match value {
+        Value::Number(n) if !is_integer_literal(n) => {
+            if let Some(rounded) = n.as_f64().and_then(Number::from_f64) {
+                *n = rounded;
+            }
+        },
+        Value::Array(items) => items.iter_mut().for_each(round_decimals),
+        Value::Object(map) => map.values_mut().for_each(round_decimals),
+        _ => {},
+    }
+}
+
+/// The exact value of the JSON number `text` without an exponent, leading
+/// zeros or trailing fractional zeros, e.g. `1.50e2` is `150` and `-0.0` is
+/// `0`. `None` if `text` is not a number or its value would be longer than
+/// [`MAX_PLAIN_DIGITS`].
+#[cfg(feature = "arbitrary-precision")]
+pub(crate) fn plain_decimal(text: &str) -> Option<String> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if integer.is_empty() || !(integer.bytes().chain(fraction.bytes())).all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    // The significant digits, and where the decimal point falls among them
+    let digits = format!("{}{}", integer, fraction);
+    let leading = digits.len() - digits.trim_start_matches('0').len();
+    let digits = digits.trim_start_matches('0').trim_end_matches('0');
+    if digits.is_empty() {
+        return Some("0".to_string());
+    }
+    let point = (integer.len() as i64)
+        .checked_add(exponent)?
+        .checked_sub(leading as i64)?;
+    let len = digits.len() as i64;
+    if point.abs() > MAX_PLAIN_DIGITS || len > MAX_PLAIN_DIGITS {
+        return None;
+    }
+
+    let plain = if point <= 0 {
+        format!("0.{}{}", "0".repeat(-point as usize), digits)
+    } else if point >= len {
+        format!("{}{}", digits, "0".repeat((point - len) as usize))
+    } else {
+        let (integer, fraction) = digits.split_at(point as usize);
+        format!("{}.{}", integer, fraction)
+    };
+    Some(if negative {
+        format!("-{}", plain)
+    } else {
+        plain
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_exact_i64() {
+        assert_eq!(exact_i64(&json!(i64::MAX)), Ok(i64::MAX));
+        assert_eq!(exact_i64(&json!(-5.0)), Ok(-5));
+        assert_eq!(
+            exact_i64(&json!(MAX_SAFE_INTEGER as f64)),
+            Ok(MAX_SAFE_INTEGER)
+        );
+        assert_eq!(
+            exact_i64(&json!(1.5)),
+            Err(NumberError::NotAnInteger("1.5".to_string()))
+        );
+        assert_eq!(
+            exact_i64(&json!(u64::MAX)),
+            Err(NumberError::OutOfRange(u64::MAX.to_string()))
+        );
+        assert!(is_precision_lost(&json!(1e19)));
+        assert!(is_precision_lost(&json!(-9007199254740992.0)));
+        assert!(!is_precision_lost(&json!(9007199254740992_i64)));
+        assert_eq!(
+            exact_i64(&json!("1")),
+            Err(NumberError::NotANumber("string"))
+        );
+
+        // A large ID that went through a float64 parser
+        let parsed: Value = serde_json::from_str("9007199254740993.0").unwrap();
+        let err = exact_i64(&parsed).unwrap_err();
+        assert!(err.to_string().contains("may have lost precision"));
+    }
+
+    #[test]
+    fn test_id_string() {
+        assert_eq!(id_string(&json!("i-0abc")).unwrap(), "i-0abc");
+        assert_eq!(id_string(&json!(u64::MAX)).unwrap(), u64::MAX.to_string());
+        assert_eq!(id_string(&json!(42.0)).unwrap(), "42");
+        assert!(matches!(
+            id_string(&json!(1e300)),
+            Err(NumberError::PrecisionLoss(_))
+        ));
+        assert_eq!(
+            id_string(&json!(true)),
+            Err(NumberError::NotANumber("bool"))
+        );
+    }
+
+    #[cfg(feature = "arbitrary-precision")]
+    #[test]
+    fn test_arbitrary_precision_ids() {
+        let id: Value = serde_json::from_str("340282366920938463463374607431768211455").unwrap();
+        assert_eq!(
+            id_string(&id).unwrap(),
+            "340282366920938463463374607431768211455"
+        );
+        assert!(matches!(exact_i64(&id), Err(NumberError::OutOfRange(_))));
+        assert_eq!(
+            serde_json::to_string(&id).unwrap(),
+            "340282366920938463463374607431768211455"
+        );
+    }
+
+    #[cfg(feature = "arbitrary-precision")]
+    #[test]
+    fn test_plain_decimal() {
+        for (text, plain) in [
+            ("0", "0"),
+            ("-0.0", "0"),
+            ("007", "7"),
+            ("1.50e2", "150"),
+            ("1E-3", "0.001"),
+            ("-12.3400", "-12.34"),
+            ("0.1000000000000000000001", "0.1000000000000000000001"),
+            ("123e+2", "12300"),
+        ] {
+            assert_eq!(plain_decimal(text).as_deref(), Some(plain), "{}", text);
+        }
+        assert_eq!(plain_decimal("1e100000"), None);
+        assert_eq!(plain_decimal("abc"), None);
+
+        let mut value: Value = serde_json::from_str(
+            r#"{"price": 0.1000000000000000000001, "ids": [18446744073709551616]}"#,
+        )
+        .unwrap();
+        round_decimals(&mut value);
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"{"ids":[18446744073709551616],"price":0.1}"#
+        );
+    }
+}
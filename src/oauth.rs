@@ -0,0 +1,440 @@
+//! OAuth2 access tokens that renew themselves (feature: `http`).
+//!
+//! A [`TokenSource`] fetches access tokens with the client-credentials or
+//! refresh-token flow, caches them and renews them before they expire, so a
+//! long apply doesn't fail halfway through with an expired token. Build one in
+//! `configure` and store it as provider data; clones share the cached token.
+//!
+//! When a caller asks for a token that expires within the renewal window
+//! ([`TokenSource::with_renew_before`], default 60 seconds), the cached token
+//! is returned right away and a new one is fetched in the background. Only an
+//! expired token makes the caller wait. Background renewals stop when the
+//! operation [`Context`] is cancelled, e.g. on server shutdown.
+//!
+//! Tokens and client secrets never appear in `Debug` output or logs.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hemmer_provider_sdk::context::Context;
+//! use hemmer_provider_sdk::http::HttpClient;
+//! use hemmer_provider_sdk::oauth::TokenSource;
+//! use hemmer_provider_sdk::types::ConfigureResult;
+//! use hemmer_provider_sdk::ProviderError;
+//!
+//! async fn configure(config: serde_json::Value) -> Result<ConfigureResult, ProviderError> {
+//!     let client = HttpClient::builder("example", env!("CARGO_PKG_VERSION")).build()?;
+//!     let tokens = TokenSource::client_credentials(
+//!         client,
+//!         "https://auth.example.com/oauth/token",
+//!         config["client_id"].as_str().unwrap_or_default(),
+//!         config["client_secret"].as_str().unwrap_or_default(),
+//!     )
+//!     .with_scopes(["servers:write"]);
+//!     Ok(ConfigureResult::new().with_provider_data(tokens))
+//! }
+//!
+//! async fn read_server(id: &str) -> Result<serde_json::Value, ProviderError> {
+//!     let tokens = Context::current().provider_data::<TokenSource>()?;
+//!     let client = tokens.client();
+//!     let url = format!("https://api.example.com/v1/servers/{}", id);
+//!     let request = tokens.authorize(client.get(&url)).await?;
+//!     let response = client.send(request).await?;
+//!     # let _ = response;
+//!     # Ok(serde_json::Value::Null)
+//! }
+//! ```
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::context::Context;
+use crate::error::ProviderError;
+use crate::http::{error_for_status, HttpClient};
+
+/// An OAuth2 access token.
+#[derive(Clone)]
+pub struct Token {
+    access_token: String,
+    token_type: String,
+    expires_at: Option<Instant>,
+}
+
+impl Token {
+    /// The access token itself. Don't log it.
+    pub fn secret(&self) -> &str {
+        &self.access_token
+    }
+
+    /// The token type, usually `Bearer`.
+    pub fn token_type(&self) -> &str {
+        &self.token_type
+    }
+
+    /// When the token expires, if the server said.
+    pub fn expires_at(&self) -> Option<Instant> {
+        self.expires_at
+    }
+
+    /// Whether the token expires within `margin` from now.
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Instant::now() + margin)
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Token")
+            .field("token_type", &self.token_type)
+            .field("expires_at", &self.expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How a [`TokenSource`] obtains tokens.
+#[derive(Clone)]
+enum Grant {
+    ClientCredentials,
+    RefreshToken(String),
+}
+
+/// Fetches, caches and renews OAuth2 access tokens.
+#[derive(Clone)]
+pub struct TokenSource {
+    client: HttpClient,
+    token_url: String,
+    client_id: String,
+    client_secret: Option<String>,
+    scopes: Vec<String>,
+    renew_before: Duration,
+    state: Arc<State>,
+}
+
+/// State shared by clones of a [`TokenSource`].
+struct State {
+    grant: Mutex<Grant>,
+    cached: tokio::sync::Mutex<Option<Token>>,
+    renewing: AtomicBool,
+}
+
+/// A successful token endpoint response (RFC 6749 section 5.1).
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    token_type: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+impl TokenSource {
+    /// A source using the client-credentials flow.
+    pub fn client_credentials(
+        client: HttpClient,
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self::new(client, token_url, client_id, Grant::ClientCredentials)
+            .with_client_secret(client_secret)
+    }
+
+    /// A source using the refresh-token flow. If the server rotates the
+    /// refresh token, the new one is used from then on.
+    pub fn refresh_token(
+        client: HttpClient,
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        refresh_token: impl Into<String>,
+    ) -> Self {
+        Self::new(
+            client,
+            token_url,
+            client_id,
+            Grant::RefreshToken(refresh_token.into()),
+        )
+    }
+
+    fn new(
+        client: HttpClient,
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        grant: Grant,
+    ) -> Self {
+        Self {
+            client,
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: None,
+            scopes: Vec::new(),
+            renew_before: Duration::from_secs(60),
+            state: Arc::new(State {
+                grant: Mutex::new(grant),
+                cached: tokio::sync::Mutex::new(None),
+                renewing: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Set the client secret (needed by confidential clients in the
+    /// refresh-token flow).
+    pub fn with_client_secret(mut self, secret: impl Into<String>) -> Self {
+        self.client_secret = Some(secret.into());
+        self
+    }
+
+    /// Request these scopes.
+    pub fn with_scopes(mut self, scopes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Renew tokens this long before they expire. Default: 60 seconds.
+    pub fn with_renew_before(mut self, margin: Duration) -> Self {
+        self.renew_before = margin;
+        self
+    }
+
+    /// The HTTP client tokens are fetched with, for sending the authorized
+    /// requests too.
+    pub fn client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    /// A valid access token.
+    ///
+    /// Returns the cached token unless it has expired, starting a background
+    /// renewal if it expires soon.
+    pub async fn token(&self) -> Result<Token, ProviderError> {
+        let mut cached = self.state.cached.lock().await;
+        if let Some(token) = cached.as_ref() {
+            if !token.expires_within(Duration::ZERO) {
+                if token.expires_within(self.renew_before) {
+                    self.renew_in_background();
+                }
+                return Ok(token.clone());
+            }
+        }
+        let token = self.fetch().await?;
+        *cached = Some(token.clone());
+        Ok(token)
+    }
+
+    /// Add the `Authorization` header to a request.
+    pub async fn authorize(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<RequestBuilder, ProviderError> {
+        let token = self.token().await?;
+        Ok(request.header(
+            reqwest::header::AUTHORIZATION,
+            format!("{} {}", token.token_type, token.access_token),
+        ))
+    }
+
+    /// Drop the cached token, e.g. after the API rejected it with 401, so the
+    /// next call fetches a new one.
+    pub async fn invalidate(&self) {
+        *self.state.cached.lock().await = None;
+    }
+
+    fn renew_in_background(&self) {
+        if self.state.renewing.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let source = self.clone();
+        let context = Context::current();
+        tokio::spawn(async move {
+            tokio::select! {
+                result = source.fetch() => match result {
+                    Ok(token) => *source.state.cached.lock().await = Some(token),
+                    // The current token is still valid; the next call tries again
+                    Err(e) => warn!(url = %source.token_url, error = %e, "Failed to renew OAuth2 token"),
+                },
+                _ = context.cancelled() => {},
+            }
+            source.state.renewing.store(false, Ordering::SeqCst);
+        });
+    }
+
+    async fn fetch(&self) -> Result<Token, ProviderError> {
+        let grant = self.state.grant.lock().unwrap().clone();
+        let mut form = vec![("client_id", self.client_id.as_str())];
+        match &grant {
+            Grant::ClientCredentials => form.push(("grant_type", "client_credentials")),
+            Grant::RefreshToken(token) => {
+                form.push(("grant_type", "refresh_token"));
+                form.push(("refresh_token", token));
+            },
+        }
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret));
+        }
+        let scope = self.scopes.join(" ");
+        if !scope.is_empty() {
+            form.push(("scope", &scope));
+        }
+
+        debug!(url = %self.token_url, client_id = %self.client_id, "Fetching OAuth2 token");
+        let requested_at = Instant::now();
+        let response = self
+            .client
+            .send(self.client.post(&self.token_url).form(&form))
+            .await?;
+        // OAuth2 servers reject bad credentials with 400 and 401 alike
+        let response = error_for_status(response).await.map_err(|e| match e {
+            ProviderError::Validation(message) => ProviderError::PermissionDenied(message),
+            e => e,
+        })?;
+        let body: TokenResponse = response.json().await.map_err(|e| {
+            ProviderError::Sdk(format!(
+                "Invalid token response from {}: {}",
+                self.token_url, e
+            ))
+        })?;
+
+        if let (Some(rotated), Grant::RefreshToken(_)) = (body.refresh_token, &grant) {
+            *self.state.grant.lock().unwrap() = Grant::RefreshToken(rotated);
+        }
+        Ok(Token {
+            access_token: body.access_token,
+            token_type: body.token_type.unwrap_or_else(|| "Bearer".to_string()),
+            expires_at: body
+                .expires_in
+                .map(|secs| requested_at + Duration::from_secs(secs)),
+        })
+    }
+}
+
+impl fmt::Debug for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenSource")
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("scopes", &self.scopes)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve the JSON bodies as token responses, recording each request body.
+    async fn token_server(bodies: Vec<&'static str>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            for body in bodies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request);
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head
+                            .lines()
+                            .find_map(|l| {
+                                l.to_lowercase()
+                                    .strip_prefix("content-length: ")
+                                    .map(String::from)
+                            })
+                            .and_then(|l| l.trim().parse().ok())
+                            .unwrap_or(0);
+                        if body.len() >= length {
+                            recorded.lock().unwrap().push(body.to_string());
+                            break;
+                        }
+                    }
+                }
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn client() -> HttpClient {
+        HttpClient::builder("test", "1.0.0")
+            .without_proxy()
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_client_credentials_renewal() {
+        let (url, requests) = token_server(vec![
+            r#"{"access_token": "t1", "token_type": "Bearer", "expires_in": 30}"#,
+            r#"{"access_token": "t2", "token_type": "Bearer", "expires_in": 3600}"#,
+        ])
+        .await;
+        let tokens = TokenSource::client_credentials(client(), url, "app", "s3cr3t")
+            .with_scopes(["read", "write"]);
+
+        // Fetched on first use, then served from the cache
+        assert_eq!(tokens.token().await.unwrap().secret(), "t1");
+        assert_eq!(
+            requests.lock().unwrap()[0],
+            "client_id=app&grant_type=client_credentials&client_secret=s3cr3t&scope=read+write"
+        );
+
+        // t1 expires within the renewal window: still returned, renewed in the background
+        assert_eq!(tokens.token().await.unwrap().secret(), "t1");
+        let mut token = tokens.token().await.unwrap();
+        for _ in 0..100 {
+            if token.secret() == "t2" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            token = tokens.token().await.unwrap();
+        }
+        assert_eq!(token.secret(), "t2");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+
+        assert!(!format!("{:?} {:?}", tokens, token).contains("s3cr3t"));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_rotation() {
+        let (url, requests) = token_server(vec![
+            r#"{"access_token": "a1", "refresh_token": "r2"}"#,
+            r#"{"access_token": "a2"}"#,
+        ])
+        .await;
+        let tokens = TokenSource::refresh_token(client(), url, "app", "r1");
+
+        let request = tokens
+            .authorize(tokens.client().get("http://localhost/"))
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["authorization"], "Bearer a1");
+
+        tokens.invalidate().await;
+        assert_eq!(tokens.token().await.unwrap().secret(), "a2");
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].contains("refresh_token=r1"));
+        assert!(requests[1].contains("refresh_token=r2"));
+    }
+}
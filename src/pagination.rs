@@ -0,0 +1,381 @@
+//! Pagination helpers for list-style data sources.
+//!
+//! [`Paginator`] walks a paged list API and returns the combined results as a
+//! single [`Page`]. Two strategies are supported:
+//!
+//! - [`Paginator::collect_tokens`] for APIs that return an opaque continuation token
+//! - [`Paginator::collect_offsets`] for offset/limit APIs, fetching up to
+//!   [`concurrency`](Paginator::with_concurrency) pages at once
+//!
+//! Data sources can expose the standard `page_size` and `next_token` attributes
+//! with [`Schema::with_pagination_attributes`] and build a paginator from the
+//! configuration with [`Paginator::from_config`].
+//!
+//! # Example
+//!
+//! ```
+//! use hemmer_provider_sdk::pagination::{Page, Paginator};
+//!
+//! # tokio_test::block_on(async {
+//! let all: Vec<u32> = (0..25).collect();
+//!
+//! let page = Paginator::new(10)
+//!     .with_concurrency(4)
+//!     .collect_offsets(|offset, limit| {
+//!         let all = all.clone();
+//!         async move {
+//!             let items = all.iter().copied().skip(offset as usize).take(limit as usize).collect();
+//!             Ok(Page::new(items))
+//!         }
+//!     })
+//!     .await
+//!     .unwrap();
+//!
+//! assert_eq!(page.items, all);
+//! assert_eq!(page.next_token, None);
+//! # });
+//! ```
+
+use std::future::Future;
+
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use serde_json::Value;
+
+use crate::error::ProviderError;
+use crate::schema::{Attribute, AttributeFlags, AttributeType, Schema};
+
+/// Name of the standard page size attribute.
+pub const PAGE_SIZE_ATTRIBUTE: &str = "page_size";
+
+/// Name of the standard continuation token attribute.
+pub const NEXT_TOKEN_ATTRIBUTE: &str = "next_token";
+
+/// A page of results from a list API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    /// Items on this page.
+    pub items: Vec<T>,
+    /// Token for the next page, if there is one.
+    pub next_token: Option<String>,
+    /// Total number of items across all pages, if the API reports it.
+    pub total: Option<u64>,
+}
+
+impl<T> Page<T> {
+    /// Create a page with no continuation token.
+    pub fn new(items: Vec<T>) -> Self {
+        Self {
+            items,
+            next_token: None,
+            total: None,
+        }
+    }
+
+    /// Set the continuation token.
+    pub fn with_next_token(mut self, token: Option<String>) -> Self {
+        self.next_token = token.filter(|t| !t.is_empty());
+        self
+    }
+
+    /// Set the total item count reported by the API.
+    pub fn with_total(mut self, total: u64) -> Self {
+        self.total = Some(total);
+        self
+    }
+}
+
+/// Walks a paged list API and collects the results.
+#[derive(Debug, Clone)]
+pub struct Paginator {
+    page_size: u32,
+    concurrency: usize,
+    max_items: Option<usize>,
+    start_token: Option<String>,
+}
+
+impl Paginator {
+    /// Create a paginator that requests `page_size` items per page.
+    pub fn new(page_size: u32) -> Self {
+        Self {
+            page_size: page_size.max(1),
+            concurrency: 1,
+            max_items: None,
+            start_token: None,
+        }
+    }
+
+    /// Create a paginator from a data source config using the standard attributes.
+    ///
+    /// `page_size` falls back to `default_page_size`; a `next_token` in the config
+    /// is used as the starting token (or offset).
+    pub fn from_config(config: &Value, default_page_size: u32) -> Self {
+        let page_size = config
+            .get(PAGE_SIZE_ATTRIBUTE)
+            .and_then(Value::as_u64)
+            .map(|n| n.min(u32::MAX as u64) as u32)
+            .unwrap_or(default_page_size);
+        let mut paginator = Self::new(page_size);
+        paginator.start_token = config
+            .get(NEXT_TOKEN_ATTRIBUTE)
+            .and_then(Value::as_str)
+            .filter(|t| !t.is_empty())
+            .map(String::from);
+        paginator
+    }
+
+    /// Fetch up to `concurrency` offset pages at once (offset strategy only).
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Stop after collecting `max_items` items.
+    pub fn with_max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    /// Start from a continuation token (or offset, for the offset strategy).
+    pub fn with_start_token(mut self, token: impl Into<String>) -> Self {
+        self.start_token = Some(token.into());
+        self
+    }
+
+    /// The number of items requested per page.
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    /// Collect all pages from a token-based API.
+    ///
+    /// `fetch` receives the token for the page to load (`None` for the first
+    /// page) and the page size. If collection stops early because of
+    /// [`with_max_items`](Self::with_max_items), the returned page carries the
+    /// token to resume from.
+    pub async fn collect_tokens<T, F, Fut>(&self, mut fetch: F) -> Result<Page<T>, ProviderError>
+    where
+        F: FnMut(Option<String>, u32) -> Fut,
+        Fut: Future<Output = Result<Page<T>, ProviderError>>,
+    {
+        let mut items = Vec::new();
+        let mut token = self.start_token.clone();
+        let mut total = None;
+
+        loop {
+            let page = fetch(token.take(), self.page_size).await?;
+            total = page.total.or(total);
+            items.extend(page.items);
+            token = page.next_token;
+
+            if token.is_none() || self.is_full(items.len()) {
+                break;
+            }
+        }
+
+        if let Some(max) = self.max_items {
+            items.truncate(max);
+        }
+        Ok(Page {
+            items,
+            next_token: token,
+            total,
+        })
+    }
+
+    /// Collect all pages from an offset/limit API.
+    ///
+    /// `fetch` receives the offset and limit of the page to load. A page with
+    /// fewer than `limit` items ends the listing. When the first page reports a
+    /// [`total`](Page::total), the remaining pages are fetched concurrently;
+    /// otherwise pages are fetched in speculative batches of `concurrency`.
+    pub async fn collect_offsets<T, F, Fut>(&self, fetch: F) -> Result<Page<T>, ProviderError>
+    where
+        F: Fn(u64, u32) -> Fut,
+        Fut: Future<Output = Result<Page<T>, ProviderError>>,
+    {
+        let limit = self.page_size;
+        let start = match &self.start_token {
+            Some(token) => token.parse::<u64>().map_err(|_| {
+                ProviderError::Validation(format!("Invalid pagination offset: {}", token))
+            })?,
+            None => 0,
+        };
+
+        let first = fetch(start, limit).await?;
+        let total = first.total;
+        let mut done = (first.items.len() as u64) < limit as u64;
+        let mut items = first.items;
+        let mut offset = start + limit as u64;
+
+        while !done && !self.is_full(items.len()) {
+            let batch = match total {
+                Some(total) => total.saturating_sub(offset).div_ceil(limit as u64),
+                None => self.concurrency as u64,
+            };
+            if batch == 0 {
+                break;
+            }
+
+            let offsets = (0..batch).map(|i| offset + i * limit as u64);
+            let pages: Vec<Page<T>> = stream::iter(offsets.map(|o| fetch(o, limit)))
+                .buffered(self.concurrency)
+                .try_collect()
+                .await?;
+
+            for page in pages {
+                offset += limit as u64;
+                let short = (page.items.len() as u64) < limit as u64;
+                items.extend(page.items);
+                if short {
+                    done = true;
+                    break;
+                }
+            }
+            if total.is_some() {
+                done = true;
+            }
+        }
+
+        let mut next_token = (!done).then(|| offset.to_string());
+        if let Some(max) = self.max_items {
+            if items.len() > max {
+                items.truncate(max);
+                next_token = Some((start + max as u64).to_string());
+            }
+        }
+        Ok(Page {
+            items,
+            next_token,
+            total,
+        })
+    }
+
+    fn is_full(&self, collected: usize) -> bool {
+        self.max_items.is_some_and(|max| collected >= max)
+    }
+}
+
+impl Schema {
+    /// Add the standard `page_size` and `next_token` pagination attributes.
+    pub fn with_pagination_attributes(self) -> Self {
+        self.with_attribute(
+            PAGE_SIZE_ATTRIBUTE,
+            Attribute::optional_int64().with_description("Maximum number of items per page"),
+        )
+        .with_attribute(
+            NEXT_TOKEN_ATTRIBUTE,
+            Attribute::new(AttributeType::String, AttributeFlags::optional_computed())
+                .with_description("Token to resume listing from; set when results were truncated"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn offset_page(all: &[u32], offset: u64, limit: u32) -> Page<u32> {
+        Page::new(
+            all.iter()
+                .copied()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_collect_tokens() {
+        let page = Paginator::new(2)
+            .collect_tokens(|token, size| async move {
+                let start: u32 = token.map(|t| t.parse().unwrap()).unwrap_or(0);
+                let items: Vec<u32> = (start..(start + size).min(5)).collect();
+                let next = (start + size < 5).then(|| (start + size).to_string());
+                Ok(Page::new(items).with_next_token(next))
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(page.items, vec![0, 1, 2, 3, 4]);
+        assert_eq!(page.next_token, None);
+    }
+
+    #[tokio::test]
+    async fn test_collect_tokens_max_items_returns_resume_token() {
+        let page = Paginator::new(2)
+            .with_max_items(3)
+            .collect_tokens(|token, _| async move {
+                let n: u32 = token.map(|t| t.parse().unwrap()).unwrap_or(0);
+                Ok(Page::new(vec![n, n + 1]).with_next_token(Some((n + 2).to_string())))
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(page.items, vec![0, 1, 2]);
+        assert_eq!(page.next_token.as_deref(), Some("4"));
+    }
+
+    #[tokio::test]
+    async fn test_collect_offsets_without_total() {
+        let all: Vec<u32> = (0..23).collect();
+        let calls = AtomicUsize::new(0);
+
+        let page = Paginator::new(5)
+            .with_concurrency(3)
+            .collect_offsets(|offset, limit| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                let page = offset_page(&all, offset, limit);
+                async move { Ok(page) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(page.items, all);
+        assert_eq!(page.next_token, None);
+        // 1 initial page plus one speculative batch of 3 and a second of 3
+        assert_eq!(calls.load(Ordering::SeqCst), 7);
+    }
+
+    #[tokio::test]
+    async fn test_collect_offsets_with_total_and_max_items() {
+        let all: Vec<u32> = (0..40).collect();
+        let calls = AtomicUsize::new(0);
+
+        let page = Paginator::new(10)
+            .with_concurrency(4)
+            .collect_offsets(|offset, limit| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                let page = offset_page(&all, offset, limit).with_total(40);
+                async move { Ok(page) }
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.items, all);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        let page = Paginator::new(10)
+            .with_max_items(15)
+            .collect_offsets(|offset, limit| {
+                let page = offset_page(&all, offset, limit);
+                async move { Ok(page) }
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 15);
+        assert_eq!(page.next_token.as_deref(), Some("15"));
+    }
+
+    #[test]
+    fn test_from_config_and_schema_attributes() {
+        let paginator = Paginator::from_config(&json!({"page_size": 50, "next_token": "abc"}), 100);
+        assert_eq!(paginator.page_size(), 50);
+        assert_eq!(paginator.start_token.as_deref(), Some("abc"));
+        assert_eq!(Paginator::from_config(&json!({}), 100).page_size(), 100);
+
+        let schema = Schema::v0().with_pagination_attributes();
+        assert!(schema.block.attributes[PAGE_SIZE_ATTRIBUTE].flags.optional);
+        assert!(schema.block.attributes[NEXT_TOKEN_ATTRIBUTE].flags.computed);
+    }
+}
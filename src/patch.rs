@@ -0,0 +1,370 @@
+//! RFC 6902 JSON Patch support for partial updates.
+//!
+//! Before calling [`ProviderService::handle_update`](crate::ProviderService::handle_update),
+//! the SDK computes the [`JsonPatch`] that transforms the prior state into the
+//! planned state and passes it as [`UpdateRequest::patch`](crate::UpdateRequest::patch),
+//! so providers backed by PATCH-style APIs can translate the operations
+//! directly instead of diffing full states themselves.
+//!
+//! # Example
+//!
+//! ```
+//! use hemmer_provider_sdk::patch::{JsonPatch, PatchOperation};
+//! use serde_json::json;
+//!
+//! let prior = json!({"name": "web", "tags": {"env": "dev"}});
+//! let planned = json!({"name": "web", "tags": {"env": "prod", "team": "infra"}});
+//!
+//! let patch = JsonPatch::diff(&prior, &planned);
+//! assert_eq!(
+//!     patch.operations(),
+//!     &[
+//!         PatchOperation::replace("/tags/env", json!("prod")),
+//!         PatchOperation::add("/tags/team", json!("infra")),
+//!     ]
+//! );
+//!
+//! let mut state = prior.clone();
+//! patch.apply(&mut state).unwrap();
+//! assert_eq!(state, planned);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::ProviderError;
+
+/// A single JSON Patch operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    /// Add a value at `path`.
+    Add {
+        /// JSON Pointer to the target location.
+        path: String,
+        /// The value to add.
+        value: Value,
+    },
+    /// Remove the value at `path`.
+    Remove {
+        /// JSON Pointer to the target location.
+        path: String,
+    },
+    /// Replace the value at `path`.
+    Replace {
+        /// JSON Pointer to the target location.
+        path: String,
+        /// The replacement value.
+        value: Value,
+    },
+    /// Move the value at `from` to `path`.
+    Move {
+        /// JSON Pointer to the source location.
+        from: String,
+        /// JSON Pointer to the target location.
+        path: String,
+    },
+    /// Copy the value at `from` to `path`.
+    Copy {
+        /// JSON Pointer to the source location.
+        from: String,
+        /// JSON Pointer to the target location.
+        path: String,
+    },
+    /// Check that the value at `path` equals `value`.
+    Test {
+        /// JSON Pointer to the target location.
+        path: String,
+        /// The expected value.
+        value: Value,
+    },
+}
+
+impl PatchOperation {
+    /// Create an `add` operation.
+    pub fn add(path: impl Into<String>, value: Value) -> Self {
+        Self::Add {
+            path: path.into(),
+            value,
+        }
+    }
+
+    /// Create a `remove` operation.
+    pub fn remove(path: impl Into<String>) -> Self {
+        Self::Remove { path: path.into() }
+    }
+
+    /// Create a `replace` operation.
+    pub fn replace(path: impl Into<String>, value: Value) -> Self {
+        Self::Replace {
+            path: path.into(),
+            value,
+        }
+    }
+
+    /// The JSON Pointer this operation targets.
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Add { path, .. }
+            | Self::Remove { path }
+            | Self::Replace { path, .. }
+            | Self::Move { path, .. }
+            | Self::Copy { path, .. }
+            | Self::Test { path, .. } => path,
+        }
+    }
+}
+
+/// An RFC 6902 JSON Patch document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JsonPatch(Vec<PatchOperation>);
+
+impl JsonPatch {
+    /// Create a patch from a list of operations.
+    pub fn new(operations: Vec<PatchOperation>) -> Self {
+        Self(operations)
+    }
+
+    /// Compute the patch that transforms `from` into `to`.
+    ///
+    /// Objects are diffed key by key. Arrays are diffed by index, with trailing
+    /// elements added or removed; only `add`, `remove` and `replace` are emitted.
+    pub fn diff(from: &Value, to: &Value) -> Self {
+        let mut operations = Vec::new();
+        diff_values(from, to, String::new(), &mut operations);
+        Self(operations)
+    }
+
+    /// The operations in this patch.
+    pub fn operations(&self) -> &[PatchOperation] {
+        &self.0
+    }
+
+    /// Whether the patch has no operations.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Apply the patch to `target` in place.
+    ///
+    /// On error `target` may be partially patched.
+    pub fn apply(&self, target: &mut Value) -> Result<(), ProviderError> {
+        for op in &self.0 {
+            match op {
+                PatchOperation::Add { path, value } => add(target, path, value.clone())?,
+                PatchOperation::Remove { path } => {
+                    remove(target, path)?;
+                },
+                PatchOperation::Replace { path, value } => {
+                    *lookup_mut(target, path)? = value.clone();
+                },
+                PatchOperation::Move { from, path } => {
+                    let value = remove(target, from)?;
+                    add(target, path, value)?;
+                },
+                PatchOperation::Copy { from, path } => {
+                    let value = lookup_mut(target, from)?.clone();
+                    add(target, path, value)?;
+                },
+                PatchOperation::Test { path, value } => {
+                    if lookup_mut(target, path)? != value {
+                        return Err(patch_error(path, "test failed"));
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl IntoIterator for JsonPatch {
+    type Item = PatchOperation;
+    type IntoIter = std::vec::IntoIter<PatchOperation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+fn diff_values(from: &Value, to: &Value, path: String, out: &mut Vec<PatchOperation>) {
+    match (from, to) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, old) in a {
+                let child = format!("{}/{}", path, escape(key));
+                match b.get(key) {
+                    Some(new) => diff_values(old, new, child, out),
+                    None => out.push(PatchOperation::remove(child)),
+                }
+            }
+            for (key, new) in b {
+                if !a.contains_key(key) {
+                    out.push(PatchOperation::add(
+                        format!("{}/{}", path, escape(key)),
+                        new.clone(),
+                    ));
+                }
+            }
+        },
+        (Value::Array(a), Value::Array(b)) => {
+            for (i, (old, new)) in a.iter().zip(b).enumerate() {
+                diff_values(old, new, format!("{}/{}", path, i), out);
+            }
+            // Remove from the end so earlier indices stay valid
+            for i in (b.len()..a.len()).rev() {
+                out.push(PatchOperation::remove(format!("{}/{}", path, i)));
+            }
+            for (i, new) in b.iter().enumerate().skip(a.len()) {
+                out.push(PatchOperation::add(format!("{}/{}", path, i), new.clone()));
+            }
+        },
+        _ if from != to => out.push(PatchOperation::replace(path, to.clone())),
+        _ => {},
+    }
+}
+
+fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+fn patch_error(path: &str, message: &str) -> ProviderError {
+    ProviderError::InvalidRequest(format!("JSON Patch at '{}': {}", path, message))
+}
+
+fn lookup_mut<'a>(target: &'a mut Value, path: &str) -> Result<&'a mut Value, ProviderError> {
+    target
+        .pointer_mut(path)
+        .ok_or_else(|| patch_error(path, "path not found"))
+}
+
+/// Split a pointer into its parent pointer and final (unescaped) segment.
+fn split_parent(path: &str) -> Result<(&str, String), ProviderError> {
+    let idx = path
+        .rfind('/')
+        .ok_or_else(|| patch_error(path, "cannot target the document root"))?;
+    Ok((&path[..idx], unescape(&path[idx + 1..])))
+}
+
+fn add(target: &mut Value, path: &str, value: Value) -> Result<(), ProviderError> {
+    if path.is_empty() {
+        *target = value;
+        return Ok(());
+    }
+    let (parent, key) = split_parent(path)?;
+    match lookup_mut(target, parent)? {
+        Value::Object(map) => {
+            map.insert(key, value);
+        },
+        Value::Array(items) => {
+            let index = if key == "-" {
+                items.len()
+            } else {
+                key.parse::<usize>()
+                    .ok()
+                    .filter(|i| *i <= items.len())
+                    .ok_or_else(|| patch_error(path, "invalid array index"))?
+            };
+            items.insert(index, value);
+        },
+        _ => return Err(patch_error(path, "parent is not a container")),
+    }
+    Ok(())
+}
+
+fn remove(target: &mut Value, path: &str) -> Result<Value, ProviderError> {
+    let (parent, key) = split_parent(path)?;
+    match lookup_mut(target, parent)? {
+        Value::Object(map) => map
+            .remove(&key)
+            .ok_or_else(|| patch_error(path, "path not found")),
+        Value::Array(items) => {
+            let index = key
+                .parse::<usize>()
+                .ok()
+                .filter(|i| *i < items.len())
+                .ok_or_else(|| patch_error(path, "invalid array index"))?;
+            Ok(items.remove(index))
+        },
+        _ => Err(patch_error(path, "parent is not a container")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_round_trip() {
+        let prior = json!({
+            "name": "web",
+            "removed": true,
+            "a/b": 1,
+            "ports": [80, 443, 8080],
+            "disks": [{"size": 10}]
+        });
+        let planned = json!({
+            "name": "web-2",
+            "a/b": 2,
+            "ports": [80],
+            "disks": [{"size": 20}, {"size": 5}]
+        });
+
+        let patch = JsonPatch::diff(&prior, &planned);
+        let mut state = prior.clone();
+        patch.apply(&mut state).unwrap();
+        assert_eq!(state, planned);
+
+        assert!(patch
+            .operations()
+            .contains(&PatchOperation::replace("/a~1b", json!(2))));
+        assert!(patch
+            .operations()
+            .contains(&PatchOperation::remove("/removed")));
+        assert!(JsonPatch::diff(&prior, &prior).is_empty());
+    }
+
+    #[test]
+    fn test_serialization_matches_rfc6902() {
+        let patch = JsonPatch::new(vec![
+            PatchOperation::add("/tags/env", json!("prod")),
+            PatchOperation::remove("/old"),
+            PatchOperation::Move {
+                from: "/a".to_string(),
+                path: "/b".to_string(),
+            },
+        ]);
+        let json = serde_json::to_value(&patch).unwrap();
+        assert_eq!(
+            json,
+            json!([
+                {"op": "add", "path": "/tags/env", "value": "prod"},
+                {"op": "remove", "path": "/old"},
+                {"op": "move", "from": "/a", "path": "/b"}
+            ])
+        );
+        let parsed: JsonPatch = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, patch);
+    }
+
+    #[test]
+    fn test_apply_errors() {
+        let mut doc = json!({"a": 1});
+        let err = JsonPatch::new(vec![PatchOperation::remove("/missing")])
+            .apply(&mut doc)
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::InvalidRequest(_)));
+
+        let err = JsonPatch::new(vec![PatchOperation::Test {
+            path: "/a".to_string(),
+            value: json!(2),
+        }])
+        .apply(&mut doc)
+        .unwrap_err();
+        assert!(err.to_string().contains("test failed"));
+    }
+}
@@ -0,0 +1,450 @@
+//! Payload size accounting and limits.
+//!
+//! The server measures every JSON state and configuration payload it receives
+//! and returns, records the sizes on the RPC's tracing span
+//! (`request_bytes` / `response_bytes`) and in [`PayloadMetrics`], and checks
+//! each payload against [`PayloadLimits`]:
+//!
+//! - Over the soft limit, the response gets a warning diagnostic.
+//! - Over the hard limit, a request fails before the provider is called. A
+//!   response still carries its state (so a created resource is not lost),
+//!   with an error diagnostic that fails the operation.
+//!
+//! ```
+//! use hemmer_provider_sdk::payload::{PayloadLimits, PayloadMetrics};
+//! use hemmer_provider_sdk::ServeOptions;
+//!
+//! let metrics = PayloadMetrics::new();
+//! let options = ServeOptions::new()
+//!     .with_payload_limits(
+//!         PayloadLimits::new()
+//!             .with_soft_limit(256 * 1024)
+//!             .with_hard_limit(4 * 1024 * 1024),
+//!     )
+//!     .with_payload_metrics(metrics.clone());
+//!
+//! // Later, e.g. from a debug endpoint
+//! if let Some(stats) = metrics.rpc("read") {
+//!     println!("largest read response: {} bytes", stats.max_response_bytes);
+//! }
+//! ```
+//!
+//! Payloads are handled as [`Bytes`] end to end: request fields borrow the
+//! buffer prost decoded them from, and response states are serialized into a
+//! per-thread buffer whose memory is reused once the previous response has
+//! been sent. `cargo bench --bench payload` measures the round trip.
+//!
+//! With the `simd-json` feature, [`decode_json`] parses large state and
+//! configuration payloads with simd-json instead of serde_json. The result is
+//! the same `serde_json::Value`, and anything simd-json rejects is re-parsed
+//! with serde_json, so accepted input and error messages don't change.
+//! simd-json reads numbers as `f64`, so it is not used with the
+//! `arbitrary-precision` feature.
+//!
+//! With the `arbitrary-precision` feature, [`decode_json`] keeps every digit of
+//! integers but still rounds decimals to the nearest `f64`;
+//! [`decode_json_exact`] keeps them as written (see [`crate::number`]).
+//!
+//! With the `trace-serde` feature, every [`decode_json`] and [`encode_json`]
+//! call runs in a `serde.decode` or `serde.encode` span (nested in the RPC's
+//! span) recording the payload size as `bytes` and the time taken as
+//! `duration_us`, and [`PayloadStats`] sums the time per RPC. That shows
+//! whether a slow plan is spent in the provider or in JSON handling.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use prost::bytes::{Bytes, BytesMut};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::ProviderError;
+use crate::schema::Diagnostic;
+
+/// Initial capacity of the per-thread serialization buffer.
+const ENCODE_BUFFER_CAPACITY: usize = 64 * 1024;
+
+/// Payloads smaller than this are parsed with serde_json even with the
+/// `simd-json` feature; copying them into a mutable buffer costs more than
+/// simd-json saves.
+#[cfg(all(feature = "simd-json", not(feature = "arbitrary-precision")))]
+const SIMD_JSON_THRESHOLD: usize = 16 * 1024;
+
+thread_local! {
+    static ENCODE_BUFFER: RefCell<BytesMut> =
+        RefCell::new(BytesMut::with_capacity(ENCODE_BUFFER_CAPACITY));
+}
+
+#[cfg(all(feature = "simd-json", not(feature = "arbitrary-precision")))]
+thread_local! {
+    static DECODE_BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Parse a JSON payload.
+///
+/// With the `simd-json` feature, payloads of 16 KiB or more are parsed with
+/// simd-json; see the [module docs](self).
+#[cfg(not(feature = "arbitrary-precision"))]
+pub fn decode_json(bytes: &[u8]) -> Result<Value, serde_json::Error> {
+    decode(bytes)
+}
+
+/// Parse a JSON payload, rounding decimals to the nearest `f64`.
+///
+/// Integers keep all their digits; [`decode_json_exact`] keeps decimals too.
+#[cfg(feature = "arbitrary-precision")]
+pub fn decode_json(bytes: &[u8]) -> Result<Value, serde_json::Error> {
+    let mut value = decode(bytes)?;
+    crate::number::round_decimals(&mut value);
+    Ok(value)
+}
+
+/// Parse a JSON payload, keeping every number exactly as written.
+#[cfg(feature = "arbitrary-precision")]
+pub fn decode_json_exact(bytes: &[u8]) -> Result<Value, serde_json::Error> {
+    decode(bytes)
+}
+
+fn decode(bytes: &[u8]) -> Result<Value, serde_json::Error> {
+    #[cfg(feature = "trace-serde")]
+    let _timer = SerdeTimer::new(tracing::debug_span!(
+        "serde.decode",
+        bytes = bytes.len(),
+        duration_us = tracing::field::Empty
+    ));
+    #[cfg(all(feature = "simd-json", not(feature = "arbitrary-precision")))]
+    if bytes.len() >= SIMD_JSON_THRESHOLD {
+        // simd-json parses in place, so it needs a mutable copy
+        let parsed = DECODE_BUFFER.with(|buffer| {
+            let mut buffer = buffer.borrow_mut();
+            buffer.clear();
+            buffer.extend_from_slice(bytes);
+            simd_json::serde::from_slice::<Value>(&mut buffer)
+        });
+        if let Ok(value) = parsed {
+            return Ok(value);
+        }
+    }
+    serde_json::from_slice(bytes)
+}
+
+/// Serialize `value` as JSON, reusing a per-thread buffer.
+///
+/// The result shares the buffer's allocation. Once every [`Bytes`] split off
+/// from it has been dropped, the next call writes into the same memory instead
+/// of allocating and regrowing a fresh `Vec`. Serialization errors produce
+/// empty bytes, like `serde_json::to_vec(..).unwrap_or_default()`.
+pub fn encode_json<T: Serialize + ?Sized>(value: &T) -> Bytes {
+    #[cfg(feature = "trace-serde")]
+    let timer = SerdeTimer::new(tracing::debug_span!(
+        "serde.encode",
+        bytes = tracing::field::Empty,
+        duration_us = tracing::field::Empty
+    ));
+    let bytes = ENCODE_BUFFER.with(|buffer| {
+        let mut buffer = buffer.borrow_mut();
+        if buffer.capacity() < ENCODE_BUFFER_CAPACITY / 16 {
+            // Moves back to the start of the allocation if nothing references it
+            buffer.reserve(ENCODE_BUFFER_CAPACITY);
+        }
+        match serde_json::to_writer(BytesWriter(&mut buffer), value) {
+            Ok(()) => buffer.split().freeze(),
+            Err(_) => {
+                buffer.clear();
+                Bytes::new()
+            },
+        }
+    });
+    #[cfg(feature = "trace-serde")]
+    timer.span.record("bytes", bytes.len());
+    bytes
+}
+
+/// Keeps a serialization span entered, and records its duration on drop.
+#[cfg(feature = "trace-serde")]
+struct SerdeTimer {
+    span: tracing::span::EnteredSpan,
+    started: std::time::Instant,
+}
+
+#[cfg(feature = "trace-serde")]
+impl SerdeTimer {
+    fn new(span: tracing::Span) -> Self {
+        Self {
+            span: span.entered(),
+            started: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "trace-serde")]
+impl Drop for SerdeTimer {
+    fn drop(&mut self) {
+        self.span
+            .record("duration_us", self.started.elapsed().as_micros() as u64);
+    }
+}
+
+/// Soft and hard size limits for individual payloads, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PayloadLimits {
+    /// Payloads larger than this produce a warning diagnostic.
+    pub soft: Option<usize>,
+    /// Payloads larger than this fail the operation.
+    pub hard: Option<usize>,
+}
+
+impl PayloadLimits {
+    /// Create limits with neither a soft nor a hard limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the soft limit.
+    pub fn with_soft_limit(mut self, bytes: usize) -> Self {
+        self.soft = Some(bytes);
+        self
+    }
+
+    /// Set the hard limit.
+    pub fn with_hard_limit(mut self, bytes: usize) -> Self {
+        self.hard = Some(bytes);
+        self
+    }
+
+    /// Check the size of a payload.
+    ///
+    /// Returns an error over the hard limit and a warning over the soft limit.
+    pub fn check(
+        &self,
+        rpc: &str,
+        field: &str,
+        size: usize,
+    ) -> Result<Option<Diagnostic>, ProviderError> {
+        if let Some(hard) = self.hard.filter(|hard| size > *hard) {
+            return Err(ProviderError::ResourceExhausted(format!(
+                "{} {} is {} bytes, over the {} byte limit",
+                rpc, field, size, hard
+            )));
+        }
+        Ok(self.soft.filter(|soft| size > *soft).map(|soft| {
+            Diagnostic::warning(format!("{} {} is unusually large", rpc, field)).with_detail(
+                format!(
+                    "{} bytes, over the {} byte soft limit; check for unbounded state growth",
+                    size, soft
+                ),
+            )
+        }))
+    }
+}
+
+/// `io::Write` for `BytesMut` without the per-write bookkeeping of `BufMut::writer`.
+struct BytesWriter<'a>(&'a mut BytesMut);
+
+impl std::io::Write for BytesWriter<'_> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        self.0.extend_from_slice(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Payload sizes recorded for one RPC.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PayloadStats {
+    /// Number of calls recorded.
+    pub calls: u64,
+    /// Total bytes of request payloads.
+    pub request_bytes: u64,
+    /// Total bytes of response payloads.
+    pub response_bytes: u64,
+    /// Largest request payload total seen in one call.
+    pub max_request_bytes: usize,
+    /// Largest response payload total seen in one call.
+    pub max_response_bytes: usize,
+    /// Total time spent parsing request payloads. Only measured with the
+    /// `trace-serde` feature.
+    pub decode_time: Duration,
+    /// Total time spent serializing response payloads. Only measured with the
+    /// `trace-serde` feature.
+    pub encode_time: Duration,
+}
+
+/// Payload size counters per RPC.
+///
+/// Cloning shares the same counters.
+#[derive(Clone, Default)]
+pub struct PayloadMetrics {
+    stats: Arc<Mutex<BTreeMap<String, PayloadStats>>>,
+}
+
+impl PayloadMetrics {
+    /// Create empty metrics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the request payload bytes of one call.
+    pub fn record_request(&self, rpc: &str, bytes: usize) {
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry(rpc.to_string()).or_default();
+        entry.calls += 1;
+        entry.request_bytes += bytes as u64;
+        entry.max_request_bytes = entry.max_request_bytes.max(bytes);
+    }
+
+    /// Record the response payload bytes of one call.
+    pub fn record_response(&self, rpc: &str, bytes: usize) {
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry(rpc.to_string()).or_default();
+        entry.response_bytes += bytes as u64;
+        entry.max_response_bytes = entry.max_response_bytes.max(bytes);
+    }
+
+    /// Record time spent parsing a request payload of a call.
+    pub fn record_decode(&self, rpc: &str, duration: Duration) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.entry(rpc.to_string()).or_default().decode_time += duration;
+    }
+
+    /// Record time spent serializing a response payload of a call.
+    pub fn record_encode(&self, rpc: &str, duration: Duration) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.entry(rpc.to_string()).or_default().encode_time += duration;
+    }
+
+    /// The stats for an RPC (e.g. `"create"`), if it has been called.
+    pub fn rpc(&self, rpc: &str) -> Option<PayloadStats> {
+        self.stats.lock().unwrap().get(rpc).copied()
+    }
+
+    /// The stats for every RPC that has been called.
+    pub fn snapshot(&self) -> BTreeMap<String, PayloadStats> {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+impl fmt::Debug for PayloadMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PayloadMetrics")
+            .field("rpcs", &self.stats.lock().unwrap().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let limits = PayloadLimits::new()
+            .with_soft_limit(10)
+            .with_hard_limit(100);
+        assert!(limits.check("read", "state", 10).unwrap().is_none());
+
+        let warning = limits.check("read", "state", 11).unwrap().unwrap();
+        assert_eq!(warning.summary, "read state is unusually large");
+
+        let err = limits.check("read", "state", 101).unwrap_err();
+        assert!(matches!(err, ProviderError::ResourceExhausted(_)));
+        assert!(err
+            .to_string()
+            .contains("101 bytes, over the 100 byte limit"));
+
+        assert!(PayloadLimits::new()
+            .check("read", "state", usize::MAX)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_encode_json_reuses_buffer() {
+        let value = serde_json::json!({"data": "x".repeat(1000)});
+        let held = encode_json(&value);
+        assert_eq!(held, serde_json::to_vec(&value).unwrap());
+        drop(held);
+
+        // Once earlier payloads are dropped, encoding wraps around to the
+        // start of the same allocation
+        let start = encode_json(&value).as_ptr();
+        let reused = (0..2 * ENCODE_BUFFER_CAPACITY / 1000)
+            .map(|_| encode_json(&value).as_ptr())
+            .any(|ptr| ptr == start);
+        assert!(reused);
+
+        // Payloads that are still referenced are never overwritten
+        let held: Vec<_> = (0..100).map(|i| encode_json(&i)).collect();
+        for (i, bytes) in held.iter().enumerate() {
+            assert_eq!(bytes, i.to_string().as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_decode_json() {
+        let small = br#"{"name": "a", "size": 1}"#;
+        assert_eq!(
+            decode_json(small).unwrap(),
+            serde_json::json!({"name": "a", "size": 1})
+        );
+
+        // Large enough for simd-json when the feature is enabled
+        let large = serde_json::json!({
+            "items": (0..2000).map(|i| serde_json::json!({"id": i, "ratio": 0.5, "big": u64::MAX}))
+                .collect::<Vec<_>>(),
+        });
+        let bytes = serde_json::to_vec(&large).unwrap();
+        assert!(bytes.len() > 16 * 1024);
+        assert_eq!(decode_json(&bytes).unwrap(), large);
+
+        // Errors are serde_json's, whichever parser ran first
+        let mut invalid = bytes.clone();
+        invalid.truncate(bytes.len() - 1);
+        let err = decode_json(&invalid).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            serde_json::from_slice::<Value>(&invalid)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_metrics() {
+        let metrics = PayloadMetrics::new();
+        metrics.record_request("create", 40);
+        metrics.record_response("create", 60);
+        metrics.record_request("create", 20);
+        metrics.record_response("create", 10);
+
+        let stats = metrics.rpc("create").unwrap();
+        assert_eq!(stats.calls, 2);
+        assert_eq!(stats.request_bytes, 60);
+        assert_eq!(stats.max_request_bytes, 40);
+        assert_eq!(stats.response_bytes, 70);
+        assert_eq!(stats.max_response_bytes, 60);
+        assert!(metrics.rpc("read").is_none());
+
+        metrics.record_decode("create", Duration::from_millis(2));
+        metrics.record_decode("create", Duration::from_millis(3));
+        metrics.record_encode("create", Duration::from_millis(1));
+        let stats = metrics.rpc("create").unwrap();
+        assert_eq!(stats.decode_time, Duration::from_millis(5));
+        assert_eq!(stats.encode_time, Duration::from_millis(1));
+        assert_eq!(stats.calls, 2);
+        assert_eq!(metrics.snapshot().len(), 1);
+    }
+}
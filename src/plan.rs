@@ -0,0 +1,688 @@
+//! Schema-driven planning.
+//!
+//! [`plan_from_schema`] is the planning pipeline behind the default
+//! [`ProviderService::plan`](crate::ProviderService::plan), so resources whose
+//! planning follows their schema only need to implement CRUD:
+//!
+//! 1. The configuration, proposed and prior state are
+//!    [normalized](crate::normalize)
+//! 2. The configuration is validated against the schema
+//! 3. Unset attributes with a [`default`](crate::schema::Attribute::with_default) get it
+//! 4. Unset computed attributes are planned as unknown (`null`) on create and
+//!    replace, and keep their prior value on update unless marked
+//!    [`PlanModifier::UnknownOnUpdate`]
+//! 5. [`PlanModifier::IgnoreChanges`] attributes keep their prior value
+//! 6. Prior and planned state are diffed, and a change to any `force_new`
+//!    attribute (see [`requires_replacement`]) plans a replacement, with a
+//!    [`ReplaceReason`](crate::ReplaceReason) naming each such attribute
+//! 7. Changes are marked sensitive and replacing from the schema (see
+//!    [`annotate_changes`])
+//!
+//! Nested blocks are planned recursively, pairing list and set items by index
+//! and map items by key.
+//!
+//! # Optional+computed attributes
+//!
+//! Optional+computed attributes (e.g. [`Attribute::optional_computed_string`])
+//! follow one rule, which is also what keeps them from producing a diff on
+//! every plan:
+//!
+//! - A null configuration value means "provider chooses": unknown on create
+//!   and replace, and the prior (provider-chosen) value on update
+//! - A configuration value always wins over the prior value
+//!
+//! `plan_from_schema` applies it. Providers that plan by hand with
+//! [`PlanResult::from_diff`] can apply it with [`normalize_optional_computed`]
+//! first, and check applied states with
+//! [`validate_applied_state`](crate::validation::validate_applied_state).
+//!
+//! [`Attribute::optional_computed_string`]: crate::schema::Attribute::optional_computed_string
+//!
+//! # Example
+//!
+//! ```
+//! use hemmer_provider_sdk::plan::plan_from_schema;
+//! use hemmer_provider_sdk::schema::{Attribute, Schema};
+//! use serde_json::json;
+//!
+//! let schema = Schema::v0()
+//!     .with_attribute("name", Attribute::required_string().with_force_new())
+//!     .with_attribute("size", Attribute::optional_int64().with_default(json!(10)))
+//!     .with_attribute("id", Attribute::computed_string());
+//!
+//! let config = json!({"name": "web"});
+//! let plan = plan_from_schema(&schema, None, config.clone(), &config).unwrap();
+//! assert_eq!(plan.planned_state, json!({"name": "web", "size": 10, "id": null}));
+//!
+//! let prior = json!({"name": "web", "size": 10, "id": "i-123"});
+//! let config = json!({"name": "api"});
+//! let plan = plan_from_schema(&schema, Some(&prior), config.clone(), &config).unwrap();
+//! assert!(plan.requires_replace);
+//! ```
+
+use serde_json::Value;
+
+use crate::codec::transform_sensitive;
+use crate::error::ProviderError;
+use crate::normalize::{has_normalizers, normalize};
+use crate::schema::{
+    force_new_prefix, is_sensitive_path, requires_replacement, AttributePath, Block,
+    BlockNestingMode, DiagnosticSeverity, PlanModifier, Schema,
+};
+use crate::secrets::REDACTED;
+use crate::types::{AttributeChange, ChangeAction, PlanResult};
+use crate::validation::validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Create,
+    /// First pass over an existing resource, used to find the real changes.
+    Unchanged,
+    Update,
+    Replace,
+}
+
+/// Apply the optional+computed rule (see the [module docs](self)) to a
+/// proposed state.
+///
+/// For every optional+computed attribute, including those in nested blocks,
+/// the configured value is used if there is one, and otherwise the prior value
+/// (or `null` if there is no prior state).
+///
+/// ```
+/// use hemmer_provider_sdk::plan::normalize_optional_computed;
+/// use hemmer_provider_sdk::schema::{Attribute, Schema};
+/// use hemmer_provider_sdk::PlanResult;
+/// use serde_json::json;
+///
+/// let schema = Schema::v0()
+///     .with_attribute("name", Attribute::required_string())
+///     .with_attribute("zone", Attribute::optional_computed_string());
+///
+/// // The provider chose "us-east-1a" when the resource was created
+/// let prior = json!({"name": "web", "zone": "us-east-1a"});
+/// let config = json!({"name": "web", "zone": null});
+/// let proposed = normalize_optional_computed(&schema, config.clone(), &config, Some(&prior));
+/// assert!(PlanResult::from_diff(Some(&prior), &proposed).changes.is_empty());
+/// ```
+pub fn normalize_optional_computed(
+    schema: &Schema,
+    mut proposed: Value,
+    config: &Value,
+    prior: Option<&Value>,
+) -> Value {
+    normalize_block(&schema.block, &mut proposed, Some(config), prior);
+    proposed
+}
+
+fn normalize_block(
+    block: &Block,
+    planned: &mut Value,
+    config: Option<&Value>,
+    prior: Option<&Value>,
+) {
+    let Value::Object(map) = planned else {
+        return;
+    };
+
+    for (name, attr) in &block.attributes {
+        if !attr.flags.is_optional_computed() {
+            continue;
+        }
+        let configured = config.and_then(|c| c.get(name)).filter(|v| !v.is_null());
+        let prior_value = prior.and_then(|p| p.get(name));
+        let value = configured.or(prior_value).cloned();
+        map.insert(name.clone(), value.unwrap_or(Value::Null));
+    }
+
+    for_each_nested(block, map, config, prior, &mut normalize_block);
+}
+
+/// Annotate plan changes from the schema, for plan rendering in core.
+///
+/// Changes within a `sensitive` attribute or block are marked
+/// [`sensitive`](AttributeChange::sensitive), and changes within a `force_new`
+/// attribute or block get the [`ChangeAction::Replace`] action, unless the
+/// provider already set one. [`plan_from_schema`] calls this for updates;
+/// providers that plan by hand can call it on their own changes. Changes that
+/// create a resource replace nothing, so only mark those sensitive.
+///
+/// ```
+/// use hemmer_provider_sdk::plan::annotate_changes;
+/// use hemmer_provider_sdk::schema::{Attribute, Schema};
+/// use hemmer_provider_sdk::{AttributeChange, ChangeAction};
+/// use serde_json::json;
+///
+/// let schema = Schema::v0()
+///     .with_attribute("name", Attribute::required_string().with_force_new())
+///     .with_attribute("password", Attribute::required_string().sensitive());
+///
+/// let mut changes = vec![
+///     AttributeChange::modified("name", json!("a"), json!("b")),
+///     AttributeChange::modified("password", json!("x"), json!("y")),
+/// ];
+/// annotate_changes(&schema, &mut changes);
+/// assert_eq!(changes[0].action, Some(ChangeAction::Replace));
+/// assert!(changes[1].sensitive);
+/// ```
+pub fn annotate_changes(schema: &Schema, changes: &mut [AttributeChange]) {
+    for change in changes {
+        let path = AttributePath::parse(&change.path);
+        if is_sensitive_path(&schema.block, path.segments()) {
+            change.sensitive = true;
+        }
+        if change.action.is_none() && force_new_prefix(&schema.block, path.segments()).is_some() {
+            change.action = Some(ChangeAction::Replace);
+        }
+    }
+}
+
+/// Redact the values of sensitive plan changes, so plan output never carries
+/// secrets.
+///
+/// Changes within a `sensitive` attribute or block (or already marked
+/// [`sensitive`](AttributeChange::sensitive)) are marked sensitive, and their
+/// `before` and `after` values replaced with [`REDACTED`]. Whether a value was
+/// there is kept, and a change without an action gets the one its values
+/// imply, so core still renders what kind of change it is. Sensitive values
+/// inside a changed block (a block added whole on create, say) are redacted
+/// in place. The server calls this on every plan result, including from
+/// custom [`ProviderService::plan`](crate::ProviderService::plan)
+/// implementations.
+///
+/// ```
+/// use hemmer_provider_sdk::plan::redact_changes;
+/// use hemmer_provider_sdk::schema::{Attribute, Schema};
+/// use hemmer_provider_sdk::{AttributeChange, ChangeAction};
+/// use serde_json::json;
+///
+/// let schema = Schema::v0().with_attribute("password", Attribute::required_string().sensitive());
+///
+/// let mut changes = vec![AttributeChange::modified("password", json!("x"), json!("y"))];
+/// redact_changes(&schema, &mut changes);
+/// assert_eq!(changes[0].before, Some(json!("[REDACTED]")));
+/// assert_eq!(changes[0].after, Some(json!("[REDACTED]")));
+/// assert_eq!(changes[0].action, Some(ChangeAction::Update));
+/// ```
+pub fn redact_changes(schema: &Schema, changes: &mut [AttributeChange]) {
+    for change in changes {
+        let path = AttributePath::parse(&change.path);
+        if change.sensitive || is_sensitive_path(&schema.block, path.segments()) {
+            change.sensitive = true;
+            if change.action.is_none() {
+                change.action = Some(match (&change.before, &change.after) {
+                    (None, _) => ChangeAction::Create,
+                    (_, None) => ChangeAction::Delete,
+                    _ => ChangeAction::Update,
+                });
+            }
+            for value in [&mut change.before, &mut change.after]
+                .into_iter()
+                .flatten()
+            {
+                *value = Value::String(REDACTED.to_string());
+            }
+        } else {
+            for value in [&mut change.before, &mut change.after]
+                .into_iter()
+                .flatten()
+            {
+                redact_within(&schema.block, path.segments(), value);
+            }
+        }
+    }
+}
+
+/// Redact the sensitive attributes within `value`, the value at `segments`.
+fn redact_within(block: &Block, segments: &[String], value: &mut Value) {
+    let redact = |_: &Value| Ok(Value::String(REDACTED.to_string()));
+    let Some((name, rest)) = segments.split_first() else {
+        let _ = transform_sensitive(block, value, false, &redact);
+        return;
+    };
+    let Some(nested) = block.blocks.get(name) else {
+        return;
+    };
+    match (nested.nesting_mode, rest.split_first()) {
+        (BlockNestingMode::Single, _) => redact_within(&nested.block, rest, value),
+        (_, Some((_, rest))) => redact_within(&nested.block, rest, value),
+        (_, None) => {
+            let items: Vec<&mut Value> = match value {
+                Value::Array(items) => items.iter_mut().collect(),
+                Value::Object(items) => items.values_mut().collect(),
+                _ => Vec::new(),
+            };
+            for item in items {
+                let _ = transform_sensitive(&nested.block, item, false, &redact);
+            }
+        },
+    }
+}
+
+/// Plan a resource change from its schema.
+///
+/// `prior_state` is `None` when creating. Configuration errors are returned as
+/// [`ProviderError::Validation`].
+pub fn plan_from_schema(
+    schema: &Schema,
+    prior_state: Option<&Value>,
+    mut proposed_state: Value,
+    config: &Value,
+) -> Result<PlanResult, ProviderError> {
+    let normalized;
+    let (config, prior_state) = if has_normalizers(schema) {
+        normalize(schema, &mut proposed_state);
+        let mut config = config.clone();
+        normalize(schema, &mut config);
+        let prior = prior_state.cloned().map(|mut prior| {
+            normalize(schema, &mut prior);
+            prior
+        });
+        normalized = (config, prior);
+        (&normalized.0, normalized.1.as_ref())
+    } else {
+        (config, prior_state)
+    };
+
+    let errors: Vec<String> = validate(schema, config)
+        .into_iter()
+        .filter(|d| d.severity == DiagnosticSeverity::Error)
+        .map(|d| d.summary)
+        .collect();
+    if !errors.is_empty() {
+        return Err(ProviderError::Validation(errors.join("; ")));
+    }
+
+    let Some(prior) = prior_state else {
+        let planned = plan_state(schema, proposed_state, config, None, Mode::Create);
+        let mut result = PlanResult::from_diff(None, &planned);
+        for change in &mut result.changes {
+            let path = AttributePath::parse(&change.path);
+            change.sensitive = is_sensitive_path(&schema.block, path.segments());
+        }
+        return Ok(result);
+    };
+
+    let planned = plan_state(
+        schema,
+        proposed_state.clone(),
+        config,
+        Some(prior),
+        Mode::Unchanged,
+    );
+    let changes = PlanResult::from_diff(Some(prior), &planned).changes;
+    if changes.is_empty() {
+        return Ok(PlanResult::no_change(prior.clone()));
+    }
+
+    let replace_paths = requires_replacement(schema, &changes);
+    let replace = !replace_paths.is_empty();
+    let mode = if replace { Mode::Replace } else { Mode::Update };
+    let planned = plan_state(schema, proposed_state, config, Some(prior), mode);
+    let mut result = PlanResult::from_diff(Some(prior), &planned);
+    for path in replace_paths {
+        result = result.with_replace_reason(
+            path.to_string(),
+            format!("'{}' cannot be changed in place", path),
+        );
+    }
+    annotate_changes(schema, &mut result.changes);
+    Ok(result)
+}
+
+fn plan_state(
+    schema: &Schema,
+    mut planned: Value,
+    config: &Value,
+    prior: Option<&Value>,
+    mode: Mode,
+) -> Value {
+    plan_block(&schema.block, &mut planned, Some(config), prior, mode);
+    planned
+}
+
+fn plan_block(
+    block: &Block,
+    planned: &mut Value,
+    config: Option<&Value>,
+    prior: Option<&Value>,
+    mode: Mode,
+) {
+    let Value::Object(map) = planned else {
+        return;
+    };
+
+    for (name, attr) in &block.attributes {
+        let configured = config
+            .and_then(|c| c.get(name))
+            .is_some_and(|v| !v.is_null());
+        let prior_value = prior.and_then(|p| p.get(name)).filter(|v| !v.is_null());
+
+        if attr.has_plan_modifier(PlanModifier::IgnoreChanges) && mode != Mode::Replace {
+            if let Some(value) = prior_value {
+                map.insert(name.clone(), value.clone());
+                continue;
+            }
+        }
+        if configured {
+            continue;
+        }
+        if let Some(default) = &attr.default {
+            map.insert(name.clone(), default.clone());
+        } else if attr.flags.computed {
+            let keep_prior = match mode {
+                Mode::Create | Mode::Replace => false,
+                Mode::Unchanged => true,
+                Mode::Update => !attr.has_plan_modifier(PlanModifier::UnknownOnUpdate),
+            };
+            let value = prior_value.filter(|_| keep_prior).cloned();
+            map.insert(name.clone(), value.unwrap_or(Value::Null));
+        }
+    }
+
+    for_each_nested(
+        block,
+        map,
+        config,
+        prior,
+        &mut |block, item, config, prior| plan_block(block, item, config, prior, mode),
+    );
+}
+
+type VisitItem<'a> = dyn FnMut(&Block, &mut Value, Option<&Value>, Option<&Value>) + 'a;
+
+/// Call `f` for every nested block item, with the matching configuration and
+/// prior items: list and set items are paired by index, map items by key.
+fn for_each_nested(
+    block: &Block,
+    map: &mut serde_json::Map<String, Value>,
+    config: Option<&Value>,
+    prior: Option<&Value>,
+    f: &mut VisitItem<'_>,
+) {
+    for (name, nested) in &block.blocks {
+        let Some(items) = map.get_mut(name) else {
+            continue;
+        };
+        let config = config.and_then(|c| c.get(name));
+        let prior = prior.and_then(|p| p.get(name));
+
+        match (nested.nesting_mode, items) {
+            (BlockNestingMode::Single, item) => f(&nested.block, item, config, prior),
+            (BlockNestingMode::List | BlockNestingMode::Set, Value::Array(items)) => {
+                for (i, item) in items.iter_mut().enumerate() {
+                    let config = config.and_then(|c| c.get(i));
+                    let prior = prior.and_then(|p| p.get(i));
+                    f(&nested.block, item, config, prior);
+                }
+            },
+            (BlockNestingMode::Map, Value::Object(items)) => {
+                for (key, item) in items.iter_mut() {
+                    let config = config.and_then(|c| c.get(key));
+                    let prior = prior.and_then(|p| p.get(key));
+                    f(&nested.block, item, config, prior);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Attribute, NestedBlock};
+    use crate::ReplaceReason;
+    use serde_json::json;
+
+    fn schema() -> Schema {
+        Schema::v0()
+            .with_attribute("name", Attribute::required_string())
+            .with_attribute("zone", Attribute::optional_string().with_force_new())
+            .with_attribute("size", Attribute::optional_int64().with_default(json!(10)))
+            .with_attribute("id", Attribute::computed_string())
+            .with_attribute(
+                "etag",
+                Attribute::computed_string().with_plan_modifier(PlanModifier::UnknownOnUpdate),
+            )
+            .with_attribute(
+                "tags",
+                Attribute::optional_string().with_plan_modifier(PlanModifier::IgnoreChanges),
+            )
+            .with_block(
+                "disk",
+                NestedBlock::list(
+                    Block::new()
+                        .with_attribute("size", Attribute::required_int64())
+                        .with_attribute("disk_id", Attribute::computed_string()),
+                ),
+            )
+    }
+
+    fn prior() -> Value {
+        json!({
+            "name": "web",
+            "zone": "a",
+            "size": 10,
+            "id": "i-1",
+            "etag": "e-1",
+            "tags": "managed-elsewhere",
+            "disk": [{"size": 20, "disk_id": "d-1"}]
+        })
+    }
+
+    fn plan(prior: Option<&Value>, config: Value) -> Result<PlanResult, ProviderError> {
+        plan_from_schema(&schema(), prior, config.clone(), &config)
+    }
+
+    #[test]
+    fn test_plan_create() {
+        let result = plan(None, json!({"name": "web", "disk": [{"size": 20}]})).unwrap();
+        assert!(!result.requires_replace);
+        assert_eq!(result.planned_state["size"], 10);
+        assert_eq!(result.planned_state["id"], Value::Null);
+        assert_eq!(result.planned_state["disk"][0]["disk_id"], Value::Null);
+    }
+
+    #[test]
+    fn test_plan_no_change() {
+        let config = json!({"name": "web", "zone": "a", "disk": [{"size": 20}]});
+        let result = plan(Some(&prior()), config).unwrap();
+        assert!(result.changes.is_empty());
+        assert_eq!(result.planned_state, prior());
+    }
+
+    #[test]
+    fn test_plan_update_keeps_computed_values() {
+        let config = json!({"name": "api", "zone": "a", "tags": "new", "disk": [{"size": 20}]});
+        let result = plan(Some(&prior()), config).unwrap();
+        assert!(!result.requires_replace);
+        assert_eq!(result.planned_state["name"], "api");
+        assert_eq!(result.planned_state["id"], "i-1");
+        assert_eq!(result.planned_state["etag"], Value::Null);
+        assert_eq!(result.planned_state["tags"], "managed-elsewhere");
+        assert_eq!(result.planned_state["disk"][0]["disk_id"], "d-1");
+    }
+
+    #[test]
+    fn test_plan_force_new_replaces() {
+        let config = json!({"name": "web", "zone": "b", "disk": [{"size": 20}]});
+        let result = plan(Some(&prior()), config).unwrap();
+        assert!(result.requires_replace);
+        assert_eq!(result.planned_state["id"], Value::Null);
+        assert_eq!(result.planned_state["disk"][0]["disk_id"], Value::Null);
+
+        assert_eq!(
+            result.replace_reasons,
+            vec![ReplaceReason::new(
+                "zone",
+                "'zone' cannot be changed in place"
+            )]
+        );
+
+        let zone = result.changes.iter().find(|c| c.path == "zone").unwrap();
+        assert_eq!(zone.action, Some(ChangeAction::Replace));
+        let id = result.changes.iter().find(|c| c.path == "id").unwrap();
+        assert_eq!(id.action, None);
+
+        // Adding a block with a force_new attribute replaces too
+        let schema = Schema::v0()
+            .with_attribute("name", Attribute::required_string())
+            .with_block(
+                "boot",
+                NestedBlock::single(
+                    Block::new()
+                        .with_attribute("image", Attribute::required_string().with_force_new()),
+                ),
+            );
+        let prior = json!({"name": "web", "boot": null});
+        let config = json!({"name": "web", "boot": {"image": "debian"}});
+        let result = plan_from_schema(&schema, Some(&prior), config.clone(), &config).unwrap();
+        assert!(result.requires_replace);
+        assert_eq!(result.replace_reasons[0].path, "boot");
+    }
+
+    #[test]
+    fn test_plan_invalid_config() {
+        let err = plan(None, json!({"size": "big"})).unwrap_err();
+        assert!(matches!(err, ProviderError::Validation(_)));
+        assert!(err.to_string().contains("'name'"));
+    }
+
+    #[test]
+    fn test_plan_normalizes() {
+        use crate::normalize::Normalizer;
+
+        let schema = Schema::v0()
+            .with_attribute(
+                "region",
+                Attribute::required_string()
+                    .with_force_new()
+                    .with_normalizer(Normalizer::trim())
+                    .with_normalizer(Normalizer::lowercase()),
+            )
+            .with_attribute("id", Attribute::computed_string());
+        let prior = json!({"region": "us-east-1", "id": "i-1"});
+
+        // A config differing only in spelling plans no change, not a replacement
+        let config = json!({"region": " US-East-1"});
+        let result = plan_from_schema(&schema, Some(&prior), config.clone(), &config).unwrap();
+        assert!(result.changes.is_empty());
+        assert!(!result.requires_replace);
+
+        let config = json!({"region": "EU-West-1"});
+        let result = plan_from_schema(&schema, Some(&prior), config.clone(), &config).unwrap();
+        assert!(result.requires_replace);
+        assert_eq!(result.planned_state["region"], "eu-west-1");
+    }
+
+    #[test]
+    fn test_optional_computed() {
+        let schema = Schema::v0()
+            .with_attribute("name", Attribute::required_string().with_force_new())
+            .with_attribute("zone", Attribute::optional_computed_string())
+            .with_block(
+                "disk",
+                NestedBlock::list(
+                    Block::new()
+                        .with_attribute("size", Attribute::required_int64())
+                        .with_attribute("type", Attribute::optional_computed_string()),
+                ),
+            );
+        let prior = json!({"name": "web", "zone": "a", "disk": [{"size": 20, "type": "ssd"}]});
+        let plan = |prior: Option<&Value>, config: Value| {
+            plan_from_schema(&schema, prior, config.clone(), &config).unwrap()
+        };
+
+        // Null config means "provider chooses"
+        let created = plan(None, json!({"name": "web", "disk": [{"size": 20}]}));
+        assert_eq!(created.planned_state["zone"], Value::Null);
+        assert_eq!(created.planned_state["disk"][0]["type"], Value::Null);
+
+        // ...and the chosen values are kept
+        let config = json!({"name": "web", "zone": null, "disk": [{"size": 20}]});
+        assert!(plan(Some(&prior), config.clone()).changes.is_empty());
+        let normalized =
+            normalize_optional_computed(&schema, config.clone(), &config, Some(&prior));
+        assert_eq!(normalized, prior);
+
+        // An explicit configuration value wins
+        let config = json!({"name": "web", "zone": "b", "disk": [{"size": 20, "type": "hdd"}]});
+        let result = plan(Some(&prior), config.clone());
+        assert_eq!(result.changes.len(), 2);
+        assert_eq!(result.planned_state["zone"], "b");
+        let normalized = normalize_optional_computed(&schema, json!({}), &config, Some(&prior));
+        assert_eq!(normalized["zone"], "b");
+
+        // Replacing lets the provider choose again
+        let result = plan(Some(&prior), json!({"name": "api", "disk": [{"size": 20}]}));
+        assert!(result.requires_replace);
+        assert_eq!(result.planned_state["zone"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_default_provider_plan() {
+        use crate::schema::ProviderSchema;
+        use crate::types::ConfigureResult;
+        use crate::ProviderService;
+
+        struct CrudProvider;
+
+        #[async_trait::async_trait]
+        impl ProviderService for CrudProvider {
+            fn schema(&self) -> ProviderSchema {
+                ProviderSchema::new().with_resource("server", schema())
+            }
+
+            async fn configure(&self, _config: Value) -> Result<ConfigureResult, ProviderError> {
+                Ok(ConfigureResult::new())
+            }
+
+            async fn create(
+                &self,
+                _resource_type: &str,
+                planned: Value,
+            ) -> Result<Value, ProviderError> {
+                Ok(planned)
+            }
+
+            async fn read(
+                &self,
+                _resource_type: &str,
+                state: Value,
+            ) -> Result<Value, ProviderError> {
+                Ok(state)
+            }
+
+            async fn update(
+                &self,
+                _resource_type: &str,
+                _prior: Value,
+                planned: Value,
+            ) -> Result<Value, ProviderError> {
+                Ok(planned)
+            }
+
+            async fn delete(
+                &self,
+                _resource_type: &str,
+                _state: Value,
+            ) -> Result<(), ProviderError> {
+                Ok(())
+            }
+        }
+
+        let config = json!({"name": "web", "zone": "b"});
+        let result = CrudProvider
+            .plan("server", Some(prior()), config.clone(), config.clone())
+            .await
+            .unwrap();
+        assert!(result.requires_replace);
+
+        let err = CrudProvider
+            .plan("missing", None, config.clone(), config)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::UnknownResource(_)));
+    }
+}
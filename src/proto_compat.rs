@@ -0,0 +1,625 @@
+//! Wire format compatibility checks.
+//!
+//! Core and providers are released independently, so an SDK change that
+//! renumbers a proto field, changes its type or alters how a JSON payload is
+//! serialized breaks every provider built against one version talking to a
+//! core built against another. The tests in this module pin the encoding of a
+//! representative request and response for every RPC as golden hex, and the
+//! JSON encoding of the schema types carried inside them.
+//!
+//! Provider authors can pin their own wire behavior the same way, e.g. the
+//! states their provider returns or a schema core has cached:
+//!
+//! ```
+//! use hemmer_provider_sdk::generated::StopResponse;
+//! use hemmer_provider_sdk::proto_compat::{assert_json_format, assert_wire_format};
+//! use hemmer_provider_sdk::schema::AttributeType;
+//!
+//! assert_wire_format(
+//!     &StopResponse {
+//!         error: "busy".to_string(),
+//!     },
+//!     "0a0462757379",
+//! );
+//! assert_json_format(&AttributeType::list(AttributeType::String), r#"{"list":"string"}"#);
+//! ```
+//!
+//! When a golden value changes on purpose, the failure message shows the new
+//! encoding to paste in; changing it is a protocol change and needs a
+//! [protocol version](crate::PROTOCOL_VERSION) bump.
+
+use std::fmt::Debug;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::ProviderError;
+
+/// Encode bytes as lowercase hex.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode hex, ignoring whitespace so long fixtures can be wrapped.
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, ProviderError> {
+    let digits: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(ProviderError::Sdk(
+            "Hex string has an odd number of digits".to_string(),
+        ));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| {
+                    ProviderError::Sdk(format!(
+                        "Invalid hex digits '{}'",
+                        String::from_utf8_lossy(pair)
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Assert that `message` encodes to `golden_hex`, and that `golden_hex`
+/// decodes back to `message`.
+///
+/// # Panics
+///
+/// Panics with the actual encoding if either direction differs.
+pub fn assert_wire_format<M>(message: &M, golden_hex: &str)
+where
+    M: prost::Message + Default + PartialEq + Debug,
+{
+    let golden = from_hex(golden_hex).unwrap_or_else(|e| panic!("Invalid golden hex: {}", e));
+    let encoded = message.encode_to_vec();
+    assert!(
+        encoded == golden,
+        "Wire format changed for {}\n  golden: {}\n  actual: {}",
+        std::any::type_name::<M>(),
+        to_hex(&golden),
+        to_hex(&encoded)
+    );
+    let decoded = M::decode(golden.as_slice())
+        .unwrap_or_else(|e| panic!("Golden bytes no longer decode: {}", e));
+    assert_eq!(&decoded, message, "Golden bytes decode differently");
+}
+
+/// Assert that `value` serializes to the JSON `golden`, and that `golden`
+/// deserializes back to `value`.
+///
+/// `golden` is compared as a JSON value, so whitespace and key order don't
+/// matter.
+///
+/// # Panics
+///
+/// Panics with the actual JSON if either direction differs.
+pub fn assert_json_format<T>(value: &T, golden: &str)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let expected: serde_json::Value =
+        serde_json::from_str(golden).unwrap_or_else(|e| panic!("Invalid golden JSON: {}", e));
+    let actual = serde_json::to_value(value).unwrap();
+    assert!(
+        actual == expected,
+        "JSON format changed for {}\n  golden: {}\n  actual: {}",
+        std::any::type_name::<T>(),
+        expected,
+        actual
+    );
+    let decoded: T = serde_json::from_value(expected)
+        .unwrap_or_else(|e| panic!("Golden JSON no longer deserializes: {}", e));
+    assert_eq!(&decoded, value, "Golden JSON deserializes differently");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::*;
+    use crate::schema::{AttributeType, ObjectType};
+
+    fn bytes(s: &str) -> prost::bytes::Bytes {
+        prost::bytes::Bytes::copy_from_slice(s.as_bytes())
+    }
+
+    fn capabilities() -> Option<ClientCapabilities> {
+        Some(ClientCapabilities {
+            deferral_allowed: true,
+            warnings_on_apply: true,
+            streamed_progress: true,
+        })
+    }
+
+    fn diagnostics() -> Vec<Diagnostic> {
+        vec![Diagnostic {
+            severity: diagnostic::Severity::Error as i32,
+            summary: "bad".to_string(),
+            detail: "why".to_string(),
+            attribute: "name".to_string(),
+            suggestion: "fix".to_string(),
+            code: 3,
+            error_kind: "validation".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(to_hex(&[0x0a, 0xff]), "0aff");
+        assert_eq!(from_hex("0a ff\n00").unwrap(), vec![0x0a, 0xff, 0x00]);
+        assert!(from_hex("0").is_err());
+        assert!(from_hex("zz").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Wire format changed")]
+    fn test_wire_format_mismatch() {
+        assert_wire_format(
+            &StopResponse {
+                error: "busy".to_string(),
+            },
+            "0a0462757378",
+        );
+    }
+
+    #[test]
+    fn test_metadata_and_schema_rpcs() {
+        assert_wire_format(&GetMetadataRequest {}, "");
+        assert_wire_format(
+            &ServerCapabilities {
+                plan_destroy: true,
+                move_state: true,
+                import: true,
+                functions: true,
+                ephemeral_resources: true,
+                streaming_progress: true,
+                batch_apply: true,
+                identity: true,
+            },
+            "08011001180120012801300138014001",
+        );
+        assert_wire_format(
+            &GetMetadataResponse {
+                server_capabilities: Some(ServerCapabilities {
+                    plan_destroy: true,
+                    ..Default::default()
+                }),
+                resources: vec!["server".to_string()],
+                data_sources: vec!["image".to_string()],
+                diagnostics: diagnostics(),
+                import_formats: [(
+                    "server".to_string(),
+                    ImportFormats {
+                        formats: vec!["{id}".to_string()],
+                    },
+                )]
+                .into(),
+                max_parallelism: [("server".to_string(), 5)].into(),
+                refresh_intervals: [("server".to_string(), 60)].into(),
+            },
+            "0a02080112067365727665721a05696d6167652225080112036261641a037768\
+             7922046e616d652a0366697830033a0a76616c69646174696f6e2a100a067365\
+             7276657212060a047b69647d320a0a0673657276657210053a0a0a0673657276\
+             6572103c",
+        );
+        assert_wire_format(
+            &GetSchemaRequest {
+                client_protocol_version: 1,
+            },
+            "0801",
+        );
+        assert_wire_format(
+            &GetSchemaResponse {
+                server_protocol_version: 1,
+                provider: Some(Schema {
+                    version: 2,
+                    block: Some(Block {
+                        attributes: vec![Attribute {
+                            name: "region".to_string(),
+                            r#type: bytes("\"string\""),
+                            required: true,
+                            description: "Region".to_string(),
+                            ..Default::default()
+                        }],
+                        block_types: vec![NestedBlock {
+                            type_name: "disk".to_string(),
+                            block: Some(Block::default()),
+                            nesting_mode: nested_block::NestingMode::List as i32,
+                            min_items: 1,
+                            max_items: 2,
+                            force_new: true,
+                            sensitive: true,
+                        }],
+                        description: "Provider".to_string(),
+                    }),
+                    ..Default::default()
+                }),
+                diagnostics: diagnostics(),
+                functions: [(
+                    "join".to_string(),
+                    Function {
+                        parameters: vec![FunctionParameter {
+                            name: "sep".to_string(),
+                            r#type: bytes("\"string\""),
+                            ..Default::default()
+                        }],
+                        return_type: bytes("\"string\""),
+                        deterministic: true,
+                        ..Default::default()
+                    },
+                )]
+                .into(),
+                ..Default::default()
+            },
+            "080112400802123c0a1c0a06726567696f6e120822737472696e672218013a06\
+             526567696f6e12120a046469736b1200180220012802300138011a0850726f76\
+             696465722a25080112036261641a0377687922046e616d652a0366697830033a\
+             0a76616c69646174696f6e32250a046a6f696e121d0a0f0a0373657012082273\
+             7472696e67221a0822737472696e67222801",
+        );
+    }
+
+    #[test]
+    fn test_stream_schema_rpc() {
+        assert_wire_format(
+            &StreamSchemaRequest {
+                client_protocol_version: 1,
+                max_chunk_bytes: 4096,
+                accept_zstd: true,
+            },
+            "08011080201801",
+        );
+        assert_wire_format(
+            &SchemaChunk {
+                schema: Some(GetSchemaResponse {
+                    resources: [("server".to_string(), Schema::default())].into(),
+                    ..Default::default()
+                }),
+                zstd_schema: prost::bytes::Bytes::new(),
+            },
+            "0a0a1a080a06736572766572",
+        );
+        assert_wire_format(
+            &SchemaChunk {
+                schema: None,
+                zstd_schema: bytes("zstd"),
+            },
+            "12047a737464",
+        );
+    }
+
+    #[test]
+    fn test_provider_config_rpcs() {
+        assert_wire_format(
+            &ValidateProviderConfigRequest {
+                config: bytes("{}"),
+            },
+            "0a027b7d",
+        );
+        assert_wire_format(
+            &ValidateProviderConfigResponse {
+                diagnostics: diagnostics(),
+            },
+            "0a25080112036261641a0377687922046e616d652a0366697830033a0a76616c\
+             69646174696f6e",
+        );
+        assert_wire_format(
+            &ConfigureRequest {
+                config: bytes("{}"),
+                client_capabilities: capabilities(),
+            },
+            "0a027b7d1206080110011801",
+        );
+        assert_wire_format(
+            &ConfigureResponse {
+                diagnostics: diagnostics(),
+            },
+            "0a25080112036261641a0377687922046e616d652a0366697830033a0a76616c\
+             69646174696f6e",
+        );
+        assert_wire_format(&StopRequest {}, "");
+        assert_wire_format(
+            &StopResponse {
+                error: "busy".to_string(),
+            },
+            "0a0462757379",
+        );
+        assert_wire_format(
+            &EndSessionRequest {
+                session_id: "plan".to_string(),
+            },
+            "0a04706c616e",
+        );
+        assert_wire_format(&EndSessionResponse {}, "");
+    }
+
+    #[test]
+    fn test_plan_rpcs() {
+        assert_wire_format(
+            &ValidateResourceConfigRequest {
+                resource_type: "server".to_string(),
+                config: bytes("{}"),
+            },
+            "0a0673657276657212027b7d",
+        );
+        assert_wire_format(
+            &ValidateResourceConfigResponse {
+                diagnostics: diagnostics(),
+            },
+            "0a25080112036261641a0377687922046e616d652a0366697830033a0a76616c\
+             69646174696f6e",
+        );
+        assert_wire_format(
+            &UpgradeResourceStateRequest {
+                resource_type: "server".to_string(),
+                version: 1,
+                raw_state: bytes("{}"),
+            },
+            "0a0673657276657210011a027b7d",
+        );
+        assert_wire_format(
+            &UpgradeResourceStateResponse {
+                upgraded_state: bytes("{}"),
+                diagnostics: diagnostics(),
+            },
+            "0a027b7d1225080112036261641a0377687922046e616d652a0366697830033a\
+             0a76616c69646174696f6e",
+        );
+        assert_wire_format(
+            &PlanRequest {
+                resource_type: "server".to_string(),
+                prior_state: bytes("{}"),
+                proposed_state: bytes("{\"a\":1}"),
+                config: bytes("{\"a\":1}"),
+                client_capabilities: capabilities(),
+            },
+            "0a0673657276657212027b7d1a077b2261223a317d22077b2261223a317d2a06\
+             080110011801",
+        );
+        assert_wire_format(
+            &PlanResponse {
+                planned_state: bytes("{\"a\":1}"),
+                changes: vec![AttributeChange {
+                    path: "a".to_string(),
+                    before: bytes("null"),
+                    after: bytes("1"),
+                    action: attribute_change::Action::Create as i32,
+                    sensitive: true,
+                    note: "new".to_string(),
+                }],
+                requires_replace: true,
+                diagnostics: diagnostics(),
+                replace_reasons: vec![ReplaceReason {
+                    path: "a".to_string(),
+                    reason: "immutable".to_string(),
+                }],
+                impact: Some(ImpactEstimate {
+                    monthly_cost: Some(1.5),
+                    currency: "USD".to_string(),
+                    destructiveness: Some(0.0),
+                    propagation_delay_secs: Some(30),
+                }),
+            },
+            "0a077b2261223a317d12150a016112046e756c6c1a01312001280132036e6577\
+             18012225080112036261641a0377687922046e616d652a0366697830033a0a76\
+             616c69646174696f6e2a0e0a01611209696d6d757461626c6532190900000000\
+             0000f83f1203555344190000000000000000201e",
+        );
+        assert_wire_format(
+            &PreApplyCheckRequest {
+                resource_type: "server".to_string(),
+                planned_state: bytes("{}"),
+                client_capabilities: capabilities(),
+            },
+            "0a0673657276657212027b7d1a06080110011801",
+        );
+        assert_wire_format(
+            &PreApplyCheckResponse {
+                diagnostics: diagnostics(),
+            },
+            "0a25080112036261641a0377687922046e616d652a0366697830033a0a76616c\
+             69646174696f6e",
+        );
+    }
+
+    #[test]
+    fn test_apply_rpcs() {
+        assert_wire_format(
+            &CreateRequest {
+                resource_type: "server".to_string(),
+                planned_state: bytes("{}"),
+                client_capabilities: capabilities(),
+                config: bytes("{}"),
+            },
+            "0a0673657276657212027b7d1a0608011001180122027b7d",
+        );
+        assert_wire_format(
+            &CreateResponse {
+                state: bytes("{}"),
+                diagnostics: diagnostics(),
+            },
+            "0a027b7d1225080112036261641a0377687922046e616d652a0366697830033a\
+             0a76616c69646174696f6e",
+        );
+        assert_wire_format(
+            &ReadRequest {
+                resource_type: "server".to_string(),
+                current_state: bytes("{}"),
+                client_capabilities: capabilities(),
+            },
+            "0a0673657276657212027b7d1a06080110011801",
+        );
+        assert_wire_format(
+            &ReadResponse {
+                state: bytes("{}"),
+                diagnostics: diagnostics(),
+                status: ReadStatus::Stale.into(),
+            },
+            "0a027b7d1225080112036261641a0377687922046e616d652a0366697830033a\
+             0a76616c69646174696f6e1801",
+        );
+        assert_wire_format(
+            &ReadResponse {
+                status: ReadStatus::Tainted.into(),
+                ..Default::default()
+            },
+            "1803",
+        );
+        assert_wire_format(
+            &UpdateRequest {
+                resource_type: "server".to_string(),
+                prior_state: bytes("{}"),
+                planned_state: bytes("{\"a\":1}"),
+                client_capabilities: capabilities(),
+                config: bytes("{\"a\":1}"),
+            },
+            "0a0673657276657212027b7d1a077b2261223a317d22060801100118012a077b\
+             2261223a317d",
+        );
+        assert_wire_format(
+            &UpdateResponse {
+                state: bytes("{\"a\":1}"),
+                diagnostics: diagnostics(),
+            },
+            "0a077b2261223a317d1225080112036261641a0377687922046e616d652a0366\
+             697830033a0a76616c69646174696f6e",
+        );
+        assert_wire_format(
+            &DeleteRequest {
+                resource_type: "server".to_string(),
+                current_state: bytes("{}"),
+                client_capabilities: capabilities(),
+                config: bytes("{}"),
+            },
+            "0a0673657276657212027b7d1a0608011001180122027b7d",
+        );
+        assert_wire_format(
+            &DeleteResponse {
+                diagnostics: diagnostics(),
+            },
+            "0a25080112036261641a0377687922046e616d652a0366697830033a0a76616c\
+             69646174696f6e",
+        );
+        assert_wire_format(
+            &ImportResourceStateRequest {
+                resource_type: "server".to_string(),
+                id: "i-1".to_string(),
+                client_capabilities: capabilities(),
+            },
+            "0a067365727665721203692d311a06080110011801",
+        );
+        assert_wire_format(
+            &ImportResourceStateResponse {
+                imported: vec![ImportedResource {
+                    resource_type: "server".to_string(),
+                    state: bytes("{}"),
+                }],
+                diagnostics: diagnostics(),
+            },
+            "0a0c0a0673657276657212027b7d1225080112036261641a0377687922046e61\
+             6d652a0366697830033a0a76616c69646174696f6e",
+        );
+        assert_wire_format(
+            &BulkImportResourceStateRequest {
+                resource_type: "server".to_string(),
+                ids: vec!["i-1".to_string(), "i-2".to_string()],
+                query: bytes("{}"),
+                client_capabilities: capabilities(),
+            },
+            "0a067365727665721203692d311203692d321a027b7d2206080110011801",
+        );
+        assert_wire_format(
+            &BulkImportResult {
+                id: "i-1".to_string(),
+                imported: vec![ImportedResource {
+                    resource_type: "server".to_string(),
+                    state: bytes("{}"),
+                }],
+                diagnostics: diagnostics(),
+            },
+            "0a03692d31120c0a0673657276657212027b7d1a25080112036261641a037768\
+             7922046e616d652a0366697830033a0a76616c69646174696f6e",
+        );
+    }
+
+    #[test]
+    fn test_data_source_and_event_rpcs() {
+        assert_wire_format(
+            &ValidateDataSourceConfigRequest {
+                data_source_type: "image".to_string(),
+                config: bytes("{}"),
+            },
+            "0a05696d61676512027b7d",
+        );
+        assert_wire_format(
+            &ValidateDataSourceConfigResponse {
+                diagnostics: diagnostics(),
+            },
+            "0a25080112036261641a0377687922046e616d652a0366697830033a0a76616c\
+             69646174696f6e",
+        );
+        assert_wire_format(
+            &ReadDataSourceRequest {
+                data_source_type: "image".to_string(),
+                config: bytes("{}"),
+                client_capabilities: capabilities(),
+            },
+            "0a05696d61676512027b7d1a06080110011801",
+        );
+        assert_wire_format(
+            &ReadDataSourceResponse {
+                state: bytes("{}"),
+                diagnostics: diagnostics(),
+                status: ReadStatus::Degraded.into(),
+            },
+            "0a027b7d1225080112036261641a0377687922046e616d652a0366697830033a\
+             0a76616c69646174696f6e1802",
+        );
+        assert_wire_format(&EventsRequest { ack_sequence: 7 }, "0807");
+        assert_wire_format(
+            &ProviderEvent {
+                sequence: 8,
+                kind: "quota".to_string(),
+                diagnostic: diagnostics().pop(),
+                resource_type: "server".to_string(),
+            },
+            "0808120571756f74611a25080112036261641a0377687922046e616d652a0366\
+             697830033a0a76616c69646174696f6e2206736572766572",
+        );
+    }
+
+    #[test]
+    fn test_schema_json_format() {
+        assert_json_format(&AttributeType::String, r#""string""#);
+        assert_json_format(
+            &AttributeType::map(AttributeType::set(AttributeType::Int64)),
+            r#"{"map":{"set":"int64"}}"#,
+        );
+        assert_json_format(
+            &AttributeType::from(
+                ObjectType::new()
+                    .with_attribute("host", AttributeType::String)
+                    .with_optional_attribute("port", AttributeType::Int64),
+            ),
+            r#"{"object":{"attributes":{"host":"string","port":"int64"},"optional":["port"]}}"#,
+        );
+        assert_json_format(
+            &crate::schema::Attribute::optional_string()
+                .with_description("Name")
+                .with_default(serde_json::json!("web")),
+            r#"{
+                "type": "string",
+                "required": false,
+                "optional": true,
+                "computed": false,
+                "sensitive": false,
+                "description": "Name",
+                "default": "web",
+                "force_new": false
+            }"#,
+        );
+    }
+}
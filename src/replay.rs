@@ -0,0 +1,628 @@
+//! Replaying recorded provider operations.
+//!
+//! Core can record the provider operations of a run (a customer's failed
+//! apply, say) as a trace: a JSONL file with one operation per line, holding
+//! the request and the response the provider gave. [`replay`] feeds the
+//! requests to a provider implementation in order and diffs its responses
+//! against the recorded ones, so the failure can be reproduced and debugged
+//! locally:
+//!
+//! ```text
+//! {"operation":"configure","config":{"region":"us-east-1"},"result":[]}
+//! {"operation":"create","resource_type":"server","planned_state":{"name":"web"},"result":{"name":"web","id":"i-1"}}
+//! {"operation":"read","resource_type":"server","current_state":{"id":"i-1"},"error":"Resource not found: i-1"}
+//! ```
+//!
+//! Each line has an `operation` with its request fields, and either the
+//! `result` of the operation or the `error` message it failed with. Results
+//! are the JSON form of what the [`ProviderService`] method returned: the state
+//! for `create`, `update` and `upgrade_resource_state`, a [`PlanResult`] for
+//! `plan`, a [`ReadResult`] for `read` and `read_data_source`, the
+//! diagnostics for `configure` and the `validate_*` operations, and `null`
+//! for `delete` and `stop`.
+//!
+//! ```
+//! use hemmer_provider_sdk::replay::{replay, ReplayOptions, Trace};
+//! # use hemmer_provider_sdk::{ConfigureResult, ProviderError, ProviderSchema, ProviderService};
+//! # use serde_json::Value;
+//! # struct Echo;
+//! # #[async_trait::async_trait]
+//! # impl ProviderService for Echo {
+//! #     fn schema(&self) -> ProviderSchema { ProviderSchema::new() }
+//! #     async fn configure(&self, _: Value) -> Result<ConfigureResult, ProviderError> { Ok(ConfigureResult::new()) }
+//! #     async fn create(&self, _: &str, s: Value) -> Result<Value, ProviderError> { Ok(s) }
+//! #     async fn read(&self, _: &str, s: Value) -> Result<Value, ProviderError> { Ok(s) }
+//! #     async fn update(&self, _: &str, _: Value, s: Value) -> Result<Value, ProviderError> { Ok(s) }
+//! #     async fn delete(&self, _: &str, _: Value) -> Result<(), ProviderError> { Ok(()) }
+//! # }
+//! # tokio_test::block_on(async {
+//! let trace = Trace::from_jsonl(
+//!     r#"{"operation":"create","resource_type":"server","planned_state":{"name":"web"},"result":{"name":"db"}}"#,
+//! )
+//! .unwrap();
+//! let report = replay(&Echo, &trace, &ReplayOptions::new()).await;
+//! assert_eq!(
+//!     report.mismatches[0].differences,
+//!     vec![r#"/name: expected "db", got "web""#]
+//! );
+//! # });
+//! ```
+//!
+//! Values that legitimately differ between runs (timestamps, generated ids)
+//! can be left out of the comparison with [`ReplayOptions::with_ignored`].
+
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::context::ProviderDataStore;
+use crate::error::ProviderError;
+use crate::patch::{JsonPatch, PatchOperation};
+use crate::server::ProviderService;
+use crate::types::{
+    CreateRequest, DeleteRequest, PlanResult, ReadDataSourceRequest, ReadRequest, ReadResult,
+    UpdateRequest,
+};
+
+/// A provider operation and its request fields.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum TraceCall {
+    /// `validate_provider_config`
+    ValidateProviderConfig {
+        /// The provider configuration.
+        config: Value,
+    },
+    /// `configure`
+    Configure {
+        /// The provider configuration.
+        config: Value,
+    },
+    /// `stop`
+    Stop,
+    /// `validate_resource_config`
+    ValidateResourceConfig {
+        /// The resource type.
+        resource_type: String,
+        /// The resource configuration.
+        config: Value,
+    },
+    /// `upgrade_resource_state`
+    UpgradeResourceState {
+        /// The resource type.
+        resource_type: String,
+        /// The schema version the state was stored with.
+        version: i64,
+        /// The stored state.
+        state: Value,
+    },
+    /// `plan`
+    Plan {
+        /// The resource type.
+        resource_type: String,
+        /// The prior state, absent for a create.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prior_state: Option<Value>,
+        /// The proposed state.
+        proposed_state: Value,
+        /// The resource configuration.
+        #[serde(default)]
+        config: Value,
+    },
+    /// `handle_create`
+    Create {
+        /// The resource type.
+        resource_type: String,
+        /// The planned state.
+        planned_state: Value,
+        /// The resource configuration.
+        #[serde(default)]
+        config: Value,
+    },
+    /// `handle_read`
+    Read {
+        /// The resource type.
+        resource_type: String,
+        /// The current state.
+        current_state: Value,
+    },
+    /// `handle_update`
+    Update {
+        /// The resource type.
+        resource_type: String,
+        /// The prior state.
+        prior_state: Value,
+        /// The planned state.
+        planned_state: Value,
+        /// The resource configuration.
+        #[serde(default)]
+        config: Value,
+    },
+    /// `handle_delete`
+    Delete {
+        /// The resource type.
+        resource_type: String,
+        /// The current state.
+        current_state: Value,
+        /// The resource's last configuration.
+        #[serde(default)]
+        config: Value,
+    },
+    /// `import_resource`
+    Import {
+        /// The resource type.
+        resource_type: String,
+        /// The import ID.
+        id: String,
+    },
+    /// `validate_data_source_config`
+    ValidateDataSourceConfig {
+        /// The data source type.
+        data_source_type: String,
+        /// The data source configuration.
+        config: Value,
+    },
+    /// `handle_read_data_source`
+    ReadDataSource {
+        /// The data source type.
+        data_source_type: String,
+        /// The data source configuration.
+        config: Value,
+    },
+}
+
+impl TraceCall {
+    /// The operation name, as written in traces.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ValidateProviderConfig { .. } => "validate_provider_config",
+            Self::Configure { .. } => "configure",
+            Self::Stop => "stop",
+            Self::ValidateResourceConfig { .. } => "validate_resource_config",
+            Self::UpgradeResourceState { .. } => "upgrade_resource_state",
+            Self::Plan { .. } => "plan",
+            Self::Create { .. } => "create",
+            Self::Read { .. } => "read",
+            Self::Update { .. } => "update",
+            Self::Delete { .. } => "delete",
+            Self::Import { .. } => "import",
+            Self::ValidateDataSourceConfig { .. } => "validate_data_source_config",
+            Self::ReadDataSource { .. } => "read_data_source",
+        }
+    }
+}
+
+/// What an operation returned: its result, or the message it failed with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceOutcome {
+    /// The JSON form of the operation's result.
+    Result(Value),
+    /// The error message.
+    Error(String),
+}
+
+/// One line of a trace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceEntry {
+    /// The operation and its request.
+    #[serde(flatten)]
+    pub call: TraceCall,
+    /// The recorded response.
+    #[serde(flatten)]
+    pub outcome: TraceOutcome,
+}
+
+/// A sequence of recorded provider operations.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    /// The operations, in the order they ran.
+    pub entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    /// Create an empty trace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an operation.
+    pub fn with_entry(mut self, call: TraceCall, outcome: TraceOutcome) -> Self {
+        self.entries.push(TraceEntry { call, outcome });
+        self
+    }
+
+    /// Parse a JSONL trace. Blank lines are skipped.
+    pub fn from_jsonl(jsonl: &str) -> Result<Self, ProviderError> {
+        let entries = jsonl
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    ProviderError::Validation(format!("Invalid trace line {}: {}", i + 1, e))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { entries })
+    }
+
+    /// Read a JSONL trace file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ProviderError> {
+        let path = path.as_ref();
+        let jsonl = std::fs::read_to_string(path).map_err(|e| {
+            ProviderError::Validation(format!("Cannot read trace {}: {}", path.display(), e))
+        })?;
+        Self::from_jsonl(&jsonl)
+    }
+
+    /// Write the trace as JSONL.
+    pub fn to_jsonl(&self) -> String {
+        self.entries
+            .iter()
+            .filter_map(|entry| serde_json::to_string(entry).ok())
+            .map(|line| line + "\n")
+            .collect()
+    }
+}
+
+/// How [`replay`] compares responses.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayOptions {
+    ignored: Vec<String>,
+}
+
+impl ReplayOptions {
+    /// Compare every value.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leave the value at `pointer` (a JSON Pointer into the result, e.g.
+    /// `/updated_at`), and everything below it, out of comparisons.
+    pub fn with_ignored(mut self, pointer: impl Into<String>) -> Self {
+        self.ignored.push(pointer.into());
+        self
+    }
+
+    fn is_ignored(&self, path: &str) -> bool {
+        self.ignored.iter().any(|ignored| {
+            path.strip_prefix(ignored.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+}
+
+/// A replayed operation whose response differs from the recorded one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// The trace line, counting from 1.
+    pub line: usize,
+    /// The operation name.
+    pub operation: &'static str,
+    /// What differs, one description per value.
+    pub differences: Vec<String>,
+}
+
+/// The outcome of a [`replay`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+    /// Number of operations replayed.
+    pub replayed: usize,
+    /// The operations whose responses differ.
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl ReplayReport {
+    /// Whether every response matched.
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for mismatch in &self.mismatches {
+            writeln!(f, "line {} ({}):", mismatch.line, mismatch.operation)?;
+            for difference in &mismatch.differences {
+                writeln!(f, "  {}", difference)?;
+            }
+        }
+        write!(
+            f,
+            "{} of {} operations matched",
+            self.replayed - self.mismatches.len(),
+            self.replayed
+        )
+    }
+}
+
+/// Replay `trace` against `provider` and diff its responses against the
+/// recorded ones.
+///
+/// Operations run in order with the provider data from the last successful
+/// `configure` in scope, as the server runs them. Entries are numbered as
+/// non-blank lines of the trace.
+pub async fn replay<P: ProviderService + ?Sized>(
+    provider: &P,
+    trace: &Trace,
+    options: &ReplayOptions,
+) -> ReplayReport {
+    let provider_data = ProviderDataStore::new();
+    let mut report = ReplayReport::default();
+    for (i, entry) in trace.entries.iter().enumerate() {
+        let outcome = provider_data
+            .context()
+            .scope(call(provider, &provider_data, entry.call.clone()))
+            .await;
+        report.replayed += 1;
+        let differences = compare(&entry.outcome, &outcome, options);
+        if !differences.is_empty() {
+            report.mismatches.push(Mismatch {
+                line: i + 1,
+                operation: entry.call.name(),
+                differences,
+            });
+        }
+    }
+    report
+}
+
+/// Run one operation, storing the provider data if it is a `configure`.
+async fn call<P: ProviderService + ?Sized>(
+    provider: &P,
+    provider_data: &ProviderDataStore,
+    call: TraceCall,
+) -> TraceOutcome {
+    let result = match call {
+        TraceCall::ValidateProviderConfig { config } => {
+            json(provider.validate_provider_config(config).await)
+        },
+        TraceCall::Configure { config } => match provider.configure(config).await {
+            Ok(result) => {
+                if let (false, Some(data)) = (result.has_errors(), result.provider_data) {
+                    provider_data.set(data);
+                }
+                json(Ok(result.diagnostics))
+            },
+            Err(e) => Err(e),
+        },
+        TraceCall::Stop => json(provider.stop().await),
+        TraceCall::ValidateResourceConfig {
+            resource_type,
+            config,
+        } => json(
+            provider
+                .validate_resource_config(&resource_type, config)
+                .await,
+        ),
+        TraceCall::UpgradeResourceState {
+            resource_type,
+            version,
+            state,
+        } => json(
+            provider
+                .upgrade_resource_state(&resource_type, version, state)
+                .await,
+        ),
+        TraceCall::Plan {
+            resource_type,
+            prior_state,
+            proposed_state,
+            config,
+        } => json::<PlanResult>(
+            provider
+                .plan(&resource_type, prior_state, proposed_state, config)
+                .await,
+        ),
+        TraceCall::Create {
+            resource_type,
+            planned_state,
+            config,
+        } => json(
+            provider
+                .handle_create(CreateRequest::new(resource_type, planned_state).with_config(config))
+                .await,
+        ),
+        TraceCall::Read {
+            resource_type,
+            current_state,
+        } => json::<ReadResult>(
+            provider
+                .handle_read(ReadRequest::new(resource_type, current_state))
+                .await,
+        ),
+        TraceCall::Update {
+            resource_type,
+            prior_state,
+            planned_state,
+            config,
+        } => json(
+            provider
+                .handle_update(
+                    UpdateRequest::new(resource_type, prior_state, planned_state)
+                        .with_config(config),
+                )
+                .await,
+        ),
+        TraceCall::Delete {
+            resource_type,
+            current_state,
+            config,
+        } => json(
+            provider
+                .handle_delete(DeleteRequest::new(resource_type, current_state).with_config(config))
+                .await,
+        ),
+        TraceCall::Import { resource_type, id } => {
+            json(provider.import_resource(&resource_type, &id).await)
+        },
+        TraceCall::ValidateDataSourceConfig {
+            data_source_type,
+            config,
+        } => json(
+            provider
+                .validate_data_source_config(&data_source_type, config)
+                .await,
+        ),
+        TraceCall::ReadDataSource {
+            data_source_type,
+            config,
+        } => json::<ReadResult>(
+            provider
+                .handle_read_data_source(ReadDataSourceRequest::new(data_source_type, config))
+                .await,
+        ),
+    };
+    match result {
+        Ok(value) => TraceOutcome::Result(value),
+        Err(e) => TraceOutcome::Error(e.to_string()),
+    }
+}
+
+fn json<T: Serialize>(result: Result<T, ProviderError>) -> Result<Value, ProviderError> {
+    Ok(serde_json::to_value(result?)?)
+}
+
+/// Describe how `actual` differs from `expected`.
+fn compare(expected: &TraceOutcome, actual: &TraceOutcome, options: &ReplayOptions) -> Vec<String> {
+    match (expected, actual) {
+        (TraceOutcome::Result(expected), TraceOutcome::Result(actual)) => {
+            JsonPatch::diff(expected, actual)
+                .operations()
+                .iter()
+                .filter(|op| !options.is_ignored(op.path()))
+                .map(|op| describe(expected, op))
+                .collect()
+        },
+        (TraceOutcome::Error(expected), TraceOutcome::Error(actual)) if expected == actual => {
+            Vec::new()
+        },
+        (TraceOutcome::Error(expected), TraceOutcome::Error(actual)) => {
+            vec![format!(
+                "expected error {:?}, got error {:?}",
+                expected, actual
+            )]
+        },
+        (TraceOutcome::Error(expected), TraceOutcome::Result(_)) => {
+            vec![format!(
+                "expected error {:?}, but the operation succeeded",
+                expected
+            )]
+        },
+        (TraceOutcome::Result(_), TraceOutcome::Error(actual)) => {
+            vec![format!("expected a result, got error {:?}", actual)]
+        },
+    }
+}
+
+fn describe(expected: &Value, op: &PatchOperation) -> String {
+    let path = if op.path().is_empty() { "/" } else { op.path() };
+    match op {
+        PatchOperation::Add { value, .. } => format!("{}: unexpected {}", path, value),
+        PatchOperation::Remove { .. } => format!("{}: missing", path),
+        PatchOperation::Replace { value, .. } => format!(
+            "{}: expected {}, got {}",
+            path,
+            expected.pointer(op.path()).unwrap_or(&Value::Null),
+            value
+        ),
+        other => format!("{}: differs ({:?})", path, other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::schema::ProviderSchema;
+    use crate::types::ConfigureResult;
+    use serde_json::json;
+
+    struct Servers;
+
+    #[async_trait::async_trait]
+    impl ProviderService for Servers {
+        fn schema(&self) -> ProviderSchema {
+            ProviderSchema::new()
+        }
+
+        async fn configure(&self, config: Value) -> Result<ConfigureResult, ProviderError> {
+            let region = config["region"].as_str().unwrap_or_default().to_string();
+            Ok(ConfigureResult::new().with_provider_data(region))
+        }
+
+        async fn create(&self, _: &str, mut state: Value) -> Result<Value, ProviderError> {
+            let region = Context::current().data::<String>()?;
+            state["id"] = json!(format!("{}/i-1", region));
+            state["created_at"] = json!("2026-10-15T12:00:00Z");
+            Ok(state)
+        }
+
+        async fn read(&self, _: &str, state: Value) -> Result<Value, ProviderError> {
+            Err(ProviderError::NotFound(state["id"].to_string()))
+        }
+
+        async fn update(&self, _: &str, _: Value, state: Value) -> Result<Value, ProviderError> {
+            Ok(state)
+        }
+
+        async fn delete(&self, _: &str, _: Value) -> Result<(), ProviderError> {
+            Ok(())
+        }
+    }
+
+    const TRACE: &str = r#"
+{"operation":"configure","config":{"region":"eu"},"result":[]}
+{"operation":"create","resource_type":"server","planned_state":{"name":"web"},"result":{"name":"web","id":"eu/i-1","created_at":"2026-01-01T00:00:00Z"}}
+{"operation":"read","resource_type":"server","current_state":{"id":"i-1"},"error":"Resource not found: \"i-1\""}
+{"operation":"delete","resource_type":"server","current_state":{"id":"i-1"},"result":null}
+"#;
+
+    #[tokio::test]
+    async fn test_replay_matches() {
+        let trace = Trace::from_jsonl(TRACE).unwrap();
+        assert_eq!(trace.entries.len(), 4);
+        assert_eq!(Trace::from_jsonl(&trace.to_jsonl()).unwrap(), trace);
+
+        let options = ReplayOptions::new().with_ignored("/created_at");
+        let report = replay(&Servers, &trace, &options).await;
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.replayed, 4);
+        assert_eq!(report.to_string(), "4 of 4 operations matched");
+    }
+
+    #[tokio::test]
+    async fn test_replay_reports_differences() {
+        let trace = Trace::from_jsonl(TRACE).unwrap().with_entry(
+            TraceCall::Update {
+                resource_type: "server".to_string(),
+                prior_state: json!({"name": "web"}),
+                planned_state: json!({"name": "api"}),
+                config: Value::Null,
+            },
+            TraceOutcome::Error("Update not allowed".to_string()),
+        );
+        let report = replay(&Servers, &trace, &ReplayOptions::new()).await;
+        assert_eq!(report.mismatches.len(), 2);
+        assert_eq!(report.mismatches[0].line, 2);
+        assert_eq!(report.mismatches[0].operation, "create");
+        assert_eq!(
+            report.mismatches[0].differences,
+            vec![r#"/created_at: expected "2026-01-01T00:00:00Z", got "2026-10-15T12:00:00Z""#]
+        );
+        assert_eq!(
+            report.mismatches[1].differences,
+            vec![r#"expected error "Update not allowed", but the operation succeeded"#]
+        );
+        assert!(report.to_string().ends_with("3 of 5 operations matched"));
+    }
+
+    #[test]
+    fn test_invalid_trace() {
+        let err = Trace::from_jsonl("\n{\"operation\":\"launch\"}").unwrap_err();
+        assert!(err.to_string().contains("Invalid trace line 2"));
+    }
+}
@@ -0,0 +1,255 @@
+//! Retries with exponential backoff.
+//!
+//! [`RetryPolicy`] decides how many times a failed call is attempted and how
+//! long to wait in between. [`RetryPolicy::run`] retries errors that are
+//! usually transient ([`is_retryable`]: unavailable, timed out, rate limited)
+//! and gives up early when the operation is cancelled (see
+//! [`Context::cancelled`](crate::context::Context::cancelled)). The
+//! [`http`](crate::http) client (feature: `http`) retries requests with the same
+//! policy.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use hemmer_provider_sdk::retry::RetryPolicy;
+//! use hemmer_provider_sdk::ProviderError;
+//!
+//! # tokio_test::block_on(async {
+//! let policy = RetryPolicy::new()
+//!     .with_max_attempts(3)
+//!     .with_initial_backoff(Duration::from_millis(1));
+//!
+//! let mut calls = 0;
+//! let result = policy
+//!     .run(|| {
+//!         calls += 1;
+//!         let attempt = calls;
+//!         async move {
+//!             if attempt < 3 {
+//!                 Err(ProviderError::Unavailable("try again".to_string()))
+//!             } else {
+//!                 Ok(attempt)
+//!             }
+//!         }
+//!     })
+//!     .await;
+//! assert_eq!(result.unwrap(), 3);
+//! # });
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use tracing::debug;
+
+use crate::context::Context;
+use crate::error::ProviderError;
+
+/// How often and how patiently to retry a failed call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first. 1 disables retries.
+    pub max_attempts: u32,
+    /// Wait before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for any single wait.
+    pub max_backoff: Duration,
+    /// Factor the wait grows by after each retry.
+    pub multiplier: f64,
+    /// Randomize each wait between half and all of its nominal length, so
+    /// clients that failed together don't retry together.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create the default policy: 4 attempts, waiting 200ms, 400ms and 800ms
+    /// (with jitter) in between.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self::default().with_max_attempts(1)
+    }
+
+    /// Set the total number of attempts, including the first.
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Set the wait before the first retry.
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Set the upper bound for any single wait.
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Set the factor the wait grows by after each retry.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Enable or disable jitter.
+    pub fn with_jitter(mut self, enabled: bool) -> Self {
+        self.jitter = enabled;
+        self
+    }
+
+    /// The wait before retry number `retry` (1 for the first retry).
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let nominal = self
+            .initial_backoff
+            .mul_f64(self.multiplier.max(1.0).powi(exponent).min(u32::MAX as f64))
+            .min(self.max_backoff);
+        if self.jitter {
+            nominal.mul_f64(0.5 + random_fraction() / 2.0)
+        } else {
+            nominal
+        }
+    }
+
+    /// Whether another attempt may follow attempt number `attempt` (1 for the
+    /// first), given the current operation has not been cancelled.
+    pub fn should_retry(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts && !Context::current().is_cancelled()
+    }
+
+    /// Call `op` until it succeeds, fails with an error that is not
+    /// [retryable](is_retryable), or runs out of attempts.
+    ///
+    /// Returns the last result. Waiting stops early if the operation is
+    /// cancelled.
+    pub async fn run<T, F, Fut>(&self, mut op: F) -> Result<T, ProviderError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Err(e) if is_retryable(&e) && self.should_retry(attempt) => {
+                    let backoff = self.backoff(attempt);
+                    debug!(attempt, error = %e, ?backoff, "Retrying after transient error");
+                    if !sleep_unless_cancelled(backoff).await {
+                        return Err(e);
+                    }
+                    attempt += 1;
+                },
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Whether an error is usually transient, so the call is worth retrying.
+pub fn is_retryable(err: &ProviderError) -> bool {
+    matches!(
+        err,
+        ProviderError::Unavailable(_)
+            | ProviderError::DeadlineExceeded(_)
+            | ProviderError::ResourceExhausted(_)
+            | ProviderError::Transport(_)
+    )
+}
+
+/// Wait for `duration`. Returns `false` if the current operation was cancelled first.
+pub(crate) async fn sleep_unless_cancelled(duration: Duration) -> bool {
+    let context = Context::current();
+    tokio::select! {
+        _ = tokio::time::sleep(duration) => true,
+        _ = context.cancelled() => false,
+    }
+}
+
+/// A random number in `[0, 1)`, good enough for spreading out retries.
+fn random_fraction() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::CancellationSource;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::new()
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_max_backoff(Duration::from_millis(350))
+            .with_jitter(false);
+        let waits: Vec<_> = (1..=4).map(|retry| policy.backoff(retry)).collect();
+        assert_eq!(
+            waits,
+            [100, 200, 350, 350].map(Duration::from_millis).to_vec()
+        );
+
+        let jittered = policy.with_jitter(true).backoff(2);
+        assert!(jittered >= Duration::from_millis(100) && jittered <= Duration::from_millis(200));
+        assert_eq!(RetryPolicy::none().max_attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_transient_errors() {
+        let policy = RetryPolicy::new().with_initial_backoff(Duration::from_millis(1));
+
+        let mut calls = 0;
+        let result: Result<(), _> = policy
+            .run(|| {
+                calls += 1;
+                async { Err(ProviderError::Unavailable("down".to_string())) }
+            })
+            .await;
+        assert!(matches!(result, Err(ProviderError::Unavailable(_))));
+        assert_eq!(calls, 4);
+
+        let mut calls = 0;
+        let result: Result<(), _> = policy
+            .run(|| {
+                calls += 1;
+                async { Err(ProviderError::NotFound("gone".to_string())) }
+            })
+            .await;
+        assert!(matches!(result, Err(ProviderError::NotFound(_))));
+        assert_eq!(calls, 1);
+
+        // A cancelled operation stops retrying
+        let source = CancellationSource::new();
+        source.cancel();
+        let mut calls = 0;
+        let result: Result<(), _> = Context::new()
+            .with_cancellation(source.token())
+            .scope(policy.run(|| {
+                calls += 1;
+                async { Err(ProviderError::Unavailable("down".to_string())) }
+            }))
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
@@ -4,7 +4,66 @@
 //! They enable validation, documentation generation, and proper state management.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::error::ProviderError;
+use crate::i18n::{sdk_diagnostic, Message};
+use crate::import::{parse_import_id, ImportId};
+use crate::normalize::Normalizer;
+
+/// Build a version 0 [`Schema`] from a list of attributes.
+///
+/// Each entry names an attribute and an [`Attribute`] constructor, optionally
+/// followed by builder calls. Names that aren't Rust identifiers can be given
+/// as string literals. Blocks and other settings are added with the usual
+/// builder methods.
+///
+/// ```
+/// use hemmer_provider_sdk::schema;
+/// use hemmer_provider_sdk::schema::{Attribute, Schema};
+/// use serde_json::json;
+///
+/// let schema = schema! {
+///     name: required_string,
+///     size: optional_int64.with_default(json!(10)),
+///     "type": optional_string.with_force_new(),
+///     id: computed_string,
+/// };
+///
+/// let expected = Schema::v0()
+///     .with_attribute("name", Attribute::required_string())
+///     .with_attribute("size", Attribute::optional_int64().with_default(json!(10)))
+///     .with_attribute("type", Attribute::optional_string().with_force_new())
+///     .with_attribute("id", Attribute::computed_string());
+/// assert_eq!(schema, expected);
+/// ```
+#[macro_export]
+macro_rules! schema {
+    ($($entries:tt)*) => {
+        $crate::schema::Schema::v0().with_attributes($crate::__schema_attributes!([] $($entries)*))
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __schema_attributes {
+    ([$($out:tt)*]) => {{
+        let attributes: ::std::vec::Vec<(&str, $crate::schema::Attribute)> = ::std::vec![$($out)*];
+        attributes
+    }};
+    ([$($out:tt)*] $name:ident : $ctor:ident $(. $method:ident ( $($arg:expr),* ))* $(, $($rest:tt)*)?) => {
+        $crate::__schema_attributes!(
+            [$($out)* (::std::stringify!($name), $crate::schema::Attribute::$ctor() $(.$method($($arg),*))*),]
+            $($($rest)*)?
+        )
+    };
+    ([$($out:tt)*] $name:literal : $ctor:ident $(. $method:ident ( $($arg:expr),* ))* $(, $($rest:tt)*)?) => {
+        $crate::__schema_attributes!(
+            [$($out)* ($name, $crate::schema::Attribute::$ctor() $(.$method($($arg),*))*),]
+            $($($rest)*)?
+        )
+    };
+}
 
 /// The type of an attribute value.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -25,7 +84,7 @@ pub enum AttributeType {
     /// A map from string keys to values of a single type.
     Map(Box<AttributeType>),
     /// An object with a fixed set of attributes.
-    Object(HashMap<String, AttributeType>),
+    Object(ObjectType),
     /// A dynamic type that can hold any value (use sparingly).
     Dynamic,
 }
@@ -46,9 +105,118 @@ impl AttributeType {
         Self::Map(Box::new(element_type))
     }
 
-    /// Create an object type.
-    pub fn object(attributes: HashMap<String, AttributeType>) -> Self {
-        Self::Object(attributes)
+    /// Create an object type whose attributes are all required.
+    pub fn object(attributes: BTreeMap<String, AttributeType>) -> Self {
+        Self::Object(ObjectType::from(attributes))
+    }
+}
+
+/// The attributes of an [`AttributeType::Object`].
+///
+/// Every attribute must be present (and not null) in a value of the type,
+/// unless it is listed in `optional`.
+///
+/// ```
+/// use hemmer_provider_sdk::schema::{AttributeType, ObjectType};
+///
+/// let endpoint: AttributeType = ObjectType::new()
+///     .with_attribute("host", AttributeType::String)
+///     .with_optional_attribute("port", AttributeType::Int64)
+///     .into();
+/// ```
+///
+/// Serialized as a map of attribute names to types, or, when some attributes
+/// are optional, as `{"attributes": {...}, "optional": [...]}`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "ObjectTypeRepr", into = "ObjectTypeRepr")]
+pub struct ObjectType {
+    /// Attribute types by name.
+    pub attributes: BTreeMap<String, AttributeType>,
+    /// Names of the attributes that may be omitted.
+    pub optional: BTreeSet<String>,
+}
+
+impl ObjectType {
+    /// Create an object type with no attributes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a required attribute.
+    pub fn with_attribute(mut self, name: impl Into<String>, attr_type: AttributeType) -> Self {
+        self.attributes.insert(name.into(), attr_type);
+        self
+    }
+
+    /// Add an attribute that may be omitted.
+    pub fn with_optional_attribute(
+        mut self,
+        name: impl Into<String>,
+        attr_type: AttributeType,
+    ) -> Self {
+        let name = name.into();
+        self.optional.insert(name.clone());
+        self.attributes.insert(name, attr_type);
+        self
+    }
+
+    /// Whether the attribute must be present.
+    pub fn is_required(&self, name: &str) -> bool {
+        self.attributes.contains_key(name) && !self.optional.contains(name)
+    }
+}
+
+impl From<BTreeMap<String, AttributeType>> for ObjectType {
+    fn from(attributes: BTreeMap<String, AttributeType>) -> Self {
+        Self {
+            attributes,
+            optional: BTreeSet::new(),
+        }
+    }
+}
+
+impl From<ObjectType> for AttributeType {
+    fn from(object: ObjectType) -> Self {
+        Self::Object(object)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ObjectTypeRepr {
+    WithOptional(ObjectTypeFields),
+    Plain(BTreeMap<String, AttributeType>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjectTypeFields {
+    attributes: BTreeMap<String, AttributeType>,
+    optional: BTreeSet<String>,
+}
+
+impl From<ObjectTypeRepr> for ObjectType {
+    fn from(repr: ObjectTypeRepr) -> Self {
+        match repr {
+            ObjectTypeRepr::WithOptional(fields) => Self {
+                attributes: fields.attributes,
+                optional: fields.optional,
+            },
+            ObjectTypeRepr::Plain(attributes) => attributes.into(),
+        }
+    }
+}
+
+impl From<ObjectType> for ObjectTypeRepr {
+    fn from(object: ObjectType) -> Self {
+        if object.optional.is_empty() {
+            Self::Plain(object.attributes)
+        } else {
+            Self::WithOptional(ObjectTypeFields {
+                attributes: object.attributes,
+                optional: object.optional,
+            })
+        }
     }
 }
 
@@ -104,6 +272,13 @@ impl AttributeFlags {
         self.sensitive = true;
         self
     }
+
+    /// Whether the attribute is optional+computed: a null configuration
+    /// value means "provider chooses", and the chosen value is kept until the
+    /// configuration sets one explicitly (see [`crate::plan::normalize_optional_computed`]).
+    pub fn is_optional_computed(&self) -> bool {
+        self.optional && self.computed && !self.required
+    }
 }
 
 /// Describes a single attribute in a schema.
@@ -124,6 +299,28 @@ pub struct Attribute {
     /// Default value for the attribute (JSON-encoded).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default: Option<serde_json::Value>,
+    /// If set, the configured value is a secret reference resolved by the SDK.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub secret_ref: bool,
+    /// If set, the configured value is a command the SDK runs to obtain a
+    /// credential (see [`Attribute::exec_credential`]).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exec_credential: bool,
+    /// Adjustments applied by the schema-driven default plan.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plan_modifiers: Vec<PlanModifier>,
+    /// Normalizers applied to the value, in order (see [`crate::normalize`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub normalizers: Vec<Normalizer>,
+    /// Example values shown in generated documentation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<serde_json::Value>,
+    /// Documentation category used to group attributes (e.g. "Networking").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Arbitrary metadata for documentation tooling and UIs.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, serde_json::Value>,
 }
 
 impl Attribute {
@@ -135,9 +332,26 @@ impl Attribute {
             description: None,
             force_new: false,
             default: None,
+            plan_modifiers: Vec::new(),
+            normalizers: Vec::new(),
+            secret_ref: false,
+            exec_credential: false,
+            examples: Vec::new(),
+            category: None,
+            metadata: BTreeMap::new(),
         }
     }
 
+    /// Create an output attribute: computed, never configured, and always set
+    /// by the provider. Mostly useful for data source results.
+    ///
+    /// With [`ServeOptions::with_state_validation`](crate::ServeOptions::with_state_validation),
+    /// a returned state or data source result that leaves an output null is
+    /// reported as an error.
+    pub fn output(attr_type: AttributeType) -> Self {
+        Self::new(attr_type, AttributeFlags::computed())
+    }
+
     /// Create a required string attribute.
     pub fn required_string() -> Self {
         Self::new(AttributeType::String, AttributeFlags::required())
@@ -153,6 +367,11 @@ impl Attribute {
         Self::new(AttributeType::String, AttributeFlags::computed())
     }
 
+    /// Create an optional+computed string attribute.
+    pub fn optional_computed_string() -> Self {
+        Self::new(AttributeType::String, AttributeFlags::optional_computed())
+    }
+
     /// Create a required int64 attribute.
     pub fn required_int64() -> Self {
         Self::new(AttributeType::Int64, AttributeFlags::required())
@@ -168,6 +387,11 @@ impl Attribute {
         Self::new(AttributeType::Int64, AttributeFlags::computed())
     }
 
+    /// Create an optional+computed int64 attribute.
+    pub fn optional_computed_int64() -> Self {
+        Self::new(AttributeType::Int64, AttributeFlags::optional_computed())
+    }
+
     /// Create a required bool attribute.
     pub fn required_bool() -> Self {
         Self::new(AttributeType::Bool, AttributeFlags::required())
@@ -183,6 +407,11 @@ impl Attribute {
         Self::new(AttributeType::Bool, AttributeFlags::computed())
     }
 
+    /// Create an optional+computed bool attribute.
+    pub fn optional_computed_bool() -> Self {
+        Self::new(AttributeType::Bool, AttributeFlags::optional_computed())
+    }
+
     /// Set the description for this attribute.
     pub fn with_description(mut self, description: impl Into<String>) -> Self {
         self.description = Some(description.into());
@@ -201,11 +430,93 @@ impl Attribute {
         self
     }
 
+    /// Add a plan modifier for this attribute.
+    pub fn with_plan_modifier(mut self, modifier: PlanModifier) -> Self {
+        self.plan_modifiers.push(modifier);
+        self
+    }
+
+    /// Add a normalizer for this attribute. Normalizers run in the order they
+    /// were added.
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizers.push(normalizer);
+        self
+    }
+
+    /// Whether this attribute has the given plan modifier.
+    pub fn has_plan_modifier(&self, modifier: PlanModifier) -> bool {
+        self.plan_modifiers.contains(&modifier)
+    }
+
+    /// Add an example value for documentation.
+    pub fn with_example(mut self, example: serde_json::Value) -> Self {
+        self.examples.push(example);
+        self
+    }
+
+    /// Set the documentation category.
+    pub fn with_category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    /// Add a metadata entry for documentation tooling.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.metadata.insert(key.into(), value);
+        self
+    }
+
     /// Mark this attribute as sensitive.
     pub fn sensitive(mut self) -> Self {
         self.flags.sensitive = true;
         self
     }
+
+    /// Mark this attribute as a secret reference (e.g. `env:API_TOKEN`).
+    ///
+    /// The SDK resolves the reference before `configure` is called; see
+    /// [`crate::secrets`]. Secret references are always sensitive.
+    pub fn secret_ref(mut self) -> Self {
+        self.secret_ref = true;
+        self.flags.sensitive = true;
+        self
+    }
+
+    /// Create an optional exec credential attribute: an object with `command`,
+    /// `args`, `env` and `timeout` (in seconds) describing a command that
+    /// prints a credential.
+    ///
+    /// The SDK runs the command before `configure` is called, which receives
+    /// the command's JSON output in place of the object; see
+    /// [`crate::secrets`] for the output contract.
+    pub fn exec_credential() -> Self {
+        let spec = BTreeMap::from([
+            ("command".to_string(), AttributeType::String),
+            (
+                "args".to_string(),
+                AttributeType::list(AttributeType::String),
+            ),
+            ("env".to_string(), AttributeType::map(AttributeType::String)),
+            ("timeout".to_string(), AttributeType::Float64),
+        ]);
+        let mut attr = Self::new(AttributeType::object(spec), AttributeFlags::optional());
+        attr.exec_credential = true;
+        attr
+    }
+}
+
+/// Adjusts how the schema-driven default plan treats an attribute.
+///
+/// See [`crate::plan::plan_from_schema`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanModifier {
+    /// Plan a computed attribute as unknown whenever the resource is updated,
+    /// instead of carrying over its prior value (e.g. `updated_at`, `etag`).
+    UnknownOnUpdate,
+    /// Keep the prior value and never plan a change for this attribute
+    /// (e.g. values the remote API normalizes or manages itself).
+    IgnoreChanges,
 }
 
 /// The nesting mode for a block.
@@ -240,11 +551,11 @@ pub type BlockType = BlockNestingMode;
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Block {
     /// The attributes within this block.
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
-    pub attributes: HashMap<String, Attribute>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, Attribute>,
     /// Nested blocks within this block.
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
-    pub blocks: HashMap<String, NestedBlock>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub blocks: BTreeMap<String, NestedBlock>,
     /// Human-readable description of the block.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
@@ -254,8 +565,8 @@ impl Block {
     /// Create a new empty block.
     pub fn new() -> Self {
         Self {
-            attributes: HashMap::new(),
-            blocks: HashMap::new(),
+            attributes: BTreeMap::new(),
+            blocks: BTreeMap::new(),
             description: None,
         }
     }
@@ -272,6 +583,29 @@ impl Block {
         self
     }
 
+    /// Add several attributes to this block.
+    pub fn with_attributes<K: Into<String>>(
+        mut self,
+        attributes: impl IntoIterator<Item = (K, Attribute)>,
+    ) -> Self {
+        self.attributes.extend(
+            attributes
+                .into_iter()
+                .map(|(name, attr)| (name.into(), attr)),
+        );
+        self
+    }
+
+    /// Add several nested blocks to this block.
+    pub fn with_blocks<K: Into<String>>(
+        mut self,
+        blocks: impl IntoIterator<Item = (K, NestedBlock)>,
+    ) -> Self {
+        self.blocks
+            .extend(blocks.into_iter().map(|(name, block)| (name.into(), block)));
+        self
+    }
+
     /// Set the description for this block.
     pub fn with_description(mut self, description: impl Into<String>) -> Self {
         self.description = Some(description.into());
@@ -285,6 +619,142 @@ impl Default for Block {
     }
 }
 
+/// A reusable group of attributes and nested blocks.
+///
+/// Fragments define cross-cutting attribute groups (tags, timeouts, identity)
+/// once and merge them into each resource schema that needs them:
+///
+/// ```
+/// use hemmer_provider_sdk::schema::{Attribute, AttributeFlags, AttributeType, Schema, SchemaFragment};
+///
+/// let tags = SchemaFragment::new("tags").with_attribute(
+///     "tags",
+///     Attribute::new(AttributeType::map(AttributeType::String), AttributeFlags::optional()),
+/// );
+///
+/// let server = Schema::v0()
+///     .with_attribute("name", Attribute::required_string())
+///     .with_fragment(&tags);
+/// let bucket = Schema::v0().with_fragment(&tags);
+/// assert!(server.block.attributes.contains_key("tags"));
+///
+/// // A different definition of the same name is a collision
+/// let conflicting = SchemaFragment::new("labels").with_attribute("name", Attribute::optional_string());
+/// assert!(server.try_with_fragment(&conflicting).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SchemaFragment {
+    name: String,
+    /// The attributes and nested blocks the fragment adds.
+    pub block: Block,
+}
+
+impl SchemaFragment {
+    /// Create an empty fragment. The name is used in collision errors.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            block: Block::new(),
+        }
+    }
+
+    /// The fragment's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Add an attribute to the fragment.
+    pub fn with_attribute(mut self, name: impl Into<String>, attr: Attribute) -> Self {
+        self.block = self.block.with_attribute(name, attr);
+        self
+    }
+
+    /// Add a nested block to the fragment.
+    pub fn with_block(mut self, name: impl Into<String>, block: NestedBlock) -> Self {
+        self.block = self.block.with_block(name, block);
+        self
+    }
+
+    /// Add several attributes to the fragment.
+    pub fn with_attributes<K: Into<String>>(
+        mut self,
+        attributes: impl IntoIterator<Item = (K, Attribute)>,
+    ) -> Self {
+        self.block = self.block.with_attributes(attributes);
+        self
+    }
+
+    /// Add several nested blocks to the fragment.
+    pub fn with_blocks<K: Into<String>>(
+        mut self,
+        blocks: impl IntoIterator<Item = (K, NestedBlock)>,
+    ) -> Self {
+        self.block = self.block.with_blocks(blocks);
+        self
+    }
+}
+
+impl Block {
+    /// Merge a fragment's attributes and nested blocks into this block.
+    ///
+    /// Fails if the fragment defines a name the block already has with a
+    /// different definition; identical definitions (e.g. the same fragment
+    /// merged twice) are not a collision.
+    pub fn try_with_fragment(mut self, fragment: &SchemaFragment) -> Result<Self, ProviderError> {
+        let collision = |name: &str| {
+            ProviderError::Sdk(format!(
+                "Schema fragment '{}' redefines '{}'",
+                fragment.name, name
+            ))
+        };
+        for (name, attr) in &fragment.block.attributes {
+            if self.blocks.contains_key(name)
+                || self
+                    .attributes
+                    .get(name)
+                    .is_some_and(|existing| existing != attr)
+            {
+                return Err(collision(name));
+            }
+        }
+        for (name, nested) in &fragment.block.blocks {
+            if self.attributes.contains_key(name)
+                || self
+                    .blocks
+                    .get(name)
+                    .is_some_and(|existing| existing != nested)
+            {
+                return Err(collision(name));
+            }
+        }
+        self.attributes.extend(
+            fragment
+                .block
+                .attributes
+                .iter()
+                .map(|(name, attr)| (name.clone(), attr.clone())),
+        );
+        self.blocks.extend(
+            fragment
+                .block
+                .blocks
+                .iter()
+                .map(|(name, nested)| (name.clone(), nested.clone())),
+        );
+        Ok(self)
+    }
+
+    /// Merge a fragment into this block.
+    ///
+    /// # Panics
+    ///
+    /// Panics on a collision; see [`Block::try_with_fragment`].
+    pub fn with_fragment(self, fragment: &SchemaFragment) -> Self {
+        self.try_with_fragment(fragment)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
 /// A nested block with its nesting mode and constraints.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NestedBlock {
@@ -300,6 +770,14 @@ pub struct NestedBlock {
     /// Maximum number of blocks allowed (0 = unlimited).
     #[serde(default)]
     pub max_items: u32,
+    /// If set, any change inside the block forces resource replacement, as if
+    /// every attribute in it (and in its nested blocks) were `force_new`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub force_new: bool,
+    /// If set, every attribute in the block (and in its nested blocks) is
+    /// treated as sensitive.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sensitive: bool,
 }
 
 impl NestedBlock {
@@ -310,6 +788,8 @@ impl NestedBlock {
             nesting_mode: BlockNestingMode::Single,
             min_items: 0,
             max_items: 1,
+            force_new: false,
+            sensitive: false,
         }
     }
 
@@ -320,6 +800,8 @@ impl NestedBlock {
             nesting_mode: BlockNestingMode::List,
             min_items: 0,
             max_items: 0,
+            force_new: false,
+            sensitive: false,
         }
     }
 
@@ -330,6 +812,8 @@ impl NestedBlock {
             nesting_mode: BlockNestingMode::Set,
             min_items: 0,
             max_items: 0,
+            force_new: false,
+            sensitive: false,
         }
     }
 
@@ -340,6 +824,8 @@ impl NestedBlock {
             nesting_mode: BlockNestingMode::Map,
             min_items: 0,
             max_items: 0,
+            force_new: false,
+            sensitive: false,
         }
     }
 
@@ -354,6 +840,18 @@ impl NestedBlock {
         self.max_items = max;
         self
     }
+
+    /// Mark the block as forcing resource replacement when anything in it changes.
+    pub fn with_force_new(mut self) -> Self {
+        self.force_new = true;
+        self
+    }
+
+    /// Mark every attribute in the block as sensitive.
+    pub fn sensitive(mut self) -> Self {
+        self.sensitive = true;
+        self
+    }
 }
 
 /// Schema for a resource or data source.
@@ -365,6 +863,26 @@ pub struct Schema {
     /// The root block containing all attributes and nested blocks.
     #[serde(flatten)]
     pub block: Block,
+    /// Example configurations shown in generated documentation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<serde_json::Value>,
+    /// Documentation category used to group resources (e.g. "Compute").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Arbitrary metadata for documentation tooling and UIs.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+    /// ID patterns the resource can be imported by (see [`crate::import`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub import_formats: Vec<String>,
+    /// How many operations on the resource may run at once (see
+    /// [`ParallelismLimits`](crate::lock::ParallelismLimits)). `None` is unlimited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_parallelism: Option<u32>,
+    /// Suggested minimum seconds between refreshes of the resource (see
+    /// [`ReadCache`](crate::cache::ReadCache)). `None` refreshes on every read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_interval_secs: Option<u64>,
 }
 
 impl Schema {
@@ -373,6 +891,12 @@ impl Schema {
         Self {
             version,
             block: Block::new(),
+            examples: Vec::new(),
+            category: None,
+            metadata: BTreeMap::new(),
+            import_formats: Vec::new(),
+            max_parallelism: None,
+            refresh_interval_secs: None,
         }
     }
 
@@ -382,16 +906,257 @@ impl Schema {
     }
 
     /// Add an attribute to the schema.
+    ///
+    /// The attribute is not checked; contradictory definitions are found by
+    /// [`lint_schema`](crate::lint::lint_schema) at startup, or rejected here
+    /// in [`checked`](Self::checked) mode.
     pub fn with_attribute(mut self, name: impl Into<String>, attr: Attribute) -> Self {
         self.block.attributes.insert(name.into(), attr);
         self
     }
 
+    /// Add an [output](Attribute::output) attribute to the schema.
+    ///
+    /// ```
+    /// use hemmer_provider_sdk::schema::{Attribute, AttributeType, Schema};
+    ///
+    /// let image = Schema::v0()
+    ///     .with_attribute("name", Attribute::required_string())
+    ///     .with_output("arn", AttributeType::String)
+    ///     .with_output("size_gb", AttributeType::Int64);
+    /// assert!(image.block.attributes["arn"].flags.computed);
+    /// ```
+    pub fn with_output(self, name: impl Into<String>, attr_type: AttributeType) -> Self {
+        self.with_attribute(name, Attribute::output(attr_type))
+    }
+
     /// Add a nested block to the schema.
     pub fn with_block(mut self, name: impl Into<String>, block: NestedBlock) -> Self {
         self.block.blocks.insert(name.into(), block);
         self
     }
+
+    /// Add several attributes to the schema.
+    ///
+    /// ```
+    /// use hemmer_provider_sdk::schema::{Attribute, Schema};
+    ///
+    /// let schema = Schema::v0().with_attributes([
+    ///     ("name", Attribute::required_string()),
+    ///     ("id", Attribute::computed_string()),
+    /// ]);
+    /// assert_eq!(schema.block.attributes.len(), 2);
+    /// ```
+    pub fn with_attributes<K: Into<String>>(
+        mut self,
+        attributes: impl IntoIterator<Item = (K, Attribute)>,
+    ) -> Self {
+        self.block = self.block.with_attributes(attributes);
+        self
+    }
+
+    /// Add several nested blocks to the schema.
+    pub fn with_blocks<K: Into<String>>(
+        mut self,
+        blocks: impl IntoIterator<Item = (K, NestedBlock)>,
+    ) -> Self {
+        self.block = self.block.with_blocks(blocks);
+        self
+    }
+
+    /// Merge a fragment into the schema (see [`SchemaFragment`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics on a collision; see [`Block::try_with_fragment`].
+    pub fn with_fragment(mut self, fragment: &SchemaFragment) -> Self {
+        self.block = self.block.with_fragment(fragment);
+        self
+    }
+
+    /// Merge a fragment into the schema, failing on a collision.
+    pub fn try_with_fragment(mut self, fragment: &SchemaFragment) -> Result<Self, ProviderError> {
+        self.block = self.block.try_with_fragment(fragment)?;
+        Ok(self)
+    }
+
+    /// Add an example configuration for documentation.
+    pub fn with_example(mut self, example: serde_json::Value) -> Self {
+        self.examples.push(example);
+        self
+    }
+
+    /// Set the documentation category.
+    pub fn with_category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    /// Add a metadata entry for documentation tooling.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.metadata.insert(key.into(), value);
+        self
+    }
+
+    /// Declare an ID pattern the resource can be imported by, such as
+    /// `{zone_id}/{record_id}` (see [`crate::import`]).
+    pub fn with_import_format(mut self, pattern: impl Into<String>) -> Self {
+        self.import_formats.push(pattern.into());
+        self
+    }
+
+    /// Limit how many operations on the resource run at once, for APIs that
+    /// throttle per resource family (e.g. 5 for DNS records).
+    pub fn with_max_parallelism(mut self, max: u32) -> Self {
+        self.max_parallelism = Some(max);
+        self
+    }
+
+    /// Mark the resource as expensive to refresh: reads of the same state
+    /// within `interval` are served from the SDK's read cache, and core is
+    /// told it can skip refreshing the resource that often. Rounded down to
+    /// whole seconds.
+    pub fn with_refresh_interval(mut self, interval: std::time::Duration) -> Self {
+        self.refresh_interval_secs = Some(interval.as_secs());
+        self
+    }
+
+    /// Split an import ID using the first declared import format it matches.
+    pub fn parse_import_id(&self, id: &str) -> Result<ImportId, ProviderError> {
+        parse_import_id(&self.import_formats, id)
+    }
+
+    /// Switch to a builder that rejects contradictory attribute and block
+    /// definitions as they are added, instead of leaving them for the linter.
+    ///
+    /// ```
+    /// use hemmer_provider_sdk::schema::{Attribute, Schema};
+    /// use serde_json::json;
+    ///
+    /// let schema = Schema::v0()
+    ///     .checked()
+    ///     .with_attribute("name", Attribute::required_string())?
+    ///     .with_attribute("id", Attribute::computed_string())?
+    ///     .build();
+    /// assert_eq!(schema.block.attributes.len(), 2);
+    ///
+    /// let err = Schema::v0()
+    ///     .checked()
+    ///     .with_attribute("size", Attribute::required_int64().with_default(json!(10)))
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "SDK error: Invalid attribute 'size': Default is set on a required or computed-only attribute"
+    /// );
+    /// # Ok::<(), hemmer_provider_sdk::ProviderError>(())
+    /// ```
+    pub fn checked(self) -> CheckedSchema {
+        CheckedSchema { schema: self }
+    }
+}
+
+/// A [`Schema`] builder that checks each definition as it is added; see
+/// [`Schema::checked`].
+///
+/// Adding an attribute or block fails if it has a definition error the
+/// [linter](crate::lint::lint_schema) would report (e.g. required and
+/// computed, a default on a required attribute, `min_items` above
+/// `max_items`), or if its name is already used or differs from a used name
+/// only in case.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckedSchema {
+    schema: Schema,
+}
+
+impl CheckedSchema {
+    /// Add an attribute, failing if its definition is contradictory.
+    pub fn with_attribute(
+        mut self,
+        name: impl Into<String>,
+        attr: Attribute,
+    ) -> Result<Self, ProviderError> {
+        let name = name.into();
+        self.check_name(&name)?;
+        let mut diagnostics = Vec::new();
+        crate::lint::lint_attribute(&attr, &name, &mut diagnostics);
+        check_diagnostics("attribute", &name, diagnostics)?;
+        self.schema.block.attributes.insert(name, attr);
+        Ok(self)
+    }
+
+    /// Add a nested block, failing if it or anything in it is contradictory.
+    pub fn with_block(
+        mut self,
+        name: impl Into<String>,
+        block: NestedBlock,
+    ) -> Result<Self, ProviderError> {
+        let name = name.into();
+        self.check_name(&name)?;
+        let mut diagnostics = Vec::new();
+        crate::lint::lint_nested_block(&block, &name, &mut diagnostics);
+        check_diagnostics("block", &name, diagnostics)?;
+        self.schema.block.blocks.insert(name, block);
+        Ok(self)
+    }
+
+    /// The checked schema.
+    pub fn build(self) -> Schema {
+        self.schema
+    }
+
+    fn check_name(&self, name: &str) -> Result<(), ProviderError> {
+        let block = &self.schema.block;
+        match block
+            .attributes
+            .keys()
+            .chain(block.blocks.keys())
+            .find(|existing| existing.eq_ignore_ascii_case(name))
+        {
+            Some(existing) if existing == name => {
+                Err(ProviderError::Sdk(format!("'{}' is already defined", name)))
+            },
+            Some(existing) => Err(ProviderError::Sdk(format!(
+                "'{}' differs from '{}' only in case",
+                name, existing
+            ))),
+            None => match crate::lint::name_error(name) {
+                Some(error) => Err(ProviderError::Sdk(error)),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+impl From<CheckedSchema> for Schema {
+    fn from(checked: CheckedSchema) -> Self {
+        checked.build()
+    }
+}
+
+/// The first error among a definition's lint diagnostics, as a builder error.
+fn check_diagnostics(
+    kind: &str,
+    name: &str,
+    diagnostics: Vec<Diagnostic>,
+) -> Result<(), ProviderError> {
+    match diagnostics
+        .into_iter()
+        .find(|d| d.severity == DiagnosticSeverity::Error)
+    {
+        Some(d) => {
+            let within = d
+                .attribute
+                .as_deref()
+                .filter(|path| *path != name)
+                .map(|path| format!(" (at '{}')", path))
+                .unwrap_or_default();
+            Err(ProviderError::Sdk(format!(
+                "Invalid {} '{}': {}{}",
+                kind, name, d.summary, within
+            )))
+        },
+        None => Ok(()),
+    }
 }
 
 /// Schema for the provider configuration.
@@ -402,10 +1167,13 @@ pub struct ProviderSchema {
     pub provider: Schema,
     /// Schemas for each resource type.
     #[serde(default)]
-    pub resources: HashMap<String, Schema>,
+    pub resources: BTreeMap<String, Schema>,
     /// Schemas for each data source type.
     #[serde(default)]
-    pub data_sources: HashMap<String, Schema>,
+    pub data_sources: BTreeMap<String, Schema>,
+    /// Signatures of provider functions, by function name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub functions: BTreeMap<String, FunctionSchema>,
 }
 
 impl ProviderSchema {
@@ -431,6 +1199,270 @@ impl ProviderSchema {
         self.data_sources.insert(name.into(), schema);
         self
     }
+
+    /// Add a provider function signature.
+    pub fn with_function(mut self, name: impl Into<String>, function: FunctionSchema) -> Self {
+        self.functions.insert(name.into(), function);
+        self
+    }
+
+    /// A machine-readable summary of the schema (see [`crate::manifest`]).
+    pub fn to_manifest(&self) -> crate::manifest::ProviderManifest {
+        crate::manifest::ProviderManifest::new(self)
+    }
+
+    /// The provider prefix of the resource and data source type names, e.g.
+    /// `example` for `example_bucket` and `example_object`.
+    ///
+    /// `None` if there are no types, or if any type isn't a valid
+    /// [`ResourceTypeName`] or has a different prefix.
+    pub fn provider_prefix(&self) -> Option<&str> {
+        let mut prefix = None;
+        for name in self.resources.keys().chain(self.data_sources.keys()) {
+            let provider = ResourceTypeName::split(name)?.0;
+            match prefix {
+                None => prefix = Some(provider),
+                Some(prefix) if prefix != provider => return None,
+                Some(_) => {},
+            }
+        }
+        prefix
+    }
+
+    /// Build a schema for provider `provider` whose resource and data source
+    /// type names are checked as they are added.
+    ///
+    /// Types can be added by their full name or without the provider prefix,
+    /// which is then added:
+    ///
+    /// ```
+    /// use hemmer_provider_sdk::schema::{Attribute, ProviderSchema, Schema};
+    ///
+    /// let bucket = Schema::v0().with_attribute("name", Attribute::required_string());
+    /// let schema = ProviderSchema::checked("example")?
+    ///     .with_resource("bucket", bucket.clone())?
+    ///     .with_data_source("example_bucket", bucket.clone())?
+    ///     .build();
+    /// assert!(schema.resources.contains_key("example_bucket"));
+    /// assert_eq!(schema.provider_prefix(), Some("example"));
+    ///
+    /// let err = ProviderSchema::checked("example")?
+    ///     .with_resource("Bucket", bucket)
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "SDK error: Invalid resource type 'example_Bucket': not lowercase snake_case"
+    /// );
+    /// # Ok::<(), hemmer_provider_sdk::ProviderError>(())
+    /// ```
+    pub fn checked(provider: impl Into<String>) -> Result<CheckedProviderSchema, ProviderError> {
+        let provider = provider.into();
+        if let Err(reason) = check_type_segment(&provider, true) {
+            return Err(ProviderError::Sdk(format!(
+                "Invalid provider name '{}': {}",
+                provider, reason
+            )));
+        }
+        Ok(CheckedProviderSchema {
+            provider,
+            schema: Self::new(),
+        })
+    }
+}
+
+/// A [`ProviderSchema`] builder that qualifies and checks type names as they
+/// are added; see [`ProviderSchema::checked`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckedProviderSchema {
+    provider: String,
+    schema: ProviderSchema,
+}
+
+impl CheckedProviderSchema {
+    /// Set the provider configuration schema.
+    pub fn with_provider_config(mut self, schema: Schema) -> Self {
+        self.schema.provider = schema;
+        self
+    }
+
+    /// Add a resource schema, failing if its type name is invalid or already
+    /// used.
+    pub fn with_resource(mut self, name: &str, schema: Schema) -> Result<Self, ProviderError> {
+        let name = self.type_name("resource", name)?;
+        if self.schema.resources.contains_key(name.as_str()) {
+            return Err(ProviderError::Sdk(format!(
+                "Resource type '{}' is already defined",
+                name
+            )));
+        }
+        self.schema.resources.insert(name.into(), schema);
+        Ok(self)
+    }
+
+    /// Add a data source schema, failing if its type name is invalid or
+    /// already used.
+    pub fn with_data_source(mut self, name: &str, schema: Schema) -> Result<Self, ProviderError> {
+        let name = self.type_name("data source", name)?;
+        if self.schema.data_sources.contains_key(name.as_str()) {
+            return Err(ProviderError::Sdk(format!(
+                "Data source type '{}' is already defined",
+                name
+            )));
+        }
+        self.schema.data_sources.insert(name.into(), schema);
+        Ok(self)
+    }
+
+    /// Add a provider function signature.
+    pub fn with_function(mut self, name: impl Into<String>, function: FunctionSchema) -> Self {
+        self.schema.functions.insert(name.into(), function);
+        self
+    }
+
+    /// The checked schema.
+    pub fn build(self) -> ProviderSchema {
+        self.schema
+    }
+
+    /// The full type name for `name`, adding the provider prefix if missing.
+    fn type_name(&self, kind: &str, name: &str) -> Result<ResourceTypeName, ProviderError> {
+        let full = if name
+            .strip_prefix(self.provider.as_str())
+            .is_some_and(|rest| rest.starts_with('_'))
+        {
+            name.to_string()
+        } else {
+            format!("{}_{}", self.provider, name)
+        };
+        match type_name_error(&full) {
+            Some(reason) => Err(ProviderError::Sdk(format!(
+                "Invalid {} type '{}': {}",
+                kind, full, reason
+            ))),
+            None => Ok(ResourceTypeName(full)),
+        }
+    }
+}
+
+impl From<CheckedProviderSchema> for ProviderSchema {
+    fn from(checked: CheckedProviderSchema) -> Self {
+        checked.build()
+    }
+}
+
+/// The signature of a provider function.
+///
+/// Reported by `GetSchema` so core can type-check calls at plan time.
+///
+/// # Example
+///
+/// ```
+/// use hemmer_provider_sdk::schema::{AttributeType, FunctionParameter, FunctionSchema};
+///
+/// let subnets = FunctionSchema::new(AttributeType::list(AttributeType::String))
+///     .with_description("Divide a CIDR block into equal subnets")
+///     .with_parameter(
+///         FunctionParameter::new("cidr", AttributeType::String)
+///             .with_description("An IPv4 or IPv6 CIDR block"),
+///     )
+///     .with_parameter(FunctionParameter::new("count", AttributeType::Int64))
+///     .deterministic();
+/// assert_eq!(subnets.parameters[0].name, "cidr");
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionSchema {
+    /// Positional parameters, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<FunctionParameter>,
+    /// If set, the function accepts any number of trailing arguments of this type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variadic_parameter: Option<FunctionParameter>,
+    /// The type of the result.
+    pub return_type: AttributeType,
+    /// Human-readable description of the function.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// If set, the same arguments always produce the same result, so core may
+    /// evaluate calls while planning.
+    #[serde(default)]
+    pub deterministic: bool,
+}
+
+impl FunctionSchema {
+    /// Create a signature with no parameters returning `return_type`.
+    pub fn new(return_type: AttributeType) -> Self {
+        Self {
+            parameters: Vec::new(),
+            variadic_parameter: None,
+            return_type,
+            description: None,
+            deterministic: false,
+        }
+    }
+
+    /// Add a positional parameter.
+    pub fn with_parameter(mut self, parameter: FunctionParameter) -> Self {
+        self.parameters.push(parameter);
+        self
+    }
+
+    /// Accept any number of trailing arguments described by `parameter`.
+    pub fn with_variadic_parameter(mut self, parameter: FunctionParameter) -> Self {
+        self.variadic_parameter = Some(parameter);
+        self
+    }
+
+    /// Set the description.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Mark the function as deterministic.
+    pub fn deterministic(mut self) -> Self {
+        self.deterministic = true;
+        self
+    }
+}
+
+/// A parameter of a [`FunctionSchema`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionParameter {
+    /// Name of the parameter, used in documentation and error messages.
+    pub name: String,
+    /// The type of the argument.
+    #[serde(rename = "type")]
+    pub param_type: AttributeType,
+    /// Human-readable description of the parameter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// If set, the argument may be null.
+    #[serde(default)]
+    pub allow_null: bool,
+}
+
+impl FunctionParameter {
+    /// Create a parameter of the given type.
+    pub fn new(name: impl Into<String>, param_type: AttributeType) -> Self {
+        Self {
+            name: name.into(),
+            param_type,
+            description: None,
+            allow_null: false,
+        }
+    }
+
+    /// Set the description.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Allow null arguments.
+    pub fn allow_null(mut self) -> Self {
+        self.allow_null = true;
+        self
+    }
 }
 
 impl Default for Schema {
@@ -439,8 +1471,424 @@ impl Default for Schema {
     }
 }
 
+/// Produce a minimal valid JSON configuration for a schema.
+///
+/// Only required attributes are included, each with a placeholder value for its
+/// type. Nested blocks are instantiated only when they require at least one item.
+/// The result passes [`crate::validation::validate`] against the same schema, which
+/// makes it useful as a documentation example and as seed input for tests.
+///
+/// # Example
+///
+/// ```
+/// use hemmer_provider_sdk::schema::{example_value, Attribute, Schema};
+/// use serde_json::json;
+///
+/// let schema = Schema::v0()
+///     .with_attribute("name", Attribute::required_string())
+///     .with_attribute("size", Attribute::optional_string())
+///     .with_attribute("id", Attribute::computed_string());
+///
+/// assert_eq!(example_value(&schema), json!({"name": "example"}));
+/// ```
+pub fn example_value(schema: &Schema) -> serde_json::Value {
+    example_block(&schema.block)
+}
+
+fn example_block(block: &Block) -> serde_json::Value {
+    let mut obj = serde_json::Map::new();
+
+    for (name, attr) in &block.attributes {
+        if attr.flags.required {
+            obj.insert(name.clone(), example_for_type(&attr.attr_type));
+        }
+    }
+
+    for (name, nested) in &block.blocks {
+        if nested.min_items == 0 {
+            continue;
+        }
+        let value = match nested.nesting_mode {
+            BlockNestingMode::Single => example_block(&nested.block),
+            BlockNestingMode::List => serde_json::Value::Array(
+                (0..nested.min_items)
+                    .map(|_| example_block(&nested.block))
+                    .collect(),
+            ),
+            // Identical elements would collapse into one in a set, so each gets
+            // its own values.
+            BlockNestingMode::Set => serde_json::Value::Array(
+                (0..nested.min_items)
+                    .map(|i| {
+                        let mut element = example_block(&nested.block);
+                        index_example(&mut element, i);
+                        element
+                    })
+                    .collect(),
+            ),
+            BlockNestingMode::Map => serde_json::Value::Object(
+                (0..nested.min_items)
+                    .map(|i| (format!("example_{}", i), example_block(&nested.block)))
+                    .collect(),
+            ),
+        };
+        obj.insert(name.clone(), value);
+    }
+
+    serde_json::Value::Object(obj)
+}
+
+/// Make `value` distinct for element `index` of a set, by suffixing its strings
+/// with the index and setting its integers to it.
+fn index_example(value: &mut serde_json::Value, index: u32) {
+    match value {
+        serde_json::Value::String(s) => s.push_str(&format!("-{}", index)),
+        serde_json::Value::Number(n) if n.is_i64() => *n = index.into(),
+        serde_json::Value::Array(items) => {
+            items.iter_mut().for_each(|item| index_example(item, index))
+        },
+        serde_json::Value::Object(map) => {
+            map.values_mut().for_each(|item| index_example(item, index))
+        },
+        _ => {},
+    }
+}
+
+pub(crate) fn example_for_type(attr_type: &AttributeType) -> serde_json::Value {
+    match attr_type {
+        AttributeType::String => serde_json::Value::String("example".to_string()),
+        AttributeType::Int64 => serde_json::json!(0),
+        AttributeType::Float64 => serde_json::json!(0.0),
+        AttributeType::Bool => serde_json::Value::Bool(false),
+        AttributeType::List(_) | AttributeType::Set(_) => serde_json::Value::Array(vec![]),
+        AttributeType::Map(_) | AttributeType::Dynamic => {
+            serde_json::Value::Object(serde_json::Map::new())
+        },
+        AttributeType::Object(object) => serde_json::Value::Object(
+            object
+                .attributes
+                .iter()
+                .filter(|(name, _)| object.is_required(name))
+                .map(|(name, t)| (name.clone(), example_for_type(t)))
+                .collect(),
+        ),
+    }
+}
+
+/// A path to an attribute instance, as a list of segments.
+///
+/// List and set elements are addressed by index and map entries by key, so
+/// `network.0.subnet_id` is the `subnet_id` attribute of the first `network`
+/// block. [`parse`](Self::parse) accepts both this dot form and the bracket form
+/// used by [`AttributeChange::path`](crate::AttributeChange::path)
+/// (`network[0].subnet_id`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AttributePath(Vec<String>);
+
+impl AttributePath {
+    /// Create a path from its segments.
+    pub fn new(segments: Vec<String>) -> Self {
+        Self(segments)
+    }
+
+    /// Parse a dot- or bracket-separated path.
+    pub fn parse(path: &str) -> Self {
+        Self(
+            path.split(['.', '[', ']'])
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect(),
+        )
+    }
+
+    /// The segments of this path.
+    pub fn segments(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl From<&str> for AttributePath {
+    fn from(path: &str) -> Self {
+        Self::parse(path)
+    }
+}
+
+impl From<String> for AttributePath {
+    fn from(path: String) -> Self {
+        Self::parse(&path)
+    }
+}
+
+impl std::fmt::Display for AttributePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0.join("."))
+    }
+}
+
+/// A resource or data source type name: the provider name, an underscore and
+/// the type's own name, e.g. `example_bucket`.
+///
+/// Core routes every request to a provider by the prefix of its type name, so
+/// a schema key without it (`bucket`) is never requested. Both parts are
+/// lowercase snake_case; the provider name has no underscores, so the prefix
+/// ends at the first one.
+///
+/// ```
+/// use hemmer_provider_sdk::schema::ResourceTypeName;
+///
+/// let name: ResourceTypeName = "example_storage_bucket".parse()?;
+/// assert_eq!(name.provider(), "example");
+/// assert_eq!(name.name(), "storage_bucket");
+/// assert!(ResourceTypeName::parse("bucket").is_err());
+/// # Ok::<(), hemmer_provider_sdk::ProviderError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResourceTypeName(String);
+
+impl ResourceTypeName {
+    /// Parse a full type name.
+    pub fn parse(name: &str) -> Result<Self, ProviderError> {
+        Self::split(name)
+            .map(|_| Self(name.to_string()))
+            .ok_or_else(|| {
+                let reason = type_name_error(name).unwrap_or_default();
+                ProviderError::Validation(format!(
+                    "Invalid resource type name '{}': {}",
+                    name, reason
+                ))
+            })
+    }
+
+    /// The type name `<provider>_<name>`.
+    pub fn new(provider: &str, name: &str) -> Result<Self, ProviderError> {
+        Self::parse(&format!("{}_{}", provider, name))
+    }
+
+    /// The provider prefix, without the underscore.
+    pub fn provider(&self) -> &str {
+        &self.0[..self.0.find('_').unwrap_or_default()]
+    }
+
+    /// The name within the provider.
+    pub fn name(&self) -> &str {
+        &self.0[self.provider().len() + 1..]
+    }
+
+    /// The full type name.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The provider and name of a valid type name.
+    pub(crate) fn split(name: &str) -> Option<(&str, &str)> {
+        match type_name_error(name) {
+            None => name.split_once('_'),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Why `name` isn't a valid [`ResourceTypeName`].
+fn type_name_error(name: &str) -> Option<String> {
+    let Some((provider, rest)) = name.split_once('_') else {
+        return Some("expected '<provider>_<name>'".to_string());
+    };
+    check_type_segment(provider, true)
+        .and_then(|()| check_type_segment(rest, false))
+        .err()
+}
+
+/// Check the provider or type part of a type name: lowercase snake_case, and
+/// for the provider a single word starting with a letter.
+fn check_type_segment(segment: &str, provider: bool) -> Result<(), String> {
+    if segment.is_empty() {
+        Err("expected '<provider>_<name>'".to_string())
+    } else if provider && segment.contains('_') {
+        Err(format!("provider name '{}' contains '_'", segment))
+    } else if provider && !segment.starts_with(|c: char| c.is_ascii_lowercase()) {
+        Err(format!(
+            "provider name '{}' doesn't start with a lowercase letter",
+            segment
+        ))
+    } else if !segment
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        Err("not lowercase snake_case".to_string())
+    } else if segment.split('_').any(str::is_empty) {
+        Err("empty word between underscores".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for ResourceTypeName {
+    type Err = ProviderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl AsRef<str> for ResourceTypeName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<ResourceTypeName> for String {
+    fn from(name: ResourceTypeName) -> Self {
+        name.0
+    }
+}
+
+impl std::fmt::Display for ResourceTypeName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The error for a request for `requested`, which isn't one of `known`,
+/// explaining a missing or wrong provider prefix.
+pub(crate) fn unknown_type<'a>(
+    requested: &str,
+    known: impl IntoIterator<Item = &'a String>,
+) -> ProviderError {
+    let known: Vec<&str> = known.into_iter().map(String::as_str).collect();
+    let prefixes: BTreeSet<&str> = known
+        .iter()
+        .filter_map(|k| ResourceTypeName::split(k))
+        .map(|(provider, _)| provider)
+        .collect();
+    let hint = match ResourceTypeName::split(requested) {
+        Some((_, name)) if known.contains(&name) => Some(format!(
+            "the schema defines '{}', without the provider prefix",
+            name
+        )),
+        Some((provider, _)) if !prefixes.is_empty() && !prefixes.contains(provider) => {
+            let prefixes: Vec<String> = prefixes.iter().map(|p| format!("'{}_'", p)).collect();
+            Some(format!(
+                "this provider's types start with {}",
+                prefixes.join(" or ")
+            ))
+        },
+        _ => None,
+    };
+    match hint {
+        Some(hint) => ProviderError::UnknownResource(format!("{} ({})", requested, hint)),
+        None => ProviderError::UnknownResource(requested.to_string()),
+    }
+}
+
+/// Find the `force_new` attributes touched by a change set.
+///
+/// Each change path is resolved against the schema, descending through nested
+/// blocks of any nesting mode. A change anywhere within a `force_new` attribute
+/// (e.g. one element of a list) counts as a change to that attribute. The
+/// result holds the path of each affected attribute instance once, sorted; it is
+/// empty when the changes can be applied in place.
+///
+/// # Example
+///
+/// ```
+/// use hemmer_provider_sdk::schema::{requires_replacement, Attribute, Block, NestedBlock, Schema};
+/// use hemmer_provider_sdk::AttributeChange;
+/// use serde_json::json;
+///
+/// let schema = Schema::v0()
+///     .with_attribute("name", Attribute::required_string())
+///     .with_block(
+///         "network",
+///         NestedBlock::list(
+///             Block::new().with_attribute("subnet_id", Attribute::required_string().with_force_new()),
+///         ),
+///     );
+///
+/// let changes = vec![
+///     AttributeChange::modified("name", json!("a"), json!("b")),
+///     AttributeChange::modified("network[0].subnet_id", json!("s-1"), json!("s-2")),
+/// ];
+/// let paths = requires_replacement(&schema, &changes);
+/// assert_eq!(paths.len(), 1);
+/// assert_eq!(paths[0].to_string(), "network.0.subnet_id");
+/// ```
+pub fn requires_replacement(
+    schema: &Schema,
+    changes: &[crate::types::AttributeChange],
+) -> Vec<AttributePath> {
+    let mut paths: Vec<AttributePath> = changes
+        .iter()
+        .filter_map(|change| {
+            let path = AttributePath::parse(&change.path);
+            let len = force_new_prefix(&schema.block, path.segments())?;
+            Some(AttributePath(path.0[..len].to_vec()))
+        })
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// The length of the prefix of `segments` naming a `force_new` attribute, if any.
+///
+/// A path ending at a nested block, or at one of its elements, adds or
+/// removes the block whole, which replaces the resource if the block
+/// contains any `force_new` attribute or block.
+pub(crate) fn force_new_prefix(block: &Block, segments: &[String]) -> Option<usize> {
+    let name = segments.first()?;
+    if let Some(attr) = block.attributes.get(name) {
+        return attr.force_new.then_some(1);
+    }
+    let nested = block.blocks.get(name)?;
+    if nested.force_new {
+        return Some(1);
+    }
+    let skip = match nested.nesting_mode {
+        BlockNestingMode::Single => 1,
+        BlockNestingMode::List | BlockNestingMode::Set | BlockNestingMode::Map => 2,
+    };
+    match segments.get(skip..) {
+        Some(rest) if !rest.is_empty() => {
+            force_new_prefix(&nested.block, rest).map(|len| len + skip)
+        },
+        _ => contains_force_new(&nested.block).then_some(segments.len()),
+    }
+}
+
+/// Whether `block` has a `force_new` attribute or block at any depth.
+fn contains_force_new(block: &Block) -> bool {
+    block.attributes.values().any(|attr| attr.force_new)
+        || block
+            .blocks
+            .values()
+            .any(|nested| nested.force_new || contains_force_new(&nested.block))
+}
+
+/// Whether `segments` name a value within a sensitive attribute or block.
+pub(crate) fn is_sensitive_path(block: &Block, segments: &[String]) -> bool {
+    let Some(name) = segments.first() else {
+        return false;
+    };
+    if let Some(attr) = block.attributes.get(name) {
+        return attr.flags.sensitive;
+    }
+    let Some(nested) = block.blocks.get(name) else {
+        return false;
+    };
+    if nested.sensitive {
+        return true;
+    }
+    let skip = match nested.nesting_mode {
+        BlockNestingMode::Single => 1,
+        BlockNestingMode::List | BlockNestingMode::Set | BlockNestingMode::Map => 2,
+    };
+    segments
+        .get(skip..)
+        .is_some_and(|rest| is_sensitive_path(&nested.block, rest))
+}
+
 /// Diagnostic severity level.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum DiagnosticSeverity {
     /// An error that prevents the operation from completing.
@@ -450,7 +1898,7 @@ pub enum DiagnosticSeverity {
 }
 
 /// A diagnostic message from the provider.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Diagnostic {
     /// The severity of the diagnostic.
     pub severity: DiagnosticSeverity,
@@ -462,6 +1910,12 @@ pub struct Diagnostic {
     /// The attribute path where the issue occurred.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub attribute: Option<String>,
+    /// A suggested fix that core can offer to the user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+    /// Identifies the message for translation (see [`crate::i18n`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<Message>,
 }
 
 impl Diagnostic {
@@ -472,6 +1926,8 @@ impl Diagnostic {
             summary: summary.into(),
             detail: None,
             attribute: None,
+            suggestion: None,
+            message: None,
         }
     }
 
@@ -482,9 +1938,103 @@ impl Diagnostic {
             summary: summary.into(),
             detail: None,
             attribute: None,
+            suggestion: None,
+            message: None,
         }
     }
 
+    /// An error for an attribute whose value is not allowed.
+    ///
+    /// ```
+    /// use hemmer_provider_sdk::schema::Diagnostic;
+    ///
+    /// let d = Diagnostic::invalid_value("disk.0.size", 3, "a multiple of 8")
+    ///     .with_suggestion("Use 8");
+    /// assert_eq!(d.summary, "Invalid value for 'disk.0.size'");
+    /// assert_eq!(d.detail.as_deref(), Some("Expected a multiple of 8, got 3"));
+    /// assert_eq!(d.path().unwrap().segments(), ["disk", "0", "size"]);
+    /// ```
+    pub fn invalid_value(
+        path: impl Into<AttributePath>,
+        got: impl std::fmt::Display,
+        expected: impl std::fmt::Display,
+    ) -> Self {
+        let path = path.into();
+        sdk_diagnostic(
+            DiagnosticSeverity::Error,
+            Message::new("invalid_value")
+                .with_arg("path", &path)
+                .with_arg("expected", expected)
+                .with_arg("got", got),
+        )
+        .with_path(&path)
+    }
+
+    /// An error for an attribute value of the wrong JSON type.
+    pub fn invalid_type(
+        path: impl Into<AttributePath>,
+        expected: &str,
+        got: &serde_json::Value,
+    ) -> Self {
+        let path = path.into();
+        sdk_diagnostic(
+            DiagnosticSeverity::Error,
+            Message::new("invalid_type")
+                .with_arg("path", &path)
+                .with_arg("expected", expected)
+                .with_arg("got", crate::validation::value_type_name(got)),
+        )
+        .with_path(&path)
+    }
+
+    /// An error for an int64 attribute whose value arrived as a float too
+    /// large to hold it exactly (see [`crate::number`]).
+    pub fn precision_loss(path: impl Into<AttributePath>, value: &serde_json::Value) -> Self {
+        let path = path.into();
+        sdk_diagnostic(
+            DiagnosticSeverity::Error,
+            Message::new("precision_loss")
+                .with_arg("path", &path)
+                .with_arg("value", value),
+        )
+        .with_path(&path)
+    }
+
+    /// An error for a required attribute that is not set.
+    pub fn missing_attribute(path: impl Into<AttributePath>) -> Self {
+        let path = path.into();
+        sdk_diagnostic(
+            DiagnosticSeverity::Error,
+            Message::new("missing_attribute").with_arg("path", &path),
+        )
+        .with_path(&path)
+    }
+
+    /// An error for an attribute the schema does not define.
+    pub fn unsupported_attribute(path: impl Into<AttributePath>) -> Self {
+        let path = path.into();
+        sdk_diagnostic(
+            DiagnosticSeverity::Error,
+            Message::new("unsupported_attribute").with_arg("path", &path),
+        )
+        .with_path(&path)
+    }
+
+    /// An error for two attributes that cannot be set together.
+    pub fn conflicting_attributes(
+        path: impl Into<AttributePath>,
+        other: impl Into<AttributePath>,
+    ) -> Self {
+        let (path, other) = (path.into(), other.into());
+        sdk_diagnostic(
+            DiagnosticSeverity::Error,
+            Message::new("conflicting_attributes")
+                .with_arg("path", &path)
+                .with_arg("other", other),
+        )
+        .with_path(&path)
+    }
+
     /// Add detail to this diagnostic.
     pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
         self.detail = Some(detail.into());
@@ -496,12 +2046,110 @@ impl Diagnostic {
         self.attribute = Some(attribute.into());
         self
     }
+
+    /// Set the attribute path for this diagnostic from its segments.
+    pub fn with_path(self, path: &AttributePath) -> Self {
+        self.with_attribute(path.to_string())
+    }
+
+    /// The attribute path of this diagnostic, if any.
+    pub fn path(&self) -> Option<AttributePath> {
+        self.attribute.as_deref().map(AttributePath::parse)
+    }
+
+    /// Suggest a fix (e.g. the closest valid value) that core can offer.
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    /// Key the diagnostic for translation by a [`Catalog`](crate::i18n::Catalog).
+    pub fn with_message(mut self, message: Message) -> Self {
+        self.message = Some(message);
+        self
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    /// Formats as `[Error] summary: detail (at attribute) Suggestion: suggestion`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{:?}] {}", self.severity, self.summary)?;
+        if let Some(detail) = &self.detail {
+            write!(f, ": {}", detail)?;
+        }
+        if let Some(attr) = &self.attribute {
+            write!(f, " (at {})", attr)?;
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " Suggestion: {}", suggestion)?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_checked_schema() {
+        let disk =
+            NestedBlock::list(Block::new().with_attribute("size", Attribute::required_int64()));
+        let schema = Schema::v0()
+            .checked()
+            .with_attribute("name", Attribute::required_string())
+            .unwrap()
+            .with_block("disk", disk.clone())
+            .unwrap()
+            .build();
+        assert_eq!(schema.block.blocks["disk"], disk);
+
+        let checked = Schema::v0()
+            .checked()
+            .with_attribute("name", Attribute::required_string())
+            .unwrap();
+        let err = |result: Result<CheckedSchema, ProviderError>| result.unwrap_err().to_string();
+        assert_eq!(
+            err(checked
+                .clone()
+                .with_attribute("Name", Attribute::optional_string())),
+            "SDK error: 'Name' differs from 'name' only in case"
+        );
+        assert_eq!(
+            err(checked.clone().with_block("name", disk.clone())),
+            "SDK error: 'name' is already defined"
+        );
+        assert_eq!(
+            err(checked
+                .clone()
+                .with_attribute("for_each", Attribute::optional_string())),
+            "SDK error: 'for_each' is a reserved word"
+        );
+        assert_eq!(
+            err(checked.clone().with_attribute(
+                "id",
+                Attribute::new(
+                    AttributeType::String,
+                    AttributeFlags {
+                        computed: true,
+                        ..AttributeFlags::required()
+                    }
+                ),
+            )),
+            "SDK error: Invalid attribute 'id': Required attribute is also optional or computed"
+        );
+        assert_eq!(
+            err(checked.with_block(
+                "rule",
+                NestedBlock::list(Block::new().with_attribute(
+                    "port",
+                    Attribute::optional_int64().with_default(serde_json::json!("http")),
+                )),
+            )),
+            "SDK error: Invalid block 'rule': Default does not match the attribute type (at 'rule.port')"
+        );
+    }
+
     #[test]
     fn test_attribute_type_constructors() {
         let list = AttributeType::list(AttributeType::String);
@@ -511,6 +2159,30 @@ mod tests {
         assert!(matches!(map, AttributeType::Map(_)));
     }
 
+    #[test]
+    fn test_object_type_serde() {
+        let mut attrs = BTreeMap::new();
+        attrs.insert("host".to_string(), AttributeType::String);
+        let plain = AttributeType::object(attrs);
+        let json = serde_json::to_value(&plain).unwrap();
+        assert_eq!(json, serde_json::json!({"object": {"host": "string"}}));
+        assert_eq!(
+            serde_json::from_value::<AttributeType>(json).unwrap(),
+            plain
+        );
+
+        let with_optional: AttributeType = ObjectType::new()
+            .with_attribute("host", AttributeType::String)
+            .with_optional_attribute("port", AttributeType::Int64)
+            .into();
+        let json = serde_json::to_value(&with_optional).unwrap();
+        assert_eq!(json["object"]["optional"], serde_json::json!(["port"]));
+        assert_eq!(
+            serde_json::from_value::<AttributeType>(json).unwrap(),
+            with_optional
+        );
+    }
+
     #[test]
     fn test_attribute_flags() {
         let required = AttributeFlags::required();
@@ -527,6 +2199,8 @@ mod tests {
         assert!(!optional_computed.required);
         assert!(optional_computed.optional);
         assert!(optional_computed.computed);
+        assert!(optional_computed.is_optional_computed());
+        assert!(!computed.is_optional_computed());
 
         let sensitive = AttributeFlags::required().sensitive();
         assert!(sensitive.sensitive);
@@ -544,6 +2218,27 @@ mod tests {
         assert!(attr.force_new);
     }
 
+    #[test]
+    fn test_doc_metadata_serialization() {
+        let attr = Attribute::optional_string()
+            .with_category("Networking")
+            .with_example(serde_json::json!("10.0.0.0/16"))
+            .with_metadata("deprecated_in", serde_json::json!("2.0"));
+        let json = serde_json::to_value(&attr).unwrap();
+        assert_eq!(json["category"], "Networking");
+        assert_eq!(json["examples"], serde_json::json!(["10.0.0.0/16"]));
+        assert_eq!(json["metadata"]["deprecated_in"], "2.0");
+        assert_eq!(serde_json::from_value::<Attribute>(json).unwrap(), attr);
+
+        // Absent fields are omitted and default when deserializing
+        let json = serde_json::to_value(Schema::v0()).unwrap();
+        assert!(json.get("examples").is_none());
+        assert_eq!(
+            serde_json::from_value::<Schema>(json).unwrap(),
+            Schema::v0()
+        );
+    }
+
     #[test]
     fn test_schema_builder() {
         let schema = Schema::v0()
@@ -588,6 +2283,28 @@ mod tests {
         assert!(provider_schema.data_sources.contains_key("example_data"));
     }
 
+    #[test]
+    fn test_provider_schema_ordering_is_deterministic() {
+        let schema = |names: &[&str]| {
+            names.iter().fold(ProviderSchema::new(), |schema, name| {
+                schema.with_resource(
+                    *name,
+                    Schema::v0()
+                        .with_attribute(format!("{}_b", name), Attribute::optional_string())
+                        .with_attribute(format!("{}_a", name), Attribute::optional_string()),
+                )
+            })
+        };
+        let forward = serde_json::to_string(&schema(&["zone", "bucket", "queue"])).unwrap();
+        let reverse = serde_json::to_string(&schema(&["queue", "bucket", "zone"])).unwrap();
+        assert_eq!(forward, reverse);
+
+        let resources = schema(&["zone", "bucket"]);
+        let names: Vec<_> = resources.resources.keys().collect();
+        assert_eq!(names, ["bucket", "zone"]);
+        assert!(forward.find("bucket_a").unwrap() < forward.find("bucket_b").unwrap());
+    }
+
     #[test]
     fn test_diagnostic() {
         let err = Diagnostic::error("Invalid configuration")
@@ -600,6 +2317,125 @@ mod tests {
         assert_eq!(err.attribute, Some("count".to_string()));
     }
 
+    #[test]
+    fn test_diagnostic_constructors() {
+        let err = Diagnostic::invalid_value("region", "\"mars\"", "a known region")
+            .with_suggestion("Use \"us-east-1\"");
+        assert_eq!(
+            err.to_string(),
+            "[Error] Invalid value for 'region': Expected a known region, got \"mars\" (at region) Suggestion: Use \"us-east-1\""
+        );
+
+        let path = AttributePath::parse("disk[0].size");
+        let err = Diagnostic::invalid_type(path.clone(), "number", &serde_json::json!("big"));
+        assert_eq!(err.attribute.as_deref(), Some("disk.0.size"));
+        assert_eq!(err.path(), Some(path));
+        assert_eq!(err.detail.as_deref(), Some("Expected number, got string"));
+
+        let err = Diagnostic::conflicting_attributes("password", "password_file");
+        assert_eq!(
+            err.summary,
+            "Conflicting attributes 'password' and 'password_file'"
+        );
+        assert_eq!(err.attribute.as_deref(), Some("password"));
+        assert!(err.suggestion.is_some());
+
+        assert_eq!(
+            Diagnostic::missing_attribute("name").summary,
+            "Missing required attribute 'name'"
+        );
+        assert_eq!(
+            Diagnostic::unsupported_attribute("nmae").summary,
+            "Unsupported attribute 'nmae'"
+        );
+        assert!(Diagnostic::error("plain").path().is_none());
+    }
+
+    #[test]
+    fn test_example_value_required_only() {
+        let schema = Schema::v0()
+            .with_attribute("name", Attribute::required_string())
+            .with_attribute("count", Attribute::required_int64())
+            .with_attribute("enabled", Attribute::optional_bool())
+            .with_attribute("id", Attribute::computed_string());
+
+        let example = example_value(&schema);
+        assert_eq!(example, serde_json::json!({"name": "example", "count": 0}));
+        assert!(crate::validation::validate(&schema, &example).is_empty());
+    }
+
+    #[test]
+    fn test_example_value_required_blocks() {
+        let mut object_attrs = BTreeMap::new();
+        object_attrs.insert("host".to_string(), AttributeType::String);
+
+        let schema = Schema::v0()
+            .with_attribute(
+                "endpoint",
+                Attribute::new(
+                    AttributeType::object(object_attrs),
+                    AttributeFlags::required(),
+                ),
+            )
+            .with_block(
+                "ingress",
+                NestedBlock::list(Block::new().with_attribute("port", Attribute::required_int64()))
+                    .with_min_items(2),
+            )
+            .with_block(
+                "volumes",
+                NestedBlock::map(Block::new().with_attribute("path", Attribute::required_string()))
+                    .with_min_items(1),
+            )
+            .with_block(
+                "optional",
+                NestedBlock::single(Block::new().with_attribute("x", Attribute::required_bool())),
+            );
+
+        let example = example_value(&schema);
+        assert_eq!(example["endpoint"]["host"], "example");
+        assert_eq!(example["ingress"].as_array().unwrap().len(), 2);
+        assert_eq!(example["volumes"]["example_0"]["path"], "example");
+        assert!(example.get("optional").is_none());
+        assert!(crate::validation::validate(&schema, &example).is_empty());
+    }
+
+    #[test]
+    fn test_example_value_set_elements_differ() {
+        let schema = Schema::v0()
+            .with_block(
+                "rule",
+                NestedBlock::set(
+                    Block::new()
+                        .with_attribute("cidr", Attribute::required_string())
+                        .with_attribute("port", Attribute::required_int64()),
+                )
+                .with_min_items(3),
+            )
+            .with_block(
+                "port",
+                NestedBlock::set(
+                    Block::new().with_attribute("number", Attribute::required_int64()),
+                )
+                .with_min_items(2),
+            );
+
+        let example = example_value(&schema);
+        assert_eq!(
+            example["rule"],
+            serde_json::json!([
+                {"cidr": "example-0", "port": 0},
+                {"cidr": "example-1", "port": 1},
+                {"cidr": "example-2", "port": 2},
+            ])
+        );
+        assert_eq!(
+            example["port"],
+            serde_json::json!([{"number": 0}, {"number": 1}])
+        );
+        assert!(crate::validation::validate(&schema, &example).is_empty());
+    }
+
     #[test]
     fn test_nested_block_modes() {
         let single = NestedBlock::single(Block::new());
@@ -613,4 +2449,279 @@ mod tests {
         assert_eq!(list.min_items, 1);
         assert_eq!(list.max_items, 5);
     }
+
+    #[test]
+    fn test_attribute_path_parse() {
+        let path = AttributePath::parse("network[0].subnet.cidr");
+        assert_eq!(path.segments(), &["network", "0", "subnet", "cidr"]);
+        assert_eq!(path, AttributePath::parse("network.0.subnet.cidr"));
+        assert_eq!(path.to_string(), "network.0.subnet.cidr");
+    }
+
+    #[test]
+    fn test_resource_type_name() {
+        let name = ResourceTypeName::new("aws", "s3_bucket").unwrap();
+        assert_eq!(name.as_str(), "aws_s3_bucket");
+        assert_eq!((name.provider(), name.name()), ("aws", "s3_bucket"));
+        assert_eq!(
+            "example_3d_model"
+                .parse::<ResourceTypeName>()
+                .unwrap()
+                .name(),
+            "3d_model"
+        );
+
+        for (name, reason) in [
+            ("bucket", "expected '<provider>_<name>'"),
+            ("example_", "expected '<provider>_<name>'"),
+            ("_bucket", "expected '<provider>_<name>'"),
+            ("2cloud_bucket", "doesn't start with a lowercase letter"),
+            ("example_Bucket", "not lowercase snake_case"),
+            ("example_storage__bucket", "empty word between underscores"),
+        ] {
+            let err = ResourceTypeName::parse(name).unwrap_err().to_string();
+            assert!(err.contains(reason), "{}: {}", name, err);
+        }
+    }
+
+    #[test]
+    fn test_provider_prefix() {
+        let schema = ProviderSchema::new()
+            .with_resource("example_bucket", Schema::v0())
+            .with_data_source("example_region", Schema::v0());
+        assert_eq!(schema.provider_prefix(), Some("example"));
+        assert_eq!(ProviderSchema::new().provider_prefix(), None);
+        assert_eq!(
+            schema
+                .clone()
+                .with_resource("other_bucket", Schema::v0())
+                .provider_prefix(),
+            None
+        );
+        assert_eq!(
+            schema
+                .with_resource("bucket", Schema::v0())
+                .provider_prefix(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_checked_provider_schema() {
+        let checked = ProviderSchema::checked("example")
+            .unwrap()
+            .with_resource("bucket", Schema::v0())
+            .unwrap()
+            .with_resource("example_object", Schema::v0())
+            .unwrap();
+        let err = checked
+            .clone()
+            .with_resource("example_bucket", Schema::v0())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "SDK error: Resource type 'example_bucket' is already defined"
+        );
+        let schema = checked
+            .with_data_source("bucket", Schema::v0())
+            .unwrap()
+            .build();
+        assert_eq!(
+            schema.resources.keys().collect::<Vec<_>>(),
+            ["example_bucket", "example_object"]
+        );
+        assert!(schema.data_sources.contains_key("example_bucket"));
+
+        assert!(ProviderSchema::checked("my_cloud").is_err());
+        assert!(ProviderSchema::checked("Example").is_err());
+    }
+
+    #[test]
+    fn test_unknown_type_hints() {
+        let known = ["example_bucket".to_string(), "object".to_string()];
+        assert_eq!(
+            unknown_type("example_object", &known).to_string(),
+            "Unknown resource type: example_object (the schema defines 'object', \
+             without the provider prefix)"
+        );
+        assert_eq!(
+            unknown_type("aws_bucket", &known).to_string(),
+            "Unknown resource type: aws_bucket (this provider's types start with 'example_')"
+        );
+        assert_eq!(
+            unknown_type("example_disk", &known).to_string(),
+            "Unknown resource type: example_disk"
+        );
+        assert_eq!(
+            unknown_type("disk", &known).to_string(),
+            "Unknown resource type: disk"
+        );
+    }
+
+    #[test]
+    fn test_requires_replacement_nested() {
+        use crate::types::AttributeChange;
+        use serde_json::json;
+
+        let schema = Schema::v0()
+            .with_attribute("name", Attribute::required_string())
+            .with_attribute(
+                "zones",
+                Attribute::new(
+                    AttributeType::list(AttributeType::String),
+                    AttributeFlags::required(),
+                )
+                .with_force_new(),
+            )
+            .with_block(
+                "boot",
+                NestedBlock::single(
+                    Block::new()
+                        .with_attribute("image", Attribute::required_string().with_force_new()),
+                ),
+            )
+            .with_block(
+                "disk",
+                NestedBlock::map(
+                    Block::new()
+                        .with_attribute("size", Attribute::required_int64())
+                        .with_attribute("kind", Attribute::required_string().with_force_new()),
+                ),
+            );
+
+        let changes = vec![
+            AttributeChange::modified("name", json!("a"), json!("b")),
+            AttributeChange::modified("zones[0]", json!("a"), json!("b")),
+            AttributeChange::added("zones[1]", json!("c")),
+            AttributeChange::modified("boot.image", json!("x"), json!("y")),
+            AttributeChange::modified("disk.data.size", json!(10), json!(20)),
+            AttributeChange::modified("disk.logs.kind", json!("ssd"), json!("hdd")),
+            AttributeChange::added("unknown.attr", json!(1)),
+        ];
+        let paths: Vec<String> = requires_replacement(&schema, &changes)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(paths, vec!["boot.image", "disk.logs.kind", "zones"]);
+
+        let in_place = vec![AttributeChange::modified(
+            "disk.data.size",
+            json!(1),
+            json!(2),
+        )];
+        assert!(requires_replacement(&schema, &in_place).is_empty());
+
+        // Adding or removing a block whole replaces if it holds a force_new attribute
+        let whole = vec![
+            AttributeChange::added("boot", json!({"image": "x"})),
+            AttributeChange::removed("disk.logs", json!({"size": 1, "kind": "ssd"})),
+        ];
+        let paths: Vec<String> = requires_replacement(&schema, &whole)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(paths, vec!["boot", "disk.logs"]);
+
+        let plain = Schema::v0().with_block(
+            "tag",
+            NestedBlock::list(Block::new().with_attribute("key", Attribute::required_string())),
+        );
+        let added = vec![AttributeChange::added("tag.0", json!({"key": "a"}))];
+        assert!(requires_replacement(&plain, &added).is_empty());
+    }
+
+    #[test]
+    fn test_bulk_builders() {
+        let disk = NestedBlock::list(Block::new().with_attributes([
+            ("size", Attribute::required_int64()),
+            ("kind", Attribute::optional_string()),
+        ]));
+        let schema = Schema::v0()
+            .with_attributes(vec![("name".to_string(), Attribute::required_string())])
+            .with_blocks([("disk", disk.clone())]);
+        assert_eq!(
+            schema,
+            Schema::v0()
+                .with_attribute("name", Attribute::required_string())
+                .with_block(
+                    "disk",
+                    NestedBlock::list(
+                        Block::new()
+                            .with_attribute("size", Attribute::required_int64())
+                            .with_attribute("kind", Attribute::optional_string())
+                    )
+                )
+        );
+
+        let from_macro = crate::schema! { name: required_string }.with_block("disk", disk);
+        assert_eq!(from_macro, schema);
+        assert_eq!(crate::schema! {}, Schema::v0());
+    }
+
+    #[test]
+    fn test_schema_fragments() {
+        let timeouts = SchemaFragment::new("timeouts").with_block(
+            "timeouts",
+            NestedBlock::single(
+                Block::new().with_attribute("create", Attribute::optional_string()),
+            ),
+        );
+        let identity = SchemaFragment::new("identity")
+            .with_attribute("id", Attribute::computed_string())
+            .with_attribute("arn", Attribute::computed_string());
+
+        let schema = Schema::v0()
+            .with_attribute("id", Attribute::computed_string())
+            .with_fragment(&identity)
+            .with_fragment(&timeouts)
+            .with_fragment(&timeouts);
+        assert_eq!(schema.block.attributes.len(), 2);
+        assert!(schema.block.blocks.contains_key("timeouts"));
+
+        let err = Schema::v0()
+            .with_attribute("arn", Attribute::required_string())
+            .try_with_fragment(&identity)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "SDK error: Schema fragment 'identity' redefines 'arn'"
+        );
+        let err = Schema::v0()
+            .with_attribute("timeouts", Attribute::optional_string())
+            .try_with_fragment(&timeouts)
+            .unwrap_err();
+        assert!(err.to_string().contains("redefines 'timeouts'"));
+    }
+
+    #[test]
+    fn test_block_level_flags() {
+        use crate::types::AttributeChange;
+        use serde_json::json;
+
+        let schema = Schema::v0().with_block(
+            "network",
+            NestedBlock::list(
+                Block::new()
+                    .with_attribute("subnet", Attribute::required_string())
+                    .with_attribute("key", Attribute::optional_string()),
+            )
+            .with_force_new()
+            .sensitive(),
+        );
+        let changes = vec![
+            AttributeChange::modified("network.0.subnet", json!("a"), json!("b")),
+            AttributeChange::added("network.1", json!({"subnet": "c"})),
+        ];
+        let paths: Vec<String> = requires_replacement(&schema, &changes)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(paths, vec!["network"]);
+
+        let json = serde_json::to_value(&schema.block.blocks["network"]).unwrap();
+        assert_eq!(json["force_new"], true);
+        assert_eq!(json["sensitive"], true);
+        let plain = serde_json::to_value(NestedBlock::single(Block::new())).unwrap();
+        assert!(plain.get("force_new").is_none());
+    }
 }
@@ -0,0 +1,168 @@
+//! Exporting the provider schema at build time.
+//!
+//! Registries index providers from their release archives, which they can't
+//! run. A build script that builds the provider schema can write it, with its
+//! [manifest](crate::manifest), next to the provider binary, so both end up in
+//! the archive:
+//!
+//! ```ignore
+//! // build.rs, with hemmer-provider-sdk in [build-dependencies]. The
+//! // schema module builds the ProviderSchema and is shared with the provider:
+//! // `fn schema(&self) -> ProviderSchema { crate::schema::provider_schema() }`
+//! #[path = "src/schema.rs"]
+//! mod schema;
+//!
+//! use hemmer_provider_sdk::schema_export::SchemaExport;
+//!
+//! fn main() {
+//!     println!("cargo:rerun-if-changed=src/schema.rs");
+//!     SchemaExport::new("example", env!("CARGO_PKG_VERSION"), schema::provider_schema())
+//!         .write_to_target_dir()
+//!         .unwrap();
+//! }
+//! ```
+//!
+//! This writes `example-schema.json` and `example-manifest.json` to
+//! `target/<profile>/`. The schema file holds the same JSON as the devtool's
+//! `schema` command. [`SchemaExport::write_to`] writes to any directory, e.g.
+//! `OUT_DIR` for a binary that embeds its schema with `include_str!`.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::ProviderError;
+use crate::manifest::{Distribution, ProviderManifest};
+use crate::schema::ProviderSchema;
+use crate::types::ServerCapabilities;
+
+/// A provider schema and manifest to write as build artifacts.
+#[derive(Debug, Clone)]
+pub struct SchemaExport {
+    schema: ProviderSchema,
+    manifest: ProviderManifest,
+}
+
+impl SchemaExport {
+    /// Export the schema of provider `name` at `version`.
+    pub fn new(
+        name: impl Into<String>,
+        version: impl Into<String>,
+        schema: ProviderSchema,
+    ) -> Self {
+        let manifest = schema.to_manifest().with_provider(name, version);
+        Self { schema, manifest }
+    }
+
+    /// Set the server capabilities recorded in the manifest.
+    pub fn with_capabilities(mut self, capabilities: ServerCapabilities) -> Self {
+        self.manifest = self.manifest.with_capabilities(capabilities);
+        self
+    }
+
+    /// Set the distribution metadata recorded in the manifest.
+    pub fn with_distribution(mut self, distribution: Distribution) -> Self {
+        self.manifest = self.manifest.with_distribution(distribution);
+        self
+    }
+
+    /// The manifest that will be written.
+    pub fn manifest(&self) -> &ProviderManifest {
+        &self.manifest
+    }
+
+    /// Write `<name>-schema.json` and `<name>-manifest.json` to `dir`,
+    /// creating it if needed. Returns the paths written.
+    pub fn write_to(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, ProviderError> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir).map_err(|e| write_error(dir, e))?;
+
+        let files = [
+            (
+                format!("{}-schema.json", self.manifest.name),
+                serde_json::to_string_pretty(&self.schema),
+            ),
+            (
+                format!("{}-manifest.json", self.manifest.name),
+                serde_json::to_string_pretty(&self.manifest),
+            ),
+        ];
+        let mut written = Vec::new();
+        for (name, json) in files {
+            let path = dir.join(name);
+            let json = json.map_err(ProviderError::Serialization)?;
+            std::fs::write(&path, json + "\n").map_err(|e| write_error(&path, e))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    /// Write the files to the directory cargo builds the provider binary in,
+    /// e.g. `target/release/`. Only works from a build script.
+    pub fn write_to_target_dir(&self) -> Result<Vec<PathBuf>, ProviderError> {
+        let out_dir = std::env::var_os("OUT_DIR").ok_or_else(|| {
+            ProviderError::Sdk(
+                "OUT_DIR is not set; write_to_target_dir only works from a build script"
+                    .to_string(),
+            )
+        })?;
+        let dir = profile_dir(Path::new(&out_dir)).ok_or_else(|| {
+            ProviderError::Sdk(format!(
+                "Can't find the target directory from OUT_DIR '{}'",
+                Path::new(&out_dir).display()
+            ))
+        })?;
+        self.write_to(dir)
+    }
+}
+
+/// The profile directory of a build script's `OUT_DIR`, which cargo places at
+/// `<profile dir>/build/<package>-<hash>/out`.
+fn profile_dir(out_dir: &Path) -> Option<&Path> {
+    out_dir.ancestors().nth(3)
+}
+
+fn write_error(path: &Path, err: std::io::Error) -> ProviderError {
+    ProviderError::Sdk(format!("Can't write '{}': {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Attribute, Schema};
+
+    #[test]
+    fn test_write_to() {
+        let schema = ProviderSchema::new().with_resource(
+            "example_bucket",
+            Schema::v0().with_attribute("name", Attribute::required_string()),
+        );
+        let dir = std::env::temp_dir().join(format!("hemmer-schema-export-{}", std::process::id()));
+        let written = SchemaExport::new("example", "1.2.0", schema.clone())
+            .write_to(&dir)
+            .unwrap();
+        assert_eq!(
+            written,
+            [
+                dir.join("example-schema.json"),
+                dir.join("example-manifest.json")
+            ]
+        );
+
+        let exported: ProviderSchema =
+            serde_json::from_str(&std::fs::read_to_string(&written[0]).unwrap()).unwrap();
+        assert_eq!(exported, schema);
+        let manifest: ProviderManifest =
+            serde_json::from_str(&std::fs::read_to_string(&written[1]).unwrap()).unwrap();
+        assert_eq!(manifest.version, "1.2.0");
+        assert_eq!(manifest.schema_hash, crate::manifest::schema_hash(&schema));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_profile_dir() {
+        assert_eq!(
+            profile_dir(Path::new("/src/target/release/build/example-1a2b/out")),
+            Some(Path::new("/src/target/release"))
+        );
+        assert_eq!(profile_dir(Path::new("out")), None);
+    }
+}
@@ -0,0 +1,259 @@
+//! Streamed schemas for very large providers.
+//!
+//! Providers generated from large cloud APIs can have thousands of resource
+//! schemas, and a single `GetSchemaResponse` then exceeds the gRPC message
+//! size limit (4 MiB by default). The `StreamSchema` RPC sends the same schema
+//! as a series of `SchemaChunk`s instead:
+//!
+//! - [`split_schema`] packs the resource and data source schemas into parts of
+//!   about `max_chunk_bytes` each, after a first part holding the protocol
+//!   version, provider schema, functions and diagnostics
+//! - with the `schema-zstd` feature, each part is zstd-compressed when core
+//!   sets `accept_zstd`
+//! - [`merge_schema`] reassembles the chunks on the receiving side
+//!
+//! ```
+//! use hemmer_provider_sdk::generated::{GetSchemaResponse, Schema};
+//! use hemmer_provider_sdk::schema_stream::{merge_schema, schema_chunks};
+//!
+//! let schema = GetSchemaResponse {
+//!     server_protocol_version: 1,
+//!     resources: (0..100)
+//!         .map(|i| (format!("resource_{}", i), Schema::default()))
+//!         .collect(),
+//!     ..Default::default()
+//! };
+//!
+//! let chunks = schema_chunks(schema.clone(), 256, false);
+//! assert!(chunks.len() > 2);
+//! assert_eq!(merge_schema(chunks).unwrap(), schema);
+//! ```
+
+use prost::bytes::Bytes;
+use prost::Message;
+
+use crate::error::ProviderError;
+use crate::generated::{GetSchemaResponse, SchemaChunk};
+
+/// Chunk size used when core doesn't ask for one.
+pub const DEFAULT_MAX_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// zstd compression level for schema chunks. Schemas are highly repetitive,
+/// so low levels already compress well.
+#[cfg(feature = "schema-zstd")]
+const ZSTD_LEVEL: i32 = 3;
+
+/// Split a schema into parts whose encoded resource and data source schemas
+/// add up to about `max_chunk_bytes` each.
+///
+/// The first part holds everything but the resource and data source schemas.
+/// A single schema larger than `max_chunk_bytes` gets a part of its own.
+pub fn split_schema(schema: GetSchemaResponse, max_chunk_bytes: usize) -> Vec<GetSchemaResponse> {
+    let GetSchemaResponse {
+        server_protocol_version,
+        provider,
+        resources,
+        data_sources,
+        diagnostics,
+        functions,
+    } = schema;
+    let mut parts = vec![GetSchemaResponse {
+        server_protocol_version,
+        provider,
+        diagnostics,
+        functions,
+        ..Default::default()
+    }];
+
+    let mut current = GetSchemaResponse::default();
+    let mut size = 0;
+    let entries = resources
+        .into_iter()
+        .map(|(name, schema)| (true, name, schema))
+        .chain(
+            data_sources
+                .into_iter()
+                .map(|(name, schema)| (false, name, schema)),
+        );
+    for (is_resource, name, schema) in entries {
+        let entry_size = name.len() + schema.encoded_len();
+        if size > 0 && size + entry_size > max_chunk_bytes {
+            parts.push(std::mem::take(&mut current));
+            size = 0;
+        }
+        size += entry_size;
+        if is_resource {
+            current.resources.insert(name, schema);
+        } else {
+            current.data_sources.insert(name, schema);
+        }
+    }
+    if size > 0 {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Split a schema with [`split_schema`] and wrap the parts in chunks,
+/// zstd-compressed if `compress` is set and the `schema-zstd` feature is
+/// enabled.
+pub fn schema_chunks(
+    schema: GetSchemaResponse,
+    max_chunk_bytes: usize,
+    compress: bool,
+) -> Vec<SchemaChunk> {
+    split_schema(schema, max_chunk_bytes)
+        .into_iter()
+        .map(|part| {
+            let compressed = if compress {
+                zstd_encode(&part.encode_to_vec())
+            } else {
+                None
+            };
+            match compressed {
+                Some(compressed) => SchemaChunk {
+                    schema: None,
+                    zstd_schema: compressed.into(),
+                },
+                None => SchemaChunk {
+                    schema: Some(part),
+                    zstd_schema: Bytes::new(),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Reassemble a schema from its chunks.
+///
+/// Fails if a chunk can't be decompressed, including when it is compressed
+/// and the `schema-zstd` feature is disabled.
+pub fn merge_schema(
+    chunks: impl IntoIterator<Item = SchemaChunk>,
+) -> Result<GetSchemaResponse, ProviderError> {
+    let mut schema = GetSchemaResponse::default();
+    for chunk in chunks {
+        let part = match chunk.schema {
+            Some(part) => part,
+            None if chunk.zstd_schema.is_empty() => continue,
+            None => GetSchemaResponse::decode(zstd_decode(&chunk.zstd_schema)?.as_slice())
+                .map_err(|e| ProviderError::Sdk(format!("Invalid schema chunk: {}", e)))?,
+        };
+        if part.server_protocol_version != 0 {
+            schema.server_protocol_version = part.server_protocol_version;
+        }
+        if part.provider.is_some() {
+            schema.provider = part.provider;
+        }
+        schema.resources.extend(part.resources);
+        schema.data_sources.extend(part.data_sources);
+        schema.diagnostics.extend(part.diagnostics);
+        schema.functions.extend(part.functions);
+    }
+    Ok(schema)
+}
+
+#[cfg(feature = "schema-zstd")]
+fn zstd_encode(bytes: &[u8]) -> Option<Vec<u8>> {
+    zstd::encode_all(bytes, ZSTD_LEVEL).ok()
+}
+
+#[cfg(not(feature = "schema-zstd"))]
+fn zstd_encode(_bytes: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(feature = "schema-zstd")]
+fn zstd_decode(bytes: &[u8]) -> Result<Vec<u8>, ProviderError> {
+    zstd::decode_all(bytes)
+        .map_err(|e| ProviderError::Sdk(format!("Invalid zstd schema chunk: {}", e)))
+}
+
+#[cfg(not(feature = "schema-zstd"))]
+fn zstd_decode(_bytes: &[u8]) -> Result<Vec<u8>, ProviderError> {
+    Err(ProviderError::Sdk(
+        "Schema chunk is zstd-compressed, but the schema-zstd feature is disabled".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::{Block, Schema};
+
+    fn large_schema() -> GetSchemaResponse {
+        let schema = |description: &str| Schema {
+            version: 1,
+            block: Some(Block {
+                description: description.repeat(20),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        GetSchemaResponse {
+            server_protocol_version: 1,
+            provider: Some(schema("provider")),
+            resources: (0..50)
+                .map(|i| (format!("resource_{}", i), schema("resource")))
+                .collect(),
+            data_sources: (0..10)
+                .map(|i| (format!("data_source_{}", i), schema("data source")))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_split_and_merge() {
+        let schema = large_schema();
+        let parts = split_schema(schema.clone(), 1024);
+        assert!(parts.len() > 3);
+        assert!(parts[0].provider.is_some());
+        assert!(parts[0].resources.is_empty());
+        for part in &parts[1..] {
+            assert!(part.provider.is_none());
+            // Every part but the first stays close to the limit
+            assert!(part.encoded_len() < 1024 + 256);
+        }
+
+        let chunks = schema_chunks(schema.clone(), 1024, false);
+        assert_eq!(chunks.len(), parts.len());
+        assert_eq!(merge_schema(chunks).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_oversized_schema_gets_own_part() {
+        let parts = split_schema(large_schema(), 1);
+        // The head, then one part per resource and data source
+        assert_eq!(parts.len(), 61);
+        assert!(parts[1..]
+            .iter()
+            .all(|p| p.resources.len() + p.data_sources.len() == 1));
+    }
+
+    #[cfg(feature = "schema-zstd")]
+    #[test]
+    fn test_zstd_chunks() {
+        let schema = large_schema();
+        let chunks = schema_chunks(schema.clone(), 4096, true);
+        assert!(chunks.iter().all(|c| c.schema.is_none()));
+        let compressed: usize = chunks.iter().map(|c| c.zstd_schema.len()).sum();
+        assert!(compressed < schema.encoded_len() / 4);
+        assert_eq!(merge_schema(chunks).unwrap(), schema);
+    }
+
+    #[cfg(not(feature = "schema-zstd"))]
+    #[test]
+    fn test_zstd_requires_feature() {
+        // Without the feature, chunks are sent uncompressed even if core accepts zstd
+        let chunks = schema_chunks(large_schema(), 4096, true);
+        assert!(chunks.iter().all(|c| c.schema.is_some()));
+
+        let err = merge_schema([SchemaChunk {
+            schema: None,
+            zstd_schema: Bytes::from_static(b"\x28\xb5\x2f\xfd"),
+        }])
+        .unwrap_err();
+        assert!(err.to_string().contains("schema-zstd feature is disabled"));
+    }
+}
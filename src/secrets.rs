@@ -0,0 +1,814 @@
+//! Pluggable secret resolution for provider configuration.
+//!
+//! Attributes marked with [`Attribute::secret_ref`](crate::schema::Attribute::secret_ref)
+//! hold a reference of the form `<scheme>:<location>` instead of the secret itself.
+//! Before `configure` is called, the server resolves these references with the
+//! [`SecretResolvers`] from [`ServeOptions`](crate::ServeOptions), caches the results,
+//! and redacts resolved values from any diagnostics returned to core. The cache
+//! is cleared at the start of every `Configure`, so reconfiguring picks up
+//! rotated secrets.
+//!
+//! Built-in resolvers:
+//!
+//! | Scheme | Example | Resolver |
+//! |--------|---------|----------|
+//! | `env` | `env:AWS_SECRET_ACCESS_KEY` | [`EnvResolver`] |
+//! | `file` | `file:/run/secrets/token` | [`FileResolver`] |
+//! | `exec` | `exec:pass show aws/key` | [`ExecResolver`] (opt-in) |
+//! | `vault` | `vault:kv/data/aws#key` | [`VaultResolver`] (feature: `vault`) |
+//!
+//! # Exec credentials
+//!
+//! Attributes created with [`Attribute::exec_credential`](crate::schema::Attribute::exec_credential)
+//! configure a command that prints a credential, like kubectl's exec
+//! credential plugins. This lets users authenticate through SSO helpers
+//! (`aws sso`, `gcloud`, vendor CLIs) without the provider embedding vendor
+//! SDKs:
+//!
+//! ```hcl
+//! auth = {
+//!   command = "mycloud-sso"
+//!   args    = ["token", "--profile", "prod"]
+//!   env     = { MYCLOUD_REGION = "eu-west-1" }
+//!   timeout = 60
+//! }
+//! ```
+//!
+//! The command is run without a shell, with `env` added to the provider's
+//! environment, and must exit successfully within `timeout` seconds (default
+//! 30) and print a JSON object to standard output:
+//!
+//! ```json
+//! {"token": "eyJhbGciOi...", "expires_at": "2024-05-01T12:00:00Z"}
+//! ```
+//!
+//! `configure` receives that object in place of the command. The output is
+//! cached until 60 seconds before `expires_at` (an RFC 3339 timestamp), or
+//! until the cache is cleared if there is none, and its string values are
+//! redacted like secrets.
+//!
+//! # Example
+//!
+//! ```
+//! use hemmer_provider_sdk::schema::{Attribute, Schema};
+//! use hemmer_provider_sdk::secrets::SecretResolvers;
+//! use serde_json::json;
+//!
+//! # tokio_test::block_on(async {
+//! std::env::set_var("EXAMPLE_API_TOKEN", "s3cr3t");
+//!
+//! let schema = Schema::v0().with_attribute("token", Attribute::required_string().secret_ref());
+//! let mut config = json!({"token": "env:EXAMPLE_API_TOKEN"});
+//!
+//! let resolvers = SecretResolvers::standard();
+//! resolvers.resolve_config(&schema.block, &mut config).await.unwrap();
+//! assert_eq!(config["token"], "s3cr3t");
+//! assert_eq!(resolvers.redact("token s3cr3t rejected"), "token [REDACTED] rejected");
+//! # });
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use crate::error::ProviderError;
+use crate::schema::{Attribute, Block, BlockNestingMode};
+
+/// Replacement text for resolved secret values in redacted output.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// How long before `expires_at` a cached exec credential is fetched again.
+const EXEC_CREDENTIAL_RENEW_BEFORE: Duration = Duration::from_secs(60);
+
+/// Resolves secret references for a single scheme.
+#[async_trait::async_trait]
+pub trait SecretResolver: Send + Sync + 'static {
+    /// The scheme this resolver handles (the part before the first `:`).
+    fn scheme(&self) -> &str;
+
+    /// Resolve a reference, given the part after `<scheme>:`.
+    async fn resolve(&self, location: &str) -> Result<String, ProviderError>;
+}
+
+/// Resolves `env:NAME` from the process environment.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvResolver;
+
+#[async_trait::async_trait]
+impl SecretResolver for EnvResolver {
+    fn scheme(&self) -> &str {
+        "env"
+    }
+
+    async fn resolve(&self, location: &str) -> Result<String, ProviderError> {
+        std::env::var(location).map_err(|_| {
+            ProviderError::Configuration(format!("Environment variable '{}' is not set", location))
+        })
+    }
+}
+
+/// Resolves `file:/path` to the file contents, without a trailing newline.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileResolver;
+
+#[async_trait::async_trait]
+impl SecretResolver for FileResolver {
+    fn scheme(&self) -> &str {
+        "file"
+    }
+
+    async fn resolve(&self, location: &str) -> Result<String, ProviderError> {
+        let contents = tokio::fs::read_to_string(location).await.map_err(|e| {
+            ProviderError::Configuration(format!(
+                "Failed to read secret file '{}': {}",
+                location, e
+            ))
+        })?;
+        Ok(contents.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Resolves `exec:command args...` to the command's standard output.
+///
+/// The command is split on whitespace and run without a shell. This resolver
+/// runs arbitrary commands from configuration, so it is not part of
+/// [`SecretResolvers::standard`] and must be registered explicitly.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExecResolver;
+
+#[async_trait::async_trait]
+impl SecretResolver for ExecResolver {
+    fn scheme(&self) -> &str {
+        "exec"
+    }
+
+    async fn resolve(&self, location: &str) -> Result<String, ProviderError> {
+        let mut parts = location.split_whitespace();
+        let program = parts.next().ok_or_else(|| {
+            ProviderError::Configuration("exec secret reference has no command".to_string())
+        })?;
+        let output = tokio::process::Command::new(program)
+            .args(parts)
+            .output()
+            .await
+            .map_err(|e| {
+                ProviderError::Configuration(format!("Failed to run '{}': {}", program, e))
+            })?;
+        if !output.status.success() {
+            return Err(ProviderError::Configuration(format!(
+                "Secret command '{}' exited with {}",
+                program, output.status
+            )));
+        }
+        let stdout = String::from_utf8(output.stdout).map_err(|_| {
+            ProviderError::Configuration(format!(
+                "Secret command '{}' output is not UTF-8",
+                program
+            ))
+        })?;
+        Ok(stdout.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Resolves `vault:<path>#<key>` from a HashiCorp Vault KV engine (v1 or v2).
+///
+/// The address and token default to `VAULT_ADDR` and `VAULT_TOKEN`.
+#[cfg(feature = "vault")]
+#[derive(Clone)]
+pub struct VaultResolver {
+    address: String,
+    token: String,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "vault")]
+impl VaultResolver {
+    /// Create a resolver for the given Vault address and token.
+    pub fn new(address: impl Into<String>, token: impl Into<String>) -> Self {
+        Self {
+            address: address.into().trim_end_matches('/').to_string(),
+            token: token.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create a resolver from `VAULT_ADDR` and `VAULT_TOKEN`.
+    pub fn from_env() -> Result<Self, ProviderError> {
+        let address = std::env::var("VAULT_ADDR")
+            .map_err(|_| ProviderError::Configuration("VAULT_ADDR is not set".to_string()))?;
+        let token = std::env::var("VAULT_TOKEN")
+            .map_err(|_| ProviderError::Configuration("VAULT_TOKEN is not set".to_string()))?;
+        Ok(Self::new(address, token))
+    }
+}
+
+#[cfg(feature = "vault")]
+impl fmt::Debug for VaultResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VaultResolver")
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "vault")]
+#[async_trait::async_trait]
+impl SecretResolver for VaultResolver {
+    fn scheme(&self) -> &str {
+        "vault"
+    }
+
+    async fn resolve(&self, location: &str) -> Result<String, ProviderError> {
+        let (path, key) = location.split_once('#').ok_or_else(|| {
+            ProviderError::Configuration(format!(
+                "Vault reference '{}' must be of the form <path>#<key>",
+                location
+            ))
+        })?;
+        let url = format!("{}/v1/{}", self.address, path.trim_start_matches('/'));
+        let response = self
+            .client
+            .get(&url)
+            .header("X-Vault-Token", &self.token)
+            .send()
+            .await
+            .map_err(|e| ProviderError::Unavailable(format!("Vault request failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(ProviderError::Configuration(format!(
+                "Vault returned {} for '{}'",
+                response.status(),
+                path
+            )));
+        }
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| ProviderError::Sdk(format!("Invalid Vault response: {}", e)))?;
+
+        // KV v2 nests the secret under data.data, KV v1 directly under data
+        let value = body["data"]["data"]
+            .get(key)
+            .or_else(|| body["data"].get(key))
+            .ok_or_else(|| {
+                ProviderError::NotFound(format!("Key '{}' not found in Vault path '{}'", key, path))
+            })?;
+        Ok(match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })
+    }
+}
+
+/// The command configured by an [`Attribute::exec_credential`] attribute.
+///
+/// See the [module docs](self#exec-credentials) for the output contract.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExecCredentialSpec {
+    /// The program to run.
+    pub command: String,
+    /// Arguments passed to the program.
+    pub args: Vec<String>,
+    /// Variables added to the provider's environment.
+    pub env: BTreeMap<String, String>,
+    /// How long the command may run.
+    pub timeout: Duration,
+}
+
+impl ExecCredentialSpec {
+    /// Create a spec for `command`, with a 30 second timeout.
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Set the arguments.
+    pub fn with_args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Add an environment variable.
+    pub fn with_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Parse the configured value of an exec credential attribute.
+    pub fn from_value(value: &Value) -> Result<Self, ProviderError> {
+        let invalid = |msg: &str| ProviderError::Configuration(msg.to_string());
+        let command = value["command"]
+            .as_str()
+            .filter(|c| !c.is_empty())
+            .ok_or_else(|| invalid("Exec credential has no command"))?;
+        let mut spec = Self::new(command);
+        for arg in value["args"].as_array().into_iter().flatten() {
+            let arg = arg
+                .as_str()
+                .ok_or_else(|| invalid("Exec credential args must be strings"))?;
+            spec.args.push(arg.to_string());
+        }
+        for (name, env_value) in value["env"].as_object().into_iter().flatten() {
+            let env_value = env_value
+                .as_str()
+                .ok_or_else(|| invalid("Exec credential env values must be strings"))?;
+            spec.env.insert(name.clone(), env_value.to_string());
+        }
+        if let Some(timeout) = value["timeout"].as_f64() {
+            if !(timeout > 0.0 && timeout.is_finite()) {
+                return Err(invalid("Exec credential timeout must be positive"));
+            }
+            spec.timeout = Duration::from_secs_f64(timeout);
+        }
+        Ok(spec)
+    }
+
+    /// Run the command and parse its output, without caching.
+    pub async fn run(&self) -> Result<ExecCredential, ProviderError> {
+        let command = &self.command;
+        let child = tokio::process::Command::new(command)
+            .args(&self.args)
+            .envs(&self.env)
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .output();
+        let output = tokio::time::timeout(self.timeout, child)
+            .await
+            .map_err(|_| {
+                ProviderError::DeadlineExceeded(format!(
+                    "Credential command '{}' did not finish within {:?}",
+                    command, self.timeout
+                ))
+            })?
+            .map_err(|e| {
+                ProviderError::Configuration(format!("Failed to run '{}': {}", command, e))
+            })?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(ProviderError::Configuration(format!(
+                "Credential command '{}' exited with {}: {}",
+                command,
+                output.status,
+                stderr.trim()
+            )));
+        }
+
+        let value: Value = serde_json::from_slice(&output.stdout).map_err(|e| {
+            ProviderError::Configuration(format!(
+                "Credential command '{}' did not print a JSON object: {}",
+                command, e
+            ))
+        })?;
+        if !value.is_object() {
+            return Err(ProviderError::Configuration(format!(
+                "Credential command '{}' did not print a JSON object",
+                command
+            )));
+        }
+        let expires_at = match value.get("expires_at") {
+            None | Some(Value::Null) => None,
+            Some(ts) => Some(ts.as_str().and_then(parse_rfc3339).ok_or_else(|| {
+                ProviderError::Configuration(format!(
+                    "Credential command '{}' printed an invalid expires_at: {}",
+                    command, ts
+                ))
+            })?),
+        };
+        Ok(ExecCredential { value, expires_at })
+    }
+}
+
+/// A credential printed by an exec credential command.
+#[derive(Clone)]
+pub struct ExecCredential {
+    /// The JSON object the command printed.
+    pub value: Value,
+    /// When the credential expires, from its `expires_at` field.
+    pub expires_at: Option<SystemTime>,
+}
+
+impl ExecCredential {
+    /// Whether the credential expires within `margin` from now.
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= SystemTime::now() + margin)
+    }
+}
+
+impl fmt::Debug for ExecCredential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExecCredential")
+            .field("expires_at", &self.expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A set of [`SecretResolver`]s keyed by scheme, with a cache of resolved values.
+#[derive(Clone, Default)]
+pub struct SecretResolvers {
+    resolvers: HashMap<String, Arc<dyn SecretResolver>>,
+    cache: Arc<Mutex<HashMap<String, String>>>,
+    exec_cache: Arc<Mutex<HashMap<ExecCredentialSpec, ExecCredential>>>,
+}
+
+impl SecretResolvers {
+    /// Create an empty set of resolvers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a set with the `env` and `file` resolvers registered.
+    pub fn standard() -> Self {
+        Self::new()
+            .with_resolver(EnvResolver)
+            .with_resolver(FileResolver)
+    }
+
+    /// Register a resolver, replacing any existing resolver for its scheme.
+    pub fn with_resolver(mut self, resolver: impl SecretResolver) -> Self {
+        self.resolvers
+            .insert(resolver.scheme().to_string(), Arc::new(resolver));
+        self
+    }
+
+    /// Whether any resolvers are registered.
+    pub fn is_empty(&self) -> bool {
+        self.resolvers.is_empty()
+    }
+
+    /// Resolve a single `<scheme>:<location>` reference, using the cache.
+    pub async fn resolve(&self, reference: &str) -> Result<String, ProviderError> {
+        if let Some(value) = self.cache.lock().unwrap().get(reference) {
+            return Ok(value.clone());
+        }
+
+        let (scheme, location) = reference.split_once(':').ok_or_else(|| {
+            ProviderError::Configuration(
+                "Secret reference must be of the form <scheme>:<location>".to_string(),
+            )
+        })?;
+        let resolver = self.resolvers.get(scheme).ok_or_else(|| {
+            ProviderError::Configuration(format!("No secret resolver for scheme '{}'", scheme))
+        })?;
+        let value = resolver.resolve(location).await?;
+
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(reference.to_string(), value.clone());
+        Ok(value)
+    }
+
+    /// Run an exec credential command, or return its cached output.
+    pub async fn resolve_exec_credential(
+        &self,
+        spec: &ExecCredentialSpec,
+    ) -> Result<Value, ProviderError> {
+        if let Some(credential) = self.exec_cache.lock().unwrap().get(spec) {
+            if !credential.expires_within(EXEC_CREDENTIAL_RENEW_BEFORE) {
+                return Ok(credential.value.clone());
+            }
+        }
+        let credential = spec.run().await?;
+        let value = credential.value.clone();
+        self.exec_cache
+            .lock()
+            .unwrap()
+            .insert(spec.clone(), credential);
+        Ok(value)
+    }
+
+    /// Replace every `secret_ref` attribute in `config` with its resolved
+    /// value, and every `exec_credential` attribute with its command's output.
+    pub async fn resolve_config(
+        &self,
+        block: &Block,
+        config: &mut Value,
+    ) -> Result<(), ProviderError> {
+        let mut refs = Vec::new();
+        collect_attributes(block, config, String::new(), &|a| a.secret_ref, &mut refs);
+        let mut execs = Vec::new();
+        collect_attributes(
+            block,
+            config,
+            String::new(),
+            &|a| a.exec_credential,
+            &mut execs,
+        );
+
+        for (pointer, reference) in refs {
+            let Value::String(reference) = reference else {
+                continue;
+            };
+            let value = self
+                .resolve(&reference)
+                .await
+                .map_err(|e| at_pointer(&pointer, e))?;
+            if let Some(slot) = config.pointer_mut(&pointer) {
+                *slot = Value::String(value);
+            }
+        }
+        for (pointer, spec) in execs {
+            if spec.is_null() {
+                continue;
+            }
+            let spec =
+                ExecCredentialSpec::from_value(&spec).map_err(|e| at_pointer(&pointer, e))?;
+            let value = self
+                .resolve_exec_credential(&spec)
+                .await
+                .map_err(|e| at_pointer(&pointer, e))?;
+            if let Some(slot) = config.pointer_mut(&pointer) {
+                *slot = value;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace every resolved secret value that appears in `text`.
+    pub fn redact(&self, text: &str) -> String {
+        let cache = self.cache.lock().unwrap();
+        let exec_cache = self.exec_cache.lock().unwrap();
+        let exec_values = exec_cache
+            .values()
+            .filter_map(|credential| credential.value.as_object())
+            .flat_map(|obj| obj.values().filter_map(Value::as_str));
+        cache
+            .values()
+            .map(String::as_str)
+            .chain(exec_values)
+            .filter(|secret| !secret.is_empty())
+            .fold(text.to_string(), |acc, secret| {
+                acc.replace(secret, REDACTED)
+            })
+    }
+
+    /// Drop all cached values so the next resolution hits the resolvers again.
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+        self.exec_cache.lock().unwrap().clear();
+    }
+}
+
+impl fmt::Debug for SecretResolvers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut schemes: Vec<_> = self.resolvers.keys().collect();
+        schemes.sort();
+        f.debug_struct("SecretResolvers")
+            .field("schemes", &schemes)
+            .finish()
+    }
+}
+
+/// Prefix a configuration error with the attribute path at `pointer`.
+fn at_pointer(pointer: &str, err: ProviderError) -> ProviderError {
+    match err {
+        ProviderError::Configuration(msg) => ProviderError::Configuration(format!(
+            "{}: {}",
+            pointer.trim_start_matches('/').replace('/', "."),
+            msg
+        )),
+        other => other,
+    }
+}
+
+/// Collect `(json pointer, value)` pairs for every attribute in `value`
+/// matching `select`.
+fn collect_attributes(
+    block: &Block,
+    value: &Value,
+    pointer: String,
+    select: &dyn Fn(&Attribute) -> bool,
+    out: &mut Vec<(String, Value)>,
+) {
+    let Some(obj) = value.as_object() else {
+        return;
+    };
+
+    for (name, attr) in &block.attributes {
+        if !select(attr) {
+            continue;
+        }
+        if let Some(value) = obj.get(name) {
+            out.push((format!("{}/{}", pointer, escape(name)), value.clone()));
+        }
+    }
+
+    for (name, nested) in &block.blocks {
+        let Some(child) = obj.get(name) else {
+            continue;
+        };
+        let base = format!("{}/{}", pointer, escape(name));
+        match nested.nesting_mode {
+            BlockNestingMode::Single => collect_attributes(&nested.block, child, base, select, out),
+            BlockNestingMode::List | BlockNestingMode::Set => {
+                for (i, item) in child.as_array().into_iter().flatten().enumerate() {
+                    collect_attributes(&nested.block, item, format!("{}/{}", base, i), select, out);
+                }
+            },
+            BlockNestingMode::Map => {
+                for (key, item) in child.as_object().into_iter().flatten() {
+                    collect_attributes(
+                        &nested.block,
+                        item,
+                        format!("{}/{}", base, escape(key)),
+                        select,
+                        out,
+                    );
+                }
+            },
+        }
+    }
+}
+
+fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+/// Parse an RFC 3339 timestamp such as `2024-05-01T12:00:00.5+02:00`.
+fn parse_rfc3339(s: &str) -> Option<SystemTime> {
+    let (date, rest) = s.split_once(['T', 't', ' '])?;
+    let mut date_parts = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (
+        date_parts.next()?.ok()?,
+        date_parts.next()?.ok()?,
+        date_parts.next()?.ok()?,
+    );
+    let (time, offset_secs) = if let Some(time) = rest.strip_suffix(['Z', 'z']) {
+        (time, 0)
+    } else {
+        let i = rest.rfind(['+', '-'])?;
+        let (time, offset) = rest.split_at(i);
+        let (hours, minutes) = offset[1..].split_once(':')?;
+        let secs = hours.parse::<i64>().ok()? * 3_600 + minutes.parse::<i64>().ok()? * 60;
+        (time, if offset.starts_with('-') { -secs } else { secs })
+    };
+    let mut time_parts = time.splitn(3, ':');
+    let (hour, minute) = (
+        time_parts.next()?.parse::<i64>().ok()?,
+        time_parts.next()?.parse::<i64>().ok()?,
+    );
+    let seconds: f64 = time_parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    // Days since the epoch from a civil date (Howard Hinnant's algorithm)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86_400 + hour * 3_600 + minute * 60 - offset_secs;
+    let secs = u64::try_from(secs).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_secs_f64(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Attribute, NestedBlock};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingResolver(Arc<AtomicUsize>);
+
+    #[async_trait::async_trait]
+    impl SecretResolver for CountingResolver {
+        fn scheme(&self) -> &str {
+            "test"
+        }
+
+        async fn resolve(&self, location: &str) -> Result<String, ProviderError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(format!("secret-{}", location))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_config_nested_and_cached() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let resolvers = SecretResolvers::new().with_resolver(CountingResolver(calls.clone()));
+
+        let block = Block::new()
+            .with_attribute("token", Attribute::required_string().secret_ref())
+            .with_attribute("region", Attribute::optional_string())
+            .with_block(
+                "endpoint",
+                NestedBlock::list(
+                    Block::new().with_attribute("key", Attribute::optional_string().secret_ref()),
+                ),
+            );
+        let mut config = json!({
+            "token": "test:a",
+            "region": "test:not-a-ref",
+            "endpoint": [{"key": "test:a"}, {"key": "test:b"}]
+        });
+
+        resolvers.resolve_config(&block, &mut config).await.unwrap();
+
+        assert_eq!(config["token"], "secret-a");
+        assert_eq!(config["region"], "test:not-a-ref");
+        assert_eq!(config["endpoint"][0]["key"], "secret-a");
+        assert_eq!(config["endpoint"][1]["key"], "secret-b");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        assert_eq!(
+            resolvers.redact("bad token secret-b"),
+            format!("bad token {}", REDACTED)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_unknown_scheme() {
+        let resolvers = SecretResolvers::standard();
+        let block = Block::new().with_attribute("token", Attribute::required_string().secret_ref());
+        let mut config = json!({"token": "vault:kv/data/aws#key"});
+
+        let err = resolvers
+            .resolve_config(&block, &mut config)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("token"));
+        assert!(err.to_string().contains("vault"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exec_credential() {
+        let block = Block::new().with_attribute("auth", Attribute::exec_credential());
+        let mut config = json!({
+            "auth": {
+                "command": "sh",
+                "args": ["-c", r#"printf '{"token": "%s", "expires_at": "2999-01-01T00:00:00Z"}' "$TOKEN""#],
+                "env": {"TOKEN": "tok-123"},
+                "timeout": null,
+            }
+        });
+        let resolvers = SecretResolvers::new();
+        resolvers.resolve_config(&block, &mut config).await.unwrap();
+        assert_eq!(
+            config["auth"],
+            json!({"token": "tok-123", "expires_at": "2999-01-01T00:00:00Z"})
+        );
+        assert_eq!(
+            resolvers.redact("rejected tok-123"),
+            format!("rejected {}", REDACTED)
+        );
+
+        let err = resolvers
+            .resolve_config(
+                &block,
+                &mut json!({"auth": {"command": "sh", "args": ["-c", "exit 3"]}}),
+            )
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("auth: Credential command 'sh' exited"));
+
+        let spec = ExecCredentialSpec::new("sleep")
+            .with_args(["5"])
+            .with_timeout(Duration::from_millis(50));
+        assert!(matches!(
+            spec.run().await,
+            Err(ProviderError::DeadlineExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(UNIX_EPOCH));
+        assert_eq!(
+            parse_rfc3339("2000-02-29T14:34:56.5+02:00"),
+            Some(UNIX_EPOCH + Duration::from_millis(951_827_696_500))
+        );
+        assert_eq!(parse_rfc3339("2000-13-01T00:00:00Z"), None);
+        assert_eq!(parse_rfc3339("tomorrow"), None);
+    }
+
+    #[tokio::test]
+    async fn test_file_resolver_trims_newline() {
+        let path = std::env::temp_dir().join("hemmer_sdk_secret_test");
+        std::fs::write(&path, "hunter2\n").unwrap();
+
+        let value = FileResolver.resolve(path.to_str().unwrap()).await.unwrap();
+        assert_eq!(value, "hunter2");
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
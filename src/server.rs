@@ -6,36 +6,62 @@
 //! # Signal Handling
 //!
 //! The server automatically handles OS signals (SIGTERM, SIGINT) for graceful shutdown.
-//! When a signal is received, the server:
+//! A signal and the `Stop` RPC take the same path; when either arrives, the server:
 //! 1. Stops accepting new connections
-//! 2. Waits for in-flight requests to complete (with configurable timeout)
-//! 3. Calls the provider's `stop()` method
-//! 4. Exits cleanly
+//! 2. Cancels the [`Context`] of in-flight operations,
+//!    so they can observe [`Context::cancelled`](crate::context::Context::cancelled)
+//! 3. Waits for in-flight requests to complete (with configurable timeout)
+//! 4. Calls the provider's `stop()` method, once
+//! 5. Exits cleanly
+//!
+//! A second signal during shutdown exits the process immediately with status 130.
 
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use tokio::net::TcpListener;
 use tonic::transport::Server;
 use tracing::{debug, error, info, instrument, warn};
 
+use crate::blocking::BlockingBudget;
+use crate::cache::{ReadCache, ReadCoalescer, SessionCache};
+use crate::codec::StateCodec;
+use crate::context::{CancellationSource, Context, ProviderDataStore};
+use crate::diagnostics::{Diagnostics, DEFAULT_DIAGNOSTIC_LIMIT};
 use crate::error::ProviderError;
+use crate::events::{Event, EventSender};
+use crate::i18n::{error_message, sdk_diagnostic, Catalog};
+use crate::idempotency::{derive_token, IdempotencyStore};
+use crate::lock::{ParallelismLimits, ResourceLocks};
+use crate::logging::{try_init_logging_with_format, LogFormat};
+use crate::metrics::{MetricsSink, Outcome};
+use crate::payload::{decode_json, encode_json, PayloadLimits, PayloadMetrics};
+use crate::plan::plan_from_schema;
 use crate::schema::{Diagnostic, DiagnosticSeverity, ProviderSchema};
+use crate::secrets::SecretResolvers;
+use crate::store::LocalStore;
 use crate::types::{
-    ImportedResource, PlanResult, ProviderMetadata, HANDSHAKE_PREFIX, PROTOCOL_VERSION,
+    AttributeChange, ConfigureResult, CreateRequest, DeleteRequest, ImpactEstimate,
+    ImportedResource, PlanResult, ProviderMetadata, ReadDataSourceRequest, ReadRequest, ReadResult,
+    ServerCapabilities, UpdateRequest, HANDSHAKE_PREFIX, PROTOCOL_VERSION,
 };
+use prost::bytes::Bytes;
 
 /// Trait that provider implementations must implement.
 ///
 /// This provides a higher-level API than the raw gRPC trait, using
 /// ergonomic Rust types instead of protobuf types.
 ///
+/// `Arc<P>`, `Box<P>` (including `Box<dyn ProviderService>`) and `&'static P`
+/// implement it too, calling the provider's own methods, so routers and
+/// middleware can wrap providers they don't own.
+///
 /// # Example
 ///
 /// ```ignore
-/// use hemmer_provider_sdk::{ProviderService, ProviderError, PlanResult, ProviderSchema};
-/// use hemmer_provider_sdk::schema::{Schema, Attribute, Diagnostic};
+/// use hemmer_provider_sdk::{ConfigureResult, ProviderService, ProviderError, PlanResult, ProviderSchema};
+/// use hemmer_provider_sdk::schema::{Schema, Attribute};
 ///
 /// struct MyProvider;
 ///
@@ -47,8 +73,8 @@ use crate::types::{
 ///                 .with_attribute("name", Attribute::required_string()))
 ///     }
 ///
-///     async fn configure(&self, config: serde_json::Value) -> Result<Vec<Diagnostic>, ProviderError> {
-///         Ok(vec![])
+///     async fn configure(&self, config: serde_json::Value) -> Result<ConfigureResult, ProviderError> {
+///         Ok(ConfigureResult::new())
 ///     }
 ///
 ///     // ... implement other methods
@@ -61,19 +87,112 @@ pub trait ProviderService: Send + Sync + 'static {
     // =========================================================================
 
     /// Return the provider's schema including all resources and data sources.
+    ///
+    /// Providers whose schema can fail to build implement
+    /// [`try_schema`](Self::try_schema) as well, which the server and lint
+    /// call instead; this then only needs to return a fallback, such as an
+    /// empty schema.
     fn schema(&self) -> ProviderSchema;
 
+    /// Return the provider's schema, or diagnostics explaining why it can't
+    /// be built (e.g. plugin discovery or API introspection failed).
+    ///
+    /// `GetSchema` returns the diagnostics to core. By default, this is
+    /// [`schema`](Self::schema).
+    fn try_schema(&self) -> Result<ProviderSchema, Vec<Diagnostic>> {
+        Ok(self.schema())
+    }
+
+    /// Build the provider's schema asynchronously.
+    ///
+    /// The server calls this rather than [`try_schema`](Self::try_schema), so
+    /// providers whose schema comes from a remote descriptor (an OpenAPI
+    /// document fetched at startup, CRDs read from a cluster) can await it
+    /// instead of blocking. Such providers should keep the result and return
+    /// it from `try_schema` too, which the synchronous tooling (lint, testing)
+    /// still uses. By default, this is `try_schema`.
+    ///
+    /// ```rust,ignore
+    /// async fn schema_async(&self) -> Result<ProviderSchema, Vec<Diagnostic>> {
+    ///     let schema = self.loaded.get_or_try_init(|| self.fetch_openapi()).await?;
+    ///     Ok(schema.clone())
+    /// }
+    ///
+    /// fn try_schema(&self) -> Result<ProviderSchema, Vec<Diagnostic>> {
+    ///     self.loaded.get().cloned().ok_or_else(|| vec![Diagnostic::error("Schema not loaded")])
+    /// }
+    /// ```
+    async fn schema_async(&self) -> Result<ProviderSchema, Vec<Diagnostic>> {
+        self.try_schema()
+    }
+
+    /// Optional protocol features this provider supports, advertised to core
+    /// in `GetMetadata`.
+    ///
+    /// The default [`metadata`](Self::metadata) adds `import` when a resource
+    /// declares import ID formats and `functions` when the schema has
+    /// functions; everything else must be declared here.
+    ///
+    /// ```rust,ignore
+    /// fn capabilities(&self) -> ServerCapabilities {
+    ///     ServerCapabilities::new().with_plan_destroy().with_move_state()
+    /// }
+    /// ```
+    fn capabilities(&self) -> ServerCapabilities {
+        ServerCapabilities::default()
+    }
+
     /// Return provider metadata for performance optimization.
     /// By default, this is derived from the schema.
     fn metadata(&self) -> ProviderMetadata {
         let schema = self.schema();
+        let mut capabilities = self.capabilities();
+        capabilities.import |= schema
+            .resources
+            .values()
+            .any(|s| !s.import_formats.is_empty());
+        capabilities.functions |= !schema.functions.is_empty();
         ProviderMetadata {
             resources: schema.resources.keys().cloned().collect(),
             data_sources: schema.data_sources.keys().cloned().collect(),
-            capabilities: Default::default(),
+            capabilities,
+            import_formats: schema
+                .resources
+                .iter()
+                .filter(|(_, s)| !s.import_formats.is_empty())
+                .map(|(name, s)| (name.clone(), s.import_formats.clone()))
+                .collect(),
+            max_parallelism: schema
+                .resources
+                .iter()
+                .filter_map(|(name, s)| Some((name.clone(), s.max_parallelism?)))
+                .collect(),
+            refresh_intervals: schema
+                .resources
+                .iter()
+                .filter_map(|(name, s)| Some((name.clone(), s.refresh_interval_secs?)))
+                .collect(),
         }
     }
 
+    /// Return provider metadata, or diagnostics explaining why it can't be
+    /// determined.
+    ///
+    /// `GetMetadata` returns the diagnostics to core. By default, this is
+    /// [`metadata`](Self::metadata), or the diagnostics of
+    /// [`try_schema`](Self::try_schema) if it fails.
+    fn try_metadata(&self) -> Result<ProviderMetadata, Vec<Diagnostic>> {
+        self.try_schema()?;
+        Ok(self.metadata())
+    }
+
+    /// Distribution metadata for the provider registry (see
+    /// [`crate::manifest`]), included in the provider manifest. By default,
+    /// none is declared.
+    fn distribution(&self) -> Option<crate::manifest::Distribution> {
+        None
+    }
+
     // =========================================================================
     // Provider Lifecycle
     // =========================================================================
@@ -89,10 +208,31 @@ pub trait ProviderService: Send + Sync + 'static {
     }
 
     /// Configure the provider with credentials and settings.
-    /// Returns diagnostics (errors and warnings).
-    async fn configure(&self, config: serde_json::Value) -> Result<Vec<Diagnostic>, ProviderError>;
+    ///
+    /// Returns diagnostics (errors and warnings) and, optionally, provider data
+    /// (API clients, connection pools) that the SDK stores and makes available to
+    /// every later operation through [`Context::provider_data`](crate::context::Context::provider_data).
+    ///
+    /// Core may call this again on a running provider to pick up new settings
+    /// or rotated credentials. Calls are serialized, the previous provider data
+    /// is still in the [`Context`] (e.g. to reuse a connection pool), and it
+    /// stays in place if reconfiguring fails.
+    async fn configure(&self, config: serde_json::Value) -> Result<ConfigureResult, ProviderError>;
+
+    /// Run provider-specific health checks, such as whether credentials from
+    /// the environment are present or an API endpoint is reachable.
+    ///
+    /// Run by the devtool `doctor` command after the built-in checks in
+    /// [`crate::lint`]; not called by [`serve`]. Returned error diagnostics
+    /// make the check fail.
+    async fn self_check(&self) -> Result<Vec<Diagnostic>, ProviderError> {
+        Ok(vec![])
+    }
 
     /// Stop the provider gracefully.
+    ///
+    /// Called once per process, whether shutdown was requested by the `Stop`
+    /// RPC or by an OS signal; repeated `Stop` calls get the first result.
     async fn stop(&self) -> Result<(), ProviderError> {
         Ok(())
     }
@@ -124,13 +264,51 @@ pub trait ProviderService: Send + Sync + 'static {
     }
 
     /// Plan changes for a resource.
+    ///
+    /// The default plans from the resource's schema with
+    /// [`plan_from_schema`]: it validates the
+    /// config, applies defaults and plan modifiers, marks computed attributes
+    /// unknown, and plans a replacement when a `force_new` attribute changes.
     async fn plan(
         &self,
         resource_type: &str,
         prior_state: Option<serde_json::Value>,
         proposed_state: serde_json::Value,
         config: serde_json::Value,
-    ) -> Result<PlanResult, ProviderError>;
+    ) -> Result<PlanResult, ProviderError> {
+        default_plan(self, resource_type, prior_state, proposed_state, config).await
+    }
+
+    /// Estimate the impact of applying a planned change: monthly cost,
+    /// destructiveness and propagation delay.
+    ///
+    /// Called after each successful plan with the planned state, which is null
+    /// for a deletion. Core shows the estimate alongside the resource in plan
+    /// output. The default returns `None`, meaning no estimate.
+    async fn estimate(
+        &self,
+        resource_type: &str,
+        planned_state: &serde_json::Value,
+    ) -> Option<ImpactEstimate> {
+        let _ = (resource_type, planned_state);
+        None
+    }
+
+    /// Check an approved plan just before it is applied.
+    ///
+    /// Core calls this between plan approval and apply with the planned state,
+    /// which is null for a deletion. Return error diagnostics, scoped to an
+    /// attribute with [`Diagnostic::with_attribute`], to refuse the change
+    /// (e.g. a public bucket ACL); warnings are shown without blocking it. The
+    /// default allows every change.
+    async fn pre_apply_check(
+        &self,
+        resource_type: &str,
+        planned_state: &serde_json::Value,
+    ) -> Vec<Diagnostic> {
+        let _ = (resource_type, planned_state);
+        Vec::new()
+    }
 
     /// Create a new resource.
     async fn create(
@@ -139,6 +317,20 @@ pub trait ProviderService: Send + Sync + 'static {
         planned_state: serde_json::Value,
     ) -> Result<serde_json::Value, ProviderError>;
 
+    /// Create a new resource, given everything core sent for the create.
+    ///
+    /// The SDK always calls this method for creates. Override it when the
+    /// resource has write-only inputs that are in
+    /// [`config`](CreateRequest::config) but never in the planned state; the
+    /// default calls [`create`](Self::create).
+    async fn handle_create(
+        &self,
+        request: CreateRequest,
+    ) -> Result<serde_json::Value, ProviderError> {
+        self.create(&request.resource_type, request.planned_state)
+            .await
+    }
+
     /// Read the current state of a resource.
     async fn read(
         &self,
@@ -146,6 +338,19 @@ pub trait ProviderService: Send + Sync + 'static {
         current_state: serde_json::Value,
     ) -> Result<serde_json::Value, ProviderError>;
 
+    /// Read the current state of a resource, possibly best-effort.
+    ///
+    /// The SDK always calls this method for reads. Override it when the
+    /// backend can be partially unavailable: instead of failing the refresh,
+    /// return the last known state as [`ReadResult::stale`] or a partial one as
+    /// [`ReadResult::degraded`], with a warning. The default calls
+    /// [`read`](Self::read) and reports its result as current.
+    async fn handle_read(&self, request: ReadRequest) -> Result<ReadResult, ProviderError> {
+        self.read(&request.resource_type, request.current_state)
+            .await
+            .map(ReadResult::new)
+    }
+
     /// Update an existing resource.
     async fn update(
         &self,
@@ -154,6 +359,25 @@ pub trait ProviderService: Send + Sync + 'static {
         planned_state: serde_json::Value,
     ) -> Result<serde_json::Value, ProviderError>;
 
+    /// Update an existing resource, given everything core sent for the update.
+    ///
+    /// The SDK always calls this method for updates. Override it to translate
+    /// the [`patch`](UpdateRequest::patch) from prior to planned state directly
+    /// into PATCH-style API calls, or for write-only inputs as with
+    /// [`handle_create`](Self::handle_create); the default calls
+    /// [`update`](Self::update).
+    async fn handle_update(
+        &self,
+        request: UpdateRequest,
+    ) -> Result<serde_json::Value, ProviderError> {
+        self.update(
+            &request.resource_type,
+            request.prior_state,
+            request.planned_state,
+        )
+        .await
+    }
+
     /// Delete a resource.
     async fn delete(
         &self,
@@ -161,7 +385,18 @@ pub trait ProviderService: Send + Sync + 'static {
         current_state: serde_json::Value,
     ) -> Result<(), ProviderError>;
 
+    /// Delete a resource, given everything core sent for the delete.
+    ///
+    /// The SDK always calls this method for deletes; the default calls
+    /// [`delete`](Self::delete).
+    async fn handle_delete(&self, request: DeleteRequest) -> Result<(), ProviderError> {
+        self.delete(&request.resource_type, request.current_state)
+            .await
+    }
+
     /// Import existing infrastructure into management.
+    ///
+    /// Bulk imports by ID call this once per ID, several at a time.
     async fn import_resource(
         &self,
         resource_type: &str,
@@ -173,6 +408,23 @@ pub trait ProviderService: Send + Sync + 'static {
         )))
     }
 
+    /// Import every existing resource matching a provider-defined query, such
+    /// as `{"zone_id": "Z123"}` for all records in a DNS zone.
+    ///
+    /// Called by `BulkImportResourceState` requests that carry a query instead
+    /// of IDs. Listing APIs usually return whole resources, so one call can
+    /// replace hundreds of single imports.
+    async fn import_query(
+        &self,
+        resource_type: &str,
+        _query: serde_json::Value,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        Err(ProviderError::Sdk(format!(
+            "Import queries not supported for resource type: {}",
+            resource_type
+        )))
+    }
+
     // =========================================================================
     // Data Source Operations
     // =========================================================================
@@ -198,958 +450,5908 @@ pub trait ProviderService: Send + Sync + 'static {
             data_source_type
         )))
     }
+
+    /// Read data from an external source, possibly best-effort.
+    ///
+    /// Like [`handle_read`](Self::handle_read), for data sources. The default
+    /// calls [`read_data_source`](Self::read_data_source) and reports its
+    /// result as current.
+    async fn handle_read_data_source(
+        &self,
+        request: ReadDataSourceRequest,
+    ) -> Result<ReadResult, ProviderError> {
+        self.read_data_source(&request.data_source_type, request.config)
+            .await
+            .map(ReadResult::new)
+    }
 }
 
-/// Wrapper that implements the generated gRPC trait.
-struct ProviderGrpcService<P: ProviderService> {
-    provider: Arc<P>,
+/// Implement [`ProviderService`] for a pointer to a provider by calling the
+/// provider's own methods, overridden or not.
+macro_rules! forward_provider_service {
+    ($($pointer:ty),+) => {$(
+        #[async_trait::async_trait]
+        impl<P: ProviderService + ?Sized> ProviderService for $pointer {
+            fn schema(&self) -> ProviderSchema {
+                (**self).schema()
+            }
+
+            fn try_schema(&self) -> Result<ProviderSchema, Vec<Diagnostic>> {
+                (**self).try_schema()
+            }
+
+            async fn schema_async(&self) -> Result<ProviderSchema, Vec<Diagnostic>> {
+                (**self).schema_async().await
+            }
+
+            fn capabilities(&self) -> ServerCapabilities {
+                (**self).capabilities()
+            }
+
+            fn metadata(&self) -> ProviderMetadata {
+                (**self).metadata()
+            }
+
+            fn try_metadata(&self) -> Result<ProviderMetadata, Vec<Diagnostic>> {
+                (**self).try_metadata()
+            }
+
+            fn distribution(&self) -> Option<crate::manifest::Distribution> {
+                (**self).distribution()
+            }
+
+            async fn validate_provider_config(
+                &self,
+                config: serde_json::Value,
+            ) -> Result<Vec<Diagnostic>, ProviderError> {
+                (**self).validate_provider_config(config).await
+            }
+
+            async fn configure(
+                &self,
+                config: serde_json::Value,
+            ) -> Result<ConfigureResult, ProviderError> {
+                (**self).configure(config).await
+            }
+
+            async fn self_check(&self) -> Result<Vec<Diagnostic>, ProviderError> {
+                (**self).self_check().await
+            }
+
+            async fn stop(&self) -> Result<(), ProviderError> {
+                (**self).stop().await
+            }
+
+            async fn validate_resource_config(
+                &self,
+                resource_type: &str,
+                config: serde_json::Value,
+            ) -> Result<Vec<Diagnostic>, ProviderError> {
+                (**self).validate_resource_config(resource_type, config).await
+            }
+
+            async fn upgrade_resource_state(
+                &self,
+                resource_type: &str,
+                version: i64,
+                state: serde_json::Value,
+            ) -> Result<serde_json::Value, ProviderError> {
+                (**self)
+                    .upgrade_resource_state(resource_type, version, state)
+                    .await
+            }
+
+            async fn plan(
+                &self,
+                resource_type: &str,
+                prior_state: Option<serde_json::Value>,
+                proposed_state: serde_json::Value,
+                config: serde_json::Value,
+            ) -> Result<PlanResult, ProviderError> {
+                (**self)
+                    .plan(resource_type, prior_state, proposed_state, config)
+                    .await
+            }
+
+            async fn estimate(
+                &self,
+                resource_type: &str,
+                planned_state: &serde_json::Value,
+            ) -> Option<ImpactEstimate> {
+                (**self).estimate(resource_type, planned_state).await
+            }
+
+            async fn pre_apply_check(
+                &self,
+                resource_type: &str,
+                planned_state: &serde_json::Value,
+            ) -> Vec<Diagnostic> {
+                (**self).pre_apply_check(resource_type, planned_state).await
+            }
+
+            async fn create(
+                &self,
+                resource_type: &str,
+                planned_state: serde_json::Value,
+            ) -> Result<serde_json::Value, ProviderError> {
+                (**self).create(resource_type, planned_state).await
+            }
+
+            async fn handle_create(
+                &self,
+                request: CreateRequest,
+            ) -> Result<serde_json::Value, ProviderError> {
+                (**self).handle_create(request).await
+            }
+
+            async fn read(
+                &self,
+                resource_type: &str,
+                current_state: serde_json::Value,
+            ) -> Result<serde_json::Value, ProviderError> {
+                (**self).read(resource_type, current_state).await
+            }
+
+            async fn handle_read(&self, request: ReadRequest) -> Result<ReadResult, ProviderError> {
+                (**self).handle_read(request).await
+            }
+
+            async fn update(
+                &self,
+                resource_type: &str,
+                prior_state: serde_json::Value,
+                planned_state: serde_json::Value,
+            ) -> Result<serde_json::Value, ProviderError> {
+                (**self).update(resource_type, prior_state, planned_state).await
+            }
+
+            async fn handle_update(
+                &self,
+                request: UpdateRequest,
+            ) -> Result<serde_json::Value, ProviderError> {
+                (**self).handle_update(request).await
+            }
+
+            async fn delete(
+                &self,
+                resource_type: &str,
+                current_state: serde_json::Value,
+            ) -> Result<(), ProviderError> {
+                (**self).delete(resource_type, current_state).await
+            }
+
+            async fn handle_delete(&self, request: DeleteRequest) -> Result<(), ProviderError> {
+                (**self).handle_delete(request).await
+            }
+
+            async fn import_resource(
+                &self,
+                resource_type: &str,
+                id: &str,
+            ) -> Result<Vec<ImportedResource>, ProviderError> {
+                (**self).import_resource(resource_type, id).await
+            }
+
+            async fn import_query(
+                &self,
+                resource_type: &str,
+                query: serde_json::Value,
+            ) -> Result<Vec<ImportedResource>, ProviderError> {
+                (**self).import_query(resource_type, query).await
+            }
+
+            async fn validate_data_source_config(
+                &self,
+                data_source_type: &str,
+                config: serde_json::Value,
+            ) -> Result<Vec<Diagnostic>, ProviderError> {
+                (**self)
+                    .validate_data_source_config(data_source_type, config)
+                    .await
+            }
+
+            async fn read_data_source(
+                &self,
+                data_source_type: &str,
+                config: serde_json::Value,
+            ) -> Result<serde_json::Value, ProviderError> {
+                (**self).read_data_source(data_source_type, config).await
+            }
+
+            async fn handle_read_data_source(
+                &self,
+                request: ReadDataSourceRequest,
+            ) -> Result<ReadResult, ProviderError> {
+                (**self).handle_read_data_source(request).await
+            }
+        }
+    )+};
 }
 
-impl<P: ProviderService> ProviderGrpcService<P> {
-    fn diagnostics_to_proto(
-        &self,
-        diagnostics: Vec<Diagnostic>,
-    ) -> Vec<crate::generated::Diagnostic> {
-        diagnostics
-            .into_iter()
-            .map(|d| crate::generated::Diagnostic {
-                severity: match d.severity {
-                    DiagnosticSeverity::Error => {
-                        crate::generated::diagnostic::Severity::Error as i32
-                    },
-                    DiagnosticSeverity::Warning => {
-                        crate::generated::diagnostic::Severity::Warning as i32
-                    },
-                },
-                summary: d.summary,
-                detail: d.detail.unwrap_or_default(),
-                attribute: d.attribute.unwrap_or_default(),
-            })
-            .collect()
-    }
+forward_provider_service!(Arc<P>, Box<P>, &'static P);
 
-    fn error_to_diagnostics(&self, err: ProviderError) -> Vec<crate::generated::Diagnostic> {
-        vec![crate::generated::Diagnostic {
-            severity: crate::generated::diagnostic::Severity::Error as i32,
-            summary: err.to_string(),
-            detail: String::new(),
-            attribute: String::new(),
-        }]
+/// Shutdown state shared by the `Stop` RPC, signal handling and the server loop.
+#[derive(Clone, Default)]
+struct Shutdown {
+    cancellation: CancellationSource,
+    stopped: Arc<tokio::sync::OnceCell<Result<(), String>>>,
+}
+
+impl Shutdown {
+    /// Request shutdown. Returns `false` if it was already requested.
+    fn trigger(&self) -> bool {
+        self.cancellation.cancel()
     }
 
-    fn schema_to_proto(&self, schema: &crate::schema::Schema) -> crate::generated::Schema {
-        crate::generated::Schema {
-            version: schema.version as i64,
-            block: Some(block_to_proto(&schema.block)),
-        }
+    /// Wait until shutdown is requested.
+    async fn triggered(&self) {
+        self.cancellation.token().cancelled().await
     }
-}
 
-fn block_to_proto(block: &crate::schema::Block) -> crate::generated::Block {
-    crate::generated::Block {
-        attributes: block
-            .attributes
-            .iter()
-            .map(|(name, attr)| crate::generated::Attribute {
-                name: name.clone(),
-                r#type: serde_json::to_vec(&attr.attr_type).unwrap_or_default(),
-                required: attr.flags.required,
-                optional: attr.flags.optional,
-                computed: attr.flags.computed,
-                sensitive: attr.flags.sensitive,
-                description: attr.description.clone().unwrap_or_default(),
-                force_new: attr.force_new,
-                default_value: attr
-                    .default
-                    .as_ref()
-                    .map(|v| serde_json::to_vec(v).unwrap_or_default())
-                    .unwrap_or_default(),
-            })
-            .collect(),
-        block_types: block
-            .blocks
-            .iter()
-            .map(|(name, nested)| crate::generated::NestedBlock {
-                type_name: name.clone(),
-                block: Some(block_to_proto(&nested.block)),
-                nesting_mode: match nested.nesting_mode {
-                    crate::schema::BlockNestingMode::Single => {
-                        crate::generated::nested_block::NestingMode::Single as i32
-                    },
-                    crate::schema::BlockNestingMode::List => {
-                        crate::generated::nested_block::NestingMode::List as i32
-                    },
-                    crate::schema::BlockNestingMode::Set => {
-                        crate::generated::nested_block::NestingMode::Set as i32
-                    },
-                    crate::schema::BlockNestingMode::Map => {
-                        crate::generated::nested_block::NestingMode::Map as i32
-                    },
-                },
-                min_items: nested.min_items as i32,
-                max_items: nested.max_items as i32,
+    /// Call the provider's `stop()` and tear down its provider data the first
+    /// time; later calls get the `stop()` result.
+    async fn stop_provider<P: ProviderService>(
+        &self,
+        provider: &P,
+        provider_data: &ProviderDataStore,
+        local_store: &LocalStore,
+    ) -> Result<(), String> {
+        self.stopped
+            .get_or_init(|| async {
+                debug!("Calling provider stop()");
+                let result = provider.stop().await.map_err(|e| e.to_string());
+                provider_data.teardown().await;
+                if let Err(e) = local_store.clear() {
+                    warn!(error = %e, "Failed to clear the local store");
+                }
+                result
             })
-            .collect(),
-        description: block.description.clone().unwrap_or_default(),
+            .await
+            .clone()
     }
 }
 
-#[tonic::async_trait]
-impl<P: ProviderService> crate::generated::provider_server::Provider for ProviderGrpcService<P> {
-    #[instrument(skip(self, _request), name = "grpc.get_metadata")]
-    async fn get_metadata(
+/// The RPC name bulk imports are recorded under.
+const BULK_IMPORT: &str = "bulk_import_resource_state";
+
+/// How many IDs of a bulk import are imported at once, before the limits in
+/// [`ProviderMetadata::max_parallelism`].
+const BULK_IMPORT_CONCURRENCY: usize = 16;
+
+/// Everything an import needs from the server, owned, so bulk imports can
+/// keep importing while their results stream back.
+struct Importer<P: ProviderService> {
+    provider: Arc<P>,
+    schema: Arc<ProviderSchema>,
+    context: Context,
+    locks: ResourceLocks,
+    parallelism: Option<ParallelismLimits>,
+    state_codec: Option<Arc<dyn StateCodec>>,
+    metrics_sink: Option<Arc<dyn MetricsSink>>,
+    catalog: Option<Catalog>,
+    payload_limits: PayloadLimits,
+    payload_metrics: PayloadMetrics,
+}
+
+impl<P: ProviderService> Importer<P> {
+    /// Import the resource with `id`, as `ImportResourceState` does.
+    async fn import(
         &self,
-        _request: tonic::Request<crate::generated::GetMetadataRequest>,
-    ) -> Result<tonic::Response<crate::generated::GetMetadataResponse>, tonic::Status> {
-        debug!("GetMetadata called");
-        let metadata = self.provider.metadata();
-        info!(
-            resources = metadata.resources.len(),
-            data_sources = metadata.data_sources.len(),
-            "GetMetadata completed"
-        );
-        Ok(tonic::Response::new(
-            crate::generated::GetMetadataResponse {
-                server_capabilities: Some(crate::generated::ServerCapabilities {
-                    plan_destroy: metadata.capabilities.plan_destroy,
-                }),
-                resources: metadata.resources,
-                data_sources: metadata.data_sources,
-                diagnostics: vec![],
-            },
-        ))
+        resource_type: &str,
+        id: &str,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        let sink = self.metrics_sink.as_deref();
+        let import = async {
+            // Reject malformed IDs with the expected formats before the provider sees them
+            if let Some(schema) = self.schema.resources.get(resource_type) {
+                schema.parse_import_id(id)?;
+            }
+            let _guard = self.locks.lock(resource_type, id).await;
+            let _permit = self.permit(resource_type).await;
+            let imported = self.provider.import_resource(resource_type, id).await?;
+            self.prepare(imported).await
+        };
+        self.context
+            .clone()
+            .scope(measure(
+                sink,
+                "import_resource_state",
+                Some(resource_type),
+                import,
+            ))
+            .await
     }
 
-    #[instrument(skip(self, request), name = "grpc.get_schema")]
-    async fn get_schema(
+    /// Import every resource matching `query`.
+    async fn query(
         &self,
-        request: tonic::Request<crate::generated::GetSchemaRequest>,
-    ) -> Result<tonic::Response<crate::generated::GetSchemaResponse>, tonic::Status> {
-        debug!("GetSchema called");
-
-        // Validate client protocol version
-        let client_version = request.get_ref().client_protocol_version;
-        crate::check_protocol_version(client_version)
-            .map_err(tonic::Status::failed_precondition)?;
-
-        debug!(
-            client_version = client_version,
-            server_version = crate::PROTOCOL_VERSION,
-            "Protocol version negotiation complete"
-        );
+        resource_type: &str,
+        query: serde_json::Value,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        let sink = self.metrics_sink.as_deref();
+        let import = async {
+            let _permit = self.permit(resource_type).await;
+            let imported = self.provider.import_query(resource_type, query).await?;
+            self.prepare(imported).await
+        };
+        self.context
+            .clone()
+            .scope(measure(sink, BULK_IMPORT, Some(resource_type), import))
+            .await
+    }
 
-        let schema = self.provider.schema();
-        info!(
-            resources = schema.resources.len(),
-            data_sources = schema.data_sources.len(),
-            "GetSchema completed"
-        );
-        Ok(tonic::Response::new(crate::generated::GetSchemaResponse {
-            server_protocol_version: crate::PROTOCOL_VERSION,
-            provider: Some(self.schema_to_proto(&schema.provider)),
-            resources: schema
-                .resources
-                .iter()
-                .map(|(k, v)| (k.clone(), self.schema_to_proto(v)))
-                .collect(),
-            data_sources: schema
-                .data_sources
-                .iter()
-                .map(|(k, v)| (k.clone(), self.schema_to_proto(v)))
-                .collect(),
-            diagnostics: vec![],
-        }))
+    async fn permit(&self, resource_type: &str) -> Option<tokio::sync::OwnedSemaphorePermit> {
+        self.parallelism.as_ref()?.acquire(resource_type).await
     }
 
-    #[instrument(skip(self, request), name = "grpc.validate_provider_config")]
-    async fn validate_provider_config(
+    /// Normalize imported states and encode them with the [`StateCodec`].
+    async fn prepare(
         &self,
-        request: tonic::Request<crate::generated::ValidateProviderConfigRequest>,
-    ) -> Result<tonic::Response<crate::generated::ValidateProviderConfigResponse>, tonic::Status>
-    {
-        debug!("ValidateProviderConfig called");
-        let req = request.into_inner();
-        let config = serde_json::from_slice(&req.config).unwrap_or(serde_json::Value::Null);
-
-        match self.provider.validate_provider_config(config).await {
-            Ok(diagnostics) => {
-                let has_errors = diagnostics
-                    .iter()
-                    .any(|d| matches!(d.severity, DiagnosticSeverity::Error));
-                if has_errors {
-                    warn!(
-                        diagnostics = diagnostics.len(),
-                        "ValidateProviderConfig completed with errors"
-                    );
-                } else {
-                    info!("ValidateProviderConfig completed successfully");
+        mut imported: Vec<ImportedResource>,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        for resource in &mut imported {
+            if let Some(schema) = self.schema.resources.get(&resource.resource_type) {
+                crate::normalize::normalize(schema, &mut resource.state);
+            }
+            if let Some(codec) = &self.state_codec {
+                if !resource.state.is_null() {
+                    let state = std::mem::take(&mut resource.state);
+                    resource.state = codec.encode(&resource.resource_type, state).await?;
                 }
-                Ok(tonic::Response::new(
-                    crate::generated::ValidateProviderConfigResponse {
-                        diagnostics: self.diagnostics_to_proto(diagnostics),
-                    },
-                ))
-            },
-            Err(e) => {
-                error!(error = %e, "ValidateProviderConfig failed");
-                Ok(tonic::Response::new(
-                    crate::generated::ValidateProviderConfigResponse {
-                        diagnostics: self.error_to_diagnostics(e),
-                    },
-                ))
-            },
+            }
         }
+        Ok(imported)
     }
 
-    #[instrument(skip(self, request), name = "grpc.configure")]
-    async fn configure(
+    /// The streamed result of importing `id`, checked against the payload limits.
+    fn result(
         &self,
-        request: tonic::Request<crate::generated::ConfigureRequest>,
-    ) -> Result<tonic::Response<crate::generated::ConfigureResponse>, tonic::Status> {
-        debug!("Configure called");
-        let req = request.into_inner();
-        let config = serde_json::from_slice(&req.config).unwrap_or(serde_json::Value::Null);
-
-        match self.provider.configure(config).await {
-            Ok(diagnostics) => {
-                let has_errors = diagnostics
-                    .iter()
-                    .any(|d| matches!(d.severity, DiagnosticSeverity::Error));
-                if has_errors {
-                    warn!(
-                        diagnostics = diagnostics.len(),
-                        "Configure completed with errors"
-                    );
-                } else {
-                    info!("Configure completed successfully");
-                }
-                Ok(tonic::Response::new(crate::generated::ConfigureResponse {
-                    diagnostics: self.diagnostics_to_proto(diagnostics),
-                }))
-            },
+        id: String,
+        imported: Result<Vec<ImportedResource>, ProviderError>,
+    ) -> crate::generated::BulkImportResult {
+        let imported = match imported {
+            Ok(imported) => imported,
             Err(e) => {
-                error!(error = %e, "Configure failed");
-                Ok(tonic::Response::new(crate::generated::ConfigureResponse {
-                    diagnostics: self.error_to_diagnostics(e),
-                }))
+                warn!(id = %id, error = %e, "Bulk import failed for ID");
+                return crate::generated::BulkImportResult {
+                    id,
+                    imported: vec![],
+                    diagnostics: error_to_diagnostics(self.catalog.as_ref(), e),
+                };
             },
+        };
+        let imported: Vec<_> = imported
+            .into_iter()
+            .map(|r| crate::generated::ImportedResource {
+                resource_type: r.resource_type,
+                state: encode_json(&r.state),
+            })
+            .collect();
+        let mut diagnostics = Vec::new();
+        let total = imported.iter().map(|r| r.state.len()).sum();
+        self.payload_metrics.record_response(BULK_IMPORT, total);
+        for resource in &imported {
+            match self
+                .payload_limits
+                .check(BULK_IMPORT, "state", resource.state.len())
+            {
+                Ok(warning) => diagnostics.extend(warning),
+                Err(e) => diagnostics.push(Diagnostic::error(e.to_string())),
+            }
+        }
+        crate::generated::BulkImportResult {
+            id,
+            imported,
+            diagnostics: diagnostics_to_proto(self.catalog.as_ref(), diagnostics),
         }
     }
+}
 
-    #[instrument(skip(self, _request), name = "grpc.stop")]
-    async fn stop(
+/// The provider schema, loaded on first use and kept until it is
+/// invalidated, so an RPC doesn't build it again for every step.
+#[derive(Default)]
+struct SchemaCache {
+    /// The cached schema, and how many times it has been invalidated.
+    inner: std::sync::Mutex<(Option<Arc<ProviderSchema>>, u64)>,
+}
+
+impl SchemaCache {
+    /// The cached schema, loading it with `schema_async` if there is none.
+    ///
+    /// Failures are not cached, so the next call tries again.
+    async fn get<P: ProviderService>(
         &self,
-        _request: tonic::Request<crate::generated::StopRequest>,
-    ) -> Result<tonic::Response<crate::generated::StopResponse>, tonic::Status> {
-        info!("Stop called");
-        match self.provider.stop().await {
-            Ok(()) => {
-                info!("Stop completed successfully");
-                Ok(tonic::Response::new(crate::generated::StopResponse {
-                    error: String::new(),
-                }))
-            },
-            Err(e) => {
-                error!(error = %e, "Stop failed");
-                Ok(tonic::Response::new(crate::generated::StopResponse {
-                    error: e.to_string(),
-                }))
-            },
+        provider: &P,
+    ) -> Result<Arc<ProviderSchema>, Vec<Diagnostic>> {
+        let generation = {
+            let inner = self.inner.lock().unwrap();
+            if let Some(schema) = &inner.0 {
+                return Ok(Arc::clone(schema));
+            }
+            inner.1
+        };
+        let schema = Arc::new(provider.schema_async().await?);
+        let mut inner = self.inner.lock().unwrap();
+        // Don't keep a schema loaded before an invalidation
+        if inner.1 == generation {
+            inner.0 = Some(Arc::clone(&schema));
         }
+        Ok(schema)
     }
 
-    #[instrument(skip(self, request), name = "grpc.validate_resource_config")]
-    async fn validate_resource_config(
-        &self,
-        request: tonic::Request<crate::generated::ValidateResourceConfigRequest>,
-    ) -> Result<tonic::Response<crate::generated::ValidateResourceConfigResponse>, tonic::Status>
-    {
-        let req = request.into_inner();
-        debug!(resource_type = %req.resource_type, "ValidateResourceConfig called");
-        let config = serde_json::from_slice(&req.config).unwrap_or(serde_json::Value::Null);
+    /// The cached schema, if it has been loaded.
+    fn cached(&self) -> Option<Arc<ProviderSchema>> {
+        self.inner.lock().unwrap().0.clone()
+    }
 
-        match self
-            .provider
-            .validate_resource_config(&req.resource_type, config)
-            .await
-        {
-            Ok(diagnostics) => {
-                let has_errors = diagnostics
-                    .iter()
-                    .any(|d| matches!(d.severity, DiagnosticSeverity::Error));
-                if has_errors {
-                    warn!(resource_type = %req.resource_type, diagnostics = diagnostics.len(), "ValidateResourceConfig completed with errors");
-                } else {
-                    info!(resource_type = %req.resource_type, "ValidateResourceConfig completed successfully");
-                }
-                Ok(tonic::Response::new(
-                    crate::generated::ValidateResourceConfigResponse {
-                        diagnostics: self.diagnostics_to_proto(diagnostics),
-                    },
-                ))
-            },
-            Err(e) => {
-                error!(resource_type = %req.resource_type, error = %e, "ValidateResourceConfig failed");
-                Ok(tonic::Response::new(
-                    crate::generated::ValidateResourceConfigResponse {
-                        diagnostics: self.error_to_diagnostics(e),
-                    },
-                ))
-            },
-        }
+    /// Drop the cached schema, so the next use loads it again.
+    fn invalidate(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.0 = None;
+        inner.1 += 1;
     }
+}
 
-    #[instrument(skip(self, request), name = "grpc.upgrade_resource_state")]
-    async fn upgrade_resource_state(
-        &self,
-        request: tonic::Request<crate::generated::UpgradeResourceStateRequest>,
-    ) -> Result<tonic::Response<crate::generated::UpgradeResourceStateResponse>, tonic::Status>
-    {
-        let req = request.into_inner();
-        debug!(resource_type = %req.resource_type, version = req.version, "UpgradeResourceState called");
-        let state = serde_json::from_slice(&req.raw_state).unwrap_or(serde_json::Value::Null);
+/// Diagnostics the SDK itself adds to a response.
+#[derive(Default)]
+struct ResponseNotes {
+    /// Internal anomalies, reported only with [`ServeOptions::internal_warnings`].
+    internal: Vec<Diagnostic>,
+    /// Payload limit diagnostics, always reported.
+    limits: Vec<Diagnostic>,
+    /// Returned state validation diagnostics, always reported.
+    state: Vec<Diagnostic>,
+}
 
-        match self
-            .provider
-            .upgrade_resource_state(&req.resource_type, req.version, state)
-            .await
-        {
-            Ok(upgraded) => {
-                info!(resource_type = %req.resource_type, from_version = req.version, "UpgradeResourceState completed");
-                Ok(tonic::Response::new(
-                    crate::generated::UpgradeResourceStateResponse {
-                        upgraded_state: serde_json::to_vec(&upgraded).unwrap_or_default(),
-                        diagnostics: vec![],
-                    },
-                ))
-            },
-            Err(e) => {
-                error!(resource_type = %req.resource_type, version = req.version, error = %e, "UpgradeResourceState failed");
-                Ok(tonic::Response::new(
-                    crate::generated::UpgradeResourceStateResponse {
-                        upgraded_state: vec![],
-                        diagnostics: self.error_to_diagnostics(e),
-                    },
-                ))
-            },
+/// Wrapper that implements the generated gRPC trait.
+pub(crate) struct ProviderGrpcService<P: ProviderService> {
+    provider: Arc<P>,
+    schema: SchemaCache,
+    secrets: SecretResolvers,
+    provider_data: ProviderDataStore,
+    /// Serializes `Configure` calls, so a slow reconfigure can't overwrite a
+    /// newer one's provider data.
+    configure_lock: tokio::sync::Mutex<()>,
+    state_codec: Option<Arc<dyn StateCodec>>,
+    locks: ResourceLocks,
+    /// Built from the provider metadata on first use.
+    parallelism: tokio::sync::OnceCell<ParallelismLimits>,
+    /// Built from the provider metadata on first use.
+    read_cache: tokio::sync::OnceCell<ReadCache<ReadResult>>,
+    coalescer: Option<ReadCoalescer<ReadResult>>,
+    session_cache: Option<SessionCache<ReadResult>>,
+    idempotency: IdempotencyStore,
+    events: EventSender,
+    shutdown: Shutdown,
+    internal_warnings: bool,
+    validate_provider_config: bool,
+    validate_resource_config: bool,
+    validate_state: bool,
+    payload_limits: PayloadLimits,
+    payload_metrics: PayloadMetrics,
+    metrics_sink: Option<Arc<dyn MetricsSink>>,
+    catalog: Option<Catalog>,
+    blocking_budget: BlockingBudget,
+    local_store: LocalStore,
+    /// Keep decimals in request payloads as written, instead of rounding
+    /// them to `f64`.
+    #[cfg(feature = "arbitrary-precision")]
+    exact_numbers: bool,
+}
+
+impl<P: ProviderService> ProviderGrpcService<P> {
+    pub(crate) fn new(provider: Arc<P>) -> Self {
+        Self {
+            provider,
+            schema: SchemaCache::default(),
+            secrets: SecretResolvers::new(),
+            provider_data: ProviderDataStore::new(),
+            configure_lock: tokio::sync::Mutex::new(()),
+            state_codec: None,
+            locks: ResourceLocks::new(),
+            parallelism: tokio::sync::OnceCell::new(),
+            read_cache: tokio::sync::OnceCell::new(),
+            coalescer: None,
+            session_cache: None,
+            idempotency: IdempotencyStore::new(DEFAULT_IDEMPOTENCY_TTL),
+            events: EventSender::default(),
+            shutdown: Shutdown::default(),
+            internal_warnings: false,
+            validate_provider_config: true,
+            validate_resource_config: false,
+            validate_state: false,
+            payload_limits: PayloadLimits::new(),
+            payload_metrics: PayloadMetrics::new(),
+            metrics_sink: None,
+            catalog: None,
+            blocking_budget: BlockingBudget::default(),
+            local_store: LocalStore::memory(),
+            #[cfg(feature = "arbitrary-precision")]
+            exact_numbers: false,
         }
     }
 
-    #[instrument(skip(self, request), name = "grpc.plan")]
-    async fn plan(
-        &self,
-        request: tonic::Request<crate::generated::PlanRequest>,
-    ) -> Result<tonic::Response<crate::generated::PlanResponse>, tonic::Status> {
-        let req = request.into_inner();
-        let is_create = req.prior_state.is_empty();
-        debug!(resource_type = %req.resource_type, is_create = is_create, "Plan called");
-
-        let prior_state = if req.prior_state.is_empty() {
-            None
-        } else {
-            serde_json::from_slice(&req.prior_state).ok()
-        };
+    /// Share the provider data set by `configure` with another front end.
+    #[cfg(feature = "gateway")]
+    pub(crate) fn with_provider_data(mut self, provider_data: ProviderDataStore) -> Self {
+        self.provider_data = provider_data;
+        self
+    }
 
-        let proposed_state =
-            serde_json::from_slice(&req.proposed_state).unwrap_or(serde_json::Value::Null);
-        let config = serde_json::from_slice(&req.config).unwrap_or(serde_json::Value::Null);
+    /// The operation [`Context`], cancelled when the server shuts down.
+    fn context(&self) -> Context {
+        let context = self
+            .provider_data
+            .context()
+            .with_cancellation(self.shutdown.cancellation.token())
+            .with_events(self.events.clone())
+            .with_blocking_budget(self.blocking_budget.clone())
+            .with_local_store(self.local_store.clone());
+        match self.schema.cached() {
+            Some(schema) => context.with_schema(schema),
+            None => context,
+        }
+    }
 
-        match self
-            .provider
-            .plan(&req.resource_type, prior_state, proposed_state, config)
-            .await
-        {
-            Ok(result) => {
-                info!(
-                    resource_type = %req.resource_type,
-                    changes = result.changes.len(),
-                    requires_replace = result.requires_replace,
-                    "Plan completed"
-                );
-                Ok(tonic::Response::new(crate::generated::PlanResponse {
-                    planned_state: serde_json::to_vec(&result.planned_state).unwrap_or_default(),
-                    changes: result.changes.into_iter().map(Into::into).collect(),
-                    requires_replace: result.requires_replace,
-                    diagnostics: vec![],
-                }))
-            },
-            Err(e) => {
-                error!(resource_type = %req.resource_type, error = %e, "Plan failed");
-                Ok(tonic::Response::new(crate::generated::PlanResponse {
-                    planned_state: vec![],
-                    changes: vec![],
-                    requires_replace: false,
-                    diagnostics: self.error_to_diagnostics(e),
-                }))
-            },
+    /// The operation [`Context`] of a request carrying the client's
+    /// capabilities and session id.
+    fn request_context(
+        &self,
+        capabilities: Option<crate::generated::ClientCapabilities>,
+        session: Option<String>,
+    ) -> Context {
+        let context = self
+            .context()
+            .with_client_capabilities(capabilities.map(Into::into).unwrap_or_default());
+        match session {
+            Some(session) => context.with_session_id(session),
+            None => context,
         }
     }
 
-    #[instrument(skip(self, request), name = "grpc.create")]
-    async fn create(
+    /// Run a create or update, deduplicated by its idempotency token if core
+    /// sent an idempotency key. Calls without one are never deduplicated.
+    async fn deduplicated<F>(
         &self,
-        request: tonic::Request<crate::generated::CreateRequest>,
-    ) -> Result<tonic::Response<crate::generated::CreateResponse>, tonic::Status> {
-        let req = request.into_inner();
-        info!(resource_type = %req.resource_type, "Create called");
-        let planned_state =
-            serde_json::from_slice(&req.planned_state).unwrap_or(serde_json::Value::Null);
+        resource_type: &str,
+        token: Option<&str>,
+        operation: F,
+    ) -> Result<serde_json::Value, ProviderError>
+    where
+        F: std::future::Future<Output = Result<serde_json::Value, ProviderError>>,
+    {
+        match token {
+            Some(token) => self.idempotency.run(resource_type, token, operation).await,
+            None => operation.await,
+        }
+    }
 
-        match self
-            .provider
-            .create(&req.resource_type, planned_state)
-            .await
-        {
-            Ok(state) => {
-                info!(resource_type = %req.resource_type, "Create completed successfully");
-                Ok(tonic::Response::new(crate::generated::CreateResponse {
-                    state: serde_json::to_vec(&state).unwrap_or_default(),
-                    diagnostics: vec![],
-                }))
-            },
-            Err(e) => {
-                error!(resource_type = %req.resource_type, error = %e, "Create failed");
-                Ok(tonic::Response::new(crate::generated::CreateResponse {
-                    state: vec![],
-                    diagnostics: self.error_to_diagnostics(e),
-                }))
-            },
+    /// Forget remembered create and update results describing a deleted
+    /// resource, so a call with the same idempotency key runs again.
+    fn forget_results(&self, resource_type: &str, deleted: &serde_json::Value) {
+        let identity = self.locks.identity(resource_type, deleted);
+        self.idempotency
+            .invalidate_resource(resource_type, |result| match &identity {
+                Some(identity) => {
+                    self.locks.identity(resource_type, result).as_ref() == Some(identity)
+                },
+                None => crate::state::canonical_eq(result, deleted),
+            });
+    }
+
+    /// Drop session-cached reads that a write to a resource may have made
+    /// stale: the resource's `prior_state` and every data source.
+    fn invalidate_session_reads(
+        &self,
+        resource_type: &str,
+        prior_state: Option<&serde_json::Value>,
+    ) {
+        if let Some(cache) = &self.session_cache {
+            if let Some(state) = prior_state {
+                cache.invalidate(resource_type, state);
+            }
+            cache.invalidate_data_sources();
         }
     }
 
-    #[instrument(skip(self, request), name = "grpc.read")]
-    async fn read(
+    /// Wait until an operation on `resource_type` may run under the limits in
+    /// [`ProviderMetadata::max_parallelism`]. Holding the permit uses a slot.
+    async fn parallelism_permit(
         &self,
-        request: tonic::Request<crate::generated::ReadRequest>,
-    ) -> Result<tonic::Response<crate::generated::ReadResponse>, tonic::Status> {
-        let req = request.into_inner();
-        debug!(resource_type = %req.resource_type, "Read called");
-        let current_state =
-            serde_json::from_slice(&req.current_state).unwrap_or(serde_json::Value::Null);
+        resource_type: &str,
+    ) -> Option<tokio::sync::OwnedSemaphorePermit> {
+        self.parallelism_limits()
+            .await?
+            .acquire(resource_type)
+            .await
+    }
 
-        match self.provider.read(&req.resource_type, current_state).await {
-            Ok(state) => {
-                debug!(resource_type = %req.resource_type, "Read completed successfully");
-                Ok(tonic::Response::new(crate::generated::ReadResponse {
-                    state: serde_json::to_vec(&state).unwrap_or_default(),
-                    diagnostics: vec![],
-                }))
-            },
-            Err(e) => {
-                error!(resource_type = %req.resource_type, error = %e, "Read failed");
-                Ok(tonic::Response::new(crate::generated::ReadResponse {
-                    state: vec![],
-                    diagnostics: self.error_to_diagnostics(e),
-                }))
-            },
+    /// An [`Importer`] running in `context`.
+    async fn importer(&self, context: Context) -> Importer<P> {
+        Importer {
+            provider: Arc::clone(&self.provider),
+            schema: self.current_schema().await.unwrap_or_default(),
+            context,
+            locks: self.locks.clone(),
+            parallelism: self.parallelism_limits().await.cloned(),
+            state_codec: self.state_codec.clone(),
+            metrics_sink: self.metrics_sink.clone(),
+            catalog: self.catalog.clone(),
+            payload_limits: self.payload_limits,
+            payload_metrics: self.payload_metrics.clone(),
         }
     }
 
-    #[instrument(skip(self, request), name = "grpc.update")]
-    async fn update(
-        &self,
-        request: tonic::Request<crate::generated::UpdateRequest>,
-    ) -> Result<tonic::Response<crate::generated::UpdateResponse>, tonic::Status> {
-        let req = request.into_inner();
-        info!(resource_type = %req.resource_type, "Update called");
-        let prior_state =
-            serde_json::from_slice(&req.prior_state).unwrap_or(serde_json::Value::Null);
-        let planned_state =
-            serde_json::from_slice(&req.planned_state).unwrap_or(serde_json::Value::Null);
+    /// The limits in [`ProviderMetadata::max_parallelism`], once the metadata
+    /// is available.
+    async fn parallelism_limits(&self) -> Option<&ParallelismLimits> {
+        self.parallelism
+            .get_or_try_init(|| async {
+                let metadata = self.current_metadata().await?;
+                Ok::<_, Vec<Diagnostic>>(ParallelismLimits::new(metadata.max_parallelism))
+            })
+            .await
+            .ok()
+    }
 
-        match self
-            .provider
-            .update(&req.resource_type, prior_state, planned_state)
+    /// The read cache, if `resource_type` has a refresh interval in
+    /// [`ProviderMetadata::refresh_intervals`].
+    async fn read_cache(&self, resource_type: &str) -> Option<&ReadCache<ReadResult>> {
+        let cache = self
+            .read_cache
+            .get_or_try_init(|| async {
+                let metadata = self.current_metadata().await?;
+                Ok::<_, Vec<Diagnostic>>(ReadCache::new(
+                    metadata
+                        .refresh_intervals
+                        .into_iter()
+                        .map(|(name, secs)| (name, Duration::from_secs(secs))),
+                ))
+            })
             .await
-        {
-            Ok(state) => {
-                info!(resource_type = %req.resource_type, "Update completed successfully");
-                Ok(tonic::Response::new(crate::generated::UpdateResponse {
-                    state: serde_json::to_vec(&state).unwrap_or_default(),
-                    diagnostics: vec![],
-                }))
-            },
-            Err(e) => {
-                error!(resource_type = %req.resource_type, error = %e, "Update failed");
-                Ok(tonic::Response::new(crate::generated::UpdateResponse {
-                    state: vec![],
-                    diagnostics: self.error_to_diagnostics(e),
-                }))
-            },
-        }
+            .ok()?;
+        cache.interval(resource_type).map(|_| cache)
     }
 
-    #[instrument(skip(self, request), name = "grpc.delete")]
-    async fn delete(
-        &self,
-        request: tonic::Request<crate::generated::DeleteRequest>,
-    ) -> Result<tonic::Response<crate::generated::DeleteResponse>, tonic::Status> {
-        let req = request.into_inner();
-        info!(resource_type = %req.resource_type, "Delete called");
-        let current_state =
-            serde_json::from_slice(&req.current_state).unwrap_or(serde_json::Value::Null);
+    /// The provider schema, loaded on first use and kept until the provider
+    /// is reconfigured.
+    pub(crate) async fn current_schema(&self) -> Result<Arc<ProviderSchema>, Vec<Diagnostic>> {
+        self.schema.get(self.provider.as_ref()).await
+    }
 
-        match self
-            .provider
-            .delete(&req.resource_type, current_state)
-            .await
-        {
-            Ok(()) => {
-                info!(resource_type = %req.resource_type, "Delete completed successfully");
-                Ok(tonic::Response::new(crate::generated::DeleteResponse {
-                    diagnostics: vec![],
-                }))
-            },
-            Err(e) => {
-                error!(resource_type = %req.resource_type, error = %e, "Delete failed");
-                Ok(tonic::Response::new(crate::generated::DeleteResponse {
-                    diagnostics: self.error_to_diagnostics(e),
-                }))
-            },
-        }
+    /// The provider metadata, after the schema has been loaded.
+    pub(crate) async fn current_metadata(&self) -> Result<ProviderMetadata, Vec<Diagnostic>> {
+        self.current_schema().await?;
+        self.provider.try_metadata()
     }
 
-    #[instrument(skip(self, request), name = "grpc.import_resource_state")]
-    async fn import_resource_state(
+    /// Run a provider call with the operation [`Context`] in scope.
+    async fn scoped<F: std::future::Future>(&self, call: F) -> F::Output {
+        self.context().scope(call).await
+    }
+
+    /// Run an operation, reporting it to the [`MetricsSink`] if one is set.
+    async fn measured<T, F>(
         &self,
-        request: tonic::Request<crate::generated::ImportResourceStateRequest>,
-    ) -> Result<tonic::Response<crate::generated::ImportResourceStateResponse>, tonic::Status> {
-        let req = request.into_inner();
-        info!(resource_type = %req.resource_type, id = %req.id, "ImportResourceState called");
+        operation: &str,
+        resource_type: Option<&str>,
+        call: F,
+    ) -> Result<T, ProviderError>
+    where
+        F: std::future::Future<Output = Result<T, ProviderError>>,
+    {
+        measure(self.metrics_sink.as_deref(), operation, resource_type, call).await
+    }
 
-        match self
-            .provider
-            .import_resource(&req.resource_type, &req.id)
-            .await
-        {
-            Ok(imported) => {
-                info!(
-                    resource_type = %req.resource_type,
-                    id = %req.id,
-                    imported_count = imported.len(),
-                    "ImportResourceState completed"
-                );
-                Ok(tonic::Response::new(
-                    crate::generated::ImportResourceStateResponse {
-                        imported: imported
-                            .into_iter()
-                            .map(|r| crate::generated::ImportedResource {
-                                resource_type: r.resource_type,
-                                state: serde_json::to_vec(&r.state).unwrap_or_default(),
-                            })
-                            .collect(),
-                        diagnostics: vec![],
-                    },
-                ))
-            },
-            Err(e) => {
-                error!(resource_type = %req.resource_type, id = %req.id, error = %e, "ImportResourceState failed");
-                Ok(tonic::Response::new(
-                    crate::generated::ImportResourceStateResponse {
-                        imported: vec![],
-                        diagnostics: self.error_to_diagnostics(e),
-                    },
-                ))
-            },
+    /// Encode a state returned to core with the configured [`StateCodec`].
+    async fn encode_state(
+        &self,
+        resource_type: &str,
+        state: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError> {
+        match &self.state_codec {
+            Some(codec) if !state.is_null() => codec.encode(resource_type, state).await,
+            _ => Ok(state),
         }
     }
 
-    #[instrument(skip(self, request), name = "grpc.validate_data_source_config")]
-    async fn validate_data_source_config(
+    /// Decode a state received from core with the configured [`StateCodec`].
+    async fn decode_state(
         &self,
-        request: tonic::Request<crate::generated::ValidateDataSourceConfigRequest>,
-    ) -> Result<tonic::Response<crate::generated::ValidateDataSourceConfigResponse>, tonic::Status>
-    {
-        let req = request.into_inner();
-        debug!(data_source_type = %req.data_source_type, "ValidateDataSourceConfig called");
-        let config = serde_json::from_slice(&req.config).unwrap_or(serde_json::Value::Null);
+        resource_type: &str,
+        state: serde_json::Value,
+    ) -> Result<serde_json::Value, ProviderError> {
+        match &self.state_codec {
+            Some(codec) if !state.is_null() => codec.decode(resource_type, state).await,
+            _ => Ok(state),
+        }
+    }
 
-        match self
-            .provider
-            .validate_data_source_config(&req.data_source_type, config)
-            .await
-        {
-            Ok(diagnostics) => {
-                let has_errors = diagnostics
-                    .iter()
-                    .any(|d| matches!(d.severity, DiagnosticSeverity::Error));
-                if has_errors {
-                    warn!(data_source_type = %req.data_source_type, diagnostics = diagnostics.len(), "ValidateDataSourceConfig completed with errors");
-                } else {
-                    info!(data_source_type = %req.data_source_type, "ValidateDataSourceConfig completed successfully");
-                }
-                Ok(tonic::Response::new(
-                    crate::generated::ValidateDataSourceConfigResponse {
-                        diagnostics: self.diagnostics_to_proto(diagnostics),
-                    },
-                ))
-            },
-            Err(e) => {
-                error!(data_source_type = %req.data_source_type, error = %e, "ValidateDataSourceConfig failed");
-                Ok(tonic::Response::new(
-                    crate::generated::ValidateDataSourceConfigResponse {
-                        diagnostics: self.error_to_diagnostics(e),
-                    },
-                ))
+    /// The provider's schema as sent by `GetSchema` and `StreamSchema`.
+    async fn schema_response(&self) -> crate::generated::GetSchemaResponse {
+        let schema = match self.current_schema().await {
+            Ok(schema) => schema,
+            Err(diagnostics) => {
+                error!(diagnostics = diagnostics.len(), "Schema retrieval failed");
+                return crate::generated::GetSchemaResponse {
+                    server_protocol_version: crate::PROTOCOL_VERSION,
+                    diagnostics: self.diagnostics_to_proto(diagnostics),
+                    ..Default::default()
+                };
             },
+        };
+        crate::generated::GetSchemaResponse {
+            server_protocol_version: crate::PROTOCOL_VERSION,
+            provider: Some(self.schema_to_proto(&schema.provider)),
+            resources: schema
+                .resources
+                .iter()
+                .map(|(k, v)| (k.clone(), self.schema_to_proto(v)))
+                .collect(),
+            data_sources: schema
+                .data_sources
+                .iter()
+                .map(|(k, v)| (k.clone(), self.schema_to_proto(v)))
+                .collect(),
+            diagnostics: vec![],
+            functions: schema
+                .functions
+                .iter()
+                .map(|(k, v)| (k.clone(), function_to_proto(v)))
+                .collect(),
         }
     }
 
-    #[instrument(skip(self, request), name = "grpc.read_data_source")]
-    async fn read_data_source(
+    /// Convert diagnostics for a response, translated with the configured
+    /// [`Catalog`].
+    fn diagnostics_to_proto(
         &self,
-        request: tonic::Request<crate::generated::ReadDataSourceRequest>,
-    ) -> Result<tonic::Response<crate::generated::ReadDataSourceResponse>, tonic::Status> {
-        let req = request.into_inner();
-        debug!(data_source_type = %req.data_source_type, "ReadDataSource called");
-        let config = serde_json::from_slice(&req.config).unwrap_or(serde_json::Value::Null);
+        diagnostics: Vec<Diagnostic>,
+    ) -> Vec<crate::generated::Diagnostic> {
+        diagnostics_to_proto(self.catalog.as_ref(), diagnostics)
+    }
 
-        match self
-            .provider
-            .read_data_source(&req.data_source_type, config)
-            .await
-        {
-            Ok(state) => {
-                info!(data_source_type = %req.data_source_type, "ReadDataSource completed successfully");
-                Ok(tonic::Response::new(
-                    crate::generated::ReadDataSourceResponse {
-                        state: serde_json::to_vec(&state).unwrap_or_default(),
-                        diagnostics: vec![],
-                    },
-                ))
-            },
+    /// Parse a JSON request payload. Empty payloads are `None`; malformed ones
+    /// are `None` with a warning recorded in `notes`.
+    #[cfg_attr(not(feature = "trace-serde"), allow(unused_variables))]
+    fn parse_payload(
+        &self,
+        rpc: &str,
+        field: &str,
+        bytes: &[u8],
+        notes: &mut ResponseNotes,
+    ) -> Option<serde_json::Value> {
+        if bytes.is_empty() {
+            return None;
+        }
+        #[cfg(feature = "trace-serde")]
+        let started = Instant::now();
+        #[cfg(feature = "arbitrary-precision")]
+        let parsed = if self.exact_numbers {
+            crate::payload::decode_json_exact(bytes)
+        } else {
+            decode_json(bytes)
+        };
+        #[cfg(not(feature = "arbitrary-precision"))]
+        let parsed = decode_json(bytes);
+        #[cfg(feature = "trace-serde")]
+        self.payload_metrics.record_decode(rpc, started.elapsed());
+        match parsed {
+            Ok(value) => Some(value),
             Err(e) => {
-                error!(data_source_type = %req.data_source_type, error = %e, "ReadDataSource failed");
-                Ok(tonic::Response::new(
-                    crate::generated::ReadDataSourceResponse {
-                        state: vec![],
-                        diagnostics: self.error_to_diagnostics(e),
-                    },
-                ))
+                warn!(field, error = %e, "Ignoring malformed JSON payload");
+                notes.internal.push(
+                    Diagnostic::warning(format!("Request field '{}' is not valid JSON", field))
+                        .with_detail(format!("{}; the SDK treated it as empty", e)),
+                );
+                None
             },
         }
     }
-}
 
-/// Options for configuring the provider server.
-#[derive(Debug, Clone)]
-pub struct ServeOptions {
-    /// Timeout for graceful shutdown. After receiving a shutdown signal,
-    /// the server will wait this long for in-flight requests to complete.
-    /// Default: 30 seconds.
-    pub shutdown_timeout: Duration,
-}
+    /// Serialize a JSON response payload of `rpc`.
+    #[cfg_attr(not(feature = "trace-serde"), allow(unused_variables))]
+    fn encode_payload<T: serde::Serialize + ?Sized>(&self, rpc: &str, value: &T) -> Bytes {
+        #[cfg(feature = "trace-serde")]
+        let started = Instant::now();
+        let bytes = encode_json(value);
+        #[cfg(feature = "trace-serde")]
+        self.payload_metrics.record_encode(rpc, started.elapsed());
+        bytes
+    }
 
-impl Default for ServeOptions {
-    fn default() -> Self {
-        Self {
-            shutdown_timeout: Duration::from_secs(30),
+    /// Record a warning if a state the operation needs is empty.
+    fn expect_state(&self, what: &str, state: &serde_json::Value, notes: &mut ResponseNotes) {
+        if state.is_null() {
+            warn!(what, "Unexpectedly empty state");
+            notes.internal.push(
+                Diagnostic::warning(format!("The {} is empty", what)).with_detail(
+                    "The SDK passed null through; the result may not be what you expect",
+                ),
+            );
         }
     }
-}
 
-impl ServeOptions {
-    /// Create new serve options with default values.
-    pub fn new() -> Self {
-        Self::default()
+    /// Validate a state returned by the provider against its resource schema,
+    /// if [`ServeOptions::validate_state`] is set.
+    fn check_state(
+        &self,
+        schema: &ProviderSchema,
+        rpc: &str,
+        resource_type: &str,
+        state: &serde_json::Value,
+        notes: &mut ResponseNotes,
+    ) {
+        if !self.validate_state || state.is_null() {
+            return;
+        }
+        let Some(schema) = schema.resources.get(resource_type) else {
+            return;
+        };
+        let diagnostics = crate::validation::validate_state(schema, state);
+        if !diagnostics.is_empty() {
+            warn!(
+                rpc,
+                resource_type,
+                violations = diagnostics.len(),
+                "Provider returned a state that does not match its schema"
+            );
+        }
+        notes.state.extend(diagnostics);
     }
 
-    /// Set the shutdown timeout.
-    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
-        self.shutdown_timeout = timeout;
-        self
+    /// Validate a data source result against its schema, including that every
+    /// [output](crate::schema::Attribute::output) is set, if
+    /// [`ServeOptions::validate_state`] is set.
+    fn check_data_source_state(
+        &self,
+        schema: &ProviderSchema,
+        data_source_type: &str,
+        state: &serde_json::Value,
+        notes: &mut ResponseNotes,
+    ) {
+        if !self.validate_state || state.is_null() {
+            return;
+        }
+        let Some(schema) = schema.data_sources.get(data_source_type) else {
+            return;
+        };
+        let diagnostics = crate::validation::validate_state(schema, state);
+        if !diagnostics.is_empty() {
+            warn!(
+                data_source_type,
+                violations = diagnostics.len(),
+                "Provider returned a data source result that does not match its schema"
+            );
+        }
+        notes.state.extend(diagnostics);
     }
-}
 
-/// Wait for a shutdown signal (SIGTERM or SIGINT).
-///
-/// On Unix, this waits for SIGTERM or SIGINT.
-/// On Windows, this waits for CTRL+C.
-async fn wait_for_shutdown_signal() {
-    #[cfg(unix)]
-    {
-        use tokio::signal::unix::{signal, SignalKind};
+    /// Apply the resource schema's [normalizers](crate::normalize) to configs
+    /// and states of `resource_type`.
+    fn normalize<'a>(
+        &self,
+        schema: &ProviderSchema,
+        resource_type: &str,
+        values: impl IntoIterator<Item = &'a mut serde_json::Value>,
+    ) {
+        let Some(schema) = schema.resources.get(resource_type) else {
+            return;
+        };
+        for value in values {
+            crate::normalize::normalize(schema, value);
+        }
+    }
 
-        let mut sigterm =
-            signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
-        let mut sigint = signal(SignalKind::interrupt()).expect("Failed to install SIGINT handler");
+    /// Redact sensitive values in plan changes (see [`crate::plan::redact_changes`]).
+    fn redact_changes(
+        &self,
+        schema: &ProviderSchema,
+        resource_type: &str,
+        changes: &mut [AttributeChange],
+    ) {
+        let empty = crate::schema::Schema::default();
+        let schema = schema.resources.get(resource_type).unwrap_or(&empty);
+        crate::plan::redact_changes(schema, changes);
+    }
 
-        tokio::select! {
-            _ = sigterm.recv() => {
-                eprintln!("Received SIGTERM, initiating graceful shutdown...");
-            }
-            _ = sigint.recv() => {
-                eprintln!("Received SIGINT, initiating graceful shutdown...");
+    /// Record the request payload sizes of an RPC and check them against the
+    /// [`PayloadLimits`]. Fails if a payload is over the hard limit.
+    fn check_request(
+        &self,
+        rpc: &str,
+        payloads: &[(&str, &[u8])],
+        notes: &mut ResponseNotes,
+    ) -> Result<(), ProviderError> {
+        let total = payloads.iter().map(|(_, bytes)| bytes.len()).sum();
+        tracing::Span::current().record("request_bytes", total);
+        self.payload_metrics.record_request(rpc, total);
+        for (field, bytes) in payloads {
+            notes
+                .limits
+                .extend(self.payload_limits.check(rpc, field, bytes.len())?);
+        }
+        Ok(())
+    }
+
+    /// Record the response payload sizes of an RPC and check them against the
+    /// [`PayloadLimits`]. The response keeps its payloads either way, so a
+    /// created resource is not lost.
+    fn check_response(&self, rpc: &str, payloads: &[(&str, &[u8])], notes: &mut ResponseNotes) {
+        let total = payloads.iter().map(|(_, bytes)| bytes.len()).sum();
+        tracing::Span::current().record("response_bytes", total);
+        self.payload_metrics.record_response(rpc, total);
+        for (field, bytes) in payloads {
+            match self.payload_limits.check(rpc, field, bytes.len()) {
+                Ok(warning) => notes.limits.extend(warning),
+                Err(e) => {
+                    error!(rpc, field, error = %e, "Response payload over the hard limit");
+                    notes.limits.push(Diagnostic::error(e.to_string()));
+                },
             }
         }
     }
 
-    #[cfg(windows)]
-    {
-        tokio::signal::ctrl_c()
-            .await
-            .expect("Failed to install CTRL+C handler");
-        eprintln!("Received CTRL+C, initiating graceful shutdown...");
+    /// Append the SDK's own notes to response diagnostics.
+    fn with_warnings(
+        &self,
+        mut diagnostics: Vec<crate::generated::Diagnostic>,
+        notes: ResponseNotes,
+    ) -> Vec<crate::generated::Diagnostic> {
+        if self.internal_warnings {
+            diagnostics.extend(self.diagnostics_to_proto(notes.internal));
+        }
+        diagnostics.extend(self.diagnostics_to_proto(notes.limits));
+        diagnostics.extend(self.diagnostics_to_proto(notes.state));
+        diagnostics
     }
 
-    #[cfg(not(any(unix, windows)))]
-    {
-        // Fallback: just wait forever (no signal handling)
-        std::future::pending::<()>().await;
+    /// A provider error as an error diagnostic carrying its gRPC code and kind.
+    fn error_to_diagnostics(&self, err: ProviderError) -> Vec<crate::generated::Diagnostic> {
+        error_to_diagnostics(self.catalog.as_ref(), err)
+    }
+
+    fn redact_diagnostics(
+        &self,
+        diagnostics: Vec<crate::generated::Diagnostic>,
+    ) -> Vec<crate::generated::Diagnostic> {
+        diagnostics
+            .into_iter()
+            .map(|d| crate::generated::Diagnostic {
+                summary: self.secrets.redact(&d.summary),
+                detail: self.secrets.redact(&d.detail),
+                suggestion: self.secrets.redact(&d.suggestion),
+                ..d
+            })
+            .collect()
+    }
+
+    fn schema_to_proto(&self, schema: &crate::schema::Schema) -> crate::generated::Schema {
+        crate::generated::Schema {
+            version: schema.version as i64,
+            block: Some(block_to_proto(&schema.block, false, false)),
+            examples: examples_to_proto(&schema.examples),
+            category: schema.category.clone().unwrap_or_default(),
+            metadata: metadata_to_proto(&schema.metadata),
+        }
     }
 }
 
-/// Serve a provider implementation as a gRPC server.
-///
-/// This function:
-/// 1. Finds an available port
-/// 2. Starts the gRPC server
-/// 3. Outputs the handshake string to stdout
-/// 4. Handles shutdown signals (SIGTERM/SIGINT) gracefully
+/// The request metadata core names the plan or apply run in.
+pub const SESSION_ID_METADATA: &str = "hemmer-session-id";
+
+/// The request metadata core sends a unique key for each create and update
+/// in, repeating it unchanged when it retries the RPC.
+pub const IDEMPOTENCY_KEY_METADATA: &str = "hemmer-idempotency-key";
+
+/// The session id of a request, from its [`SESSION_ID_METADATA`].
+fn session_id<T>(request: &tonic::Request<T>) -> Option<String> {
+    metadata_value(request, SESSION_ID_METADATA)
+}
+
+/// The idempotency key of a request, from its [`IDEMPOTENCY_KEY_METADATA`].
+fn idempotency_key<T>(request: &tonic::Request<T>) -> Option<String> {
+    metadata_value(request, IDEMPOTENCY_KEY_METADATA)
+}
+
+/// A non-empty request metadata value.
+fn metadata_value<T>(request: &tonic::Request<T>, key: &str) -> Option<String> {
+    request
+        .metadata()
+        .get(key)?
+        .to_str()
+        .ok()
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// The idempotency token of a create or update: derived from the request's
+/// idempotency key, so retries share it, or unique to this call when core
+/// sent no key.
+pub(crate) fn idempotency_token(operation: &str, resource_type: &str, key: Option<&str>) -> String {
+    match key {
+        Some(key) => derive_token(operation, resource_type, &[key.as_bytes()]),
+        None => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos();
+            derive_token(
+                operation,
+                resource_type,
+                &[next_request_id().as_bytes(), &now.to_le_bytes()],
+            )
+        },
+    }
+}
+
+/// Validate a provider configuration against the provider schema, then with
+/// the provider's own `validate_provider_config`, merging the diagnostics.
 ///
-/// The handshake format is: `HEMMER_PROVIDER|<version>|<address>`
+/// Diagnostics the provider reports again (e.g. because it runs the same
+/// schema validation itself) are only included once.
+pub(crate) async fn validate_provider_config_with_schema<P: ProviderService>(
+    provider: &P,
+    schema: Result<Arc<ProviderSchema>, Vec<Diagnostic>>,
+    config: serde_json::Value,
+) -> Result<Vec<Diagnostic>, ProviderError> {
+    let mut diagnostics = Diagnostics::from(match schema {
+        Ok(schema) => crate::validation::validate(&schema.provider, &config),
+        Err(diagnostics) => diagnostics,
+    });
+    diagnostics.append(provider.validate_provider_config(config).await?);
+    diagnostics.dedupe();
+    Ok(diagnostics.into_vec())
+}
+
+/// Validate a resource configuration against its resource schema, then with
+/// the provider's own `validate_resource_config`, merging the diagnostics.
 ///
-/// For custom configuration, use [`serve_with_options`].
-pub async fn serve<P: ProviderService>(provider: P) -> Result<(), Box<dyn std::error::Error>> {
-    serve_with_options(provider, ServeOptions::default()).await
+/// Unknown resource types are left to the provider.
+pub(crate) async fn validate_resource_config_with_schema<P: ProviderService>(
+    provider: &P,
+    schema: Result<Arc<ProviderSchema>, Vec<Diagnostic>>,
+    resource_type: &str,
+    config: serde_json::Value,
+) -> Result<Vec<Diagnostic>, ProviderError> {
+    let mut diagnostics = Diagnostics::from(match schema {
+        Ok(schema) => schema
+            .resources
+            .get(resource_type)
+            .map(|schema| crate::validation::validate(schema, &config))
+            .unwrap_or_default(),
+        Err(diagnostics) => diagnostics,
+    });
+    diagnostics.append(
+        provider
+            .validate_resource_config(resource_type, config)
+            .await?,
+    );
+    diagnostics.dedupe();
+    Ok(diagnostics.into_vec())
+}
+
+/// The error for an operation that needs the schema when
+/// [`ProviderService::schema_async`] failed.
+/// Plan from the resource's schema, as the default [`ProviderService::plan`]
+/// does, using the server's cached schema when one is in scope.
+pub(crate) async fn default_plan<P: ProviderService + ?Sized>(
+    provider: &P,
+    resource_type: &str,
+    prior_state: Option<serde_json::Value>,
+    proposed_state: serde_json::Value,
+    config: serde_json::Value,
+) -> Result<PlanResult, ProviderError> {
+    let schema = match Context::current().schema() {
+        Some(schema) => schema,
+        None => Arc::new(provider.schema_async().await.map_err(schema_unavailable)?),
+    };
+    let resource = schema
+        .resources
+        .get(resource_type)
+        .ok_or_else(|| crate::schema::unknown_type(resource_type, schema.resources.keys()))?;
+    plan_from_schema(resource, prior_state.as_ref(), proposed_state, &config)
 }
 
-/// Serve a provider with custom options.
-///
-/// See [`serve`] for details. This function allows configuring
-/// shutdown behavior via [`ServeOptions`].
-pub async fn serve_with_options<P: ProviderService>(
-    provider: P,
-    options: ServeOptions,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Find an available port by binding to port 0
-    let listener = TcpListener::bind("127.0.0.1:0").await?;
-    let addr = listener.local_addr()?;
+/// Routes serving only the gRPC service.
+fn grpc_routes<P: ProviderService>(service: Arc<ProviderGrpcService<P>>) -> tonic::service::Routes {
+    tonic::service::Routes::new(crate::generated::provider_server::ProviderServer::from_arc(
+        service,
+    ))
+}
+
+pub(crate) fn schema_unavailable(diagnostics: Vec<Diagnostic>) -> ProviderError {
+    let summaries: Vec<_> = diagnostics.iter().map(|d| d.summary.as_str()).collect();
+    ProviderError::Unavailable(format!(
+        "Provider schema is unavailable: {}",
+        summaries.join("; ")
+    ))
+}
+
+/// JSON-encode documentation examples, or empty bytes if there are none.
+fn examples_to_proto(examples: &[serde_json::Value]) -> Bytes {
+    if examples.is_empty() {
+        return Bytes::new();
+    }
+    serde_json::to_vec(examples).unwrap_or_default().into()
+}
+
+/// JSON-encode documentation metadata, or empty bytes if there is none.
+fn metadata_to_proto(metadata: &std::collections::BTreeMap<String, serde_json::Value>) -> Bytes {
+    if metadata.is_empty() {
+        return Bytes::new();
+    }
+    serde_json::to_vec(metadata).unwrap_or_default().into()
+}
+
+/// Convert a function signature, with JSON-encoded parameter and return types.
+fn function_to_proto(function: &crate::schema::FunctionSchema) -> crate::generated::Function {
+    crate::generated::Function {
+        parameters: function
+            .parameters
+            .iter()
+            .map(function_parameter_to_proto)
+            .collect(),
+        variadic_parameter: function
+            .variadic_parameter
+            .as_ref()
+            .map(function_parameter_to_proto),
+        return_type: serde_json::to_vec(&function.return_type)
+            .unwrap_or_default()
+            .into(),
+        description: function.description.clone().unwrap_or_default(),
+        deterministic: function.deterministic,
+    }
+}
+
+fn function_parameter_to_proto(
+    parameter: &crate::schema::FunctionParameter,
+) -> crate::generated::FunctionParameter {
+    crate::generated::FunctionParameter {
+        name: parameter.name.clone(),
+        r#type: serde_json::to_vec(&parameter.param_type)
+            .unwrap_or_default()
+            .into(),
+        description: parameter.description.clone().unwrap_or_default(),
+        allow_null: parameter.allow_null,
+    }
+}
+
+/// Convert a block, applying the `force_new` and `sensitive` flags inherited
+/// from enclosing nested blocks to its attributes.
+fn block_to_proto(
+    block: &crate::schema::Block,
+    force_new: bool,
+    sensitive: bool,
+) -> crate::generated::Block {
+    crate::generated::Block {
+        attributes: block
+            .attributes
+            .iter()
+            .map(|(name, attr)| crate::generated::Attribute {
+                name: name.clone(),
+                r#type: serde_json::to_vec(&attr.attr_type)
+                    .unwrap_or_default()
+                    .into(),
+                required: attr.flags.required,
+                optional: attr.flags.optional,
+                computed: attr.flags.computed,
+                sensitive: attr.flags.sensitive || sensitive,
+                description: attr.description.clone().unwrap_or_default(),
+                force_new: attr.force_new || force_new,
+                default_value: attr
+                    .default
+                    .as_ref()
+                    .map(|v| serde_json::to_vec(v).unwrap_or_default().into())
+                    .unwrap_or_default(),
+                examples: examples_to_proto(&attr.examples),
+                category: attr.category.clone().unwrap_or_default(),
+                metadata: metadata_to_proto(&attr.metadata),
+            })
+            .collect(),
+        block_types: block
+            .blocks
+            .iter()
+            .map(|(name, nested)| crate::generated::NestedBlock {
+                type_name: name.clone(),
+                block: Some(block_to_proto(
+                    &nested.block,
+                    force_new || nested.force_new,
+                    sensitive || nested.sensitive,
+                )),
+                nesting_mode: match nested.nesting_mode {
+                    crate::schema::BlockNestingMode::Single => {
+                        crate::generated::nested_block::NestingMode::Single as i32
+                    },
+                    crate::schema::BlockNestingMode::List => {
+                        crate::generated::nested_block::NestingMode::List as i32
+                    },
+                    crate::schema::BlockNestingMode::Set => {
+                        crate::generated::nested_block::NestingMode::Set as i32
+                    },
+                    crate::schema::BlockNestingMode::Map => {
+                        crate::generated::nested_block::NestingMode::Map as i32
+                    },
+                },
+                min_items: nested.min_items as i32,
+                max_items: nested.max_items as i32,
+                force_new: nested.force_new,
+                sensitive: nested.sensitive,
+            })
+            .collect(),
+        description: block.description.clone().unwrap_or_default(),
+    }
+}
+
+#[tonic::async_trait]
+impl<P: ProviderService> crate::generated::provider_server::Provider for ProviderGrpcService<P> {
+    #[instrument(skip(self, _request), name = "grpc.get_metadata")]
+    async fn get_metadata(
+        &self,
+        _request: tonic::Request<crate::generated::GetMetadataRequest>,
+    ) -> Result<tonic::Response<crate::generated::GetMetadataResponse>, tonic::Status> {
+        debug!("GetMetadata called");
+        // Core asks for metadata first, so make sure an asynchronously loaded
+        // schema is ready before the metadata is derived from it
+        let metadata = match self.current_metadata().await {
+            Ok(metadata) => metadata,
+            Err(diagnostics) => {
+                error!(diagnostics = diagnostics.len(), "GetMetadata failed");
+                return Ok(tonic::Response::new(
+                    crate::generated::GetMetadataResponse {
+                        diagnostics: self.diagnostics_to_proto(diagnostics),
+                        ..Default::default()
+                    },
+                ));
+            },
+        };
+        info!(
+            resources = metadata.resources.len(),
+            data_sources = metadata.data_sources.len(),
+            "GetMetadata completed"
+        );
+        Ok(tonic::Response::new(
+            crate::generated::GetMetadataResponse {
+                server_capabilities: Some(metadata.capabilities.into()),
+                resources: metadata.resources,
+                data_sources: metadata.data_sources,
+                diagnostics: vec![],
+                import_formats: metadata
+                    .import_formats
+                    .into_iter()
+                    .map(|(name, formats)| (name, crate::generated::ImportFormats { formats }))
+                    .collect(),
+                max_parallelism: metadata.max_parallelism,
+                refresh_intervals: metadata.refresh_intervals,
+            },
+        ))
+    }
+
+    #[instrument(skip(self, request), name = "grpc.get_schema")]
+    async fn get_schema(
+        &self,
+        request: tonic::Request<crate::generated::GetSchemaRequest>,
+    ) -> Result<tonic::Response<crate::generated::GetSchemaResponse>, tonic::Status> {
+        debug!("GetSchema called");
+
+        // Validate client protocol version
+        let client_version = request.get_ref().client_protocol_version;
+        crate::check_protocol_version(client_version)
+            .map_err(tonic::Status::failed_precondition)?;
+
+        debug!(
+            client_version = client_version,
+            server_version = crate::PROTOCOL_VERSION,
+            "Protocol version negotiation complete"
+        );
+
+        let response = self.schema_response().await;
+        info!(
+            resources = response.resources.len(),
+            data_sources = response.data_sources.len(),
+            "GetSchema completed"
+        );
+        Ok(tonic::Response::new(response))
+    }
+
+    type StreamSchemaStream = tonic::codegen::BoxStream<crate::generated::SchemaChunk>;
+
+    #[instrument(skip(self, request), name = "grpc.stream_schema")]
+    async fn stream_schema(
+        &self,
+        request: tonic::Request<crate::generated::StreamSchemaRequest>,
+    ) -> Result<tonic::Response<Self::StreamSchemaStream>, tonic::Status> {
+        debug!("StreamSchema called");
+        let req = request.into_inner();
+        crate::check_protocol_version(req.client_protocol_version)
+            .map_err(tonic::Status::failed_precondition)?;
+
+        let max_chunk_bytes = match req.max_chunk_bytes {
+            0 => crate::schema_stream::DEFAULT_MAX_CHUNK_BYTES,
+            n => n as usize,
+        };
+        let chunks = crate::schema_stream::schema_chunks(
+            self.schema_response().await,
+            max_chunk_bytes,
+            req.accept_zstd,
+        );
+        info!(chunks = chunks.len(), "StreamSchema completed");
+        Ok(tonic::Response::new(Box::pin(tokio_stream::iter(
+            chunks.into_iter().map(Ok),
+        ))))
+    }
+
+    #[instrument(skip(self, request), name = "grpc.validate_provider_config", fields(request_id = tracing::field::Empty, request_bytes = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn validate_provider_config(
+        &self,
+        request: tonic::Request<crate::generated::ValidateProviderConfigRequest>,
+    ) -> Result<tonic::Response<crate::generated::ValidateProviderConfigResponse>, tonic::Status>
+    {
+        debug!("ValidateProviderConfig called");
+        let req = request.into_inner();
+        let mut notes = ResponseNotes::default();
+        let checked = self.check_request(
+            "validate_provider_config",
+            &[("config", &req.config)],
+            &mut notes,
+        );
+        let config = self
+            .parse_payload(
+                "validate_provider_config",
+                "config",
+                &req.config,
+                &mut notes,
+            )
+            .unwrap_or_default();
+
+        match self
+            .scoped(self.measured("validate_provider_config", None, async {
+                checked?;
+                if self.validate_provider_config {
+                    validate_provider_config_with_schema(
+                        self.provider.as_ref(),
+                        self.current_schema().await,
+                        config,
+                    )
+                    .await
+                } else {
+                    self.provider.validate_provider_config(config).await
+                }
+            }))
+            .await
+        {
+            Ok(diagnostics) => {
+                let has_errors = diagnostics
+                    .iter()
+                    .any(|d| matches!(d.severity, DiagnosticSeverity::Error));
+                if has_errors {
+                    warn!(
+                        diagnostics = diagnostics.len(),
+                        "ValidateProviderConfig completed with errors"
+                    );
+                } else {
+                    info!("ValidateProviderConfig completed successfully");
+                }
+                Ok(tonic::Response::new(
+                    crate::generated::ValidateProviderConfigResponse {
+                        diagnostics: self
+                            .with_warnings(self.diagnostics_to_proto(diagnostics), notes),
+                    },
+                ))
+            },
+            Err(e) => {
+                error!(error = %e, "ValidateProviderConfig failed");
+                Ok(tonic::Response::new(
+                    crate::generated::ValidateProviderConfigResponse {
+                        diagnostics: self.with_warnings(self.error_to_diagnostics(e), notes),
+                    },
+                ))
+            },
+        }
+    }
+
+    #[instrument(skip(self, request), name = "grpc.configure", fields(request_id = tracing::field::Empty, request_bytes = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn configure(
+        &self,
+        request: tonic::Request<crate::generated::ConfigureRequest>,
+    ) -> Result<tonic::Response<crate::generated::ConfigureResponse>, tonic::Status> {
+        debug!("Configure called");
+        let session = session_id(&request);
+        let req = request.into_inner();
+        let mut notes = ResponseNotes::default();
+        let checked = self.check_request("configure", &[("config", &req.config)], &mut notes);
+        let mut config = self
+            .parse_payload("configure", "config", &req.config, &mut notes)
+            .unwrap_or_default();
+        let _configuring = self.configure_lock.lock().await;
+
+        let provider_schema = match self.current_schema().await {
+            Ok(schema) => schema.provider.clone(),
+            Err(diagnostics) => {
+                error!(
+                    diagnostics = diagnostics.len(),
+                    "Provider schema is unavailable"
+                );
+                return Ok(tonic::Response::new(crate::generated::ConfigureResponse {
+                    diagnostics: self.with_warnings(self.diagnostics_to_proto(diagnostics), notes),
+                }));
+            },
+        };
+        // Resolve every secret afresh so a rotated value is picked up on
+        // reconfigure instead of the one cached by the previous Configure.
+        self.secrets.clear_cache();
+        if let Err(e) = self
+            .secrets
+            .resolve_config(&provider_schema.block, &mut config)
+            .await
+        {
+            error!(error = %e, "Secret resolution failed");
+            return Ok(tonic::Response::new(crate::generated::ConfigureResponse {
+                diagnostics: self.with_warnings(self.error_to_diagnostics(e), notes),
+            }));
+        }
+
+        match self
+            .request_context(req.client_capabilities, session)
+            .scope(self.measured("configure", None, async {
+                checked?;
+                self.provider.configure(config).await
+            }))
+            .await
+        {
+            Ok(result) => {
+                if result.has_errors() {
+                    warn!(
+                        diagnostics = result.diagnostics.len(),
+                        "Configure completed with errors"
+                    );
+                } else {
+                    info!("Configure completed successfully");
+                    // The schema may depend on the configuration (e.g. on the
+                    // plugins or API version it points at)
+                    self.schema.invalidate();
+                    if let Some(data) = result.provider_data {
+                        let generation = self.provider_data.set(data);
+                        if generation > 1 {
+                            info!(generation, "Provider reconfigured");
+                        }
+                    }
+                }
+                let diagnostics = self.diagnostics_to_proto(result.diagnostics);
+                Ok(tonic::Response::new(crate::generated::ConfigureResponse {
+                    diagnostics: self.redact_diagnostics(self.with_warnings(diagnostics, notes)),
+                }))
+            },
+            Err(e) => {
+                let diagnostics = self.redact_diagnostics(self.error_to_diagnostics(e));
+                error!(error = %diagnostics[0].summary, "Configure failed");
+                let diagnostics = self.with_warnings(diagnostics, notes);
+                Ok(tonic::Response::new(crate::generated::ConfigureResponse {
+                    diagnostics,
+                }))
+            },
+        }
+    }
+
+    #[instrument(skip(self, _request), name = "grpc.stop")]
+    async fn stop(
+        &self,
+        _request: tonic::Request<crate::generated::StopRequest>,
+    ) -> Result<tonic::Response<crate::generated::StopResponse>, tonic::Status> {
+        info!("Stop called");
+        if !self.shutdown.trigger() {
+            debug!("Shutdown already in progress");
+        }
+        if let Some(cache) = &self.session_cache {
+            cache.clear();
+        }
+        match self
+            .scoped(self.shutdown.stop_provider(
+                self.provider.as_ref(),
+                &self.provider_data,
+                &self.local_store,
+            ))
+            .await
+        {
+            Ok(()) => {
+                info!("Stop completed successfully");
+                Ok(tonic::Response::new(crate::generated::StopResponse {
+                    error: String::new(),
+                }))
+            },
+            Err(e) => {
+                error!(error = %e, "Stop failed");
+                Ok(tonic::Response::new(crate::generated::StopResponse {
+                    error: e,
+                }))
+            },
+        }
+    }
+
+    #[instrument(skip(self, request), name = "grpc.end_session")]
+    async fn end_session(
+        &self,
+        request: tonic::Request<crate::generated::EndSessionRequest>,
+    ) -> Result<tonic::Response<crate::generated::EndSessionResponse>, tonic::Status> {
+        let req = request.into_inner();
+        let ended = self
+            .session_cache
+            .as_ref()
+            .is_some_and(|cache| cache.end_session(&req.session_id));
+        debug!(session_id = %req.session_id, ended, "EndSession called");
+        Ok(tonic::Response::new(
+            crate::generated::EndSessionResponse {},
+        ))
+    }
+
+    #[instrument(skip(self, request), name = "grpc.validate_resource_config", fields(request_id = tracing::field::Empty, request_bytes = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn validate_resource_config(
+        &self,
+        request: tonic::Request<crate::generated::ValidateResourceConfigRequest>,
+    ) -> Result<tonic::Response<crate::generated::ValidateResourceConfigResponse>, tonic::Status>
+    {
+        let req = request.into_inner();
+        debug!(resource_type = %req.resource_type, "ValidateResourceConfig called");
+        let mut notes = ResponseNotes::default();
+        let checked = self.check_request(
+            "validate_resource_config",
+            &[("config", &req.config)],
+            &mut notes,
+        );
+        let config = self
+            .parse_payload(
+                "validate_resource_config",
+                "config",
+                &req.config,
+                &mut notes,
+            )
+            .unwrap_or_default();
+
+        match self
+            .scoped(self.measured(
+                "validate_resource_config",
+                Some(&req.resource_type),
+                async {
+                    checked?;
+                    let schema = self.current_schema().await;
+                    let mut config = config;
+                    if let Ok(schema) = &schema {
+                        self.normalize(schema, &req.resource_type, [&mut config]);
+                    }
+                    if self.validate_resource_config {
+                        validate_resource_config_with_schema(
+                            self.provider.as_ref(),
+                            schema,
+                            &req.resource_type,
+                            config,
+                        )
+                        .await
+                    } else {
+                        self.provider
+                            .validate_resource_config(&req.resource_type, config)
+                            .await
+                    }
+                },
+            ))
+            .await
+        {
+            Ok(diagnostics) => {
+                let has_errors = diagnostics
+                    .iter()
+                    .any(|d| matches!(d.severity, DiagnosticSeverity::Error));
+                if has_errors {
+                    warn!(resource_type = %req.resource_type, diagnostics = diagnostics.len(), "ValidateResourceConfig completed with errors");
+                } else {
+                    info!(resource_type = %req.resource_type, "ValidateResourceConfig completed successfully");
+                }
+                Ok(tonic::Response::new(
+                    crate::generated::ValidateResourceConfigResponse {
+                        diagnostics: self
+                            .with_warnings(self.diagnostics_to_proto(diagnostics), notes),
+                    },
+                ))
+            },
+            Err(e) => {
+                error!(resource_type = %req.resource_type, error = %e, "ValidateResourceConfig failed");
+                Ok(tonic::Response::new(
+                    crate::generated::ValidateResourceConfigResponse {
+                        diagnostics: self.with_warnings(self.error_to_diagnostics(e), notes),
+                    },
+                ))
+            },
+        }
+    }
+
+    #[instrument(skip(self, request), name = "grpc.upgrade_resource_state", fields(request_id = tracing::field::Empty, request_bytes = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn upgrade_resource_state(
+        &self,
+        request: tonic::Request<crate::generated::UpgradeResourceStateRequest>,
+    ) -> Result<tonic::Response<crate::generated::UpgradeResourceStateResponse>, tonic::Status>
+    {
+        let req = request.into_inner();
+        debug!(resource_type = %req.resource_type, version = req.version, "UpgradeResourceState called");
+        let mut notes = ResponseNotes::default();
+        let checked = self.check_request(
+            "upgrade_resource_state",
+            &[("raw_state", &req.raw_state)],
+            &mut notes,
+        );
+        let state = self
+            .parse_payload(
+                "upgrade_resource_state",
+                "raw_state",
+                &req.raw_state,
+                &mut notes,
+            )
+            .unwrap_or_default();
+        let had_state = !state.is_null();
+
+        match self
+            .scoped(
+                self.measured("upgrade_resource_state", Some(&req.resource_type), async {
+                    checked?;
+                    let state = self.decode_state(&req.resource_type, state).await?;
+                    let upgraded = self
+                        .provider
+                        .upgrade_resource_state(&req.resource_type, req.version, state)
+                        .await?;
+                    self.encode_state(&req.resource_type, upgraded).await
+                }),
+            )
+            .await
+        {
+            Ok(upgraded) => {
+                if had_state {
+                    self.expect_state("upgraded state", &upgraded, &mut notes);
+                }
+                let upgraded = self.encode_payload("upgrade_resource_state", &upgraded);
+                self.check_response(
+                    "upgrade_resource_state",
+                    &[("upgraded_state", &upgraded[..])],
+                    &mut notes,
+                );
+                info!(resource_type = %req.resource_type, from_version = req.version, "UpgradeResourceState completed");
+                Ok(tonic::Response::new(
+                    crate::generated::UpgradeResourceStateResponse {
+                        upgraded_state: upgraded,
+                        diagnostics: self.with_warnings(vec![], notes),
+                    },
+                ))
+            },
+            Err(e) => {
+                error!(resource_type = %req.resource_type, version = req.version, error = %e, "UpgradeResourceState failed");
+                Ok(tonic::Response::new(
+                    crate::generated::UpgradeResourceStateResponse {
+                        upgraded_state: Bytes::new(),
+                        diagnostics: self.with_warnings(self.error_to_diagnostics(e), notes),
+                    },
+                ))
+            },
+        }
+    }
+
+    #[instrument(skip(self, request), name = "grpc.plan", fields(request_id = tracing::field::Empty, request_bytes = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn plan(
+        &self,
+        request: tonic::Request<crate::generated::PlanRequest>,
+    ) -> Result<tonic::Response<crate::generated::PlanResponse>, tonic::Status> {
+        let session = session_id(&request);
+        let req = request.into_inner();
+        let is_create = req.prior_state.is_empty();
+        debug!(resource_type = %req.resource_type, is_create = is_create, "Plan called");
+
+        let mut notes = ResponseNotes::default();
+        let checked = self.check_request(
+            "plan",
+            &[
+                ("prior_state", &req.prior_state),
+                ("proposed_state", &req.proposed_state),
+                ("config", &req.config),
+            ],
+            &mut notes,
+        );
+        let prior_state = self.parse_payload("plan", "prior_state", &req.prior_state, &mut notes);
+        let proposed_state = self
+            .parse_payload("plan", "proposed_state", &req.proposed_state, &mut notes)
+            .unwrap_or_default();
+        let proposed_state_was_null = proposed_state.is_null();
+        let config = self
+            .parse_payload("plan", "config", &req.config, &mut notes)
+            .unwrap_or_default();
+        // Loaded before the context is built, so the default plan finds it there
+        let schema = self.current_schema().await.unwrap_or_default();
+
+        match self
+            .request_context(req.client_capabilities, session)
+            .scope(self.measured("plan", Some(&req.resource_type), async {
+                checked?;
+                let _permit = self.parallelism_permit(&req.resource_type).await;
+                let mut prior_state = match prior_state {
+                    Some(state) => Some(self.decode_state(&req.resource_type, state).await?),
+                    None => None,
+                };
+                let (mut proposed_state, mut config) = (proposed_state, config);
+                self.normalize(
+                    &schema,
+                    &req.resource_type,
+                    [&mut proposed_state, &mut config]
+                        .into_iter()
+                        .chain(prior_state.as_mut()),
+                );
+                let mut result = self
+                    .provider
+                    .plan(&req.resource_type, prior_state, proposed_state, config)
+                    .await?;
+                self.redact_changes(&schema, &req.resource_type, &mut result.changes);
+                let impact = self
+                    .provider
+                    .estimate(&req.resource_type, &result.planned_state)
+                    .await;
+                result.planned_state = self
+                    .encode_state(&req.resource_type, result.planned_state)
+                    .await?;
+                Ok::<_, ProviderError>((result, impact))
+            }))
+            .await
+        {
+            Ok((result, impact)) => {
+                if !proposed_state_was_null {
+                    self.expect_state("planned state", &result.planned_state, &mut notes);
+                }
+                info!(
+                    resource_type = %req.resource_type,
+                    changes = result.changes.len(),
+                    requires_replace = result.requires_replace,
+                    "Plan completed"
+                );
+                let planned_state = self.encode_payload("plan", &result.planned_state);
+                self.check_response("plan", &[("planned_state", &planned_state[..])], &mut notes);
+                Ok(tonic::Response::new(crate::generated::PlanResponse {
+                    planned_state,
+                    changes: result.changes.into_iter().map(Into::into).collect(),
+                    requires_replace: result.requires_replace,
+                    diagnostics: self.with_warnings(vec![], notes),
+                    replace_reasons: result.replace_reasons.into_iter().map(Into::into).collect(),
+                    impact: impact.map(Into::into),
+                }))
+            },
+            Err(e) => {
+                error!(resource_type = %req.resource_type, error = %e, "Plan failed");
+                Ok(tonic::Response::new(crate::generated::PlanResponse {
+                    planned_state: Bytes::new(),
+                    changes: vec![],
+                    requires_replace: false,
+                    diagnostics: self.with_warnings(self.error_to_diagnostics(e), notes),
+                    replace_reasons: vec![],
+                    impact: None,
+                }))
+            },
+        }
+    }
+
+    #[instrument(skip(self, request), name = "grpc.pre_apply_check", fields(request_id = tracing::field::Empty, request_bytes = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn pre_apply_check(
+        &self,
+        request: tonic::Request<crate::generated::PreApplyCheckRequest>,
+    ) -> Result<tonic::Response<crate::generated::PreApplyCheckResponse>, tonic::Status> {
+        let session = session_id(&request);
+        let req = request.into_inner();
+        debug!(resource_type = %req.resource_type, "PreApplyCheck called");
+        let mut notes = ResponseNotes::default();
+        let checked = self.check_request(
+            "pre_apply_check",
+            &[("planned_state", &req.planned_state)],
+            &mut notes,
+        );
+        let planned_state = self
+            .parse_payload(
+                "pre_apply_check",
+                "planned_state",
+                &req.planned_state,
+                &mut notes,
+            )
+            .unwrap_or_default();
+
+        match self
+            .request_context(req.client_capabilities, session)
+            .scope(
+                self.measured("pre_apply_check", Some(&req.resource_type), async {
+                    checked?;
+                    let planned_state =
+                        self.decode_state(&req.resource_type, planned_state).await?;
+                    Ok::<_, ProviderError>(
+                        self.provider
+                            .pre_apply_check(&req.resource_type, &planned_state)
+                            .await,
+                    )
+                }),
+            )
+            .await
+        {
+            Ok(diagnostics) => {
+                let has_errors = diagnostics
+                    .iter()
+                    .any(|d| matches!(d.severity, DiagnosticSeverity::Error));
+                if has_errors {
+                    warn!(resource_type = %req.resource_type, diagnostics = diagnostics.len(), "PreApplyCheck refused the change");
+                } else {
+                    info!(resource_type = %req.resource_type, "PreApplyCheck passed");
+                }
+                Ok(tonic::Response::new(
+                    crate::generated::PreApplyCheckResponse {
+                        diagnostics: self
+                            .with_warnings(self.diagnostics_to_proto(diagnostics), notes),
+                    },
+                ))
+            },
+            Err(e) => {
+                error!(resource_type = %req.resource_type, error = %e, "PreApplyCheck failed");
+                Ok(tonic::Response::new(
+                    crate::generated::PreApplyCheckResponse {
+                        diagnostics: self.with_warnings(self.error_to_diagnostics(e), notes),
+                    },
+                ))
+            },
+        }
+    }
+
+    #[instrument(skip(self, request), name = "grpc.create", fields(request_id = tracing::field::Empty, request_bytes = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn create(
+        &self,
+        request: tonic::Request<crate::generated::CreateRequest>,
+    ) -> Result<tonic::Response<crate::generated::CreateResponse>, tonic::Status> {
+        let session = session_id(&request);
+        let key = idempotency_key(&request);
+        let req = request.into_inner();
+        info!(resource_type = %req.resource_type, "Create called");
+        let mut notes = ResponseNotes::default();
+        let checked = self.check_request(
+            "create",
+            &[
+                ("planned_state", &req.planned_state),
+                ("config", &req.config),
+            ],
+            &mut notes,
+        );
+        let planned_state = self
+            .parse_payload("create", "planned_state", &req.planned_state, &mut notes)
+            .unwrap_or_default();
+        let config = self
+            .parse_payload("create", "config", &req.config, &mut notes)
+            .unwrap_or_default();
+        self.expect_state("planned state", &planned_state, &mut notes);
+
+        let token = idempotency_token("create", &req.resource_type, key.as_deref());
+        let context = self
+            .request_context(req.client_capabilities, session)
+            .with_idempotency_token(token.clone());
+
+        match context
+            .scope(self.measured(
+                "create",
+                Some(&req.resource_type),
+                self.deduplicated(
+                    &req.resource_type,
+                    key.as_ref().map(|_| &token[..]),
+                    async {
+                        checked?;
+                        let _permit = self.parallelism_permit(&req.resource_type).await;
+                        let planned_state =
+                            self.decode_state(&req.resource_type, planned_state).await?;
+                        self.invalidate_session_reads(&req.resource_type, None);
+                        let request = CreateRequest::new(&req.resource_type, planned_state)
+                            .with_config(config);
+                        let mut state = self.provider.handle_create(request).await?;
+                        let schema = self.current_schema().await.unwrap_or_default();
+                        self.normalize(&schema, &req.resource_type, [&mut state]);
+                        self.check_state(&schema, "create", &req.resource_type, &state, &mut notes);
+                        self.encode_state(&req.resource_type, state).await
+                    },
+                ),
+            ))
+            .await
+        {
+            Ok(state) => {
+                self.expect_state("state returned by create", &state, &mut notes);
+                let state = self.encode_payload("create", &state);
+                self.check_response("create", &[("state", &state[..])], &mut notes);
+                info!(resource_type = %req.resource_type, "Create completed successfully");
+                Ok(tonic::Response::new(crate::generated::CreateResponse {
+                    state,
+                    diagnostics: self.with_warnings(vec![], notes),
+                }))
+            },
+            Err(e) => {
+                error!(resource_type = %req.resource_type, error = %e, "Create failed");
+                Ok(tonic::Response::new(crate::generated::CreateResponse {
+                    state: Bytes::new(),
+                    diagnostics: self.with_warnings(self.error_to_diagnostics(e), notes),
+                }))
+            },
+        }
+    }
+
+    #[instrument(skip(self, request), name = "grpc.read", fields(request_id = tracing::field::Empty, request_bytes = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn read(
+        &self,
+        request: tonic::Request<crate::generated::ReadRequest>,
+    ) -> Result<tonic::Response<crate::generated::ReadResponse>, tonic::Status> {
+        let session = session_id(&request);
+        let req = request.into_inner();
+        debug!(resource_type = %req.resource_type, "Read called");
+        let mut notes = ResponseNotes::default();
+        let checked =
+            self.check_request("read", &[("current_state", &req.current_state)], &mut notes);
+        let current_state = self
+            .parse_payload("read", "current_state", &req.current_state, &mut notes)
+            .unwrap_or_default();
+        self.expect_state("current state", &current_state, &mut notes);
+
+        match self
+            .request_context(req.client_capabilities, session.clone())
+            .scope(self.measured("read", Some(&req.resource_type), async {
+                checked?;
+                let current_state = self.decode_state(&req.resource_type, current_state).await?;
+                let session_cache = self.session_cache.as_ref().zip(session.as_deref());
+                let session_key = session_cache.map(|_| current_state.clone());
+                let coalesce_key = self.coalescer.as_ref().map(|_| current_state.clone());
+                let upstream = async {
+                    let _guard = self
+                        .locks
+                        .lock_state(&req.resource_type, &current_state)
+                        .await;
+                    match self.read_cache(&req.resource_type).await {
+                        Some(cache) => {
+                            let key = current_state.clone();
+                            let result = cache
+                                .get_or_read(&req.resource_type, &key, || async {
+                                    let _permit = self.parallelism_permit(&req.resource_type).await;
+                                    self.provider
+                                        .handle_read(ReadRequest::new(
+                                            &req.resource_type,
+                                            current_state,
+                                        ))
+                                        .await
+                                })
+                                .await?;
+                            // Only complete results are worth serving again
+                            if !result.is_current() {
+                                cache.invalidate(&req.resource_type, &key);
+                            }
+                            Ok(result)
+                        },
+                        None => {
+                            let _permit = self.parallelism_permit(&req.resource_type).await;
+                            self.provider
+                                .handle_read(ReadRequest::new(&req.resource_type, current_state))
+                                .await
+                        },
+                    }
+                };
+                // Boxed, or the nested read futures overflow small stacks
+                let coalesced = Box::pin(async {
+                    match (&self.coalescer, &coalesce_key) {
+                        (Some(coalescer), Some(key)) => {
+                            coalescer.read(&req.resource_type, key, || upstream).await
+                        },
+                        _ => upstream.await,
+                    }
+                });
+                let ReadResult {
+                    mut state,
+                    status,
+                    diagnostics,
+                } = match (session_cache, &session_key) {
+                    (Some((cache, session)), Some(key)) => {
+                        let result = cache
+                            .read(session, &req.resource_type, key, || coalesced)
+                            .await?;
+                        if !result.is_current() {
+                            cache.invalidate(&req.resource_type, key);
+                        }
+                        result
+                    },
+                    _ => coalesced.await?,
+                };
+                let schema = self.current_schema().await.unwrap_or_default();
+                self.normalize(&schema, &req.resource_type, [&mut state]);
+                self.check_state(&schema, "read", &req.resource_type, &state, &mut notes);
+                let state = self.encode_state(&req.resource_type, state).await?;
+                Ok((state, status, diagnostics))
+            }))
+            .await
+        {
+            Ok((state, status, diagnostics)) => {
+                let state = self.encode_payload("read", &state);
+                self.check_response("read", &[("state", &state[..])], &mut notes);
+                debug!(resource_type = %req.resource_type, ?status, "Read completed successfully");
+                Ok(tonic::Response::new(crate::generated::ReadResponse {
+                    state,
+                    diagnostics: self.with_warnings(self.diagnostics_to_proto(diagnostics), notes),
+                    status: crate::generated::ReadStatus::from(status).into(),
+                }))
+            },
+            Err(e) => {
+                error!(resource_type = %req.resource_type, error = %e, "Read failed");
+                Ok(tonic::Response::new(crate::generated::ReadResponse {
+                    state: Bytes::new(),
+                    diagnostics: self.with_warnings(self.error_to_diagnostics(e), notes),
+                    status: crate::generated::ReadStatus::Current.into(),
+                }))
+            },
+        }
+    }
+
+    #[instrument(skip(self, request), name = "grpc.update", fields(request_id = tracing::field::Empty, request_bytes = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn update(
+        &self,
+        request: tonic::Request<crate::generated::UpdateRequest>,
+    ) -> Result<tonic::Response<crate::generated::UpdateResponse>, tonic::Status> {
+        let session = session_id(&request);
+        let key = idempotency_key(&request);
+        let req = request.into_inner();
+        info!(resource_type = %req.resource_type, "Update called");
+        let mut notes = ResponseNotes::default();
+        let checked = self.check_request(
+            "update",
+            &[
+                ("prior_state", &req.prior_state),
+                ("planned_state", &req.planned_state),
+                ("config", &req.config),
+            ],
+            &mut notes,
+        );
+        let prior_state = self
+            .parse_payload("update", "prior_state", &req.prior_state, &mut notes)
+            .unwrap_or_default();
+        let planned_state = self
+            .parse_payload("update", "planned_state", &req.planned_state, &mut notes)
+            .unwrap_or_default();
+        let config = self
+            .parse_payload("update", "config", &req.config, &mut notes)
+            .unwrap_or_default();
+        self.expect_state("prior state", &prior_state, &mut notes);
+        self.expect_state("planned state", &planned_state, &mut notes);
+
+        let token = idempotency_token("update", &req.resource_type, key.as_deref());
+        let context = self
+            .request_context(req.client_capabilities, session)
+            .with_idempotency_token(token.clone());
+
+        match context
+            .scope(self.measured(
+                "update",
+                Some(&req.resource_type),
+                self.deduplicated(&req.resource_type, key.as_ref().map(|_| &token[..]), async {
+                checked?;
+                let prior_state = self.decode_state(&req.resource_type, prior_state).await?;
+                let planned_state = self.decode_state(&req.resource_type, planned_state).await?;
+                let _guard = self
+                    .locks
+                    .lock_state(&req.resource_type, &prior_state)
+                    .await;
+                let _permit = self.parallelism_permit(&req.resource_type).await;
+                // Reads of the resource wait for its lock, so none can cache
+                // the prior state again before the update finishes
+                if let Some(cache) = self.read_cache(&req.resource_type).await {
+                    cache.invalidate(&req.resource_type, &prior_state);
+                }
+                self.invalidate_session_reads(&req.resource_type, Some(&prior_state));
+
+                let request = UpdateRequest::new(&req.resource_type, prior_state, planned_state)
+                    .with_config(config);
+                debug!(resource_type = %req.resource_type, operations = request.patch.operations().len(), "Computed update patch");
+
+                let mut state = self.provider.handle_update(request).await?;
+                let schema = self.current_schema().await.unwrap_or_default();
+                self.normalize(&schema, &req.resource_type, [&mut state]);
+                self.check_state(&schema, "update", &req.resource_type, &state, &mut notes);
+                self.encode_state(&req.resource_type, state).await
+            }),
+            ))
+            .await
+        {
+            Ok(state) => {
+                self.expect_state("state returned by update", &state, &mut notes);
+                let state = self.encode_payload("update", &state);
+                self.check_response("update", &[("state", &state[..])], &mut notes);
+                info!(resource_type = %req.resource_type, "Update completed successfully");
+                Ok(tonic::Response::new(crate::generated::UpdateResponse {
+                    state,
+                    diagnostics: self.with_warnings(vec![], notes),
+                }))
+            },
+            Err(e) => {
+                error!(resource_type = %req.resource_type, error = %e, "Update failed");
+                Ok(tonic::Response::new(crate::generated::UpdateResponse {
+                    state: Bytes::new(),
+                    diagnostics: self.with_warnings(self.error_to_diagnostics(e), notes),
+                }))
+            },
+        }
+    }
+
+    #[instrument(skip(self, request), name = "grpc.delete", fields(request_id = tracing::field::Empty, request_bytes = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn delete(
+        &self,
+        request: tonic::Request<crate::generated::DeleteRequest>,
+    ) -> Result<tonic::Response<crate::generated::DeleteResponse>, tonic::Status> {
+        let session = session_id(&request);
+        let req = request.into_inner();
+        info!(resource_type = %req.resource_type, "Delete called");
+        let mut notes = ResponseNotes::default();
+        let checked = self.check_request(
+            "delete",
+            &[
+                ("current_state", &req.current_state),
+                ("config", &req.config),
+            ],
+            &mut notes,
+        );
+        let current_state = self
+            .parse_payload("delete", "current_state", &req.current_state, &mut notes)
+            .unwrap_or_default();
+        let config = self
+            .parse_payload("delete", "config", &req.config, &mut notes)
+            .unwrap_or_default();
+        self.expect_state("current state", &current_state, &mut notes);
+
+        match self
+            .request_context(req.client_capabilities, session)
+            .scope(self.measured("delete", Some(&req.resource_type), async {
+                checked?;
+                let current_state = self.decode_state(&req.resource_type, current_state).await?;
+                let _guard = self
+                    .locks
+                    .lock_state(&req.resource_type, &current_state)
+                    .await;
+                let _permit = self.parallelism_permit(&req.resource_type).await;
+                if let Some(cache) = self.read_cache(&req.resource_type).await {
+                    cache.invalidate(&req.resource_type, &current_state);
+                }
+                self.invalidate_session_reads(&req.resource_type, Some(&current_state));
+                self.provider
+                    .handle_delete(
+                        DeleteRequest::new(&req.resource_type, current_state.clone())
+                            .with_config(config),
+                    )
+                    .await?;
+                self.forget_results(&req.resource_type, &current_state);
+                Ok(())
+            }))
+            .await
+        {
+            Ok(()) => {
+                info!(resource_type = %req.resource_type, "Delete completed successfully");
+                Ok(tonic::Response::new(crate::generated::DeleteResponse {
+                    diagnostics: self.with_warnings(vec![], notes),
+                }))
+            },
+            Err(e) => {
+                error!(resource_type = %req.resource_type, error = %e, "Delete failed");
+                Ok(tonic::Response::new(crate::generated::DeleteResponse {
+                    diagnostics: self.with_warnings(self.error_to_diagnostics(e), notes),
+                }))
+            },
+        }
+    }
+
+    #[instrument(skip(self, request), name = "grpc.import_resource_state", fields(request_id = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn import_resource_state(
+        &self,
+        request: tonic::Request<crate::generated::ImportResourceStateRequest>,
+    ) -> Result<tonic::Response<crate::generated::ImportResourceStateResponse>, tonic::Status> {
+        let session = session_id(&request);
+        let req = request.into_inner();
+        info!(resource_type = %req.resource_type, id = %req.id, "ImportResourceState called");
+
+        match self
+            .importer(self.request_context(req.client_capabilities, session))
+            .await
+            .import(&req.resource_type, &req.id)
+            .await
+        {
+            Ok(imported) => {
+                info!(
+                    resource_type = %req.resource_type,
+                    id = %req.id,
+                    imported_count = imported.len(),
+                    "ImportResourceState completed"
+                );
+                let imported: Vec<_> = imported
+                    .into_iter()
+                    .map(|r| crate::generated::ImportedResource {
+                        resource_type: r.resource_type,
+                        state: self.encode_payload("import_resource_state", &r.state),
+                    })
+                    .collect();
+                let mut notes = ResponseNotes::default();
+                let payloads: Vec<_> = imported.iter().map(|r| ("state", &r.state[..])).collect();
+                self.check_response("import_resource_state", &payloads, &mut notes);
+                Ok(tonic::Response::new(
+                    crate::generated::ImportResourceStateResponse {
+                        imported,
+                        diagnostics: self.with_warnings(vec![], notes),
+                    },
+                ))
+            },
+            Err(e) => {
+                error!(resource_type = %req.resource_type, id = %req.id, error = %e, "ImportResourceState failed");
+                Ok(tonic::Response::new(
+                    crate::generated::ImportResourceStateResponse {
+                        imported: vec![],
+                        diagnostics: self.error_to_diagnostics(e),
+                    },
+                ))
+            },
+        }
+    }
+
+    type BulkImportResourceStateStream =
+        tonic::codegen::BoxStream<crate::generated::BulkImportResult>;
+
+    #[instrument(skip(self, request), name = "grpc.bulk_import_resource_state", fields(request_id = tracing::field::Empty))]
+    async fn bulk_import_resource_state(
+        &self,
+        request: tonic::Request<crate::generated::BulkImportResourceStateRequest>,
+    ) -> Result<tonic::Response<Self::BulkImportResourceStateStream>, tonic::Status> {
+        use futures_util::StreamExt;
+
+        let session = session_id(&request);
+        let req = request.into_inner();
+        info!(
+            resource_type = %req.resource_type,
+            ids = req.ids.len(),
+            query = !req.query.is_empty(),
+            "BulkImportResourceState called"
+        );
+        let query = match (req.ids.is_empty(), req.query.is_empty()) {
+            (true, true) => return Err(tonic::Status::invalid_argument("Set ids or a query")),
+            (false, false) => {
+                return Err(tonic::Status::invalid_argument(
+                    "Set either ids or a query, not both",
+                ))
+            },
+            (true, false) => Some(decode_json(&req.query).map_err(|e| {
+                tonic::Status::invalid_argument(format!("query is not valid JSON: {}", e))
+            })?),
+            (false, true) => None,
+        };
+        self.payload_metrics
+            .record_request(BULK_IMPORT, req.query.len());
+
+        let importer = Arc::new(
+            self.importer(self.request_context(req.client_capabilities, session))
+                .await,
+        );
+        let resource_type = req.resource_type;
+        let stream = match query {
+            Some(query) => futures_util::stream::once(async move {
+                let results = match importer.query(&resource_type, query).await {
+                    Ok(imported) => imported
+                        .into_iter()
+                        .map(|resource| importer.result(String::new(), Ok(vec![resource])))
+                        .collect(),
+                    Err(e) => vec![importer.result(String::new(), Err(e))],
+                };
+                futures_util::stream::iter(results)
+            })
+            .flatten()
+            .boxed(),
+            None => futures_util::stream::iter(req.ids)
+                .map(move |id| {
+                    let importer = Arc::clone(&importer);
+                    let resource_type = resource_type.clone();
+                    async move {
+                        let imported = importer.import(&resource_type, &id).await;
+                        importer.result(id, imported)
+                    }
+                })
+                .buffer_unordered(BULK_IMPORT_CONCURRENCY)
+                .boxed(),
+        };
+        Ok(tonic::Response::new(Box::pin(stream.map(Ok))))
+    }
+
+    #[instrument(skip(self, request), name = "grpc.validate_data_source_config", fields(request_id = tracing::field::Empty, request_bytes = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn validate_data_source_config(
+        &self,
+        request: tonic::Request<crate::generated::ValidateDataSourceConfigRequest>,
+    ) -> Result<tonic::Response<crate::generated::ValidateDataSourceConfigResponse>, tonic::Status>
+    {
+        let req = request.into_inner();
+        debug!(data_source_type = %req.data_source_type, "ValidateDataSourceConfig called");
+        let mut notes = ResponseNotes::default();
+        let checked = self.check_request(
+            "validate_data_source_config",
+            &[("config", &req.config)],
+            &mut notes,
+        );
+        let config = self
+            .parse_payload(
+                "validate_data_source_config",
+                "config",
+                &req.config,
+                &mut notes,
+            )
+            .unwrap_or_default();
+
+        match self
+            .scoped(self.measured(
+                "validate_data_source_config",
+                Some(&req.data_source_type),
+                async {
+                    checked?;
+                    self.provider
+                        .validate_data_source_config(&req.data_source_type, config)
+                        .await
+                },
+            ))
+            .await
+        {
+            Ok(diagnostics) => {
+                let has_errors = diagnostics
+                    .iter()
+                    .any(|d| matches!(d.severity, DiagnosticSeverity::Error));
+                if has_errors {
+                    warn!(data_source_type = %req.data_source_type, diagnostics = diagnostics.len(), "ValidateDataSourceConfig completed with errors");
+                } else {
+                    info!(data_source_type = %req.data_source_type, "ValidateDataSourceConfig completed successfully");
+                }
+                Ok(tonic::Response::new(
+                    crate::generated::ValidateDataSourceConfigResponse {
+                        diagnostics: self
+                            .with_warnings(self.diagnostics_to_proto(diagnostics), notes),
+                    },
+                ))
+            },
+            Err(e) => {
+                error!(data_source_type = %req.data_source_type, error = %e, "ValidateDataSourceConfig failed");
+                Ok(tonic::Response::new(
+                    crate::generated::ValidateDataSourceConfigResponse {
+                        diagnostics: self.with_warnings(self.error_to_diagnostics(e), notes),
+                    },
+                ))
+            },
+        }
+    }
+
+    #[instrument(skip(self, request), name = "grpc.read_data_source", fields(request_id = tracing::field::Empty, request_bytes = tracing::field::Empty, response_bytes = tracing::field::Empty))]
+    async fn read_data_source(
+        &self,
+        request: tonic::Request<crate::generated::ReadDataSourceRequest>,
+    ) -> Result<tonic::Response<crate::generated::ReadDataSourceResponse>, tonic::Status> {
+        let session = session_id(&request);
+        let req = request.into_inner();
+        debug!(data_source_type = %req.data_source_type, "ReadDataSource called");
+        let mut notes = ResponseNotes::default();
+        let checked =
+            self.check_request("read_data_source", &[("config", &req.config)], &mut notes);
+        let config = self
+            .parse_payload("read_data_source", "config", &req.config, &mut notes)
+            .unwrap_or_default();
+
+        match self
+            .request_context(req.client_capabilities, session.clone())
+            .scope(
+                self.measured("read_data_source", Some(&req.data_source_type), async {
+                    checked?;
+                    let session_cache = self.session_cache.as_ref().zip(session.as_deref());
+                    let session_key = session_cache.map(|_| config.clone());
+                    let coalesced = async {
+                        match &self.coalescer {
+                            Some(coalescer) => {
+                                let key = config.clone();
+                                coalescer
+                                    .read_data_source(&req.data_source_type, &key, || {
+                                        self.provider.handle_read_data_source(
+                                            ReadDataSourceRequest::new(
+                                                &req.data_source_type,
+                                                config,
+                                            ),
+                                        )
+                                    })
+                                    .await
+                            },
+                            None => {
+                                self.provider
+                                    .handle_read_data_source(ReadDataSourceRequest::new(
+                                        &req.data_source_type,
+                                        config,
+                                    ))
+                                    .await
+                            },
+                        }
+                    };
+                    match (session_cache, &session_key) {
+                        (Some((cache, session)), Some(key)) => {
+                            let result = cache
+                                .read_data_source(session, &req.data_source_type, key, || coalesced)
+                                .await?;
+                            if !result.is_current() {
+                                cache.invalidate_data_source(&req.data_source_type, key);
+                            }
+                            Ok(result)
+                        },
+                        _ => coalesced.await,
+                    }
+                }),
+            )
+            .await
+        {
+            Ok(result) => {
+                let schema = self.current_schema().await.unwrap_or_default();
+                self.check_data_source_state(
+                    &schema,
+                    &req.data_source_type,
+                    &result.state,
+                    &mut notes,
+                );
+                let state = self.encode_payload("read_data_source", &result.state);
+                self.check_response("read_data_source", &[("state", &state[..])], &mut notes);
+                info!(data_source_type = %req.data_source_type, status = ?result.status, "ReadDataSource completed successfully");
+                Ok(tonic::Response::new(
+                    crate::generated::ReadDataSourceResponse {
+                        state,
+                        diagnostics: self
+                            .with_warnings(self.diagnostics_to_proto(result.diagnostics), notes),
+                        status: crate::generated::ReadStatus::from(result.status).into(),
+                    },
+                ))
+            },
+            Err(e) => {
+                error!(data_source_type = %req.data_source_type, error = %e, "ReadDataSource failed");
+                Ok(tonic::Response::new(
+                    crate::generated::ReadDataSourceResponse {
+                        state: Bytes::new(),
+                        diagnostics: self.with_warnings(self.error_to_diagnostics(e), notes),
+                        status: crate::generated::ReadStatus::Current.into(),
+                    },
+                ))
+            },
+        }
+    }
+
+    type EventsStream = tonic::codegen::BoxStream<crate::generated::ProviderEvent>;
+
+    #[instrument(skip(self, request), name = "grpc.events")]
+    async fn events(
+        &self,
+        request: tonic::Request<tonic::Streaming<crate::generated::EventsRequest>>,
+    ) -> Result<tonic::Response<Self::EventsStream>, tonic::Status> {
+        use futures_util::StreamExt;
+
+        info!("Events stream opened");
+        let mut acks = request.into_inner();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            while let Ok(Some(request)) = acks.message().await {
+                events.ack(request.ack_sequence);
+            }
+        });
+
+        // End the stream on shutdown, or the server would wait for it to close
+        let shutdown = self.shutdown.clone();
+        let catalog = self.catalog.clone();
+        let stream =
+            futures_util::stream::unfold(self.events.subscribe(), |mut subscription| async {
+                let events = subscription.next().await;
+                Some((futures_util::stream::iter(events), subscription))
+            })
+            .flatten()
+            .map(move |mut event| {
+                if let Some(catalog) = &catalog {
+                    event.diagnostic = catalog.localize(event.diagnostic);
+                }
+                Ok(event_to_proto(event))
+            })
+            .take_until(async move { shutdown.triggered().await });
+        Ok(tonic::Response::new(Box::pin(stream)))
+    }
+}
+
+/// Run an operation with its name, resource type and a new request id in the
+/// [`Context`], reporting it to `sink` if there is one.
+async fn measure<T, F>(
+    sink: Option<&dyn MetricsSink>,
+    operation: &str,
+    resource_type: Option<&str>,
+    call: F,
+) -> Result<T, ProviderError>
+where
+    F: std::future::Future<Output = Result<T, ProviderError>>,
+{
+    let request_id = next_request_id();
+    tracing::Span::current().record("request_id", request_id.as_str());
+    let mut context = Context::current()
+        .with_operation(operation)
+        .with_request_id(request_id);
+    if let Some(resource_type) = resource_type {
+        context = context.with_resource_type(resource_type);
+    }
+    let call = context.scope(call);
+
+    let Some(sink) = sink else {
+        return call.await;
+    };
+    sink.on_operation_start(operation, resource_type);
+    let started = Instant::now();
+    let result = call.await;
+    sink.on_operation_finish(
+        operation,
+        resource_type,
+        started.elapsed(),
+        Outcome::from(&result),
+    );
+    result
+}
+
+/// A process-unique id for an operation: the process id and a counter.
+fn next_request_id() -> String {
+    static NEXT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+    format!(
+        "{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    )
+}
+
+/// Diagnostics as sent to core, translated with `catalog` if there is one.
+/// Lists longer than [`DEFAULT_DIAGNOSTIC_LIMIT`] end in a summary of the rest.
+fn diagnostics_to_proto(
+    catalog: Option<&Catalog>,
+    diagnostics: Vec<Diagnostic>,
+) -> Vec<crate::generated::Diagnostic> {
+    let mut diagnostics = Diagnostics::from(diagnostics);
+    diagnostics.truncate(DEFAULT_DIAGNOSTIC_LIMIT);
+    diagnostics
+        .into_iter()
+        .map(|d| match catalog {
+            Some(catalog) => diagnostic_to_proto(catalog.localize(d)),
+            None => diagnostic_to_proto(d),
+        })
+        .collect()
+}
+
+/// A provider error as an error diagnostic carrying its gRPC code and kind.
+fn error_to_diagnostics(
+    catalog: Option<&Catalog>,
+    err: ProviderError,
+) -> Vec<crate::generated::Diagnostic> {
+    diagnostics_to_proto(
+        catalog,
+        vec![sdk_diagnostic(
+            DiagnosticSeverity::Error,
+            error_message(&err),
+        )],
+    )
+    .into_iter()
+    .map(|d| crate::generated::Diagnostic {
+        code: err.code() as i32,
+        error_kind: err.kind().to_string(),
+        ..d
+    })
+    .collect()
+}
+
+fn diagnostic_to_proto(d: Diagnostic) -> crate::generated::Diagnostic {
+    crate::generated::Diagnostic {
+        severity: match d.severity {
+            DiagnosticSeverity::Error => crate::generated::diagnostic::Severity::Error as i32,
+            DiagnosticSeverity::Warning => crate::generated::diagnostic::Severity::Warning as i32,
+        },
+        summary: d.summary,
+        detail: d.detail.unwrap_or_default(),
+        attribute: d.attribute.unwrap_or_default(),
+        suggestion: d.suggestion.unwrap_or_default(),
+        code: 0,
+        error_kind: String::new(),
+    }
+}
+
+fn event_to_proto(event: Event) -> crate::generated::ProviderEvent {
+    crate::generated::ProviderEvent {
+        sequence: event.sequence,
+        kind: event.kind,
+        diagnostic: Some(diagnostic_to_proto(event.diagnostic)),
+        resource_type: event.resource_type.unwrap_or_default(),
+    }
+}
+
+/// Default for [`ServeOptions::idempotency_ttl`].
+const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(600);
+
+/// Options for configuring the provider server.
+#[derive(Clone)]
+pub struct ServeOptions {
+    /// Timeout for graceful shutdown. After receiving a shutdown signal,
+    /// the server will wait this long for in-flight requests to complete.
+    /// Default: 30 seconds.
+    pub shutdown_timeout: Duration,
+
+    /// Also serve gRPC-Web and the read-only JSON gateway (see [`crate::gateway`]).
+    /// Default: false.
+    #[cfg(feature = "gateway")]
+    pub gateway: bool,
+
+    /// Key used to sign the handshake when core passes a nonce (see
+    /// [`crate::attestation`]).
+    /// Default: none, falling back to the key in the environment.
+    #[cfg(feature = "attestation")]
+    pub handshake_signer: Option<crate::attestation::HandshakeSigner>,
+
+    /// Keep decimals in state and configuration payloads exactly as written,
+    /// instead of rounding them to the nearest `f64` (see [`crate::number`]).
+    /// Default: false.
+    #[cfg(feature = "arbitrary-precision")]
+    pub exact_numbers: bool,
+
+    /// Resolvers for `secret_ref` attributes in the provider configuration.
+    /// Default: [`SecretResolvers::standard`] (`env` and `file`).
+    pub secret_resolvers: SecretResolvers,
+
+    /// Transforms resource state on its way to and from core (see [`crate::codec`]).
+    /// Default: none.
+    pub state_codec: Option<Arc<dyn StateCodec>>,
+
+    /// How long successful creates and updates are remembered by idempotency
+    /// token, so retried requests are not applied twice (see [`crate::idempotency`]).
+    /// Default: 10 minutes. Zero disables deduplication.
+    pub idempotency_ttl: Duration,
+
+    /// Locks that serialize operations on the same resource (see [`crate::lock`]).
+    /// Default: [`ResourceLocks::new`], keyed by the `id` attribute.
+    pub resource_locks: ResourceLocks,
+
+    /// Lint the schema and metadata before emitting the handshake, and refuse
+    /// to start if they have errors (see [`crate::lint`]).
+    /// Default: true.
+    pub self_check: bool,
+
+    /// Report SDK-internal anomalies (malformed JSON payloads, unexpectedly
+    /// empty states) as warning diagnostics on the response, in addition to
+    /// logging them.
+    /// Default: false.
+    pub internal_warnings: bool,
+
+    /// Validate the provider configuration against the provider schema (see
+    /// [`crate::validation::validate`]) before calling the provider's own
+    /// `validate_provider_config`, and return both sets of diagnostics.
+    /// Default: true.
+    pub validate_provider_config: bool,
+
+    /// Validate resource configurations against the resource schema before
+    /// calling the provider's own `validate_resource_config`, and return both
+    /// sets of diagnostics.
+    /// Default: false.
+    pub validate_resource_config: bool,
+
+    /// Validate the states returned by `create`, `update` and `read` against
+    /// the resource schema, and `read_data_source` results against the data
+    /// source schema (see [`crate::validation::validate_state`]), and report
+    /// violations as error diagnostics. The state is still returned, so a
+    /// created resource is not lost.
+    /// Default: false.
+    pub validate_state: bool,
+
+    /// Soft and hard size limits for state and configuration payloads (see
+    /// [`crate::payload`]).
+    /// Default: no limits.
+    pub payload_limits: PayloadLimits,
+
+    /// Per-RPC payload size counters (see [`crate::payload`]).
+    /// Default: fresh counters.
+    pub payload_metrics: PayloadMetrics,
+
+    /// Receives the duration and outcome of every provider operation (see
+    /// [`crate::metrics`]).
+    /// Default: none.
+    pub metrics_sink: Option<Arc<dyn MetricsSink>>,
+
+    /// Translates response diagnostics into the operator's locale (see
+    /// [`crate::i18n`]).
+    /// Default: none (English).
+    pub catalog: Option<Catalog>,
+
+    /// Collapse concurrent identical `Read` and `ReadDataSource` requests into
+    /// a single provider call (see [`crate::cache::ReadCoalescer`]).
+    /// Default: false.
+    pub read_coalescing: bool,
+
+    /// Keep `Read` and `ReadDataSource` results for the rest of the plan or
+    /// apply run named by the request's [`SESSION_ID_METADATA`], until core
+    /// ends it with `EndSession` (see [`crate::cache::SessionCache`]). Requests
+    /// without a session id are never cached.
+    /// Default: true.
+    pub session_cache: bool,
+
+    /// Maximum number of requests served at once; further requests wait.
+    /// Default: unlimited.
+    pub max_concurrency: Option<usize>,
+
+    /// Initialize logging in this format when serving starts (see
+    /// [`crate::logging::try_init_logging_with_format`]). Has no effect if the
+    /// provider already initialized logging.
+    /// Default: none, leaving logging to the provider.
+    pub log_format: Option<LogFormat>,
+
+    /// Bounds the CPU-heavy work providers run with
+    /// [`Context::run_blocking`] (see [`crate::blocking`]).
+    /// Default: one task per available CPU.
+    pub blocking_budget: BlockingBudget,
+
+    /// The provider-process key/value store providers reach through
+    /// [`Context::local_store`], cleared when the provider stops (see
+    /// [`crate::store`]).
+    /// Default: in memory.
+    pub local_store: LocalStore,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self {
+            shutdown_timeout: Duration::from_secs(30),
+            #[cfg(feature = "gateway")]
+            gateway: false,
+            #[cfg(feature = "attestation")]
+            handshake_signer: None,
+            #[cfg(feature = "arbitrary-precision")]
+            exact_numbers: false,
+            secret_resolvers: SecretResolvers::standard(),
+            state_codec: None,
+            idempotency_ttl: DEFAULT_IDEMPOTENCY_TTL,
+            resource_locks: ResourceLocks::new(),
+            self_check: true,
+            internal_warnings: false,
+            validate_provider_config: true,
+            validate_resource_config: false,
+            validate_state: false,
+            payload_limits: PayloadLimits::new(),
+            payload_metrics: PayloadMetrics::new(),
+            metrics_sink: None,
+            catalog: None,
+            read_coalescing: false,
+            session_cache: true,
+            max_concurrency: None,
+            log_format: None,
+            blocking_budget: BlockingBudget::default(),
+            local_store: LocalStore::memory(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ServeOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut s = f.debug_struct("ServeOptions");
+        s.field("shutdown_timeout", &self.shutdown_timeout);
+        #[cfg(feature = "gateway")]
+        s.field("gateway", &self.gateway);
+        #[cfg(feature = "attestation")]
+        s.field("handshake_signer", &self.handshake_signer);
+        #[cfg(feature = "arbitrary-precision")]
+        s.field("exact_numbers", &self.exact_numbers);
+        s.field("secret_resolvers", &self.secret_resolvers)
+            .field("state_codec", &self.state_codec.is_some())
+            .field("idempotency_ttl", &self.idempotency_ttl)
+            .field("resource_locks", &self.resource_locks)
+            .field("self_check", &self.self_check)
+            .field("internal_warnings", &self.internal_warnings)
+            .field("validate_provider_config", &self.validate_provider_config)
+            .field("validate_resource_config", &self.validate_resource_config)
+            .field("validate_state", &self.validate_state)
+            .field("payload_limits", &self.payload_limits)
+            .field("payload_metrics", &self.payload_metrics)
+            .field("metrics_sink", &self.metrics_sink.is_some())
+            .field(
+                "catalog",
+                &self.catalog.as_ref().map(|catalog| catalog.locale()),
+            )
+            .field("read_coalescing", &self.read_coalescing)
+            .field("session_cache", &self.session_cache)
+            .field("max_concurrency", &self.max_concurrency)
+            .field("log_format", &self.log_format)
+            .field("blocking_budget", &self.blocking_budget.max_tasks())
+            .field("local_store", &self.local_store)
+            .finish()
+    }
+}
+
+impl ServeOptions {
+    /// Create new serve options with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Default options with overrides from the environment, as used by
+    /// [`serve`] and [`serve_on`]. See [`with_env_overrides`](Self::with_env_overrides).
+    pub fn from_env() -> Result<Self, ProviderError> {
+        Self::default().with_env_overrides()
+    }
+
+    /// Override options set in code with those set in the environment, so
+    /// operators can tune a deployed provider without a rebuild:
+    ///
+    /// | Variable | Option |
+    /// |---|---|
+    /// | `HEMMER_PROVIDER_SHUTDOWN_TIMEOUT` | [`shutdown_timeout`](Self::shutdown_timeout) |
+    /// | `HEMMER_PROVIDER_IDEMPOTENCY_TTL` | [`idempotency_ttl`](Self::idempotency_ttl) |
+    /// | `HEMMER_PROVIDER_MAX_CONCURRENCY` | [`max_concurrency`](Self::max_concurrency) (`0` for unlimited) |
+    /// | `HEMMER_PROVIDER_LOG_FORMAT` | [`log_format`](Self::log_format) (`text`, `compact` or `json`) |
+    /// | `HEMMER_PROVIDER_MAX_BLOCKING_TASKS` | [`blocking_budget`](Self::blocking_budget) |
+    /// | `HEMMER_PROVIDER_SELF_CHECK` | [`self_check`](Self::self_check) |
+    /// | `HEMMER_PROVIDER_INTERNAL_WARNINGS` | [`internal_warnings`](Self::internal_warnings) |
+    /// | `HEMMER_PROVIDER_VALIDATE_RESOURCE_CONFIG` | [`validate_resource_config`](Self::validate_resource_config) |
+    /// | `HEMMER_PROVIDER_VALIDATE_STATE` | [`validate_state`](Self::validate_state) |
+    /// | `HEMMER_PROVIDER_READ_COALESCING` | [`read_coalescing`](Self::read_coalescing) |
+    /// | `HEMMER_PROVIDER_SESSION_CACHE` | [`session_cache`](Self::session_cache) |
+    ///
+    /// Durations are seconds, or a number with an `ms`, `s` or `m` suffix.
+    /// Flags accept `true`/`false`, `1`/`0`, `yes`/`no` and `on`/`off`. Empty
+    /// variables are ignored.
+    ///
+    /// Fails with [`ProviderError::Configuration`] naming the variable if a
+    /// value can't be parsed.
+    pub fn with_env_overrides(self) -> Result<Self, ProviderError> {
+        self.with_overrides_from(|name| std::env::var(name).ok())
+    }
+
+    /// Apply overrides looked up by variable name.
+    fn with_overrides_from(
+        mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ProviderError> {
+        let var = |name: &str| -> Option<(String, String)> {
+            let name = format!("HEMMER_PROVIDER_{}", name);
+            let value = lookup(&name)?.trim().to_string();
+            (!value.is_empty()).then_some((name, value))
+        };
+        let invalid = |name: &str, value: &str, expected: &str| {
+            ProviderError::Configuration(format!(
+                "Invalid value '{}' for {}: expected {}",
+                value, name, expected
+            ))
+        };
+        let duration = |(name, value): (String, String)| {
+            parse_duration(&value).ok_or_else(|| invalid(&name, &value, "a duration"))
+        };
+        let flag = |(name, value): (String, String)| match value.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Ok(true),
+            "false" | "0" | "no" | "off" => Ok(false),
+            _ => Err(invalid(&name, &value, "true or false")),
+        };
+
+        if let Some(v) = var("SHUTDOWN_TIMEOUT") {
+            self.shutdown_timeout = duration(v)?;
+        }
+        if let Some(v) = var("IDEMPOTENCY_TTL") {
+            self.idempotency_ttl = duration(v)?;
+        }
+        if let Some((name, value)) = var("MAX_CONCURRENCY") {
+            let max: usize = value
+                .parse()
+                .map_err(|_| invalid(&name, &value, "a number"))?;
+            self.max_concurrency = (max > 0).then_some(max);
+        }
+        if let Some((name, value)) = var("MAX_BLOCKING_TASKS") {
+            let max: usize = value
+                .parse()
+                .map_err(|_| invalid(&name, &value, "a number"))?;
+            self.blocking_budget = BlockingBudget::new(max);
+        }
+        if let Some((name, value)) = var("LOG_FORMAT") {
+            self.log_format = Some(
+                value
+                    .parse()
+                    .map_err(|_| invalid(&name, &value, "text, compact or json"))?,
+            );
+        }
+        for (name, option) in [
+            ("SELF_CHECK", &mut self.self_check),
+            ("INTERNAL_WARNINGS", &mut self.internal_warnings),
+            (
+                "VALIDATE_RESOURCE_CONFIG",
+                &mut self.validate_resource_config,
+            ),
+            ("VALIDATE_STATE", &mut self.validate_state),
+            ("READ_COALESCING", &mut self.read_coalescing),
+            ("SESSION_CACHE", &mut self.session_cache),
+        ] {
+            if let Some(v) = var(name) {
+                *option = flag(v)?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Set the shutdown timeout.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Set the resolvers used for `secret_ref` attributes.
+    pub fn with_secret_resolvers(mut self, resolvers: SecretResolvers) -> Self {
+        self.secret_resolvers = resolvers;
+        self
+    }
+
+    /// Set the codec applied to resource state (see [`crate::codec`]).
+    pub fn with_state_codec(mut self, codec: impl StateCodec) -> Self {
+        self.state_codec = Some(Arc::new(codec));
+        self
+    }
+
+    /// Set how long successful creates and updates are remembered by idempotency token.
+    pub fn with_idempotency_ttl(mut self, ttl: Duration) -> Self {
+        self.idempotency_ttl = ttl;
+        self
+    }
+
+    /// Set the locks used to serialize operations on the same resource.
+    pub fn with_resource_locks(mut self, locks: ResourceLocks) -> Self {
+        self.resource_locks = locks;
+        self
+    }
+
+    /// Enable or disable the startup self-check.
+    pub fn with_self_check(mut self, enabled: bool) -> Self {
+        self.self_check = enabled;
+        self
+    }
+
+    /// Enable or disable warning diagnostics for SDK-internal anomalies.
+    pub fn with_internal_warnings(mut self, enabled: bool) -> Self {
+        self.internal_warnings = enabled;
+        self
+    }
+
+    /// Enable or disable validation of the provider configuration against the
+    /// provider schema.
+    pub fn with_provider_config_validation(mut self, enabled: bool) -> Self {
+        self.validate_provider_config = enabled;
+        self
+    }
+
+    /// Enable or disable validation of resource configurations against the
+    /// resource schema.
+    pub fn with_resource_config_validation(mut self, enabled: bool) -> Self {
+        self.validate_resource_config = enabled;
+        self
+    }
+
+    /// Enable or disable validation of the states returned by the provider.
+    pub fn with_state_validation(mut self, enabled: bool) -> Self {
+        self.validate_state = enabled;
+        self
+    }
+
+    /// Set the payload size limits.
+    pub fn with_payload_limits(mut self, limits: PayloadLimits) -> Self {
+        self.payload_limits = limits;
+        self
+    }
+
+    /// Set the counters that payload sizes are recorded in.
+    pub fn with_payload_metrics(mut self, metrics: PayloadMetrics) -> Self {
+        self.payload_metrics = metrics;
+        self
+    }
+
+    /// Set the sink that operation durations and outcomes are reported to.
+    pub fn with_metrics_sink(mut self, sink: impl MetricsSink) -> Self {
+        self.metrics_sink = Some(Arc::new(sink));
+        self
+    }
+
+    /// Set the catalog response diagnostics are translated with.
+    pub fn with_catalog(mut self, catalog: Catalog) -> Self {
+        self.catalog = Some(catalog);
+        self
+    }
+
+    /// Enable or disable coalescing of concurrent identical reads.
+    pub fn with_read_coalescing(mut self, enabled: bool) -> Self {
+        self.read_coalescing = enabled;
+        self
+    }
+
+    /// Enable or disable caching reads for the session core names.
+    pub fn with_session_cache(mut self, enabled: bool) -> Self {
+        self.session_cache = enabled;
+        self
+    }
+
+    /// Limit how many requests are served at once. `0` means unlimited.
+    pub fn with_max_concurrency(mut self, max: usize) -> Self {
+        self.max_concurrency = (max > 0).then_some(max);
+        self
+    }
+
+    /// Initialize logging in this format when serving starts.
+    pub fn with_log_format(mut self, format: LogFormat) -> Self {
+        self.log_format = Some(format);
+        self
+    }
+
+    /// Set the budget for CPU-heavy work run with [`Context::run_blocking`].
+    pub fn with_blocking_budget(mut self, budget: BlockingBudget) -> Self {
+        self.blocking_budget = budget;
+        self
+    }
+
+    /// Set the store providers reach through [`Context::local_store`].
+    pub fn with_local_store(mut self, store: LocalStore) -> Self {
+        self.local_store = store;
+        self
+    }
+
+    /// Enable the gRPC-Web and JSON/HTTP gateway.
+    #[cfg(feature = "gateway")]
+    pub fn with_gateway(mut self, enabled: bool) -> Self {
+        self.gateway = enabled;
+        self
+    }
+
+    /// Keep decimals in payloads exactly as written, e.g. for providers of
+    /// financial or metrics APIs that must round-trip exact values.
+    #[cfg(feature = "arbitrary-precision")]
+    pub fn with_exact_numbers(mut self, enabled: bool) -> Self {
+        self.exact_numbers = enabled;
+        self
+    }
+
+    /// Sign the handshake with this key when core asks for attestation,
+    /// instead of the key in the environment.
+    #[cfg(feature = "attestation")]
+    pub fn with_handshake_signer(mut self, signer: crate::attestation::HandshakeSigner) -> Self {
+        self.handshake_signer = Some(signer);
+        self
+    }
+}
+
+/// Parse seconds, or a number with an `ms`, `s` or `m` suffix.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (number, unit) = match value.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(at) => value.split_at(at),
+        None => (value, "s"),
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    let secs = match unit {
+        "ms" => number / 1000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// Wait for a shutdown signal (SIGTERM or SIGINT) and return its name.
+///
+/// On Unix, this waits for SIGTERM or SIGINT.
+/// On Windows, this waits for CTRL+C.
+async fn wait_for_shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sigterm =
+            signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
+        let mut sigint = signal(SignalKind::interrupt()).expect("Failed to install SIGINT handler");
+
+        tokio::select! {
+            _ = sigterm.recv() => "SIGTERM",
+            _ = sigint.recv() => "SIGINT",
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install CTRL+C handler");
+        "CTRL+C"
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        // Fallback: just wait forever (no signal handling)
+        std::future::pending().await
+    }
+}
+
+/// Trigger shutdown on the first signal and exit the process on the next one.
+async fn handle_shutdown_signals(shutdown: Shutdown) {
+    tokio::select! {
+        name = wait_for_shutdown_signal() => {
+            eprintln!("Received {}, initiating graceful shutdown...", name);
+            shutdown.trigger();
+        }
+        _ = shutdown.triggered() => {}
+    }
+
+    let name = wait_for_shutdown_signal().await;
+    eprintln!("Received {} during shutdown, exiting immediately", name);
+    std::process::exit(130);
+}
+
+/// Serve a provider implementation as a gRPC server.
+///
+/// This function:
+/// 1. Finds an available port
+/// 2. Checks the schema and metadata (see [`ServeOptions::with_self_check`])
+/// 3. Starts the gRPC server
+/// 4. Outputs the handshake string to stdout
+/// 5. Handles shutdown signals (SIGTERM/SIGINT) gracefully
+///
+/// The handshake format is: `HEMMER_PROVIDER|<version>|<address>`
+///
+/// Options are the defaults with overrides from the environment (see
+/// [`ServeOptions::from_env`]). For custom configuration, use
+/// [`serve_with_options`].
+///
+/// Fails before the handshake if an environment override can't be parsed.
+pub async fn serve<P: ProviderService>(provider: P) -> Result<(), Box<dyn std::error::Error>> {
+    serve_with_options(provider, ServeOptions::default()).await
+}
+
+/// Serve a provider with custom options.
+///
+/// See [`serve`] for details. This function allows configuring
+/// shutdown behavior via [`ServeOptions`]. Environment overrides are applied
+/// over `options` (see [`ServeOptions::with_env_overrides`]).
+pub async fn serve_with_options<P: ProviderService>(
+    provider: P,
+    options: ServeOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let options = options.with_env_overrides()?;
+    // Find an available port by binding to port 0
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    serve_on_listener(provider, listener, addr, options).await
+}
+
+/// Serve a provider on a specific address.
+///
+/// Unlike [`serve`], this function binds to the specified address rather than
+/// finding an available port.
+pub async fn serve_on<P: ProviderService>(
+    provider: P,
+    addr: SocketAddr,
+) -> Result<(), Box<dyn std::error::Error>> {
+    serve_on_with_options(provider, addr, ServeOptions::default()).await
+}
+
+/// Serve a provider on a specific address with custom options, with
+/// environment overrides applied over them.
+pub async fn serve_on_with_options<P: ProviderService>(
+    provider: P,
+    addr: SocketAddr,
+    options: ServeOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let options = options.with_env_overrides()?;
+    let listener = TcpListener::bind(addr).await?;
+    let actual_addr = listener.local_addr()?;
+
+    serve_on_listener(provider, listener, actual_addr, options).await
+}
+
+/// Run the static self-checks, printing errors to stderr so they are visible
+/// even though core only reads the handshake from stdout.
+async fn run_startup_checks<P: ProviderService>(
+    provider: &P,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Build the schema first, so providers that load it asynchronously have it
+    // ready for the synchronous checks
+    let diagnostics = match provider.schema_async().await {
+        Ok(_) => crate::lint::static_checks(provider),
+        Err(diagnostics) => diagnostics,
+    };
+    let mut errors = 0;
+    for diagnostic in &diagnostics {
+        match diagnostic.severity {
+            DiagnosticSeverity::Error => {
+                errors += 1;
+                eprintln!("{}", diagnostic);
+            },
+            DiagnosticSeverity::Warning => warn!("Self-check: {}", diagnostic),
+        }
+    }
+    if errors > 0 {
+        return Err(format!(
+            "Provider self-check found {} error(s); fix the schema or disable the check with ServeOptions::with_self_check(false)",
+            errors
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// The handshake line announcing `addr`, signed if core passed a nonce and a
+/// signing key is configured (see [`crate::attestation`]), with environment
+/// variables looked up by name.
+fn handshake_line(
+    addr: SocketAddr,
+    options: &ServeOptions,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    #[cfg(feature = "attestation")]
+    if let Some(nonce) = env(crate::attestation::NONCE_ENV) {
+        let signer = match &options.handshake_signer {
+            Some(signer) => Some(signer.clone()),
+            None => crate::attestation::HandshakeSigner::from_lookup(&env)?,
+        };
+        match signer {
+            Some(signer) => {
+                return Ok(signer.sign_handshake(PROTOCOL_VERSION, &addr.to_string(), &nonce))
+            },
+            None => warn!("Core sent a handshake nonce, but no signing key is configured"),
+        }
+    }
+    #[cfg(not(feature = "attestation"))]
+    let _ = (options, env);
+    Ok(format!(
+        "{}|{}|{}",
+        HANDSHAKE_PREFIX, PROTOCOL_VERSION, addr
+    ))
+}
+
+/// Internal function to serve on an already-bound listener.
+async fn serve_on_listener<P: ProviderService>(
+    provider: P,
+    listener: TcpListener,
+    addr: SocketAddr,
+    options: ServeOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(format) = options.log_format {
+        try_init_logging_with_format(format);
+    }
+    if options.self_check {
+        run_startup_checks(&provider).await?;
+    }
+
+    // Output the handshake
+    println!(
+        "{}",
+        handshake_line(addr, &options, |name| std::env::var(name).ok())?
+    );
+
+    info!(address = %addr, "Provider server starting");
+
+    // Wrap provider in Arc so we can share it between the gRPC service and shutdown handler
+    let provider = Arc::new(provider);
+    let provider_for_shutdown = Arc::clone(&provider);
+    let shutdown = Shutdown::default();
+
+    // Create the gRPC service
+    let provider_data = ProviderDataStore::new();
+    let provider_data_for_shutdown = provider_data.clone();
+    let grpc_service = ProviderGrpcService {
+        secrets: options.secret_resolvers.clone(),
+        provider_data: provider_data.clone(),
+        state_codec: options.state_codec.clone(),
+        locks: options.resource_locks.clone(),
+        idempotency: IdempotencyStore::new(options.idempotency_ttl),
+        shutdown: shutdown.clone(),
+        internal_warnings: options.internal_warnings,
+        validate_provider_config: options.validate_provider_config,
+        validate_resource_config: options.validate_resource_config,
+        validate_state: options.validate_state,
+        payload_limits: options.payload_limits,
+        payload_metrics: options.payload_metrics.clone(),
+        metrics_sink: options.metrics_sink.clone(),
+        catalog: options.catalog.clone(),
+        blocking_budget: options.blocking_budget.clone(),
+        local_store: options.local_store.clone(),
+        coalescer: options.read_coalescing.then(ReadCoalescer::new),
+        session_cache: options.session_cache.then(SessionCache::default),
+        #[cfg(feature = "arbitrary-precision")]
+        exact_numbers: options.exact_numbers,
+        ..ProviderGrpcService::new(Arc::clone(&provider))
+    };
+    let context = grpc_service.context();
+    let grpc_service = Arc::new(grpc_service);
+
+    // Browsers and curl speak HTTP/1.1, so the gateway needs it enabled
+    #[cfg(feature = "gateway")]
+    let (routes, accept_http1) = if options.gateway {
+        info!(address = %addr, "gRPC-Web and JSON gateway enabled");
+        (crate::gateway::routes(grpc_service), true)
+    } else {
+        (grpc_routes(grpc_service), false)
+    };
+    #[cfg(not(feature = "gateway"))]
+    let (routes, accept_http1) = (grpc_routes(grpc_service), false);
+
+    let signals = tokio::spawn(handle_shutdown_signals(shutdown.clone()));
+
+    // Run the server until shutdown is requested by a signal or the Stop RPC
+    let mut builder = Server::builder();
+    if let Some(max) = options.max_concurrency {
+        builder = builder.concurrency_limit_per_connection(max);
+    }
+    let server_future = builder
+        .accept_http1(accept_http1)
+        .add_routes(routes)
+        .serve_with_incoming_shutdown(
+            tokio_stream::wrappers::TcpListenerStream::new(listener),
+            shutdown.triggered(),
+        );
+    tokio::pin!(server_future);
+
+    let result = tokio::select! {
+        result = &mut server_future => Some(result),
+        _ = shutdown.triggered() => None,
+    };
+    // The shutdown_timeout limits how long we wait for in-flight requests to complete
+    let result = match result {
+        Some(result) => Some(result),
+        None => tokio::time::timeout(options.shutdown_timeout, &mut server_future)
+            .await
+            .ok(),
+    };
+
+    match result {
+        Some(Ok(())) => {
+            info!("Server shutdown complete");
+        },
+        Some(Err(e)) => {
+            error!(error = %e, "Server error during shutdown");
+            signals.abort();
+            return Err(e.into());
+        },
+        None => {
+            warn!(
+                timeout = ?options.shutdown_timeout,
+                "Shutdown timeout exceeded, forcing shutdown"
+            );
+        },
+    }
+
+    // Call the provider's stop() method, unless the Stop RPC already did
+    if let Err(e) = context
+        .scope(shutdown.stop_provider(
+            provider_for_shutdown.as_ref(),
+            &provider_data_for_shutdown,
+            &options.local_store,
+        ))
+        .await
+    {
+        warn!(error = %e, "Provider stop() returned error");
+    }
+    signals.abort();
+
+    info!("Provider shutdown complete");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::provider_server::Provider;
+    use crate::schema::{Attribute, ProviderSchema, Schema};
+    use crate::types::PlanResult;
+    use futures_util::future::BoxFuture;
+
+    type Hook<A, R> = Option<Box<dyn Fn(A) -> BoxFuture<'static, R> + Send + Sync>>;
+    type SchemaResult = Result<ProviderSchema, Vec<Diagnostic>>;
+
+    /// Box an async closure as a [`TestProvider`] hook.
+    fn hook<A, R, F, Fut>(f: F) -> Hook<A, R>
+    where
+        F: Fn(A) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = R> + Send + 'static,
+    {
+        Some(Box::new(move |args| Box::pin(f(args))))
+    }
+
+    /// A provider that echoes states back, with hooks replacing the
+    /// operations a test cares about. Tests set them with struct update
+    /// syntax, on [`TestProvider::new`] or the empty default.
+    #[derive(Default)]
+    struct TestProvider {
+        schema: ProviderSchema,
+        /// Also replaces `schema`, which then returns its schema or an empty one.
+        try_schema: Option<Box<dyn Fn() -> SchemaResult + Send + Sync>>,
+        schema_async: Hook<(), SchemaResult>,
+        capabilities: ServerCapabilities,
+        configure: Hook<serde_json::Value, Result<ConfigureResult, ProviderError>>,
+        stop: Hook<(), Result<(), ProviderError>>,
+        /// Called with the prior and proposed states; plans from the schema if unset.
+        plan:
+            Hook<(Option<serde_json::Value>, serde_json::Value), Result<PlanResult, ProviderError>>,
+        estimate: Hook<serde_json::Value, Option<ImpactEstimate>>,
+        pre_apply_check: Hook<serde_json::Value, Vec<Diagnostic>>,
+        create: Hook<CreateRequest, Result<serde_json::Value, ProviderError>>,
+        read: Hook<ReadRequest, Result<ReadResult, ProviderError>>,
+        update: Hook<UpdateRequest, Result<serde_json::Value, ProviderError>>,
+        delete: Hook<DeleteRequest, Result<(), ProviderError>>,
+        /// Called with the resource type and ID.
+        import: Hook<(String, String), Result<Vec<ImportedResource>, ProviderError>>,
+        /// Called with the resource type and query.
+        import_query:
+            Hook<(String, serde_json::Value), Result<Vec<ImportedResource>, ProviderError>>,
+        /// Echoes the config back if unset.
+        read_data_source: Hook<ReadDataSourceRequest, Result<ReadResult, ProviderError>>,
+    }
+
+    impl TestProvider {
+        /// A provider with a `test_resource` importable as `{zone}/{name}`,
+        /// which rejects any `token` in its config.
+        fn new() -> Self {
+            Self {
+                schema: ProviderSchema::new()
+                    .with_provider_config(
+                        Schema::v0()
+                            .with_attribute("token", Attribute::optional_string().secret_ref()),
+                    )
+                    .with_resource(
+                        "test_resource",
+                        Schema::v0()
+                            .with_attribute("name", Attribute::required_string())
+                            .with_import_format("{zone}/{name}"),
+                    ),
+                configure: hook(|config: serde_json::Value| async move {
+                    match config["token"].as_str() {
+                        Some(token) => Err(ProviderError::Configuration(format!(
+                            "token {} rejected",
+                            token
+                        ))),
+                        None => Ok(ConfigureResult::new()),
+                    }
+                }),
+                ..Self::default()
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ProviderService for TestProvider {
+        fn schema(&self) -> ProviderSchema {
+            match &self.try_schema {
+                Some(try_schema) => try_schema().unwrap_or_default(),
+                None => self.schema.clone(),
+            }
+        }
+
+        fn try_schema(&self) -> Result<ProviderSchema, Vec<Diagnostic>> {
+            match &self.try_schema {
+                Some(try_schema) => try_schema(),
+                None => Ok(self.schema.clone()),
+            }
+        }
+
+        async fn schema_async(&self) -> Result<ProviderSchema, Vec<Diagnostic>> {
+            match &self.schema_async {
+                Some(schema_async) => schema_async(()).await,
+                None => self.try_schema(),
+            }
+        }
+
+        fn capabilities(&self) -> ServerCapabilities {
+            self.capabilities
+        }
+
+        async fn configure(
+            &self,
+            config: serde_json::Value,
+        ) -> Result<ConfigureResult, ProviderError> {
+            match &self.configure {
+                Some(configure) => configure(config).await,
+                None => Ok(ConfigureResult::new()),
+            }
+        }
+
+        async fn stop(&self) -> Result<(), ProviderError> {
+            match &self.stop {
+                Some(stop) => stop(()).await,
+                None => Ok(()),
+            }
+        }
+
+        async fn plan(
+            &self,
+            resource_type: &str,
+            prior_state: Option<serde_json::Value>,
+            proposed_state: serde_json::Value,
+            config: serde_json::Value,
+        ) -> Result<PlanResult, ProviderError> {
+            match &self.plan {
+                Some(plan) => plan((prior_state, proposed_state)).await,
+                None => {
+                    default_plan(self, resource_type, prior_state, proposed_state, config).await
+                },
+            }
+        }
+
+        async fn estimate(
+            &self,
+            _resource_type: &str,
+            planned_state: &serde_json::Value,
+        ) -> Option<ImpactEstimate> {
+            match &self.estimate {
+                Some(estimate) => estimate(planned_state.clone()).await,
+                None => None,
+            }
+        }
+
+        async fn pre_apply_check(
+            &self,
+            _resource_type: &str,
+            planned_state: &serde_json::Value,
+        ) -> Vec<Diagnostic> {
+            match &self.pre_apply_check {
+                Some(pre_apply_check) => pre_apply_check(planned_state.clone()).await,
+                None => Vec::new(),
+            }
+        }
+
+        async fn create(
+            &self,
+            _resource_type: &str,
+            planned_state: serde_json::Value,
+        ) -> Result<serde_json::Value, ProviderError> {
+            Ok(planned_state)
+        }
+
+        async fn handle_create(
+            &self,
+            request: CreateRequest,
+        ) -> Result<serde_json::Value, ProviderError> {
+            match &self.create {
+                Some(create) => create(request).await,
+                None => Ok(request.planned_state),
+            }
+        }
+
+        async fn read(
+            &self,
+            _resource_type: &str,
+            current_state: serde_json::Value,
+        ) -> Result<serde_json::Value, ProviderError> {
+            Ok(current_state)
+        }
+
+        async fn handle_read(&self, request: ReadRequest) -> Result<ReadResult, ProviderError> {
+            match &self.read {
+                Some(read) => read(request).await,
+                None => Ok(ReadResult::new(request.current_state)),
+            }
+        }
+
+        async fn update(
+            &self,
+            _resource_type: &str,
+            _prior_state: serde_json::Value,
+            planned_state: serde_json::Value,
+        ) -> Result<serde_json::Value, ProviderError> {
+            Ok(planned_state)
+        }
+
+        async fn handle_update(
+            &self,
+            request: UpdateRequest,
+        ) -> Result<serde_json::Value, ProviderError> {
+            match &self.update {
+                Some(update) => update(request).await,
+                None => Ok(request.planned_state),
+            }
+        }
+
+        async fn delete(
+            &self,
+            _resource_type: &str,
+            _current_state: serde_json::Value,
+        ) -> Result<(), ProviderError> {
+            Ok(())
+        }
+
+        async fn handle_delete(&self, request: DeleteRequest) -> Result<(), ProviderError> {
+            match &self.delete {
+                Some(delete) => delete(request).await,
+                None => Ok(()),
+            }
+        }
+
+        async fn import_resource(
+            &self,
+            resource_type: &str,
+            id: &str,
+        ) -> Result<Vec<ImportedResource>, ProviderError> {
+            match &self.import {
+                Some(import) => import((resource_type.to_string(), id.to_string())).await,
+                None => Err(ProviderError::Sdk(format!(
+                    "Import not supported for resource type: {}",
+                    resource_type
+                ))),
+            }
+        }
+
+        async fn import_query(
+            &self,
+            resource_type: &str,
+            query: serde_json::Value,
+        ) -> Result<Vec<ImportedResource>, ProviderError> {
+            match &self.import_query {
+                Some(import_query) => import_query((resource_type.to_string(), query)).await,
+                None => Err(ProviderError::Sdk(format!(
+                    "Import queries not supported for resource type: {}",
+                    resource_type
+                ))),
+            }
+        }
+
+        async fn handle_read_data_source(
+            &self,
+            request: ReadDataSourceRequest,
+        ) -> Result<ReadResult, ProviderError> {
+            match &self.read_data_source {
+                Some(read_data_source) => read_data_source(request).await,
+                None => Ok(ReadResult::new(request.config)),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_schema_with_current_version() {
+        let service = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+
+        let request = tonic::Request::new(crate::generated::GetSchemaRequest {
+            client_protocol_version: crate::PROTOCOL_VERSION,
+        });
+
+        let response = service.get_schema(request).await;
+        assert!(response.is_ok());
+
+        let inner = response.unwrap().into_inner();
+        assert_eq!(inner.server_protocol_version, crate::PROTOCOL_VERSION);
+        assert!(inner.provider.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_schema_and_metadata_diagnostics() {
+        let provider = Arc::new(TestProvider {
+            try_schema: Some(Box::new(|| {
+                Err(vec![Diagnostic::error("Plugin discovery failed")])
+            })),
+            ..TestProvider::default()
+        });
+
+        let service = ProviderGrpcService::new(Arc::clone(&provider));
+        let schema = service
+            .get_schema(tonic::Request::new(crate::generated::GetSchemaRequest {
+                client_protocol_version: crate::PROTOCOL_VERSION,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(schema.server_protocol_version, crate::PROTOCOL_VERSION);
+        assert!(schema.provider.is_none());
+        assert_eq!(schema.diagnostics[0].summary, "Plugin discovery failed");
+
+        let metadata = service
+            .get_metadata(tonic::Request::new(crate::generated::GetMetadataRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(metadata.resources.is_empty());
+        assert_eq!(metadata.diagnostics[0].summary, "Plugin discovery failed");
+
+        assert_eq!(
+            crate::lint::static_checks(provider.as_ref())[0].summary,
+            "Plugin discovery failed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_schema_async() {
+        // Loads its schema on first use, and only has it from then on
+        let remote_schema = || {
+            let loaded = Arc::new(tokio::sync::OnceCell::<ProviderSchema>::new());
+            let cached = Arc::clone(&loaded);
+            TestProvider {
+                schema_async: hook(move |()| {
+                    let loaded = Arc::clone(&loaded);
+                    async move {
+                        let schema = loaded
+                            .get_or_init(|| async {
+                                tokio::task::yield_now().await;
+                                ProviderSchema::new().with_resource(
+                                    "remote_widget",
+                                    Schema::v0()
+                                        .with_attribute("name", Attribute::required_string()),
+                                )
+                            })
+                            .await;
+                        Ok(schema.clone())
+                    }
+                }),
+                try_schema: Some(Box::new(move || {
+                    cached
+                        .get()
+                        .cloned()
+                        .ok_or_else(|| vec![Diagnostic::error("Schema not loaded")])
+                })),
+                ..TestProvider::default()
+            }
+        };
+
+        // Core asks for metadata before the schema, and the default plan
+        // needs the schema too
+        let service = ProviderGrpcService::new(Arc::new(remote_schema()));
+        let metadata = service
+            .get_metadata(tonic::Request::new(crate::generated::GetMetadataRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(metadata.diagnostics.is_empty());
+        assert_eq!(metadata.resources, vec!["remote_widget".to_string()]);
+
+        let schema = service
+            .get_schema(tonic::Request::new(crate::generated::GetSchemaRequest {
+                client_protocol_version: crate::PROTOCOL_VERSION,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(schema.resources.contains_key("remote_widget"));
+
+        let plan = remote_schema()
+            .plan(
+                "remote_widget",
+                None,
+                serde_json::json!({"name": "a"}),
+                serde_json::json!({"name": "a"}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(plan.planned_state["name"], "a");
+        assert!(remote_schema()
+            .plan(
+                "missing",
+                None,
+                serde_json::json!({}),
+                serde_json::json!({})
+            )
+            .await
+            .is_err());
+
+        // The startup self-check loads the schema before checking it
+        let fresh = remote_schema();
+        run_startup_checks(&fresh).await.unwrap();
+        assert!(fresh.try_schema().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_schema_cached_until_reconfigured() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let schema = ProviderSchema::new().with_resource(
+            "test_resource",
+            Schema::v0().with_attribute("name", Attribute::required_string()),
+        );
+        let loads = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&loads);
+        let provider = TestProvider {
+            schema_async: hook(move |()| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let schema = schema.clone();
+                async move { Ok(schema) }
+            }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService {
+            validate_state: true,
+            ..ProviderGrpcService::new(Arc::new(provider))
+        };
+        let plan = || {
+            service.plan(tonic::Request::new(crate::generated::PlanRequest {
+                resource_type: "test_resource".to_string(),
+                prior_state: Bytes::new(),
+                proposed_state: Bytes::from_static(br#"{"name": "a"}"#),
+                config: Bytes::from_static(br#"{"name": "a"}"#),
+                client_capabilities: None,
+            }))
+        };
+
+        // Normalizing, the default plan and redaction share one load
+        let response = plan().await.unwrap().into_inner();
+        assert!(response.diagnostics.is_empty());
+        plan().await.unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        service
+            .configure(tonic::Request::new(crate::generated::ConfigureRequest {
+                config: Bytes::from_static(b"{}"),
+                client_capabilities: None,
+            }))
+            .await
+            .unwrap();
+        plan().await.unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_function_to_proto() {
+        use crate::schema::{AttributeType, FunctionParameter, FunctionSchema};
+
+        let function = FunctionSchema::new(AttributeType::String)
+            .with_description("Join strings")
+            .with_parameter(
+                FunctionParameter::new("separator", AttributeType::String)
+                    .with_description("Placed between elements"),
+            )
+            .with_variadic_parameter(
+                FunctionParameter::new("value", AttributeType::String).allow_null(),
+            )
+            .deterministic();
+
+        let proto = function_to_proto(&function);
+        assert_eq!(proto.description, "Join strings");
+        assert!(proto.deterministic);
+        assert_eq!(proto.return_type, &br#""string""#[..]);
+        assert_eq!(proto.parameters.len(), 1);
+        assert_eq!(proto.parameters[0].name, "separator");
+        assert_eq!(proto.parameters[0].description, "Placed between elements");
+        let variadic = proto.variadic_parameter.unwrap();
+        assert_eq!(variadic.name, "value");
+        assert!(variadic.allow_null);
+    }
+
+    #[test]
+    fn test_schema_to_proto_carries_doc_metadata() {
+        let service = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+        let schema = Schema::v0()
+            .with_category("Compute")
+            .with_example(serde_json::json!({"name": "web"}))
+            .with_attribute(
+                "name",
+                Attribute::required_string()
+                    .with_example(serde_json::json!("web"))
+                    .with_metadata("since", serde_json::json!("0.4.0")),
+            )
+            .with_attribute("id", Attribute::computed_string());
+
+        let proto = service.schema_to_proto(&schema);
+        assert_eq!(proto.category, "Compute");
+        assert_eq!(proto.examples, &br#"[{"name":"web"}]"#[..]);
+        assert!(proto.metadata.is_empty());
+
+        let attributes = proto.block.unwrap().attributes;
+        assert_eq!(attributes[1].name, "name");
+        assert_eq!(attributes[1].examples, &br#"["web"]"#[..]);
+        assert_eq!(attributes[1].metadata, &br#"{"since":"0.4.0"}"#[..]);
+        assert!(attributes[0].examples.is_empty());
+    }
+
+    #[test]
+    fn test_schema_to_proto_inherits_block_flags() {
+        use crate::schema::{Block, NestedBlock};
+
+        let service = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+        let schema = Schema::v0().with_block(
+            "boot",
+            NestedBlock::single(Block::new().with_block(
+                "disk",
+                NestedBlock::single(
+                    Block::new().with_attribute("image", Attribute::required_string()),
+                ),
+            ))
+            .with_force_new()
+            .sensitive(),
+        );
+
+        let proto = service.schema_to_proto(&schema);
+        let boot = &proto.block.unwrap().block_types[0];
+        assert!(boot.force_new && boot.sensitive);
+        let disk = &boot.block.as_ref().unwrap().block_types[0];
+        assert!(!disk.force_new && !disk.sensitive);
+        let image = &disk.block.as_ref().unwrap().attributes[0];
+        assert!(image.force_new && image.sensitive);
+    }
+
+    #[tokio::test]
+    async fn test_get_schema_with_old_version() {
+        let service = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+
+        let request = tonic::Request::new(crate::generated::GetSchemaRequest {
+            client_protocol_version: 0, // Too old
+        });
+
+        let response = service.get_schema(request).await;
+        assert!(response.is_err());
+
+        let err = response.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::FailedPrecondition);
+        assert!(err.message().contains("too old"));
+    }
+
+    #[tokio::test]
+    async fn test_get_schema_with_min_version() {
+        let service = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+
+        let request = tonic::Request::new(crate::generated::GetSchemaRequest {
+            client_protocol_version: crate::MIN_PROTOCOL_VERSION,
+        });
+
+        let response = service.get_schema(request).await;
+        assert!(response.is_ok());
+
+        let inner = response.unwrap().into_inner();
+        assert_eq!(inner.server_protocol_version, crate::PROTOCOL_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_get_schema_with_newer_version() {
+        let service = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+
+        let request = tonic::Request::new(crate::generated::GetSchemaRequest {
+            client_protocol_version: crate::PROTOCOL_VERSION + 1,
+        });
+
+        let response = service.get_schema(request).await;
+        // Should succeed (with a warning in logs)
+        assert!(response.is_ok());
+
+        let inner = response.unwrap().into_inner();
+        assert_eq!(inner.server_protocol_version, crate::PROTOCOL_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_stream_schema_matches_get_schema() {
+        use futures_util::StreamExt;
+
+        let service = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+        let schema = service
+            .get_schema(tonic::Request::new(crate::generated::GetSchemaRequest {
+                client_protocol_version: crate::PROTOCOL_VERSION,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let stream = service
+            .stream_schema(tonic::Request::new(crate::generated::StreamSchemaRequest {
+                client_protocol_version: crate::PROTOCOL_VERSION,
+                max_chunk_bytes: 1,
+                accept_zstd: true,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let chunks: Vec<_> = stream.map(Result::unwrap).collect().await;
+        assert_eq!(
+            chunks.len(),
+            1 + schema.resources.len() + schema.data_sources.len()
+        );
+        assert_eq!(crate::schema_stream::merge_schema(chunks).unwrap(), schema);
+
+        let old_client = service
+            .stream_schema(tonic::Request::new(crate::generated::StreamSchemaRequest {
+                client_protocol_version: 0,
+                ..Default::default()
+            }))
+            .await;
+        assert_eq!(
+            old_client.err().unwrap().code(),
+            tonic::Code::FailedPrecondition
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_coalescing() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (read_calls, data_source_calls) = (Arc::clone(&calls), Arc::clone(&calls));
+        let provider = TestProvider {
+            read: hook(move |request: ReadRequest| {
+                read_calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok(ReadResult::new(request.current_state))
+                }
+            }),
+            read_data_source: hook(move |request: ReadDataSourceRequest| {
+                data_source_calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok(ReadResult::new(request.config))
+                }
+            }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService {
+            coalescer: Some(ReadCoalescer::new()),
+            ..ProviderGrpcService::new(Arc::new(provider))
+        };
+        let read = || {
+            service.read(tonic::Request::new(crate::generated::ReadRequest {
+                resource_type: "bucket".to_string(),
+                current_state: Bytes::from_static(br#"{"id":"b-1"}"#),
+                ..Default::default()
+            }))
+        };
+        let read_data_source = || {
+            service.read_data_source(tonic::Request::new(
+                crate::generated::ReadDataSourceRequest {
+                    data_source_type: "images".to_string(),
+                    config: Bytes::from_static(br#"{"name":"web"}"#),
+                    ..Default::default()
+                },
+            ))
+        };
+
+        let (reads, data_reads) = tokio::join!(
+            futures_util::future::join_all((0..5).map(|_| read())),
+            futures_util::future::join_all((0..5).map(|_| read_data_source())),
+        );
+        for response in reads {
+            assert_eq!(
+                &response.unwrap().into_inner().state[..],
+                br#"{"id":"b-1"}"#
+            );
+        }
+        for response in data_reads {
+            assert_eq!(
+                &response.unwrap().into_inner().state[..],
+                br#"{"name":"web"}"#
+            );
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Completed reads aren't cached
+        read().await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_session_cache() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (read_calls, data_source_calls) = (Arc::clone(&calls), Arc::clone(&calls));
+        let provider = TestProvider {
+            read: hook(move |request: ReadRequest| {
+                read_calls.fetch_add(1, Ordering::SeqCst);
+                assert!(Context::current().session_id().is_some());
+                async move { Ok(ReadResult::new(request.current_state)) }
+            }),
+            read_data_source: hook(move |request: ReadDataSourceRequest| {
+                data_source_calls.fetch_add(1, Ordering::SeqCst);
+                async move { Ok(ReadResult::new(request.config)) }
+            }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService {
+            session_cache: Some(SessionCache::default()),
+            ..ProviderGrpcService::new(Arc::new(provider))
+        };
+        fn in_session<T>(message: T, session: &str) -> tonic::Request<T> {
+            let mut request = tonic::Request::new(message);
+            request
+                .metadata_mut()
+                .insert(SESSION_ID_METADATA, session.parse().unwrap());
+            request
+        }
+        let read = |session| {
+            service.read(in_session(
+                crate::generated::ReadRequest {
+                    resource_type: "bucket".to_string(),
+                    current_state: Bytes::from_static(br#"{"id":"b-1"}"#),
+                    ..Default::default()
+                },
+                session,
+            ))
+        };
+        let read_data_source = |session| {
+            service.read_data_source(in_session(
+                crate::generated::ReadDataSourceRequest {
+                    data_source_type: "images".to_string(),
+                    config: Bytes::from_static(br#"{"name":"web"}"#),
+                    ..Default::default()
+                },
+                session,
+            ))
+        };
+        let calls = || calls.load(Ordering::SeqCst);
+
+        for _ in 0..3 {
+            let response = read("plan-1").await.unwrap().into_inner();
+            assert_eq!(&response.state[..], br#"{"id":"b-1"}"#);
+            read_data_source("plan-1").await.unwrap();
+        }
+        assert_eq!(calls(), 2);
+        read("plan-2").await.unwrap();
+        assert_eq!(calls(), 3);
+
+        // A write drops the written resource and data sources from every session
+        service
+            .delete(in_session(
+                crate::generated::DeleteRequest {
+                    resource_type: "bucket".to_string(),
+                    current_state: Bytes::from_static(br#"{"id":"b-1"}"#),
+                    ..Default::default()
+                },
+                "plan-1",
+            ))
+            .await
+            .unwrap();
+        read("plan-1").await.unwrap();
+        read("plan-2").await.unwrap();
+        read_data_source("plan-1").await.unwrap();
+        assert_eq!(calls(), 6);
+
+        service
+            .end_session(tonic::Request::new(crate::generated::EndSessionRequest {
+                session_id: "plan-1".to_string(),
+            }))
+            .await
+            .unwrap();
+        read("plan-1").await.unwrap();
+        read("plan-2").await.unwrap();
+        assert_eq!(calls(), 7);
+
+        // Requests without a session id are never cached
+        service
+            .read_data_source(tonic::Request::new(
+                crate::generated::ReadDataSourceRequest {
+                    data_source_type: "images".to_string(),
+                    config: Bytes::from_static(br#"{"name":"web"}"#),
+                    ..Default::default()
+                },
+            ))
+            .await
+            .unwrap();
+        assert_eq!(calls(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_read_stale_degraded_and_tainted() {
+        let provider = TestProvider {
+            read: hook(|request: ReadRequest| async move {
+                let state = request.current_state;
+                if state["id"] == "b-2" {
+                    return Ok(ReadResult::tainted(
+                        serde_json::json!({"id": "b-3"}),
+                        "Bucket was recreated with a new region",
+                    ));
+                }
+                // The API is down, so the last known state is kept
+                let error = ProviderError::Unavailable("API unreachable".to_string());
+                Ok(ReadResult::stale(state, error.to_string()))
+            }),
+            read_data_source: hook(|_| async {
+                Ok(ReadResult::degraded(
+                    serde_json::json!({"regions": ["us-east-1"]}),
+                    "Some regions couldn't be listed",
+                ))
+            }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let response = service
+            .read(tonic::Request::new(crate::generated::ReadRequest {
+                resource_type: "bucket".to_string(),
+                current_state: Bytes::from_static(br#"{"id":"b-1"}"#),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(&response.state[..], br#"{"id":"b-1"}"#);
+        assert_eq!(response.status(), crate::generated::ReadStatus::Stale);
+        assert_eq!(response.diagnostics.len(), 1);
+        assert_eq!(
+            response.diagnostics[0].severity(),
+            crate::generated::diagnostic::Severity::Warning
+        );
+        assert!(response.diagnostics[0].summary.contains("API unreachable"));
+
+        // A resource recreated upstream asks for a replacement
+        let response = service
+            .read(tonic::Request::new(crate::generated::ReadRequest {
+                resource_type: "bucket".to_string(),
+                current_state: Bytes::from_static(br#"{"id":"b-2"}"#),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(&response.state[..], br#"{"id":"b-3"}"#);
+        assert_eq!(response.status(), crate::generated::ReadStatus::Tainted);
+        assert_eq!(
+            response.diagnostics[0].summary,
+            "Bucket was recreated with a new region"
+        );
+
+        let response = service
+            .read_data_source(tonic::Request::new(
+                crate::generated::ReadDataSourceRequest {
+                    data_source_type: "regions".to_string(),
+                    config: Bytes::from_static(b"{}"),
+                    ..Default::default()
+                },
+            ))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(&response.state[..], br#"{"regions":["us-east-1"]}"#);
+        assert_eq!(response.status(), crate::generated::ReadStatus::Degraded);
+        assert_eq!(
+            response.diagnostics[0].summary,
+            "Some regions couldn't be listed"
+        );
+    }
+
+    /// Resolves `env:NAME` from variables the test sets, instead of the
+    /// process environment that tests running in parallel share.
+    #[derive(Clone, Default)]
+    struct TestEnv(Arc<std::sync::Mutex<std::collections::HashMap<String, String>>>);
+
+    impl TestEnv {
+        fn set(&self, name: &str, value: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(name.to_string(), value.to_string());
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl crate::secrets::SecretResolver for TestEnv {
+        fn scheme(&self) -> &str {
+            "env"
+        }
+
+        async fn resolve(&self, location: &str) -> Result<String, ProviderError> {
+            self.0
+                .lock()
+                .unwrap()
+                .get(location)
+                .cloned()
+                .ok_or_else(|| {
+                    ProviderError::Configuration(format!(
+                        "Environment variable '{}' is not set",
+                        location
+                    ))
+                })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_configure_resolves_and_redacts_secrets() {
+        let env = TestEnv::default();
+        env.set("HEMMER_SDK_TEST_TOKEN", "tok-123");
+        let service = ProviderGrpcService {
+            secrets: SecretResolvers::new().with_resolver(env),
+            ..ProviderGrpcService::new(Arc::new(TestProvider::new()))
+        };
+
+        let request = tonic::Request::new(crate::generated::ConfigureRequest {
+            config: Bytes::from_static(br#"{"token": "env:HEMMER_SDK_TEST_TOKEN"}"#),
+            client_capabilities: None,
+        });
+        let diagnostics = service
+            .configure(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .diagnostics;
+
+        // The provider saw the resolved value, but it never reaches the response
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].summary.contains("[REDACTED]"));
+        assert!(!diagnostics[0].summary.contains("tok-123"));
+    }
+
+    #[tokio::test]
+    async fn test_configure_resolves_rotated_secrets() {
+        let env = TestEnv::default();
+        let service = ProviderGrpcService {
+            secrets: SecretResolvers::new().with_resolver(env.clone()),
+            ..ProviderGrpcService::new(Arc::new(TestProvider::new()))
+        };
+        let configure = || {
+            service.configure(tonic::Request::new(crate::generated::ConfigureRequest {
+                config: Bytes::from_static(br#"{"token": "env:HEMMER_SDK_TEST_ROTATED"}"#),
+                client_capabilities: None,
+            }))
+        };
+
+        env.set("HEMMER_SDK_TEST_ROTATED", "tok-old");
+        configure().await.unwrap();
+        env.set("HEMMER_SDK_TEST_ROTATED", "tok-new");
+        let diagnostics = configure().await.unwrap().into_inner().diagnostics;
+
+        // The second Configure resolved the new value rather than the cached one
+        assert!(diagnostics[0].summary.contains("[REDACTED]"));
+        assert_eq!(
+            service.secrets.redact("tok-old tok-new"),
+            "tok-old [REDACTED]"
+        );
+    }
+
+    /// Wraps state as `{"sealed": state}`.
+    struct SealCodec;
+
+    #[async_trait::async_trait]
+    impl StateCodec for SealCodec {
+        async fn encode(
+            &self,
+            _resource_type: &str,
+            state: serde_json::Value,
+        ) -> Result<serde_json::Value, ProviderError> {
+            Ok(serde_json::json!({ "sealed": state }))
+        }
+
+        async fn decode(
+            &self,
+            _resource_type: &str,
+            mut state: serde_json::Value,
+        ) -> Result<serde_json::Value, ProviderError> {
+            state
+                .get_mut("sealed")
+                .map(serde_json::Value::take)
+                .ok_or_else(|| ProviderError::Validation("state is not sealed".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_state_codec_applied_to_state_payloads() {
+        let service = ProviderGrpcService {
+            state_codec: Some(Arc::new(SealCodec)),
+            ..ProviderGrpcService::new(Arc::new(TestProvider::new()))
+        };
+
+        let response = service
+            .create(tonic::Request::new(crate::generated::CreateRequest {
+                resource_type: "test_resource".to_string(),
+                planned_state: Bytes::from_static(br#"{"sealed": {"name": "a"}}"#),
+                client_capabilities: None,
+                config: Bytes::new(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.diagnostics.is_empty());
+        let state: serde_json::Value = serde_json::from_slice(&response.state).unwrap();
+        assert_eq!(state, serde_json::json!({"sealed": {"name": "a"}}));
+
+        let response = service
+            .read(tonic::Request::new(crate::generated::ReadRequest {
+                resource_type: "test_resource".to_string(),
+                current_state: Bytes::from_static(br#"{"name": "a"}"#),
+                client_capabilities: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.state.is_empty());
+        assert!(response.diagnostics[0].summary.contains("not sealed"));
+    }
+
+    #[cfg(feature = "arbitrary-precision")]
+    #[tokio::test]
+    async fn test_exact_numbers() {
+        let create = |service: ProviderGrpcService<TestProvider>| async move {
+            let response = service
+                .create(tonic::Request::new(crate::generated::CreateRequest {
+                    resource_type: "test_resource".to_string(),
+                    planned_state: Bytes::from_static(
+                        br#"{"price":0.1000000000000000000001,"id":340282366920938463463374607431768211455}"#,
+                    ),
+                    client_capabilities: None,
+                    config: Bytes::new(),
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            String::from_utf8(response.state.to_vec()).unwrap()
+        };
+
+        // Integers keep their digits either way; decimals only when enabled
+        assert_eq!(
+            create(ProviderGrpcService::new(Arc::new(TestProvider::new()))).await,
+            r#"{"id":340282366920938463463374607431768211455,"price":0.1}"#
+        );
+        let exact = ProviderGrpcService {
+            exact_numbers: true,
+            ..ProviderGrpcService::new(Arc::new(TestProvider::new()))
+        };
+        assert_eq!(
+            create(exact).await,
+            r#"{"id":340282366920938463463374607431768211455,"price":0.1000000000000000000001}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_provider_config_against_schema() {
+        let request = || {
+            tonic::Request::new(crate::generated::ValidateProviderConfigRequest {
+                config: Bytes::from_static(br#"{"token": 5}"#),
+            })
+        };
+
+        let service = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+        let diagnostics = service
+            .validate_provider_config(request())
+            .await
+            .unwrap()
+            .into_inner()
+            .diagnostics;
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].severity,
+            crate::generated::diagnostic::Severity::Error as i32
+        );
+        assert!(diagnostics[0].summary.contains("token"));
+
+        let unchecked = ProviderGrpcService {
+            validate_provider_config: false,
+            ..ProviderGrpcService::new(Arc::new(TestProvider::new()))
+        };
+        let response = unchecked.validate_provider_config(request()).await.unwrap();
+        assert!(response.into_inner().diagnostics.is_empty());
+
+        // A provider repeating the schema validation doesn't duplicate diagnostics
+        let dynamic = crate::dynamic::DynamicProvider::new().with_provider_config(
+            Schema::v0().with_attribute("endpoint", Attribute::required_string()),
+        );
+        let diagnostics = validate_provider_config_with_schema(
+            &dynamic,
+            Ok(Arc::new(dynamic.schema())),
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(diagnostics.len(), 1);
+
+        // A long list of bad items is summarized past the diagnostic limit
+        let dynamic = crate::dynamic::DynamicProvider::new().with_provider_config(
+            Schema::v0().with_attribute(
+                "ports",
+                Attribute::new(
+                    crate::schema::AttributeType::list(crate::schema::AttributeType::Int64),
+                    crate::schema::AttributeFlags::required(),
+                ),
+            ),
+        );
+        let service = ProviderGrpcService::new(Arc::new(dynamic));
+        let diagnostics = service
+            .validate_provider_config(tonic::Request::new(
+                crate::generated::ValidateProviderConfigRequest {
+                    config: serde_json::to_vec(&serde_json::json!({"ports": vec!["http"; 500]}))
+                        .unwrap()
+                        .into(),
+                },
+            ))
+            .await
+            .unwrap()
+            .into_inner()
+            .diagnostics;
+        assert_eq!(diagnostics.len(), DEFAULT_DIAGNOSTIC_LIMIT + 1);
+        assert_eq!(diagnostics[0].attribute, "ports.0");
+        assert_eq!(
+            diagnostics[DEFAULT_DIAGNOSTIC_LIMIT].summary,
+            "400 more diagnostics not shown"
+        );
+        assert_eq!(
+            diagnostics[DEFAULT_DIAGNOSTIC_LIMIT].severity,
+            crate::generated::diagnostic::Severity::Error as i32
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_resource_config_against_schema() {
+        let request = |resource_type: &str| {
+            tonic::Request::new(crate::generated::ValidateResourceConfigRequest {
+                resource_type: resource_type.to_string(),
+                config: Bytes::from_static(br#"{"name": 5}"#),
+            })
+        };
+
+        // Off by default
+        let unchecked = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+        let response = unchecked
+            .validate_resource_config(request("test_resource"))
+            .await
+            .unwrap();
+        assert!(response.into_inner().diagnostics.is_empty());
+
+        let service = ProviderGrpcService {
+            validate_resource_config: true,
+            ..ProviderGrpcService::new(Arc::new(TestProvider::new()))
+        };
+        let diagnostics = service
+            .validate_resource_config(request("test_resource"))
+            .await
+            .unwrap()
+            .into_inner()
+            .diagnostics;
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].summary.contains("name"));
+
+        let response = service
+            .validate_resource_config(request("unknown_resource"))
+            .await
+            .unwrap();
+        assert!(response.into_inner().diagnostics.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_error_diagnostics_carry_code() {
+        let service = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+        let response = service
+            .configure(tonic::Request::new(crate::generated::ConfigureRequest {
+                config: Bytes::from_static(br#"{"token": "abc"}"#),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let error = &response.diagnostics[0];
+        assert_eq!(error.code, tonic::Code::FailedPrecondition as i32);
+        assert_eq!(error.error_kind, "configuration");
+
+        // Validation diagnostics are not errors from the provider
+        let service = ProviderGrpcService {
+            validate_resource_config: true,
+            ..ProviderGrpcService::new(Arc::new(TestProvider::new()))
+        };
+        let response = service
+            .validate_resource_config(tonic::Request::new(
+                crate::generated::ValidateResourceConfigRequest {
+                    resource_type: "test_resource".to_string(),
+                    config: Bytes::from_static(br#"{"name": 5}"#),
+                },
+            ))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.diagnostics[0].code, 0);
+        assert!(response.diagnostics[0].error_kind.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pointer_providers() {
+        // Pointers to a provider keep the provider's own methods and overrides
+        async fn check<P: ProviderService>(provider: P) {
+            let service = ProviderGrpcService {
+                validate_resource_config: true,
+                ..ProviderGrpcService::new(Arc::new(provider))
+            };
+            let response = service
+                .configure(tonic::Request::new(crate::generated::ConfigureRequest {
+                    config: Bytes::from_static(br#"{"token": "abc"}"#),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(response.diagnostics[0].error_kind, "configuration");
+
+            let response = service
+                .validate_resource_config(tonic::Request::new(
+                    crate::generated::ValidateResourceConfigRequest {
+                        resource_type: "test_resource".to_string(),
+                        config: Bytes::from_static(br#"{"name": 5}"#),
+                    },
+                ))
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(response.diagnostics[0].summary.contains("name"));
+        }
+
+        check(Arc::new(TestProvider::new())).await;
+        check(Box::new(TestProvider::new()) as Box<dyn ProviderService>).await;
+        check(&*Box::leak(Box::new(TestProvider::new()))).await;
+
+        // Providers of different types behind one type
+        let providers: Vec<Box<dyn ProviderService>> = vec![
+            Box::new(TestProvider::new()),
+            Box::new(crate::dynamic::DynamicProvider::new()),
+        ];
+        let resources: Vec<_> = providers
+            .iter()
+            .map(|p| p.metadata().resources.len())
+            .collect();
+        assert_eq!(resources, [1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_catalog_localizes_diagnostics() {
+        let service = ProviderGrpcService {
+            validate_resource_config: true,
+            catalog: Some(
+                Catalog::new("de")
+                    .with_template("invalid_type", "Ungültiger Typ für Attribut '{path}'")
+                    .with_template("invalid_type.detail", "{expected} erwartet, {got} erhalten")
+                    .with_template("error.configuration", "Konfigurationsfehler: {message}"),
+            ),
+            ..ProviderGrpcService::new(Arc::new(TestProvider::new()))
+        };
+        let diagnostics = service
+            .validate_resource_config(tonic::Request::new(
+                crate::generated::ValidateResourceConfigRequest {
+                    resource_type: "test_resource".to_string(),
+                    config: Bytes::from_static(br#"{"name": 5}"#),
+                },
+            ))
+            .await
+            .unwrap()
+            .into_inner()
+            .diagnostics;
+        assert_eq!(diagnostics[0].summary, "Ungültiger Typ für Attribut 'name'");
+        assert_eq!(diagnostics[0].detail, "string erwartet, number erhalten");
+        assert_eq!(diagnostics[0].attribute, "name");
+
+        // Provider errors are keyed by variant
+        let response = service
+            .configure(tonic::Request::new(crate::generated::ConfigureRequest {
+                config: Bytes::from_static(br#"{"token": "abc"}"#),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.diagnostics[0]
+            .summary
+            .starts_with("Konfigurationsfehler: token:"));
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_replaces_provider_data() {
+        // Stores the configured token, and fails to configure without one
+        let provider = TestProvider {
+            configure: hook(|config: serde_json::Value| async move {
+                let token = config["token"]
+                    .as_str()
+                    .ok_or_else(|| ProviderError::Configuration("token is required".to_string()))?;
+                Ok(ConfigureResult::new().with_provider_data(token.to_string()))
+            }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let configure = |config: &'static [u8]| {
+            tonic::Request::new(crate::generated::ConfigureRequest {
+                config: Bytes::from_static(config),
+                client_capabilities: None,
+            })
+        };
+        let token = || {
+            let context = service.context();
+            let token = context.provider_data::<String>().unwrap();
+            (token.as_ref().clone(), context.configuration_generation())
+        };
+
+        let response = service
+            .configure(configure(br#"{"token": "a"}"#))
+            .await
+            .unwrap();
+        assert!(response.into_inner().diagnostics.is_empty());
+        assert_eq!(token(), ("a".to_string(), 1));
+
+        let response = service
+            .configure(configure(br#"{"token": "b"}"#))
+            .await
+            .unwrap();
+        assert!(response.into_inner().diagnostics.is_empty());
+        assert_eq!(token(), ("b".to_string(), 2));
+
+        // A failed reconfigure keeps the previous provider data
+        let response = service.configure(configure(b"{}")).await.unwrap();
+        assert!(response.into_inner().diagnostics[0]
+            .summary
+            .contains("token is required"));
+        assert_eq!(token(), ("b".to_string(), 2));
+    }
+
+    #[tokio::test]
+    async fn test_read_waits_for_resource_lock() {
+        let service = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+        let request = || {
+            tonic::Request::new(crate::generated::ReadRequest {
+                resource_type: "test_resource".to_string(),
+                current_state: Bytes::from_static(br#"{"id": "r-1", "name": "a"}"#),
+                client_capabilities: None,
+            })
+        };
+
+        let guard = service.locks.lock("test_resource", "r-1").await;
+        let blocked =
+            tokio::time::timeout(Duration::from_millis(50), service.read(request())).await;
+        assert!(blocked.is_err());
+
+        drop(guard);
+        let response = service.read(request()).await.unwrap().into_inner();
+        assert!(response.diagnostics.is_empty());
+        assert_eq!(service.locks.active(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_create_retries_share_idempotency_token() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let creates = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&creates);
+        let provider = TestProvider {
+            create: hook(move |_| {
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let token = Context::current().idempotency_token().map(String::from);
+                async move { Ok(serde_json::json!({ "id": n, "token": token })) }
+            }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let create = |name: &str, key: Option<&str>| {
+            let mut request = tonic::Request::new(crate::generated::CreateRequest {
+                resource_type: "server".to_string(),
+                planned_state: serde_json::to_vec(&serde_json::json!({ "name": name }))
+                    .unwrap()
+                    .into(),
+                client_capabilities: None,
+                config: Bytes::new(),
+            });
+            if let Some(key) = key {
+                request
+                    .metadata_mut()
+                    .insert(IDEMPOTENCY_KEY_METADATA, key.parse().unwrap());
+            }
+            let service = &service;
+            async move {
+                let state = service.create(request).await.unwrap().into_inner().state;
+                serde_json::from_slice::<serde_json::Value>(&state).unwrap()
+            }
+        };
+
+        let first = create("a", Some("key-1")).await;
+        let retry = create("a", Some("key-1")).await;
+        assert_eq!(first, retry);
+        assert_eq!(first["token"].as_str().unwrap().len(), 32);
+        assert_eq!(creates.load(Ordering::SeqCst), 1);
+
+        // Another instance with the same planned state is a new request
+        let other = create("a", Some("key-2")).await;
+        assert_ne!(other["token"], first["token"]);
+        assert_eq!(creates.load(Ordering::SeqCst), 2);
+
+        // Creating the other instance doesn't forget the first one's result
+        assert_eq!(create("a", Some("key-1")).await, first);
+        assert_eq!(creates.load(Ordering::SeqCst), 2);
+
+        // Without a key, every call gets its own token and is applied
+        let unkeyed = create("a", None).await;
+        let again = create("a", None).await;
+        assert_eq!(unkeyed["token"].as_str().unwrap().len(), 32);
+        assert_ne!(unkeyed["token"], again["token"]);
+        assert_eq!(creates.load(Ordering::SeqCst), 4);
+
+        // Deleting the first instance forgets only its result
+        let response = service
+            .delete(tonic::Request::new(crate::generated::DeleteRequest {
+                resource_type: "server".to_string(),
+                current_state: serde_json::to_vec(&first).unwrap().into(),
+                client_capabilities: None,
+                config: Bytes::new(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.diagnostics.is_empty());
+        assert_eq!(create("a", Some("key-2")).await, other);
+        assert_eq!(creates.load(Ordering::SeqCst), 4);
+        assert_ne!(create("a", Some("key-1")).await, first);
+        assert_eq!(creates.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_plan_includes_impact_estimate() {
+        let provider = TestProvider {
+            schema: ProviderSchema::new().with_resource("disk", Schema::v0()),
+            plan: hook(
+                |(_, proposed_state)| async move { Ok(PlanResult::no_change(proposed_state)) },
+            ),
+            estimate: hook(|planned_state: serde_json::Value| async move {
+                match planned_state["size_gb"].as_f64() {
+                    Some(size) => Some(ImpactEstimate::new().with_monthly_cost(size * 0.1, "USD")),
+                    // Deleting the disk loses its data
+                    None if planned_state.is_null() => {
+                        Some(ImpactEstimate::new().with_destructiveness(1.0))
+                    },
+                    None => None,
+                }
+            }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let plan = |prior: &'static [u8], proposed: &'static [u8]| {
+            service.plan(tonic::Request::new(crate::generated::PlanRequest {
+                resource_type: "disk".to_string(),
+                prior_state: Bytes::from_static(prior),
+                proposed_state: Bytes::from_static(proposed),
+                config: Bytes::from_static(proposed),
+                client_capabilities: None,
+            }))
+        };
+
+        let create = plan(b"", br#"{"size_gb": 100}"#)
+            .await
+            .unwrap()
+            .into_inner();
+        let impact = create.impact.unwrap();
+        assert_eq!(impact.monthly_cost, Some(10.0));
+        assert_eq!(impact.currency, "USD");
+        assert_eq!(impact.destructiveness, None);
+
+        let delete = plan(br#"{"size_gb": 100}"#, b"null")
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(delete.impact.unwrap().destructiveness, Some(1.0));
+
+        let unknown = plan(b"", br#"{"name": "a"}"#).await.unwrap().into_inner();
+        assert!(unknown.diagnostics.is_empty());
+        assert!(unknown.impact.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_plan_redacts_sensitive_changes() {
+        let provider = TestProvider {
+            schema: ProviderSchema::new().with_resource(
+                "db",
+                Schema::v0()
+                    .with_attribute("name", Attribute::required_string())
+                    .with_attribute("password", Attribute::required_string().sensitive())
+                    .with_block(
+                        "user",
+                        crate::schema::NestedBlock::list(
+                            crate::schema::Block::new()
+                                .with_attribute("login", Attribute::required_string())
+                                .with_attribute("token", Attribute::optional_string().sensitive()),
+                        ),
+                    ),
+            ),
+            // Plans by hand, with no knowledge of which attributes are sensitive
+            plan: hook(
+                |(prior_state, proposed_state): (Option<serde_json::Value>, _)| async move {
+                    let mut result = PlanResult::from_diff(prior_state.as_ref(), &proposed_state);
+                    result.changes.push(AttributeChange::added(
+                        "user",
+                        serde_json::json!([{"login": "app", "token": "t0k3n"}]),
+                    ));
+                    Ok(result)
+                },
+            ),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let response = service
+            .plan(tonic::Request::new(crate::generated::PlanRequest {
+                resource_type: "db".to_string(),
+                prior_state: Bytes::from_static(br#"{"name": "a", "password": "hunter2"}"#),
+                proposed_state: Bytes::from_static(br#"{"name": "b", "password": "s3cr3t!"}"#),
+                config: Bytes::new(),
+                client_capabilities: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let body = format!("{:?}", response.changes);
+        for secret in ["hunter2", "s3cr3t!", "t0k3n"] {
+            assert!(!body.contains(secret), "{} leaked: {}", secret, body);
+        }
+        let changes: Vec<AttributeChange> = response.changes.into_iter().map(Into::into).collect();
+        assert_eq!(changes.len(), 3);
+        assert!(!changes[0].sensitive);
+        assert_eq!(changes[0].after, Some(serde_json::json!("b")));
+        assert!(changes[1].sensitive);
+        assert_eq!(changes[1].action, Some(crate::ChangeAction::Update));
+        assert_eq!(changes[1].before, Some(serde_json::json!("[REDACTED]")));
+        assert_eq!(changes[1].after, Some(serde_json::json!("[REDACTED]")));
+        assert_eq!(
+            changes[2].after,
+            Some(serde_json::json!([{"login": "app", "token": "[REDACTED]"}]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pre_apply_check() {
+        let provider = TestProvider {
+            pre_apply_check: hook(|planned_state: serde_json::Value| async move {
+                if planned_state["acl"] == "public-read" {
+                    vec![Diagnostic::error("Public buckets are not allowed").with_attribute("acl")]
+                } else {
+                    vec![]
+                }
+            }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let check = |planned: &'static [u8]| {
+            service.pre_apply_check(tonic::Request::new(
+                crate::generated::PreApplyCheckRequest {
+                    resource_type: "bucket".to_string(),
+                    planned_state: Bytes::from_static(planned),
+                    client_capabilities: None,
+                },
+            ))
+        };
+
+        let refused = check(br#"{"acl": "public-read"}"#)
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(refused.diagnostics.len(), 1);
+        assert_eq!(refused.diagnostics[0].attribute, "acl");
+        assert_eq!(
+            refused.diagnostics[0].severity,
+            crate::generated::diagnostic::Severity::Error as i32
+        );
+
+        let allowed = check(br#"{"acl": "private"}"#).await.unwrap().into_inner();
+        assert!(allowed.diagnostics.is_empty());
+        // Deletes are checked with a null planned state
+        let delete = check(b"").await.unwrap().into_inner();
+        assert!(delete.diagnostics.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_apply_passes_config() {
+        let configs = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (create_configs, update_configs, delete_configs) = (
+            Arc::clone(&configs),
+            Arc::clone(&configs),
+            Arc::clone(&configs),
+        );
+        let provider = TestProvider {
+            create: hook(move |request: CreateRequest| {
+                create_configs.lock().unwrap().push(request.config);
+                async move { Ok(request.planned_state) }
+            }),
+            update: hook(move |request: UpdateRequest| {
+                update_configs.lock().unwrap().push(request.config);
+                async move { Ok(request.planned_state) }
+            }),
+            delete: hook(move |request: DeleteRequest| {
+                delete_configs.lock().unwrap().push(request.config);
+                async { Ok(()) }
+            }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let response = service
+            .create(tonic::Request::new(crate::generated::CreateRequest {
+                resource_type: "db_user".to_string(),
+                planned_state: Bytes::from_static(br#"{"name": "app"}"#),
+                client_capabilities: None,
+                config: Bytes::from_static(br#"{"name": "app", "password": "hunter2"}"#),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.diagnostics.is_empty());
+        // The write-only password reaches the provider but not the state
+        assert_eq!(
+            decode_json(&response.state).unwrap(),
+            serde_json::json!({"name": "app"})
+        );
+
+        service
+            .update(tonic::Request::new(crate::generated::UpdateRequest {
+                resource_type: "db_user".to_string(),
+                prior_state: response.state.clone(),
+                planned_state: response.state.clone(),
+                client_capabilities: None,
+                config: Bytes::from_static(br#"{"name": "app", "password": "swordfish"}"#),
+            }))
+            .await
+            .unwrap();
+        // A delete of a resource removed from configuration has no config
+        service
+            .delete(tonic::Request::new(crate::generated::DeleteRequest {
+                resource_type: "db_user".to_string(),
+                current_state: response.state,
+                client_capabilities: None,
+                config: Bytes::new(),
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *configs.lock().unwrap(),
+            vec![
+                serde_json::json!({"name": "app", "password": "hunter2"}),
+                serde_json::json!({"name": "app", "password": "swordfish"}),
+                serde_json::Value::Null,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_normalizes_configs_and_states() {
+        use crate::normalize::Normalizer;
+
+        let provider = TestProvider {
+            schema: ProviderSchema::new().with_resource(
+                "bucket",
+                Schema::v0().with_attribute(
+                    "region",
+                    Attribute::required_string()
+                        .with_force_new()
+                        .with_normalizer(Normalizer::trim())
+                        .with_normalizer(Normalizer::lowercase()),
+                ),
+            ),
+            // The API reports regions in upper case
+            create: hook(|_| async { Ok(serde_json::json!({"region": "US-EAST-1"})) }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let created = service
+            .create(tonic::Request::new(crate::generated::CreateRequest {
+                resource_type: "bucket".to_string(),
+                planned_state: Bytes::from_static(br#"{"region": "us-east-1"}"#),
+                client_capabilities: None,
+                config: Bytes::new(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            decode_json(&created.state).unwrap(),
+            serde_json::json!({"region": "us-east-1"})
+        );
+
+        let config = Bytes::from_static(br#"{"region": " US-East-1 "}"#);
+        let plan = service
+            .plan(tonic::Request::new(crate::generated::PlanRequest {
+                resource_type: "bucket".to_string(),
+                prior_state: created.state,
+                proposed_state: config.clone(),
+                config: config.clone(),
+                client_capabilities: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(plan.diagnostics.is_empty());
+        assert!(plan.changes.is_empty());
+        assert!(!plan.requires_replace);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_interval_caches_reads() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let reads = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&reads);
+        let provider = TestProvider {
+            schema: ProviderSchema::new()
+                .with_resource(
+                    "bucket",
+                    Schema::v0().with_refresh_interval(Duration::from_secs(600)),
+                )
+                .with_resource("object", Schema::v0()),
+            read: hook(move |request: ReadRequest| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async move { Ok(ReadResult::new(request.current_state)) }
+            }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let metadata = service
+            .get_metadata(tonic::Request::new(crate::generated::GetMetadataRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            metadata.refresh_intervals,
+            [("bucket".to_string(), 600)].into_iter().collect()
+        );
+
+        let state = Bytes::from_static(br#"{"id": "b-1"}"#);
+        let read = |resource_type: &str| {
+            service.read(tonic::Request::new(crate::generated::ReadRequest {
+                resource_type: resource_type.to_string(),
+                current_state: state.clone(),
+                client_capabilities: None,
+            }))
+        };
+        for _ in 0..3 {
+            read("bucket").await.unwrap();
+            read("object").await.unwrap();
+        }
+        assert_eq!(reads.load(Ordering::SeqCst), 4);
+
+        // A deleted resource is read again, so core learns it is gone
+        service
+            .delete(tonic::Request::new(crate::generated::DeleteRequest {
+                resource_type: "bucket".to_string(),
+                current_state: state.clone(),
+                client_capabilities: None,
+                config: Bytes::new(),
+            }))
+            .await
+            .unwrap();
+        read("bucket").await.unwrap();
+        assert_eq!(reads.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_max_parallelism_per_resource_type() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let (now_running, most_running) = (Arc::clone(&running), Arc::clone(&max_running));
+        let provider = TestProvider {
+            schema: ProviderSchema::new()
+                .with_resource("record", Schema::v0().with_max_parallelism(2))
+                .with_resource("zone", Schema::v0()),
+            read: hook(move |request: ReadRequest| {
+                let (running, max_running) = (Arc::clone(&now_running), Arc::clone(&most_running));
+                async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(ReadResult::new(request.current_state))
+                }
+            }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let metadata = service
+            .get_metadata(tonic::Request::new(crate::generated::GetMetadataRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            metadata.max_parallelism,
+            [("record".to_string(), 2)].into_iter().collect()
+        );
+
+        let read = |resource_type: &str, id: usize| {
+            service.read(tonic::Request::new(crate::generated::ReadRequest {
+                resource_type: resource_type.to_string(),
+                current_state: serde_json::to_vec(&serde_json::json!({ "id": id }))
+                    .unwrap()
+                    .into(),
+                client_capabilities: None,
+            }))
+        };
+        let reads = futures_util::future::join_all((0..6).map(|id| read("record", id))).await;
+        assert!(reads.iter().all(Result::is_ok));
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+
+        max_running.store(0, Ordering::SeqCst);
+        futures_util::future::join_all((0..6).map(|id| read("zone", id))).await;
+        assert_eq!(max_running.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn test_serve_options_env_overrides() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+
+        let options = ServeOptions::new()
+            .with_shutdown_timeout(Duration::from_secs(5))
+            .with_max_concurrency(8)
+            .with_overrides_from(env(&[
+                ("HEMMER_PROVIDER_SHUTDOWN_TIMEOUT", "1500ms"),
+                ("HEMMER_PROVIDER_IDEMPOTENCY_TTL", "2m"),
+                ("HEMMER_PROVIDER_LOG_FORMAT", "json"),
+                ("HEMMER_PROVIDER_VALIDATE_STATE", "yes"),
+                ("HEMMER_PROVIDER_SELF_CHECK", "off"),
+                ("HEMMER_PROVIDER_READ_COALESCING", ""),
+            ]))
+            .unwrap();
+        assert_eq!(options.shutdown_timeout, Duration::from_millis(1500));
+        assert_eq!(options.idempotency_ttl, Duration::from_secs(120));
+        assert_eq!(options.max_concurrency, Some(8));
+        assert_eq!(options.log_format, Some(LogFormat::Json));
+        assert!(options.validate_state);
+        assert!(!options.self_check);
+        assert!(!options.read_coalescing);
 
-    serve_on_listener(provider, listener, addr, options).await
-}
+        let options = ServeOptions::new()
+            .with_max_concurrency(8)
+            .with_overrides_from(env(&[
+                ("HEMMER_PROVIDER_MAX_CONCURRENCY", "0"),
+                ("HEMMER_PROVIDER_SHUTDOWN_TIMEOUT", "45"),
+            ]))
+            .unwrap();
+        assert_eq!(options.max_concurrency, None);
+        assert_eq!(options.shutdown_timeout, Duration::from_secs(45));
+        assert_eq!(
+            ServeOptions::new().with_max_concurrency(0).max_concurrency,
+            None
+        );
 
-/// Serve a provider on a specific address.
-///
-/// Unlike [`serve`], this function binds to the specified address rather than
-/// finding an available port.
-pub async fn serve_on<P: ProviderService>(
-    provider: P,
-    addr: SocketAddr,
-) -> Result<(), Box<dyn std::error::Error>> {
-    serve_on_with_options(provider, addr, ServeOptions::default()).await
-}
+        let err = ServeOptions::new()
+            .with_overrides_from(env(&[("HEMMER_PROVIDER_SHUTDOWN_TIMEOUT", "soon")]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Configuration error: Invalid value 'soon' for HEMMER_PROVIDER_SHUTDOWN_TIMEOUT: expected a duration"
+        );
+    }
 
-/// Serve a provider on a specific address with custom options.
-pub async fn serve_on_with_options<P: ProviderService>(
-    provider: P,
-    addr: SocketAddr,
-    options: ServeOptions,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let listener = TcpListener::bind(addr).await?;
-    let actual_addr = listener.local_addr()?;
+    #[cfg(feature = "attestation")]
+    #[test]
+    fn test_signed_handshake() {
+        use crate::attestation::{verify_handshake, HandshakeSigner, NONCE_ENV};
 
-    serve_on_listener(provider, listener, actual_addr, options).await
-}
+        let addr: SocketAddr = "127.0.0.1:50051".parse().unwrap();
+        let signer = HandshakeSigner::from_seed(&[1; 32]).unwrap();
+        let options = ServeOptions::new().with_handshake_signer(signer.clone());
 
-/// Internal function to serve on an already-bound listener.
-async fn serve_on_listener<P: ProviderService>(
-    provider: P,
-    listener: TcpListener,
-    addr: SocketAddr,
-    options: ServeOptions,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Output the handshake
-    println!("{}|{}|{}", HANDSHAKE_PREFIX, PROTOCOL_VERSION, addr);
+        let nonce = |name: &str| (name == NONCE_ENV).then(|| "nonce-from-core".to_string());
+        let line = handshake_line(addr, &options, nonce).unwrap();
+        verify_handshake(&line, "nonce-from-core", &signer.public_key()).unwrap();
 
-    info!(address = %addr, "Provider server starting");
+        // Core didn't ask for attestation
+        assert_eq!(
+            handshake_line(addr, &options, |_| None).unwrap(),
+            "HEMMER_PROVIDER|1|127.0.0.1:50051"
+        );
+    }
 
-    // Wrap provider in Arc so we can share it between the gRPC service and shutdown handler
-    let provider = Arc::new(provider);
-    let provider_for_shutdown = Arc::clone(&provider);
+    #[tokio::test]
+    async fn test_duplicate_stop_cancels_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
 
-    // Create the gRPC service
-    let grpc_service = ProviderGrpcService { provider };
-    let server = crate::generated::provider_server::ProviderServer::new(grpc_service);
+        let stops = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&stops);
+        let provider = TestProvider {
+            stop: hook(move |()| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async { Err(ProviderError::Sdk("connections still open".to_string())) }
+            }),
+            // Wait for shutdown, then report what the operation observed
+            read: hook(|request: ReadRequest| async move {
+                Context::current().cancelled().await;
+                Ok(ReadResult::new(request.current_state))
+            }),
+            ..TestProvider::default()
+        };
 
-    // Run the server with graceful shutdown
-    // The shutdown_timeout limits how long we wait for in-flight requests to complete
-    let server_future = Server::builder()
-        .add_service(server)
-        .serve_with_incoming_shutdown(
-            tokio_stream::wrappers::TcpListenerStream::new(listener),
-            async {
-                wait_for_shutdown_signal().await;
-            },
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let teardowns = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&teardowns);
+        service.provider_data.set(
+            crate::context::ProviderData::new("client".to_string()).with_teardown(
+                move |_: Arc<String>| async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                },
+            ),
         );
+        let store = service.context().local_store();
+        store.put("regions", &["us-east-1"]).unwrap();
+        let stop = || service.stop(tonic::Request::new(crate::generated::StopRequest {}));
 
-    // Apply shutdown timeout - if the server doesn't shut down in time, we proceed anyway
-    let shutdown_result = tokio::time::timeout(options.shutdown_timeout, server_future).await;
+        let read = service.read(tonic::Request::new(crate::generated::ReadRequest {
+            resource_type: "server".to_string(),
+            current_state: Bytes::from_static(br#"{"name": "a"}"#),
+            client_capabilities: None,
+        }));
+        let (read, first, second) = tokio::join!(read, stop(), stop());
 
-    match shutdown_result {
-        Ok(Ok(())) => {
-            info!("Server shutdown complete");
-        },
-        Ok(Err(e)) => {
-            error!(error = %e, "Server error during shutdown");
-            return Err(e.into());
-        },
-        Err(_) => {
-            warn!(
-                timeout = ?options.shutdown_timeout,
-                "Shutdown timeout exceeded, forcing shutdown"
-            );
-        },
+        // The in-flight read was released by the cancellation
+        assert!(read.unwrap().into_inner().diagnostics.is_empty());
+        assert_eq!(
+            first.unwrap().into_inner().error,
+            "SDK error: connections still open"
+        );
+        assert_eq!(
+            second.unwrap().into_inner().error,
+            "SDK error: connections still open"
+        );
+        assert_eq!(stops.load(Ordering::SeqCst), 1);
+        assert!(service.context().is_cancelled());
+        // The provider data was torn down once, after stop(), and the local
+        // store cleared
+        assert_eq!(teardowns.load(Ordering::SeqCst), 1);
+        assert!(!service.context().is_configured());
+        assert_eq!(store.get::<Vec<String>>("regions").unwrap(), None);
     }
 
-    // Call the provider's stop() method
-    debug!("Calling provider stop()");
-    if let Err(e) = provider_for_shutdown.stop().await {
-        warn!(error = %e, "Provider stop() returned error");
+    #[tokio::test]
+    async fn test_internal_warnings() {
+        let request = || {
+            tonic::Request::new(crate::generated::PlanRequest {
+                resource_type: "test_resource".to_string(),
+                prior_state: Bytes::from_static(b"{not json"),
+                proposed_state: Bytes::from_static(br#"{"name": "a"}"#),
+                config: Bytes::from_static(br#"{"name": "a"}"#),
+                client_capabilities: None,
+            })
+        };
+
+        let quiet = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+        let response = quiet.plan(request()).await.unwrap().into_inner();
+        assert!(response.diagnostics.is_empty());
+
+        let service = ProviderGrpcService {
+            internal_warnings: true,
+            ..ProviderGrpcService::new(Arc::new(TestProvider::new()))
+        };
+        let response = service.plan(request()).await.unwrap().into_inner();
+        assert_eq!(response.diagnostics.len(), 1);
+        let warning = &response.diagnostics[0];
+        assert_eq!(
+            warning.severity,
+            crate::generated::diagnostic::Severity::Warning as i32
+        );
+        assert_eq!(
+            warning.summary,
+            "Request field 'prior_state' is not valid JSON"
+        );
+
+        let response = service
+            .read(tonic::Request::new(crate::generated::ReadRequest {
+                resource_type: "test_resource".to_string(),
+                current_state: Bytes::new(),
+                client_capabilities: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            response.diagnostics[0].summary,
+            "The current state is empty"
+        );
     }
 
-    info!("Provider shutdown complete");
-    Ok(())
-}
+    #[tokio::test]
+    async fn test_state_validation() {
+        let request = || {
+            tonic::Request::new(crate::generated::CreateRequest {
+                resource_type: "test_resource".to_string(),
+                planned_state: Bytes::from_static(br#"{"name":1}"#),
+                client_capabilities: None,
+                config: Bytes::new(),
+            })
+        };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::generated::provider_server::Provider;
-    use crate::schema::{Attribute, ProviderSchema, Schema};
-    use crate::types::PlanResult;
+        let unchecked = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+        let response = unchecked.create(request()).await.unwrap().into_inner();
+        assert!(response.diagnostics.is_empty());
 
-    // A simple test provider
-    struct TestProvider;
+        let service = ProviderGrpcService {
+            validate_state: true,
+            ..ProviderGrpcService::new(Arc::new(TestProvider::new()))
+        };
+        let response = service.create(request()).await.unwrap().into_inner();
+        assert_eq!(response.state, &br#"{"name":1}"#[..]);
+        assert_eq!(response.diagnostics.len(), 1);
+        let error = &response.diagnostics[0];
+        assert_eq!(
+            error.severity,
+            crate::generated::diagnostic::Severity::Error as i32
+        );
+        assert_eq!(error.summary, "Invalid type for attribute 'name'");
+        assert_eq!(error.attribute, "name");
 
-    #[async_trait::async_trait]
-    impl ProviderService for TestProvider {
-        fn schema(&self) -> ProviderSchema {
-            ProviderSchema::new().with_resource(
-                "test_resource",
-                Schema::v0().with_attribute("name", Attribute::required_string()),
-            )
-        }
+        let response = service
+            .read(tonic::Request::new(crate::generated::ReadRequest {
+                resource_type: "test_resource".to_string(),
+                current_state: Bytes::from_static(br#"{"name":"a"}"#),
+                client_capabilities: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.diagnostics.is_empty());
+    }
 
-        async fn configure(
-            &self,
-            _config: serde_json::Value,
-        ) -> Result<Vec<crate::schema::Diagnostic>, crate::error::ProviderError> {
-            Ok(vec![])
-        }
+    #[tokio::test]
+    async fn test_validate_data_source_outputs() {
+        let provider = TestProvider {
+            schema: ProviderSchema::new().with_data_source(
+                "image",
+                Schema::v0()
+                    .with_attribute("name", Attribute::required_string())
+                    .with_output("arn", crate::schema::AttributeType::String),
+            ),
+            // Forgets the ARN for images named "broken"
+            read_data_source: hook(|request: ReadDataSourceRequest| async move {
+                let config = request.config;
+                Ok(ReadResult::new(match config["name"].as_str() {
+                    Some("broken") => config,
+                    _ => serde_json::json!({"name": config["name"], "arn": "arn:image/1"}),
+                }))
+            }),
+            ..TestProvider::default()
+        };
 
-        async fn plan(
-            &self,
-            _resource_type: &str,
-            _prior_state: Option<serde_json::Value>,
-            proposed_state: serde_json::Value,
-            _config: serde_json::Value,
-        ) -> Result<PlanResult, crate::error::ProviderError> {
-            Ok(PlanResult::no_change(proposed_state))
-        }
+        let service = ProviderGrpcService {
+            validate_state: true,
+            ..ProviderGrpcService::new(Arc::new(provider))
+        };
+        let read = |config: &'static [u8]| {
+            service.read_data_source(tonic::Request::new(
+                crate::generated::ReadDataSourceRequest {
+                    data_source_type: "image".to_string(),
+                    config: Bytes::from_static(config),
+                    ..Default::default()
+                },
+            ))
+        };
 
-        async fn create(
-            &self,
-            _resource_type: &str,
-            planned_state: serde_json::Value,
-        ) -> Result<serde_json::Value, crate::error::ProviderError> {
-            Ok(planned_state)
-        }
+        let response = read(br#"{"name":"web"}"#).await.unwrap().into_inner();
+        assert!(response.diagnostics.is_empty());
 
-        async fn read(
-            &self,
-            _resource_type: &str,
-            current_state: serde_json::Value,
-        ) -> Result<serde_json::Value, crate::error::ProviderError> {
-            Ok(current_state)
-        }
+        let response = read(br#"{"name":"broken"}"#).await.unwrap().into_inner();
+        assert_eq!(&response.state[..], br#"{"name":"broken"}"#);
+        assert_eq!(response.diagnostics.len(), 1);
+        assert_eq!(
+            response.diagnostics[0].summary,
+            "Provider did not set computed attribute 'arn'"
+        );
+        assert_eq!(response.diagnostics[0].attribute, "arn");
+    }
 
-        async fn update(
-            &self,
-            _resource_type: &str,
-            _prior_state: serde_json::Value,
-            planned_state: serde_json::Value,
-        ) -> Result<serde_json::Value, crate::error::ProviderError> {
-            Ok(planned_state)
-        }
+    #[tokio::test]
+    async fn test_server_capabilities() {
+        let capable = TestProvider {
+            schema: ProviderSchema::new().with_function(
+                "upper",
+                crate::schema::FunctionSchema::new(crate::schema::AttributeType::String),
+            ),
+            capabilities: ServerCapabilities::new()
+                .with_plan_destroy()
+                .with_move_state(),
+            ..TestProvider::default()
+        };
 
-        async fn delete(
-            &self,
-            _resource_type: &str,
-            _current_state: serde_json::Value,
-        ) -> Result<(), crate::error::ProviderError> {
-            Ok(())
+        async fn capabilities<P: ProviderService>(provider: P) -> ServerCapabilities {
+            ProviderGrpcService::new(Arc::new(provider))
+                .get_metadata(tonic::Request::new(crate::generated::GetMetadataRequest {}))
+                .await
+                .unwrap()
+                .into_inner()
+                .server_capabilities
+                .unwrap()
+                .into()
         }
+
+        // Declared flags are kept and functions are derived from the schema
+        assert_eq!(
+            capabilities(capable).await.enabled(),
+            vec!["plan_destroy", "move_state", "functions"]
+        );
+
+        // Import is derived from declared import ID formats
+        assert_eq!(
+            capabilities(TestProvider::new()).await.enabled(),
+            vec!["import"]
+        );
     }
 
     #[tokio::test]
-    async fn test_get_schema_with_current_version() {
-        let service = ProviderGrpcService {
-            provider: Arc::new(TestProvider),
+    async fn test_import_formats() {
+        let service = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+        let metadata = service
+            .get_metadata(tonic::Request::new(crate::generated::GetMetadataRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            metadata.import_formats["test_resource"].formats,
+            vec!["{zone}/{name}"]
+        );
+
+        let import = |id: &str| {
+            tonic::Request::new(crate::generated::ImportResourceStateRequest {
+                resource_type: "test_resource".to_string(),
+                id: id.to_string(),
+                client_capabilities: None,
+            })
+        };
+        let response = service
+            .import_resource_state(import("web"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            response.diagnostics[0].summary,
+            "Validation error: Invalid import ID 'web': expected '{zone}/{name}'"
+        );
+
+        // Well-formed IDs reach the provider, which doesn't support import
+        let response = service
+            .import_resource_state(import("a/web"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.diagnostics[0]
+            .summary
+            .contains("Import not supported"));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_import() {
+        use futures_util::StreamExt;
+
+        let provider = TestProvider {
+            import: hook(|(resource_type, id): (String, String)| async move {
+                if id == "missing" {
+                    return Err(ProviderError::NotFound(id));
+                }
+                Ok(vec![ImportedResource::new(
+                    resource_type,
+                    serde_json::json!({"id": id}),
+                )])
+            }),
+            import_query: hook(
+                |(resource_type, query): (String, serde_json::Value)| async move {
+                    let zone = query["zone"].as_str().unwrap_or_default();
+                    Ok(["www", "mail"]
+                        .iter()
+                        .map(|name| {
+                            ImportedResource::new(
+                                &resource_type,
+                                serde_json::json!({"id": format!("{}/{}", zone, name)}),
+                            )
+                        })
+                        .collect())
+                },
+            ),
+            ..TestProvider::default()
         };
 
-        let request = tonic::Request::new(crate::generated::GetSchemaRequest {
-            client_protocol_version: crate::PROTOCOL_VERSION,
-        });
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let bulk_import = |ids: &[&str], query: &'static [u8]| {
+            service.bulk_import_resource_state(tonic::Request::new(
+                crate::generated::BulkImportResourceStateRequest {
+                    resource_type: "dns_record".to_string(),
+                    ids: ids.iter().map(|id| id.to_string()).collect(),
+                    query: Bytes::from_static(query),
+                    client_capabilities: None,
+                },
+            ))
+        };
+        let states = |result: &crate::generated::BulkImportResult| {
+            result
+                .imported
+                .iter()
+                .map(|r| decode_json(&r.state).unwrap()["id"].clone())
+                .collect::<Vec<_>>()
+        };
 
-        let response = service.get_schema(request).await;
-        assert!(response.is_ok());
+        let stream = bulk_import(&["a", "missing", "b"], b"").await.unwrap();
+        let mut results: Vec<_> = stream
+            .into_inner()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        results.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(results.len(), 3);
+        assert_eq!(states(&results[0]), vec!["a"]);
+        assert_eq!(states(&results[1]), vec!["b"]);
+        // A failed ID is reported on its own result
+        assert_eq!(results[2].id, "missing");
+        assert!(results[2].imported.is_empty());
+        assert_eq!(results[2].diagnostics[0].error_kind, "not_found");
 
-        let inner = response.unwrap().into_inner();
-        assert_eq!(inner.server_protocol_version, crate::PROTOCOL_VERSION);
-        assert!(inner.provider.is_some());
+        let stream = bulk_import(&[], br#"{"zone": "Z1"}"#).await.unwrap();
+        let results: Vec<_> = stream
+            .into_inner()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(states(&results[0]), vec!["Z1/www"]);
+        assert!(results.iter().all(|r| r.id.is_empty()));
+
+        assert_eq!(
+            bulk_import(&[], b"").await.err().unwrap().code(),
+            tonic::Code::InvalidArgument
+        );
+        assert_eq!(
+            bulk_import(&["a"], b"{}").await.err().unwrap().code(),
+            tonic::Code::InvalidArgument
+        );
     }
 
     #[tokio::test]
-    async fn test_get_schema_with_old_version() {
+    async fn test_payload_limits() {
+        let metrics = PayloadMetrics::new();
         let service = ProviderGrpcService {
-            provider: Arc::new(TestProvider),
+            payload_limits: PayloadLimits::new().with_soft_limit(20).with_hard_limit(40),
+            payload_metrics: metrics.clone(),
+            ..ProviderGrpcService::new(Arc::new(TestProvider::new()))
+        };
+        let create = |planned_state: &[u8]| {
+            tonic::Request::new(crate::generated::CreateRequest {
+                resource_type: "test_resource".to_string(),
+                planned_state: Bytes::copy_from_slice(planned_state),
+                client_capabilities: None,
+                config: Bytes::new(),
+            })
         };
 
-        let request = tonic::Request::new(crate::generated::GetSchemaRequest {
-            client_protocol_version: 0, // Too old
-        });
+        let response = service
+            .create(create(br#"{"name": "a"}"#))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.diagnostics.is_empty());
 
-        let response = service.get_schema(request).await;
-        assert!(response.is_err());
+        // Over the soft limit: created with a warning
+        let response = service
+            .create(create(br#"{"name": "abcdefghijklmn"}"#))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!response.state.is_empty());
+        assert_eq!(
+            response.diagnostics[0].summary,
+            "create planned_state is unusually large"
+        );
 
-        let err = response.unwrap_err();
-        assert_eq!(err.code(), tonic::Code::FailedPrecondition);
-        assert!(err.message().contains("too old"));
+        // Over the hard limit: rejected before the provider is called
+        let response = service
+            .create(create(
+                br#"{"name": "abcdefghijklmnopqrstuvwxyz0123456789"}"#,
+            ))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.state.is_empty());
+        assert_eq!(
+            response.diagnostics[0].severity,
+            crate::generated::diagnostic::Severity::Error as i32
+        );
+        assert!(response.diagnostics[0]
+            .summary
+            .contains("over the 40 byte limit"));
+
+        let stats = metrics.rpc("create").unwrap();
+        assert_eq!(stats.calls, 3);
+        assert_eq!(stats.max_request_bytes, 48);
+        assert_eq!(stats.max_response_bytes, 25);
     }
 
     #[tokio::test]
-    async fn test_get_schema_with_min_version() {
+    async fn test_metrics_sink() {
+        #[derive(Default)]
+        struct Recorder(std::sync::Mutex<Vec<(String, Option<String>, bool)>>);
+
+        impl MetricsSink for Arc<Recorder> {
+            fn on_operation_finish(
+                &self,
+                operation: &str,
+                resource_type: Option<&str>,
+                _duration: Duration,
+                outcome: Outcome<'_>,
+            ) {
+                self.0.lock().unwrap().push((
+                    operation.to_string(),
+                    resource_type.map(String::from),
+                    outcome.is_success(),
+                ));
+            }
+        }
+
+        let recorder = Arc::new(Recorder::default());
         let service = ProviderGrpcService {
-            provider: Arc::new(TestProvider),
+            metrics_sink: Some(Arc::new(recorder.clone())),
+            ..ProviderGrpcService::new(Arc::new(TestProvider::new()))
         };
 
-        let request = tonic::Request::new(crate::generated::GetSchemaRequest {
-            client_protocol_version: crate::MIN_PROTOCOL_VERSION,
-        });
+        service
+            .configure(tonic::Request::new(crate::generated::ConfigureRequest {
+                config: Bytes::from_static(b"{}"),
+                client_capabilities: None,
+            }))
+            .await
+            .unwrap();
+        service
+            .create(tonic::Request::new(crate::generated::CreateRequest {
+                resource_type: "test_resource".to_string(),
+                planned_state: Bytes::from_static(br#"{"name": "a"}"#),
+                client_capabilities: None,
+                config: Bytes::new(),
+            }))
+            .await
+            .unwrap();
+        service
+            .import_resource_state(tonic::Request::new(
+                crate::generated::ImportResourceStateRequest {
+                    resource_type: "test_resource".to_string(),
+                    id: "no-zone".to_string(),
+                    client_capabilities: None,
+                },
+            ))
+            .await
+            .unwrap();
 
-        let response = service.get_schema(request).await;
-        assert!(response.is_ok());
+        let recorded = recorder.0.lock().unwrap().clone();
+        let test_resource = Some("test_resource".to_string());
+        assert_eq!(
+            recorded,
+            vec![
+                ("configure".to_string(), None, true),
+                ("create".to_string(), test_resource.clone(), true),
+                ("import_resource_state".to_string(), test_resource, false),
+            ]
+        );
+    }
 
-        let inner = response.unwrap().into_inner();
-        assert_eq!(inner.server_protocol_version, crate::PROTOCOL_VERSION);
+    #[tokio::test]
+    async fn test_client_capabilities_in_context() {
+        let provider = TestProvider {
+            read: hook(|_| async {
+                let capabilities = Context::current().client_capabilities();
+                Ok(ReadResult::new(serde_json::json!({
+                    "deferral_allowed": capabilities.deferral_allowed,
+                    "warnings_on_apply": capabilities.warnings_on_apply,
+                })))
+            }),
+            ..TestProvider::default()
+        };
+
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        let read = |client_capabilities| {
+            tonic::Request::new(crate::generated::ReadRequest {
+                resource_type: "thing".to_string(),
+                current_state: Bytes::from_static(b"{}"),
+                client_capabilities,
+            })
+        };
+
+        let response = service.read(read(None)).await.unwrap().into_inner();
+        assert_eq!(
+            response.state,
+            &br#"{"deferral_allowed":false,"warnings_on_apply":false}"#[..]
+        );
+
+        let capabilities = crate::generated::ClientCapabilities {
+            deferral_allowed: true,
+            warnings_on_apply: false,
+            streamed_progress: true,
+        };
+        let response = service
+            .read(read(Some(capabilities)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            response.state,
+            &br#"{"deferral_allowed":true,"warnings_on_apply":false}"#[..]
+        );
     }
 
     #[tokio::test]
-    async fn test_get_schema_with_newer_version() {
-        let service = ProviderGrpcService {
-            provider: Arc::new(TestProvider),
+    async fn test_operation_in_context() {
+        let provider = TestProvider {
+            configure: hook(|_| async {
+                assert_eq!(Context::current().operation(), Some("configure"));
+                assert_eq!(Context::current().resource_type(), None);
+                Ok(ConfigureResult::new())
+            }),
+            read: hook(|_| async {
+                let context = Context::current();
+                Ok(ReadResult::new(serde_json::json!({
+                    "operation": context.operation(),
+                    "resource_type": context.resource_type(),
+                    "request_id": context.request_id(),
+                })))
+            }),
+            ..TestProvider::default()
         };
 
-        let request = tonic::Request::new(crate::generated::GetSchemaRequest {
-            client_protocol_version: crate::PROTOCOL_VERSION + 1,
+        let service = ProviderGrpcService::new(Arc::new(provider));
+        service
+            .configure(tonic::Request::new(crate::generated::ConfigureRequest {
+                config: Bytes::from_static(b"{}"),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+
+        let read = || async {
+            let response = service
+                .read(tonic::Request::new(crate::generated::ReadRequest {
+                    resource_type: "thing".to_string(),
+                    current_state: Bytes::from_static(b"{}"),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            serde_json::from_slice::<serde_json::Value>(&response.state).unwrap()
+        };
+        let first = read().await;
+        assert_eq!(first["operation"], "read");
+        assert_eq!(first["resource_type"], "thing");
+        let request_id = first["request_id"].as_str().unwrap();
+        assert!(request_id.starts_with(&format!("{}-", std::process::id())));
+        assert_ne!(read().await["request_id"], request_id);
+        assert_eq!(Context::current().request_id(), None);
+    }
+
+    #[tokio::test]
+    async fn test_events_stream() {
+        use crate::events::Event;
+        use crate::generated::provider_client::ProviderClient;
+        use crate::generated::provider_server::ProviderServer;
+        use crate::schema::Diagnostic;
+
+        let service = ProviderGrpcService::new(Arc::new(TestProvider::new()));
+        // Operations send through the server's sender
+        let events = service.context().events();
+        let shutdown = service.shutdown.clone();
+        events.send(Event::new("quota", Diagnostic::warning("Quota 90% used")));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(ProviderServer::new(service))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        let mut client = ProviderClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+
+        let (acks, ack_rx) = tokio::sync::mpsc::channel(4);
+        let ack_stream = futures_util::stream::unfold(ack_rx, |mut rx| async move {
+            rx.recv().await.map(|request| (request, rx))
         });
+        let mut stream = client.events(ack_stream).await.unwrap().into_inner();
 
-        let response = service.get_schema(request).await;
-        // Should succeed (with a warning in logs)
-        assert!(response.is_ok());
+        // Events sent before the stream opened are replayed
+        let first = stream.message().await.unwrap().unwrap();
+        assert_eq!(first.sequence, 1);
+        assert_eq!(first.kind, "quota");
+        assert_eq!(first.diagnostic.unwrap().summary, "Quota 90% used");
+        assert_eq!(first.resource_type, "");
 
-        let inner = response.unwrap().into_inner();
-        assert_eq!(inner.server_protocol_version, crate::PROTOCOL_VERSION);
+        events.send(
+            Event::new("deprecation", Diagnostic::warning("v1 API is deprecated"))
+                .with_resource_type("bucket"),
+        );
+        let second = stream.message().await.unwrap().unwrap();
+        assert_eq!(second.sequence, 2);
+        assert_eq!(second.resource_type, "bucket");
+
+        acks.send(crate::generated::EventsRequest { ack_sequence: 2 })
+            .await
+            .unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while !events.events_after(0).is_empty() {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // Shutdown ends the stream
+        shutdown.trigger();
+        assert!(stream.message().await.unwrap().is_none());
     }
 }
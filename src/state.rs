@@ -0,0 +1,193 @@
+//! Canonical serialization of states.
+//!
+//! `serde_json` output depends on how a value was built: object keys keep
+//! insertion order when any crate in the build enables serde_json's
+//! `preserve_order` feature, and `1` and `1.0` serialize differently even
+//! though core treats them as the same number. [`canonical_bytes`] removes
+//! both differences, so hashes of a state (content-addressed caches, drift
+//! fingerprints, idempotency tokens) are the same across provider restarts,
+//! builds and platforms:
+//!
+//! - object keys are sorted by their UTF-8 bytes
+//! - there is no insignificant whitespace
+//! - integral numbers are written without a fraction or exponent (`1.0` is `1`)
+//! - other numbers use the shortest representation that round-trips
+//!
+//! [`canonical_eq`] compares values by their canonical form.
+//!
+//! With the `arbitrary-precision` feature, decimals keep every digit in the
+//! canonical form instead of being read as `f64`, so values that differ only
+//! beyond `f64` precision (see [`crate::number`]) hash and compare as
+//! different.
+//!
+//! # Example
+//!
+//! ```
+//! use hemmer_provider_sdk::state::{canonical_bytes, canonical_eq};
+//! use serde_json::json;
+//!
+//! let a = json!({"size": 1.0, "name": "disk"});
+//! let b = json!({"name": "disk", "size": 1});
+//! assert_eq!(canonical_bytes(&a), br#"{"name":"disk","size":1}"#);
+//! assert!(canonical_eq(&a, &b));
+//! ```
+
+use serde_json::{Number, Value};
+
+/// Integral floats below this magnitude are written as integers. Beyond it,
+/// `f64` can't represent every integer, so the float form is kept.
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Serialize `value` as canonical JSON.
+pub fn canonical_bytes(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+/// Whether `a` and `b` have the same canonical form, e.g. `1` and `1.0`.
+pub fn canonical_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => canonical_number(a) == canonical_number(b),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| canonical_eq(a, b))
+        },
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| canonical_eq(a, b)))
+        },
+        _ => a == b,
+    }
+}
+
+fn write_canonical(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Number(number) => out.extend_from_slice(canonical_number(number).as_bytes()),
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(b']');
+        },
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+            out.push(b'{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_string(key, out);
+                out.push(b':');
+                write_canonical(item, out);
+            }
+            out.push(b'}');
+        },
+        Value::String(s) => write_string(s, out),
+        // null and booleans have a single spelling
+        other => out.extend_from_slice(other.to_string().as_bytes()),
+    }
+}
+
+fn write_string(s: &str, out: &mut Vec<u8>) {
+    // serde_json escapes deterministically; writing to a Vec can't fail
+    serde_json::to_writer(&mut *out, s).unwrap();
+}
+
+fn canonical_number(number: &Number) -> String {
+    if number.is_i64() || number.is_u64() {
+        return number.to_string();
+    }
+    #[cfg(feature = "arbitrary-precision")]
+    if let Some(plain) = crate::number::plain_decimal(&number.to_string()) {
+        return plain;
+    }
+    match number.as_f64() {
+        Some(f) if f.fract() == 0.0 && f.abs() < MAX_SAFE_INTEGER => {
+            // Avoid "-0" for negative zero
+            (f as i64).to_string()
+        },
+        // Rust prints the shortest round-tripping decimal on every platform
+        Some(f) => f.to_string(),
+        None => number.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_canonical_bytes() {
+        let value = json!({
+            "zone": "b",
+            "name": "web \"1\"\n",
+            "ports": [443, 80.0, -0.0, 0.5],
+            "nested": {"y": null, "x": true, "ä": 1, "z": 1e300},
+        });
+        assert_eq!(
+            String::from_utf8(canonical_bytes(&value)).unwrap(),
+            format!(
+                r#"{{"name":"web \"1\"\n","nested":{{"x":true,"y":null,"z":{},"ä":1}},"ports":[443,80,0,0.5],"zone":"b"}}"#,
+                1e300
+            )
+        );
+    }
+
+    #[test]
+    fn test_canonical_bytes_ignores_key_order() {
+        let mut a = serde_json::Map::new();
+        a.insert("b".to_string(), json!(2));
+        a.insert("a".to_string(), json!(1));
+        let mut b = serde_json::Map::new();
+        b.insert("a".to_string(), json!(1.0));
+        b.insert("b".to_string(), json!(2));
+        assert_eq!(
+            canonical_bytes(&Value::Object(a)),
+            canonical_bytes(&Value::Object(b))
+        );
+    }
+
+    #[test]
+    fn test_canonical_eq() {
+        assert!(canonical_eq(
+            &json!({"n": [1, 2.0]}),
+            &json!({"n": [1.0, 2]})
+        ));
+        assert!(!canonical_eq(
+            &json!({"n": [1, 2]}),
+            &json!({"n": [1, 2.5]})
+        ));
+        assert!(!canonical_eq(&json!({"n": 1}), &json!({"n": 1, "m": null})));
+        assert!(!canonical_eq(&json!("1"), &json!(1)));
+        assert!(canonical_eq(
+            &json!(9007199254740993u64),
+            &json!(9007199254740993u64)
+        ));
+    }
+
+    #[cfg(feature = "arbitrary-precision")]
+    #[test]
+    fn test_canonical_exact_decimals() {
+        let parse = |s: &str| serde_json::from_str::<Value>(s).unwrap();
+        assert!(canonical_eq(&parse("0.10"), &parse("1e-1")));
+        assert!(canonical_eq(
+            &parse("9007199254740993.0"),
+            &parse("9007199254740993")
+        ));
+        assert!(!canonical_eq(
+            &parse("0.1"),
+            &parse("0.1000000000000000000001")
+        ));
+        assert_eq!(
+            canonical_bytes(&parse(r#"{"v": [2.50, 1e300, -0.0]}"#)),
+            format!(r#"{{"v":[2.5,{},0]}}"#, 1e300).into_bytes()
+        );
+    }
+}
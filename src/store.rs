@@ -0,0 +1,390 @@
+//! Provider-local key/value storage.
+//!
+//! Providers often keep results around between operations: discovered
+//! endpoints, region lists, ETags for conditional requests. A [`LocalStore`]
+//! gives them one place to put those instead of ad hoc temp files. It is
+//! scoped to the provider process: the server clears it when the provider
+//! stops, after the provider's own `stop()` has run.
+//!
+//! The store is available from any operation through
+//! [`Context::local_store`](crate::context::Context::local_store). Values are
+//! anything serde can serialize, and may be given a time to live:
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use hemmer_provider_sdk::store::LocalStore;
+//!
+//! let store = LocalStore::memory();
+//! store
+//!     .put_with_ttl("etag:bucket/logs", &"\"abc123\"", Duration::from_secs(300))
+//!     .unwrap();
+//! let etag: Option<String> = store.get("etag:bucket/logs").unwrap();
+//! assert_eq!(etag.as_deref(), Some("\"abc123\""));
+//! ```
+//!
+//! The server's store is in memory unless set with
+//! [`ServeOptions::with_local_store`](crate::ServeOptions::with_local_store).
+//! With the `disk-store` feature, [`DiskStore`] keeps entries as files in a
+//! directory, for caches too large to hold in memory. Other backends implement
+//! [`StoreBackend`].
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::ProviderError;
+
+/// Where a [`LocalStore`] keeps its entries.
+///
+/// Methods are synchronous and should be quick; they run on the request path.
+pub trait StoreBackend: Send + Sync + 'static {
+    /// The value stored under `key`, if any.
+    fn get(&self, key: &str) -> Result<Option<Value>, ProviderError>;
+
+    /// Store `value` under `key`, replacing any previous value.
+    fn put(&self, key: &str, value: Value) -> Result<(), ProviderError>;
+
+    /// Remove the value under `key`. Returns whether there was one.
+    fn remove(&self, key: &str) -> Result<bool, ProviderError>;
+
+    /// Remove every value.
+    fn clear(&self) -> Result<(), ProviderError>;
+}
+
+/// A [`StoreBackend`] holding entries in memory.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, Value>>,
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StoreBackend for MemoryStore {
+    fn get(&self, key: &str) -> Result<Option<Value>, ProviderError> {
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+
+    fn put(&self, key: &str, value: Value) -> Result<(), ProviderError> {
+        self.entries.lock().unwrap().insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<bool, ProviderError> {
+        Ok(self.entries.lock().unwrap().remove(key).is_some())
+    }
+
+    fn clear(&self) -> Result<(), ProviderError> {
+        self.entries.lock().unwrap().clear();
+        Ok(())
+    }
+}
+
+/// A [`StoreBackend`] keeping each entry as a JSON file in a directory.
+///
+/// The directory is created on first write and removed by
+/// [`clear`](StoreBackend::clear), so it should be dedicated to the store.
+/// Writes go through a temporary file, so a crash never leaves a partial entry.
+#[cfg(feature = "disk-store")]
+#[derive(Debug)]
+pub struct DiskStore {
+    dir: std::path::PathBuf,
+}
+
+#[cfg(feature = "disk-store")]
+impl DiskStore {
+    /// Create a store in `dir`.
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Create a store in a directory under the system temp directory named
+    /// after the current process.
+    pub fn temporary() -> Self {
+        Self::new(std::env::temp_dir().join(format!("hemmer-provider-{}", std::process::id())))
+    }
+
+    /// The directory entries are kept in.
+    pub fn dir(&self) -> &std::path::Path {
+        &self.dir
+    }
+
+    /// The file for `key`. Keys are hex-encoded, so any key is a valid name.
+    fn path(&self, key: &str) -> std::path::PathBuf {
+        let name: String = key.bytes().map(|b| format!("{:02x}", b)).collect();
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+#[cfg(feature = "disk-store")]
+impl StoreBackend for DiskStore {
+    fn get(&self, key: &str) -> Result<Option<Value>, ProviderError> {
+        match std::fs::read(self.path(key)) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| ProviderError::Sdk(format!("Corrupt store entry '{}': {}", key, e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(store_error(key, e)),
+        }
+    }
+
+    fn put(&self, key: &str, value: Value) -> Result<(), ProviderError> {
+        let path = self.path(key);
+        let temp = path.with_extension("tmp");
+        std::fs::create_dir_all(&self.dir).map_err(|e| store_error(key, e))?;
+        std::fs::write(&temp, serde_json::to_vec(&value)?).map_err(|e| store_error(key, e))?;
+        std::fs::rename(&temp, &path).map_err(|e| store_error(key, e))
+    }
+
+    fn remove(&self, key: &str) -> Result<bool, ProviderError> {
+        match std::fs::remove_file(self.path(key)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(store_error(key, e)),
+        }
+    }
+
+    fn clear(&self) -> Result<(), ProviderError> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(ProviderError::Sdk(format!(
+                "Failed to remove store {}: {}",
+                self.dir.display(),
+                e
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "disk-store")]
+fn store_error(key: &str, err: std::io::Error) -> ProviderError {
+    ProviderError::Sdk(format!("Store entry '{}': {}", key, err))
+}
+
+/// A stored value and when it expires, in milliseconds since the Unix epoch.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    value: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Provider-process key/value storage over a [`StoreBackend`].
+///
+/// Cloning a `LocalStore` shares the same entries.
+#[derive(Clone)]
+pub struct LocalStore {
+    backend: Arc<dyn StoreBackend>,
+}
+
+impl LocalStore {
+    /// Create a store over `backend`.
+    pub fn new(backend: impl StoreBackend) -> Self {
+        Self {
+            backend: Arc::new(backend),
+        }
+    }
+
+    /// Create an in-memory store.
+    pub fn memory() -> Self {
+        Self::new(MemoryStore::new())
+    }
+
+    /// The process-wide store used when the context carries none.
+    pub(crate) fn shared() -> &'static LocalStore {
+        static SHARED: OnceLock<LocalStore> = OnceLock::new();
+        SHARED.get_or_init(LocalStore::memory)
+    }
+
+    /// The value stored under `key`, or `None` if there is none or it has
+    /// expired.
+    ///
+    /// Fails with [`ProviderError::Serialization`] if the value doesn't
+    /// deserialize as `T`.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, ProviderError> {
+        let Some(entry) = self.backend.get(key)? else {
+            return Ok(None);
+        };
+        let entry: Entry = serde_json::from_value(entry)?;
+        if entry.expires_at.is_some_and(|at| at <= now_millis()) {
+            self.backend.remove(key)?;
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_value(entry.value)?))
+    }
+
+    /// Store `value` under `key` until the provider stops.
+    pub fn put<T: Serialize + ?Sized>(&self, key: &str, value: &T) -> Result<(), ProviderError> {
+        self.put_entry(key, value, None)
+    }
+
+    /// Store `value` under `key` for at most `ttl`.
+    pub fn put_with_ttl<T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<(), ProviderError> {
+        let expires_at = now_millis().saturating_add(ttl.as_millis() as u64);
+        self.put_entry(key, value, Some(expires_at))
+    }
+
+    /// Return the value stored under `key`, or compute it with `f` and store
+    /// it for at most `ttl` (until the provider stops if `None`).
+    ///
+    /// Errors from `f` are returned and nothing is stored.
+    pub async fn get_or_insert_with<T, F, Fut>(
+        &self,
+        key: &str,
+        ttl: Option<Duration>,
+        f: F,
+    ) -> Result<T, ProviderError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<T, ProviderError>>,
+    {
+        if let Some(value) = self.get(key)? {
+            return Ok(value);
+        }
+        let value = f().await?;
+        match ttl {
+            Some(ttl) => self.put_with_ttl(key, &value, ttl)?,
+            None => self.put(key, &value)?,
+        }
+        Ok(value)
+    }
+
+    /// Remove the value under `key`. Returns whether there was one.
+    pub fn remove(&self, key: &str) -> Result<bool, ProviderError> {
+        self.backend.remove(key)
+    }
+
+    /// Remove every value. The server calls this when the provider stops.
+    pub fn clear(&self) -> Result<(), ProviderError> {
+        self.backend.clear()
+    }
+
+    fn put_entry<T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        value: &T,
+        expires_at: Option<u64>,
+    ) -> Result<(), ProviderError> {
+        let entry = Entry {
+            value: serde_json::to_value(value)?,
+            expires_at,
+        };
+        self.backend.put(key, serde_json::to_value(entry)?)
+    }
+}
+
+impl Default for LocalStore {
+    fn default() -> Self {
+        Self::memory()
+    }
+}
+
+impl fmt::Debug for LocalStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalStore").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn exercise(store: &LocalStore) {
+        assert_eq!(store.get::<String>("missing").unwrap(), None);
+
+        store.put("regions", &["us-east-1", "eu-west-1"]).unwrap();
+        let regions: Vec<String> = store.get("regions").unwrap().unwrap();
+        assert_eq!(regions, ["us-east-1", "eu-west-1"]);
+
+        // Keys may contain anything, including path separators
+        store
+            .put("etag:bucket/logs", &json!({"etag": "abc"}))
+            .unwrap();
+        assert_eq!(
+            store.get::<Value>("etag:bucket/logs").unwrap(),
+            Some(json!({"etag": "abc"}))
+        );
+        assert!(store.get::<u32>("regions").is_err());
+
+        store.put_with_ttl("short", &1, Duration::ZERO).unwrap();
+        assert_eq!(store.get::<u32>("short").unwrap(), None);
+        assert!(!store.remove("short").unwrap());
+
+        assert!(store.remove("regions").unwrap());
+        assert_eq!(store.get::<Vec<String>>("regions").unwrap(), None);
+
+        store.clear().unwrap();
+        assert_eq!(store.get::<Value>("etag:bucket/logs").unwrap(), None);
+    }
+
+    #[test]
+    fn test_memory_store() {
+        exercise(&LocalStore::memory());
+    }
+
+    #[cfg(feature = "disk-store")]
+    #[test]
+    fn test_disk_store() {
+        let dir = std::env::temp_dir().join(format!("hemmer-store-test-{}", std::process::id()));
+        let store = LocalStore::new(DiskStore::new(&dir));
+        exercise(&store);
+        assert!(!dir.exists());
+
+        // Entries outlive the handle until the store is cleared
+        store.put("zones", &3).unwrap();
+        let reopened = LocalStore::new(DiskStore::new(&dir));
+        assert_eq!(reopened.get::<u32>("zones").unwrap(), Some(3));
+        reopened.clear().unwrap();
+        assert!(!dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_get_or_insert_with() {
+        let store = LocalStore::memory();
+        let endpoint: String = store
+            .get_or_insert_with("endpoint", None, || async { Ok("https://a".to_string()) })
+            .await
+            .unwrap();
+        assert_eq!(endpoint, "https://a");
+
+        let cached: String = store
+            .get_or_insert_with("endpoint", None, || async {
+                Err(ProviderError::Unavailable("not called".to_string()))
+            })
+            .await
+            .unwrap();
+        assert_eq!(cached, "https://a");
+
+        let failed = store
+            .get_or_insert_with::<String, _, _>("other", None, || async {
+                Err(ProviderError::Unavailable("down".to_string()))
+            })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(store.get::<String>("other").unwrap(), None);
+    }
+}
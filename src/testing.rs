@@ -24,12 +24,81 @@
 //!     assert_eq!(state["name"], "test-resource");
 //! }
 //! ```
+//!
+//! # Secret leaks
+//!
+//! [`ProviderTester::with_leak_check`] turns secret-leak prevention into an
+//! automated check: the tester records the values of sensitive attributes
+//! (per the schema) that pass through it, and panics if one of them shows up
+//! in a diagnostic, an error message, a log line emitted during the
+//! operation, or a plan change that isn't marked sensitive.
+//!
+//! ```ignore
+//! let tester = ProviderTester::new(MyProvider::new()).with_leak_check();
+//! tester.configure(json!({"api_key": "sk-live-1234"})).await.unwrap();
+//! // Panics if the provider logs or reports the API key
+//! tester.lifecycle_crud("my_resource", config, updated_config).await.unwrap();
+//! ```
+//!
+//! # Cancellation
+//!
+//! [`ProviderTester::interrupt`] runs an operation and interrupts it midway the
+//! way a real server would: by dropping it (core hung up), firing the context's
+//! cancellation token, or sending `Stop`. Afterwards, [`UpstreamTracker`] and
+//! [`assert_locks_released`] check that the provider cleaned up:
+//!
+//! ```ignore
+//! let upstream = UpstreamTracker::new();
+//! let tester = ProviderTester::new(MyProvider::with_upstream(upstream.clone()));
+//! let result = tester
+//!     .interrupt(Interrupt::Cancel, Duration::from_millis(50), tester.create("vm", config))
+//!     .await;
+//! assert!(result.unwrap().is_err());
+//! upstream.assert_cleaned_up();
+//! ```
+//!
+//! # Call log
+//!
+//! Every provider call made through the tester is recorded with its resource
+//! type, duration, input and output. [`ProviderTester::call_log`] returns the
+//! calls so far, for asserting on what the provider was asked to do:
+//!
+//! ```ignore
+//! tester.lifecycle_update("my_resource", state, updated_config).await.unwrap();
+//! let calls = tester.call_log();
+//! assert_eq!(calls.count("update"), 1);
+//! assert_eq!(calls.count_for("read", "my_resource"), 1);
+//! ```
+//!
+//! # State matching
+//!
+//! [`assert_state_matches!`](crate::assert_state_matches) compares a state
+//! with a JSON-like pattern naming only the attributes a test cares about,
+//! with [`matchers`] for values it can't pin exactly.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use tracing::instrument::WithSubscriber;
+
+use crate::codec::transform_sensitive;
+use crate::context::{CancellationSource, ClientCapabilities, Context, ProviderDataStore};
 use crate::error::ProviderError;
-use crate::schema::{Diagnostic, DiagnosticSeverity, ProviderSchema};
-use crate::server::ProviderService;
-use crate::types::{ImportedResource, PlanResult};
-use serde_json::Value;
+use crate::fuzz::{FuzzRng, SeedRng};
+use crate::lock::ResourceLocks;
+use crate::schema::{Block, BlockNestingMode, Diagnostic, DiagnosticSeverity, ProviderSchema};
+use crate::server::{idempotency_token, ProviderService};
+use crate::types::{
+    ConfigureResult, CreateRequest, DeleteRequest, ImpactEstimate, ImportedResource, PlanResult,
+    ReadDataSourceRequest, ReadRequest, ReadResult, UpdateRequest,
+};
+use crate::validation::validate_state;
+use serde::Serialize;
+use serde_json::{json, Value};
 
 /// A test harness for provider implementations.
 ///
@@ -47,12 +116,43 @@ use serde_json::Value;
 /// ```
 pub struct ProviderTester<P: ProviderService> {
     provider: P,
+    provider_data: ProviderDataStore,
+    client_capabilities: ClientCapabilities,
+    leaks: Option<LeakCheck>,
+    cancellation: Mutex<CancellationSource>,
+    calls: Mutex<Vec<Call>>,
 }
 
 impl<P: ProviderService> ProviderTester<P> {
     /// Create a new tester for the given provider.
     pub fn new(provider: P) -> Self {
-        Self { provider }
+        Self {
+            provider,
+            provider_data: ProviderDataStore::new(),
+            client_capabilities: ClientCapabilities::default(),
+            leaks: None,
+            cancellation: Mutex::new(CancellationSource::new()),
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Panic if a sensitive value leaks out of an operation.
+    ///
+    /// The tester records the value of every sensitive attribute in the
+    /// configs and states passed to and returned by the provider, and checks
+    /// that none of them appears in diagnostics, error messages, log lines
+    /// emitted by the operation's task, or plan changes not marked
+    /// `sensitive`. Only string values of at least four characters are
+    /// tracked, since shorter ones would match by accident.
+    pub fn with_leak_check(mut self) -> Self {
+        self.leaks = Some(LeakCheck::new());
+        self
+    }
+
+    /// Run operations as if core had sent these capabilities (none by default).
+    pub fn with_client_capabilities(mut self, capabilities: ClientCapabilities) -> Self {
+        self.client_capabilities = capabilities;
+        self
     }
 
     /// Get a reference to the underlying provider.
@@ -65,6 +165,121 @@ impl<P: ProviderService> ProviderTester<P> {
         &mut self.provider
     }
 
+    /// The provider calls made through this tester so far.
+    pub fn call_log(&self) -> CallLog {
+        CallLog {
+            calls: self.calls.lock().unwrap().clone(),
+        }
+    }
+
+    /// Forget the calls recorded so far, e.g. after setting up fixtures.
+    pub fn clear_call_log(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+
+    /// The operation context.
+    fn context(&self) -> Context {
+        self.provider_data
+            .context()
+            .with_client_capabilities(self.client_capabilities)
+            .with_cancellation(self.cancellation.lock().unwrap().token())
+    }
+
+    /// Run a provider call with the operation context in scope.
+    async fn scoped<F>(
+        &self,
+        operation: &'static str,
+        resource_type: Option<&str>,
+        input: Value,
+        call: F,
+    ) -> F::Output
+    where
+        F: Future,
+        F::Output: Snapshot,
+    {
+        self.scoped_with(self.context(), operation, resource_type, input, call)
+            .await
+    }
+
+    /// Run a provider call with `context` in scope, capturing its logs if
+    /// leaks are checked, and record it in the call log.
+    async fn scoped_with<F>(
+        &self,
+        context: Context,
+        operation: &'static str,
+        resource_type: Option<&str>,
+        input: Value,
+        call: F,
+    ) -> F::Output
+    where
+        F: Future,
+        F::Output: Snapshot,
+    {
+        let started = Instant::now();
+        let output = match &self.leaks {
+            Some(leaks) => {
+                context
+                    .scope(call)
+                    .with_subscriber(leaks.dispatch.clone())
+                    .await
+            },
+            None => context.scope(call).await,
+        };
+        self.calls.lock().unwrap().push(Call {
+            operation,
+            resource_type: resource_type.map(str::to_string),
+            duration: started.elapsed(),
+            input,
+            output: output.snapshot(),
+        });
+        output
+    }
+
+    /// Record the sensitive values in `values`, which belong to `target`.
+    fn watch(&self, target: Target<'_>, values: &[&Value]) {
+        let Some(leaks) = &self.leaks else {
+            return;
+        };
+        let schema = self.provider.schema();
+        let block = match target {
+            Target::Provider => Some(&schema.provider.block),
+            Target::Resource(name) => schema.resources.get(name).map(|s| &s.block),
+            Target::DataSource(name) => schema.data_sources.get(name).map(|s| &s.block),
+        };
+        if let Some(block) = block {
+            for value in values {
+                leaks.record(block, value);
+            }
+        }
+    }
+
+    /// Check an operation's logs, error and diagnostics for leaked values.
+    fn check_leaks<T>(
+        &self,
+        operation: &str,
+        result: &Result<T, ProviderError>,
+        diagnostics: &[Diagnostic],
+    ) {
+        let Some(leaks) = &self.leaks else {
+            return;
+        };
+        leaks.check_logs(operation);
+        if let Err(e) = result {
+            leaks.check(operation, "error message", &e.to_string());
+        }
+        for diagnostic in diagnostics {
+            leaks.check(operation, "diagnostic summary", &diagnostic.summary);
+            for (place, text) in [
+                ("diagnostic detail", &diagnostic.detail),
+                ("diagnostic suggestion", &diagnostic.suggestion),
+            ] {
+                if let Some(text) = text {
+                    leaks.check(operation, place, text);
+                }
+            }
+        }
+    }
+
     // =========================================================================
     // Schema & Metadata
     // =========================================================================
@@ -93,22 +308,111 @@ impl<P: ProviderService> ProviderTester<P> {
     /// Returns `Ok(())` if validation passes (no error diagnostics).
     /// Returns `Err` with the diagnostics if there are errors.
     pub async fn validate_provider_config(&self, config: Value) -> Result<(), TestError> {
-        let diagnostics = self.provider.validate_provider_config(config).await?;
-        check_diagnostics(diagnostics)
+        self.watch(Target::Provider, &[&config]);
+        let result = self
+            .scoped(
+                "validate_provider_config",
+                None,
+                json!({ "config": config }),
+                self.provider.validate_provider_config(config),
+            )
+            .await;
+        let diagnostics = result.as_deref().unwrap_or_default();
+        self.check_leaks("validate_provider_config", &result, diagnostics);
+        check_diagnostics(result?)
     }
 
     /// Configure the provider.
     ///
     /// Returns `Ok(())` if configuration succeeds.
     /// Returns `Err` with the diagnostics if there are errors.
+    /// On success, provider data from the [`ConfigureResult`]
+    /// is stored and made available to later operations, as the server does.
     pub async fn configure(&self, config: Value) -> Result<(), TestError> {
-        let diagnostics = self.provider.configure(config).await?;
-        check_diagnostics(diagnostics)
+        self.watch(Target::Provider, &[&config]);
+        let result = self
+            .scoped(
+                "configure",
+                None,
+                json!({ "config": config }),
+                self.provider.configure(config),
+            )
+            .await;
+        let diagnostics = result.as_ref().map_or(&[][..], |r| &r.diagnostics);
+        self.check_leaks("configure", &result, diagnostics);
+        let result = result?;
+        if let (false, Some(data)) = (result.has_errors(), result.provider_data) {
+            self.provider_data.set(data);
+        }
+        check_diagnostics(result.diagnostics)
     }
 
     /// Stop the provider.
+    ///
+    /// Like the server's `Stop`, this cancels running and later operations,
+    /// then calls `stop()` and tears down the provider data.
     pub async fn stop(&self) -> Result<(), ProviderError> {
-        self.provider.stop().await
+        self.cancel();
+        let result = self
+            .scoped("stop", None, Value::Null, self.provider.stop())
+            .await;
+        self.provider_data.teardown().await;
+        self.check_leaks("stop", &result, &[]);
+        result
+    }
+
+    /// Fire the cancellation token of running and later operations, as the
+    /// server does on shutdown.
+    pub fn cancel(&self) {
+        self.cancellation.lock().unwrap().cancel();
+    }
+
+    /// Run `call`, a tester operation, and interrupt it after `after`.
+    ///
+    /// Returns `None` if the call was dropped, and its output otherwise,
+    /// including when it finished before `after`. After
+    /// [`Interrupt::Cancel`], later operations get a fresh cancellation
+    /// token.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the call doesn't return within [`CANCELLATION_GRACE`] of
+    /// being cancelled or stopped, or if `stop()` fails.
+    pub async fn interrupt<F: Future>(
+        &self,
+        interrupt: Interrupt,
+        after: Duration,
+        call: F,
+    ) -> Option<F::Output> {
+        tokio::pin!(call);
+        if let Ok(output) = tokio::time::timeout(after, &mut call).await {
+            return Some(output);
+        }
+        let output = match interrupt {
+            Interrupt::Drop => return None,
+            Interrupt::Cancel => {
+                self.cancel();
+                tokio::time::timeout(CANCELLATION_GRACE, call).await
+            },
+            Interrupt::Stop => {
+                let (output, stopped) =
+                    tokio::join!(tokio::time::timeout(CANCELLATION_GRACE, call), self.stop());
+                if let Err(e) = stopped {
+                    panic!("Provider stop() failed: {}", e);
+                }
+                output
+            },
+        };
+        let Ok(output) = output else {
+            panic!(
+                "Operation didn't return within {:?} of being interrupted by {:?}",
+                CANCELLATION_GRACE, interrupt
+            );
+        };
+        if interrupt == Interrupt::Cancel {
+            *self.cancellation.lock().unwrap() = CancellationSource::new();
+        }
+        Some(output)
     }
 
     // =========================================================================
@@ -121,11 +425,19 @@ impl<P: ProviderService> ProviderTester<P> {
         resource_type: &str,
         config: Value,
     ) -> Result<(), TestError> {
-        let diagnostics = self
-            .provider
-            .validate_resource_config(resource_type, config)
-            .await?;
-        check_diagnostics(diagnostics)
+        self.watch(Target::Resource(resource_type), &[&config]);
+        let result = self
+            .scoped(
+                "validate_resource_config",
+                Some(resource_type),
+                json!({ "config": config }),
+                self.provider
+                    .validate_resource_config(resource_type, config),
+            )
+            .await;
+        let diagnostics = result.as_deref().unwrap_or_default();
+        self.check_leaks("validate_resource_config", &result, diagnostics);
+        check_diagnostics(result?)
     }
 
     /// Plan a resource creation (no prior state).
@@ -134,8 +446,7 @@ impl<P: ProviderService> ProviderTester<P> {
         resource_type: &str,
         proposed_state: Value,
     ) -> Result<PlanResult, ProviderError> {
-        self.provider
-            .plan(resource_type, None, proposed_state.clone(), proposed_state)
+        self.plan(resource_type, None, proposed_state.clone(), proposed_state)
             .await
     }
 
@@ -146,14 +457,13 @@ impl<P: ProviderService> ProviderTester<P> {
         prior_state: Value,
         proposed_state: Value,
     ) -> Result<PlanResult, ProviderError> {
-        self.provider
-            .plan(
-                resource_type,
-                Some(prior_state),
-                proposed_state.clone(),
-                proposed_state,
-            )
-            .await
+        self.plan(
+            resource_type,
+            Some(prior_state),
+            proposed_state.clone(),
+            proposed_state,
+        )
+        .await
     }
 
     /// Plan a resource deletion.
@@ -162,8 +472,7 @@ impl<P: ProviderService> ProviderTester<P> {
         resource_type: &str,
         prior_state: Value,
     ) -> Result<PlanResult, ProviderError> {
-        self.provider
-            .plan(resource_type, Some(prior_state), Value::Null, Value::Null)
+        self.plan(resource_type, Some(prior_state), Value::Null, Value::Null)
             .await
     }
 
@@ -175,9 +484,72 @@ impl<P: ProviderService> ProviderTester<P> {
         proposed_state: Value,
         config: Value,
     ) -> Result<PlanResult, ProviderError> {
-        self.provider
-            .plan(resource_type, prior_state, proposed_state, config)
-            .await
+        let target = Target::Resource(resource_type);
+        self.watch(target, &[&proposed_state, &config]);
+        if let Some(prior_state) = &prior_state {
+            self.watch(target, &[prior_state]);
+        }
+        let result = self
+            .scoped(
+                "plan",
+                Some(resource_type),
+                json!({
+                    "prior_state": prior_state,
+                    "proposed_state": proposed_state,
+                    "config": config,
+                }),
+                self.provider
+                    .plan(resource_type, prior_state, proposed_state, config),
+            )
+            .await;
+        if let Ok(plan) = &result {
+            self.watch(target, &[&plan.planned_state]);
+        }
+        self.check_leaks("plan", &result, &[]);
+        if let (Some(leaks), Ok(plan)) = (&self.leaks, &result) {
+            for change in plan.changes.iter().filter(|c| !c.sensitive) {
+                let text = serde_json::to_string(change).unwrap_or_default();
+                leaks.check("plan", &format!("change to '{}'", change.path), &text);
+            }
+            for reason in &plan.replace_reasons {
+                leaks.check("plan", "replace reason", &reason.reason);
+            }
+        }
+        result
+    }
+
+    /// Estimate the impact of a planned change.
+    pub async fn estimate(
+        &self,
+        resource_type: &str,
+        planned_state: &Value,
+    ) -> Option<ImpactEstimate> {
+        self.scoped(
+            "estimate",
+            Some(resource_type),
+            json!({ "planned_state": planned_state }),
+            self.provider.estimate(resource_type, planned_state),
+        )
+        .await
+    }
+
+    /// Run the provider's pre-apply check on a planned state.
+    pub async fn pre_apply_check(
+        &self,
+        resource_type: &str,
+        planned_state: &Value,
+    ) -> Vec<Diagnostic> {
+        self.watch(Target::Resource(resource_type), &[planned_state]);
+        let diagnostics = self
+            .scoped(
+                "pre_apply_check",
+                Some(resource_type),
+                json!({ "planned_state": planned_state }),
+                self.provider.pre_apply_check(resource_type, planned_state),
+            )
+            .await;
+        self.check_leaks::<()>("pre_apply_check", &Ok(()), &diagnostics);
+        diagnostics
     }
 
     /// Create a new resource.
@@ -186,7 +558,31 @@ impl<P: ProviderService> ProviderTester<P> {
         resource_type: &str,
         planned_state: Value,
     ) -> Result<Value, ProviderError> {
-        self.provider.create(resource_type, planned_state).await
+        self.handle_create(CreateRequest::new(resource_type, planned_state))
+            .await
+    }
+
+    /// Create a new resource from a full request, such as one carrying the
+    /// configuration.
+    pub async fn handle_create(&self, request: CreateRequest) -> Result<Value, ProviderError> {
+        let resource_type = request.resource_type.clone();
+        let target = Target::Resource(&resource_type);
+        self.watch(target, &[&request.planned_state, &request.config]);
+        let token = idempotency_token("create", &resource_type, None);
+        let result = self
+            .scoped_with(
+                self.context().with_idempotency_token(token),
+                "create",
+                Some(&resource_type),
+                json!({ "planned_state": request.planned_state, "config": request.config }),
+                self.provider.handle_create(request),
+            )
+            .await;
+        if let Ok(state) = &result {
+            self.watch(target, &[state]);
+        }
+        self.check_leaks("create", &result, &[]);
+        result
     }
 
     /// Read the current state of a resource.
@@ -195,19 +591,77 @@ impl<P: ProviderService> ProviderTester<P> {
         resource_type: &str,
         current_state: Value,
     ) -> Result<Value, ProviderError> {
-        self.provider.read(resource_type, current_state).await
+        self.handle_read(ReadRequest::new(resource_type, current_state))
+            .await
+            .map(|result| result.state)
+    }
+
+    /// Read the current state of a resource, with its status and warnings.
+    pub async fn handle_read(&self, request: ReadRequest) -> Result<ReadResult, ProviderError> {
+        let resource_type = request.resource_type.clone();
+        let target = Target::Resource(&resource_type);
+        self.watch(target, &[&request.current_state]);
+        let result = self
+            .scoped(
+                "read",
+                Some(&resource_type),
+                json!({ "current_state": request.current_state }),
+                self.provider.handle_read(request),
+            )
+            .await;
+        self.check_read("read", target, &result);
+        result
     }
 
     /// Update an existing resource.
+    ///
+    /// Like the server, this computes the JSON Patch and calls `handle_update`.
     pub async fn update(
         &self,
         resource_type: &str,
         prior_state: Value,
         planned_state: Value,
     ) -> Result<Value, ProviderError> {
-        self.provider
-            .update(resource_type, prior_state, planned_state)
-            .await
+        self.handle_update(UpdateRequest::new(
+            resource_type,
+            prior_state,
+            planned_state,
+        ))
+        .await
+    }
+
+    /// Update an existing resource from a full request, such as one carrying
+    /// the configuration.
+    pub async fn handle_update(&self, request: UpdateRequest) -> Result<Value, ProviderError> {
+        let resource_type = request.resource_type.clone();
+        let target = Target::Resource(&resource_type);
+        self.watch(
+            target,
+            &[
+                &request.prior_state,
+                &request.planned_state,
+                &request.config,
+            ],
+        );
+        let token = idempotency_token("update", &resource_type, None);
+        let result = self
+            .scoped_with(
+                self.context().with_idempotency_token(token),
+                "update",
+                Some(&resource_type),
+                json!({
+                    "prior_state": request.prior_state,
+                    "planned_state": request.planned_state,
+                    "config": request.config,
+                }),
+                self.provider.handle_update(request),
+            )
+            .await;
+        if let Ok(state) = &result {
+            self.watch(target, &[state]);
+        }
+        self.check_leaks("update", &result, &[]);
+        result
     }
 
     /// Delete a resource.
@@ -216,7 +670,28 @@ impl<P: ProviderService> ProviderTester<P> {
         resource_type: &str,
         current_state: Value,
     ) -> Result<(), ProviderError> {
-        self.provider.delete(resource_type, current_state).await
+        self.handle_delete(DeleteRequest::new(resource_type, current_state))
+            .await
+    }
+
+    /// Delete a resource from a full request, such as one carrying the
+    /// configuration.
+    pub async fn handle_delete(&self, request: DeleteRequest) -> Result<(), ProviderError> {
+        let resource_type = request.resource_type.clone();
+        self.watch(
+            Target::Resource(&resource_type),
+            &[&request.current_state, &request.config],
+        );
+        let result = self
+            .scoped(
+                "delete",
+                Some(&resource_type),
+                json!({ "current_state": request.current_state, "config": request.config }),
+                self.provider.handle_delete(request),
+            )
+            .await;
+        self.check_leaks("delete", &result, &[]);
+        result
     }
 
     /// Import an existing resource.
@@ -225,7 +700,46 @@ impl<P: ProviderService> ProviderTester<P> {
         resource_type: &str,
         id: &str,
     ) -> Result<Vec<ImportedResource>, ProviderError> {
-        self.provider.import_resource(resource_type, id).await
+        let result = self
+            .scoped(
+                "import",
+                Some(resource_type),
+                json!({ "id": id }),
+                self.provider.import_resource(resource_type, id),
+            )
+            .await;
+        for imported in result.iter().flatten() {
+            self.watch(
+                Target::Resource(&imported.resource_type),
+                &[&imported.state],
+            );
+        }
+        self.check_leaks("import", &result, &[]);
+        result
+    }
+
+    /// Import every existing resource matching a query.
+    pub async fn import_query(
+        &self,
+        resource_type: &str,
+        query: Value,
+    ) -> Result<Vec<ImportedResource>, ProviderError> {
+        let result = self
+            .scoped(
+                "import_query",
+                Some(resource_type),
+                json!({ "query": query }),
+                self.provider.import_query(resource_type, query.clone()),
+            )
+            .await;
+        for imported in result.iter().flatten() {
+            self.watch(
+                Target::Resource(&imported.resource_type),
+                &[&imported.state],
+            );
+        }
+        self.check_leaks("import_query", &result, &[]);
+        result
     }
 
     /// Upgrade resource state from an older schema version.
@@ -235,9 +749,22 @@ impl<P: ProviderService> ProviderTester<P> {
         version: i64,
         state: Value,
     ) -> Result<Value, ProviderError> {
-        self.provider
-            .upgrade_resource_state(resource_type, version, state)
-            .await
+        let target = Target::Resource(resource_type);
+        self.watch(target, &[&state]);
+        let result = self
+            .scoped(
+                "upgrade_resource_state",
+                Some(resource_type),
+                json!({ "version": version, "state": state }),
+                self.provider
+                    .upgrade_resource_state(resource_type, version, state),
+            )
+            .await;
+        if let Ok(state) = &result {
+            self.watch(target, &[state]);
+        }
+        self.check_leaks("upgrade_resource_state", &result, &[]);
+        result
     }
 
     // =========================================================================
@@ -250,11 +777,19 @@ impl<P: ProviderService> ProviderTester<P> {
         data_source_type: &str,
         config: Value,
     ) -> Result<(), TestError> {
-        let diagnostics = self
-            .provider
-            .validate_data_source_config(data_source_type, config)
-            .await?;
-        check_diagnostics(diagnostics)
+        self.watch(Target::DataSource(data_source_type), &[&config]);
+        let result = self
+            .scoped(
+                "validate_data_source_config",
+                Some(data_source_type),
+                json!({ "config": config }),
+                self.provider
+                    .validate_data_source_config(data_source_type, config),
+            )
+            .await;
+        let diagnostics = result.as_deref().unwrap_or_default();
+        self.check_leaks("validate_data_source_config", &result, diagnostics);
+        check_diagnostics(result?)
     }
 
     /// Read data from a data source.
@@ -263,9 +798,43 @@ impl<P: ProviderService> ProviderTester<P> {
         data_source_type: &str,
         config: Value,
     ) -> Result<Value, ProviderError> {
-        self.provider
-            .read_data_source(data_source_type, config)
+        self.handle_read_data_source(ReadDataSourceRequest::new(data_source_type, config))
             .await
+            .map(|result| result.state)
+    }
+
+    /// Read data from a data source, with its status and warnings.
+    pub async fn handle_read_data_source(
+        &self,
+        request: ReadDataSourceRequest,
+    ) -> Result<ReadResult, ProviderError> {
+        let data_source_type = request.data_source_type.clone();
+        let target = Target::DataSource(&data_source_type);
+        self.watch(target, &[&request.config]);
+        let result = self
+            .scoped(
+                "read_data_source",
+                Some(&data_source_type),
+                json!({ "config": request.config }),
+                self.provider.handle_read_data_source(request),
+            )
+            .await;
+        self.check_read("read_data_source", target, &result);
+        result
+    }
+
+    /// Record the state read and check the read for leaks.
+    fn check_read(
+        &self,
+        operation: &str,
+        target: Target<'_>,
+        result: &Result<ReadResult, ProviderError>,
+    ) {
+        if let Ok(read) = result {
+            self.watch(target, &[&read.state]);
+        }
+        let diagnostics = result.as_ref().map_or(&[][..], |r| &r.diagnostics);
+        self.check_leaks(operation, result, diagnostics);
     }
 
     // =========================================================================
@@ -281,11 +850,13 @@ impl<P: ProviderService> ProviderTester<P> {
         config: Value,
     ) -> Result<Value, ProviderError> {
         // Plan
-        let plan_result = self.plan_create(resource_type, config).await?;
+        let plan_result = self.plan_create(resource_type, config.clone()).await?;
 
         // Create
         let created_state = self
-            .create(resource_type, plan_result.planned_state)
+            .handle_create(
+                CreateRequest::new(resource_type, plan_result.planned_state).with_config(config),
+            )
             .await?;
 
         // Read to verify
@@ -303,12 +874,15 @@ impl<P: ProviderService> ProviderTester<P> {
     ) -> Result<Value, ProviderError> {
         // Plan
         let plan_result = self
-            .plan_update(resource_type, prior_state.clone(), proposed_state)
+            .plan_update(resource_type, prior_state.clone(), proposed_state.clone())
             .await?;
 
         // Update
         let updated_state = self
-            .update(resource_type, prior_state, plan_result.planned_state)
+            .handle_update(
+                UpdateRequest::new(resource_type, prior_state, plan_result.planned_state)
+                    .with_config(proposed_state),
+            )
             .await?;
 
         // Read to verify
@@ -355,6 +929,205 @@ impl<P: ProviderService> ProviderTester<P> {
     }
 }
 
+/// How long an interrupted operation may take to return after being
+/// cancelled or stopped.
+pub const CANCELLATION_GRACE: Duration = Duration::from_secs(5);
+
+/// How [`ProviderTester::interrupt`] interrupts an operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    /// Drop the operation's future, as when core hangs up on a request.
+    Drop,
+    /// Fire the operation context's cancellation token.
+    Cancel,
+    /// Stop the provider, as the `Stop` RPC does.
+    Stop,
+}
+
+/// A provider call made through a [`ProviderTester`].
+#[derive(Debug, Clone)]
+pub struct Call {
+    /// The operation, e.g. `"plan"`, `"read"` or `"read_data_source"`.
+    pub operation: &'static str,
+    /// The resource or data source type, if the operation has one.
+    pub resource_type: Option<String>,
+    /// How long the provider took.
+    pub duration: Duration,
+    /// The arguments passed to the provider, as a JSON object keyed by name.
+    pub input: Value,
+    /// The result returned, or the error message if the call failed.
+    pub output: Result<Value, String>,
+}
+
+/// The calls recorded by a [`ProviderTester`], in the order they were made.
+#[derive(Debug, Clone, Default)]
+pub struct CallLog {
+    calls: Vec<Call>,
+}
+
+impl CallLog {
+    /// All recorded calls.
+    pub fn calls(&self) -> &[Call] {
+        &self.calls
+    }
+
+    /// The calls of `operation`.
+    pub fn of<'a>(&'a self, operation: &'a str) -> impl Iterator<Item = &'a Call> + 'a {
+        self.calls.iter().filter(move |c| c.operation == operation)
+    }
+
+    /// The calls of `operation` on `resource_type`.
+    pub fn of_type<'a>(
+        &'a self,
+        operation: &'a str,
+        resource_type: &'a str,
+    ) -> impl Iterator<Item = &'a Call> + 'a {
+        self.of(operation)
+            .filter(move |c| c.resource_type.as_deref() == Some(resource_type))
+    }
+
+    /// How many times `operation` was called.
+    pub fn count(&self, operation: &str) -> usize {
+        self.of(operation).count()
+    }
+
+    /// How many times `operation` was called on `resource_type`.
+    pub fn count_for(&self, operation: &str, resource_type: &str) -> usize {
+        self.of_type(operation, resource_type).count()
+    }
+
+    /// The most recent call of `operation`.
+    pub fn last(&self, operation: &str) -> Option<&Call> {
+        self.calls.iter().rev().find(|c| c.operation == operation)
+    }
+
+    /// The operations called, in order.
+    pub fn operations(&self) -> Vec<&'static str> {
+        self.calls.iter().map(|c| c.operation).collect()
+    }
+
+    /// The total time the provider spent in `operation`.
+    pub fn total_duration(&self, operation: &str) -> Duration {
+        self.of(operation).map(|c| c.duration).sum()
+    }
+
+    /// Whether no calls were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// The number of recorded calls.
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Assert that `operation` was called exactly `times` times.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the recorded operations if the count differs.
+    pub fn assert_called(&self, operation: &str, times: usize) {
+        let count = self.count(operation);
+        assert_eq!(
+            count,
+            times,
+            "Expected '{}' to be called {} time(s), but it was called {} time(s); calls: {:?}",
+            operation,
+            times,
+            count,
+            self.operations()
+        );
+    }
+}
+
+/// The output of a provider call as recorded in the [`CallLog`].
+trait Snapshot {
+    fn snapshot(&self) -> Result<Value, String>;
+}
+
+impl<T: Serialize> Snapshot for Result<T, ProviderError> {
+    fn snapshot(&self) -> Result<Value, String> {
+        match self {
+            Ok(output) => Ok(json!(output)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+impl Snapshot for Result<ConfigureResult, ProviderError> {
+    fn snapshot(&self) -> Result<Value, String> {
+        self.as_ref()
+            .map(|result| json!({ "diagnostics": result.diagnostics }))
+            .map_err(ToString::to_string)
+    }
+}
+
+impl Snapshot for Option<ImpactEstimate> {
+    fn snapshot(&self) -> Result<Value, String> {
+        Ok(json!(self))
+    }
+}
+
+impl Snapshot for Vec<Diagnostic> {
+    fn snapshot(&self) -> Result<Value, String> {
+        Ok(json!(self))
+    }
+}
+
+/// Records the upstream objects a fake backend holds, to check that
+/// interrupted operations don't leak them.
+///
+/// Cloning shares the same records.
+#[derive(Debug, Clone, Default)]
+pub struct UpstreamTracker {
+    live: Arc<Mutex<BTreeSet<String>>>,
+}
+
+impl UpstreamTracker {
+    /// Create a tracker with no live objects.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `id` was created upstream.
+    pub fn created(&self, id: impl Into<String>) {
+        self.live.lock().unwrap().insert(id.into());
+    }
+
+    /// Record that `id` was deleted upstream.
+    pub fn deleted(&self, id: &str) {
+        self.live.lock().unwrap().remove(id);
+    }
+
+    /// The objects that exist upstream.
+    pub fn live(&self) -> Vec<String> {
+        self.live.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Assert that nothing is left upstream.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the leaked object ids if any is still live.
+    pub fn assert_cleaned_up(&self) {
+        let live = self.live();
+        assert!(live.is_empty(), "Upstream objects leaked: {:?}", live);
+    }
+}
+
+/// Assert that no resource lock is held or waited on.
+///
+/// # Panics
+///
+/// Panics if `locks` has active entries.
+pub fn assert_locks_released(locks: &ResourceLocks) {
+    assert_eq!(
+        locks.active(),
+        0,
+        "Expected all resource locks to be released"
+    );
+}
+
 /// Error type for test operations that may fail with diagnostics.
 #[derive(Debug)]
 pub enum TestError {
@@ -408,6 +1181,110 @@ fn check_diagnostics(diagnostics: Vec<Diagnostic>) -> Result<(), TestError> {
     }
 }
 
+/// The schema a value checked for leaks belongs to.
+#[derive(Clone, Copy)]
+enum Target<'a> {
+    Provider,
+    Resource(&'a str),
+    DataSource(&'a str),
+}
+
+/// Sensitive values seen by a [`ProviderTester`], and the logs captured
+/// while running operations.
+struct LeakCheck {
+    secrets: Mutex<BTreeSet<String>>,
+    logs: Arc<Mutex<Vec<u8>>>,
+    dispatch: tracing::Dispatch,
+}
+
+impl LeakCheck {
+    fn new() -> Self {
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let writer = Arc::clone(&logs);
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .without_time()
+            .with_writer(move || LogWriter(Arc::clone(&writer)))
+            .finish();
+        Self {
+            secrets: Mutex::new(BTreeSet::new()),
+            logs,
+            dispatch: tracing::Dispatch::new(subscriber),
+        }
+    }
+
+    /// Record the sensitive values in `value`, a value of `block`.
+    fn record(&self, block: &Block, value: &Value) {
+        let found = Mutex::new(BTreeSet::new());
+        let _ = transform_sensitive(block, &mut value.clone(), false, &|v| {
+            collect_strings(v, &mut found.lock().unwrap());
+            Ok(v.clone())
+        });
+        self.secrets
+            .lock()
+            .unwrap()
+            .extend(found.into_inner().unwrap());
+    }
+
+    /// Panic if `text` contains a recorded sensitive value.
+    fn check(&self, operation: &str, place: &str, text: &str) {
+        let redacted = self
+            .secrets
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|secret| text.contains(secret.as_str()))
+            .fold(None, |acc: Option<String>, secret| {
+                Some(
+                    acc.as_deref()
+                        .unwrap_or(text)
+                        .replace(secret.as_str(), "***"),
+                )
+            });
+        if let Some(redacted) = redacted {
+            panic!(
+                "Sensitive value leaked in {} during {}: {}",
+                place, operation, redacted
+            );
+        }
+    }
+
+    /// Check the logs captured since the last check, then discard them.
+    fn check_logs(&self, operation: &str) {
+        let logs = std::mem::take(&mut *self.logs.lock().unwrap());
+        for line in String::from_utf8_lossy(&logs).lines() {
+            self.check(operation, "log line", line);
+        }
+    }
+}
+
+/// Collect the strings in `value` long enough to be checked for leaks.
+fn collect_strings(value: &Value, out: &mut BTreeSet<String>) {
+    match value {
+        Value::String(s) if s.chars().count() >= 4 => {
+            out.insert(s.clone());
+        },
+        Value::Array(items) => items.iter().for_each(|v| collect_strings(v, out)),
+        Value::Object(obj) => obj.values().for_each(|v| collect_strings(v, out)),
+        _ => {},
+    }
+}
+
+/// Appends log output to a shared buffer.
+struct LogWriter(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 // =========================================================================
 // Assertion Helpers
 // =========================================================================
@@ -561,20 +1438,835 @@ pub fn assert_error_contains(diagnostics: &[Diagnostic], substring: &str) {
     );
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::schema::{Attribute, Schema};
-    use crate::types::AttributeChange;
-    use serde_json::json;
+// =========================================================================
+// State Matchers
+// =========================================================================
 
-    // A simple test provider for testing the tester
-    struct TestProvider;
+/// Assert that a state matches a pattern, ignoring attributes the pattern
+/// doesn't mention.
+///
+/// Comparing a whole state with `assert_eq!` breaks whenever the provider
+/// adds a computed attribute. A pattern names only the attributes the test
+/// cares about, as JSON with [`matchers`] in place of values it can't pin:
+///
+/// ```
+/// use hemmer_provider_sdk::assert_state_matches;
+/// use serde_json::json;
+///
+/// let state = json!({
+///     "id": "i-0abc",
+///     "name": "web",
+///     "tags": ["a", "b"],
+///     "disk": {"size": 20, "iops": 3000},
+///     "created_at": "2024-05-01T12:00:00Z",
+/// });
+///
+/// assert_state_matches!(state, {
+///     "id": starts_with("i-"),
+///     "name": "web",
+///     "tags": contains("a"),
+///     "disk": {"size": 20},
+///     "deleted_at": absent(),
+/// });
+/// ```
+///
+/// Objects in the pattern match objects with at least their keys; lists match
+/// lists of the same length, item by item; other values must be equal (`1`
+/// equals `1.0`). A function call in value position is one of the
+/// [`matchers`]; wrap other calls in parentheses to compare with their
+/// result.
+///
+/// # Panics
+///
+/// Panics listing every attribute that doesn't match, and the state.
+#[macro_export]
+macro_rules! assert_state_matches {
+    ($state:expr, $($pattern:tt)+) => {
+        $crate::testing::assert_state_matches(&$state, &$crate::state_pattern!($($pattern)+))
+    };
+}
 
-    #[async_trait::async_trait]
-    impl ProviderService for TestProvider {
-        fn schema(&self) -> ProviderSchema {
-            ProviderSchema::new()
+/// Build a [`Matcher`](crate::testing::Matcher) from a JSON-like pattern, as
+/// taken by [`assert_state_matches!`].
+#[macro_export]
+macro_rules! state_pattern {
+    (null) => {
+        $crate::testing::Matcher::equals($crate::serde_json::Value::Null)
+    };
+    ({ $($entries:tt)* }) => {
+        $crate::testing::Matcher::object($crate::__state_pattern_object!([] $($entries)*))
+    };
+    ([ $($items:tt)* ]) => {
+        $crate::testing::Matcher::array($crate::__state_pattern_array!([] [] $($items)*))
+    };
+    ($matcher:ident ( $($args:tt)* )) => {
+        $crate::testing::matchers::$matcher($($args)*)
+    };
+    ($value:expr) => {
+        $crate::testing::Matcher::equals($value)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __state_pattern_object {
+    ([$($out:tt)*]) => {
+        ::std::vec![$($out)*]
+    };
+    ([$($out:tt)*] $key:literal : $($rest:tt)*) => {
+        $crate::__state_pattern_object!(@value [$($out)*] $key [] $($rest)*)
+    };
+    ([$($out:tt)*] $key:ident : $($rest:tt)*) => {
+        $crate::__state_pattern_object!(@value [$($out)*] (::std::stringify!($key)) [] $($rest)*)
+    };
+    (@value [$($out:tt)*] $key:tt [$($value:tt)+] $(, $($rest:tt)*)?) => {
+        $crate::__state_pattern_object!(
+            [$($out)* ($key.to_string(), $crate::state_pattern!($($value)+)),]
+            $($($rest)*)?
+        )
+    };
+    (@value [$($out:tt)*] $key:tt [$($value:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__state_pattern_object!(@value [$($out)*] $key [$($value)* $next] $($rest)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __state_pattern_array {
+    ([$($out:tt)*] []) => {
+        ::std::vec![$($out)*]
+    };
+    ([$($out:tt)*] [$($item:tt)+] $(, $($rest:tt)*)?) => {
+        $crate::__state_pattern_array!([$($out)* $crate::state_pattern!($($item)+),] [] $($($rest)*)?)
+    };
+    ([$($out:tt)*] [$($item:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__state_pattern_array!([$($out)*] [$($item)* $next] $($rest)*)
+    };
+}
+
+/// A pattern for a state or a value within it; see
+/// [`assert_state_matches!`](crate::assert_state_matches).
+#[derive(Clone)]
+pub struct Matcher(MatcherKind);
+
+type Test = Arc<dyn Fn(Option<&Value>) -> bool + Send + Sync>;
+
+#[derive(Clone)]
+enum MatcherKind {
+    Equals(Value),
+    Object(Vec<(String, Matcher)>),
+    Array(Vec<Matcher>),
+    Test(String, Test),
+}
+
+impl Matcher {
+    /// Match values equal to `value`, comparing numbers by value.
+    pub fn equals(value: impl Serialize) -> Self {
+        Self(MatcherKind::Equals(
+            serde_json::to_value(value).expect("pattern value is not JSON"),
+        ))
+    }
+
+    /// Match objects with at least these keys, each matching its pattern.
+    pub fn object(entries: Vec<(String, Matcher)>) -> Self {
+        Self(MatcherKind::Object(entries))
+    }
+
+    /// Match lists of the same length, item by item.
+    pub fn array(items: Vec<Matcher>) -> Self {
+        Self(MatcherKind::Array(items))
+    }
+
+    /// Match with a custom test, given the value or `None` if the attribute is
+    /// missing. `description` completes "expected ..." in failure messages.
+    pub fn new(
+        description: impl Into<String>,
+        test: impl Fn(Option<&Value>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self(MatcherKind::Test(description.into(), Arc::new(test)))
+    }
+
+    /// The ways `value` doesn't match, one message per attribute.
+    pub fn mismatches(&self, value: &Value) -> Vec<String> {
+        let mut out = Vec::new();
+        self.check("", Some(value), &mut out);
+        out
+    }
+
+    fn check(&self, path: &str, value: Option<&Value>, out: &mut Vec<String>) {
+        let mismatch = |out: &mut Vec<String>| {
+            out.push(format!(
+                "{}: expected {}, got {}",
+                if path.is_empty() { "state" } else { path },
+                self.description(),
+                value.map_or("nothing".to_string(), Value::to_string)
+            ))
+        };
+        match (&self.0, value) {
+            (MatcherKind::Test(_, test), value) => {
+                if !test(value) {
+                    mismatch(out);
+                }
+            },
+            (MatcherKind::Equals(expected), Some(value))
+                if crate::state::canonical_eq(expected, value) => {},
+            (MatcherKind::Object(entries), Some(Value::Object(map))) => {
+                for (key, matcher) in entries {
+                    matcher.check(&join_path(path, key), map.get(key), out);
+                }
+            },
+            (MatcherKind::Array(items), Some(Value::Array(values)))
+                if items.len() == values.len() =>
+            {
+                for (i, (matcher, value)) in items.iter().zip(values).enumerate() {
+                    matcher.check(&join_path(path, &i.to_string()), Some(value), out);
+                }
+            },
+            _ => mismatch(out),
+        }
+    }
+
+    fn description(&self) -> String {
+        match &self.0 {
+            MatcherKind::Equals(value) => value.to_string(),
+            MatcherKind::Object(_) => "an object".to_string(),
+            MatcherKind::Array(items) => format!("a list of {}", items.len()),
+            MatcherKind::Test(description, _) => description.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Matcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            MatcherKind::Object(entries) => f
+                .debug_map()
+                .entries(entries.iter().map(|(k, v)| (k, v)))
+                .finish(),
+            MatcherKind::Array(items) => f.debug_list().entries(items).finish(),
+            _ => f.write_str(&self.description()),
+        }
+    }
+}
+
+fn join_path(path: &str, segment: &str) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{}.{}", path, segment)
+    }
+}
+
+/// Assert that `state` matches `pattern`; see
+/// [`assert_state_matches!`](crate::assert_state_matches).
+///
+/// # Panics
+///
+/// Panics listing every attribute that doesn't match, and the state.
+pub fn assert_state_matches(state: &Value, pattern: &Matcher) {
+    let mismatches = pattern.mismatches(state);
+    assert!(
+        mismatches.is_empty(),
+        "State does not match:\n  {}\nState: {}",
+        mismatches.join("\n  "),
+        serde_json::to_string_pretty(state).unwrap_or_default()
+    );
+}
+
+/// Matchers for values a test can't or needn't pin exactly, for use in
+/// [`assert_state_matches!`](crate::assert_state_matches) patterns.
+pub mod matchers {
+    use serde::Serialize;
+    use serde_json::Value;
+
+    use super::Matcher;
+    use crate::state::canonical_eq;
+
+    fn to_value(value: impl Serialize) -> Value {
+        serde_json::to_value(value).expect("pattern value is not JSON")
+    }
+
+    /// Any value other than null.
+    pub fn present() -> Matcher {
+        Matcher::new("a value", |v| v.is_some_and(|v| !v.is_null()))
+    }
+
+    /// A missing attribute or null.
+    pub fn absent() -> Matcher {
+        Matcher::new("nothing", |v| v.is_none_or(Value::is_null))
+    }
+
+    /// A list with an item equal to `item`, or a string containing it.
+    pub fn contains(item: impl Serialize) -> Matcher {
+        let item = to_value(item);
+        Matcher::new(format!("a value containing {}", item), move |v| {
+            match (v, &item) {
+                (Some(Value::Array(items)), item) => items.iter().any(|v| canonical_eq(v, item)),
+                (Some(Value::String(s)), Value::String(part)) => s.contains(part.as_str()),
+                _ => false,
+            }
+        })
+    }
+
+    /// A string starting with `prefix`.
+    pub fn starts_with(prefix: impl Into<String>) -> Matcher {
+        let prefix = prefix.into();
+        Matcher::new(format!("a string starting with {:?}", prefix), move |v| {
+            v.and_then(Value::as_str)
+                .is_some_and(|s| s.starts_with(&prefix))
+        })
+    }
+
+    /// A list, object or string of `len` items, entries or characters.
+    pub fn len(len: usize) -> Matcher {
+        Matcher::new(format!("a value of length {}", len), move |v| match v {
+            Some(Value::Array(items)) => items.len() == len,
+            Some(Value::Object(map)) => map.len() == len,
+            Some(Value::String(s)) => s.chars().count() == len,
+            _ => false,
+        })
+    }
+
+    /// A list with the same items as `items` in any order, as sets are.
+    pub fn unordered(items: impl Serialize) -> Matcher {
+        let expected = to_value(items);
+        Matcher::new(format!("{} in any order", expected), move |v| {
+            let (Some(Value::Array(actual)), Value::Array(expected)) = (v, &expected) else {
+                return false;
+            };
+            let mut remaining: Vec<&Value> = actual.iter().collect();
+            actual.len() == expected.len()
+                && expected.iter().all(|item| {
+                    match remaining.iter().position(|v| canonical_eq(v, item)) {
+                        Some(i) => {
+                            remaining.swap_remove(i);
+                            true
+                        },
+                        None => false,
+                    }
+                })
+        })
+    }
+
+    /// A value equal to one of `values`.
+    pub fn one_of(values: impl Serialize) -> Matcher {
+        let values = to_value(values);
+        Matcher::new(format!("one of {}", values), move |v| {
+            let (Some(v), Value::Array(values)) = (v, &values) else {
+                return false;
+            };
+            values.iter().any(|value| canonical_eq(v, value))
+        })
+    }
+
+    /// A value passing `test`, described as `description` in failures.
+    pub fn satisfies(
+        description: impl Into<String>,
+        test: impl Fn(&Value) -> bool + Send + Sync + 'static,
+    ) -> Matcher {
+        Matcher::new(description, move |v| v.is_some_and(&test))
+    }
+}
+
+// =========================================================================
+// State Upgrade Fixtures
+// =========================================================================
+
+/// Assert that stored states of every older schema version upgrade cleanly.
+///
+/// Each `v<version>[-<label>].json` file in `fixtures_dir` holds a state as
+/// stored at that schema version, such as `v0.json` or `v1-without-tags.json`.
+/// Every fixture is passed through
+/// [`upgrade_resource_state`](ProviderService::upgrade_resource_state) and the
+/// result must pass [`validate_state`] against the current resource schema
+/// and not contain attributes the schema doesn't define. If a
+/// `<fixture>.expected.json` file sits next to a fixture (e.g.
+/// `v0.expected.json`), the upgraded state must also equal it.
+///
+/// ```ignore
+/// #[tokio::test]
+/// async fn test_state_upgrades() {
+///     assert_upgrades(&MyProvider::new(), "mycloud_instance", "tests/fixtures/instance").await;
+/// }
+/// ```
+///
+/// # Panics
+///
+/// Panics listing every failing fixture and its version, or if the directory
+/// holds no fixtures.
+pub async fn assert_upgrades<P: ProviderService + ?Sized>(
+    provider: &P,
+    resource_type: &str,
+    fixtures_dir: impl AsRef<Path>,
+) {
+    let dir = fixtures_dir.as_ref();
+    let schema = provider.schema();
+    let schema = schema.resources.get(resource_type).unwrap_or_else(|| {
+        panic!(
+            "Provider has no schema for resource type '{}'",
+            resource_type
+        )
+    });
+
+    let mut fixtures: Vec<_> = std::fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Cannot read fixtures directory {}: {}", dir.display(), e))
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "json")
+                && !path.to_string_lossy().ends_with(".expected.json")
+        })
+        .collect();
+    fixtures.sort();
+    assert!(
+        !fixtures.is_empty(),
+        "No state fixtures found in {}",
+        dir.display()
+    );
+
+    let mut failures = Vec::new();
+    for path in &fixtures {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if let Err(e) = check_upgrade(provider, resource_type, schema, path).await {
+            failures.push(format!("{}: {}", name, e));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} state fixture(s) failed to upgrade:\n  {}",
+        failures.len(),
+        fixtures.len(),
+        failures.join("\n  ")
+    );
+}
+
+async fn check_upgrade<P: ProviderService + ?Sized>(
+    provider: &P,
+    resource_type: &str,
+    schema: &crate::schema::Schema,
+    path: &Path,
+) -> Result<(), String> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let version: i64 = stem
+        .strip_prefix('v')
+        .and_then(|rest| rest.split('-').next())
+        .and_then(|v| v.parse().ok())
+        .ok_or("name must look like v<version>[-<label>].json")?;
+    let read = |path: &Path| -> Result<Value, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        serde_json::from_slice(&bytes).map_err(|e| format!("invalid JSON: {}", e))
+    };
+    let state = read(path)?;
+
+    let upgraded = provider
+        .upgrade_resource_state(resource_type, version, state)
+        .await
+        .map_err(|e| format!("upgrade from version {} failed: {}", version, e))?;
+
+    let mut problems: Vec<String> = validate_state(schema, &upgraded)
+        .into_iter()
+        .filter(|d| d.severity == DiagnosticSeverity::Error)
+        .map(|d| d.summary)
+        .collect();
+    undefined_attributes(&schema.block, &upgraded, "", &mut problems);
+
+    let expected_path = path.with_extension("expected.json");
+    if expected_path.exists() {
+        let expected = read(&expected_path)?;
+        if upgraded != expected {
+            problems.push(format!(
+                "upgraded state {} does not match {}",
+                upgraded,
+                expected_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "upgraded from version {}: {}",
+            version,
+            problems.join("; ")
+        ))
+    }
+}
+
+/// Report attributes in `value` that `block` doesn't define, e.g. ones an
+/// upgrade renamed but didn't remove.
+fn undefined_attributes(block: &Block, value: &Value, path: &str, problems: &mut Vec<String>) {
+    let Value::Object(obj) = value else {
+        return;
+    };
+    for (name, child) in obj {
+        let child_path = if path.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", path, name)
+        };
+        if block.attributes.contains_key(name) {
+            continue;
+        }
+        let Some(nested) = block.blocks.get(name) else {
+            problems.push(format!("Attribute '{}' is not in the schema", child_path));
+            continue;
+        };
+        match (nested.nesting_mode, child) {
+            (BlockNestingMode::Single, _) => {
+                undefined_attributes(&nested.block, child, &child_path, problems);
+            },
+            (BlockNestingMode::List | BlockNestingMode::Set, Value::Array(items)) => {
+                for (i, item) in items.iter().enumerate() {
+                    let item_path = format!("{}.{}", child_path, i);
+                    undefined_attributes(&nested.block, item, &item_path, problems);
+                }
+            },
+            (BlockNestingMode::Map, Value::Object(items)) => {
+                for (key, item) in items {
+                    let item_path = format!("{}.{}", child_path, key);
+                    undefined_attributes(&nested.block, item, &item_path, problems);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+// =========================================================================
+// Soak Testing
+// =========================================================================
+
+/// Reports the process's currently allocated bytes, e.g. from a counting
+/// global allocator or jemalloc's `stats.allocated`.
+pub type MemoryProbe = Arc<dyn Fn() -> usize + Send + Sync>;
+
+/// What [`soak`] runs against a provider.
+#[derive(Clone)]
+pub struct SoakScenario {
+    resource_type: String,
+    configs: Vec<Value>,
+    provider_config: Option<Value>,
+    max_live: usize,
+    seed: u64,
+    memory_probe: Option<MemoryProbe>,
+}
+
+impl SoakScenario {
+    /// Run CRUD cycles on `resource_type`, with configs from
+    /// [`example_value`](crate::schema::example_value) unless some are added
+    /// with [`with_config`](Self::with_config).
+    pub fn new(resource_type: impl Into<String>) -> Self {
+        Self {
+            resource_type: resource_type.into(),
+            configs: Vec::new(),
+            provider_config: None,
+            max_live: 8,
+            seed: 0,
+            memory_probe: None,
+        }
+    }
+
+    /// Add a config that creates and updates pick from at random.
+    pub fn with_config(mut self, config: Value) -> Self {
+        self.configs.push(config);
+        self
+    }
+
+    /// Configure the provider with `config` before the first cycle.
+    pub fn with_provider_config(mut self, config: Value) -> Self {
+        self.provider_config = Some(config);
+        self
+    }
+
+    /// Keep at most `max_live` resources at a time (8 by default).
+    pub fn with_max_live(mut self, max_live: usize) -> Self {
+        self.max_live = max_live.max(1);
+        self
+    }
+
+    /// Seed the choice of operations, for reproducible runs (0 by default).
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sample memory use with `probe` during the run.
+    pub fn with_memory_probe(mut self, probe: impl Fn() -> usize + Send + Sync + 'static) -> Self {
+        self.memory_probe = Some(Arc::new(probe));
+        self
+    }
+}
+
+impl std::fmt::Debug for SoakScenario {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SoakScenario")
+            .field("resource_type", &self.resource_type)
+            .field("configs", &self.configs.len())
+            .field("max_live", &self.max_live)
+            .field("seed", &self.seed)
+            .field("memory_probe", &self.memory_probe.is_some())
+            .finish()
+    }
+}
+
+/// Calls, errors and latencies of one operation during a soak run.
+#[derive(Debug, Clone, Default)]
+pub struct OperationStats {
+    /// Number of calls.
+    pub calls: u64,
+    /// Number of calls that failed.
+    pub errors: u64,
+    /// The latency of every call, in call order.
+    pub latencies: Vec<Duration>,
+}
+
+impl OperationStats {
+    /// The fraction of calls that failed.
+    pub fn error_rate(&self) -> f64 {
+        if self.calls == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.calls as f64
+    }
+
+    /// The latency below which `percentile` percent of calls finished.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        let mut sorted = self.latencies.clone();
+        sorted.sort();
+        let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted
+            .get(rank.clamp(1, sorted.len().max(1)) - 1)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn record(&mut self, latency: Duration, failed: bool) {
+        self.calls += 1;
+        self.errors += u64::from(failed);
+        self.latencies.push(latency);
+    }
+}
+
+/// The outcome of a [`soak`] run.
+#[derive(Debug, Clone, Default)]
+pub struct SoakReport {
+    /// Number of CRUD cycles run.
+    pub cycles: u64,
+    /// How long the run took.
+    pub elapsed: Duration,
+    /// Stats by operation (`plan`, `create`, `read`, `update`, `delete`).
+    pub operations: BTreeMap<String, OperationStats>,
+    /// How often each error message occurred.
+    pub errors: BTreeMap<String, u64>,
+    /// Memory samples from the scenario's memory probe, first to last.
+    pub memory_samples: Vec<usize>,
+}
+
+impl SoakReport {
+    /// The fraction of all calls that failed.
+    pub fn error_rate(&self) -> f64 {
+        let (calls, errors) = self
+            .operations
+            .values()
+            .fold((0, 0), |(c, e), s| (c + s.calls, e + s.errors));
+        if calls == 0 {
+            return 0.0;
+        }
+        errors as f64 / calls as f64
+    }
+
+    /// Bytes allocated at the end of the run minus at the start, if memory
+    /// was sampled.
+    pub fn memory_growth(&self) -> Option<i64> {
+        let first = *self.memory_samples.first()?;
+        let last = *self.memory_samples.last()?;
+        Some(last as i64 - first as i64)
+    }
+
+    fn record<T>(&mut self, operation: &str, latency: Duration, result: &Result<T, ProviderError>) {
+        self.operations
+            .entry(operation.to_string())
+            .or_default()
+            .record(latency, result.is_err());
+        if let Err(e) = result {
+            *self.errors.entry(e.to_string()).or_default() += 1;
+        }
+    }
+}
+
+impl std::fmt::Display for SoakReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} cycles in {:?}, {:.2}% errors",
+            self.cycles,
+            self.elapsed,
+            self.error_rate() * 100.0
+        )?;
+        for (operation, stats) in &self.operations {
+            writeln!(
+                f,
+                "  {:<8} {:>8} calls {:>6} errors  p50 {:?}  p99 {:?}",
+                operation,
+                stats.calls,
+                stats.errors,
+                stats.percentile(50.0),
+                stats.percentile(99.0)
+            )?;
+        }
+        if let Some(growth) = self.memory_growth() {
+            writeln!(f, "  memory growth: {} bytes", growth)?;
+        }
+        Ok(())
+    }
+}
+
+/// How many cycles run between memory samples.
+const SOAK_SAMPLE_INTERVAL: u64 = 100;
+
+/// Run randomized CRUD cycles against `provider` for `duration`.
+///
+/// Each cycle creates a resource (plan, then create) while fewer than the
+/// scenario's maximum are live, or else reads, updates (plan, then update) or
+/// deletes a random live one. Resources still live at the end are deleted.
+/// Latencies and errors are recorded per operation, and memory is sampled
+/// every 100 cycles if the scenario has a memory probe, to surface leaks
+/// and degradation that only show up in long-lived provider processes.
+///
+/// ```ignore
+/// #[tokio::test]
+/// #[ignore = "soak test"]
+/// async fn soak_instances() {
+///     let report = soak(
+///         MyProvider::new(),
+///         SoakScenario::new("mycloud_instance").with_config(json!({"name": "soak"})),
+///         Duration::from_secs(600),
+///     )
+///     .await
+///     .unwrap();
+///     println!("{}", report);
+///     assert!(report.error_rate() < 0.01);
+///     assert!(report.memory_growth().unwrap_or(0) < 10 << 20);
+/// }
+/// ```
+///
+/// Fails only if the provider can't be configured or has no schema for the
+/// resource type; operation errors are counted in the report.
+pub async fn soak<P: ProviderService>(
+    provider: P,
+    scenario: SoakScenario,
+    duration: Duration,
+) -> Result<SoakReport, TestError> {
+    let tester = ProviderTester::new(provider);
+    if let Some(config) = scenario.provider_config.clone() {
+        tester.configure(config).await?;
+    }
+    let configs = if scenario.configs.is_empty() {
+        let schema = tester.schema();
+        let schema = schema
+            .resources
+            .get(&scenario.resource_type)
+            .ok_or_else(|| ProviderError::UnknownResource(scenario.resource_type.clone()))?;
+        vec![crate::schema::example_value(schema)]
+    } else {
+        scenario.configs.clone()
+    };
+    let resource_type = scenario.resource_type.as_str();
+    let sample = || scenario.memory_probe.as_ref().map(|probe| probe());
+
+    let mut rng = SeedRng::new(scenario.seed);
+    let mut report = SoakReport::default();
+    let mut live: Vec<Value> = Vec::new();
+    report.memory_samples.extend(sample());
+    let started = Instant::now();
+
+    macro_rules! timed {
+        ($operation:expr, $call:expr) => {{
+            let start = Instant::now();
+            let result = $call.await;
+            report.record($operation, start.elapsed(), &result);
+            result
+        }};
+    }
+
+    while started.elapsed() < duration {
+        let config = configs[rng.below(configs.len())].clone();
+        if live.is_empty() || (live.len() < scenario.max_live && rng.one_in(3)) {
+            if let Ok(plan) = timed!("plan", tester.plan_create(resource_type, config.clone())) {
+                let created = timed!(
+                    "create",
+                    tester.handle_create(
+                        CreateRequest::new(resource_type, plan.planned_state).with_config(config)
+                    )
+                );
+                live.extend(created.ok());
+            }
+        } else {
+            let index = rng.below(live.len());
+            match rng.below(4) {
+                0 | 1 => {
+                    let read = timed!("read", tester.read(resource_type, live[index].clone()));
+                    if let Ok(state) = read {
+                        live[index] = state;
+                    }
+                },
+                2 => {
+                    let prior = live[index].clone();
+                    let plan = timed!(
+                        "plan",
+                        tester.plan_update(resource_type, prior.clone(), config.clone())
+                    );
+                    if let Ok(plan) = plan {
+                        let updated = timed!(
+                            "update",
+                            tester.handle_update(
+                                UpdateRequest::new(resource_type, prior, plan.planned_state)
+                                    .with_config(config)
+                            )
+                        );
+                        if let Ok(state) = updated {
+                            live[index] = state;
+                        }
+                    }
+                },
+                _ => {
+                    let state = live.swap_remove(index);
+                    let _ = timed!("delete", tester.delete(resource_type, state));
+                },
+            }
+        }
+        report.cycles += 1;
+        if report.cycles % SOAK_SAMPLE_INTERVAL == 0 {
+            report.memory_samples.extend(sample());
+        }
+    }
+
+    for state in live {
+        let _ = timed!("delete", tester.delete(resource_type, state));
+    }
+    report.elapsed = started.elapsed();
+    report.memory_samples.extend(sample());
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::schema::{Attribute, Schema};
+    use crate::types::{AttributeChange, ConfigureResult};
+    use serde_json::json;
+
+    // A simple test provider for testing the tester
+    struct TestProvider;
+
+    #[async_trait::async_trait]
+    impl ProviderService for TestProvider {
+        fn schema(&self) -> ProviderSchema {
+            ProviderSchema::new()
                 .with_provider_config(
                     Schema::v0().with_attribute("api_key", Attribute::optional_string()),
                 )
@@ -586,8 +2278,18 @@ mod tests {
                 )
         }
 
-        async fn configure(&self, _config: Value) -> Result<Vec<Diagnostic>, ProviderError> {
-            Ok(vec![])
+        async fn configure(&self, config: Value) -> Result<ConfigureResult, ProviderError> {
+            let api_key = config["api_key"].as_str().unwrap_or_default().to_string();
+            Ok(ConfigureResult::new().with_provider_data(api_key))
+        }
+
+        async fn read_data_source(
+            &self,
+            _data_source_type: &str,
+            _config: Value,
+        ) -> Result<Value, ProviderError> {
+            let api_key = Context::current().provider_data::<String>()?;
+            Ok(json!({ "api_key": *api_key }))
         }
 
         async fn plan(
@@ -658,6 +2360,12 @@ mod tests {
             Ok(planned_state)
         }
 
+        async fn handle_update(&self, request: UpdateRequest) -> Result<Value, ProviderError> {
+            let mut state = request.prior_state;
+            request.patch.apply(&mut state)?;
+            Ok(state)
+        }
+
         async fn delete(
             &self,
             _resource_type: &str,
@@ -756,6 +2464,50 @@ mod tests {
         assert_eq!(final_state["name"], "updated");
     }
 
+    #[tokio::test]
+    async fn test_call_log() {
+        let tester = ProviderTester::new(TestProvider);
+        tester
+            .lifecycle_crud(
+                "test_resource",
+                json!({"name": "initial"}),
+                json!({"name": "updated"}),
+            )
+            .await
+            .unwrap();
+        let _ = tester
+            .read_data_source("test_data", json!({}))
+            .await
+            .unwrap_err();
+
+        let calls = tester.call_log();
+        assert_eq!(
+            calls.operations(),
+            vec![
+                "plan",
+                "create",
+                "read",
+                "plan",
+                "update",
+                "read",
+                "plan",
+                "delete",
+                "read_data_source"
+            ]
+        );
+        calls.assert_called("update", 1);
+        assert_eq!(calls.count_for("read", "test_resource"), 2);
+        assert_eq!(calls.count_for("read", "other_resource"), 0);
+
+        let update = calls.last("update").unwrap();
+        assert_eq!(update.input["planned_state"]["name"], "updated");
+        assert_eq!(update.output.as_ref().unwrap()["id"], "generated-id");
+        assert!(calls.last("read_data_source").unwrap().output.is_err());
+
+        tester.clear_call_log();
+        assert!(tester.call_log().is_empty());
+    }
+
     #[test]
     fn test_assert_no_errors() {
         let diagnostics = vec![Diagnostic::warning("Just a warning")];
@@ -782,6 +2534,55 @@ mod tests {
         assert_error_contains(&diagnostics, "configuration");
     }
 
+    #[test]
+    fn test_state_matchers() {
+        let state = json!({
+            "id": 7,
+            "name": "web-1",
+            "ports": [443, 80],
+            "network": [{"subnet": "s-1", "ip": "10.0.0.4"}],
+            "size": 2.0,
+        });
+        let port = 443;
+        assert_state_matches!(state, {
+            id: present(),
+            "name": starts_with("web"),
+            "ports": unordered([80, 443]),
+            "network": [{"subnet": "s-1"}],
+            "size": one_of([1, 2]),
+            "zone": absent(),
+        });
+        assert_state_matches!(
+            state["ports"],
+            [(port), satisfies("a low port", |v| v.as_i64() < Some(1024))]
+        );
+        assert_state_matches!(state["name"], len(5));
+
+        let pattern = crate::state_pattern!({
+            "id": 8,
+            "name": contains("db"),
+            "ports": [443],
+            "network": [{"subnet": null}],
+            "zone": present(),
+        });
+        assert_eq!(
+            pattern.mismatches(&state),
+            [
+                "id: expected 8, got 7",
+                "name: expected a value containing \"db\", got \"web-1\"",
+                "ports: expected a list of 1, got [443,80]",
+                "network.0.subnet: expected null, got \"s-1\"",
+                "zone: expected a value, got nothing",
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "State does not match")]
+    fn test_assert_state_matches_fails() {
+        assert_state_matches!(json!({"tags": ["a"]}), {"tags": contains("b")});
+    }
+
     #[test]
     fn test_test_error_display() {
         let err = TestError::Diagnostics(vec![
@@ -795,4 +2596,339 @@ mod tests {
         assert!(display.contains("field1"));
         assert!(display.contains("More info"));
     }
+
+    #[tokio::test]
+    async fn test_tester_provider_data_from_configure() {
+        let tester = ProviderTester::new(TestProvider);
+
+        let err = tester
+            .read_data_source("test_data", json!({}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::FailedPrecondition(_)));
+
+        tester.configure(json!({"api_key": "k-123"})).await.unwrap();
+        let data = tester
+            .read_data_source("test_data", json!({}))
+            .await
+            .unwrap();
+        assert_eq!(data["api_key"], "k-123");
+    }
+
+    #[tokio::test]
+    async fn test_assert_upgrades() {
+        use futures_util::FutureExt;
+
+        let dir = std::env::temp_dir().join("hemmer_sdk_upgrade_fixtures");
+        std::fs::create_dir_all(&dir).unwrap();
+        let fixture = |name: &str, state: Value| {
+            std::fs::write(dir.join(name), state.to_string()).unwrap();
+        };
+        fixture("v0.json", json!({"name": "a", "id": "i-1"}));
+        fixture("v0.expected.json", json!({"name": "a", "id": "i-1"}));
+        assert_upgrades(&TestProvider, "test_resource", &dir).await;
+
+        fixture("v0-no-id.json", json!({"name": "a"}));
+        fixture(
+            "v1-renamed.json",
+            json!({"name": "a", "id": "i-1", "title": "a"}),
+        );
+        let panic =
+            std::panic::AssertUnwindSafe(assert_upgrades(&TestProvider, "test_resource", &dir))
+                .catch_unwind()
+                .await
+                .unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with("2 of 3 state fixture(s) failed"));
+        assert!(message.contains(
+            "v0-no-id.json: upgraded from version 0: Provider did not set computed attribute 'id'"
+        ));
+        assert!(message.contains(
+            "v1-renamed.json: upgraded from version 1: Attribute 'title' is not in the schema"
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_leak_check() {
+        use futures_util::FutureExt;
+
+        struct Leaky;
+
+        #[async_trait::async_trait]
+        impl ProviderService for Leaky {
+            fn schema(&self) -> ProviderSchema {
+                ProviderSchema::new().with_resource(
+                    "user",
+                    Schema::v0()
+                        .with_attribute("name", Attribute::required_string())
+                        .with_attribute("password", Attribute::optional_string().sensitive()),
+                )
+            }
+
+            async fn configure(&self, _: Value) -> Result<ConfigureResult, ProviderError> {
+                Ok(ConfigureResult::new())
+            }
+
+            async fn create(&self, _: &str, state: Value) -> Result<Value, ProviderError> {
+                if state["name"] == "logged" {
+                    tracing::info!(password = %state["password"], "Creating user");
+                }
+                Ok(state)
+            }
+
+            async fn read(&self, _: &str, state: Value) -> Result<Value, ProviderError> {
+                Ok(state)
+            }
+
+            async fn handle_read(&self, request: ReadRequest) -> Result<ReadResult, ProviderError> {
+                let state = request.current_state;
+                let password = state["password"].as_str().unwrap_or_default().to_string();
+                Ok(
+                    ReadResult::new(state).with_diagnostic(Diagnostic::warning(format!(
+                        "Password {} expires soon",
+                        password
+                    ))),
+                )
+            }
+
+            async fn update(
+                &self,
+                _: &str,
+                _: Value,
+                state: Value,
+            ) -> Result<Value, ProviderError> {
+                Ok(state)
+            }
+
+            async fn delete(&self, _: &str, _: Value) -> Result<(), ProviderError> {
+                Ok(())
+            }
+        }
+
+        async fn leak<T: std::fmt::Debug>(call: impl std::future::Future<Output = T>) -> String {
+            let panic = std::panic::AssertUnwindSafe(call)
+                .catch_unwind()
+                .await
+                .unwrap_err();
+            panic.downcast_ref::<String>().unwrap().clone()
+        }
+
+        let tester = ProviderTester::new(Leaky).with_leak_check();
+        let user = json!({"name": "quiet", "password": "hunter22"});
+        tester.create("user", user.clone()).await.unwrap();
+
+        // The message names where the value leaked without repeating it
+        let message =
+            leak(tester.create("user", json!({"name": "logged", "password": "hunter22"}))).await;
+        assert!(message.starts_with("Sensitive value leaked in log line during create"));
+        assert!(!message.contains("hunter22"));
+
+        let message = leak(tester.read("user", user.clone())).await;
+        assert_eq!(
+            message,
+            "Sensitive value leaked in diagnostic summary during read: Password *** expires soon"
+        );
+
+        // Without the check, the same calls succeed
+        let tester = ProviderTester::new(Leaky);
+        tester.read("user", user).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_interrupt() {
+        struct Slow {
+            upstream: UpstreamTracker,
+            locks: ResourceLocks,
+        }
+
+        #[async_trait::async_trait]
+        impl ProviderService for Slow {
+            fn schema(&self) -> ProviderSchema {
+                ProviderSchema::new()
+            }
+
+            async fn configure(&self, _: Value) -> Result<ConfigureResult, ProviderError> {
+                Ok(ConfigureResult::new())
+            }
+
+            async fn create(&self, _: &str, state: Value) -> Result<Value, ProviderError> {
+                let id = state["id"].as_str().unwrap_or_default().to_string();
+                let _guard = self.locks.lock("vm", &id).await;
+                self.upstream.created(id.clone());
+                if state["wait"] == true {
+                    let context = Context::current();
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::from_secs(60)) => {},
+                        _ = context.cancelled() => {
+                            self.upstream.deleted(&id);
+                            return Err(ProviderError::Unavailable("Cancelled".to_string()));
+                        },
+                    }
+                }
+                Ok(state)
+            }
+
+            async fn read(&self, _: &str, state: Value) -> Result<Value, ProviderError> {
+                Ok(state)
+            }
+
+            async fn update(
+                &self,
+                _: &str,
+                _: Value,
+                state: Value,
+            ) -> Result<Value, ProviderError> {
+                Ok(state)
+            }
+
+            async fn delete(&self, _: &str, _: Value) -> Result<(), ProviderError> {
+                Ok(())
+            }
+        }
+
+        let upstream = UpstreamTracker::new();
+        let tester = ProviderTester::new(Slow {
+            upstream: upstream.clone(),
+            locks: ResourceLocks::new(),
+        });
+        let after = Duration::from_millis(20);
+
+        // A cancelled create cleans up after itself
+        let result = tester
+            .interrupt(
+                Interrupt::Cancel,
+                after,
+                tester.create("vm", json!({"id": "vm-1", "wait": true})),
+            )
+            .await;
+        assert!(matches!(result, Some(Err(ProviderError::Unavailable(_)))));
+        upstream.assert_cleaned_up();
+        assert_locks_released(&tester.provider().locks);
+
+        // Later operations aren't cancelled, and fast ones aren't interrupted
+        let result = tester
+            .interrupt(
+                Interrupt::Cancel,
+                Duration::from_secs(5),
+                tester.create("vm", json!({"id": "vm-2"})),
+            )
+            .await;
+        assert!(result.unwrap().is_ok());
+        upstream.deleted("vm-2");
+
+        // A dropped create releases its lock but leaks the upstream object
+        let result = tester
+            .interrupt(
+                Interrupt::Drop,
+                after,
+                tester.create("vm", json!({"id": "vm-3", "wait": true})),
+            )
+            .await;
+        assert!(result.is_none());
+        assert_locks_released(&tester.provider().locks);
+        assert_eq!(upstream.live(), vec!["vm-3"]);
+        upstream.deleted("vm-3");
+
+        // Stop cancels the running create and everything after it
+        let result = tester
+            .interrupt(
+                Interrupt::Stop,
+                after,
+                tester.create("vm", json!({"id": "vm-4", "wait": true})),
+            )
+            .await;
+        assert!(matches!(result, Some(Err(_))));
+        upstream.assert_cleaned_up();
+        assert!(tester.context().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_soak() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Leaks 10 bytes per created resource, and can't rename "stuck"
+        #[derive(Default)]
+        struct Store {
+            allocated: Arc<AtomicUsize>,
+        }
+
+        #[async_trait::async_trait]
+        impl ProviderService for Store {
+            fn schema(&self) -> ProviderSchema {
+                ProviderSchema::new().with_resource(
+                    "item",
+                    Schema::v0().with_attribute("name", Attribute::required_string()),
+                )
+            }
+
+            async fn configure(&self, _: Value) -> Result<ConfigureResult, ProviderError> {
+                Ok(ConfigureResult::new())
+            }
+
+            async fn create(&self, _: &str, state: Value) -> Result<Value, ProviderError> {
+                self.allocated.fetch_add(100, Ordering::SeqCst);
+                Ok(state)
+            }
+
+            async fn read(&self, _: &str, state: Value) -> Result<Value, ProviderError> {
+                Ok(state)
+            }
+
+            async fn update(
+                &self,
+                _: &str,
+                prior: Value,
+                state: Value,
+            ) -> Result<Value, ProviderError> {
+                if prior["name"] == "stuck" && state["name"] != "stuck" {
+                    return Err(ProviderError::FailedPrecondition(
+                        "Item is stuck".to_string(),
+                    ));
+                }
+                Ok(state)
+            }
+
+            async fn delete(&self, _: &str, _: Value) -> Result<(), ProviderError> {
+                self.allocated.fetch_sub(90, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        let store = Store::default();
+        let allocated = Arc::clone(&store.allocated);
+        let scenario = SoakScenario::new("item")
+            .with_config(json!({"name": "a"}))
+            .with_config(json!({"name": "stuck"}))
+            .with_seed(7)
+            .with_memory_probe(move || allocated.load(Ordering::SeqCst));
+        let report = soak(store, scenario, Duration::from_millis(100))
+            .await
+            .unwrap();
+
+        assert!(report.cycles > 100);
+        let creates = report.operations["create"].calls;
+        assert_eq!(report.operations["delete"].calls, creates);
+        assert_eq!(report.operations["read"].errors, 0);
+        assert!(report.operations["update"].errors > 0);
+        assert_eq!(
+            report.errors["Failed precondition: Item is stuck"],
+            report.operations["update"].errors
+        );
+        assert!(report.error_rate() > 0.0 && report.error_rate() < 1.0);
+        assert!(
+            report.operations["read"].percentile(99.0)
+                >= report.operations["read"].percentile(50.0)
+        );
+        assert!(report.memory_samples.len() > 2);
+        assert_eq!(report.memory_growth(), Some(10 * creates as i64));
+        assert!(report.to_string().contains("memory growth"));
+
+        // An unknown resource type can't be soaked
+        let err = soak(Store::default(), SoakScenario::new("nope"), Duration::ZERO)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown resource type: nope"));
+    }
 }
@@ -0,0 +1,563 @@
+//! SSH tunnels through bastion hosts (feature: `ssh-tunnel`).
+//!
+//! Many on-premises APIs are only reachable through a bastion (jump) host.
+//! [`Bastion::forward`] opens an SSH tunnel with the system OpenSSH client
+//! (`ssh -N -L ...`), so users' SSH agents, keys and `~/.ssh/config` work as
+//! usual, and returns an [`SshTunnel`] listening on a local port. Point the
+//! provider's API client at [`SshTunnel::local_addr`] instead of the API host.
+//!
+//! Open the tunnel in `configure` and keep it in provider data. It is closed
+//! when the server shuts down (the operation [`Context`] is cancelled), when
+//! [`SshTunnel::close`] is called, or when the last handle is dropped.
+//!
+//! For HTTPS APIs, keep the real host name in URLs so certificates still
+//! verify, and send its traffic to the tunnel with
+//! `HttpClientBuilder::with_resolve(host, tunnel.local_addr())` (feature:
+//! `http`).
+//!
+//! [`bastion_fragment`] adds a standard optional `bastion` block to the
+//! provider config schema, which [`Bastion::from_config`] reads.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hemmer_provider_sdk::tunnel::Bastion;
+//! use hemmer_provider_sdk::types::ConfigureResult;
+//! use hemmer_provider_sdk::ProviderError;
+//!
+//! async fn configure(config: serde_json::Value) -> Result<ConfigureResult, ProviderError> {
+//!     let Some(bastion) = Bastion::from_config(&config["bastion"])? else {
+//!         return Ok(ConfigureResult::new());
+//!     };
+//!     let tunnel = bastion.forward("api.internal", 443).await?;
+//!     println!("API reachable at {}", tunnel.local_addr());
+//!     // The tunnel stays open while the provider data holds it
+//!     Ok(ConfigureResult::new().with_provider_data(tunnel))
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, ChildStderr, Command};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::context::Context;
+use crate::error::ProviderError;
+use crate::schema::{Attribute, Block, NestedBlock, SchemaFragment};
+
+/// How many local ports to try when another process takes the chosen one
+/// before `ssh` binds it.
+const PORT_ATTEMPTS: usize = 3;
+
+/// How many lines of `ssh`'s stderr to keep for error messages.
+const STDERR_LINES: usize = 20;
+
+/// A `bastion` block for the provider config schema: `host` (required),
+/// `user`, `port`, `private_key_file`, `known_hosts_file` and `jump_host`.
+pub fn bastion_fragment() -> SchemaFragment {
+    SchemaFragment::new("bastion").with_block(
+        "bastion",
+        NestedBlock::single(
+            Block::new()
+                .with_attribute(
+                    "host",
+                    Attribute::required_string().with_description("Bastion host name or address"),
+                )
+                .with_attribute(
+                    "user",
+                    Attribute::optional_string().with_description("User to log in as"),
+                )
+                .with_attribute(
+                    "port",
+                    Attribute::optional_int64().with_description("SSH port. Default: 22"),
+                )
+                .with_attribute(
+                    "private_key_file",
+                    Attribute::optional_string()
+                        .with_description("Private key file. Default: the SSH agent and ~/.ssh"),
+                )
+                .with_attribute(
+                    "known_hosts_file",
+                    Attribute::optional_string()
+                        .with_description("Known hosts file to verify the bastion's key"),
+                )
+                .with_attribute(
+                    "jump_host",
+                    Attribute::optional_string().with_description(
+                        "Another host to jump through first ([user@]host[:port])",
+                    ),
+                )
+                .with_description("Reach the API through an SSH bastion host"),
+        ),
+    )
+}
+
+/// An SSH bastion host to tunnel through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bastion {
+    host: String,
+    user: Option<String>,
+    port: Option<u16>,
+    identity_file: Option<PathBuf>,
+    known_hosts_file: Option<PathBuf>,
+    jump_host: Option<String>,
+    accept_new_host_keys: bool,
+    connect_timeout: Duration,
+    options: Vec<(String, String)>,
+    program: String,
+}
+
+impl Bastion {
+    /// A bastion at `host`.
+    pub fn new(host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            user: None,
+            port: None,
+            identity_file: None,
+            known_hosts_file: None,
+            jump_host: None,
+            accept_new_host_keys: false,
+            connect_timeout: Duration::from_secs(15),
+            options: Vec::new(),
+            program: "ssh".to_string(),
+        }
+    }
+
+    /// Read a [`bastion_fragment`] block. Returns `None` if it is not set.
+    pub fn from_config(config: &Value) -> Result<Option<Self>, ProviderError> {
+        if config.is_null() {
+            return Ok(None);
+        }
+        let field = |name: &str| config[name].as_str().filter(|s| !s.is_empty());
+        let host = field("host")
+            .ok_or_else(|| ProviderError::Configuration("bastion.host is required".to_string()))?;
+        check_host("bastion.host", host)?;
+        if let Some(jump) = field("jump_host") {
+            check_host("bastion.jump_host", jump)?;
+        }
+        let mut bastion = Self::new(host);
+        bastion.user = field("user").map(String::from);
+        bastion.identity_file = field("private_key_file").map(PathBuf::from);
+        bastion.known_hosts_file = field("known_hosts_file").map(PathBuf::from);
+        bastion.jump_host = field("jump_host").map(String::from);
+        if let Some(port) = config["port"].as_i64() {
+            let port = u16::try_from(port).map_err(|_| {
+                ProviderError::Configuration(format!("bastion.port {} is out of range", port))
+            })?;
+            bastion.port = Some(port);
+        }
+        Ok(Some(bastion))
+    }
+
+    /// Log in as `user`. Default: from `~/.ssh/config` or the local user.
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Connect to `port`. Default: 22.
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Authenticate with this private key file. Default: the SSH agent and
+    /// the keys in `~/.ssh`.
+    pub fn with_identity_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.identity_file = Some(path.into());
+        self
+    }
+
+    /// Verify the bastion's host key against this file. Default:
+    /// `~/.ssh/known_hosts`.
+    pub fn with_known_hosts_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.known_hosts_file = Some(path.into());
+        self
+    }
+
+    /// Jump through `host` (`[user@]host[:port]`) to reach the bastion.
+    pub fn with_jump_host(mut self, host: impl Into<String>) -> Self {
+        self.jump_host = Some(host.into());
+        self
+    }
+
+    /// Trust host keys that are not in the known hosts file yet (but still
+    /// reject changed keys). Default: unknown hosts are rejected.
+    pub fn accept_new_host_keys(mut self) -> Self {
+        self.accept_new_host_keys = true;
+        self
+    }
+
+    /// How long to wait for the tunnel to come up. Default: 15 seconds.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Pass an extra `-o name=value` option to `ssh`.
+    pub fn with_option(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.push((name.into(), value.into()));
+        self
+    }
+
+    /// Use this `ssh` executable. Default: `ssh` from `PATH`.
+    pub fn with_ssh_program(mut self, program: impl Into<String>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    /// Open a tunnel from a local port to `remote_host:remote_port`, as seen
+    /// from the bastion.
+    ///
+    /// Fails with [`ProviderError::Configuration`] if a host name starts
+    /// with `-`, and with [`ProviderError::Unavailable`] if `ssh` exits or
+    /// the tunnel doesn't accept connections within the connect timeout.
+    pub async fn forward(
+        &self,
+        remote_host: &str,
+        remote_port: u16,
+    ) -> Result<SshTunnel, ProviderError> {
+        check_host("bastion host", &self.host)?;
+        if let Some(jump) = &self.jump_host {
+            check_host("jump host", jump)?;
+        }
+        check_host("remote host", remote_host)?;
+
+        let mut attempt = 1;
+        loop {
+            match self.open(remote_host, remote_port).await {
+                Ok(tunnel) => return Ok(tunnel),
+                // The free port was taken before ssh could bind it
+                Err(OpenError::PortTaken(e)) if attempt < PORT_ATTEMPTS => {
+                    debug!(error = %e, attempt, "SSH tunnel port taken, retrying");
+                    attempt += 1;
+                },
+                Err(OpenError::PortTaken(e) | OpenError::Failed(e)) => return Err(e),
+            }
+        }
+    }
+
+    /// Start `ssh` on a free local port and wait for the tunnel to accept
+    /// connections.
+    async fn open(&self, remote_host: &str, remote_port: u16) -> Result<SshTunnel, OpenError> {
+        let local_port = free_local_port().map_err(OpenError::Failed)?;
+        let local_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, local_port));
+        let args = self.args(local_port, remote_host, remote_port);
+        debug!(program = %self.program, ?args, "Starting SSH tunnel");
+
+        let mut child = Command::new(&self.program)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                OpenError::Failed(ProviderError::Configuration(format!(
+                    "Failed to run '{}': {}",
+                    self.program, e
+                )))
+            })?;
+        let stderr = child.stderr.take().map(drain_stderr);
+
+        let deadline = Instant::now() + self.connect_timeout + Duration::from_secs(5);
+        loop {
+            if let Some(status) = child.try_wait().map_err(|e| {
+                OpenError::Failed(ProviderError::Sdk(format!(
+                    "Failed to check SSH tunnel process: {}",
+                    e
+                )))
+            })? {
+                let stderr = match stderr {
+                    Some(drain) => tokio::time::timeout(Duration::from_secs(1), drain)
+                        .await
+                        .ok()
+                        .and_then(Result::ok)
+                        .unwrap_or_default(),
+                    None => String::new(),
+                };
+                let error = ProviderError::Unavailable(format!(
+                    "SSH tunnel through {} exited with {}: {}",
+                    self.host, status, stderr
+                ));
+                return Err(if port_taken(&stderr) {
+                    OpenError::PortTaken(error)
+                } else {
+                    OpenError::Failed(error)
+                });
+            }
+            if tokio::net::TcpStream::connect(local_addr).await.is_ok() {
+                break;
+            }
+            if Instant::now() >= deadline {
+                return Err(OpenError::Failed(ProviderError::Unavailable(format!(
+                    "SSH tunnel through {} was not ready within {:?}",
+                    self.host, self.connect_timeout
+                ))));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        info!(bastion = %self.host, %local_addr, remote = %format!("{}:{}", remote_host, remote_port), "SSH tunnel open");
+
+        let tunnel = SshTunnel {
+            local_addr,
+            child: Arc::new(Mutex::new(Some(child))),
+        };
+        tunnel.close_on_cancel(Context::current());
+        Ok(tunnel)
+    }
+
+    /// The `ssh` arguments for a tunnel from `local_port`.
+    fn args(&self, local_port: u16, remote_host: &str, remote_port: u16) -> Vec<String> {
+        let mut args = vec![
+            "-N".to_string(),
+            "-L".to_string(),
+            format!("127.0.0.1:{}:{}:{}", local_port, remote_host, remote_port),
+        ];
+        let mut option = |name: &str, value: &str| {
+            args.push("-o".to_string());
+            args.push(format!("{}={}", name, value));
+        };
+        option("BatchMode", "yes");
+        option("ExitOnForwardFailure", "yes");
+        option("ServerAliveInterval", "15");
+        option(
+            "ConnectTimeout",
+            &self.connect_timeout.as_secs().max(1).to_string(),
+        );
+        if self.accept_new_host_keys {
+            option("StrictHostKeyChecking", "accept-new");
+        }
+        if let Some(path) = &self.known_hosts_file {
+            option("UserKnownHostsFile", &path.display().to_string());
+        }
+        for (name, value) in &self.options {
+            option(name, value);
+        }
+        if let Some(port) = self.port {
+            args.extend(["-p".to_string(), port.to_string()]);
+        }
+        if let Some(path) = &self.identity_file {
+            args.extend(["-i".to_string(), path.display().to_string()]);
+        }
+        if let Some(jump) = &self.jump_host {
+            args.extend(["-J".to_string(), jump.clone()]);
+        }
+        // Nothing after this is read as an option
+        args.push("--".to_string());
+        args.push(match &self.user {
+            Some(user) => format!("{}@{}", user, self.host),
+            None => self.host.clone(),
+        });
+        args
+    }
+}
+
+/// An open SSH tunnel. Clones share the tunnel, which closes when the last
+/// one is dropped.
+#[derive(Clone)]
+pub struct SshTunnel {
+    local_addr: SocketAddr,
+    child: Arc<Mutex<Option<Child>>>,
+}
+
+impl SshTunnel {
+    /// The local address that forwards to the remote host.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Whether the `ssh` process is still running.
+    pub async fn is_open(&self) -> bool {
+        match self.child.lock().await.as_mut() {
+            Some(child) => matches!(child.try_wait(), Ok(None)),
+            None => false,
+        }
+    }
+
+    /// Close the tunnel.
+    pub async fn close(&self) {
+        if let Some(mut child) = self.child.lock().await.take() {
+            if let Err(e) = child.kill().await {
+                warn!(error = %e, "Failed to stop SSH tunnel");
+            }
+            debug!(local_addr = %self.local_addr, "SSH tunnel closed");
+        }
+    }
+
+    /// Close the tunnel when `context` is cancelled.
+    fn close_on_cancel(&self, context: Context) {
+        let child = Arc::downgrade(&self.child);
+        let local_addr = self.local_addr;
+        tokio::spawn(async move {
+            context.cancelled().await;
+            if let Some(child) = child.upgrade() {
+                if let Some(mut child) = child.lock().await.take() {
+                    let _ = child.kill().await;
+                    debug!(%local_addr, "SSH tunnel closed on shutdown");
+                }
+            }
+        });
+    }
+}
+
+impl fmt::Debug for SshTunnel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SshTunnel")
+            .field("local_addr", &self.local_addr)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Why an attempt to open a tunnel failed.
+enum OpenError {
+    /// `ssh` couldn't listen on the local port.
+    PortTaken(ProviderError),
+    Failed(ProviderError),
+}
+
+/// Reject host names `ssh` would read as options, such as
+/// `-oProxyCommand=...`.
+fn check_host(name: &str, host: &str) -> Result<(), ProviderError> {
+    if host.starts_with('-') {
+        return Err(ProviderError::Configuration(format!(
+            "{} '{}' must not start with '-'",
+            name, host
+        )));
+    }
+    Ok(())
+}
+
+/// Log `ssh`'s stderr as it arrives, so the process never blocks on a full
+/// pipe, and return its last lines once it closes.
+fn drain_stderr(stderr: ChildStderr) -> JoinHandle<String> {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        let mut tail = VecDeque::with_capacity(STDERR_LINES);
+        while let Ok(Some(line)) = lines.next_line().await {
+            debug!("ssh: {}", line);
+            if tail.len() == STDERR_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+        Vec::from(tail).join("\n").trim().to_string()
+    })
+}
+
+/// Whether `ssh` failed because it couldn't listen on the local port.
+fn port_taken(stderr: &str) -> bool {
+    stderr.contains("Address already in use")
+        || stderr.contains("Could not request local forwarding")
+}
+
+/// A local port that is free right now. Another process can take it before
+/// `ssh` binds it, which [`Bastion::forward`] retries.
+fn free_local_port() -> Result<u16, ProviderError> {
+    std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| ProviderError::Sdk(format!("No free local port for SSH tunnel: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::CancellationSource;
+    use serde_json::json;
+
+    #[test]
+    fn test_ssh_args() {
+        let bastion = Bastion::from_config(&json!({
+            "host": "bastion.example.com",
+            "user": "ops",
+            "port": 2222,
+            "private_key_file": "/keys/id_ed25519",
+            "known_hosts_file": null,
+            "jump_host": "edge.example.com",
+        }))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            bastion.args(10022, "api.internal", 443).join(" "),
+            "-N -L 127.0.0.1:10022:api.internal:443 -o BatchMode=yes \
+             -o ExitOnForwardFailure=yes -o ServerAliveInterval=15 -o ConnectTimeout=15 \
+             -p 2222 -i /keys/id_ed25519 -J edge.example.com -- ops@bastion.example.com"
+        );
+
+        assert_eq!(Bastion::from_config(&Value::Null).unwrap(), None);
+        assert!(Bastion::from_config(&json!({"host": "b", "port": 70000})).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hosts_cannot_be_options() {
+        for config in [
+            json!({"host": "-oProxyCommand=touch /tmp/pwned"}),
+            json!({"host": "bastion", "jump_host": "-oProxyCommand=touch /tmp/pwned"}),
+        ] {
+            assert!(matches!(
+                Bastion::from_config(&config),
+                Err(ProviderError::Configuration(_))
+            ));
+        }
+
+        let err = Bastion::new("bastion")
+            .with_ssh_program("false")
+            .forward("-oProxyCommand=touch /tmp/pwned", 443)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::Configuration(_)));
+
+        assert!(port_taken(
+            "bind [127.0.0.1]:10022: Address already in use\n\
+             Could not request local forwarding."
+        ));
+        assert!(!port_taken("Permission denied (publickey)."));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_forward_lifecycle() {
+        // ssh exiting early fails with its exit status
+        let err = Bastion::new("bastion")
+            .with_ssh_program("false")
+            .forward("api", 443)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::Unavailable(_)));
+
+        // A tunnel whose process keeps running closes when the context is cancelled
+        let child = Command::new("sleep")
+            .arg("30")
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let tunnel = SshTunnel {
+            local_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 10022)),
+            child: Arc::new(Mutex::new(Some(child))),
+        };
+
+        let source = CancellationSource::new();
+        tunnel.close_on_cancel(Context::new().with_cancellation(source.token()));
+        assert!(tunnel.is_open().await);
+        source.cancel();
+        for _ in 0..100 {
+            if !tunnel.is_open().await {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!tunnel.is_open().await);
+    }
+}
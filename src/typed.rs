@@ -0,0 +1,652 @@
+//! Resources with typed state.
+//!
+//! A [`TypedResource`] works with a Rust state type instead of
+//! `serde_json::Value`. Wrapped in [`Typed`], it is a
+//! [`DynamicResource`] that decodes every state it receives into the state
+//! type and encodes every state it returns, all through the one
+//! [`StateSerde`] profile the resource declares. Plan, read and apply can
+//! therefore never disagree about how a state is encoded; an encoding that
+//! does not round-trip shows up as a planned change instead of a diff after
+//! apply.
+//!
+//! The profile maps the snake_case attribute names of the schema to the
+//! field names of the state type, chooses whether nulls are kept or dropped,
+//! and converts internally tagged blocks to serde's default enum
+//! representation. It lets API client types with their own serde attributes
+//! be used as state directly:
+//!
+//! ```
+//! use hemmer_provider_sdk::typed::{RenameRule, StateSerde};
+//! use hemmer_provider_sdk::schema::{Attribute, Schema};
+//! use serde::{Deserialize, Serialize};
+//! use serde_json::json;
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! #[serde(rename_all = "camelCase")]
+//! struct Instance {
+//!     instance_type: String,
+//!     public_ip: Option<String>,
+//! }
+//!
+//! let schema = Schema::v0()
+//!     .with_attribute("instance_type", Attribute::required_string())
+//!     .with_attribute("public_ip", Attribute::computed_string());
+//! let serde = StateSerde::new().with_rename_all(RenameRule::Camel);
+//!
+//! let state = json!({"instance_type": "t3.micro", "public_ip": null});
+//! let instance: Instance = serde.decode(&schema, state.clone()).unwrap();
+//! assert_eq!(instance.instance_type, "t3.micro");
+//! assert_eq!(serde.encode(&schema, &instance).unwrap(), state);
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::dynamic::DynamicResource;
+use crate::error::ProviderError;
+use crate::schema::{Block, BlockNestingMode, Diagnostic, Schema};
+use crate::types::{ImportedResource, PlanResult};
+
+/// How schema attribute names map to the field names of a state type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenameRule {
+    /// Fields are named like the attributes (`instance_type`).
+    #[default]
+    Snake,
+    /// `instanceType`
+    Camel,
+    /// `InstanceType`
+    Pascal,
+    /// `instance-type`
+    Kebab,
+    /// `INSTANCE_TYPE`
+    ScreamingSnake,
+}
+
+impl RenameRule {
+    /// The field name for the attribute `name`.
+    pub fn apply(&self, name: &str) -> String {
+        match self {
+            Self::Snake => name.to_string(),
+            Self::Camel | Self::Pascal => {
+                let mut out = String::with_capacity(name.len());
+                for (i, word) in name.split('_').enumerate() {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) if i > 0 || *self == Self::Pascal => {
+                            out.extend(first.to_uppercase());
+                            out.push_str(chars.as_str());
+                        },
+                        _ => out.push_str(word),
+                    }
+                }
+                out
+            },
+            Self::Kebab => name.replace('_', "-"),
+            Self::ScreamingSnake => name.to_uppercase(),
+        }
+    }
+}
+
+/// Whether null attributes are kept in states.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Nulls {
+    /// Nulls are passed through in both directions.
+    #[default]
+    Keep,
+    /// Null attributes are dropped before decoding and after encoding, so
+    /// `#[serde(default)]` fields decode from null and `None` fields are left
+    /// out of states.
+    Skip,
+}
+
+/// The serialization profile of a typed resource's state.
+///
+/// The same profile decodes and encodes every state of the resource.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateSerde {
+    rename_all: RenameRule,
+    nulls: Nulls,
+    tagged_enums: Vec<(String, String)>,
+}
+
+impl StateSerde {
+    /// A profile that maps states to the state type as they are.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rename attributes (and nested block attributes) to the state type's
+    /// field names. Only names in the schema are renamed, so map keys are
+    /// left alone.
+    pub fn with_rename_all(mut self, rule: RenameRule) -> Self {
+        self.rename_all = rule;
+        self
+    }
+
+    /// Set whether null attributes are kept.
+    pub fn with_nulls(mut self, nulls: Nulls) -> Self {
+        self.nulls = nulls;
+        self
+    }
+
+    /// Decode the top-level `attribute`, an object whose `tag` attribute
+    /// names the variant (`{"type": "disk", "size": 10}`), as serde's default
+    /// externally tagged enum (`{"disk": {"size": 10}}`), and encode it back.
+    /// An object holding only the tag is a unit variant (`"disk"`).
+    pub fn with_tagged_enum(
+        mut self,
+        attribute: impl Into<String>,
+        tag: impl Into<String>,
+    ) -> Self {
+        self.tagged_enums.push((attribute.into(), tag.into()));
+        self
+    }
+
+    /// Decode a state into the state type.
+    pub fn decode<T: DeserializeOwned>(
+        &self,
+        schema: &Schema,
+        mut state: Value,
+    ) -> Result<T, ProviderError> {
+        if self.nulls == Nulls::Skip {
+            strip_nulls(&mut state);
+        }
+        rename(
+            &schema.block,
+            &mut state,
+            &|name| name.to_string(),
+            &|name| self.rename_all.apply(name),
+        );
+        if let Some(obj) = state.as_object_mut() {
+            for (attribute, tag) in &self.tagged_enums {
+                let tag = self.rename_all.apply(tag);
+                if let Some(slot) = obj.get_mut(&self.rename_all.apply(attribute)) {
+                    untag(slot, &tag);
+                }
+            }
+        }
+        Ok(serde_json::from_value(state)?)
+    }
+
+    /// Encode a value of the state type as a state.
+    pub fn encode<T: Serialize>(&self, schema: &Schema, value: &T) -> Result<Value, ProviderError> {
+        let mut state = serde_json::to_value(value)?;
+        if let Some(obj) = state.as_object_mut() {
+            for (attribute, tag) in &self.tagged_enums {
+                let tag = self.rename_all.apply(tag);
+                if let Some(slot) = obj.get_mut(&self.rename_all.apply(attribute)) {
+                    retag(slot, tag);
+                }
+            }
+        }
+        rename(
+            &schema.block,
+            &mut state,
+            &|name| self.rename_all.apply(name),
+            &|name| name.to_string(),
+        );
+        if self.nulls == Nulls::Skip {
+            strip_nulls(&mut state);
+        }
+        Ok(state)
+    }
+}
+
+/// Rename the keys of `value` that name attributes or blocks of `block`,
+/// from `from(name)` to `to(name)`.
+fn rename(
+    block: &Block,
+    value: &mut Value,
+    from: &dyn Fn(&str) -> String,
+    to: &dyn Fn(&str) -> String,
+) {
+    let Some(obj) = value.as_object_mut() else {
+        return;
+    };
+    let mut renamed = Map::new();
+    for name in block.attributes.keys().chain(block.blocks.keys()) {
+        if let Some(mut child) = obj.remove(&from(name)) {
+            if let Some(nested) = block.blocks.get(name) {
+                match nested.nesting_mode {
+                    BlockNestingMode::Single => rename(&nested.block, &mut child, from, to),
+                    BlockNestingMode::List | BlockNestingMode::Set => {
+                        for item in child.as_array_mut().into_iter().flatten() {
+                            rename(&nested.block, item, from, to);
+                        }
+                    },
+                    BlockNestingMode::Map => {
+                        for item in child
+                            .as_object_mut()
+                            .into_iter()
+                            .flat_map(|m| m.values_mut())
+                        {
+                            rename(&nested.block, item, from, to);
+                        }
+                    },
+                }
+            }
+            renamed.insert(to(name), child);
+        }
+    }
+    obj.extend(renamed);
+}
+
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(obj) => {
+            obj.retain(|_, v| !v.is_null());
+            obj.values_mut().for_each(strip_nulls);
+        },
+        Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {},
+    }
+}
+
+/// `{tag: variant, ...fields}` to `{variant: {...fields}}`, or `variant` if
+/// there are no fields.
+fn untag(value: &mut Value, tag: &str) {
+    let Some(obj) = value.as_object_mut() else {
+        return;
+    };
+    let Some(Value::String(variant)) = obj.remove(tag) else {
+        return;
+    };
+    *value = if obj.is_empty() {
+        Value::String(variant)
+    } else {
+        let fields = std::mem::take(obj);
+        Value::Object(Map::from_iter([(variant, Value::Object(fields))]))
+    };
+}
+
+/// The inverse of [`untag`].
+fn retag(value: &mut Value, tag: String) {
+    match value {
+        Value::String(variant) => {
+            let variant = std::mem::take(variant);
+            *value = Value::Object(Map::from_iter([(tag, Value::String(variant))]));
+        },
+        Value::Object(obj) if obj.len() == 1 => {
+            let (variant, fields) = obj
+                .iter_mut()
+                .next()
+                .map(|(k, v)| (k.clone(), v.take()))
+                .unwrap_or_default();
+            let mut fields = match fields {
+                Value::Object(fields) => fields,
+                _ => return,
+            };
+            fields.insert(tag, Value::String(variant));
+            *value = Value::Object(fields);
+        },
+        _ => {},
+    }
+}
+
+/// A resource type whose state is a Rust type.
+///
+/// Register it with a [`DynamicProvider`](crate::dynamic::DynamicProvider)
+/// by wrapping it in [`Typed`].
+#[async_trait::async_trait]
+pub trait TypedResource: Send + Sync + 'static {
+    /// The state type.
+    type State: Serialize + DeserializeOwned + Send + Sync + 'static;
+
+    /// The resource type name.
+    fn type_name(&self) -> &str;
+
+    /// The resource schema.
+    fn schema(&self) -> Schema;
+
+    /// The profile used to decode and encode states. Called once, when the
+    /// resource is wrapped.
+    fn serde(&self) -> StateSerde {
+        StateSerde::new()
+    }
+
+    /// Compute the planned state. The plan's changes are the diff between
+    /// the encoded prior and planned states.
+    async fn plan(
+        &self,
+        prior_state: Option<&Self::State>,
+        proposed_state: Self::State,
+    ) -> Result<Self::State, ProviderError> {
+        let _ = prior_state;
+        Ok(proposed_state)
+    }
+
+    /// Create the resource.
+    async fn create(&self, planned_state: Self::State) -> Result<Self::State, ProviderError>;
+
+    /// Read the resource.
+    async fn read(&self, current_state: Self::State) -> Result<Self::State, ProviderError>;
+
+    /// Update the resource.
+    async fn update(
+        &self,
+        prior_state: Self::State,
+        planned_state: Self::State,
+    ) -> Result<Self::State, ProviderError>;
+
+    /// Delete the resource.
+    async fn delete(&self, current_state: Self::State) -> Result<(), ProviderError>;
+
+    /// Import existing infrastructure by ID.
+    async fn import(&self, id: &str) -> Result<Vec<Self::State>, ProviderError> {
+        let _ = id;
+        Err(ProviderError::Sdk(format!(
+            "Import not supported for resource type: {}",
+            self.type_name()
+        )))
+    }
+}
+
+/// Adapts a [`TypedResource`] to a [`DynamicResource`].
+pub struct Typed<R: TypedResource> {
+    resource: R,
+    schema: Schema,
+    serde: StateSerde,
+}
+
+impl<R: TypedResource> Typed<R> {
+    /// Wrap `resource`, fixing its schema and serialization profile.
+    pub fn new(resource: R) -> Self {
+        Self {
+            schema: resource.schema(),
+            serde: resource.serde(),
+            resource,
+        }
+    }
+
+    /// The wrapped resource.
+    pub fn resource(&self) -> &R {
+        &self.resource
+    }
+
+    fn decode(&self, state: Value) -> Result<R::State, ProviderError> {
+        self.serde.decode(&self.schema, state).map_err(|e| {
+            ProviderError::Validation(format!(
+                "Invalid state for resource type '{}': {}",
+                self.resource.type_name(),
+                e
+            ))
+        })
+    }
+
+    fn encode(&self, state: &R::State) -> Result<Value, ProviderError> {
+        self.serde.encode(&self.schema, state)
+    }
+}
+
+impl<R: TypedResource + std::fmt::Debug> std::fmt::Debug for Typed<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Typed")
+            .field("resource", &self.resource)
+            .field("serde", &self.serde)
+            .finish()
+    }
+}
+
+#[async_trait::async_trait]
+impl<R: TypedResource> DynamicResource for Typed<R> {
+    fn type_name(&self) -> &str {
+        self.resource.type_name()
+    }
+
+    fn schema(&self) -> Schema {
+        self.schema.clone()
+    }
+
+    async fn validate(&self, config: Value) -> Result<Vec<Diagnostic>, ProviderError> {
+        Ok(crate::validation::validate(&self.schema, &config))
+    }
+
+    async fn plan(
+        &self,
+        prior_state: Option<Value>,
+        proposed_state: Value,
+        config: Value,
+    ) -> Result<PlanResult, ProviderError> {
+        let _ = config;
+        let prior = prior_state.clone().map(|s| self.decode(s)).transpose()?;
+        let planned = self
+            .resource
+            .plan(prior.as_ref(), self.decode(proposed_state)?)
+            .await?;
+        Ok(PlanResult::from_diff(
+            prior_state.as_ref(),
+            &self.encode(&planned)?,
+        ))
+    }
+
+    async fn create(&self, planned_state: Value) -> Result<Value, ProviderError> {
+        let created = self.resource.create(self.decode(planned_state)?).await?;
+        self.encode(&created)
+    }
+
+    async fn read(&self, current_state: Value) -> Result<Value, ProviderError> {
+        let read = self.resource.read(self.decode(current_state)?).await?;
+        self.encode(&read)
+    }
+
+    async fn update(
+        &self,
+        prior_state: Value,
+        planned_state: Value,
+    ) -> Result<Value, ProviderError> {
+        let updated = self
+            .resource
+            .update(self.decode(prior_state)?, self.decode(planned_state)?)
+            .await?;
+        self.encode(&updated)
+    }
+
+    async fn delete(&self, current_state: Value) -> Result<(), ProviderError> {
+        self.resource.delete(self.decode(current_state)?).await
+    }
+
+    async fn import(&self, id: &str) -> Result<Vec<ImportedResource>, ProviderError> {
+        self.resource
+            .import(id)
+            .await?
+            .iter()
+            .map(|state| {
+                Ok(ImportedResource::new(
+                    self.resource.type_name(),
+                    self.encode(state)?,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dynamic::DynamicProvider;
+    use crate::schema::{Attribute, AttributeFlags, AttributeType, NestedBlock};
+    use crate::server::ProviderService;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Volume {
+        volume_name: String,
+        #[serde(default)]
+        size_gb: u32,
+        source: Source,
+        #[serde(default)]
+        labels: std::collections::BTreeMap<String, String>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    enum Source {
+        Blank,
+        Snapshot {
+            #[serde(rename = "snapshotId")]
+            snapshot_id: String,
+        },
+    }
+
+    fn schema() -> Schema {
+        Schema::v0()
+            .with_attribute("volume_name", Attribute::required_string())
+            .with_attribute("size_gb", Attribute::optional_int64())
+            .with_attribute(
+                "labels",
+                Attribute::new(
+                    AttributeType::map(AttributeType::String),
+                    AttributeFlags::optional(),
+                ),
+            )
+            .with_block(
+                "source",
+                NestedBlock::single(
+                    Block::new()
+                        .with_attribute("type", Attribute::required_string())
+                        .with_attribute("snapshot_id", Attribute::optional_string()),
+                ),
+            )
+    }
+
+    fn serde() -> StateSerde {
+        StateSerde::new()
+            .with_rename_all(RenameRule::Camel)
+            .with_nulls(Nulls::Skip)
+            .with_tagged_enum("source", "type")
+    }
+
+    #[test]
+    fn test_rename_rules() {
+        let cases = [
+            (RenameRule::Snake, "public_ip_address"),
+            (RenameRule::Camel, "publicIpAddress"),
+            (RenameRule::Pascal, "PublicIpAddress"),
+            (RenameRule::Kebab, "public-ip-address"),
+            (RenameRule::ScreamingSnake, "PUBLIC_IP_ADDRESS"),
+        ];
+        for (rule, expected) in cases {
+            assert_eq!(rule.apply("public_ip_address"), expected);
+        }
+    }
+
+    #[test]
+    fn test_state_serde_round_trip() {
+        let state = json!({
+            "volume_name": "data",
+            "size_gb": null,
+            "labels": {"team_name": "infra"},
+            "source": {"type": "snapshot", "snapshot_id": "snap-1"},
+        });
+        let volume: Volume = serde().decode(&schema(), state).unwrap();
+        assert_eq!(
+            volume,
+            Volume {
+                volume_name: "data".to_string(),
+                size_gb: 0,
+                source: Source::Snapshot {
+                    snapshot_id: "snap-1".to_string()
+                },
+                labels: [("team_name".to_string(), "infra".to_string())].into(),
+            }
+        );
+        assert_eq!(
+            serde().encode(&schema(), &volume).unwrap(),
+            json!({
+                "volume_name": "data",
+                "size_gb": 0,
+                "labels": {"team_name": "infra"},
+                "source": {"type": "snapshot", "snapshot_id": "snap-1"},
+            })
+        );
+
+        let blank = json!({"volume_name": "tmp", "size_gb": 1, "source": {"type": "blank"}});
+        let volume: Volume = serde().decode(&schema(), blank.clone()).unwrap();
+        assert_eq!(volume.source, Source::Blank);
+        let mut encoded = serde().encode(&schema(), &volume).unwrap();
+        encoded.as_object_mut().unwrap().remove("labels");
+        assert_eq!(encoded, blank);
+    }
+
+    struct Volumes;
+
+    #[async_trait::async_trait]
+    impl TypedResource for Volumes {
+        type State = Volume;
+
+        fn type_name(&self) -> &str {
+            "volume"
+        }
+
+        fn schema(&self) -> Schema {
+            schema()
+        }
+
+        fn serde(&self) -> StateSerde {
+            serde()
+        }
+
+        async fn plan(
+            &self,
+            _prior_state: Option<&Volume>,
+            mut proposed_state: Volume,
+        ) -> Result<Volume, ProviderError> {
+            proposed_state.size_gb = proposed_state.size_gb.max(10);
+            Ok(proposed_state)
+        }
+
+        async fn create(&self, planned_state: Volume) -> Result<Volume, ProviderError> {
+            Ok(planned_state)
+        }
+
+        async fn read(&self, current_state: Volume) -> Result<Volume, ProviderError> {
+            Ok(current_state)
+        }
+
+        async fn update(&self, _prior: Volume, planned: Volume) -> Result<Volume, ProviderError> {
+            Ok(planned)
+        }
+
+        async fn delete(&self, _current_state: Volume) -> Result<(), ProviderError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_typed_resource() {
+        let provider = DynamicProvider::new().with_resource(Typed::new(Volumes));
+        let proposed = json!({"volume_name": "data", "source": {"type": "blank"}});
+
+        let plan = provider
+            .plan("volume", None, proposed, Value::Null)
+            .await
+            .unwrap();
+        let planned = json!({"volume_name": "data", "size_gb": 10, "labels": {}, "source": {"type": "blank"}});
+        assert_eq!(plan.planned_state, planned);
+
+        let created = provider.create("volume", plan.planned_state).await.unwrap();
+        assert_eq!(created, planned);
+        let replan = provider
+            .plan(
+                "volume",
+                Some(created.clone()),
+                created.clone(),
+                Value::Null,
+            )
+            .await
+            .unwrap();
+        assert!(replan.changes.is_empty());
+
+        let err = provider
+            .read("volume", json!({"volume_name": "data"}))
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid state for resource type 'volume'"));
+    }
+}
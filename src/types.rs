@@ -3,6 +3,28 @@
 //! These types provide a more ergonomic API over the raw protobuf types.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::context::ProviderData;
+use crate::error::ProviderError;
+use crate::patch::JsonPatch;
+use crate::payload::encode_json;
+use crate::schema::{Diagnostic, DiagnosticSeverity, ProviderSchema};
+use crate::state::canonical_eq;
+
+/// What a change does to an attribute, for plan rendering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeAction {
+    /// The attribute gets a value.
+    Create,
+    /// The attribute's value changes in place.
+    Update,
+    /// The attribute's value is removed.
+    Delete,
+    /// The change forces replacement of the resource.
+    Replace,
+}
 
 /// A change to a single attribute during a plan.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -13,6 +35,15 @@ pub struct AttributeChange {
     pub before: Option<serde_json::Value>,
     /// The value after the change (JSON-encoded, None if deleting).
     pub after: Option<serde_json::Value>,
+    /// What the change does. When None, core infers it from `before` and `after`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<ChangeAction>,
+    /// Whether core should redact `before` and `after` in plan output.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sensitive: bool,
+    /// A free-form explanation shown alongside the change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
 }
 
 impl AttributeChange {
@@ -26,6 +57,9 @@ impl AttributeChange {
             path: path.into(),
             before,
             after,
+            action: None,
+            sensitive: false,
+            note: None,
         }
     }
 
@@ -47,10 +81,30 @@ impl AttributeChange {
     ) -> Self {
         Self::new(path, Some(before), Some(after))
     }
+
+    /// Set what the change does.
+    pub fn with_action(mut self, action: ChangeAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    /// Mark the values as sensitive, so core redacts them in plan output.
+    pub fn sensitive(mut self) -> Self {
+        self.sensitive = true;
+        self
+    }
+
+    /// Attach an explanation of the change (e.g. why it forces replacement).
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
 }
 
 impl From<crate::generated::AttributeChange> for AttributeChange {
     fn from(proto: crate::generated::AttributeChange) -> Self {
+        use crate::generated::attribute_change::Action;
+
         Self {
             path: proto.path,
             before: if proto.before.is_empty() {
@@ -63,22 +117,65 @@ impl From<crate::generated::AttributeChange> for AttributeChange {
             } else {
                 serde_json::from_slice(&proto.after).ok()
             },
+            action: match Action::try_from(proto.action) {
+                Ok(Action::Create) => Some(ChangeAction::Create),
+                Ok(Action::Update) => Some(ChangeAction::Update),
+                Ok(Action::Delete) => Some(ChangeAction::Delete),
+                Ok(Action::Replace) => Some(ChangeAction::Replace),
+                Ok(Action::Unspecified) | Err(_) => None,
+            },
+            sensitive: proto.sensitive,
+            note: Some(proto.note).filter(|n| !n.is_empty()),
         }
     }
 }
 
 impl From<AttributeChange> for crate::generated::AttributeChange {
     fn from(change: AttributeChange) -> Self {
+        use crate::generated::attribute_change::Action;
+
+        let action = match change.action {
+            None => Action::Unspecified,
+            Some(ChangeAction::Create) => Action::Create,
+            Some(ChangeAction::Update) => Action::Update,
+            Some(ChangeAction::Delete) => Action::Delete,
+            Some(ChangeAction::Replace) => Action::Replace,
+        };
         Self {
             path: change.path,
-            before: change
-                .before
-                .map(|v| serde_json::to_vec(&v).unwrap_or_default())
-                .unwrap_or_default(),
-            after: change
-                .after
-                .map(|v| serde_json::to_vec(&v).unwrap_or_default())
-                .unwrap_or_default(),
+            before: change.before.map(|v| encode_json(&v)).unwrap_or_default(),
+            after: change.after.map(|v| encode_json(&v)).unwrap_or_default(),
+            action: action as i32,
+            sensitive: change.sensitive,
+            note: change.note.unwrap_or_default(),
+        }
+    }
+}
+
+/// Why a change cannot be applied in place.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplaceReason {
+    /// The attribute or block whose change forces replacement.
+    pub path: String,
+    /// A human-readable explanation, e.g. "'engine_version' cannot be changed in place".
+    pub reason: String,
+}
+
+impl ReplaceReason {
+    /// Create a replace reason.
+    pub fn new(path: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl From<ReplaceReason> for crate::generated::ReplaceReason {
+    fn from(reason: ReplaceReason) -> Self {
+        Self {
+            path: reason.path,
+            reason: reason.reason,
         }
     }
 }
@@ -92,6 +189,9 @@ pub struct PlanResult {
     pub changes: Vec<AttributeChange>,
     /// Whether the resource requires replacement.
     pub requires_replace: bool,
+    /// Why the resource requires replacement.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replace_reasons: Vec<ReplaceReason>,
 }
 
 impl PlanResult {
@@ -101,6 +201,7 @@ impl PlanResult {
             planned_state: state,
             changes: Vec::new(),
             requires_replace: false,
+            replace_reasons: Vec::new(),
         }
     }
 
@@ -114,14 +215,46 @@ impl PlanResult {
             planned_state,
             changes,
             requires_replace,
+            replace_reasons: Vec::new(),
         }
     }
 
+    /// Require replacement, explaining why.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hemmer_provider_sdk::PlanResult;
+    /// use serde_json::json;
+    ///
+    /// let prior = json!({"engine_version": "14"});
+    /// let proposed = json!({"engine_version": "15"});
+    /// let result = PlanResult::from_diff(Some(&prior), &proposed).with_replace_reason(
+    ///     "engine_version",
+    ///     "Major version upgrades require a new instance",
+    /// );
+    /// assert!(result.requires_replace);
+    /// assert_eq!(result.replace_reasons[0].path, "engine_version");
+    /// ```
+    pub fn with_replace_reason(
+        mut self,
+        path: impl Into<String>,
+        reason: impl Into<String>,
+    ) -> Self {
+        self.requires_replace = true;
+        self.replace_reasons.push(ReplaceReason::new(path, reason));
+        self
+    }
+
     /// Automatically compute attribute changes by comparing prior and proposed states.
     ///
     /// This method walks both JSON trees and emits an `AttributeChange` for each difference.
     /// Nested objects use dot-notation paths (e.g., `"metadata.labels.app"`).
     ///
+    /// For schemas with optional+computed attributes, pass the proposed state through
+    /// [`normalize_optional_computed`](crate::plan::normalize_optional_computed) first so
+    /// values the provider chose don't show up as changes.
+    ///
     /// # Arguments
     ///
     /// * `prior` - The previous state (None if creating a new resource)
@@ -158,6 +291,7 @@ impl PlanResult {
                     planned_state: proposed.clone(),
                     changes,
                     requires_replace: false,
+                    replace_reasons: Vec::new(),
                 }
             },
             Some(prior_state) => {
@@ -166,6 +300,7 @@ impl PlanResult {
                     planned_state: proposed.clone(),
                     changes,
                     requires_replace: false,
+                    replace_reasons: Vec::new(),
                 }
             },
         }
@@ -245,7 +380,7 @@ fn compute_json_diff(
     use serde_json::Value;
 
     // If values are identical, no changes
-    if prior == proposed {
+    if canonical_eq(prior, proposed) {
         return Vec::new();
     }
 
@@ -255,7 +390,8 @@ fn compute_json_diff(
             let mut changes = Vec::new();
 
             // Find all keys from both objects
-            let mut all_keys = std::collections::HashSet::new();
+            // Sorted, so changes come out in the same order every time
+            let mut all_keys = std::collections::BTreeSet::new();
             all_keys.extend(prior_map.keys());
             all_keys.extend(proposed_map.keys());
 
@@ -269,7 +405,7 @@ fn compute_json_diff(
                 match (prior_map.get(key), proposed_map.get(key)) {
                     (Some(prior_val), Some(proposed_val)) => {
                         // Field exists in both - check for differences
-                        if prior_val != proposed_val {
+                        if !canonical_eq(prior_val, proposed_val) {
                             match (prior_val, proposed_val) {
                                 (Value::Object(_), Value::Object(_))
                                 | (Value::Array(_), Value::Array(_)) => {
@@ -343,7 +479,7 @@ fn compute_json_diff(
                 match (prior_arr.get(idx), proposed_arr.get(idx)) {
                     (Some(prior_val), Some(proposed_val)) => {
                         // Element exists in both arrays
-                        if prior_val != proposed_val {
+                        if !canonical_eq(prior_val, proposed_val) {
                             match (prior_val, proposed_val) {
                                 (Value::Object(_), Value::Object(_))
                                 | (Value::Array(_), Value::Array(_)) => {
@@ -418,6 +554,398 @@ fn compute_json_diff(
     }
 }
 
+/// The estimated impact of applying a planned change, shown per resource in
+/// core's plan output. Every figure is optional; set the ones the provider can
+/// compute.
+///
+/// # Examples
+///
+/// ```
+/// use hemmer_provider_sdk::ImpactEstimate;
+/// use std::time::Duration;
+///
+/// let impact = ImpactEstimate::new()
+///     .with_monthly_cost(73.0, "USD")
+///     .with_destructiveness(0.2)
+///     .with_propagation_delay(Duration::from_secs(300));
+/// assert_eq!(impact.propagation_delay_secs, Some(300));
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ImpactEstimate {
+    /// Estimated monthly cost of the resource once applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly_cost: Option<f64>,
+    /// ISO 4217 currency code of `monthly_cost`, e.g. "USD".
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub currency: String,
+    /// How destructive the change is, from 0.0 (no data loss or downtime) to
+    /// 1.0 (irreversible loss of data).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destructiveness: Option<f64>,
+    /// Seconds until the change is visible everywhere (DNS, CDN, IAM).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub propagation_delay_secs: Option<u64>,
+}
+
+impl ImpactEstimate {
+    /// Create an empty estimate.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the estimated monthly cost.
+    pub fn with_monthly_cost(mut self, amount: f64, currency: impl Into<String>) -> Self {
+        self.monthly_cost = Some(amount);
+        self.currency = currency.into();
+        self
+    }
+
+    /// Set the destructiveness score, clamped to 0.0..=1.0.
+    pub fn with_destructiveness(mut self, score: f64) -> Self {
+        self.destructiveness = Some(score.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Set how long the change takes to propagate.
+    pub fn with_propagation_delay(mut self, delay: std::time::Duration) -> Self {
+        self.propagation_delay_secs = Some(delay.as_secs());
+        self
+    }
+}
+
+impl From<ImpactEstimate> for crate::generated::ImpactEstimate {
+    fn from(impact: ImpactEstimate) -> Self {
+        Self {
+            monthly_cost: impact.monthly_cost,
+            currency: impact.currency,
+            destructiveness: impact.destructiveness,
+            propagation_delay_secs: impact.propagation_delay_secs,
+        }
+    }
+}
+
+/// The result of configuring a provider.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigureResult {
+    /// Diagnostics (errors and warnings) from configuration.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Data made available to later operations via [`Context::provider_data`](crate::context::Context::provider_data).
+    pub provider_data: Option<ProviderData>,
+}
+
+impl ConfigureResult {
+    /// Create an empty result.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store provider data (e.g. an API client) for later operations.
+    pub fn with_provider_data<T: Send + Sync + 'static>(mut self, data: T) -> Self {
+        self.provider_data = Some(ProviderData::new(data));
+        self
+    }
+
+    /// Register a hook that cleans up the provider data, e.g. closing a
+    /// connection pool. Call after [`with_provider_data`](Self::with_provider_data).
+    ///
+    /// The server runs the hook after the provider's `stop()`. Data replaced
+    /// by a later `configure` is torn down once the last operation using it
+    /// finishes.
+    ///
+    /// ```ignore
+    /// Ok(ConfigureResult::new()
+    ///     .with_provider_data(pool)
+    ///     .with_teardown(|pool: Arc<Pool>| async move { pool.close().await }))
+    /// ```
+    pub fn with_teardown<T, F, Fut>(mut self, teardown: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(std::sync::Arc<T>) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.provider_data = self.provider_data.map(|data| data.with_teardown(teardown));
+        self
+    }
+
+    /// Add a diagnostic.
+    pub fn with_diagnostic(mut self, diagnostic: Diagnostic) -> Self {
+        self.diagnostics.push(diagnostic);
+        self
+    }
+
+    /// Whether any diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == DiagnosticSeverity::Error)
+    }
+}
+
+impl From<Vec<Diagnostic>> for ConfigureResult {
+    fn from(diagnostics: Vec<Diagnostic>) -> Self {
+        Self {
+            diagnostics,
+            provider_data: None,
+        }
+    }
+}
+
+/// How complete a [`ReadResult`] is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadStatus {
+    /// The state reflects the remote object.
+    #[default]
+    Current,
+    /// The remote object couldn't be read; the state is from an earlier read.
+    Stale,
+    /// Parts of the remote object couldn't be read; the state is partial or
+    /// partly from an earlier read.
+    Degraded,
+    /// The remote object changed in a way that can't be updated in place
+    /// (e.g. it was recreated upstream with a new immutable property); core
+    /// plans a replacement instead of an update. Only meaningful for resources.
+    Tainted,
+}
+
+/// The result of reading a resource or data source, possibly best-effort.
+///
+/// When an upstream outage makes a read fail, returning the last known state
+/// marked [`Stale`](ReadStatus::Stale) (or a partial one marked
+/// [`Degraded`](ReadStatus::Degraded)) with a warning lets the rest of the
+/// refresh go ahead instead of failing the whole plan. A resource whose remote
+/// object was replaced behind core's back can be marked
+/// [`Tainted`](ReadStatus::Tainted) so core plans a replacement rather than an
+/// update that would fail.
+///
+/// # Examples
+///
+/// ```
+/// use hemmer_provider_sdk::{ReadResult, ReadStatus};
+/// use serde_json::json;
+///
+/// let prior = json!({"id": "i-1", "size": "small"});
+/// let result = ReadResult::stale(prior, "Compute API unavailable, using the last known state");
+/// assert_eq!(result.status, ReadStatus::Stale);
+/// assert_eq!(result.diagnostics.len(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadResult {
+    /// The state read.
+    pub state: serde_json::Value,
+    /// How complete `state` is.
+    #[serde(default)]
+    pub status: ReadStatus,
+    /// Warnings (or errors) about the read, returned with the state.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ReadResult {
+    /// A complete, current result.
+    pub fn new(state: serde_json::Value) -> Self {
+        Self {
+            state,
+            status: ReadStatus::Current,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// A result from an earlier read, with a warning explaining why.
+    pub fn stale(state: serde_json::Value, reason: impl Into<String>) -> Self {
+        Self::new(state)
+            .with_status(ReadStatus::Stale)
+            .with_diagnostic(Diagnostic::warning(reason))
+    }
+
+    /// A partial result, with a warning explaining what is missing.
+    pub fn degraded(state: serde_json::Value, reason: impl Into<String>) -> Self {
+        Self::new(state)
+            .with_status(ReadStatus::Degraded)
+            .with_diagnostic(Diagnostic::warning(reason))
+    }
+
+    /// A current result for a resource that must be replaced, with a warning
+    /// explaining why.
+    pub fn tainted(state: serde_json::Value, reason: impl Into<String>) -> Self {
+        Self::new(state)
+            .with_status(ReadStatus::Tainted)
+            .with_diagnostic(Diagnostic::warning(reason))
+    }
+
+    /// Set the status.
+    pub fn with_status(mut self, status: ReadStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Add a diagnostic.
+    pub fn with_diagnostic(mut self, diagnostic: Diagnostic) -> Self {
+        self.diagnostics.push(diagnostic);
+        self
+    }
+
+    /// Whether the state is complete and current, and the resource needs no
+    /// replacement.
+    pub fn is_current(&self) -> bool {
+        self.status == ReadStatus::Current
+    }
+}
+
+impl From<serde_json::Value> for ReadResult {
+    fn from(state: serde_json::Value) -> Self {
+        Self::new(state)
+    }
+}
+
+impl From<ReadStatus> for crate::generated::ReadStatus {
+    fn from(status: ReadStatus) -> Self {
+        match status {
+            ReadStatus::Current => Self::Current,
+            ReadStatus::Stale => Self::Stale,
+            ReadStatus::Degraded => Self::Degraded,
+            ReadStatus::Tainted => Self::Tainted,
+        }
+    }
+}
+
+/// A create, as passed to [`ProviderService::handle_create`](crate::ProviderService::handle_create).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateRequest {
+    /// The resource type.
+    pub resource_type: String,
+    /// The planned state.
+    pub planned_state: serde_json::Value,
+    /// The resource's configuration, including write-only inputs (passwords,
+    /// one-time tokens) that are deliberately never in the planned state.
+    /// Null if core didn't send it.
+    pub config: serde_json::Value,
+}
+
+impl CreateRequest {
+    /// Create a request without configuration.
+    pub fn new(resource_type: impl Into<String>, planned_state: serde_json::Value) -> Self {
+        Self {
+            resource_type: resource_type.into(),
+            planned_state,
+            config: serde_json::Value::Null,
+        }
+    }
+
+    /// Set the resource's configuration.
+    pub fn with_config(mut self, config: serde_json::Value) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+/// A read, as passed to [`ProviderService::handle_read`](crate::ProviderService::handle_read).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadRequest {
+    /// The resource type.
+    pub resource_type: String,
+    /// The current state.
+    pub current_state: serde_json::Value,
+}
+
+impl ReadRequest {
+    /// Create a request.
+    pub fn new(resource_type: impl Into<String>, current_state: serde_json::Value) -> Self {
+        Self {
+            resource_type: resource_type.into(),
+            current_state,
+        }
+    }
+}
+
+/// An update, as passed to [`ProviderService::handle_update`](crate::ProviderService::handle_update).
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateRequest {
+    /// The resource type.
+    pub resource_type: String,
+    /// The prior state.
+    pub prior_state: serde_json::Value,
+    /// The planned state.
+    pub planned_state: serde_json::Value,
+    /// The JSON Patch from the prior to the planned state.
+    pub patch: JsonPatch,
+    /// The resource's configuration, as for [`CreateRequest::config`].
+    pub config: serde_json::Value,
+}
+
+impl UpdateRequest {
+    /// Create a request without configuration, computing the patch from the
+    /// prior to the planned state.
+    pub fn new(
+        resource_type: impl Into<String>,
+        prior_state: serde_json::Value,
+        planned_state: serde_json::Value,
+    ) -> Self {
+        Self {
+            resource_type: resource_type.into(),
+            patch: JsonPatch::diff(&prior_state, &planned_state),
+            prior_state,
+            planned_state,
+            config: serde_json::Value::Null,
+        }
+    }
+
+    /// Set the resource's configuration.
+    pub fn with_config(mut self, config: serde_json::Value) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+/// A delete, as passed to [`ProviderService::handle_delete`](crate::ProviderService::handle_delete).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteRequest {
+    /// The resource type.
+    pub resource_type: String,
+    /// The current state.
+    pub current_state: serde_json::Value,
+    /// The resource's last configuration. Null when the resource was removed
+    /// from configuration, so deletes must not depend on it.
+    pub config: serde_json::Value,
+}
+
+impl DeleteRequest {
+    /// Create a request without configuration.
+    pub fn new(resource_type: impl Into<String>, current_state: serde_json::Value) -> Self {
+        Self {
+            resource_type: resource_type.into(),
+            current_state,
+            config: serde_json::Value::Null,
+        }
+    }
+
+    /// Set the resource's last configuration.
+    pub fn with_config(mut self, config: serde_json::Value) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+/// A data source read, as passed to
+/// [`ProviderService::handle_read_data_source`](crate::ProviderService::handle_read_data_source).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadDataSourceRequest {
+    /// The data source type.
+    pub data_source_type: String,
+    /// The data source configuration.
+    pub config: serde_json::Value,
+}
+
+impl ReadDataSourceRequest {
+    /// Create a request.
+    pub fn new(data_source_type: impl Into<String>, config: serde_json::Value) -> Self {
+        Self {
+            data_source_type: data_source_type.into(),
+            config,
+        }
+    }
+}
+
 /// An imported resource.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ImportedResource {
@@ -446,13 +974,279 @@ pub struct ProviderMetadata {
     pub data_sources: Vec<String>,
     /// Server capabilities.
     pub capabilities: ServerCapabilities,
+    /// Declared import ID formats by resource type (see [`crate::import`]).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub import_formats: BTreeMap<String, Vec<String>>,
+    /// Maximum concurrent operations by resource type, enforced by the server
+    /// and advertised to core (see [`crate::lock::ParallelismLimits`]).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub max_parallelism: BTreeMap<String, u32>,
+    /// Suggested minimum seconds between refreshes by resource type, honored
+    /// by the server's read cache and advertised to core (see
+    /// [`crate::cache::ReadCache`]).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub refresh_intervals: BTreeMap<String, u64>,
+}
+
+impl ProviderMetadata {
+    /// Start building metadata that is checked against the schema.
+    ///
+    /// ```
+    /// use hemmer_provider_sdk::schema::{ProviderSchema, Schema};
+    /// use hemmer_provider_sdk::{ProviderMetadata, ServerCapabilities};
+    ///
+    /// let schema = ProviderSchema::new()
+    ///     .with_resource("bucket", Schema::v0())
+    ///     .with_data_source("region", Schema::v0());
+    ///
+    /// let metadata = ProviderMetadata::builder()
+    ///     .with_resource("bucket")
+    ///     .with_data_source("region")
+    ///     .with_capabilities(ServerCapabilities::new().with_plan_destroy())
+    ///     .build(&schema)
+    ///     .unwrap();
+    /// assert_eq!(metadata.resources, vec!["bucket"]);
+    ///
+    /// // "bucket" has a schema but isn't listed
+    /// assert!(ProviderMetadata::builder().with_data_source("region").build(&schema).is_err());
+    /// ```
+    pub fn builder() -> ProviderMetadataBuilder {
+        ProviderMetadataBuilder::default()
+    }
+}
+
+/// Builds a [`ProviderMetadata`] that matches a [`ProviderSchema`].
+///
+/// Hand-written metadata that drifts from the schema only shows up at runtime,
+/// as "unknown resource type" errors from core. [`build`](Self::build) fails
+/// instead if a listed type has no schema, a type with a schema isn't listed,
+/// or a per-resource setting names a resource that isn't listed.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderMetadataBuilder {
+    metadata: ProviderMetadata,
+}
+
+impl ProviderMetadataBuilder {
+    /// List a resource type.
+    pub fn with_resource(mut self, name: impl Into<String>) -> Self {
+        self.metadata.resources.push(name.into());
+        self
+    }
+
+    /// List a data source type.
+    pub fn with_data_source(mut self, name: impl Into<String>) -> Self {
+        self.metadata.data_sources.push(name.into());
+        self
+    }
+
+    /// Set the server capabilities.
+    pub fn with_capabilities(mut self, capabilities: ServerCapabilities) -> Self {
+        self.metadata.capabilities = capabilities;
+        self
+    }
+
+    /// Declare the import ID formats of a resource type.
+    pub fn with_import_formats(
+        mut self,
+        resource_type: impl Into<String>,
+        formats: Vec<String>,
+    ) -> Self {
+        self.metadata
+            .import_formats
+            .insert(resource_type.into(), formats);
+        self
+    }
+
+    /// Limit the concurrent operations on a resource type.
+    pub fn with_max_parallelism(mut self, resource_type: impl Into<String>, max: u32) -> Self {
+        self.metadata
+            .max_parallelism
+            .insert(resource_type.into(), max);
+        self
+    }
+
+    /// Suggest a minimum number of seconds between refreshes of a resource type.
+    pub fn with_refresh_interval(mut self, resource_type: impl Into<String>, secs: u64) -> Self {
+        self.metadata
+            .refresh_intervals
+            .insert(resource_type.into(), secs);
+        self
+    }
+
+    /// Check the metadata against `schema` and build it.
+    ///
+    /// Fails with [`ProviderError::Sdk`] listing every mismatch.
+    pub fn build(self, schema: &ProviderSchema) -> Result<ProviderMetadata, ProviderError> {
+        let metadata = self.metadata;
+        let mut problems: Vec<String> = crate::lint::lint_metadata(schema, &metadata)
+            .into_iter()
+            .map(|d| d.summary)
+            .collect();
+        for (setting, resource_types) in [
+            (
+                "Import formats",
+                metadata.import_formats.keys().collect::<Vec<_>>(),
+            ),
+            ("Max parallelism", metadata.max_parallelism.keys().collect()),
+            (
+                "Refresh interval",
+                metadata.refresh_intervals.keys().collect(),
+            ),
+        ] {
+            for name in resource_types {
+                if !metadata.resources.contains(name) {
+                    problems.push(format!(
+                        "{} set for resource '{}', which is not listed",
+                        setting, name
+                    ));
+                }
+            }
+        }
+        if problems.is_empty() {
+            Ok(metadata)
+        } else {
+            Err(ProviderError::Sdk(format!(
+                "Provider metadata does not match the schema: {}",
+                problems.join("; ")
+            )))
+        }
+    }
 }
 
 /// Server capability flags.
+///
+/// Core uses these to feature-detect optional protocol behavior instead of
+/// calling an RPC and interpreting the failure. Flags missing from an older
+/// provider's metadata read as `false`.
+///
+/// ```
+/// use hemmer_provider_sdk::ServerCapabilities;
+///
+/// let capabilities = ServerCapabilities::new().with_import().with_identity();
+/// assert!(capabilities.import);
+/// assert_eq!(capabilities.enabled(), vec!["import", "identity"]);
+/// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct ServerCapabilities {
     /// Whether the provider supports planning destroy operations.
     pub plan_destroy: bool,
+    /// Whether the provider supports moving state between resource types.
+    pub move_state: bool,
+    /// Whether the provider supports importing existing infrastructure.
+    pub import: bool,
+    /// Whether the provider supports provider functions.
+    pub functions: bool,
+    /// Whether the provider supports ephemeral resources, which are never persisted to state.
+    pub ephemeral_resources: bool,
+    /// Whether the provider supports streaming progress events during apply.
+    pub streaming_progress: bool,
+    /// Whether the provider supports applying several changes in one call.
+    pub batch_apply: bool,
+    /// Whether the provider supports resource identities alongside state.
+    pub identity: bool,
+}
+
+impl ServerCapabilities {
+    /// Create capabilities with every flag disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare support for planning destroy operations.
+    pub fn with_plan_destroy(mut self) -> Self {
+        self.plan_destroy = true;
+        self
+    }
+
+    /// Declare support for moving state between resource types.
+    pub fn with_move_state(mut self) -> Self {
+        self.move_state = true;
+        self
+    }
+
+    /// Declare support for importing existing infrastructure.
+    pub fn with_import(mut self) -> Self {
+        self.import = true;
+        self
+    }
+
+    /// Declare support for provider functions.
+    pub fn with_functions(mut self) -> Self {
+        self.functions = true;
+        self
+    }
+
+    /// Declare support for ephemeral resources, which are never persisted to state.
+    pub fn with_ephemeral_resources(mut self) -> Self {
+        self.ephemeral_resources = true;
+        self
+    }
+
+    /// Declare support for streaming progress events during apply.
+    pub fn with_streaming_progress(mut self) -> Self {
+        self.streaming_progress = true;
+        self
+    }
+
+    /// Declare support for applying several changes in one call.
+    pub fn with_batch_apply(mut self) -> Self {
+        self.batch_apply = true;
+        self
+    }
+
+    /// Declare support for resource identities alongside state.
+    pub fn with_identity(mut self) -> Self {
+        self.identity = true;
+        self
+    }
+
+    /// The names of the enabled flags, in declaration order.
+    pub fn enabled(&self) -> Vec<&'static str> {
+        [
+            ("plan_destroy", self.plan_destroy),
+            ("move_state", self.move_state),
+            ("import", self.import),
+            ("functions", self.functions),
+            ("ephemeral_resources", self.ephemeral_resources),
+            ("streaming_progress", self.streaming_progress),
+            ("batch_apply", self.batch_apply),
+            ("identity", self.identity),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect()
+    }
+}
+
+impl From<ServerCapabilities> for crate::generated::ServerCapabilities {
+    fn from(capabilities: ServerCapabilities) -> Self {
+        Self {
+            plan_destroy: capabilities.plan_destroy,
+            move_state: capabilities.move_state,
+            import: capabilities.import,
+            functions: capabilities.functions,
+            ephemeral_resources: capabilities.ephemeral_resources,
+            streaming_progress: capabilities.streaming_progress,
+            batch_apply: capabilities.batch_apply,
+            identity: capabilities.identity,
+        }
+    }
+}
+
+impl From<crate::generated::ServerCapabilities> for ServerCapabilities {
+    fn from(capabilities: crate::generated::ServerCapabilities) -> Self {
+        Self {
+            plan_destroy: capabilities.plan_destroy,
+            move_state: capabilities.move_state,
+            import: capabilities.import,
+            functions: capabilities.functions,
+            ephemeral_resources: capabilities.ephemeral_resources,
+            streaming_progress: capabilities.streaming_progress,
+            batch_apply: capabilities.batch_apply,
+            identity: capabilities.identity,
+        }
+    }
 }
 
 /// The current protocol version.
@@ -532,6 +1326,34 @@ mod tests {
         assert_eq!(modified.after, Some(serde_json::json!(2)));
     }
 
+    #[test]
+    fn test_metadata_builder_checks_schema() {
+        use crate::schema::Schema;
+
+        let schema = ProviderSchema::new()
+            .with_resource("bucket", Schema::v0())
+            .with_data_source("region", Schema::v0());
+
+        let metadata = ProviderMetadata::builder()
+            .with_resource("bucket")
+            .with_data_source("region")
+            .with_max_parallelism("bucket", 4)
+            .build(&schema)
+            .unwrap();
+        assert_eq!(metadata.max_parallelism["bucket"], 4);
+
+        let err = ProviderMetadata::builder()
+            .with_resource("bucket")
+            .with_resource("object")
+            .with_refresh_interval("queue", 60)
+            .build(&schema)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Metadata lists resource 'object' without a schema"));
+        assert!(err.contains("Data source 'region' is missing from metadata"));
+        assert!(err.contains("Refresh interval set for resource 'queue', which is not listed"));
+    }
+
     #[test]
     fn test_attribute_change_conversion() {
         let change =
@@ -546,6 +1368,34 @@ mod tests {
         assert_eq!(back.path, change.path);
         assert_eq!(back.before, change.before);
         assert_eq!(back.after, change.after);
+        assert_eq!(back.action, None);
+        assert!(!back.sensitive);
+        assert_eq!(back.note, None);
+    }
+
+    #[test]
+    fn test_attribute_change_annotations() {
+        let change =
+            AttributeChange::modified("password", serde_json::json!("a"), serde_json::json!("b"))
+                .with_action(ChangeAction::Replace)
+                .sensitive()
+                .with_note("password can only be set at creation");
+
+        let proto: crate::generated::AttributeChange = change.clone().into();
+        assert_eq!(
+            proto.action,
+            crate::generated::attribute_change::Action::Replace as i32
+        );
+        assert!(proto.sensitive);
+        assert_eq!(AttributeChange::from(proto), change);
+
+        let json = serde_json::to_value(&change).unwrap();
+        assert_eq!(json["action"], "replace");
+        let plain = serde_json::to_value(AttributeChange::added("name", "x".into())).unwrap();
+        assert_eq!(
+            plain,
+            serde_json::json!({"path": "name", "before": null, "after": "x"})
+        );
     }
 
     #[test]
@@ -690,6 +1540,18 @@ mod tests {
         assert_eq!(result.planned_state, state);
     }
 
+    #[test]
+    fn test_from_diff_canonical() {
+        // Numbers that differ only in formatting are not changes
+        let prior = serde_json::json!({"size": 10, "ratio": 0.5, "tags": {"b": "1", "a": "1"}});
+        let proposed =
+            serde_json::json!({"size": 10.0, "ratio": 0.5, "tags": {"b": "2", "a": "2"}});
+
+        let result = PlanResult::from_diff(Some(&prior), &proposed);
+        let paths: Vec<_> = result.changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["tags.a", "tags.b"]);
+    }
+
     #[test]
     fn test_from_diff_simple_modification() {
         // Modifying a single field
@@ -0,0 +1,347 @@
+//! Helpers for upgrading resource state between schema versions.
+//!
+//! An [`upgrade_resource_state`](crate::ProviderService::upgrade_resource_state)
+//! implementation is mostly renames and moves. These helpers do them by
+//! [attribute path](crate::schema::AttributePath), so each version step reads
+//! as a list of changes:
+//!
+//! ```
+//! use hemmer_provider_sdk::upgrade;
+//! use serde_json::json;
+//!
+//! // Version 0 had a flat `host` and `port`, and `address` on each rule.
+//! let mut state = json!({
+//!     "name": "web",
+//!     "host": "10.0.0.1",
+//!     "port": 80,
+//!     "rules": [{"address": "0.0.0.0/0"}, {"address": "::/0"}],
+//! });
+//!
+//! upgrade::move_into_block(&mut state, "host", "endpoint").unwrap();
+//! upgrade::move_into_block(&mut state, "port", "endpoint").unwrap();
+//! upgrade::rename_attribute(&mut state, "rules.*.address", "cidr").unwrap();
+//! upgrade::set_default(&mut state, "protocol", json!("tcp")).unwrap();
+//!
+//! assert_eq!(
+//!     state,
+//!     json!({
+//!         "name": "web",
+//!         "endpoint": {"host": "10.0.0.1", "port": 80},
+//!         "rules": [{"cidr": "0.0.0.0/0"}, {"cidr": "::/0"}],
+//!         "protocol": "tcp",
+//!     })
+//! );
+//! ```
+//!
+//! Paths use the dot form of [`AttributePath`]: list elements by index and map
+//! entries by key. A `*` segment matches every element of a list or every
+//! entry of a map, so `rules.*.address` is the `address` of each `rules` block.
+//!
+//! Optional attributes are often missing from old state, so an attribute that
+//! isn't there, or a block that is null, is skipped rather than an error. A
+//! path that runs into a value that isn't a block is an error, since it means
+//! the path doesn't match the state.
+
+use serde_json::{Map, Value};
+
+use crate::error::ProviderError;
+use crate::schema::AttributePath;
+
+/// Rename the attribute at `path` to `new_name`, keeping it in the same block.
+///
+/// A value already present under `new_name` is replaced.
+pub fn rename_attribute(
+    state: &mut Value,
+    path: impl Into<AttributePath>,
+    new_name: &str,
+) -> Result<(), ProviderError> {
+    for_each_parent(state, &path.into(), &mut |block, name| {
+        if let Some(value) = block.remove(name) {
+            block.insert(new_name.to_string(), value);
+        }
+        Ok(())
+    })
+}
+
+/// Move the attribute at `path` into the nested block `block`, a sibling of the
+/// attribute, creating the block if it isn't there.
+///
+/// `move_into_block(&mut state, "port", "endpoint")` moves `port` to
+/// `endpoint.port`.
+pub fn move_into_block(
+    state: &mut Value,
+    path: impl Into<AttributePath>,
+    block: &str,
+) -> Result<(), ProviderError> {
+    let path = path.into();
+    for_each_parent(state, &path, &mut |parent, name| {
+        let Some(value) = parent.remove(name) else {
+            return Ok(());
+        };
+        let target = parent
+            .entry(block)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if target.is_null() {
+            *target = Value::Object(Map::new());
+        }
+        match target {
+            Value::Object(target) => {
+                target.insert(name.to_string(), value);
+                Ok(())
+            },
+            other => Err(not_a_block(&path, other)),
+        }
+    })
+}
+
+/// Move the attribute at `path` out of its block, next to the block, removing
+/// the block once it is empty.
+///
+/// `move_out_of_block(&mut state, "endpoint.port")` moves `endpoint.port` to
+/// `port`.
+pub fn move_out_of_block(
+    state: &mut Value,
+    path: impl Into<AttributePath>,
+) -> Result<(), ProviderError> {
+    let path = path.into();
+    let segments = path.segments();
+    if segments.len() < 2 {
+        return Err(ProviderError::Validation(format!(
+            "State upgrade at '{}': the attribute is not in a block",
+            path
+        )));
+    }
+    let name = &segments[segments.len() - 1];
+    let parent = AttributePath::new(segments[..segments.len() - 1].to_vec());
+    for_each_parent(state, &parent, &mut |parent, block_name| {
+        let Some(Value::Object(inner)) = parent.get_mut(block_name) else {
+            return Ok(());
+        };
+        let Some(value) = inner.remove(name) else {
+            return Ok(());
+        };
+        if inner.is_empty() {
+            parent.remove(block_name);
+        }
+        parent.insert(name.clone(), value);
+        Ok(())
+    })
+}
+
+/// Remove the attribute at `path`.
+pub fn remove_attribute(
+    state: &mut Value,
+    path: impl Into<AttributePath>,
+) -> Result<(), ProviderError> {
+    for_each_parent(state, &path.into(), &mut |block, name| {
+        block.remove(name);
+        Ok(())
+    })
+}
+
+/// Set the attribute at `path` to `value` where it is missing or null.
+pub fn set_default(
+    state: &mut Value,
+    path: impl Into<AttributePath>,
+    value: Value,
+) -> Result<(), ProviderError> {
+    for_each_parent(state, &path.into(), &mut |block, name| {
+        let current = block.entry(name).or_insert(Value::Null);
+        if current.is_null() {
+            *current = value.clone();
+        }
+        Ok(())
+    })
+}
+
+/// Replace the value of the attribute at `path` with `f(value)`.
+///
+/// `f` is only called for attributes that are set, not for missing or null
+/// ones.
+pub fn convert<F>(
+    state: &mut Value,
+    path: impl Into<AttributePath>,
+    mut f: F,
+) -> Result<(), ProviderError>
+where
+    F: FnMut(Value) -> Result<Value, ProviderError>,
+{
+    for_each_parent(state, &path.into(), &mut |block, name| {
+        if let Some(value) = block.get_mut(name) {
+            if !value.is_null() {
+                *value = f(value.take())?;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Call `f` with each block holding the attribute at `path`, and the
+/// attribute's name.
+fn for_each_parent<F>(
+    state: &mut Value,
+    path: &AttributePath,
+    f: &mut F,
+) -> Result<(), ProviderError>
+where
+    F: FnMut(&mut Map<String, Value>, &str) -> Result<(), ProviderError>,
+{
+    let Some((name, parents)) = path.segments().split_last() else {
+        return Err(ProviderError::Validation(
+            "State upgrade: the attribute path is empty".to_string(),
+        ));
+    };
+    if name == "*" {
+        return Err(ProviderError::Validation(format!(
+            "State upgrade at '{}': the attribute name can't be '*'",
+            path
+        )));
+    }
+    visit(state, parents, path, &mut |value| match value {
+        Value::Object(block) => f(block, name),
+        Value::Null => Ok(()),
+        other => Err(not_a_block(path, other)),
+    })
+}
+
+/// Call `f` with each value `segments` leads to from `value`, skipping
+/// branches that are missing or null.
+fn visit(
+    value: &mut Value,
+    segments: &[String],
+    path: &AttributePath,
+    f: &mut dyn FnMut(&mut Value) -> Result<(), ProviderError>,
+) -> Result<(), ProviderError> {
+    let Some((segment, rest)) = segments.split_first() else {
+        return f(value);
+    };
+    match value {
+        Value::Null => Ok(()),
+        Value::Object(map) if segment == "*" => map
+            .values_mut()
+            .try_for_each(|value| visit(value, rest, path, f)),
+        Value::Object(map) => match map.get_mut(segment) {
+            Some(value) => visit(value, rest, path, f),
+            None => Ok(()),
+        },
+        Value::Array(items) if segment == "*" => items
+            .iter_mut()
+            .try_for_each(|value| visit(value, rest, path, f)),
+        Value::Array(items) => match segment.parse::<usize>() {
+            Ok(index) => match items.get_mut(index) {
+                Some(value) => visit(value, rest, path, f),
+                None => Ok(()),
+            },
+            Err(_) => Err(ProviderError::Validation(format!(
+                "State upgrade at '{}': '{}' is not a list index",
+                path, segment
+            ))),
+        },
+        other => Err(not_a_block(path, other)),
+    }
+}
+
+fn not_a_block(path: &AttributePath, value: &Value) -> ProviderError {
+    ProviderError::Validation(format!(
+        "State upgrade at '{}': expected a block, found {}",
+        path, value
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_rename_attribute() {
+        let mut state = json!({
+            "data": "a",
+            "network": [{"subnet": "s-1"}, {"subnet": "s-2"}, null],
+            "tags": {"env": {"old": 1}},
+        });
+        rename_attribute(&mut state, "data", "value").unwrap();
+        rename_attribute(&mut state, "network.*.subnet", "subnet_id").unwrap();
+        rename_attribute(&mut state, "tags.env.old", "new").unwrap();
+        rename_attribute(&mut state, "missing.attr", "other").unwrap();
+        assert_eq!(
+            state,
+            json!({
+                "value": "a",
+                "network": [{"subnet_id": "s-1"}, {"subnet_id": "s-2"}, null],
+                "tags": {"env": {"new": 1}},
+            })
+        );
+
+        let err = rename_attribute(&mut state, "value.inner", "x").unwrap_err();
+        assert!(err.to_string().contains("expected a block"), "{}", err);
+        assert!(rename_attribute(&mut state, "network.first.subnet_id", "x").is_err());
+        assert!(rename_attribute(&mut state, "network.*", "x").is_err());
+    }
+
+    #[test]
+    fn test_move_between_blocks() {
+        let mut state = json!({
+            "host": "h",
+            "port": 80,
+            "listeners": [{"port": 1, "tls": null}, {"port": 2}],
+        });
+        move_into_block(&mut state, "host", "endpoint").unwrap();
+        move_into_block(&mut state, "port", "endpoint").unwrap();
+        move_into_block(&mut state, "listeners.*.port", "tls").unwrap();
+        assert_eq!(
+            state,
+            json!({
+                "endpoint": {"host": "h", "port": 80},
+                "listeners": [{"tls": {"port": 1}}, {"tls": {"port": 2}}],
+            })
+        );
+
+        move_out_of_block(&mut state, "endpoint.host").unwrap();
+        assert_eq!(state["host"], "h");
+        assert_eq!(state["endpoint"], json!({"port": 80}));
+        move_out_of_block(&mut state, "endpoint.port").unwrap();
+        move_out_of_block(&mut state, "listeners.*.tls.port").unwrap();
+        assert_eq!(
+            state,
+            json!({
+                "host": "h",
+                "port": 80,
+                "listeners": [{"port": 1}, {"port": 2}],
+            })
+        );
+        assert!(move_out_of_block(&mut state, "port").is_err());
+        assert!(move_into_block(&mut state, "host", "port").is_err());
+    }
+
+    #[test]
+    fn test_remove_default_and_convert() {
+        let mut state = json!({
+            "legacy": true,
+            "size": "10",
+            "disks": [{"size": "1"}, {"size": null}],
+            "region": null,
+        });
+        remove_attribute(&mut state, "legacy").unwrap();
+        set_default(&mut state, "region", json!("us-east-1")).unwrap();
+        set_default(&mut state, "zone", json!("a")).unwrap();
+        set_default(&mut state, "size", json!("20")).unwrap();
+
+        let to_number = |v: Value| match v.as_str().and_then(|s| s.parse::<i64>().ok()) {
+            Some(n) => Ok(json!(n)),
+            None => Err(ProviderError::Validation(format!("bad size {}", v))),
+        };
+        convert(&mut state, "size", to_number).unwrap();
+        convert(&mut state, "disks.*.size", to_number).unwrap();
+        assert_eq!(
+            state,
+            json!({
+                "size": 10,
+                "disks": [{"size": 1}, {"size": null}],
+                "region": "us-east-1",
+                "zone": "a",
+            })
+        );
+        assert!(convert(&mut state, "zone", to_number).is_err());
+    }
+}
@@ -32,12 +32,13 @@
 //! assert_eq!(diagnostics[0].attribute, Some("count".to_string()));
 //! ```
 
+use crate::i18n::{sdk_diagnostic, Message};
+use crate::number::NumberError;
 use crate::schema::{
     Attribute, AttributeType, Block, BlockNestingMode, Diagnostic, DiagnosticSeverity, NestedBlock,
-    Schema,
+    ObjectType, Schema,
 };
 use serde_json::Value;
-use std::collections::HashMap;
 
 /// Validate a JSON value against a schema.
 ///
@@ -77,6 +78,155 @@ pub fn is_valid(schema: &Schema, value: &Value) -> bool {
     validate(schema, value).is_empty()
 }
 
+/// Check a state returned by create or update against its configuration.
+///
+/// Returns warnings for optional+computed attributes that break the rule
+/// described in [`crate::plan`], each of which shows up as a diff on every
+/// later plan:
+///
+/// - The configuration sets a value and the state has a different one (e.g.
+///   the remote API normalized `"US-EAST-1"` to `"us-east-1"`)
+/// - The configuration leaves the value null and the provider did not choose one
+pub fn validate_applied_state(schema: &Schema, config: &Value, state: &Value) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    check_applied_block(&schema.block, config, state, "", &mut diagnostics);
+    diagnostics
+}
+
+fn check_applied_block(
+    block: &Block,
+    config: &Value,
+    state: &Value,
+    path: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (name, attr) in &block.attributes {
+        if !attr.flags.is_optional_computed() {
+            continue;
+        }
+        let attr_path = join_path(path, name);
+        let configured = config.get(name).filter(|v| !v.is_null());
+        let applied = state.get(name).filter(|v| !v.is_null());
+        match (configured, applied) {
+            (Some(configured), applied) if applied != Some(configured) => diagnostics.push(
+                sdk_diagnostic(
+                    DiagnosticSeverity::Warning,
+                    Message::new("state.configured_value_changed").with_arg("path", &attr_path),
+                )
+                .with_attribute(&attr_path),
+            ),
+            (None, None) => diagnostics.push(
+                sdk_diagnostic(
+                    DiagnosticSeverity::Warning,
+                    Message::new("state.value_not_chosen").with_arg("path", &attr_path),
+                )
+                .with_attribute(&attr_path),
+            ),
+            _ => {},
+        }
+    }
+
+    for (name, nested) in &block.blocks {
+        let (Some(config), Some(state)) = (config.get(name), state.get(name)) else {
+            continue;
+        };
+        let block_path = join_path(path, name);
+        match (nested.nesting_mode, config, state) {
+            (BlockNestingMode::Single, _, _) => {
+                check_applied_block(&nested.block, config, state, &block_path, diagnostics);
+            },
+            (BlockNestingMode::List | BlockNestingMode::Set, Value::Array(configs), _) => {
+                for (i, config) in configs.iter().enumerate() {
+                    if let Some(state) = state.get(i) {
+                        let item_path = format!("{}.{}", block_path, i);
+                        check_applied_block(&nested.block, config, state, &item_path, diagnostics);
+                    }
+                }
+            },
+            (BlockNestingMode::Map, Value::Object(configs), _) => {
+                for (key, config) in configs {
+                    if let Some(state) = state.get(key) {
+                        let item_path = format!("{}.{}", block_path, key);
+                        check_applied_block(&nested.block, config, state, &item_path, diagnostics);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Validate a state returned by the provider against its schema.
+///
+/// In addition to the checks of [`validate`], computed attributes must have
+/// the right type, and computed attributes that cannot be configured must be
+/// set. The server runs this on created, updated and read states when
+/// [`ServeOptions::validate_state`](crate::ServeOptions::validate_state) is set.
+pub fn validate_state(schema: &Schema, state: &Value) -> Vec<Diagnostic> {
+    let mut diagnostics = validate(schema, state);
+    check_computed_block(&schema.block, state, "", &mut diagnostics);
+    diagnostics
+}
+
+fn check_computed_block(
+    block: &Block,
+    state: &Value,
+    path: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let Value::Object(obj) = state else {
+        return;
+    };
+
+    for (name, attr) in &block.attributes {
+        if !attr.flags.computed {
+            continue;
+        }
+        let attr_path = join_path(path, name);
+        let configurable = attr.flags.optional || attr.flags.required;
+        match obj.get(name).filter(|v| !v.is_null()) {
+            // Configurable attributes were already type checked by validate
+            Some(v) if !configurable => {
+                validate_attribute_type(&attr.attr_type, v, &attr_path, diagnostics);
+            },
+            Some(_) => {},
+            None if !configurable => diagnostics.push(
+                sdk_diagnostic(
+                    DiagnosticSeverity::Error,
+                    Message::new("state.computed_not_set").with_arg("path", &attr_path),
+                )
+                .with_attribute(&attr_path),
+            ),
+            None => {},
+        }
+    }
+
+    for (name, nested) in &block.blocks {
+        let Some(value) = obj.get(name) else {
+            continue;
+        };
+        let block_path = join_path(path, name);
+        match (nested.nesting_mode, value) {
+            (BlockNestingMode::Single, _) => {
+                check_computed_block(&nested.block, value, &block_path, diagnostics);
+            },
+            (BlockNestingMode::List | BlockNestingMode::Set, Value::Array(items)) => {
+                for (i, item) in items.iter().enumerate() {
+                    let item_path = format!("{}.{}", block_path, i);
+                    check_computed_block(&nested.block, item, &item_path, diagnostics);
+                }
+            },
+            (BlockNestingMode::Map, Value::Object(items)) => {
+                for (key, item) in items {
+                    let item_path = format!("{}.{}", block_path, key);
+                    check_computed_block(&nested.block, item, &item_path, diagnostics);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
 fn validate_block(block: &Block, value: &Value, path: &str, diagnostics: &mut Vec<Diagnostic>) {
     let obj = match value {
         Value::Object(map) => map,
@@ -86,9 +236,11 @@ fn validate_block(block: &Block, value: &Value, path: &str, diagnostics: &mut Ve
         },
         _ => {
             diagnostics.push(
-                Diagnostic::error("Expected object")
-                    .with_detail(format!("Got {}", value_type_name(value)))
-                    .with_attribute_if_not_empty(path),
+                sdk_diagnostic(
+                    DiagnosticSeverity::Error,
+                    Message::new("expected_object").with_arg("got", value_type_name(value)),
+                )
+                .with_attribute_if_not_empty(path),
             );
             return;
         },
@@ -124,11 +276,7 @@ fn validate_attribute(
         None | Some(Value::Null) => {
             // Check if required
             if attr.flags.required {
-                diagnostics.push(
-                    Diagnostic::error(format!("Missing required attribute '{}'", path))
-                        .with_detail("This attribute is required and must be provided")
-                        .with_attribute(path),
-                );
+                diagnostics.push(Diagnostic::missing_attribute(path));
             }
             // Optional attributes can be missing/null
         },
@@ -139,7 +287,7 @@ fn validate_attribute(
     }
 }
 
-fn validate_attribute_type(
+pub(crate) fn validate_attribute_type(
     attr_type: &AttributeType,
     value: &Value,
     path: &str,
@@ -148,22 +296,24 @@ fn validate_attribute_type(
     match attr_type {
         AttributeType::String => {
             if !value.is_string() {
-                diagnostics.push(type_error(path, "string", value));
+                diagnostics.push(Diagnostic::invalid_type(path, "string", value));
             }
         },
-        AttributeType::Int64 => {
-            if !is_int64(value) {
-                diagnostics.push(type_error(path, "int64", value));
-            }
+        AttributeType::Int64 => match crate::number::exact_i64(value) {
+            Ok(_) => {},
+            Err(NumberError::PrecisionLoss(_)) => {
+                diagnostics.push(Diagnostic::precision_loss(path, value));
+            },
+            Err(_) => diagnostics.push(Diagnostic::invalid_type(path, "int64", value)),
         },
         AttributeType::Float64 => {
             if !value.is_number() {
-                diagnostics.push(type_error(path, "float64", value));
+                diagnostics.push(Diagnostic::invalid_type(path, "float64", value));
             }
         },
         AttributeType::Bool => {
             if !value.is_boolean() {
-                diagnostics.push(type_error(path, "bool", value));
+                diagnostics.push(Diagnostic::invalid_type(path, "bool", value));
             }
         },
         AttributeType::List(element_type) => {
@@ -173,7 +323,7 @@ fn validate_attribute_type(
                     validate_attribute_type(element_type, elem, &elem_path, diagnostics);
                 }
             } else {
-                diagnostics.push(type_error(path, "list", value));
+                diagnostics.push(Diagnostic::invalid_type(path, "list", value));
             }
         },
         AttributeType::Set(element_type) => {
@@ -184,7 +334,7 @@ fn validate_attribute_type(
                     validate_attribute_type(element_type, elem, &elem_path, diagnostics);
                 }
             } else {
-                diagnostics.push(type_error(path, "set", value));
+                diagnostics.push(Diagnostic::invalid_type(path, "set", value));
             }
         },
         AttributeType::Map(value_type) => {
@@ -194,14 +344,14 @@ fn validate_attribute_type(
                     validate_attribute_type(value_type, val, &key_path, diagnostics);
                 }
             } else {
-                diagnostics.push(type_error(path, "map", value));
+                diagnostics.push(Diagnostic::invalid_type(path, "map", value));
             }
         },
-        AttributeType::Object(attrs) => {
+        AttributeType::Object(object) => {
             if let Some(obj) = value.as_object() {
-                validate_object_type(attrs, obj, path, diagnostics);
+                validate_object_type(object, obj, path, diagnostics);
             } else {
-                diagnostics.push(type_error(path, "object", value));
+                diagnostics.push(Diagnostic::invalid_type(path, "object", value));
             }
         },
         AttributeType::Dynamic => {
@@ -211,18 +361,21 @@ fn validate_attribute_type(
 }
 
 fn validate_object_type(
-    attrs: &HashMap<String, AttributeType>,
+    object: &ObjectType,
     obj: &serde_json::Map<String, Value>,
     path: &str,
     diagnostics: &mut Vec<Diagnostic>,
 ) {
-    for (name, attr_type) in attrs {
+    for (name, attr_type) in &object.attributes {
         let attr_path = join_path(path, name);
-        if let Some(value) = obj.get(name) {
-            validate_attribute_type(attr_type, value, &attr_path, diagnostics);
+        match obj.get(name) {
+            None | Some(Value::Null) => {
+                if object.is_required(name) {
+                    diagnostics.push(Diagnostic::missing_attribute(attr_path));
+                }
+            },
+            Some(value) => validate_attribute_type(attr_type, value, &attr_path, diagnostics),
         }
-        // Object attributes within a type don't have required/optional flags,
-        // so we don't enforce presence
     }
 }
 
@@ -259,9 +412,11 @@ fn validate_single_block(
         None | Some(Value::Null) => {
             if nested.min_items > 0 {
                 diagnostics.push(
-                    Diagnostic::error(format!("Missing required block '{}'", path))
-                        .with_detail("At least one block is required")
-                        .with_attribute(path),
+                    sdk_diagnostic(
+                        DiagnosticSeverity::Error,
+                        Message::new("missing_block").with_arg("path", path),
+                    )
+                    .with_attribute(path),
                 );
             }
         },
@@ -281,10 +436,12 @@ fn validate_list_block(
         None | Some(Value::Null) => {
             if nested.min_items > 0 {
                 diagnostics.push(
-                    Diagnostic::error(format!(
-                        "Block '{}' requires at least {} item(s)",
-                        path, nested.min_items
-                    ))
+                    sdk_diagnostic(
+                        DiagnosticSeverity::Error,
+                        Message::new("block_required_items")
+                            .with_arg("path", path)
+                            .with_arg("min", nested.min_items),
+                    )
                     .with_attribute(path),
                 );
             }
@@ -295,10 +452,13 @@ fn validate_list_block(
             // Check min_items
             if len < nested.min_items {
                 diagnostics.push(
-                    Diagnostic::error(format!(
-                        "Block '{}' requires at least {} item(s), got {}",
-                        path, nested.min_items, len
-                    ))
+                    sdk_diagnostic(
+                        DiagnosticSeverity::Error,
+                        Message::new("block_min_items")
+                            .with_arg("path", path)
+                            .with_arg("min", nested.min_items)
+                            .with_arg("got", len),
+                    )
                     .with_attribute(path),
                 );
             }
@@ -306,10 +466,13 @@ fn validate_list_block(
             // Check max_items (0 means unlimited)
             if nested.max_items > 0 && len > nested.max_items {
                 diagnostics.push(
-                    Diagnostic::error(format!(
-                        "Block '{}' allows at most {} item(s), got {}",
-                        path, nested.max_items, len
-                    ))
+                    sdk_diagnostic(
+                        DiagnosticSeverity::Error,
+                        Message::new("block_max_items")
+                            .with_arg("path", path)
+                            .with_arg("max", nested.max_items)
+                            .with_arg("got", len),
+                    )
                     .with_attribute(path),
                 );
             }
@@ -322,9 +485,13 @@ fn validate_list_block(
         },
         Some(v) => {
             diagnostics.push(
-                Diagnostic::error(format!("Expected list for block '{}'", path))
-                    .with_detail(format!("Got {}", value_type_name(v)))
-                    .with_attribute(path),
+                sdk_diagnostic(
+                    DiagnosticSeverity::Error,
+                    Message::new("block_expected_list")
+                        .with_arg("path", path)
+                        .with_arg("got", value_type_name(v)),
+                )
+                .with_attribute(path),
             );
         },
     }
@@ -340,10 +507,12 @@ fn validate_map_block(
         None | Some(Value::Null) => {
             if nested.min_items > 0 {
                 diagnostics.push(
-                    Diagnostic::error(format!(
-                        "Block '{}' requires at least {} item(s)",
-                        path, nested.min_items
-                    ))
+                    sdk_diagnostic(
+                        DiagnosticSeverity::Error,
+                        Message::new("block_required_items")
+                            .with_arg("path", path)
+                            .with_arg("min", nested.min_items),
+                    )
                     .with_attribute(path),
                 );
             }
@@ -354,10 +523,13 @@ fn validate_map_block(
             // Check min_items
             if len < nested.min_items {
                 diagnostics.push(
-                    Diagnostic::error(format!(
-                        "Block '{}' requires at least {} item(s), got {}",
-                        path, nested.min_items, len
-                    ))
+                    sdk_diagnostic(
+                        DiagnosticSeverity::Error,
+                        Message::new("block_min_items")
+                            .with_arg("path", path)
+                            .with_arg("min", nested.min_items)
+                            .with_arg("got", len),
+                    )
                     .with_attribute(path),
                 );
             }
@@ -365,10 +537,13 @@ fn validate_map_block(
             // Check max_items (0 means unlimited)
             if nested.max_items > 0 && len > nested.max_items {
                 diagnostics.push(
-                    Diagnostic::error(format!(
-                        "Block '{}' allows at most {} item(s), got {}",
-                        path, nested.max_items, len
-                    ))
+                    sdk_diagnostic(
+                        DiagnosticSeverity::Error,
+                        Message::new("block_max_items")
+                            .with_arg("path", path)
+                            .with_arg("max", nested.max_items)
+                            .with_arg("got", len),
+                    )
                     .with_attribute(path),
                 );
             }
@@ -381,9 +556,13 @@ fn validate_map_block(
         },
         Some(v) => {
             diagnostics.push(
-                Diagnostic::error(format!("Expected map for block '{}'", path))
-                    .with_detail(format!("Got {}", value_type_name(v)))
-                    .with_attribute(path),
+                sdk_diagnostic(
+                    DiagnosticSeverity::Error,
+                    Message::new("block_expected_map")
+                        .with_arg("path", path)
+                        .with_arg("got", value_type_name(v)),
+                )
+                .with_attribute(path),
             );
         },
     }
@@ -399,7 +578,7 @@ fn join_path(base: &str, name: &str) -> String {
     }
 }
 
-fn value_type_name(value: &Value) -> &'static str {
+pub(crate) fn value_type_name(value: &Value) -> &'static str {
     match value {
         Value::Null => "null",
         Value::Bool(_) => "bool",
@@ -410,38 +589,6 @@ fn value_type_name(value: &Value) -> &'static str {
     }
 }
 
-fn is_int64(value: &Value) -> bool {
-    match value {
-        Value::Number(n) => {
-            // Check if it's an integer (no fractional part)
-            if let Some(i) = n.as_i64() {
-                // It's already an i64
-                let _ = i;
-                true
-            } else if let Some(f) = n.as_f64() {
-                // Check if the float is actually an integer
-                f.fract() == 0.0 && f >= i64::MIN as f64 && f <= i64::MAX as f64
-            } else {
-                false
-            }
-        },
-        _ => false,
-    }
-}
-
-fn type_error(path: &str, expected: &str, got: &Value) -> Diagnostic {
-    Diagnostic {
-        severity: DiagnosticSeverity::Error,
-        summary: format!("Invalid type for attribute '{}'", path),
-        detail: Some(format!(
-            "Expected {}, got {}",
-            expected,
-            value_type_name(got)
-        )),
-        attribute: Some(path.to_string()),
-    }
-}
-
 trait DiagnosticExt {
     fn with_attribute_if_not_empty(self, path: &str) -> Self;
 }
@@ -461,6 +608,7 @@ mod tests {
     use super::*;
     use crate::schema::{Attribute, AttributeFlags, Block, NestedBlock, Schema};
     use serde_json::json;
+    use std::collections::BTreeMap;
 
     #[test]
     fn test_validate_required_string() {
@@ -519,6 +667,54 @@ mod tests {
         assert!(diagnostics.is_empty());
     }
 
+    #[test]
+    fn test_validate_applied_state() {
+        let schema = Schema::v0()
+            .with_attribute("name", Attribute::required_string())
+            .with_attribute("region", Attribute::optional_computed_string())
+            .with_attribute("zone", Attribute::optional_computed_string())
+            .with_block(
+                "disk",
+                NestedBlock::list(
+                    Block::new().with_attribute("type", Attribute::optional_computed_string()),
+                ),
+            );
+        let config = json!({"name": "web", "region": "US-EAST-1", "disk": [{"type": null}]});
+
+        let state =
+            json!({"name": "web", "region": "US-EAST-1", "zone": "a", "disk": [{"type": "ssd"}]});
+        assert!(validate_applied_state(&schema, &config, &state).is_empty());
+
+        let state = json!({"name": "web", "region": "us-east-1", "zone": null, "disk": [{}]});
+        let paths: Vec<_> = validate_applied_state(&schema, &config, &state)
+            .into_iter()
+            .map(|d| d.attribute.unwrap())
+            .collect();
+        assert_eq!(paths, vec!["region", "zone", "disk.0.type"]);
+    }
+
+    #[test]
+    fn test_validate_state() {
+        let schema = Schema::v0()
+            .with_attribute("name", Attribute::required_string())
+            .with_attribute("id", Attribute::computed_string())
+            .with_attribute("zone", Attribute::optional_computed_string())
+            .with_block(
+                "disk",
+                NestedBlock::list(Block::new().with_attribute("size", Attribute::computed_int64())),
+            );
+
+        let state = json!({"name": "web", "id": "i-1", "zone": null, "disk": [{"size": 10}]});
+        assert!(validate_state(&schema, &state).is_empty());
+
+        let state = json!({"name": 1, "disk": [{"size": "big"}, {}]});
+        let paths: Vec<_> = validate_state(&schema, &state)
+            .into_iter()
+            .map(|d| d.attribute.unwrap())
+            .collect();
+        assert_eq!(paths, vec!["name", "id", "disk.0.size", "disk.1.size"]);
+    }
+
     #[test]
     fn test_validate_int64() {
         let schema = Schema::v0().with_attribute("count", Attribute::required_int64());
@@ -538,6 +734,17 @@ mod tests {
         // String
         let diagnostics = validate(&schema, &json!({"count": "42"}));
         assert_eq!(diagnostics.len(), 1);
+
+        // An integer beyond 2^53 that went through a float64 parser
+        let diagnostics = validate(&schema, &json!({"count": 9007199254740994.0}));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].summary,
+            "Value of attribute 'count' may have lost precision"
+        );
+        assert!(diagnostics[0].suggestion.is_some());
+        let diagnostics = validate(&schema, &json!({"count": 9007199254740994_i64}));
+        assert!(diagnostics.is_empty());
     }
 
     #[test]
@@ -761,14 +968,14 @@ mod tests {
 
     #[test]
     fn test_validate_object_type() {
-        let mut object_attrs = HashMap::new();
+        let mut object_attrs = BTreeMap::new();
         object_attrs.insert("host".to_string(), AttributeType::String);
         object_attrs.insert("port".to_string(), AttributeType::Int64);
 
         let schema = Schema::v0().with_attribute(
             "endpoint",
             Attribute::new(
-                AttributeType::Object(object_attrs),
+                AttributeType::object(object_attrs),
                 AttributeFlags::required(),
             ),
         );
@@ -787,6 +994,34 @@ mod tests {
         );
         assert_eq!(diagnostics.len(), 1);
         assert_eq!(diagnostics[0].attribute, Some("endpoint.port".to_string()));
+
+        // Missing member
+        let diagnostics = validate(&schema, &json!({"endpoint": {"host": "localhost"}}));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].attribute, Some("endpoint.port".to_string()));
+        assert!(diagnostics[0].summary.contains("Missing required"));
+    }
+
+    #[test]
+    fn test_validate_object_optional_keys() {
+        let endpoint = ObjectType::new()
+            .with_attribute("host", AttributeType::String)
+            .with_optional_attribute("port", AttributeType::Int64);
+        let schema = Schema::v0().with_attribute(
+            "endpoint",
+            Attribute::new(endpoint.into(), AttributeFlags::required()),
+        );
+
+        assert!(validate(&schema, &json!({"endpoint": {"host": "localhost"}})).is_empty());
+        assert!(validate(
+            &schema,
+            &json!({"endpoint": {"host": "localhost", "port": null}})
+        )
+        .is_empty());
+
+        let diagnostics = validate(&schema, &json!({"endpoint": {"host": null, "port": 80}}));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].attribute, Some("endpoint.host".to_string()));
     }
 
     #[test]
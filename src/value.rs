@@ -0,0 +1,377 @@
+//! Typed, path-aware accessors for `serde_json::Value`.
+//!
+//! Provider code often digs into configuration and state with chains like
+//! `config["network"][0]["subnet"]["cidr"].as_str().unwrap()`, which panic or
+//! silently produce `null` on bad input. [`ValueExt::path`] resolves a
+//! dot-separated attribute path (the same format used in diagnostics) and the
+//! accessors on [`PathValue`] return [`ProviderError::Validation`] errors that
+//! name the full path.
+//!
+//! # Example
+//!
+//! ```
+//! use hemmer_provider_sdk::value::ValueExt;
+//! use serde_json::json;
+//!
+//! let config = json!({
+//!     "name": "web",
+//!     "network": [{"subnet": {"cidr": "10.0.0.0/24"}}]
+//! });
+//!
+//! let cidr = config.path("network.0.subnet.cidr")?.as_required_str()?;
+//! assert_eq!(cidr, "10.0.0.0/24");
+//!
+//! let size = config.path("disk.size")?.as_optional_i64()?;
+//! assert_eq!(size, None);
+//!
+//! let err = config.path("name")?.as_required_i64().unwrap_err();
+//! assert!(err.to_string().contains("'name'"));
+//! # Ok::<(), hemmer_provider_sdk::ProviderError>(())
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::error::ProviderError;
+use crate::number::NumberError;
+use crate::validation::value_type_name;
+
+/// Extension trait for resolving attribute paths in a `serde_json::Value`.
+pub trait ValueExt {
+    /// Resolve a dot-separated attribute path such as `network.0.subnet.cidr`.
+    ///
+    /// Numeric segments index into lists. A missing attribute (or `null`) along
+    /// the way yields an absent [`PathValue`]; traversing into a scalar or using
+    /// a non-numeric segment on a list is a validation error.
+    fn path(&self, path: &str) -> Result<PathValue<'_>, ProviderError>;
+}
+
+impl ValueExt for Value {
+    fn path(&self, path: &str) -> Result<PathValue<'_>, ProviderError> {
+        PathValue {
+            path: String::new(),
+            value: Some(self),
+        }
+        .path(path)
+    }
+}
+
+/// A value resolved from an attribute path, which may be absent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathValue<'a> {
+    path: String,
+    value: Option<&'a Value>,
+}
+
+impl<'a> PathValue<'a> {
+    /// The attribute path this value was resolved from.
+    pub fn attribute_path(&self) -> &str {
+        &self.path
+    }
+
+    /// The resolved value, or `None` if it is absent or `null`.
+    pub fn value(&self) -> Option<&'a Value> {
+        self.value
+    }
+
+    /// Whether the value is present and not `null`.
+    pub fn is_present(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// Resolve a path relative to this value.
+    pub fn path(&self, path: &str) -> Result<PathValue<'a>, ProviderError> {
+        let mut current = self.clone();
+        for segment in path.split('.').filter(|s| !s.is_empty()) {
+            current = current.child(segment)?;
+        }
+        Ok(current)
+    }
+
+    /// The value, or a "missing required attribute" error.
+    pub fn required(&self) -> Result<&'a Value, ProviderError> {
+        self.value.ok_or_else(|| {
+            ProviderError::Validation(format!("Missing required attribute '{}'", self.path))
+        })
+    }
+
+    /// The value as a string; errors if absent or not a string.
+    pub fn as_required_str(&self) -> Result<&'a str, ProviderError> {
+        self.required_as("string", Value::as_str)
+    }
+
+    /// The value as a string, if present; errors if it is not a string.
+    pub fn as_optional_str(&self) -> Result<Option<&'a str>, ProviderError> {
+        self.optional_as("string", Value::as_str)
+    }
+
+    /// The value as an integer; errors if absent or not an exact integer
+    /// (see [`crate::number::exact_i64`]).
+    pub fn as_required_i64(&self) -> Result<i64, ProviderError> {
+        self.exact_i64(self.required()?)
+    }
+
+    /// The value as an integer, if present; errors if it is not an exact
+    /// integer.
+    pub fn as_optional_i64(&self) -> Result<Option<i64>, ProviderError> {
+        self.value.map(|value| self.exact_i64(value)).transpose()
+    }
+
+    /// The value as an ID that may be a string or an integer; errors if
+    /// absent or neither (see [`crate::number::id_string`]).
+    pub fn as_required_id(&self) -> Result<String, ProviderError> {
+        let value = self.required()?;
+        crate::number::id_string(value)
+            .map_err(|e| self.number_error("string or integer", e, value))
+    }
+
+    /// The value as a float; errors if absent or not a number.
+    pub fn as_required_f64(&self) -> Result<f64, ProviderError> {
+        self.required_as("float64", Value::as_f64)
+    }
+
+    /// The value as a float, if present; errors if it is not a number.
+    pub fn as_optional_f64(&self) -> Result<Option<f64>, ProviderError> {
+        self.optional_as("float64", Value::as_f64)
+    }
+
+    /// The value as a bool; errors if absent or not a bool.
+    pub fn as_required_bool(&self) -> Result<bool, ProviderError> {
+        self.required_as("bool", Value::as_bool)
+    }
+
+    /// The value as a bool, if present; errors if it is not a bool.
+    pub fn as_optional_bool(&self) -> Result<Option<bool>, ProviderError> {
+        self.optional_as("bool", Value::as_bool)
+    }
+
+    /// The value as a list; errors if absent or not a list.
+    pub fn as_required_array(&self) -> Result<&'a Vec<Value>, ProviderError> {
+        self.required_as("list", Value::as_array)
+    }
+
+    /// The value as a list, if present; errors if it is not a list.
+    pub fn as_optional_array(&self) -> Result<Option<&'a Vec<Value>>, ProviderError> {
+        self.optional_as("list", Value::as_array)
+    }
+
+    /// The value as an object; errors if absent or not an object.
+    pub fn as_required_object(&self) -> Result<&'a Map<String, Value>, ProviderError> {
+        self.required_as("object", Value::as_object)
+    }
+
+    /// The value as an object, if present; errors if it is not an object.
+    pub fn as_optional_object(&self) -> Result<Option<&'a Map<String, Value>>, ProviderError> {
+        self.optional_as("object", Value::as_object)
+    }
+
+    /// Deserialize the value into `T`; errors if absent or malformed.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, ProviderError> {
+        T::deserialize(self.required()?).map_err(|e| {
+            ProviderError::Validation(format!(
+                "Invalid value for attribute '{}': {}",
+                self.path, e
+            ))
+        })
+    }
+
+    /// Deserialize the value into `T`, if present; errors if malformed.
+    pub fn deserialize_optional<T: DeserializeOwned>(&self) -> Result<Option<T>, ProviderError> {
+        match self.value {
+            Some(_) => self.deserialize().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn child(&self, segment: &str) -> Result<PathValue<'a>, ProviderError> {
+        let path = if self.path.is_empty() {
+            segment.to_string()
+        } else {
+            format!("{}.{}", self.path, segment)
+        };
+        let value = match self.value {
+            None => None,
+            Some(Value::Object(map)) => map.get(segment),
+            Some(Value::Array(items)) => {
+                let index = segment.parse::<usize>().map_err(|_| {
+                    ProviderError::Validation(format!(
+                        "Invalid list index '{}' in attribute path '{}'",
+                        segment, path
+                    ))
+                })?;
+                items.get(index)
+            },
+            Some(other) => return Err(self.type_error("object or list", other)),
+        };
+        Ok(PathValue {
+            path,
+            value: value.filter(|v| !v.is_null()),
+        })
+    }
+
+    fn optional_as<T>(
+        &self,
+        expected: &str,
+        convert: impl FnOnce(&'a Value) -> Option<T>,
+    ) -> Result<Option<T>, ProviderError> {
+        match self.value {
+            None => Ok(None),
+            Some(value) => convert(value)
+                .map(Some)
+                .ok_or_else(|| self.type_error(expected, value)),
+        }
+    }
+
+    fn required_as<T>(
+        &self,
+        expected: &str,
+        convert: impl FnOnce(&'a Value) -> Option<T>,
+    ) -> Result<T, ProviderError> {
+        let value = self.required()?;
+        convert(value).ok_or_else(|| self.type_error(expected, value))
+    }
+
+    fn exact_i64(&self, value: &Value) -> Result<i64, ProviderError> {
+        crate::number::exact_i64(value).map_err(|e| self.number_error("int64", e, value))
+    }
+
+    fn number_error(&self, expected: &str, err: NumberError, value: &Value) -> ProviderError {
+        match err {
+            NumberError::NotANumber(_) => self.type_error(expected, value),
+            err => ProviderError::Validation(format!(
+                "Invalid value for attribute '{}': {}",
+                self.path, err
+            )),
+        }
+    }
+
+    fn type_error(&self, expected: &str, got: &Value) -> ProviderError {
+        ProviderError::Validation(format!(
+            "Invalid type for attribute '{}': expected {}, got {}",
+            self.path,
+            expected,
+            value_type_name(got)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> Value {
+        json!({
+            "name": "web",
+            "replicas": 3,
+            "enabled": true,
+            "description": null,
+            "network": [{"subnet": {"cidr": "10.0.0.0/24"}, "ports": [80, 443]}]
+        })
+    }
+
+    #[test]
+    fn test_required_and_optional_accessors() {
+        let config = config();
+        assert_eq!(
+            config.path("name").unwrap().as_required_str().unwrap(),
+            "web"
+        );
+        assert_eq!(
+            config.path("replicas").unwrap().as_required_i64().unwrap(),
+            3
+        );
+        assert!(config.path("enabled").unwrap().as_required_bool().unwrap());
+        assert_eq!(
+            config
+                .path("network.0.ports.1")
+                .unwrap()
+                .as_required_i64()
+                .unwrap(),
+            443
+        );
+        assert_eq!(
+            config
+                .path("description")
+                .unwrap()
+                .as_optional_str()
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            config
+                .path("network.3.subnet")
+                .unwrap()
+                .as_optional_object()
+                .unwrap(),
+            None
+        );
+
+        let network = config.path("network.0").unwrap();
+        let cidr = network.path("subnet.cidr").unwrap();
+        assert_eq!(cidr.attribute_path(), "network.0.subnet.cidr");
+        assert_eq!(cidr.as_required_str().unwrap(), "10.0.0.0/24");
+    }
+
+    #[test]
+    fn test_errors_include_path() {
+        let config = config();
+
+        let err = config
+            .path("network.0.subnet.gateway")
+            .unwrap()
+            .as_required_str()
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::Validation(_)));
+        assert!(err.to_string().contains("'network.0.subnet.gateway'"));
+
+        let err = config.path("name").unwrap().as_optional_i64().unwrap_err();
+        assert!(err.to_string().contains("expected int64, got string"));
+
+        let ids = json!({"id": 9007199254740993_i64, "rounded": 9007199254740992.0, "count": 2.0});
+        assert_eq!(
+            ids.path("id").unwrap().as_required_id().unwrap(),
+            "9007199254740993"
+        );
+        assert_eq!(ids.path("count").unwrap().as_required_i64().unwrap(), 2);
+        let err = ids.path("rounded").unwrap().as_required_i64().unwrap_err();
+        assert!(err.to_string().contains("'rounded'"));
+        assert!(err.to_string().contains("may have lost precision"));
+
+        let err = config.path("name.first").unwrap_err();
+        assert!(err.to_string().contains("'name'"));
+
+        let err = config.path("network.primary").unwrap_err();
+        assert!(err.to_string().contains("'network.primary'"));
+    }
+
+    #[test]
+    fn test_deserialize() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Subnet {
+            cidr: String,
+        }
+
+        let config = config();
+        let subnet: Subnet = config
+            .path("network.0.subnet")
+            .unwrap()
+            .deserialize()
+            .unwrap();
+        assert_eq!(subnet.cidr, "10.0.0.0/24");
+
+        let missing: Option<Subnet> = config
+            .path("network.0.backup")
+            .unwrap()
+            .deserialize_optional()
+            .unwrap();
+        assert_eq!(missing, None);
+
+        let err = config
+            .path("name")
+            .unwrap()
+            .deserialize::<Subnet>()
+            .unwrap_err();
+        assert!(err.to_string().contains("'name'"));
+    }
+}